@@ -0,0 +1,220 @@
+//! Message transport between the GUI and the helper
+//!
+//! On Linux this is a Unix socket; opened devices are passed as file descriptors with
+//! `SCM_RIGHTS`. On Windows it is a named pipe and the helper duplicates handles straight
+//! into the client process, so only the handle value is sent.
+
+use crate::protocol;
+use serde::{Serialize, de::DeserializeOwned};
+use std::io;
+
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+/// Size of a single read from the underlying stream
+const READ_CHUNK: usize = 4096;
+
+/// One end of the connection between the GUI and the helper
+pub struct Channel {
+    #[cfg(unix)]
+    stream: UnixStream,
+    #[cfg(windows)]
+    pipe: std::fs::File,
+    /// Bytes received but not yet decoded into a message
+    pending: Vec<u8>,
+    /// File descriptors received but not yet claimed
+    #[cfg(unix)]
+    fds: Vec<OwnedFd>,
+}
+
+impl Channel {
+    /// Wrap a connected Unix socket
+    #[cfg(unix)]
+    pub fn new(stream: UnixStream) -> Self {
+        Self {
+            stream,
+            pending: Vec::new(),
+            fds: Vec::new(),
+        }
+    }
+
+    /// Wrap a connected named pipe
+    #[cfg(windows)]
+    pub fn new(pipe: std::fs::File) -> Self {
+        Self {
+            pipe,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Send a message
+    pub fn send<T: Serialize>(&mut self, message: &T) -> io::Result<()> {
+        let line = protocol::encode(message)?;
+        self.write_line(&line)
+    }
+
+    /// Send a message together with a file descriptor
+    #[cfg(unix)]
+    pub fn send_with_fd<T: Serialize>(&mut self, message: &T, fd: BorrowedFd) -> io::Result<()> {
+        let line = protocol::encode(message)?;
+        self.send_bytes(&line, Some(fd.as_raw_fd()))
+    }
+
+    /// Wait for the next message
+    ///
+    /// # Returns
+    /// * `io::Result<Option<T>>` - The message, or `None` when the other end closed the connection
+    pub fn recv<T: DeserializeOwned>(&mut self) -> io::Result<Option<T>> {
+        loop {
+            if let Some(message) = protocol::decode_next(&mut self.pending)? {
+                return Ok(Some(message));
+            }
+            if self.read_more()? == 0 {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Take the oldest file descriptor received so far
+    #[cfg(unix)]
+    pub fn take_fd(&mut self) -> Option<OwnedFd> {
+        if self.fds.is_empty() {
+            None
+        } else {
+            Some(self.fds.remove(0))
+        }
+    }
+
+    /// Peer process credentials of the socket
+    #[cfg(target_os = "linux")]
+    pub fn peer_credentials(&self) -> io::Result<libc::ucred> {
+        let mut credentials: libc::ucred = unsafe { std::mem::zeroed() };
+        let mut length = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let result = unsafe {
+            libc::getsockopt(
+                self.stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut credentials as *mut _ as *mut libc::c_void,
+                &mut length,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(credentials)
+    }
+
+    #[cfg(unix)]
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        self.send_bytes(line, None)
+    }
+
+    #[cfg(unix)]
+    fn send_bytes(&mut self, line: &[u8], fd: Option<RawFd>) -> io::Result<()> {
+        use std::io::Write;
+
+        let mut iov = libc::iovec {
+            iov_base: line.as_ptr() as *mut libc::c_void,
+            iov_len: line.len(),
+        };
+        // u64 storage keeps the control buffer aligned for cmsghdr
+        let mut control = [0u64; 8];
+        let mut header: libc::msghdr = unsafe { std::mem::zeroed() };
+        header.msg_iov = &mut iov;
+        header.msg_iovlen = 1;
+
+        if let Some(fd) = fd {
+            let fd_size = std::mem::size_of::<RawFd>() as u32;
+            header.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            header.msg_controllen = unsafe { libc::CMSG_SPACE(fd_size) } as _;
+            unsafe {
+                let cmsg = libc::CMSG_FIRSTHDR(&header);
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(fd_size) as _;
+                std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+            }
+        }
+
+        let sent = unsafe { libc::sendmsg(self.stream.as_raw_fd(), &header, libc::MSG_NOSIGNAL) };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // The descriptor went out with the first part, the rest is plain data
+        let sent = sent as usize;
+        if sent < line.len() {
+            self.stream.write_all(&line[sent..])?;
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        use std::io::Write;
+
+        self.pipe.write_all(line)?;
+        self.pipe.flush()
+    }
+
+    #[cfg(unix)]
+    fn read_more(&mut self) -> io::Result<usize> {
+        let mut data = [0u8; READ_CHUNK];
+        let mut control = [0u64; 8];
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut header: libc::msghdr = unsafe { std::mem::zeroed() };
+        header.msg_iov = &mut iov;
+        header.msg_iovlen = 1;
+        header.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        header.msg_controllen = std::mem::size_of_val(&control) as _;
+
+        let received =
+            unsafe { libc::recvmsg(self.stream.as_raw_fd(), &mut header, libc::MSG_CMSG_CLOEXEC) };
+        if received < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&header);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                    let payload = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                    let fds = libc::CMSG_DATA(cmsg) as *const RawFd;
+                    for i in 0..payload / std::mem::size_of::<RawFd>() {
+                        self.fds
+                            .push(OwnedFd::from_raw_fd(std::ptr::read_unaligned(fds.add(i))));
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(&header, cmsg);
+            }
+        }
+
+        let received = received as usize;
+        self.pending.extend_from_slice(&data[..received]);
+        Ok(received)
+    }
+
+    #[cfg(windows)]
+    fn read_more(&mut self) -> io::Result<usize> {
+        use std::io::Read;
+
+        // ERROR_BROKEN_PIPE - the other end closed the pipe
+        const ERROR_BROKEN_PIPE: i32 = 109;
+
+        let mut data = [0u8; READ_CHUNK];
+        match self.pipe.read(&mut data) {
+            Ok(received) => {
+                self.pending.extend_from_slice(&data[..received]);
+                Ok(received)
+            }
+            Err(e) if e.raw_os_error() == Some(ERROR_BROKEN_PIPE) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+}
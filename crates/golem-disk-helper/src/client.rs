@@ -0,0 +1,115 @@
+//! GUI side of the helper connection
+
+use crate::channel::Channel;
+use crate::protocol::{PROTOCOL_VERSION, Request, Response};
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+
+/// Connection to a running helper
+pub struct HelperClient {
+    channel: Channel,
+}
+
+impl HelperClient {
+    /// Connect to the helper listening on `endpoint` and check it speaks our protocol
+    ///
+    /// # Arguments
+    /// * `endpoint` - Socket path (Linux) or pipe name (Windows) the helper was started with
+    pub fn connect(endpoint: &str) -> Result<Self> {
+        #[cfg(unix)]
+        let channel = Channel::new(
+            std::os::unix::net::UnixStream::connect(endpoint)
+                .with_context(|| format!("Failed to connect to helper at {}", endpoint))?,
+        );
+        #[cfg(windows)]
+        let channel = Channel::new(
+            std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(endpoint)
+                .with_context(|| format!("Failed to connect to helper at {}", endpoint))?,
+        );
+
+        let mut client = Self { channel };
+        match client.call(&Request::Hello {
+            version: PROTOCOL_VERSION,
+            client_pid: std::process::id(),
+        })? {
+            Response::Hello { version } if version == PROTOCOL_VERSION => Ok(client),
+            Response::Hello { version } => Err(anyhow!(
+                "Helper speaks protocol version {}, expected {}",
+                version,
+                PROTOCOL_VERSION
+            )),
+            other => Err(anyhow!("Unexpected handshake response: {:?}", other)),
+        }
+    }
+
+    /// Open a whole-disk device with the helper's rights
+    ///
+    /// # Returns
+    /// * `Result<File>` - A handle owned by this process
+    pub fn open_device(&mut self, path: &str, writable: bool) -> Result<File> {
+        let response = self.call(&Request::OpenDevice {
+            path: path.to_string(),
+            writable,
+        })?;
+        let Response::Opened { handle } = response else {
+            return Err(anyhow!("Unexpected response to open: {:?}", response));
+        };
+
+        #[cfg(unix)]
+        {
+            let _ = handle;
+            let fd = self
+                .channel
+                .take_fd()
+                .ok_or_else(|| anyhow!("Helper opened {} but sent no descriptor", path))?;
+            Ok(File::from(fd))
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::io::FromRawHandle;
+            // The helper already duplicated the handle into this process
+            Ok(unsafe { File::from_raw_handle(handle as std::os::windows::io::RawHandle) })
+        }
+    }
+
+    /// Unmount every filesystem on the device
+    pub fn unmount(&mut self, path: &str) -> Result<()> {
+        self.call_done(&Request::Unmount {
+            path: path.to_string(),
+        })
+    }
+
+    /// Remove all partitions from the device
+    pub fn clean_disk(&mut self, path: &str) -> Result<()> {
+        self.call_done(&Request::CleanDisk {
+            path: path.to_string(),
+        })
+    }
+
+    /// Ask the helper to exit
+    pub fn shutdown(mut self) -> Result<()> {
+        self.call_done(&Request::Shutdown)
+    }
+
+    fn call_done(&mut self, request: &Request) -> Result<()> {
+        match self.call(request)? {
+            Response::Done => Ok(()),
+            other => Err(anyhow!("Unexpected response: {:?}", other)),
+        }
+    }
+
+    /// Send a request and wait for its response, turning error responses into errors
+    fn call(&mut self, request: &Request) -> Result<Response> {
+        self.channel
+            .send(request)
+            .context("Failed to send request to helper")?;
+        match self.channel.recv()? {
+            Some(Response::Error { message }) => Err(anyhow!(message)),
+            Some(response) => Ok(response),
+            None => Err(anyhow!("Helper closed the connection")),
+        }
+    }
+}
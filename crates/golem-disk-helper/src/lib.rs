@@ -0,0 +1,16 @@
+//! Privileged disk helper for the Golem GPU Imager
+//!
+//! The helper is a small process started with root / Administrator rights so the GUI
+//! itself can stay unprivileged. It only opens, unmounts and cleans whole disks; the
+//! opened device handle is passed back to the GUI, which does the actual writing. Image
+//! data never goes through the IPC channel.
+//!
+//! The GUI talks to the helper over a Unix socket (Linux) or a named pipe (Windows),
+//! one JSON message per line.
+
+pub mod channel;
+pub mod client;
+pub mod protocol;
+
+pub use client::HelperClient;
+pub use protocol::{PROTOCOL_VERSION, Request, Response};
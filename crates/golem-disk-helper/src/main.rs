@@ -0,0 +1,506 @@
+use anyhow::{Context, Result, anyhow};
+use clap::Parser;
+use golem_disk_helper::channel::Channel;
+use golem_disk_helper::{PROTOCOL_VERSION, Request, Response};
+use std::fs::File;
+use tracing::{error, info, warn};
+
+/// Privileged helper that opens disks on behalf of the Golem GPU Imager
+#[derive(Parser, Debug)]
+#[clap(
+    name = "golem-disk-helper",
+    about = "Privileged disk access helper for the Golem GPU Imager"
+)]
+struct Args {
+    /// Socket path (Linux) or pipe name (Windows) to listen on
+    #[clap(long)]
+    endpoint: String,
+
+    /// Process ID of the GUI allowed to connect
+    #[clap(long)]
+    client_pid: u32,
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let args = Args::parse();
+    info!(
+        "Waiting for client {} on {}",
+        args.client_pid, args.endpoint
+    );
+
+    let mut channel = platform::accept(&args)?;
+    if let Err(e) = serve(&mut channel, args.client_pid) {
+        error!("Helper stopped: {:#}", e);
+        return Err(e);
+    }
+
+    info!("Client disconnected, exiting");
+    Ok(())
+}
+
+/// Answer requests until the client disconnects or asks us to stop
+fn serve(channel: &mut Channel, client_pid: u32) -> Result<()> {
+    match channel.recv::<Request>()? {
+        Some(Request::Hello {
+            version,
+            client_pid: pid,
+        }) if pid == client_pid => {
+            if version != PROTOCOL_VERSION {
+                warn!("Client speaks protocol version {}", version);
+            }
+            channel.send(&Response::Hello {
+                version: PROTOCOL_VERSION,
+            })?;
+        }
+        Some(other) => return Err(anyhow!("Expected handshake, got {:?}", other)),
+        None => return Ok(()),
+    }
+
+    while let Some(request) = channel.recv::<Request>()? {
+        info!("Request: {:?}", request);
+        let result = match request {
+            Request::Hello { .. } => Err(anyhow!("Handshake already done")),
+            Request::OpenDevice { path, writable } => {
+                match platform::open_device(&path, writable) {
+                    Ok(file) => {
+                        platform::send_opened(channel, &file, client_pid)?;
+                        continue;
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Request::Unmount { path } => platform::unmount(&path),
+            Request::CleanDisk { path } => platform::clean_disk(&path),
+            Request::Shutdown => {
+                channel.send(&Response::Done)?;
+                return Ok(());
+            }
+        };
+
+        let response = match result {
+            Ok(()) => Response::Done,
+            Err(e) => {
+                warn!("Request failed: {:#}", e);
+                Response::Error {
+                    message: format!("{:#}", e),
+                }
+            }
+        };
+        channel.send(&response)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+    use std::ffi::CString;
+    use std::os::fd::AsFd;
+    use std::os::unix::fs::{FileTypeExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+    use std::os::unix::net::UnixListener;
+
+    /// Listen on the socket and accept the GUI's connection
+    ///
+    /// The socket is only reachable by the user who started the helper through pkexec, and
+    /// the connecting process must be the GUI that started us.
+    pub fn accept(args: &Args) -> Result<Channel> {
+        let client_uid = match std::env::var("PKEXEC_UID") {
+            Ok(uid) => uid.parse::<u32>().context("Invalid PKEXEC_UID")?,
+            Err(_) => unsafe { libc::getuid() },
+        };
+
+        let _ = std::fs::remove_file(&args.endpoint);
+        let listener = UnixListener::bind(&args.endpoint)
+            .with_context(|| format!("Failed to listen on {}", args.endpoint))?;
+        std::fs::set_permissions(&args.endpoint, std::fs::Permissions::from_mode(0o600))?;
+        std::os::unix::fs::chown(&args.endpoint, Some(client_uid), None)
+            .context("Failed to hand the socket to the client user")?;
+
+        let (stream, _) = listener.accept()?;
+        let _ = std::fs::remove_file(&args.endpoint);
+
+        let channel = Channel::new(stream);
+        let peer = channel.peer_credentials()?;
+        if peer.uid != client_uid || peer.pid as u32 != args.client_pid {
+            return Err(anyhow!(
+                "Refusing connection from pid {} uid {}",
+                peer.pid,
+                peer.uid
+            ));
+        }
+        Ok(channel)
+    }
+
+    pub fn open_device(path: &str, writable: bool) -> Result<File> {
+        ensure_whole_disk(path)?;
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(writable)
+            .custom_flags(libc::O_EXCL | libc::O_SYNC | libc::O_CLOEXEC)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path))
+    }
+
+    pub fn send_opened(channel: &mut Channel, file: &File, _client_pid: u32) -> Result<()> {
+        channel.send_with_fd(&Response::Opened { handle: 0 }, file.as_fd())?;
+        Ok(())
+    }
+
+    /// Unmount every mounted partition of the device
+    pub fn unmount(path: &str) -> Result<()> {
+        ensure_whole_disk(path)?;
+        let mounts = std::fs::read_to_string("/proc/self/mounts")?;
+        for line in mounts.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(source), Some(target)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            if !is_partition_of(source, path) {
+                continue;
+            }
+
+            let target = unescape_mount_path(target);
+            info!("Unmounting {} from {}", source, target);
+            let target_c = CString::new(target.as_str())?;
+            if unsafe { libc::umount(target_c.as_ptr()) } != 0 {
+                return Err(anyhow!(
+                    "Failed to unmount {}: {}",
+                    target,
+                    std::io::Error::last_os_error()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Partitions are overwritten by the image itself on Linux
+    pub fn clean_disk(path: &str) -> Result<()> {
+        ensure_whole_disk(path)
+    }
+
+    /// Only whole-disk block devices may be opened, never partitions or regular files
+    fn ensure_whole_disk(path: &str) -> Result<()> {
+        let metadata = std::fs::metadata(path).with_context(|| format!("{} not found", path))?;
+        if !metadata.file_type().is_block_device() {
+            return Err(anyhow!("{} is not a block device", path));
+        }
+
+        let rdev = metadata.rdev();
+        let sysfs = format!(
+            "/sys/dev/block/{}:{}/partition",
+            libc::major(rdev),
+            libc::minor(rdev)
+        );
+        if std::path::Path::new(&sysfs).exists() {
+            return Err(anyhow!("{} is a partition, not a whole disk", path));
+        }
+        Ok(())
+    }
+
+    /// Whether `source` is the device itself or one of its partitions (sdb1, nvme0n1p1)
+    fn is_partition_of(source: &str, device: &str) -> bool {
+        let Some(rest) = source.strip_prefix(device) else {
+            return false;
+        };
+        let rest = rest.strip_prefix('p').unwrap_or(rest);
+        rest.chars().all(|c| c.is_ascii_digit())
+    }
+
+    /// Decode the octal escapes (`\040` for space) used in /proc/self/mounts
+    fn unescape_mount_path(path: &str) -> String {
+        let bytes = path.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let escaped = if bytes[i] == b'\\' {
+                path.get(i + 1..i + 4)
+                    .and_then(|digits| u8::from_str_radix(digits, 8).ok())
+            } else {
+                None
+            };
+            match escaped {
+                Some(value) => {
+                    decoded.push(value);
+                    i += 4;
+                }
+                None => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    use std::io::Write;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle};
+    use std::process::{Command, Stdio};
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, DUPLICATE_SAME_ACCESS, DuplicateHandle, ERROR_PIPE_CONNECTED, FALSE, HANDLE,
+        INVALID_HANDLE_VALUE, LocalFree,
+    };
+    use windows_sys::Win32::Security::Authorization::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows_sys::Win32::Security::SECURITY_ATTRIBUTES;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAG_NO_BUFFERING, FILE_FLAG_WRITE_THROUGH,
+        PIPE_ACCESS_DUPLEX,
+    };
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+    use windows_sys::Win32::System::Ioctl::{
+        DISK_ATTRIBUTE_OFFLINE, GET_DISK_ATTRIBUTES, IOCTL_DISK_GET_DISK_ATTRIBUTES,
+        IOCTL_DISK_SET_DISK_ATTRIBUTES, IOCTL_DISK_UPDATE_PROPERTIES, SET_DISK_ATTRIBUTES,
+    };
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, GetNamedPipeClientProcessId, PIPE_READMODE_BYTE,
+        PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_WAIT,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, OpenProcess, PROCESS_DUP_HANDLE,
+    };
+
+    /// Interactive users may connect; the client process ID is checked after connecting
+    const PIPE_SDDL: &str = "D:(A;;GA;;;SY)(A;;GA;;;BA)(A;;GRGW;;;IU)";
+
+    const PIPE_BUFFER_SIZE: u32 = 64 * 1024;
+
+    /// Create the named pipe and accept the GUI's connection
+    pub fn accept(args: &Args) -> Result<Channel> {
+        let name = wide(&args.endpoint);
+        let sddl = wide(PIPE_SDDL);
+
+        unsafe {
+            let mut descriptor = std::ptr::null_mut();
+            if ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                std::ptr::null_mut(),
+            ) == FALSE
+            {
+                return Err(anyhow!(
+                    "Failed to build pipe security descriptor: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+            let attributes = SECURITY_ATTRIBUTES {
+                nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+                lpSecurityDescriptor: descriptor,
+                bInheritHandle: FALSE,
+            };
+
+            let pipe: HANDLE = CreateNamedPipeW(
+                name.as_ptr(),
+                PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                1,
+                PIPE_BUFFER_SIZE,
+                PIPE_BUFFER_SIZE,
+                0,
+                &attributes,
+            );
+            LocalFree(descriptor as _);
+            if pipe == INVALID_HANDLE_VALUE {
+                return Err(anyhow!(
+                    "Failed to create pipe {}: {}",
+                    args.endpoint,
+                    std::io::Error::last_os_error()
+                ));
+            }
+            let pipe_file = File::from_raw_handle(pipe as _);
+
+            if ConnectNamedPipe(pipe, std::ptr::null_mut()) == FALSE {
+                let e = std::io::Error::last_os_error();
+                if e.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+                    return Err(anyhow!("Failed to accept client: {}", e));
+                }
+            }
+
+            let mut pid = 0u32;
+            if GetNamedPipeClientProcessId(pipe, &mut pid) == FALSE || pid != args.client_pid {
+                return Err(anyhow!("Refusing connection from pid {}", pid));
+            }
+
+            Ok(Channel::new(pipe_file))
+        }
+    }
+
+    pub fn open_device(path: &str, writable: bool) -> Result<File> {
+        let number = physical_drive_number(path)?;
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(writable)
+            .share_mode(0x1 | 0x2) // FILE_SHARE_READ | FILE_SHARE_WRITE
+            .custom_flags(FILE_FLAG_NO_BUFFERING | FILE_FLAG_WRITE_THROUGH)
+            .open(format!(r"\\.\PhysicalDrive{}", number))
+            .with_context(|| format!("Failed to open {}", path))
+    }
+
+    /// Duplicate the handle into the GUI process and tell it the value
+    pub fn send_opened(channel: &mut Channel, file: &File, client_pid: u32) -> Result<()> {
+        unsafe {
+            let process = OpenProcess(PROCESS_DUP_HANDLE, FALSE, client_pid);
+            if process == 0 {
+                return Err(anyhow!(
+                    "Failed to open client process: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+
+            let mut target: HANDLE = 0;
+            let duplicated = DuplicateHandle(
+                GetCurrentProcess(),
+                file.as_raw_handle() as HANDLE,
+                process,
+                &mut target,
+                0,
+                FALSE,
+                DUPLICATE_SAME_ACCESS,
+            );
+            CloseHandle(process);
+            if duplicated == FALSE {
+                return Err(anyhow!(
+                    "Failed to pass handle to client: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+
+            channel.send(&Response::Opened {
+                handle: target as u64,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Volumes are dismounted by the client through the device handle it receives
+    pub fn unmount(path: &str) -> Result<()> {
+        physical_drive_number(path).map(|_| ())
+    }
+
+    /// Remove all partitions with diskpart
+    pub fn clean_disk(path: &str) -> Result<()> {
+        let number = physical_drive_number(path)?;
+        bring_online(number)?;
+        let script = format!(
+            "select disk {}\ndetail disk\nclean\ndetail disk\nrescan\nexit\n",
+            number
+        );
+
+        let mut child = Command::new("diskpart")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to start diskpart")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(script.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "diskpart failed: {}",
+                String::from_utf8_lossy(&output.stdout)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Clear the offline attribute Windows sets on disks with colliding signatures
+    fn bring_online(number: u32) -> Result<()> {
+        let disk = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .share_mode(0x1 | 0x2) // FILE_SHARE_READ | FILE_SHARE_WRITE
+            .open(format!(r"\\.\PhysicalDrive{}", number))
+            .with_context(|| format!("Failed to open PhysicalDrive{}", number))?;
+        let handle = disk.as_raw_handle() as HANDLE;
+
+        unsafe {
+            let mut returned = 0u32;
+            let mut attributes: GET_DISK_ATTRIBUTES = std::mem::zeroed();
+            if DeviceIoControl(
+                handle,
+                IOCTL_DISK_GET_DISK_ATTRIBUTES,
+                std::ptr::null(),
+                0,
+                &mut attributes as *mut _ as *mut _,
+                std::mem::size_of::<GET_DISK_ATTRIBUTES>() as u32,
+                &mut returned,
+                std::ptr::null_mut(),
+            ) == FALSE
+                || attributes.Attributes & DISK_ATTRIBUTE_OFFLINE == 0
+            {
+                return Ok(());
+            }
+
+            warn!("PhysicalDrive{} is offline, bringing it online", number);
+            let mut request: SET_DISK_ATTRIBUTES = std::mem::zeroed();
+            request.Version = std::mem::size_of::<SET_DISK_ATTRIBUTES>() as u32;
+            request.Persist = 1;
+            request.AttributesMask = DISK_ATTRIBUTE_OFFLINE;
+            if DeviceIoControl(
+                handle,
+                IOCTL_DISK_SET_DISK_ATTRIBUTES,
+                &request as *const _ as *const _,
+                std::mem::size_of::<SET_DISK_ATTRIBUTES>() as u32,
+                std::ptr::null_mut(),
+                0,
+                &mut returned,
+                std::ptr::null_mut(),
+            ) == FALSE
+            {
+                return Err(anyhow!(
+                    "PhysicalDrive{} is offline, most likely because its disk signature \
+                     matches another disk, and could not be brought online: {}",
+                    number,
+                    std::io::Error::last_os_error()
+                ));
+            }
+            DeviceIoControl(
+                handle,
+                IOCTL_DISK_UPDATE_PROPERTIES,
+                std::ptr::null(),
+                0,
+                std::ptr::null_mut(),
+                0,
+                &mut returned,
+                std::ptr::null_mut(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Only `\\.\PhysicalDriveN` (or a bare drive number) may be opened
+    fn physical_drive_number(path: &str) -> Result<u32> {
+        let number = path
+            .get(..17)
+            .filter(|prefix| prefix.eq_ignore_ascii_case(r"\\.\PhysicalDrive"))
+            .map_or(path, |_| &path[17..]);
+        number
+            .parse()
+            .map_err(|_| anyhow!("{} is not a physical drive", path))
+    }
+
+    fn wide(s: &str) -> Vec<u16> {
+        std::ffi::OsStr::new(s)
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect()
+    }
+}
@@ -0,0 +1,60 @@
+//! Messages exchanged between the GUI and the helper
+//!
+//! Every message is a single JSON object followed by a newline.
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::io;
+
+/// Bumped whenever a message changes in an incompatible way
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Request sent by the GUI
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// First message on a connection, checked before anything else is served
+    Hello { version: u32, client_pid: u32 },
+    /// Open a whole-disk device and hand the handle to the client
+    OpenDevice { path: String, writable: bool },
+    /// Unmount every filesystem on the device
+    Unmount { path: String },
+    /// Remove all partitions from the device
+    CleanDisk { path: String },
+    /// Stop the helper
+    Shutdown,
+}
+
+/// Response sent by the helper, one per request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    /// Reply to [`Request::Hello`]
+    Hello { version: u32 },
+    /// The device was opened
+    ///
+    /// On Linux the file descriptor travels alongside the message and `handle` is unused.
+    /// On Windows `handle` is the handle value already duplicated into the client process.
+    Opened { handle: u64 },
+    /// The request succeeded
+    Done,
+    /// The request failed
+    Error { message: String },
+}
+
+/// Serialize a message as a single line
+pub fn encode<T: Serialize>(message: &T) -> io::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(message).map_err(io::Error::other)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Take the next complete message out of `pending`, if one has arrived
+pub fn decode_next<T: DeserializeOwned>(pending: &mut Vec<u8>) -> io::Result<Option<T>> {
+    let Some(end) = pending.iter().position(|&b| b == b'\n') else {
+        return Ok(None);
+    };
+    let line: Vec<u8> = pending.drain(..=end).collect();
+    serde_json::from_slice(&line[..end])
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -41,12 +41,13 @@ cp "$PROJECT_ROOT/target/release/$PACKAGE_NAME" "$PACKAGE_DIR/usr/bin/"
 # Copy control file and update version
 sed "s/Version: .*/Version: $VERSION/" "$PROJECT_ROOT/installers/linux/control" > "$PACKAGE_DIR/DEBIAN/control"
 
-# Create desktop entry
+# Create desktop entry, also opening golem-imager:// links and .img.xz files
 cat > "$PACKAGE_DIR/usr/share/applications/$PACKAGE_NAME.desktop" << EOF
 [Desktop Entry]
 Name=Golem GPU Imager
 Comment=GPU Image Management Tool for Golem Network
-Exec=/usr/bin/$PACKAGE_NAME
+Exec=/usr/bin/$PACKAGE_NAME %u
+MimeType=x-scheme-handler/golem-imager;application/x-raw-disk-image-xz-compressed;
 Icon=$PACKAGE_NAME
 Terminal=false
 Type=Application
@@ -3,17 +3,14 @@
 // This module provides platform-independent disk access with platform-specific
 // implementations where necessary. Common operations share implementation code.
 
+use ::gpt::GptConfig;
 use anyhow::{Context, Result, anyhow};
-use crc32fast::Hasher;
-use gpt::GptConfig;
-use iced::task::{self, Sipper};
-use sha2::Digest;
-use std::cmp;
+use sipper::{Sipper, sipper};
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
-use xz4rust::XzReader;
 
 // Platform-specific modules
 #[cfg(target_os = "linux")]
@@ -22,23 +19,113 @@ mod linux;
 #[cfg(windows)]
 mod windows;
 
-#[cfg(windows)]
-mod windows_aligned_io;
-#[cfg(windows)]
-pub use windows_aligned_io::aligned_disk_io;
+/// Opening, clearing and listing disks through the OS, or a fake in tests
+pub mod access;
+pub use access::{DiskAccess, DiskBackend};
+
+// Sector-aligned reads and writes for unbuffered disk access
+mod aligned_io;
+pub use aligned_io::{AlignedDiskIo, aligned_disk_io};
+
+/// Reopening devices that re-enumerate mid-operation
+mod reopen;
+
+/// Per-block comparison of written and read-back data
+mod bad_blocks;
+pub use bad_blocks::{BadBlockKind, BadBlockReport, BadRange, BlockHasher};
+
+/// Retrying writes on transient I/O errors
+mod write_retry;
+
+/// Reopening the disk with buffered I/O when it refuses direct I/O writes
+mod buffered_fallback;
 
-// Aligned I/O modules
-mod aligned_reader;
-#[allow(unused_imports)]
-pub use aligned_reader::AlignedReader;
+/// Write speed limit
+mod throttle;
+
+/// Copying an image to a disk and reading it back, over any reader and writer
+pub mod writer;
 
 /// Common functionality for disk access regardless of platform
 mod common;
-pub use common::{DiskDevice, WriteProgress};
+pub use common::{
+    DiskDevice, DownloadProgress, FlashPhase, ImagePartition, ImageSource, PartitionProgress,
+    WriteLatency,
+};
+
+/// XZ / Zstandard decoding of image streams
+mod decoder;
+pub use decoder::{Compression, ImageFormat, check_image_file, open_image_file};
+
+/// Resumable HTTPS image streaming
+mod remote_source;
+
+/// Configuration settings understood by each image generation
+mod config_schema;
+pub use config_schema::{ConfigField, ConfigSchema};
 
 /// Configuration types and parsing
 mod configuration;
-pub use configuration::ImageConfiguration;
+pub use configuration::{ImageConfiguration, MANAGED_ENV_KEYS, MANAGED_TOML_KEYS};
+
+/// Round-tripping golem.env parser
+mod env_file;
+pub use env_file::EnvFile;
+
+/// Disk health (S.M.A.R.T.) probing
+pub mod health;
+pub use health::{DiskHealth, HealthStatus};
+
+/// Stable device identification by serial number / WWN
+pub mod identity;
+pub use identity::DeviceIdentity;
+
+/// Mount points and programs that keep a disk busy
+pub mod usage;
+pub use usage::DeviceUsage;
+
+/// Whole disks, partitions and the other block devices they consist of
+pub mod topology;
+
+/// Read-only flag of disks, shown in the device lists and cleared before writing
+pub mod read_only;
+
+/// Ejecting written disks in duplicator mode
+pub mod eject;
+
+/// Logical and physical sector sizes, queried once per device
+pub mod geometry;
+
+/// GPT structures read by hand
+mod gpt;
+pub use gpt::randomize::GuidRandomization;
+
+/// Read-only partition table inspection
+pub mod layout;
+pub use layout::DiskLayout;
+
+/// Preserving the configuration partition across in-place image updates
+pub mod update;
+pub use update::{ConfigPartitionContents, ConfigSnapshot, InstalledImage};
+
+/// Saving and restoring the configuration partition
+pub mod backup;
+
+/// Saving the regions wiped before a write, so a cancelled wipe can be undone
+pub mod wipe;
+pub use wipe::WipeSnapshot;
+
+/// Write and read speed of a device, measured on an area that is put back afterwards
+pub mod speed_test;
+pub use speed_test::SpeedTestResult;
+
+/// Finding devices that claim more capacity than their flash has, by erasing them
+pub mod capacity_test;
+pub use capacity_test::CapacityReport;
+
+/// Synthetic GPT and FAT disks, and a fake backend serving them, for unit tests
+#[cfg(test)]
+pub mod test_support;
 
 /// Platform-specific disk operations trait
 #[cfg(target_os = "linux")]
@@ -60,6 +147,15 @@ pub struct GolemConfig {
     pub configuration_server: Option<String>,
     pub metrics_server: Option<String>,
     pub central_net_host: Option<String>,
+    pub node_name: Option<String>,
+}
+
+/// Configuration and installed image of a Golem device, returned by read_device_info
+#[derive(Debug, Clone)]
+pub struct GolemDeviceInfo {
+    pub config: GolemConfig,
+    /// Image version, if the device records one
+    pub version: Option<String>,
 }
 
 /// Main disk access struct that provides platform-independent access to disks
@@ -69,7 +165,7 @@ pub struct Disk {
     file: File,
 
     // Platform-specific data and operations
-    platform: PlatformDiskAccess,
+    platform: Arc<dyn DiskAccess>,
 
     // Original path used to open this disk - preserved for operations that need path info
     // This is particularly important for Windows disk cleaning
@@ -100,15 +196,20 @@ impl Disk {
     ///
     /// # Arguments
     /// * `path` - The path to the disk device
-    ///   (e.g., "/dev/sda" on Linux, "\\.\PhysicalDrive0" or "C:" on Windows)
+    ///   (e.g., "/dev/sda" on Linux, "\\.\PhysicalDrive0" on Windows). Partitions and
+    ///   volume letters such as "/dev/sda1" or "D:" are refused, naming the disk they belong to.
     /// * `edit_mode` - When true, we're opening for editing configuration only, not writing an image.
     ///   This skips diskpart cleaning on Windows, which avoids potential data loss during editing.
+    ///   Also used when the disk was already cleaned with [`Disk::clear_partitions`].
     ///
     /// # Returns
     /// * `Result<Self>` - A new Disk instance on success, Error on failure
     pub async fn lock_path(path: &str, edit_mode: bool) -> Result<Self> {
+        // Second line of defense behind the device lists, which hide the same devices
+        check_device_rules(path).await?;
+
         // Platform-specific implementation to open and lock disk
-        let (file, platform) = PlatformDiskAccess::lock_path(path, edit_mode).await?;
+        let (file, platform) = access::backend().lock_path(path, edit_mode).await?;
 
         Ok(Disk {
             file,
@@ -117,22 +218,239 @@ impl Disk {
         })
     }
 
+    /// Remove existing partitions before the disk is locked for writing
+    ///
+    /// On Windows this runs diskpart and dismounts remaining volumes, reporting each step
+    /// as `FlashPhase::Clearing`. Other platforms have nothing to clear.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the disk device
+    /// * `cancel_token` - Token to cancel the operation
+    pub fn clear_partitions(
+        path: &str,
+        cancel_token: crate::models::CancelToken,
+    ) -> impl Sipper<Result<FlashPhase>, FlashPhase> + Send + 'static {
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let clearing =
+            access::backend().clear_partitions(path.to_string(), cancel_token, progress_tx);
+        sipper(async move |mut sipper| -> Result<FlashPhase> {
+            let clearing = tokio::spawn(clearing);
+
+            // Forward progress until the backend is done and drops its sender
+            while let Some(progress) = progress_rx.recv().await {
+                sipper.send(progress).await;
+            }
+
+            clearing.await?
+        })
+    }
+
     /// Get a cloned file handle to the disk
     fn get_cloned_file_handle(&self) -> Result<File> {
         self.platform.clone_file_handle(&self.file)
     }
 
+    /// Sector sizes of the disk
+    pub fn geometry(&self) -> geometry::SectorGeometry {
+        geometry::query(&self.original_path)
+    }
+
+    /// Cloned file handle that reads and writes whole sectors at aligned offsets
+    fn aligned_io(&self) -> Result<AlignedDiskIo> {
+        let file = self.get_cloned_file_handle()?;
+        Ok(AlignedDiskIo::new(
+            file,
+            self.geometry().io_alignment() as u32,
+        )?)
+    }
+
+    /// Read the existing partition layout without modifying the disk
+    ///
+    /// # Returns
+    /// * `Result<DiskLayout>` - Partition table scheme, partitions and detected filesystems
+    pub fn read_layout(&self) -> Result<DiskLayout> {
+        let mut reader = self.aligned_io()?;
+        layout::read_layout(&mut reader, self.geometry().logical)
+    }
+
+    /// Read the installed image version and configuration partition of a Golem device
+    ///
+    /// Run before an in-place update so the partition's files can be restored afterwards.
+    ///
+    /// # Returns
+    /// * `Result<InstalledImage>` - Detected version, parsed configuration and the files
+    pub fn read_installed_image(&self) -> Result<InstalledImage> {
+        let mut reader = self.aligned_io()?;
+
+        let layout = layout::read_layout(&mut reader, self.geometry().logical)?;
+        let Some(partition) = layout.golem_config_partition() else {
+            return Err(anyhow!("Device has no Golem configuration partition"));
+        };
+        let partition_data = read_partition_data(&mut reader, partition)?;
+
+        let fs = fatfs::FileSystem::new(
+            std::io::Cursor::new(partition_data),
+            fatfs::FsOptions::new(),
+        )
+        .context("Configuration partition does not contain a FAT filesystem")?;
+        let root_dir = fs.root_dir();
+
+        let snapshot = ConfigSnapshot::capture(&root_dir)?;
+        let config = match read_golem_config(&root_dir) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                warn!("Failed to parse configuration on device: {}", e);
+                None
+            }
+        };
+        let version = update::detect_image_version(&snapshot, &layout);
+        info!(
+            "Installed image version: {}",
+            version.as_deref().unwrap_or("unknown")
+        );
+
+        Ok(InstalledImage {
+            version,
+            config,
+            snapshot,
+        })
+    }
+
+    /// Save a raw copy of the configuration partition to a file
+    ///
+    /// # Arguments
+    /// * `path` - Where to write the backup; an existing file is replaced
+    ///
+    /// # Returns
+    /// * `Result<u64>` - Size of the backup in bytes
+    pub fn backup_config_partition(&self, path: &std::path::Path) -> Result<u64> {
+        let mut reader = self.aligned_io()?;
+
+        let layout = layout::read_layout(&mut reader, self.geometry().logical)?;
+        let Some(partition) = layout.golem_config_partition() else {
+            return Err(anyhow!("Device has no Golem configuration partition"));
+        };
+        let partition_data = read_partition_data(&mut reader, partition)?;
+
+        // A backup that can't be restored is worse than none, so check it mounts first
+        fatfs::FileSystem::new(
+            std::io::Cursor::new(&partition_data[..]),
+            fatfs::FsOptions::new(),
+        )
+        .context("Configuration partition does not contain a FAT filesystem")?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &partition_data)
+            .with_context(|| format!("Failed to write backup to {}", path.display()))?;
+
+        info!(
+            "Backed up configuration partition ({} bytes) to {}",
+            partition_data.len(),
+            path.display()
+        );
+        Ok(partition_data.len() as u64)
+    }
+
+    /// Save the regions the image write wipes to a file, see [`WipeSnapshot`]
+    ///
+    /// # Arguments
+    /// * `path` - Where to keep the saved bytes; the file must not exist yet
+    pub fn snapshot_wipe_regions(&self, path: &std::path::Path) -> Result<WipeSnapshot> {
+        let mut disk_file = self.get_cloned_file_handle()?;
+        let disk_size = get_disk_size_windows(&mut disk_file)?;
+        let mut reader = AlignedDiskIo::new(disk_file, self.geometry().io_alignment() as u32)?;
+        WipeSnapshot::capture(&mut reader, disk_size, path)
+    }
+
+    /// Put back the regions saved by [`Disk::snapshot_wipe_regions`]
+    ///
+    /// Only meant for a disk whose write was cancelled before any image data was written;
+    /// the regions are written as they were, whatever the disk holds now.
+    pub fn restore_wipe_regions(&mut self, snapshot: &WipeSnapshot) -> Result<()> {
+        let mut disk_file = self.get_cloned_file_handle()?;
+        let disk_size = get_disk_size_windows(&mut disk_file)?;
+        let mut writer = AlignedDiskIo::new(disk_file, self.geometry().io_alignment() as u32)?;
+        snapshot.restore(&mut writer, disk_size)?;
+        info!("Undid the wipe of {}", self.original_path);
+        Ok(())
+    }
+
+    /// Measure how fast the disk writes and reads, see [`speed_test`]
+    ///
+    /// The tested area is written back as it was, nothing else on the disk is touched.
+    pub fn speed_test(&mut self) -> Result<SpeedTestResult> {
+        let mut disk_file = self.get_cloned_file_handle()?;
+        let disk_size = get_disk_size_windows(&mut disk_file)?;
+        let mut disk = AlignedDiskIo::new(disk_file, self.geometry().io_alignment() as u32)?;
+        speed_test::run(&mut disk, disk_size, |disk| {
+            speed_test::uncache_file(disk.get_ref())
+        })
+    }
+
+    /// Check that the disk holds as much data as it claims, see [`capacity_test`]
+    ///
+    /// This erases the disk: test blocks are written across all of it and not put back.
+    pub fn capacity_test(
+        &mut self,
+        cancel_token: &crate::models::CancelToken,
+        progress: impl FnMut(f32),
+    ) -> Result<CapacityReport> {
+        let mut disk_file = self.get_cloned_file_handle()?;
+        let disk_size = get_disk_size_windows(&mut disk_file)?;
+        let mut disk = AlignedDiskIo::new(disk_file, self.geometry().io_alignment() as u32)?;
+        capacity_test::run(
+            &mut disk,
+            disk_size,
+            |disk| speed_test::uncache_file(disk.get_ref()),
+            cancel_token,
+            progress,
+        )
+    }
+
+    /// Copy the files of a backup made by [`Disk::backup_config_partition`] onto the device
+    ///
+    /// Files are restored rather than the raw partition, so the backup may come from a
+    /// partition of a different size. Files not in the backup are left in place.
+    pub fn restore_config_partition(&mut self, path: &std::path::Path) -> Result<()> {
+        let snapshot = backup::read_backup(path)?;
+
+        let mut reader = self.aligned_io()?;
+        let layout = layout::read_layout(&mut reader, self.geometry().logical)?;
+        let Some(partition) = layout.golem_config_partition() else {
+            return Err(anyhow!("Device has no Golem configuration partition"));
+        };
+        let start_offset = partition.start_offset;
+        let mut partition_data = read_partition_data(&mut reader, partition)?;
+
+        ensure_fat_filesystem(&mut partition_data)?;
+        {
+            let fs = fatfs::FileSystem::new(
+                std::io::Cursor::new(&mut partition_data[..]),
+                fatfs::FsOptions::new(),
+            )?;
+            snapshot.restore(&fs.root_dir())?;
+        }
+
+        self.write_partition_to_disk(start_offset, &partition_data)?;
+        info!("Restored configuration partition from {}", path.display());
+        Ok(())
+    }
+
     /// Write configuration to a specific partition using an existing file handle
     ///
     /// # Arguments
     /// * `disk_file` - The locked disk file handle
-    /// * `config` - Configuration to write to the partition
+    /// * `contents` - Configuration to merge in, or files of a previous partition to restore
+    /// * `geometry` - Sector sizes of the disk
     ///
     /// # Returns
     /// * Result indicating success or failure
     fn write_configuration_to_partition(
         disk_file: &mut File,
-        config: &ImageConfiguration,
+        contents: &ConfigPartitionContents,
+        geometry: &geometry::SectorGeometry,
     ) -> Result<()> {
         use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 
@@ -151,73 +469,47 @@ impl Disk {
         // Read GPT manually to find the configuration partition (no GPT library, no file cloning)
         info!("Reading GPT header manually to find configuration partition");
 
-        const LOGICAL_SECTOR_SIZE: u64 = 512;
-        const PHYSICAL_SECTOR_SIZE: u64 = 4096; // Windows direct I/O alignment requirement
-        const GPT_SIGNATURE: [u8; 8] = [0x45, 0x46, 0x49, 0x20, 0x50, 0x41, 0x52, 0x54]; // "EFI PART"
+        let logical_sector_size = geometry.logical;
+        let disk_sectors = get_disk_size_windows(disk_file)? / logical_sector_size;
 
         // For Windows direct I/O, we need to read aligned blocks
-        // Read from LBA 0 (which includes LBA 1 where GPT header is) using 4KB alignment
+        // Read from LBA 0 (which includes LBA 1 where GPT header is) using aligned blocks
         let read_start = 0u64; // Start from beginning of disk
-        let read_size = PHYSICAL_SECTOR_SIZE * 2; // Read 8KB to ensure we get GPT header at LBA 1
+        let read_size = geometry.align_up(logical_sector_size * 2); // LBA 0 and the GPT header at LBA 1
 
         disk_file.seek(SeekFrom::Start(read_start))?;
         let mut aligned_buffer = vec![0u8; read_size as usize];
         disk_file.read_exact(&mut aligned_buffer)?;
 
-        // Extract GPT header from LBA 1 (starts at offset 512 in our buffer)
-        let gpt_header_offset = LOGICAL_SECTOR_SIZE as usize;
-        if aligned_buffer.len() < gpt_header_offset + LOGICAL_SECTOR_SIZE as usize {
+        // Extract GPT header from LBA 1 (starts one logical sector into our buffer)
+        let gpt_header_offset = logical_sector_size as usize;
+        if aligned_buffer.len() < gpt_header_offset + logical_sector_size as usize {
             return Err(anyhow!("Buffer too small for GPT header"));
         }
         let header_buffer =
-            &aligned_buffer[gpt_header_offset..gpt_header_offset + LOGICAL_SECTOR_SIZE as usize];
-
-        // Verify GPT signature
-        if header_buffer[0..8] != GPT_SIGNATURE {
-            return Err(anyhow!("No valid GPT found on disk"));
-        }
+            &aligned_buffer[gpt_header_offset..gpt_header_offset + logical_sector_size as usize];
 
-        // Extract partition table info from GPT header
-        let partition_entries_lba = u64::from_le_bytes([
-            header_buffer[72],
-            header_buffer[73],
-            header_buffer[74],
-            header_buffer[75],
-            header_buffer[76],
-            header_buffer[77],
-            header_buffer[78],
-            header_buffer[79],
-        ]);
-        let num_partition_entries = u32::from_le_bytes([
-            header_buffer[80],
-            header_buffer[81],
-            header_buffer[82],
-            header_buffer[83],
-        ]);
-        let partition_entry_size = u32::from_le_bytes([
-            header_buffer[84],
-            header_buffer[85],
-            header_buffer[86],
-            header_buffer[87],
-        ]);
+        // Check the header before any buffer is sized from it
+        let header = gpt::header::GptHeader::parse(header_buffer)?;
+        header.check_entries(logical_sector_size, disk_sectors)?;
+        let num_partition_entries = header.entry_count;
+        let partition_entry_size = header.entry_size;
 
         info!(
             "GPT: {} partition entries at LBA {}, {} bytes each",
-            num_partition_entries, partition_entries_lba, partition_entry_size
+            num_partition_entries, header.entries_lba, partition_entry_size
         );
 
         // Read partition entries with proper alignment for Windows direct I/O
-        let partition_entries_offset = partition_entries_lba * LOGICAL_SECTOR_SIZE;
-        let partition_table_logical_size =
-            num_partition_entries as u64 * partition_entry_size as u64;
+        let partition_entries_offset = geometry.lba_offset(header.entries_lba);
+        let partition_table_logical_size = header.entries_len();
 
-        // Round up to physical sector alignment for Windows
-        let aligned_offset =
-            (partition_entries_offset / PHYSICAL_SECTOR_SIZE) * PHYSICAL_SECTOR_SIZE;
+        // Round to the direct I/O alignment
+        let aligned_offset = geometry.align_down(partition_entries_offset);
         let offset_within_read = (partition_entries_offset - aligned_offset) as usize;
         // Need to account for the offset when calculating aligned size
         let total_needed = offset_within_read as u64 + partition_table_logical_size;
-        let aligned_size = total_needed.div_ceil(PHYSICAL_SECTOR_SIZE) * PHYSICAL_SECTOR_SIZE;
+        let aligned_size = geometry.align_up(total_needed);
 
         info!(
             "Reading partition table: logical offset={}, logical size={}, aligned offset={}, aligned size={}, offset within read={}, total needed={}",
@@ -246,11 +538,9 @@ impl Disk {
         let mut found = false;
         let mut discovered_partitions = Vec::new();
 
+        // The header was checked, so the table holds every entry
         for i in 0..num_partition_entries {
             let entry_offset = (i as usize) * (partition_entry_size as usize);
-            if entry_offset + 128 > partition_table.len() {
-                break; // Safety check
-            }
 
             // Extract partition GUID (bytes 16-31 of partition entry)
             let partition_guid = &partition_table[entry_offset + 16..entry_offset + 32];
@@ -260,35 +550,12 @@ impl Disk {
                 continue;
             }
 
-            // Convert partition GUID to UUID string for logging
-            // GPT uses mixed-endian format: first 8 bytes need byte-swapping, last 8 bytes unchanged
-            let mut guid_bytes = [0u8; 16];
-            guid_bytes.copy_from_slice(partition_guid);
-
-            // Apply UEFI GUID mixed-endian conversion:
-            // - Bytes 0-3: little-endian (swap)
-            // - Bytes 4-5: little-endian (swap)
-            // - Bytes 6-7: little-endian (swap)
-            // - Bytes 8-15: big-endian (unchanged)
-            guid_bytes[0..4].reverse(); // First 4 bytes
-            guid_bytes[4..6].reverse(); // Next 2 bytes  
-            guid_bytes[6..8].reverse(); // Next 2 bytes
-            // Last 8 bytes remain unchanged
-
-            let partition_uuid = Uuid::from_bytes(guid_bytes);
+            let partition_uuid = gpt::guid::from_gpt_bytes(partition_guid.try_into()?);
 
             // Extract partition type GUID (bytes 0-15 of partition entry)
-            let type_guid = &partition_table[entry_offset..entry_offset + 16];
-            let mut type_bytes = [0u8; 16];
-            type_bytes.copy_from_slice(type_guid);
-
-            // Apply same UEFI GUID conversion for type UUID
-            type_bytes[0..4].reverse(); // First 4 bytes
-            type_bytes[4..6].reverse(); // Next 2 bytes  
-            type_bytes[6..8].reverse(); // Next 2 bytes
-            // Last 8 bytes remain unchanged
-
-            let type_uuid = Uuid::from_bytes(type_bytes);
+            let type_uuid = gpt::guid::from_gpt_bytes(
+                partition_table[entry_offset..entry_offset + 16].try_into()?,
+            );
 
             // Extract LBA range for size calculation
             let first_lba = u64::from_le_bytes([
@@ -311,7 +578,8 @@ impl Disk {
                 partition_table[entry_offset + 46],
                 partition_table[entry_offset + 47],
             ]);
-            let part_size = (last_lba - first_lba + 1) * LOGICAL_SECTOR_SIZE;
+            gpt::header::check_partition(i, first_lba, last_lba, disk_sectors)?;
+            let part_size = (last_lba - first_lba + 1) * logical_sector_size;
 
             // Store discovered partition info for logging
             discovered_partitions.push(format!(
@@ -322,21 +590,10 @@ impl Disk {
                 part_size / (1024 * 1024)
             ));
 
-            // Convert target UUID to byte array for comparison
-            let target_bytes = target_uuid.as_bytes();
-
-            // Apply same UEFI GUID conversion to partition_guid for comparison
-            let mut comparison_guid_bytes = [0u8; 16];
-            comparison_guid_bytes.copy_from_slice(partition_guid);
-            comparison_guid_bytes[0..4].reverse(); // First 4 bytes
-            comparison_guid_bytes[4..6].reverse(); // Next 2 bytes  
-            comparison_guid_bytes[6..8].reverse(); // Next 2 bytes
-            // Last 8 bytes remain unchanged
-
-            if comparison_guid_bytes == *target_bytes {
+            if partition_uuid == target_uuid {
                 // Found our partition! Use already extracted LBA values
-                start_offset = first_lba * LOGICAL_SECTOR_SIZE;
-                partition_size = (last_lba - first_lba + 1) * LOGICAL_SECTOR_SIZE;
+                start_offset = geometry.lba_offset(first_lba);
+                partition_size = (last_lba - first_lba + 1) * logical_sector_size;
                 found = true;
 
                 info!(
@@ -380,14 +637,15 @@ impl Disk {
             "Found partition at offset {}, size {} bytes",
             start_offset, partition_size
         );
+        check_in_memory_size(CONFIG_PARTITION_UUID, partition_size)?;
 
         // Read partition into memory with proper alignment for Windows direct I/O
-        // Round partition boundaries to physical sector alignment
-        let aligned_start = (start_offset / PHYSICAL_SECTOR_SIZE) * PHYSICAL_SECTOR_SIZE;
+        // Round partition boundaries to the direct I/O alignment
+        let aligned_start = geometry.align_down(start_offset);
         let offset_within_aligned = (start_offset - aligned_start) as usize;
         // Need to account for the offset when calculating aligned size
         let total_needed = offset_within_aligned as u64 + partition_size;
-        let aligned_size = total_needed.div_ceil(PHYSICAL_SECTOR_SIZE) * PHYSICAL_SECTOR_SIZE;
+        let aligned_size = geometry.align_up(total_needed);
 
         info!(
             "Reading partition data: logical offset={}, logical size={}, aligned offset={}, aligned size={}, offset within aligned={}, total needed={}",
@@ -411,38 +669,42 @@ impl Disk {
         let mut partition_data =
             aligned_partition_data[offset_within_aligned..partition_end].to_vec();
 
-        // Format partition with FAT filesystem
-        {
-            let cursor = Cursor::new(&mut partition_data[..]);
-            fatfs::format_volume(
-                cursor,
-                fatfs::FormatVolumeOptions::new().volume_label(*b"GOLEMCONF  "),
-            )?;
-        }
+        // Keep the filesystem shipped with the image, only format if it isn't valid FAT
+        ensure_fat_filesystem(&mut partition_data)?;
 
-        // Create filesystem on formatted data and write files
-        {
+        // Open the filesystem and update the configuration files in place
+        let expected_files = {
             let cursor = Cursor::new(&mut partition_data[..]);
             let fs = fatfs::FileSystem::new(cursor, fatfs::FsOptions::new())?;
             let root_dir = fs.root_dir();
 
-            // Generate both files using elegant methods
-            let (toml_content, env_content) = config.generate_config_files();
-            
-            // Write golemwz.toml
-            let mut toml_file = root_dir.create_file("golemwz.toml")?;
-            toml_file.write_all(toml_content.as_bytes())?;
-            toml_file.flush()?;
-            drop(toml_file);
-
-            // Write golem.env
-            let mut env_file = root_dir.create_file("golem.env")?;
-            env_file.write_all(env_content.as_bytes())?;
-            env_file.flush()?;
-            drop(env_file);
+            match contents {
+                ConfigPartitionContents::Configuration(config) => {
+                    // Merge into whatever the image shipped so extra keys survive
+                    let existing_toml = read_config_file(&root_dir, "golemwz.toml");
+                    let existing_env = read_config_file(&root_dir, "golem.env");
+                    let (toml_content, env_content) = config
+                        .merge_config_files(existing_toml.as_deref(), existing_env.as_deref());
+
+                    write_config_file(&root_dir, "golemwz.toml", &toml_content)?;
+                    write_config_file(&root_dir, "golem.env", &env_content)?;
+                    let mut files = vec![
+                        ("golemwz.toml".to_string(), toml_content.into_bytes()),
+                        ("golem.env".to_string(), env_content.into_bytes()),
+                    ];
+                    if let Some(firstboot) = &config.firstboot {
+                        files.push(write_firstboot_file(&root_dir, firstboot)?);
+                    }
+                    files
+                }
+                ConfigPartitionContents::Restore(snapshot) => {
+                    snapshot.restore(&root_dir)?;
+                    snapshot.files.clone()
+                }
+            }
 
             // Filesystem will be dropped at end of this block, releasing the mutable borrow
-        }
+        };
 
         // Write partition back to disk with proper alignment for Windows direct I/O
         // We need to write back the entire aligned block to preserve data outside our partition
@@ -458,43 +720,83 @@ impl Disk {
         disk_file.write_all(&aligned_partition_data)?;
         disk_file.flush()?;
 
+        // Read the partition back so a write that didn't stick fails here, not at first boot
+        disk_file.seek(SeekFrom::Start(aligned_start))?;
+        disk_file.read_exact(&mut aligned_partition_data)?;
+        verify_config_partition(
+            &mut aligned_partition_data[offset_within_aligned..partition_end],
+            contents,
+            &expected_files,
+        )?;
+
         info!("Successfully wrote configuration to partition");
         Ok(())
     }
 
-    /// Write an image file to the disk with progress reporting
+    /// Write an image to the disk with progress reporting
+    ///
+    /// Images streamed from a URL usually have no metadata yet. The image is then written
+    /// until the stream ends and verified against the hash of the data streamed to disk.
     ///
     /// # Arguments
-    /// * `image_path` - Path to the image file to write
-    /// * `metadata` - Image metadata containing expected size and hash
+    /// * `image` - Local file or URL of the compressed image
+    /// * `metadata` - Expected uncompressed size and hash, if known
+    /// * `partitions` - Partitions of the uncompressed image, to report which one is written
     /// * `cancel_token` - Token to cancel the operation
-    /// * `config` - Optional configuration to write after image writing
+    /// * `skip_verification` - Token to stop verifying while keeping the completed write;
+    ///   the GPT fix and configuration are still written and the result is
+    ///   `FlashPhase::Unverified`
+    /// * `config` - Optional configuration partition contents to write after image writing
+    /// * `write_speed_limit` - Most bytes per second written to the disk, unlimited if `None`
+    /// * `guids` - GPT GUIDs replaced with random ones once the image is written
     ///
     /// # Returns
     /// * A sipper that reports progress updates as the write proceeds
     pub fn write_image(
         self,
-        image_path: &str,
-        metadata: crate::models::ImageMetadata,
+        image: ImageSource,
+        metadata: Option<crate::models::ImageMetadata>,
+        partitions: Vec<ImagePartition>,
         cancel_token: crate::models::CancelToken,
-        config: Option<ImageConfiguration>,
-    ) -> impl Sipper<Result<WriteProgress>, WriteProgress> + Send + 'static {
-        debug!("Opening image file: {}", image_path);
-        let image_path_owned = image_path.to_string();
-        let image_file_r = File::open(&image_path_owned)
-            .with_context(|| format!("Failed to open image file: {}", image_path_owned));
+        skip_verification: crate::models::CancelToken,
+        config: Option<ConfigPartitionContents>,
+        write_speed_limit: Option<u64>,
+        guids: GuidRandomization,
+    ) -> impl Sipper<Result<FlashPhase>, FlashPhase> + Send + 'static {
+        debug!("Opening image: {}", image);
 
         // Use a larger buffer for better performance (matching disk-image-writer)
         const BUFFER_SIZE: usize = 4 * 1024 * 1024; // 4MB buffer
 
         // Save original path and platform data before moving self into the task
-        let original_path = self.original_path.clone();
-        let _platform_data = self.platform.clone();
+        let mut original_path = self.original_path.clone();
+        let platform = self.platform.clone();
 
         let disk_file_r = self.get_cloned_file_handle();
-        task::sipper(async move |mut sipper| -> Result<WriteProgress> {
-            let image_file = std::io::BufReader::with_capacity(BUFFER_SIZE, image_file_r?);
-            let _size = image_file.get_ref().metadata()?.len();
+        sipper(async move |mut sipper| -> Result<FlashPhase> {
+            // Size of an uncompressed image file, which is written as it is
+            let mut raw_size = None;
+            let (image_file, stream_stats): (Box<dyn Read + Send>, _) = match &image {
+                ImageSource::File(path) => {
+                    let file = File::open(path)
+                        .with_context(|| format!("Failed to open image file: {}", path))?;
+                    if ImageFormat::detect_file(std::path::Path::new(path))? == Some(ImageFormat::Raw) {
+                        info!("Writing uncompressed image {}", path);
+                        raw_size = Some(file.metadata()?.len());
+                    }
+                    (
+                        Box::new(std::io::BufReader::with_capacity(BUFFER_SIZE, file)),
+                        None,
+                    )
+                }
+                ImageSource::Url { url, .. } => {
+                    let reader = remote_source::HttpImageReader::open(url, cancel_token.clone())
+                        .await
+                        .with_context(|| format!("Failed to start streaming {}", url))?;
+                    let stats = reader.stats();
+                    (Box::new(reader), Some(stats))
+                }
+            };
 
             // Don't use buffered writers as they can interfere with direct I/O alignment
             // For consistent behavior across platforms, use unbuffered writes everywhere
@@ -504,10 +806,16 @@ impl Disk {
             //let (tracked_image_file, events) = tracker::track_progress(image_file, size);
             let tracked_image_file = image_file;
 
-            sipper.send(WriteProgress::Start).await;
+            sipper.send(FlashPhase::Preparing).await;
 
             // Use blocking task for I/O operations to avoid blocking the async runtime
             tokio::task::spawn_blocking(move || {
+                // Report a phase without waiting for the UI to take it
+                let send_phase = |phase: FlashPhase| {
+                    let mut sipper = sipper.clone();
+                    std::mem::drop(tokio::spawn(async move { sipper.send(phase).await }));
+                };
+
                 // Platform-specific pre-write checks
                 // Note: Disk cleaning is now done during lock_path, before we have an exclusive lock
                 // We still pass the original path for verification purposes
@@ -518,7 +826,17 @@ impl Disk {
 
                 // Pass the original_path to pre_write_checks for any platform-specific final checks
                 // Use ? operator for more concise error handling
-                PlatformDiskAccess::pre_write_checks(&disk_file, Some(&original_path))?;
+                platform.pre_write_checks(&disk_file, Some(&original_path))?;
+
+                // Every read and write below stays aligned to the disk's sectors
+                let geometry = geometry::query(&original_path);
+                info!("Sector sizes of {}: {:?} ({})", original_path, geometry, geometry.format());
+
+                // A write cancelled while the disk was being prepared leaves the partition table alone
+                if cancel_token.is_cancelled() {
+                    info!("Write cancelled before the disk was wiped");
+                    return Err(anyhow!("Operation cancelled by user"));
+                }
 
                 // Clear first and last 4MB of disk to remove any existing partition tables or file systems
                 info!("Clearing first and last 4MB of disk");
@@ -527,17 +845,15 @@ impl Disk {
                 let disk_size = get_disk_size_windows(&mut disk_file)?;
 
                 // Create 4MB zero buffer (sector-aligned for Windows compatibility)
-                let zero_buffer = vec![0u8; 4 * 1024 * 1024];
+                let zero_buffer = vec![0u8; wipe::WIPE_SIZE as usize];
 
-                // Clear first 4MB
-                disk_file.seek(SeekFrom::Start(0))?;
-                disk_file.write_all(&zero_buffer)?;
-
-                // Clear last 4MB (if disk is large enough)
-                if disk_size > 8 * 1024 * 1024 {
-                    let last_4mb_start = disk_size - (4 * 1024 * 1024);
-                    disk_file.seek(SeekFrom::Start(last_4mb_start))?;
-                    disk_file.write_all(&zero_buffer)?;
+                // The last 4MB are only cleared if the disk is large enough
+                let regions = wipe::wiped_regions(disk_size);
+                for &(offset, length) in &regions {
+                    disk_file.seek(SeekFrom::Start(offset))?;
+                    disk_file.write_all(&zero_buffer[..length as usize])?;
+                }
+                if regions.len() > 1 {
                     info!("Cleared first and last 4MB of disk ({} MB total disk size)", disk_size / (1024 * 1024));
                 } else {
                     info!("Disk too small ({} MB), only cleared first 4MB", disk_size / (1024 * 1024));
@@ -546,85 +862,77 @@ impl Disk {
                 // Seek back to the beginning of the disk to start writing image data
                 disk_file.seek(SeekFrom::Start(0))?;
 
-                // Create XZ reader with our tracked file
-                // Force buffer size to be a multiple of 4096 for Windows direct I/O
-                let buffer_size = std::num::NonZeroUsize::new(4 * 1024 * 1024).unwrap(); // 4MB aligned buffer
-                info!(
-                    "Creating XZ reader with aligned buffer size: {} bytes",
-                    buffer_size
-                );
-
-                // XzReader::new_with_buffer_size returns XzReader directly, not a Result
-                let mut source_file =
-                    XzReader::new_with_buffer_size(tracked_image_file, buffer_size);
+                // Pick the XZ or Zstandard decoder from the stream's magic bytes
+                let mut source_file = decoder::open_decoder(tracked_image_file, raw_size.is_some())
+                    .context("Failed to read image header")?;
 
                 info!("Starting to copy decompressed image data to disk");
 
-                // Track total bytes copied for verification later
-                let mut total_copied: u64 = 0;
-                let mut total_written: u64 = 0;
-
-                // Use a properly aligned buffer for consistent behavior across platforms
-                // Direct I/O on Windows requires alignment, and this approach helps with
-                // buffer management on all platforms
-                {
-                    // Use aligned buffer copies instead of direct copy
-                    const ALIGNED_BUFFER_SIZE: usize = 4 * 1024 * 1024; // 4MB buffer aligned to 4K
-
-                    // Create a buffer that's a multiple of sector size for alignment
-                    let mut buffer = vec![0u8; ALIGNED_BUFFER_SIZE];
-
-                    info!(
-                        "Windows: Using aligned intermediate buffer of {} bytes",
-                        ALIGNED_BUFFER_SIZE
-                    );
-
-                    let mut ramaining_bytes = metadata.uncompressed_size;
-                    let total_size = metadata.uncompressed_size;
-
-                    while ramaining_bytes > 0 {
-                        // Check if operation was cancelled before reading the next chunk
-                        if cancel_token.is_cancelled() {
-                            info!("Disk write operation cancelled by user");
-                            return Err(anyhow::anyhow!("Operation cancelled by user"));
+                // Remember what the device starts with so it can be found again if it re-enumerates
+                let mut signature = reopen::DeviceSignature {
+                    identity: identity::query_device_identity(&original_path),
+                    disk_size,
+                    head: Vec::new(),
+                };
+
+                let policy = writer::FlashPolicy {
+                    alignment: geometry.io_alignment(),
+                    size_hint: raw_size,
+                    partitions,
+                    retry: write_retry::RetryPolicy::default(),
+                    speed_limit: write_speed_limit,
+                    cancel: cancel_token.clone(),
+                    skip_verification,
+                };
+                // Downloads report how much of the compressed image has arrived
+                let mut report = |phase: FlashPhase| match phase {
+                    FlashPhase::Writing { bytes, total, rate, partition, latency, .. } => send_phase(FlashPhase::Writing {
+                        bytes,
+                        total,
+                        rate,
+                        download: stream_stats.as_ref().map(|stats| DownloadProgress {
+                            downloaded: stats.downloaded.load(std::sync::atomic::Ordering::Relaxed),
+                            size: stats.download_size,
+                        }),
+                        partition,
+                        latency,
+                    }),
+                    phase => send_phase(phase),
+                };
+                // Readers that refuse aligned direct writes get a buffered handle instead
+                let mut fallback = buffered_fallback::BufferedFallback::new(disk_file, |file: &File| platform.reopen_buffered(file));
+                let written = writer::write(&mut source_file, &mut fallback, metadata.as_ref(), &policy, &mut report)?;
+                if fallback.is_buffered() {
+                    warn!("The image was written with buffered I/O, the device refused direct I/O");
+                }
+                disk_file = fallback.into_inner();
+                signature.head = written.head.clone();
+
+                // The download is only complete once the decoder consumed the stream's footer
+                if let (Some(stats), ImageSource::Url { sha256: Some(expected), .. }) = (&stream_stats, &image) {
+                    std::io::copy(&mut source_file, &mut std::io::sink())?;
+                    match stats.sha256.lock().unwrap().as_deref() {
+                        Some(actual) if actual.eq_ignore_ascii_case(expected) => {
+                            info!("Downloaded image matches the expected checksum");
                         }
-
-                        let bytes_to_write: usize = cmp::min(ramaining_bytes, ALIGNED_BUFFER_SIZE as u64).try_into()?;
-
-                        source_file.read_exact(&mut buffer[..bytes_to_write])?;
-                        disk_file.write_all(&buffer[0..bytes_to_write])?;
-
-                        total_copied += bytes_to_write as u64;
-                        total_written += bytes_to_write as u64;
-                        ramaining_bytes -= bytes_to_write as u64;
-
-                        {
-                            let mut sipper = sipper.clone();
-                            std::mem::drop(tokio::spawn(async move {
-                                sipper
-                                    .send(WriteProgress::Write {
-                                        total_written,
-                                        total_size,
-                                    })
-                                    .await
-                            }));
+                        Some(actual) => {
+                            error!("Downloaded image checksum mismatch: expected {}, got {}", expected, actual);
+                            return Err(anyhow::anyhow!(
+                                "The downloaded image is corrupted (checksum mismatch). Flash the device again."
+                            ));
                         }
+                        None => warn!("Image stream was not read to the end, download checksum not checked"),
                     }
-
-                    info!(
-                        "Successfully copied {} bytes with aligned buffers",
-                        total_copied
-                    );
                 }
 
                 // DEBUG: Block-by-block comparison of XZ content vs disk content
                 #[cfg(feature = "debug")]
-                {
+                if let ImageSource::File(image_path_owned) = &image {
+                    use sha2::Digest;
                     info!("DEBUG: Starting block-by-block comparison of XZ content vs disk content");
                         // Re-open XZ file for comparison
-                        let debug_image_file = File::open(&image_path_owned)?;
-                        let debug_buffer_size = std::num::NonZeroUsize::new(4 * 1024 * 1024).unwrap();
-                        let mut debug_xz_reader = XzReader::new_with_buffer_size(debug_image_file, debug_buffer_size);
+                        let debug_image_file = File::open(image_path_owned)?;
+                        let mut debug_xz_reader = decoder::open_decoder(debug_image_file, raw_size.is_some())?;
 
                         // Seek disk back to start for comparison
                         disk_file.seek(SeekFrom::Start(0))?;
@@ -638,12 +946,12 @@ impl Disk {
 
                         loop {
                             // Check if we've compared enough (limit to image size)
-                            if total_compared >= metadata.uncompressed_size {
+                            if total_compared >= written.bytes {
                                 break;
                             }
 
                             // Calculate how much to read in this block
-                            let remaining = metadata.uncompressed_size - total_compared;
+                            let remaining = written.bytes - total_compared;
                             let bytes_to_read = std::cmp::min(block_size as u64, remaining) as usize;
                             if remaining == 0 {
                                 break
@@ -711,171 +1019,42 @@ impl Disk {
                         }
                 }
 
-                // Verify written data
-                info!("Starting written data verification");
-
-                // Seek to start of disk for verification
-                disk_file.seek(SeekFrom::Start(0))?;
-
-                // Initialize hasher for verification
-                let mut verifier = sha2::Sha256::new();
-                let mut verified_bytes = 0u64;
-                // Use metadata.uncompressed_size for verification to match hash calculation
-                // This ensures we only verify the exact bytes that were in the original image
-                let total_size = metadata.uncompressed_size;
-                    const SECTOR_SIZE: u64 = 4096; // Use 4KB alignment for Windows compatibility
-                    let buffer_size = 4 * 1024 * 1024; // 4MB buffer
-                    let mut buffer = vec![0u8; buffer_size];
-
-                    info!(
-                        "Reading back {} bytes for verification (actual bytes written)",
-                        total_size
-                    );
+                // Read the image back, finding the device again if it drops off the bus
+                let (mut disk_file, verified) = writer::verify(disk_file, &written, &policy, &mut report, |stale| {
+                    let (path, file) = reopen::reopen_device(stale, &original_path, &signature, &cancel_token)?;
+                    if path != original_path {
+                        info!("Device moved from {} to {}", original_path, path);
+                        original_path = path;
+                    }
+                    Ok(file)
+                })?;
 
-                    while verified_bytes < total_size {
-                        // Check for cancellation
-                        if cancel_token.is_cancelled() {
-                            info!("Verification cancelled by user");
-                            return Err(anyhow::anyhow!("Verification cancelled by user"));
-                        }
+                info!("Post-copy checks starting");
 
-                        let remaining = total_size - verified_bytes;
-
-                        // For Windows direct I/O, we need to read in sector-aligned chunks
-                        // Calculate aligned read size, but ensure we don't read beyond what we need
-
-                        // For the final chunk, we need to be careful not to read beyond the image boundary
-                        // even when aligning to sectors. Only align if we're reading the final incomplete chunk.
-                        let aligned_read_size = if remaining <= buffer.len() as u64 {
-                            // This is the final chunk - only align if the remaining bytes are not already aligned
-                            if remaining % SECTOR_SIZE == 0 {
-                                // Already sector-aligned, read exactly what we need
-                                remaining
-                            } else {
-                                // Not aligned, so we need to read a sector-aligned amount
-                                // But limit it to avoid reading beyond device boundaries
-                                let aligned_size =
-                                    remaining.div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
-                                // Only use aligned size if it's within reasonable bounds (not more than one extra sector)
-                                if aligned_size - remaining <= SECTOR_SIZE {
-                                    aligned_size
-                                } else {
-                                    // Fall back to exact size if alignment would read too much
-                                    remaining
-                                }
-                            }
-                        } else {
-                            // For full buffer reads, use the buffer size (which should be sector-aligned)
-                            buffer.len() as u64
-                        };
-
-                        // Ensure we don't exceed buffer size
-                        let final_read_size =
-                            std::cmp::min(aligned_read_size, buffer.len() as u64) as usize;
-
-                        match disk_file.read(&mut buffer[0..final_read_size]) {
-                            Ok(0) => {
-                                warn!(
-                                    "Unexpected EOF during verification at {} bytes",
-                                    verified_bytes
-                                );
-                                break;
-                            }
-                            Ok(bytes_read) => {
-                                // Only hash the actual data bytes, not padding
-                                let actual_data_bytes =
-                                    std::cmp::min(bytes_read as u64, remaining) as usize;
-                                verifier.update(&buffer[0..actual_data_bytes]);
-                                verified_bytes += actual_data_bytes as u64;
-
-                                // Send verification progress
-                                let mut sipper_clone = sipper.clone();
-                                let total_size_copy = total_size;
-                                std::mem::drop(tokio::spawn(async move {
-                                    sipper_clone
-                                        .send(WriteProgress::Verifying {
-                                            verified_bytes,
-                                            total_size: total_size_copy,
-                                        })
-                                        .await
-                                }));
-
-                                // Log progress every 100MB
-                                if verified_bytes % (100 * 1024 * 1024) == 0
-                                    || verified_bytes == total_size
-                                {
-                                    info!(
-                                        "Verified {} / {} MB",
-                                        verified_bytes / (1024 * 1024),
-                                        total_size / (1024 * 1024)
-                                    );
-                                }
-                            }
-                            Err(e) => {
-                                error!("Error reading data for verification: {}", e);
-
-                                // Check for specific Windows errors that indicate device disconnection
-                                if let Some(error_code) = e.raw_os_error() {
-                                    match error_code {
-                                        433 => {
-                                            // Device does not exist - USB disconnected or reassigned
-                                            warn!("USB device became unavailable during verification (error 433)");
-                                            warn!("This often happens with USB devices during long operations");
-                                            return Err(anyhow::anyhow!(
-                                                "Device became unavailable during verification. \
-                                                The image was successfully written, but verification failed because \
-                                                the USB device was disconnected or reassigned by Windows. \
-                                                You can safely use the device - the write operation completed successfully."
-                                            ));
-                                        }
-                                        21 => {
-                                            // Device not ready
-                                            warn!("Device not ready during verification (error 21)");
-                                            return Err(anyhow::anyhow!(
-                                                "Device not ready during verification. \
-                                                The image was successfully written, but the device may need to be \
-                                                reconnected for verification."
-                                            ));
-                                        }
-                                        _ => {
-                                            // Other I/O errors
-                                            return Err(anyhow::anyhow!("Verification read failed: {}", e));
-                                        }
-                                    }
-                                } else {
-                                    return Err(anyhow::anyhow!("Verification read failed: {}", e));
-                                }
+                // New GUIDs go into the primary GPT, the repair below copies them to the backup
+                if guids != GuidRandomization::Keep {
+                    info!("Replacing GPT GUIDs: {}", guids);
+                    match randomize_gpt_guids(&mut disk_file, &geometry, guids) {
+                        Ok(replaced) => {
+                            for (old, new) in replaced {
+                                info!("Replaced GUID {} with {}", old, new);
                             }
                         }
+                        Err(e) => warn!("Failed to replace the GPT GUIDs (non-fatal): {:?}", e),
                     }
+                }
 
-                    // Finalize hash and compare
-                    let calculated_hash = verifier.finalize();
-                    let calculated_hash_hex = hex::encode(calculated_hash);
-
-                    info!("Calculated hash: {}", &calculated_hash_hex[..16]);
-                    info!("Expected hash:   {}", &metadata.uncompressed_hash[..16]);
-
-                    if calculated_hash_hex != metadata.uncompressed_hash {
-                        error!("Hash verification failed!");
-                        error!("Expected: {}", metadata.uncompressed_hash);
-                        error!("Got:      {}", calculated_hash_hex);
-                        return Err(anyhow::anyhow!(
-                            "Data verification failed: written data does not match expected hash"
-                        ));
-                    }
-
-                    info!("Hash verification successful - written data is correct");
-
-                info!("Post-copy checks starting");
-
-                // Fix GPT backup header location after unlocking volume
-                info!("Checking and fixing GPT backup header location if needed");
-                if let Err(e) = fix_gpt_backup_header(&mut disk_file) {
-                    warn!("Failed to fix GPT backup header (non-fatal): {:?}", e);
+                // Move the backup GPT to the end of the device and fix both headers' CRCs
+                info!("Verifying both GPT headers and repairing them if needed");
+                send_phase(FlashPhase::FixingGpt);
+                match repair_gpt(&mut disk_file, &geometry) {
+                    Ok(report) if report.is_empty() => info!("Both GPT headers are consistent"),
+                    Ok(report) => info!("Repaired the GPT: {}", report),
+                    Err(e) => warn!("Failed to repair the GPT (non-fatal): {:?}", e),
                 }
                 if let Some(config) = config {
-                    Self::write_configuration_to_partition(&mut disk_file, &config).context("failed to write configuration")?;
+                    send_phase(FlashPhase::WritingConfig);
+                    Self::write_configuration_to_partition(&mut disk_file, &config, &geometry).context("failed to write configuration")?;
                 }
 
                 // On Windows, unlock the volume first to allow GPT operations
@@ -905,7 +1084,7 @@ impl Disk {
                     error!("Failed to write image to disk: {}", e);
 
                     // Platform-specific error handling
-                    if let Some(error_context) = PlatformDiskAccess::handle_write_error(e) {
+                    if let Some(error_context) = platform.handle_write_error(e) {
                         return Err(error_context);
                     }
 
@@ -965,7 +1144,7 @@ impl Disk {
                     );
 
                     // Platform-specific flush error handling
-                    if let Some(error_context) = PlatformDiskAccess::handle_flush_error(&e) {
+                    if let Some(error_context) = platform.handle_flush_error(&e) {
                         return Err(error_context);
                     }
 
@@ -978,7 +1157,11 @@ impl Disk {
                 }
                 info!("Successfully wrote image to disk");
 
-                anyhow::Ok(WriteProgress::Finish)
+                if verified {
+                    anyhow::Ok(FlashPhase::Done)
+                } else {
+                    anyhow::Ok(FlashPhase::Unverified)
+                }
             })
             .await?
         })
@@ -1012,11 +1195,15 @@ impl Disk {
             config.network_type,
             &config.subnet,
             &config.glm_account,
+            config.glm_node_name.as_deref(),
             config.non_interactive_install,
             &config.ssh_keys,
             config.configuration_server.as_deref(),
             config.metrics_server.as_deref(),
             config.central_net_host.as_deref(),
+            &config.extra_env,
+            &config.extra_toml,
+            config.firstboot.as_ref(),
         )?;
 
         info!("Successfully wrote configuration to disk");
@@ -1025,21 +1212,28 @@ impl Disk {
 
     /// Read an entire partition into memory
     ///
+    /// Only partitions up to [`MAX_CONFIG_PARTITION_SIZE`] are read, in chunks of
+    /// [`PARTITION_READ_CHUNK`] bytes; larger ones are refused before anything is allocated,
+    /// and the buffer only grows by what was actually read.
+    ///
     /// # Arguments
     /// * `uuid_str` - The UUID of the partition to read
     ///
     /// # Returns
     /// * A tuple containing (start_offset, partition_size, partition_data) if the partition is found
     fn read_partition_to_memory(&mut self, uuid_str: &str) -> Result<(u64, u64, Vec<u8>)> {
-        use std::io::{Read, Seek, SeekFrom};
+        use std::io::{Seek, SeekFrom};
         use tracing::{error, info};
 
         // Parse the provided UUID string
         let target_uuid = Uuid::parse_str(uuid_str)
             .context(format!("Failed to parse UUID string: {}", uuid_str))?;
 
-        // Create a GPT configuration with the default logical block size (usually 512 bytes)
-        let cfg = GptConfig::new().writable(false);
+        // Create a GPT configuration matching the disk's logical sector size
+        let geometry = self.geometry();
+        let cfg = GptConfig::new()
+            .writable(false)
+            .logical_block_size(geometry.gpt_block_size()?);
 
         // Clone the file handle
         let file_for_gpt = self.get_cloned_file_handle()?;
@@ -1052,7 +1246,8 @@ impl Disk {
             Ok(disk) => disk,
             Err(e) => {
                 let error_msg = format!("Failed to parse GPT partition table: {}", e);
-                if let Some(fixed_disk) = PlatformDiskAccess::handle_gpt_error(self, e.into())? {
+                let platform = self.platform.clone();
+                if let Some(fixed_disk) = platform.handle_gpt_error(self, e.into())? {
                     fixed_disk
                 } else {
                     return Err(anyhow!(error_msg));
@@ -1060,6 +1255,17 @@ impl Disk {
             }
         };
 
+        // The platform may have read the table in sectors of the other size, LBAs are in those
+        let block_size: u64 = (*disk.logical_block_size()).into();
+        if block_size != geometry.logical {
+            warn!(
+                "Partition table of {} uses {}-byte sectors, the disk reports {}",
+                self.original_path,
+                block_size,
+                geometry.format()
+            );
+        }
+
         // Get partitions from the disk
         let partitions = disk.partitions();
 
@@ -1071,14 +1277,13 @@ impl Disk {
 
                 // Get start sector and length for the partition
                 let start_sector = part.first_lba;
-                const SECTOR_SIZE: u64 = 512;
-                let start_offset = start_sector * SECTOR_SIZE;
+                let start_offset = start_sector * block_size;
 
-                // Calculate partition size for better boundary checking
+                // Calculate partition size, the last LBA belongs to the partition
                 let partition_size = part
                     .last_lba
                     .checked_sub(part.first_lba)
-                    .map(|sectors| sectors * SECTOR_SIZE)
+                    .map(|sectors| (sectors + 1) * block_size)
                     .unwrap_or(0);
 
                 info!(
@@ -1086,6 +1291,7 @@ impl Disk {
                     partition_size,
                     partition_size / (1024 * 1024)
                 );
+                check_in_memory_size(uuid_str, partition_size)?;
 
                 // Create a new file handle
                 let mut partition_file = self.get_cloned_file_handle()?;
@@ -1093,9 +1299,9 @@ impl Disk {
                 // Seek to the start of the partition
                 partition_file.seek(SeekFrom::Start(start_offset))?;
 
-                // Read the entire partition into memory
-                let mut partition_data = vec![0u8; partition_size as usize];
-                let bytes_read = partition_file.read(&mut partition_data)?;
+                // Read the partition chunk by chunk, each a whole number of sectors
+                let partition_data = read_chunked(&mut partition_file, partition_size)?;
+                let bytes_read = partition_data.len();
 
                 if bytes_read < partition_size as usize {
                     error!(
@@ -1103,9 +1309,6 @@ impl Disk {
                         bytes_read, partition_size
                     );
 
-                    // Truncate the buffer to the actual size read
-                    partition_data.truncate(bytes_read);
-
                     // Update partition_size to reflect what we actually read
                     let actual_partition_size = bytes_read as u64;
                     info!(
@@ -1195,7 +1398,7 @@ impl Disk {
             error!("Failed to flush data to disk: {}", e);
 
             #[cfg(windows)]
-            if let Some(platform_error) = PlatformDiskAccess::handle_flush_error(&e) {
+            if let Some(platform_error) = self.platform.handle_flush_error(&e) {
                 return Err(platform_error);
             }
 
@@ -1326,6 +1529,28 @@ impl Disk {
         Ok(config)
     }
 
+    /// Read Golem configuration and the installed image version from a device
+    ///
+    /// The version is looked up like for in-place updates; a device without one still
+    /// returns its configuration.
+    ///
+    /// # Arguments
+    /// * `uuid_str` - The UUID of the partition containing the configuration
+    ///
+    /// # Returns
+    /// * `Result<GolemDeviceInfo>` - The Golem configuration and the detected version
+    pub fn read_device_info(&mut self, uuid_str: &str) -> Result<GolemDeviceInfo> {
+        let config = self.read_configuration(uuid_str)?;
+        let version = match self.read_installed_image() {
+            Ok(installed) => installed.version,
+            Err(e) => {
+                warn!("Failed to detect the installed image version: {}", e);
+                None
+            }
+        };
+        Ok(GolemDeviceInfo { config, version })
+    }
+
     /// Read Golem configuration from a partition using in-memory approach
     ///
     /// This implementation reads the partition contents directly rather than
@@ -1337,61 +1562,11 @@ impl Disk {
     /// # Returns
     /// * The Golem configuration if found
     fn read_configuration_in_memory(&mut self, uuid_str: &str) -> Result<GolemConfig> {
-        use std::io::Read;
-        use tracing::debug;
-
         // Use the find_partition function to get a properly initialized FAT filesystem
         let fs = self.find_partition(uuid_str)?;
 
         debug!("Using find_partition to get a properly initialized FAT filesystem");
-        let root_dir = fs.root_dir();
-
-        // Read configuration files
-        let mut toml_content = String::new();
-        let mut env_content = String::new();
-
-        // Try to read golemwz.toml
-        if let Ok(mut toml_file) = root_dir.open_file("golemwz.toml") {
-            match toml_file.read_to_string(&mut toml_content) {
-                Ok(_) => {
-                    debug!("Successfully read golemwz.toml file: {} bytes", toml_content.len());
-                }
-                Err(e) => {
-                    debug!("Error reading golemwz.toml: {}", e);
-                }
-            }
-        }
-
-        // Try to read golem.env
-        if let Ok(mut env_file) = root_dir.open_file("golem.env") {
-            match env_file.read_to_string(&mut env_content) {
-                Ok(_) => {
-                    debug!("Successfully read golem.env file: {} bytes", env_content.len());
-                }
-                Err(e) => {
-                    debug!("Error reading golem.env: {}", e);
-                }
-            }
-        }
-
-        // Use our elegant parsing methods
-        let image_config = if !toml_content.is_empty() && !env_content.is_empty() {
-            // Parse both files
-            ImageConfiguration::from_config_files(&toml_content, &env_content)?
-        } else if !env_content.is_empty() {
-            // Parse only env file
-            ImageConfiguration::from_env_content(&env_content)?
-        } else if !toml_content.is_empty() {
-            // Parse only TOML file
-            ImageConfiguration::from_toml_content(&toml_content)?
-        } else {
-            // No configuration files found, use defaults
-            debug!("No configuration files found, using defaults");
-            ImageConfiguration::default()
-        };
-
-        // Convert to GolemConfig and return
-        Ok(image_config.into())
+        read_golem_config(&fs.root_dir())
     }
 
     /// Write Golem configuration to a partition
@@ -1402,11 +1577,15 @@ impl Disk {
     /// * `network_type` - The network type (Hybrid or Central)
     /// * `subnet` - The subnet name
     /// * `wallet_address` - The GLM wallet address
+    /// * `node_name` - Optional node name
     /// * `non_interactive_install` - Whether to enable non-interactive installation
     /// * `ssh_keys` - SSH public keys for user golem
     /// * `configuration_server` - Optional configuration server URL
     /// * `metrics_server` - Optional metrics server URL
     /// * `central_net_host` - Optional central net host
+    /// * `extra_env` - Additional golem.env variables
+    /// * `extra_toml` - Additional top-level golemwz.toml entries
+    /// * `firstboot` - Optional first-boot script or cloud-init user-data to add
     ///
     /// # Returns
     /// * `Result<()>` - Ok on success, Error on failure
@@ -1417,11 +1596,15 @@ impl Disk {
         network_type: crate::models::NetworkType,
         subnet: &str,
         wallet_address: &str,
+        node_name: Option<&str>,
         non_interactive_install: bool,
         ssh_keys: &[String],
         configuration_server: Option<&str>,
         metrics_server: Option<&str>,
         central_net_host: Option<&str>,
+        extra_env: &[crate::models::ExtraSetting],
+        extra_toml: &[crate::models::ExtraSetting],
+        firstboot: Option<&crate::models::FirstBootFile>,
     ) -> Result<()> {
         // Use the in-memory approach to avoid small I/O operations
         self.write_configuration_in_memory(
@@ -1430,11 +1613,15 @@ impl Disk {
             network_type,
             subnet,
             wallet_address,
+            node_name,
             non_interactive_install,
             ssh_keys,
             configuration_server,
             metrics_server,
             central_net_host,
+            extra_env,
+            extra_toml,
+            firstboot,
         )
     }
 
@@ -1450,11 +1637,15 @@ impl Disk {
     /// * `network_type` - The network type (Hybrid or Central)
     /// * `subnet` - The subnet name
     /// * `wallet_address` - The GLM wallet address
+    /// * `node_name` - Optional node name
     /// * `non_interactive_install` - Whether to enable non-interactive installation
     /// * `ssh_keys` - SSH public keys for user golem
     /// * `configuration_server` - Optional configuration server URL
     /// * `metrics_server` - Optional metrics server URL
     /// * `central_net_host` - Optional central net host
+    /// * `extra_env` - Additional golem.env variables
+    /// * `extra_toml` - Additional top-level golemwz.toml entries
+    /// * `firstboot` - Optional first-boot script or cloud-init user-data to add
     ///
     /// # Returns
     /// * `Result<()>` - Ok on success, Error on failure
@@ -1465,14 +1656,18 @@ impl Disk {
         network_type: crate::models::NetworkType,
         subnet: &str,
         wallet_address: &str,
+        node_name: Option<&str>,
         non_interactive_install: bool,
         ssh_keys: &[String],
         configuration_server: Option<&str>,
         metrics_server: Option<&str>,
         central_net_host: Option<&str>,
+        extra_env: &[crate::models::ExtraSetting],
+        extra_toml: &[crate::models::ExtraSetting],
+        firstboot: Option<&crate::models::FirstBootFile>,
     ) -> Result<()> {
-        use std::io::{Cursor, Seek, SeekFrom, Write};
-        use tracing::{info, warn};
+        use std::io::Cursor;
+        use tracing::info;
 
         // First, read the entire partition into memory
         let (start_offset, _partition_size, mut partition_data) =
@@ -1484,35 +1679,20 @@ impl Disk {
             start_offset
         );
 
+        // Only format when the partition doesn't hold a valid FAT filesystem, so any other
+        // files on GOLEMCONF (logs, keys) survive a configuration update
+        ensure_fat_filesystem(&mut partition_data)?;
+
         // Create a cursor that provides Read+Write+Seek for the FAT filesystem
         // The cursor operates directly on our partition data
-        let mut cursor = Cursor::new(&mut partition_data[..]);
-
-        // Format the partition if needed
-        let format_result = if true {
-            // Always format to ensure clean state
-            info!("Formatting in-memory partition with volume label GOLEMCONF");
-            fatfs::format_volume(
-                &mut cursor,
-                fatfs::FormatVolumeOptions::new().volume_label(*b"GOLEMCONF  "), // 11 bytes padded with spaces
-            )
-        } else {
-            Ok(())
-        };
-
-        if let Err(e) = format_result {
-            warn!("Format error (non-fatal): {}", e);
-        }
-
-        // Reset cursor position to beginning of data
-        cursor.seek(SeekFrom::Start(0))?;
+        let cursor = Cursor::new(&mut partition_data[..]);
 
         // Create ImageConfiguration from parameters and generate content using elegant methods
         let image_config = ImageConfiguration {
             accepted_terms: true,
             glm_account: wallet_address.to_string(),
             glm_per_hour: "0.25".to_string(),
-            glm_node_name: None,
+            glm_node_name: node_name.map(|s| s.to_string()),
             non_interactive_install,
             ssh_keys: ssh_keys.to_vec(),
             configuration_server: configuration_server.map(|s| s.to_string()),
@@ -1523,56 +1703,426 @@ impl Disk {
             metrics_server: metrics_server.map(|s| s.to_string()),
             metrics_job_name: None,
             metrics_group: None,
+            extra_env: extra_env.to_vec(),
+            extra_toml: extra_toml.to_vec(),
+            firstboot: firstboot.cloned(),
             server_toml_content: None,
+            schema: ConfigSchema::default(),
         };
 
-        // Generate content using our elegant methods
-        let (toml_content, mut env_content) = image_config.generate_config_files();
-        
-        // Add additional legacy environment variables for backward compatibility
-        if !env_content.contains("YAGNA_METRICS_URL") {
-            env_content.push_str("YAGNA_METRICS_URL=https://metrics.golem.network:9092/\n");
-        }
-        env_content.push_str("YAGNA_METRICS_JOB_NAME=community.1\n");
-        env_content.push_str("YAGNA_METRICS_GROUP=\n");
-
         info!("Subnet value being written: '{}'", subnet);
 
         // Create a block to ensure root_dir and fs are dropped before we attempt to write partition data
-        {
+        let expected_files = {
             // Create a FAT filesystem on the in-memory data
             let fs = fatfs::FileSystem::new(cursor, fatfs::FsOptions::new())?;
 
             // Get the root directory
             let root_dir = fs.root_dir();
 
+            // Merge managed keys into the existing files so custom settings survive the edit
+            let existing_toml = read_config_file(&root_dir, "golemwz.toml");
+            let existing_env = read_config_file(&root_dir, "golem.env");
+            let (toml_content, env_content) =
+                image_config.merge_config_files(existing_toml.as_deref(), existing_env.as_deref());
+
             // Write golemwz.toml as a complete file
             info!("Writing golemwz.toml file ({} bytes)", toml_content.len());
-            let mut toml_file = root_dir.create_file("golemwz.toml")?;
-            toml_file.write_all(toml_content.as_bytes())?;
-            toml_file.flush()?;
-            drop(toml_file); // Close the file to ensure it's flushed
+            write_config_file(&root_dir, "golemwz.toml", &toml_content)?;
 
             // Write golem.env as a complete file
             info!("Writing golem.env file ({} bytes)", env_content.len());
-            let mut env_file = root_dir.create_file("golem.env")?;
-            env_file.write_all(env_content.as_bytes())?;
-            env_file.flush()?;
-            drop(env_file); // Close the file to ensure it's flushed
+            write_config_file(&root_dir, "golem.env", &env_content)?;
+
+            let mut files = vec![
+                ("golemwz.toml".to_string(), toml_content.into_bytes()),
+                ("golem.env".to_string(), env_content.into_bytes()),
+            ];
+            if let Some(firstboot) = &image_config.firstboot {
+                files.push(write_firstboot_file(&root_dir, firstboot)?);
+            }
 
             // root_dir and fs will be dropped automatically at the end of this block
             // which will flush all changes to our cursor_data
-        }
+            files
+        };
 
         // Now we need to write the modified partition data back to disk
         info!("Writing modified partition data back to disk");
         self.write_partition_to_disk(start_offset, &partition_data)?;
 
+        // Check what actually landed on the device
+        let (_, _, mut written_data) = self.read_partition_to_memory(uuid_str)?;
+        verify_config_partition(
+            &mut written_data,
+            &ConfigPartitionContents::Configuration(Box::new(image_config)),
+            &expected_files,
+        )?;
+
         info!("Successfully wrote configuration to partition and saved to disk");
         Ok(())
     }
 }
 
+/// Refuse a device that the device rules of the settings hide
+///
+/// The details are looked up again instead of being taken from a device list, since the
+/// path may belong to a different device by now.
+async fn check_device_rules(path: &str) -> Result<()> {
+    use crate::utils::device_rules::{self, RuleTarget};
+    use crate::utils::disks::{LIST_DEVICES_TIMEOUT, ProbeOutcome, probe_with_timeout};
+
+    let rules = device_rules::active();
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    let device_path = path.to_string();
+    let outcome = probe_with_timeout(
+        &format!("device-rules:{}", path),
+        LIST_DEVICES_TIMEOUT,
+        &crate::models::CancelToken::new(),
+        move || {
+            let identity = identity::query_device_identity(&device_path);
+            let drive = rs_drivelist::drive_list().ok().and_then(|drives| {
+                drives
+                    .into_iter()
+                    .find(|drive| drive.device.eq_ignore_ascii_case(&device_path))
+            });
+            // A device missing from the list only passes rules it doesn't need details for
+            let target = RuleTarget {
+                path: &device_path,
+                serial: identity.serial.as_deref().or(identity.wwn.as_deref()),
+                model: drive
+                    .as_ref()
+                    .map_or("", |drive| drive.description.as_str()),
+                vendor_id: identity.vendor_id.as_deref(),
+                size_bytes: drive.as_ref().map_or(0, |drive| drive.size),
+                is_removable: drive.as_ref().is_some_and(|drive| drive.isRemovable),
+            };
+            device_rules::check(&rules, &target)
+        },
+    )
+    .await;
+
+    match outcome {
+        ProbeOutcome::Completed(Ok(())) => Ok(()),
+        ProbeOutcome::Completed(Err(reason)) => {
+            warn!("Refusing to open {}", path);
+            Err(anyhow!("{}", reason))
+        }
+        _ => Err(anyhow!("Failed to check {} against the device rules", path)),
+    }
+}
+
+/// Ensure an in-memory partition image contains a usable FAT filesystem
+///
+/// The existing filesystem is left untouched when it can be opened; the partition is
+/// only formatted (with the GOLEMCONF volume label) when no valid FAT filesystem is found.
+///
+/// # Returns
+/// * `true` if the partition had to be formatted
+fn ensure_fat_filesystem(partition_data: &mut [u8]) -> Result<bool> {
+    use std::io::Cursor;
+
+    let probe = fatfs::FileSystem::new(Cursor::new(&mut *partition_data), fatfs::FsOptions::new())
+        .map(|_| ());
+
+    match probe {
+        Ok(()) => {
+            debug!("Existing FAT filesystem found, updating files in place");
+            Ok(false)
+        }
+        Err(e) => {
+            warn!(
+                "No valid FAT filesystem on configuration partition ({}), formatting",
+                e
+            );
+            fatfs::format_volume(
+                Cursor::new(&mut *partition_data),
+                fatfs::FormatVolumeOptions::new().volume_label(*b"GOLEMCONF  "), // 11 bytes padded with spaces
+            )
+            .context("Failed to format configuration partition")?;
+            Ok(true)
+        }
+    }
+}
+
+/// Parse golemwz.toml and golem.env from the root of a configuration partition
+///
+/// Missing files fall back to defaults so that a freshly formatted partition still
+/// yields a usable configuration.
+fn read_golem_config<T: fatfs::ReadWriteSeek>(
+    root_dir: &fatfs::Dir<'_, T>,
+) -> Result<GolemConfig> {
+    let toml_content = read_config_file(root_dir, "golemwz.toml").unwrap_or_default();
+    let env_content = read_config_file(root_dir, "golem.env").unwrap_or_default();
+    debug!(
+        "Read configuration files: golemwz.toml {} bytes, golem.env {} bytes",
+        toml_content.len(),
+        env_content.len()
+    );
+
+    // Use our elegant parsing methods
+    let image_config = if !toml_content.is_empty() && !env_content.is_empty() {
+        // Parse both files
+        ImageConfiguration::from_config_files(&toml_content, &env_content)?
+    } else if !env_content.is_empty() {
+        // Parse only env file
+        ImageConfiguration::from_env_content(&env_content)?
+    } else if !toml_content.is_empty() {
+        // Parse only TOML file
+        ImageConfiguration::from_toml_content(&toml_content)?
+    } else {
+        // No configuration files found, use defaults
+        debug!("No configuration files found, using defaults");
+        ImageConfiguration::default()
+    };
+
+    // Convert to GolemConfig and return
+    Ok(image_config.into())
+}
+
+/// Check a configuration partition read back from the disk against what was written
+///
+/// Every written file must come back byte for byte, and a merged configuration must parse
+/// to the intended values, which catches merges that left a stale key in effect.
+fn verify_config_partition(
+    partition_data: &mut [u8],
+    contents: &ConfigPartitionContents,
+    expected_files: &[(String, Vec<u8>)],
+) -> Result<()> {
+    let fs = fatfs::FileSystem::new(
+        std::io::Cursor::new(partition_data),
+        fatfs::FsOptions::new(),
+    )
+    .context("Configuration partition is unreadable after writing")?;
+    let root_dir = fs.root_dir();
+
+    for (path, expected) in expected_files {
+        let mut actual = Vec::new();
+        root_dir
+            .open_file(path)
+            .and_then(|mut file| file.read_to_end(&mut actual))
+            .with_context(|| format!("{} is missing after writing the configuration", path))?;
+        if actual != *expected {
+            error!(
+                "{} read back from the device differs from what was written",
+                path
+            );
+            return Err(anyhow!(
+                "{} on the device does not match what was written ({} bytes written, {} read back)",
+                path,
+                expected.len(),
+                actual.len()
+            ));
+        }
+    }
+
+    match contents {
+        ConfigPartitionContents::Configuration(config) if config.server_toml_content.is_none() => {
+            let toml_content = read_config_file(&root_dir, "golemwz.toml").unwrap_or_default();
+            let env_content = read_config_file(&root_dir, "golem.env").unwrap_or_default();
+            let written = ImageConfiguration::from_config_files_with_schema(
+                &toml_content,
+                &env_content,
+                &config.schema,
+            )
+            .context("Configuration written to the device can't be parsed")?;
+
+            let mismatched = config.mismatched_fields(&written);
+            if !mismatched.is_empty() {
+                error!(
+                    "Configuration read back from the device differs: {:?}",
+                    mismatched
+                );
+                return Err(anyhow!(
+                    "Configuration on the device does not match the intended settings: {}",
+                    mismatched.join(", ")
+                ));
+            }
+        }
+        // Server configuration and restored files are written verbatim, checked above
+        _ => {}
+    }
+
+    info!(
+        "Verified {} configuration file(s) after writing",
+        expected_files.len()
+    );
+    Ok(())
+}
+
+/// Read a file from the root of a FAT filesystem, returning None if it is missing or unreadable
+fn read_config_file<T: fatfs::ReadWriteSeek>(
+    root_dir: &fatfs::Dir<'_, T>,
+    name: &str,
+) -> Option<String> {
+    let mut file = root_dir.open_file(name).ok()?;
+    let mut content = String::new();
+    match file.read_to_string(&mut content) {
+        Ok(_) => Some(content),
+        Err(e) => {
+            warn!("Failed to read existing {}: {}", name, e);
+            None
+        }
+    }
+}
+
+/// Create or overwrite a single file in the root of a FAT filesystem
+///
+/// `create_file` opens existing files without truncating them, so the file is truncated
+/// explicitly before writing to avoid leftover bytes from a longer previous version.
+fn write_config_file<T: fatfs::ReadWriteSeek>(
+    root_dir: &fatfs::Dir<'_, T>,
+    name: &str,
+    content: &str,
+) -> Result<()> {
+    let mut file = root_dir
+        .create_file(name)
+        .with_context(|| format!("Failed to create {}", name))?;
+    file.truncate()
+        .with_context(|| format!("Failed to truncate {}", name))?;
+    file.write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write {}", name))?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Write a first-boot file under the name the image looks for
+///
+/// # Returns
+/// * The file's name and contents, to check against what the device reads back
+fn write_firstboot_file<T: fatfs::ReadWriteSeek>(
+    root_dir: &fatfs::Dir<'_, T>,
+    firstboot: &crate::models::FirstBootFile,
+) -> Result<(String, Vec<u8>)> {
+    let name = firstboot.kind.file_name();
+    info!(
+        "Writing {} from {} ({} bytes)",
+        name,
+        firstboot.source_name,
+        firstboot.content.len()
+    );
+    write_config_file(root_dir, name, &firstboot.content)?;
+    Ok((name.to_string(), firstboot.content.clone().into_bytes()))
+}
+
+/// Largest configuration partition we are willing to read into memory
+///
+/// Configuration partitions take a few MB; anything larger is the data or root partition
+/// of a disk whose partition was misidentified, and reading it would exhaust the memory.
+const MAX_CONFIG_PARTITION_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Probe a disk for a Golem configuration without locking, unmounting or writing to it
+///
+/// The disk is opened with a plain read-only handle, so this is safe to run while the
+/// device is mounted or in use, but it may fail when the process lacks raw disk access.
+///
+/// # Returns
+/// * `Ok(Some(config))` - The disk has a readable Golem configuration partition
+/// * `Ok(None)` - The disk has no Golem configuration partition
+/// * `Err` - The disk could not be opened or read
+pub fn probe_golem_config(path: &str) -> Result<Option<GolemConfig>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {} for probing", path))?;
+    let geometry = geometry::query(path);
+    let mut reader = AlignedDiskIo::new(file, geometry.io_alignment() as u32)?;
+
+    let layout = layout::read_layout(&mut reader, geometry.logical)?;
+    let Some(partition) = layout.golem_config_partition() else {
+        return Ok(None);
+    };
+
+    let partition_data = read_partition_data(&mut reader, partition)
+        .with_context(|| format!("Failed to probe {}", path))?;
+
+    let fs = fatfs::FileSystem::new(
+        std::io::Cursor::new(partition_data),
+        fatfs::FsOptions::new(),
+    )
+    .context("Configuration partition does not contain a FAT filesystem")?;
+
+    read_golem_config(&fs.root_dir()).map(Some)
+}
+
+/// Bytes read from the device at a time when a partition is read into memory
+const PARTITION_READ_CHUNK: usize = 1024 * 1024;
+
+/// Refuse to read the partition `uuid_str` of `size` bytes into memory if it is too large
+fn check_in_memory_size(uuid_str: &str, size: u64) -> Result<()> {
+    if size > MAX_CONFIG_PARTITION_SIZE {
+        return Err(anyhow!(
+            "Partition {} is {} MB, too large to be a configuration partition (at most {} MB); \
+             it is not read into memory",
+            uuid_str,
+            size / (1024 * 1024),
+            MAX_CONFIG_PARTITION_SIZE / (1024 * 1024)
+        ));
+    }
+    Ok(())
+}
+
+/// Read a configuration partition into memory, refusing implausibly large ones
+fn read_partition_data<R: Read + Seek>(
+    reader: &mut R,
+    partition: &layout::PartitionInfo,
+) -> Result<Vec<u8>> {
+    if partition.size > MAX_CONFIG_PARTITION_SIZE {
+        return Err(anyhow!(
+            "Configuration partition is unexpectedly large ({} bytes)",
+            partition.size
+        ));
+    }
+
+    reader.seek(SeekFrom::Start(partition.start_offset))?;
+    let partition_data =
+        read_chunked(reader, partition.size).context("Failed to read configuration partition")?;
+    if (partition_data.len() as u64) < partition.size {
+        return Err(anyhow!(
+            "Failed to read configuration partition, the device ends after {} of {} bytes",
+            partition_data.len(),
+            partition.size
+        ));
+    }
+    Ok(partition_data)
+}
+
+/// Read up to `size` bytes from `reader`, growing the buffer a chunk at a time
+///
+/// A partition table can claim more than the device holds, so memory is only taken for
+/// what is actually read.
+fn read_chunked(reader: &mut impl Read, size: u64) -> std::io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    while (data.len() as u64) < size {
+        let filled = data.len();
+        let wanted = PARTITION_READ_CHUNK.min((size - filled as u64) as usize);
+        data.resize(filled + wanted, 0);
+        let read = read_full(reader, &mut data[filled..])?;
+        data.truncate(filled + read);
+        if read < wanted {
+            break;
+        }
+    }
+    Ok(data)
+}
+
+/// Fill `buffer` from `reader`, stopping early only at the end of the stream
+///
+/// Decoders return short reads, but every chunk written to the disk except the last must
+/// be a full buffer to stay sector aligned.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
 /// Get disk size using Windows-specific IOCTL (for when seek to end fails)
 #[cfg(windows)]
 fn get_disk_size_windows(disk_file: &mut File) -> Result<u64> {
@@ -1660,409 +2210,282 @@ fn get_disk_size_windows(disk_file: &mut File) -> Result<u64> {
     Ok(disk_file.seek(SeekFrom::End(0))?)
 }
 
-/// Fix GPT backup header location when device is larger than image
+/// Verify both GPT headers of the disk and repair what is wrong after a flash
 ///
-/// When a smaller image is written to a larger device, the backup GPT header
-/// remains at the image's end position instead of the device's end position.
-/// This causes CRC validation failures. This function relocates the backup
-/// header to the correct position at the end of the device.
-///
-/// All reads and writes are sector-aligned for Windows compatibility.
-///
-/// # Arguments
-/// * `disk_file` - The disk file handle
-///
-/// # Returns
-/// * `Result<()>` - Ok on success, Error on failure
-fn fix_gpt_backup_header(disk_file: &mut File) -> Result<()> {
-    // GPT uses 512-byte logical sectors, but Windows I/O requires 4KB physical alignment
-    const LOGICAL_SECTOR_SIZE: u64 = 512;
-    const GPT_SIGNATURE: [u8; 8] = [0x45, 0x46, 0x49, 0x20, 0x50, 0x41, 0x52, 0x54]; // "EFI PART"
-
-    // Physical sector size for I/O alignment (Windows needs 4KB, Linux can use 512B)
-    #[cfg(windows)]
-    const PHYSICAL_SECTOR_SIZE: u64 = 4096;
-    #[cfg(not(windows))]
-    const PHYSICAL_SECTOR_SIZE: u64 = 512;
-
-    // Get disk size (must be aligned to logical sector boundary for GPT calculations)
-    info!("Step 1: Getting disk size");
+/// See [`gpt::repair`]; all reads and writes go through [`AlignedDiskIo`] for Windows
+/// compatibility.
+fn repair_gpt(
+    disk_file: &mut File,
+    geometry: &geometry::SectorGeometry,
+) -> Result<gpt::repair::RepairReport> {
     let disk_size = get_disk_size_windows(disk_file)?;
-    let disk_sectors = disk_size / LOGICAL_SECTOR_SIZE;
-
     info!(
-        "Disk size: {} bytes ({} logical sectors), physical sector size: {} bytes",
-        disk_size, disk_sectors, PHYSICAL_SECTOR_SIZE
+        "Disk size: {} bytes ({} logical sectors), I/O alignment: {} bytes",
+        disk_size,
+        disk_size / geometry.logical,
+        geometry.io_alignment()
     );
+    let mut disk = AlignedDiskIo::new(disk_file, geometry.io_alignment() as u32)?;
+    gpt::repair::repair(&mut disk, geometry.logical, disk_size / geometry.logical)
+}
 
-    // Read primary GPT header (physically-aligned buffer for Windows compatibility)
-    info!("Step 2: Reading primary GPT header");
-
-    // For Windows direct I/O, read from sector 0 to get both MBR and GPT header
-    let read_start = 0u64;
-    let read_size = PHYSICAL_SECTOR_SIZE * 2; // Read 8KB to ensure we get GPT header at LBA 1
-
-    info!(
-        "Reading aligned data from offset {} with size {} bytes",
-        read_start, read_size
-    );
-    disk_file
-        .seek(SeekFrom::Start(read_start))
-        .with_context(|| format!("Failed to seek to aligned position {}", read_start))?;
-
-    let mut aligned_buffer = vec![0u8; read_size as usize];
-    disk_file.read_exact(&mut aligned_buffer).with_context(|| {
-        format!(
-            "Failed to read {} bytes for aligned GPT data",
-            aligned_buffer.len()
-        )
-    })?;
+/// Replace the GUIDs `mode` names in the primary GPT of the disk
+///
+/// See [`gpt::randomize`]; the configuration partition keeps its GUID.
+fn randomize_gpt_guids(
+    disk_file: &mut File,
+    geometry: &geometry::SectorGeometry,
+    mode: GuidRandomization,
+) -> Result<Vec<(Uuid, Uuid)>> {
+    let disk_sectors = get_disk_size_windows(disk_file)? / geometry.logical;
+    let keep = Uuid::parse_str(layout::GOLEM_CONFIG_PARTITION_GUID)?;
+    let mut disk = AlignedDiskIo::new(disk_file, geometry.io_alignment() as u32)?;
+    gpt::randomize::randomize(&mut disk, geometry.logical, disk_sectors, mode, keep)
+}
 
-    // Extract GPT header from LBA 1 (starts at offset 512 in our buffer)
-    let gpt_header_offset_in_buffer = LOGICAL_SECTOR_SIZE as usize;
-    if aligned_buffer.len() < gpt_header_offset_in_buffer + LOGICAL_SECTOR_SIZE as usize {
-        return Err(anyhow!("Buffer too small for GPT header"));
-    }
-    let mut header_buffer = aligned_buffer
-        [gpt_header_offset_in_buffer..gpt_header_offset_in_buffer + LOGICAL_SECTOR_SIZE as usize]
-        .to_vec();
-    // Pad to physical sector size for later writing
-    header_buffer.resize(PHYSICAL_SECTOR_SIZE as usize, 0);
-
-    // Verify GPT signature (first 512 bytes contain the GPT header)
-    if header_buffer[0..8] != GPT_SIGNATURE {
-        info!("No GPT signature found - skipping GPT backup header fix");
-        return Ok(());
-    }
+/// Lists available disk devices in the system
+///
+/// # Returns
+/// * `Result<Vec<DiskDevice>>` - A list of available disk devices
+#[allow(dead_code)]
+pub async fn list_available_disks() -> Result<Vec<DiskDevice>> {
+    access::backend().list_disks().await
+}
 
-    // Extract backup_lba field (bytes 32-39)
-    let current_backup_lba = u64::from_le_bytes([
-        header_buffer[32],
-        header_buffer[33],
-        header_buffer[34],
-        header_buffer[35],
-        header_buffer[36],
-        header_buffer[37],
-        header_buffer[38],
-        header_buffer[39],
-    ]);
-
-    // Calculate expected backup LBA (last sector of disk)
-    let expected_backup_lba = disk_sectors - 1;
+#[cfg(test)]
+mod tests {
+    use super::test_support::*;
+    use super::*;
+    use crate::disk::layout::GOLEM_CONFIG_PARTITION_GUID;
+    use crate::models::{NetworkType, PaymentNetwork};
 
-    info!(
-        "Current backup LBA: {}, Expected backup LBA: {}",
-        current_backup_lba, expected_backup_lba
-    );
+    const SHIPPED_ENV: &str = "YA_NET_TYPE=central\nSUBNET=public\nCUSTOM_SETTING=kept\n";
+    const SHIPPED_TOML: &str = "accepted_terms = false\ncustom_key = \"kept\"\n";
 
-    // If backup header is already at the correct location, no fix needed
-    if current_backup_lba == expected_backup_lba {
-        info!("GPT backup header is already at correct location");
-        return Ok(());
+    fn configuration() -> ConfigPartitionContents {
+        ConfigPartitionContents::Configuration(Box::new(ImageConfiguration {
+            accepted_terms: true,
+            glm_account: "0x1234567890abcdef1234567890abcdef12345678".to_string(),
+            payment_network: PaymentNetwork::Mainnet,
+            network_type: NetworkType::Hybrid,
+            subnet: "devnet-beta".to_string(),
+            ..Default::default()
+        }))
     }
 
-    info!(
-        "Fixing GPT backup header location from LBA {} to LBA {}",
-        current_backup_lba, expected_backup_lba
-    );
+    /// Byte range of the configuration partition of a [`golem_disk`]
+    fn config_partition() -> std::ops::Range<usize> {
+        CONFIG_PARTITION_OFFSET as usize..(CONFIG_PARTITION_OFFSET + CONFIG_PARTITION_SIZE) as usize
+    }
 
-    // Read the current backup header from its current location (physically-aligned)
-    info!("Step 3: Reading current backup header");
-    let backup_header_logical_offset = current_backup_lba * LOGICAL_SECTOR_SIZE;
+    /// golem.env and golemwz.toml on the configuration partition of a [`golem_disk`]
+    fn config_files(disk: &mut [u8]) -> (Option<String>, Option<String>) {
+        let partition = &mut disk[config_partition()];
+        let mut files = read_files(partition, &["golem.env", "golemwz.toml"]).into_iter();
+        (files.next().unwrap(), files.next().unwrap())
+    }
 
-    // Align backup header reading to physical sector boundaries
-    let backup_aligned_start =
-        (backup_header_logical_offset / PHYSICAL_SECTOR_SIZE) * PHYSICAL_SECTOR_SIZE;
-    let backup_offset_within_read = (backup_header_logical_offset - backup_aligned_start) as usize;
+    fn write_configuration(disk: &[u8], sector_size: u64) -> Result<Vec<u8>> {
+        let image = DiskImage::new(disk);
+        let geometry = geometry::SectorGeometry::new(sector_size, sector_size);
+        Disk::write_configuration_to_partition(&mut image.file(), &configuration(), &geometry)?;
+        Ok(image.contents())
+    }
 
-    info!(
-        "Reading backup header: logical offset={}, aligned offset={}, offset within read={}",
-        backup_header_logical_offset, backup_aligned_start, backup_offset_within_read
-    );
+    #[test]
+    fn test_write_configuration_refuses_corrupt_gpt() {
+        use super::gpt::header::GptError;
 
-    disk_file
-        .seek(SeekFrom::Start(backup_aligned_start))
-        .with_context(|| {
-            format!(
-                "Failed to seek to aligned backup position {}",
-                backup_aligned_start
-            )
-        })?;
+        let disk = golem_disk(512, &[("golem.env", SHIPPED_ENV)]);
+        let gpt_error = |disk: &[u8]| {
+            write_configuration(disk, 512)
+                .unwrap_err()
+                .downcast::<GptError>()
+                .unwrap()
+        };
 
-    let mut backup_aligned_buffer = vec![0u8; PHYSICAL_SECTOR_SIZE as usize];
-    disk_file
-        .read_exact(&mut backup_aligned_buffer)
-        .with_context(|| {
-            format!(
-                "Failed to read {} bytes for aligned backup data",
-                backup_aligned_buffer.len()
-            )
-        })?;
+        // An entry count that would size a buffer of hundreds of GB
+        let mut huge = disk.clone();
+        huge[512 + 80..512 + 84].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(gpt_error(&huge), GptError::EntryCount(u32::MAX));
+
+        let mut beyond = disk.clone();
+        beyond[512 + 72..512 + 80].copy_from_slice(&(GOLEM_DISK_SIZE / 512).to_le_bytes());
+        assert!(matches!(
+            gpt_error(&beyond),
+            GptError::EntriesOutOfRange { .. }
+        ));
+
+        // The first entry, the configuration partition, runs off the end of the disk
+        let mut partition = disk.clone();
+        partition[2 * 512 + 40..2 * 512 + 48].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            gpt_error(&partition),
+            GptError::PartitionOutOfRange { index: 0, .. }
+        ));
+    }
 
-    // Extract backup header from the aligned buffer
-    if backup_aligned_buffer.len() < backup_offset_within_read + LOGICAL_SECTOR_SIZE as usize {
-        return Err(anyhow!("Aligned buffer too small for backup header"));
+    #[test]
+    fn test_write_configuration_merges_into_shipped_files() {
+        let disk = golem_disk(
+            512,
+            &[("golem.env", SHIPPED_ENV), ("golemwz.toml", SHIPPED_TOML)],
+        );
+        let mut written = write_configuration(&disk, 512).unwrap();
+
+        let (env, toml) = config_files(&mut written);
+        let (env, toml) = (env.unwrap(), toml.unwrap());
+        assert!(env.contains("YA_NET_TYPE=hybrid"));
+        assert!(env.contains("SUBNET=devnet-beta"));
+        assert!(env.contains("CUSTOM_SETTING=kept"));
+        assert!(toml.contains("accepted_terms = true"));
+        assert!(toml.contains("custom_key = \"kept\""));
+
+        // Only the configuration partition changed
+        let config = config_partition();
+        assert_eq!(written[..config.start], disk[..config.start]);
+        assert_eq!(written[config.end..], disk[config.end..]);
     }
-    let mut backup_buffer = backup_aligned_buffer
-        [backup_offset_within_read..backup_offset_within_read + LOGICAL_SECTOR_SIZE as usize]
-        .to_vec();
-    // Pad to physical sector size for later writing
-    backup_buffer.resize(PHYSICAL_SECTOR_SIZE as usize, 0);
-
-    // Update the current_lba field in the backup header to point to new location
-    let new_backup_lba_bytes = expected_backup_lba.to_le_bytes();
-    backup_buffer[24..32].copy_from_slice(&new_backup_lba_bytes);
-
-    // Calculate partition entries LBA for backup header (typically backup_lba - 32)
-    let partition_entries_sectors = 32u64; // Standard GPT partition entries use 32 sectors
-    let backup_partition_entries_lba =
-        expected_backup_lba.saturating_sub(partition_entries_sectors);
-    let backup_partition_entries_bytes = backup_partition_entries_lba.to_le_bytes();
-    backup_buffer[72..80].copy_from_slice(&backup_partition_entries_bytes);
-
-    // Zero out CRC32 field before recalculating
-    backup_buffer[16] = 0;
-    backup_buffer[17] = 0;
-    backup_buffer[18] = 0;
-    backup_buffer[19] = 0;
-
-    // Calculate new CRC32 for backup header (standard GPT header size is 92 bytes)
-    let mut hasher = Hasher::new();
-    hasher.update(&backup_buffer[0..92]);
-    let backup_crc32 = hasher.finalize();
-    let backup_crc32_bytes = backup_crc32.to_le_bytes();
-    backup_buffer[16..20].copy_from_slice(&backup_crc32_bytes);
-
-    // Write backup header to new location (physically-aligned write)
-    info!("Step 4: Writing backup header to new location");
-    let new_backup_logical_offset = expected_backup_lba * LOGICAL_SECTOR_SIZE;
-
-    // Align new backup header writing to physical sector boundaries
-    let new_backup_aligned_start =
-        (new_backup_logical_offset / PHYSICAL_SECTOR_SIZE) * PHYSICAL_SECTOR_SIZE;
-    let new_backup_offset_within_write =
-        (new_backup_logical_offset - new_backup_aligned_start) as usize;
 
-    info!(
-        "Writing backup header: logical offset={}, aligned offset={}, offset within write={}",
-        new_backup_logical_offset, new_backup_aligned_start, new_backup_offset_within_write
-    );
+    #[test]
+    fn test_write_configuration_on_4k_native_disk() {
+        let disk = golem_disk(4096, &[("golem.env", SHIPPED_ENV)]);
+        let mut written = write_configuration(&disk, 4096).unwrap();
 
-    // Read the existing data at the target location to preserve surrounding data
-    disk_file
-        .seek(SeekFrom::Start(new_backup_aligned_start))
-        .with_context(|| {
-            format!(
-                "Failed to seek to new backup aligned position {}",
-                new_backup_aligned_start
-            )
-        })?;
+        let (env, toml) = config_files(&mut written);
+        assert!(env.unwrap().contains("CUSTOM_SETTING=kept"));
+        assert!(toml.unwrap().contains("accepted_terms = true"));
+    }
 
-    let mut new_backup_aligned_buffer = vec![0u8; PHYSICAL_SECTOR_SIZE as usize];
-    // Try to read existing data, but don't fail if we can't (might be at end of disk)
-    let _ = disk_file.read_exact(&mut new_backup_aligned_buffer);
+    #[test]
+    fn test_write_configuration_formats_blank_partition() {
+        let mut disk = golem_disk(512, &[]);
+        let config = config_partition();
+        disk[config].fill(0);
+        let mut written = write_configuration(&disk, 512).unwrap();
 
-    // Copy our backup header into the aligned buffer
-    let backup_end = new_backup_offset_within_write + LOGICAL_SECTOR_SIZE as usize;
-    if new_backup_aligned_buffer.len() >= backup_end {
-        new_backup_aligned_buffer[new_backup_offset_within_write..backup_end]
-            .copy_from_slice(&backup_buffer[0..LOGICAL_SECTOR_SIZE as usize]);
+        let (env, toml) = config_files(&mut written);
+        assert!(env.unwrap().contains("YA_NET_TYPE=hybrid"));
+        assert!(toml.is_some());
     }
 
-    // Write the aligned buffer
-    disk_file
-        .seek(SeekFrom::Start(new_backup_aligned_start))
-        .with_context(|| {
-            format!(
-                "Failed to seek to new backup location at offset {}",
-                new_backup_aligned_start
+    #[test]
+    fn test_write_configuration_without_config_partition() {
+        let disk = GptDiskBuilder::new(GOLEM_DISK_SIZE, 512)
+            .partition(
+                LINUX_FILESYSTEM_TYPE_GUID,
+                ROOT_PARTITION_GUID,
+                "root",
+                ROOT_PARTITION_OFFSET,
+                ROOT_PARTITION_SIZE,
+                Vec::new(),
             )
-        })?;
-    info!(
-        "Writing {} bytes for aligned backup header",
-        new_backup_aligned_buffer.len()
-    );
-    disk_file
-        .write_all(&new_backup_aligned_buffer)
-        .with_context(|| {
-            format!(
-                "Failed to write {} bytes for backup header",
-                new_backup_aligned_buffer.len()
-            )
-        })?;
-
-    // Update primary header's backup_lba field
-    info!("Step 5: Updating primary header");
-    header_buffer[32..40].copy_from_slice(&new_backup_lba_bytes);
-
-    // Zero out primary header CRC32 before recalculating
-    header_buffer[16] = 0;
-    header_buffer[17] = 0;
-    header_buffer[18] = 0;
-    header_buffer[19] = 0;
-
-    // Calculate new CRC32 for primary header
-    let mut hasher = Hasher::new();
-    hasher.update(&header_buffer[0..92]);
-    let primary_crc32 = hasher.finalize();
-    let primary_crc32_bytes = primary_crc32.to_le_bytes();
-    header_buffer[16..20].copy_from_slice(&primary_crc32_bytes);
-
-    // Write updated primary header (physically-aligned write)
-    info!("Step 5b: Writing updated primary header");
-
-    // We already read the aligned buffer earlier, now copy the updated header back
-    let primary_header_offset_in_buffer = LOGICAL_SECTOR_SIZE as usize;
-    aligned_buffer[primary_header_offset_in_buffer
-        ..primary_header_offset_in_buffer + LOGICAL_SECTOR_SIZE as usize]
-        .copy_from_slice(&header_buffer[0..LOGICAL_SECTOR_SIZE as usize]);
-
-    // Write the entire aligned buffer back
-    disk_file
-        .seek(SeekFrom::Start(0))
-        .with_context(|| "Failed to seek back to beginning for primary header update")?;
-    info!(
-        "Writing {} bytes for updated primary header area",
-        aligned_buffer.len()
-    );
-    disk_file.write_all(&aligned_buffer).with_context(|| {
-        format!(
-            "Failed to write {} bytes for primary header area",
-            aligned_buffer.len()
-        )
-    })?;
-
-    // Move partition entries table for backup if needed
-    if current_backup_lba != expected_backup_lba {
-        info!("Step 6: Moving partition entries table");
-        // Read partition entries from after primary header (physically-aligned)
-        let primary_partition_entries_lba = 2u64; // Standard location
-        let primary_partition_entries_logical_offset =
-            primary_partition_entries_lba * LOGICAL_SECTOR_SIZE;
-        let partition_entries_logical_size =
-            (partition_entries_sectors * LOGICAL_SECTOR_SIZE) as usize;
-
-        // Align partition entries reading to physical sector boundaries
-        let entries_aligned_start = (primary_partition_entries_logical_offset
-            / PHYSICAL_SECTOR_SIZE)
-            * PHYSICAL_SECTOR_SIZE;
-        let entries_offset_within_read =
-            (primary_partition_entries_logical_offset - entries_aligned_start) as usize;
-        let entries_aligned_size = (partition_entries_logical_size + entries_offset_within_read)
-            .div_ceil(PHYSICAL_SECTOR_SIZE as usize)
-            * PHYSICAL_SECTOR_SIZE as usize;
+            .build();
+        let error = write_configuration(&disk, 512).unwrap_err();
+        assert!(error.to_string().contains("not found"), "{}", error);
 
-        info!(
-            "Reading partition entries: logical offset={}, logical size={}, aligned offset={}, aligned size={}",
-            primary_partition_entries_logical_offset,
-            partition_entries_logical_size,
-            entries_aligned_start,
-            entries_aligned_size
+        let error = write_configuration(&vec![0u8; GOLEM_DISK_SIZE as usize], 512).unwrap_err();
+        assert!(error.to_string().contains("No valid GPT"), "{}", error);
+    }
+
+    #[test]
+    fn test_read_partition_to_memory() {
+        let image = DiskImage::new(&golem_disk(512, &[("golem.env", SHIPPED_ENV)]));
+        let mut disk = image.disk();
+
+        let (offset, size, mut data) = disk
+            .read_partition_to_memory(GOLEM_CONFIG_PARTITION_GUID)
+            .unwrap();
+        assert_eq!(offset, CONFIG_PARTITION_OFFSET);
+        assert_eq!(size, CONFIG_PARTITION_SIZE);
+        assert_eq!(data.len() as u64, CONFIG_PARTITION_SIZE);
+        assert_eq!(
+            read_files(&mut data, &["golem.env"]),
+            vec![Some(SHIPPED_ENV.to_string())]
         );
 
-        disk_file
-            .seek(SeekFrom::Start(entries_aligned_start))
-            .with_context(|| {
-                format!(
-                    "Failed to seek to aligned partition entries at offset {}",
-                    entries_aligned_start
-                )
-            })?;
-
-        let mut entries_aligned_buffer = vec![0u8; entries_aligned_size];
-        disk_file
-            .read_exact(&mut entries_aligned_buffer)
-            .with_context(|| {
-                format!(
-                    "Failed to read {} bytes for aligned partition entries",
-                    entries_aligned_size
-                )
-            })?;
-
-        // Extract the actual partition entries from the aligned buffer
-        let entries_end = entries_offset_within_read + partition_entries_logical_size;
-        if entries_aligned_buffer.len() < entries_end {
-            return Err(anyhow!("Aligned buffer too small for partition entries"));
-        }
-        let partition_entries = &entries_aligned_buffer[entries_offset_within_read..entries_end];
+        let (offset, _, data) = disk.read_partition_to_memory(ROOT_PARTITION_GUID).unwrap();
+        assert_eq!(offset, ROOT_PARTITION_OFFSET);
+        assert!(data.iter().all(|&byte| byte == ROOT_FILL));
 
-        // Write partition entries to backup location (physically-aligned write)
-        let backup_partition_entries_logical_offset =
-            backup_partition_entries_lba * LOGICAL_SECTOR_SIZE;
+        assert!(
+            disk.read_partition_to_memory("00000000-0000-0000-0000-000000000001")
+                .is_err()
+        );
+    }
 
-        // Align backup partition entries writing to physical sector boundaries
-        let backup_entries_aligned_start =
-            (backup_partition_entries_logical_offset / PHYSICAL_SECTOR_SIZE) * PHYSICAL_SECTOR_SIZE;
-        let backup_entries_offset_within_write =
-            (backup_partition_entries_logical_offset - backup_entries_aligned_start) as usize;
+    #[test]
+    fn test_read_partition_to_memory_of_4kn_disk() {
+        let image = DiskImage::new(&golem_disk(4096, &[("golem.env", SHIPPED_ENV)]));
+        let mut disk = image.disk();
+        geometry::remember(
+            &disk.original_path,
+            geometry::SectorGeometry::new(4096, 4096),
+        );
 
-        info!(
-            "Writing backup partition entries: logical offset={}, aligned offset={}, offset within write={}",
-            backup_partition_entries_logical_offset,
-            backup_entries_aligned_start,
-            backup_entries_offset_within_write
+        let (offset, size, mut data) = disk
+            .read_partition_to_memory(GOLEM_CONFIG_PARTITION_GUID)
+            .unwrap();
+        assert_eq!(offset, CONFIG_PARTITION_OFFSET);
+        assert_eq!(size, CONFIG_PARTITION_SIZE);
+        assert_eq!(
+            read_files(&mut data, &["golem.env"]),
+            vec![Some(SHIPPED_ENV.to_string())]
         );
+    }
 
-        // Read existing data at backup location to preserve surrounding data
-        disk_file
-            .seek(SeekFrom::Start(backup_entries_aligned_start))
-            .with_context(|| {
-                format!(
-                    "Failed to seek to backup entries aligned position {}",
-                    backup_entries_aligned_start
-                )
-            })?;
-
-        let mut backup_entries_aligned_buffer = vec![0u8; entries_aligned_size];
-        // Try to read existing data, but don't fail if we can't (might be at end of disk)
-        let _ = disk_file.read_exact(&mut backup_entries_aligned_buffer);
-
-        // Copy partition entries into the aligned buffer
-        let backup_entries_end = backup_entries_offset_within_write + partition_entries.len();
-        if backup_entries_aligned_buffer.len() >= backup_entries_end {
-            backup_entries_aligned_buffer[backup_entries_offset_within_write..backup_entries_end]
-                .copy_from_slice(partition_entries);
-        }
+    #[test]
+    fn test_large_partitions_are_not_read_into_memory() {
+        assert!(check_in_memory_size(ROOT_PARTITION_GUID, CONFIG_PARTITION_SIZE).is_ok());
+        assert!(check_in_memory_size(ROOT_PARTITION_GUID, MAX_CONFIG_PARTITION_SIZE).is_ok());
 
-        // Write the aligned buffer
-        disk_file
-            .seek(SeekFrom::Start(backup_entries_aligned_start))
-            .with_context(|| {
-                format!(
-                    "Failed to seek to backup partition entries at offset {}",
-                    backup_entries_aligned_start
-                )
-            })?;
-        info!(
-            "Writing {} bytes for aligned backup partition entries",
-            backup_entries_aligned_buffer.len()
+        // e.g. the data partition of a 64 GB card
+        let error = check_in_memory_size(ROOT_PARTITION_GUID, 60 << 30).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Partition {} is 61440 MB, too large to be a configuration partition \
+                 (at most 64 MB); it is not read into memory",
+                ROOT_PARTITION_GUID
+            )
         );
-        disk_file
-            .write_all(&backup_entries_aligned_buffer)
-            .with_context(|| {
-                format!(
-                    "Failed to write {} bytes for partition entries",
-                    backup_entries_aligned_buffer.len()
-                )
-            })?;
     }
 
-    // Ensure all data is flushed to disk
-    info!("Step 7: Flushing all data to disk");
-    disk_file
-        .flush()
-        .with_context(|| "Failed to flush data to disk")?;
+    #[test]
+    fn test_partitions_are_read_as_far_as_the_device_goes() {
+        let device = vec![7u8; PARTITION_READ_CHUNK + 512];
+        let data = read_chunked(&mut device.as_slice(), 1536).unwrap();
+        assert_eq!(data, vec![7u8; 1536]);
 
-    info!("Successfully fixed GPT backup header location");
-    Ok(())
-}
+        // A table claiming more than the device holds gets what is there
+        let data = read_chunked(&mut device.as_slice(), MAX_CONFIG_PARTITION_SIZE).unwrap();
+        assert_eq!(data.len(), device.len());
+        assert!(data.capacity() < 2 * PARTITION_READ_CHUNK + 512);
+    }
 
-/// Lists available disk devices in the system
-///
-/// # Returns
-/// * `Result<Vec<DiskDevice>>` - A list of available disk devices
-#[allow(dead_code)]
-pub async fn list_available_disks() -> Result<Vec<DiskDevice>> {
-    PlatformDiskAccess::list_available_disks().await
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fake_backend_serves_attached_images() {
+        let backend = Arc::new(FakeBackend::default());
+        backend.attach(
+            "/dev/fake0",
+            &golem_disk(512, &[("golem.env", SHIPPED_ENV)]),
+        );
+        let _installed = FakeBackend::install(&backend);
+
+        let devices = list_available_disks().await.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].path, "/dev/fake0");
+
+        let mut disk = Disk::lock_path("/dev/fake0", true).await.unwrap();
+        let (offset, _, _) = disk
+            .read_partition_to_memory(GOLEM_CONFIG_PARTITION_GUID)
+            .unwrap();
+        assert_eq!(offset, CONFIG_PARTITION_OFFSET);
+        assert!(Disk::lock_path("/dev/fake1", true).await.is_err());
+
+        assert_eq!(
+            backend.calls(),
+            vec!["list", "lock /dev/fake0", "lock /dev/fake1"]
+        );
+    }
 }
-
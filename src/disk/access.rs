@@ -0,0 +1,134 @@
+// The platform seam of the disk module
+//
+// Everything `Disk` asks of the OS goes through two traits. A `DiskBackend` opens, clears
+// and lists devices; the `DiskAccess` it hands out with an opened device deals with the
+// platform quirks of that handle. The native backend wraps `LinuxDiskAccess` or
+// `WindowsDiskAccess`. Tests install a fake backend with [`set_backend`] so the flash and
+// edit workflows can be driven end to end without root privileges or real hardware.
+
+use super::common::{DiskDevice, FlashPhase};
+use super::{Disk, PlatformDiskAccess, topology};
+use crate::models::CancelToken;
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::debug;
+
+/// Backend installed in place of the native one, if any
+static BACKEND: RwLock<Option<Arc<dyn DiskBackend>>> = RwLock::new(None);
+
+/// Platform handling of an opened disk
+pub trait DiskAccess: fmt::Debug + Send + Sync {
+    /// A second handle to the open disk, sharing its lock
+    fn clone_file_handle(&self, file: &File) -> Result<File>;
+
+    /// Last checks on the handle before the image is written
+    fn pre_write_checks(&self, disk_file: &File, original_path: Option<&str>) -> Result<()>;
+
+    /// Platform explanation of a failed write, if there is one
+    fn handle_write_error(&self, e: &io::Error) -> Option<anyhow::Error>;
+
+    /// Platform explanation of a failed flush, if there is one
+    fn handle_flush_error(&self, e: &io::Error) -> Option<anyhow::Error>;
+
+    /// A handle to the disk without direct I/O, for devices that refuse aligned writes
+    fn reopen_buffered(&self, file: &File) -> Result<File>;
+
+    /// Another attempt at the partition table of `disk` after it failed to parse
+    fn handle_gpt_error<'a>(
+        &self,
+        disk: &'a Disk,
+        error: anyhow::Error,
+    ) -> Result<Option<gpt::GptDisk<'a>>>;
+}
+
+/// Opens, clears and lists disks
+pub trait DiskBackend: Send + Sync {
+    /// Open and lock the disk at `path`, see [`Disk::lock_path`]
+    fn lock_path<'a>(
+        &'a self,
+        path: &'a str,
+        edit_mode: bool,
+    ) -> BoxFuture<'a, Result<(File, Arc<dyn DiskAccess>)>>;
+
+    /// Remove the partitions of the disk at `path`, see [`Disk::clear_partitions`]
+    ///
+    /// Progress is sent as `FlashPhase::Clearing` on `progress`.
+    fn clear_partitions(
+        &self,
+        path: String,
+        cancel_token: CancelToken,
+        progress: UnboundedSender<FlashPhase>,
+    ) -> BoxFuture<'static, Result<FlashPhase>>;
+
+    /// Disks attached to the system
+    fn list_disks(&self) -> BoxFuture<'_, Result<Vec<DiskDevice>>>;
+
+    /// Power off or eject the disk at `path`, see [`super::eject::eject`]
+    fn eject(&self, path: String) -> BoxFuture<'static, Result<()>>;
+}
+
+/// The backend disks are opened with
+pub fn backend() -> Arc<dyn DiskBackend> {
+    let installed = match BACKEND.read() {
+        Ok(backend) => backend.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    };
+    installed.unwrap_or_else(|| Arc::new(NativeBackend))
+}
+
+/// Open disks through `backend`, or through the OS again with `None`
+#[cfg(test)]
+pub fn set_backend(backend: Option<Arc<dyn DiskBackend>>) {
+    let mut installed = match BACKEND.write() {
+        Ok(installed) => installed,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    *installed = backend;
+}
+
+/// Disk access of the OS the imager runs on
+struct NativeBackend;
+
+impl DiskBackend for NativeBackend {
+    fn lock_path<'a>(
+        &'a self,
+        path: &'a str,
+        edit_mode: bool,
+    ) -> BoxFuture<'a, Result<(File, Arc<dyn DiskAccess>)>> {
+        Box::pin(async move {
+            // Writing to a partition would put the image in the middle of the disk
+            let topology = topology::inspect(path)?;
+            topology.require_whole_disk()?;
+            debug!("{} has partitions {:?}", topology.node, topology.partitions);
+
+            let (file, platform) = PlatformDiskAccess::lock_path(path, edit_mode).await?;
+            Ok((file, Arc::new(platform) as Arc<dyn DiskAccess>))
+        })
+    }
+
+    fn clear_partitions(
+        &self,
+        path: String,
+        cancel_token: CancelToken,
+        progress: UnboundedSender<FlashPhase>,
+    ) -> BoxFuture<'static, Result<FlashPhase>> {
+        Box::pin(PlatformDiskAccess::clear_disk_partitions(
+            path,
+            cancel_token,
+            progress,
+        ))
+    }
+
+    fn list_disks(&self) -> BoxFuture<'_, Result<Vec<DiskDevice>>> {
+        Box::pin(PlatformDiskAccess::list_available_disks())
+    }
+
+    fn eject(&self, path: String) -> BoxFuture<'static, Result<()>> {
+        Box::pin(super::eject::eject_native(path))
+    }
+}
@@ -0,0 +1,491 @@
+// Aligned disk I/O
+//
+// Unbuffered disk access (FILE_FLAG_NO_BUFFERING on Windows, O_DIRECT on Linux, raw
+// devices on macOS) only accepts reads and writes of whole sectors, at sector-aligned
+// offsets, from sector-aligned memory. `AlignedDiskIo` turns arbitrary reads, writes and
+// seeks into such operations: partial sectors are read, patched and written back, so the
+// bytes around a write are kept. The alignment logic is the same on every platform and is
+// tested against a device that refuses unaligned access.
+
+use std::alloc::{self, Layout};
+use std::cmp;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ptr::NonNull;
+use tracing::{debug, info};
+
+use super::geometry::MIN_IO_ALIGNMENT;
+
+/// Most bytes moved by a single read or write of the wrapped device
+const WINDOW_SIZE: usize = 4 * 1024 * 1024;
+
+/// An I/O wrapper that ensures all operations are properly aligned to disk sector boundaries
+pub struct AlignedDiskIo<T = File> {
+    /// The wrapped device
+    inner: T,
+    /// Position of the next read or write, at any byte offset
+    position: u64,
+    /// Size of the device, if the OS reports it separately from seeking to the end
+    device_len: Option<u64>,
+    /// Sector-aligned memory every unaligned operation goes through
+    buffer: AlignedBuffer,
+}
+
+impl<T> fmt::Debug for AlignedDiskIo<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlignedDiskIo")
+            .field("position", &self.position)
+            .field("alignment", &self.buffer.alignment)
+            .field("device_len", &self.device_len)
+            .finish()
+    }
+}
+
+/// Zeroed memory aligned for direct I/O
+struct AlignedBuffer {
+    ptr: NonNull<u8>,
+    capacity: usize,
+    alignment: usize,
+}
+
+// The buffer owns its allocation like a Vec does
+unsafe impl Send for AlignedBuffer {}
+
+impl AlignedBuffer {
+    /// Allocate `capacity` bytes, rounded up to whole `alignment` blocks
+    fn new(capacity: usize, alignment: usize) -> io::Result<Self> {
+        let capacity = capacity.div_ceil(alignment) * alignment;
+        let layout = Layout::from_size_align(capacity, alignment).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid aligned buffer layout: capacity={}, alignment={}: {}",
+                    capacity, alignment, e
+                ),
+            )
+        })?;
+        let ptr = NonNull::new(unsafe { alloc::alloc_zeroed(layout) }).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                format!(
+                    "Failed to allocate {} bytes aligned to {} bytes",
+                    capacity, alignment
+                ),
+            )
+        })?;
+        Ok(Self {
+            ptr,
+            capacity,
+            alignment,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.capacity) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.capacity) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        let layout = Layout::from_size_align(self.capacity, self.alignment)
+            .expect("Invalid layout in AlignedBuffer::drop");
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), layout) };
+    }
+}
+
+/// Effective alignment for a sector size: a power of two, and at least the direct I/O minimum
+fn effective_alignment(sector_size: u32) -> usize {
+    (sector_size as usize)
+        .max(MIN_IO_ALIGNMENT as usize)
+        .next_power_of_two()
+}
+
+impl<T: Read + Write + Seek> AlignedDiskIo<T> {
+    /// Wrap `inner`, aligning every operation to `sector_size`
+    ///
+    /// The alignment is never less than [`MIN_IO_ALIGNMENT`], whatever the disk reports.
+    pub fn new(mut inner: T, sector_size: u32) -> io::Result<Self> {
+        let alignment = effective_alignment(sector_size);
+        if alignment != sector_size as usize {
+            debug!(
+                "AlignedDiskIo: aligning to {} bytes (sector size {} bytes)",
+                alignment, sector_size
+            );
+        }
+        let buffer = AlignedBuffer::new(WINDOW_SIZE, alignment)?;
+        let position = inner.stream_position()?;
+        Ok(Self {
+            inner,
+            position,
+            device_len: None,
+            buffer,
+        })
+    }
+
+    /// Alignment of the operations on the wrapped device
+    pub fn alignment(&self) -> usize {
+        self.buffer.alignment
+    }
+
+    /// Get a reference to the wrapped device
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped device
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Get the current position
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Unwrap this wrapper and return the wrapped device
+    pub fn into_inner(mut self) -> io::Result<T> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    /// The aligned window around `len` bytes at the current position
+    ///
+    /// Returns the window's start, its length and where the current position lies in it.
+    fn window(&self, len: usize) -> (u64, usize, usize) {
+        let alignment = self.alignment() as u64;
+        let start = self.position / alignment * alignment;
+        let offset = (self.position - start) as usize;
+        let len = cmp::min(offset + len, self.buffer.capacity);
+        let aligned_len = (len as u64).div_ceil(alignment) * alignment;
+        (start, aligned_len as usize, offset)
+    }
+
+    /// Whether `buf` at the current position can be handed to the device as it is
+    fn is_aligned(&self, buf: &[u8]) -> bool {
+        let alignment = self.alignment();
+        self.position % alignment as u64 == 0
+            && buf.len() % alignment == 0
+            && buf.as_ptr() as usize % alignment == 0
+    }
+
+    /// Read the window at `start` into the buffer, returning the bytes the device had
+    ///
+    /// A window reaching past the end of the device is read as far as it goes.
+    fn read_window(&mut self, start: u64, len: usize) -> io::Result<usize> {
+        self.inner.seek(SeekFrom::Start(start))?;
+        let window = &mut self.buffer.as_mut_slice()[..len];
+        let mut filled = 0;
+        while filled < len {
+            match self.inner.read(&mut window[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        window[filled..].fill(0);
+        Ok(filled)
+    }
+}
+
+impl<T: Read + Write + Seek> Read for AlignedDiskIo<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.is_aligned(buf) {
+            self.inner.seek(SeekFrom::Start(self.position))?;
+            let n = self.inner.read(buf)?;
+            self.position += n as u64;
+            return Ok(n);
+        }
+
+        let (start, len, offset) = self.window(buf.len());
+        let filled = self.read_window(start, len)?;
+        let available = filled.saturating_sub(offset);
+        let copied = cmp::min(available, buf.len());
+        buf[..copied].copy_from_slice(&self.buffer.as_slice()[offset..offset + copied]);
+        self.position += copied as u64;
+        Ok(copied)
+    }
+}
+
+impl<T: Read + Write + Seek> Write for AlignedDiskIo<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.is_aligned(buf) {
+            self.inner.seek(SeekFrom::Start(self.position))?;
+            let n = self.inner.write(buf)?;
+            self.position += n as u64;
+            return Ok(n);
+        }
+
+        // Read, patch and write back the sectors the data falls in
+        let (start, len, offset) = self.window(buf.len());
+        let copied = cmp::min(len - offset, buf.len());
+        let head_is_partial = offset != 0;
+        let tail_is_partial = offset + copied != len;
+        if head_is_partial || tail_is_partial {
+            self.read_window(start, len)?;
+        }
+        self.buffer.as_mut_slice()[offset..offset + copied].copy_from_slice(&buf[..copied]);
+
+        self.inner.seek(SeekFrom::Start(start))?;
+        self.inner.write_all(&self.buffer.as_slice()[..len])?;
+        self.position += copied as u64;
+        Ok(copied)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Writes go to the device as they are made, nothing is held back
+        self.inner.flush()
+    }
+}
+
+impl<T: Read + Write + Seek> Seek for AlignedDiskIo<T> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let offset_from = |base: u64, delta: i64| {
+            base.checked_add_signed(delta)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek position"))
+        };
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::Current(delta) => offset_from(self.position, delta)?,
+            SeekFrom::End(delta) => {
+                let end = match self.device_len {
+                    Some(len) => len,
+                    None => self.inner.seek(SeekFrom::End(0))?,
+                };
+                offset_from(end, delta)?
+            }
+        };
+        Ok(self.position)
+    }
+}
+
+/// Size of a disk as reported by the OS
+///
+/// Seeking to the end of a physical drive opened for unbuffered I/O often fails on
+/// Windows, so the size is asked for with IOCTL_DISK_GET_LENGTH_INFO instead.
+#[cfg(windows)]
+fn device_length(file: &File) -> Option<u64> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    const IOCTL_DISK_GET_LENGTH_INFO: u32 = 0x0007405C;
+
+    let mut length: u64 = 0;
+    let mut bytes_returned: u32 = 0;
+    let result = unsafe {
+        DeviceIoControl(
+            file.as_raw_handle() as HANDLE,
+            IOCTL_DISK_GET_LENGTH_INFO,
+            std::ptr::null_mut(),
+            0,
+            &mut length as *mut _ as *mut _,
+            std::mem::size_of::<u64>() as u32,
+            &mut bytes_returned,
+            std::ptr::null_mut(),
+        )
+    };
+    if result == 0 {
+        debug!(
+            "AlignedDiskIo: IOCTL_DISK_GET_LENGTH_INFO failed: {}",
+            io::Error::last_os_error()
+        );
+        return None;
+    }
+    Some(length)
+}
+
+/// Size of a disk as reported by the OS
+///
+/// Block devices report their size when seeking to the end.
+#[cfg(not(windows))]
+fn device_length(_file: &File) -> Option<u64> {
+    None
+}
+
+/// Wrap a disk handle opened for unbuffered I/O so any read, write or seek works on it
+pub fn aligned_disk_io(
+    file: File,
+    sector_size: u32,
+) -> io::Result<impl Read + Write + Seek + fmt::Debug> {
+    let device_len = device_length(&file);
+    let mut aligned = AlignedDiskIo::new(file, sector_size)?;
+    aligned.device_len = device_len;
+    info!(
+        "Using aligned disk I/O with {}-byte alignment",
+        aligned.alignment()
+    );
+    Ok(aligned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const ALIGNMENT: usize = MIN_IO_ALIGNMENT as usize;
+
+    /// In-memory disk that, like unbuffered I/O, refuses anything but whole aligned sectors
+    struct StrictDisk {
+        data: Cursor<Vec<u8>>,
+    }
+
+    impl StrictDisk {
+        fn new(data: Vec<u8>) -> Self {
+            Self {
+                data: Cursor::new(data),
+            }
+        }
+
+        fn check(&self, ptr: *const u8, len: usize) -> io::Result<()> {
+            let aligned = self.data.position() % ALIGNMENT as u64 == 0
+                && len % ALIGNMENT == 0
+                && ptr as usize % ALIGNMENT == 0;
+            if aligned {
+                Ok(())
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "unaligned access: position {}, length {}",
+                        self.data.position(),
+                        len
+                    ),
+                ))
+            }
+        }
+    }
+
+    impl Read for StrictDisk {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.check(buf.as_ptr(), buf.len())?;
+            self.data.read(buf)
+        }
+    }
+
+    impl Write for StrictDisk {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.check(buf.as_ptr(), buf.len())?;
+            self.data.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for StrictDisk {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.data.seek(pos)
+        }
+    }
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_unaligned_reads() {
+        let data = pattern(4 * ALIGNMENT);
+        let mut disk = AlignedDiskIo::new(StrictDisk::new(data.clone()), 512).unwrap();
+        assert_eq!(disk.alignment(), ALIGNMENT);
+
+        let mut buf = [0u8; 100];
+        disk.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[..100]);
+
+        // A read across a sector boundary
+        disk.seek(SeekFrom::Start(ALIGNMENT as u64 - 10)).unwrap();
+        let mut across = [0u8; 20];
+        disk.read_exact(&mut across).unwrap();
+        assert_eq!(&across[..], &data[ALIGNMENT - 10..ALIGNMENT + 10]);
+        assert_eq!(disk.position(), ALIGNMENT as u64 + 10);
+
+        // A large read at an unaligned offset
+        disk.seek(SeekFrom::Start(123)).unwrap();
+        let mut large = vec![0u8; 3 * ALIGNMENT];
+        disk.read_exact(&mut large).unwrap();
+        assert_eq!(&large[..], &data[123..123 + 3 * ALIGNMENT]);
+    }
+
+    #[test]
+    fn test_read_stops_at_end_of_device() {
+        let data = pattern(2 * ALIGNMENT);
+        let mut disk = AlignedDiskIo::new(StrictDisk::new(data.clone()), 512).unwrap();
+
+        disk.seek(SeekFrom::End(-10)).unwrap();
+        let mut buf = [0u8; 100];
+        assert_eq!(disk.read(&mut buf).unwrap(), 10);
+        assert_eq!(&buf[..10], &data[data.len() - 10..]);
+        assert_eq!(disk.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_unaligned_write_keeps_surrounding_bytes() {
+        let data = pattern(3 * ALIGNMENT);
+        let mut disk = AlignedDiskIo::new(StrictDisk::new(data.clone()), 512).unwrap();
+
+        disk.seek(SeekFrom::Start(ALIGNMENT as u64 - 5)).unwrap();
+        disk.write_all(b"0123456789").unwrap();
+        disk.flush().unwrap();
+
+        let mut expected = data;
+        expected[ALIGNMENT - 5..ALIGNMENT + 5].copy_from_slice(b"0123456789");
+        let written = disk.into_inner().unwrap().data.into_inner();
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_aligned_write_goes_straight_to_device() {
+        let mut disk = AlignedDiskIo::new(StrictDisk::new(vec![0u8; 2 * ALIGNMENT]), 4096).unwrap();
+        let mut block = AlignedBuffer::new(ALIGNMENT, ALIGNMENT).unwrap();
+        block.as_mut_slice().fill(0xAB);
+
+        disk.seek(SeekFrom::Start(ALIGNMENT as u64)).unwrap();
+        assert_eq!(disk.write(block.as_slice()).unwrap(), ALIGNMENT);
+
+        let written = disk.into_inner().unwrap().data.into_inner();
+        assert!(written[..ALIGNMENT].iter().all(|&b| b == 0));
+        assert!(written[ALIGNMENT..].iter().all(|&b| b == 0xAB));
+    }
+
+    #[test]
+    fn test_reads_from_short_device() {
+        // The device doesn't have to end on a sector boundary, e.g. an image file
+        let data = pattern(1024);
+        let mut disk = AlignedDiskIo::new(Cursor::new(data.clone()), 512).unwrap();
+
+        let mut buf = [0u8; 100];
+        disk.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[..100]);
+        let mut buf2 = [0u8; 200];
+        disk.read_exact(&mut buf2).unwrap();
+        assert_eq!(&buf2[..], &data[100..300]);
+
+        disk.seek(SeekFrom::Start(500)).unwrap();
+        disk.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[500..600]);
+
+        let mut rest = Vec::new();
+        disk.read_to_end(&mut rest).unwrap();
+        assert_eq!(&rest[..], &data[600..]);
+    }
+
+    #[test]
+    fn test_alignment_is_at_least_the_minimum() {
+        assert_eq!(effective_alignment(512), ALIGNMENT);
+        assert_eq!(effective_alignment(16384), 16384);
+        assert_eq!(effective_alignment(5000), 8192);
+    }
+}
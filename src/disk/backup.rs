@@ -0,0 +1,105 @@
+/// Backups of the configuration partition
+///
+/// A backup is a raw copy of the GOLEMCONF partition. Restoring one copies its files back
+/// onto the device instead of writing the raw bytes, so a backup taken from one image can
+/// be applied after re-flashing with an image whose configuration partition is sized
+/// differently.
+use super::ConfigSnapshot;
+use anyhow::{Context, Result, anyhow};
+use std::path::Path;
+
+/// Extension of backup files, which mount as disk images on most systems
+pub const BACKUP_EXTENSION: &str = "img";
+
+/// File name for a new backup of `device_name`, e.g. `golemconf-SanDisk_Ultra-20250101-120000.img`
+pub fn backup_file_name(device_name: &str) -> String {
+    let device: String = device_name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let device = device.trim_matches('_');
+    let device = if device.is_empty() { "device" } else { device };
+
+    format!(
+        "golemconf-{}-{}.{}",
+        device,
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        BACKUP_EXTENSION
+    )
+}
+
+/// Read the files out of a backup
+///
+/// # Returns
+/// * `Result<ConfigSnapshot>` - Files to restore; fails if the file isn't a FAT partition image
+pub fn read_backup(path: &Path) -> Result<ConfigSnapshot> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to open backup {}", path.display()))?
+        .len();
+    if size > super::MAX_CONFIG_PARTITION_SIZE {
+        return Err(anyhow!(
+            "{} is too large to be a configuration backup ({} bytes)",
+            path.display(),
+            size
+        ));
+    }
+
+    let data =
+        std::fs::read(path).with_context(|| format!("Failed to read backup {}", path.display()))?;
+    let fs = fatfs::FileSystem::new(std::io::Cursor::new(data), fatfs::FsOptions::new())
+        .with_context(|| format!("{} is not a configuration partition backup", path.display()))?;
+    ConfigSnapshot::capture(&fs.root_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("golem-backup-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_backup_file_name() {
+        let name = backup_file_name(" SanDisk Ultra (/dev/sdb) ");
+        assert!(name.starts_with("golemconf-SanDisk_Ultra___dev_sdb-"));
+        assert!(name.ends_with(".img"));
+        assert!(backup_file_name("///").starts_with("golemconf-device-"));
+    }
+
+    #[test]
+    fn test_read_backup() {
+        let mut volume = vec![0u8; 4 * 1024 * 1024];
+        fatfs::format_volume(
+            Cursor::new(&mut volume[..]),
+            fatfs::FormatVolumeOptions::new().volume_label(*b"GOLEMCONF  "),
+        )
+        .unwrap();
+        {
+            let fs = fatfs::FileSystem::new(Cursor::new(&mut volume[..]), fatfs::FsOptions::new())
+                .unwrap();
+            let mut file = fs.root_dir().create_file("golem.env").unwrap();
+            file.write_all(b"YA_NET_TYPE=central\n").unwrap();
+        }
+
+        let path = temp_path("volume.img");
+        std::fs::write(&path, &volume).unwrap();
+        let snapshot = read_backup(&path).unwrap();
+        assert_eq!(
+            snapshot.file("golem.env"),
+            Some(&b"YA_NET_TYPE=central\n"[..])
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_rejects_non_fat_backup() {
+        let path = temp_path("garbage.img");
+        std::fs::write(&path, vec![0xAB; 64 * 1024]).unwrap();
+        assert!(read_backup(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,341 @@
+/// Locating the blocks of a device that don't read back what was written
+///
+/// Every block of the image is hashed as it is written, and verification hashes what it
+/// reads back block by block. The data written is checked against the image's hash on its
+/// own, so a corrupt image is told apart from failing media, where some blocks come back
+/// different or can't be read at all.
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use tracing::{debug, warn};
+
+/// Size of the blocks compared individually, also the verification read size
+pub const BLOCK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Unreadable blocks after which verification gives up on the device
+pub const MAX_UNREADABLE_BLOCKS: usize = 64;
+
+/// Times a bad block is read again before it is reported
+const READ_RETRIES: usize = 2;
+
+/// Logical block size the report counts in
+const LBA_SIZE: u64 = super::geometry::DEFAULT_LOGICAL_SECTOR_SIZE;
+
+/// Blocks listed in the report, the rest are summed up
+const LISTED_RANGES: usize = 10;
+
+/// Sector alignment of reads for direct I/O
+const SECTOR_SIZE: u64 = super::geometry::MIN_IO_ALIGNMENT;
+
+/// SHA-256 of each `BLOCK_SIZE` block of a stream of data
+#[derive(Default)]
+pub struct BlockHasher {
+    hashes: Vec<[u8; 32]>,
+    current: Sha256,
+    current_len: u64,
+}
+
+impl BlockHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let take = (BLOCK_SIZE - self.current_len).min(data.len() as u64) as usize;
+            self.current.update(&data[..take]);
+            self.current_len += take as u64;
+            data = &data[take..];
+            if self.current_len == BLOCK_SIZE {
+                self.finish_block();
+            }
+        }
+    }
+
+    /// Continue at the start of block `index`, leaving the blocks before it unhashed
+    pub fn skip_to(&mut self, index: usize) {
+        self.current = Sha256::new();
+        self.current_len = 0;
+        self.hashes.resize(index.max(self.hashes.len()), [0; 32]);
+    }
+
+    /// Hashes of all blocks, the last one possibly shorter than `BLOCK_SIZE`
+    pub fn finish(mut self) -> Vec<[u8; 32]> {
+        if self.current_len > 0 {
+            self.finish_block();
+        }
+        self.hashes
+    }
+
+    fn finish_block(&mut self) {
+        let hash = std::mem::take(&mut self.current).finalize();
+        self.hashes.push(hash.into());
+        self.current_len = 0;
+    }
+}
+
+/// Why a block is bad
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadBlockKind {
+    /// The block reads back different data than was written
+    Mismatch,
+    /// Reading the block fails
+    Unreadable,
+}
+
+/// Blocks among `read` that differ from `written`, by index
+///
+/// Blocks listed in `unreadable` and blocks missing from `read`, because reading ended
+/// early, are unreadable.
+pub fn find_bad_blocks(
+    written: &[[u8; 32]],
+    read: &[[u8; 32]],
+    unreadable: &[usize],
+) -> Vec<(usize, BadBlockKind)> {
+    written
+        .iter()
+        .enumerate()
+        .filter_map(|(index, hash)| {
+            if unreadable.contains(&index) {
+                return Some((index, BadBlockKind::Unreadable));
+            }
+            match read.get(index) {
+                None => Some((index, BadBlockKind::Unreadable)),
+                Some(read) if read != hash => Some((index, BadBlockKind::Mismatch)),
+                Some(_) => None,
+            }
+        })
+        .collect()
+}
+
+/// Read the bad blocks again, keeping those that still don't read back as written
+///
+/// A block that reads correctly on a retry was a transient error, not bad media.
+/// `buffer` must hold at least `BLOCK_SIZE` bytes.
+pub fn recheck<R: Read + Seek>(
+    device: &mut R,
+    bad: Vec<(usize, BadBlockKind)>,
+    written: &[[u8; 32]],
+    image_size: u64,
+    buffer: &mut [u8],
+) -> Vec<(usize, BadBlockKind)> {
+    bad.into_iter()
+        .filter_map(|(index, mut kind)| {
+            for attempt in 1..=READ_RETRIES {
+                match read_block(device, index, image_size, buffer) {
+                    Ok(hash) if hash == written[index] => {
+                        debug!("Block {} read back correctly on retry {}", index, attempt);
+                        return None;
+                    }
+                    Ok(_) => kind = BadBlockKind::Mismatch,
+                    Err(e) => {
+                        warn!("Block {} still can't be read: {}", index, e);
+                        kind = BadBlockKind::Unreadable;
+                    }
+                }
+            }
+            Some((index, kind))
+        })
+        .collect()
+}
+
+/// Hash block `index` of the device
+fn read_block<R: Read + Seek>(
+    device: &mut R,
+    index: usize,
+    image_size: u64,
+    buffer: &mut [u8],
+) -> std::io::Result<[u8; 32]> {
+    let start = index as u64 * BLOCK_SIZE;
+    let len = BLOCK_SIZE.min(image_size - start);
+    // Direct I/O reads whole sectors; the padding after the image is not hashed
+    let aligned_len = len.div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+    device.seek(SeekFrom::Start(start))?;
+    device.read_exact(&mut buffer[..aligned_len as usize])?;
+    Ok(Sha256::digest(&buffer[..len as usize]).into())
+}
+
+/// Contiguous bad blocks of the same kind, as logical block addresses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadRange {
+    pub first_lba: u64,
+    pub last_lba: u64,
+    pub kind: BadBlockKind,
+}
+
+/// The regions of a device that don't hold what was written to them
+#[derive(Debug, Clone, PartialEq)]
+pub struct BadBlockReport {
+    pub ranges: Vec<BadRange>,
+    /// Bytes of the image in bad blocks
+    pub bad_bytes: u64,
+}
+
+impl BadBlockReport {
+    /// Report on the bad blocks of an image of `image_size` bytes, sorted by index
+    pub fn new(bad: &[(usize, BadBlockKind)], image_size: u64) -> Self {
+        let block_range = |index: usize| {
+            let start = index as u64 * BLOCK_SIZE;
+            let end = (start + BLOCK_SIZE).min(image_size);
+            (start, end)
+        };
+
+        let mut ranges: Vec<BadRange> = Vec::new();
+        let mut bad_bytes = 0;
+        let mut previous: Option<usize> = None;
+        for &(index, kind) in bad {
+            let (start, end) = block_range(index);
+            bad_bytes += end - start;
+            let last_lba = end.div_ceil(LBA_SIZE) - 1;
+            match ranges.last_mut() {
+                Some(range) if previous == Some(index - 1) && range.kind == kind => {
+                    range.last_lba = last_lba;
+                }
+                _ => ranges.push(BadRange {
+                    first_lba: start / LBA_SIZE,
+                    last_lba,
+                    kind,
+                }),
+            }
+            previous = Some(index);
+        }
+        Self { ranges, bad_bytes }
+    }
+}
+
+impl fmt::Display for BadBlockReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Data verification failed: {} region(s) of the device, {} in total, don't hold what was written:",
+            self.ranges.len(),
+            super::layout::format_size(self.bad_bytes)
+        )?;
+        for range in self.ranges.iter().take(LISTED_RANGES) {
+            let reason = match range.kind {
+                BadBlockKind::Mismatch => "reads back different data",
+                BadBlockKind::Unreadable => "can't be read",
+            };
+            writeln!(
+                f,
+                "  LBA {}-{}: {}",
+                range.first_lba, range.last_lba, reason
+            )?;
+        }
+        if self.ranges.len() > LISTED_RANGES {
+            writeln!(f, "  ...and {} more", self.ranges.len() - LISTED_RANGES)?;
+        }
+        write!(
+            f,
+            "The image itself is intact, so the flash media is likely failing. Try another card or drive."
+        )
+    }
+}
+
+impl std::error::Error for BadBlockReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const BLOCK: usize = BLOCK_SIZE as usize;
+
+    fn hashes(data: &[u8]) -> Vec<[u8; 32]> {
+        let mut hasher = BlockHasher::new();
+        // Uneven pieces, as reads can return less than asked for
+        for piece in data.chunks(BLOCK / 3 + 7) {
+            hasher.update(piece);
+        }
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_block_hasher_splits_at_block_boundaries() {
+        let data = vec![7u8; BLOCK * 2 + 100];
+        let hashes = hashes(&data);
+
+        assert_eq!(hashes.len(), 3);
+        assert_eq!(hashes[0], hashes[1]);
+        assert_eq!(hashes[2], <[u8; 32]>::from(Sha256::digest(&data[..100])));
+    }
+
+    #[test]
+    fn test_find_bad_blocks() {
+        let written = hashes(&vec![1u8; BLOCK * 4]);
+        let mut device = vec![1u8; BLOCK * 4];
+        device[BLOCK + 5] = 0;
+
+        // Block 2 failed to read and reading stopped before block 3
+        let mut read = BlockHasher::new();
+        read.update(&device[..BLOCK * 2]);
+        read.skip_to(3);
+        let bad = find_bad_blocks(&written, &read.finish(), &[2]);
+
+        assert_eq!(
+            bad,
+            vec![
+                (1, BadBlockKind::Mismatch),
+                (2, BadBlockKind::Unreadable),
+                (3, BadBlockKind::Unreadable),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recheck_drops_transient_errors() {
+        let image_size = (BLOCK * 2 + 4096) as u64;
+        let mut device = vec![3u8; BLOCK * 3];
+        let written = hashes(&device[..image_size as usize]);
+        device[5] = 0;
+        let mut buffer = vec![0u8; BLOCK];
+
+        let bad = recheck(
+            &mut Cursor::new(&mut device),
+            vec![(0, BadBlockKind::Unreadable), (2, BadBlockKind::Mismatch)],
+            &written,
+            image_size,
+            &mut buffer,
+        );
+
+        assert_eq!(bad, vec![(0, BadBlockKind::Mismatch)]);
+    }
+
+    #[test]
+    fn test_report_merges_adjacent_blocks() {
+        let image_size = BLOCK_SIZE * 10 + 1000;
+        let report = BadBlockReport::new(
+            &[
+                (1, BadBlockKind::Mismatch),
+                (2, BadBlockKind::Mismatch),
+                (3, BadBlockKind::Unreadable),
+                (10, BadBlockKind::Mismatch),
+            ],
+            image_size,
+        );
+        let lbas_per_block = BLOCK_SIZE / LBA_SIZE;
+
+        assert_eq!(
+            report.ranges,
+            vec![
+                BadRange {
+                    first_lba: lbas_per_block,
+                    last_lba: lbas_per_block * 3 - 1,
+                    kind: BadBlockKind::Mismatch,
+                },
+                BadRange {
+                    first_lba: lbas_per_block * 3,
+                    last_lba: lbas_per_block * 4 - 1,
+                    kind: BadBlockKind::Unreadable,
+                },
+                BadRange {
+                    first_lba: lbas_per_block * 10,
+                    last_lba: lbas_per_block * 10 + 1,
+                    kind: BadBlockKind::Mismatch,
+                },
+            ]
+        );
+        assert_eq!(report.bad_bytes, BLOCK_SIZE * 3 + 1000);
+        assert!(report.to_string().contains("can't be read"));
+    }
+}
@@ -0,0 +1,208 @@
+// Falling back to buffered I/O when a device refuses direct I/O
+//
+// Physical drives are opened with FILE_FLAG_NO_BUFFERING on Windows, which needs every
+// write to be aligned to the device's sectors. Some card readers report sector sizes they
+// don't actually accept, and every write then fails with "The parameter is incorrect"
+// (error 87). Rather than failing the flash on the first chunk, the chunk is written once
+// more and, if the device refuses it again, the disk is reopened without direct I/O and the
+// chunk written through the buffered handle.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use tracing::{info, warn};
+
+/// Writes of a chunk on the direct I/O handle before it is reopened with buffered I/O
+const DIRECT_ATTEMPTS: u32 = 2;
+
+/// Whether a write failed because the device wants other alignment than it was given
+pub fn is_alignment_error(e: &io::Error) -> bool {
+    #[cfg(windows)]
+    const ALIGNMENT_CODES: &[i32] = &[
+        87, // ERROR_INVALID_PARAMETER
+    ];
+    #[cfg(not(windows))]
+    const ALIGNMENT_CODES: &[i32] = &[libc::EINVAL];
+
+    e.raw_os_error()
+        .is_some_and(|code| ALIGNMENT_CODES.contains(&code))
+}
+
+/// A disk handle that is replaced by a buffered one after repeated alignment errors
+///
+/// `reopen` opens the buffered handle from the direct one, which is closed once the new one
+/// is open.
+pub struct BufferedFallback<D, F> {
+    disk: D,
+    reopen: F,
+    buffered: bool,
+}
+
+impl<D: Write + Seek, F: FnMut(&D) -> anyhow::Result<D>> BufferedFallback<D, F> {
+    pub fn new(disk: D, reopen: F) -> Self {
+        Self {
+            disk,
+            reopen,
+            buffered: false,
+        }
+    }
+
+    /// Whether the disk was reopened with buffered I/O
+    pub fn is_buffered(&self) -> bool {
+        self.buffered
+    }
+
+    /// The handle writes went to last
+    pub fn into_inner(self) -> D {
+        self.disk
+    }
+
+    /// Swap in the buffered handle, positioned where the failed write started
+    ///
+    /// Returns `error` when the disk can't be reopened, so the write fails as it would have.
+    fn fall_back(&mut self, offset: u64, error: io::Error) -> io::Result<()> {
+        warn!(
+            "Direct I/O write at offset {} failed {} times: {} - reopening the disk with buffered I/O",
+            offset, DIRECT_ATTEMPTS, error
+        );
+        match (self.reopen)(&self.disk) {
+            Ok(disk) => {
+                self.disk = disk;
+                self.buffered = true;
+                self.disk.seek(SeekFrom::Start(offset))?;
+                info!("Disk reopened with buffered I/O, continuing the write");
+                Ok(())
+            }
+            Err(e) => {
+                warn!("Failed to reopen the disk with buffered I/O: {:#}", e);
+                Err(error)
+            }
+        }
+    }
+}
+
+impl<D: Write + Seek, F: FnMut(&D) -> anyhow::Result<D>> Write for BufferedFallback<D, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffered {
+            return self.disk.write(buf);
+        }
+
+        let offset = self.disk.stream_position()?;
+        let mut attempt = 1;
+        loop {
+            match self.disk.write(buf) {
+                Err(e) if is_alignment_error(&e) && attempt < DIRECT_ATTEMPTS => {
+                    attempt += 1;
+                    self.disk.seek(SeekFrom::Start(offset))?;
+                }
+                Err(e) if is_alignment_error(&e) => {
+                    self.fall_back(offset, e)?;
+                    return self.disk.write(buf);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.disk.flush()
+    }
+}
+
+impl<D: Seek, F> Seek for BufferedFallback<D, F> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.disk.seek(pos)
+    }
+}
+
+impl<D: Read, F> Read for BufferedFallback<D, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.disk.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[cfg(windows)]
+    const ALIGNMENT_CODE: i32 = 87;
+    #[cfg(not(windows))]
+    const ALIGNMENT_CODE: i32 = libc::EINVAL;
+
+    /// A disk whose direct handle refuses every write with an alignment error
+    struct Device {
+        inner: Cursor<Vec<u8>>,
+        direct: bool,
+        refused: u32,
+    }
+
+    impl Write for Device {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.direct {
+                self.refused += 1;
+                return Err(io::Error::from_raw_os_error(ALIGNMENT_CODE));
+            }
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for Device {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn direct_device() -> Device {
+        Device {
+            inner: Cursor::new(vec![0u8; 64]),
+            direct: true,
+            refused: 0,
+        }
+    }
+
+    #[test]
+    fn test_falls_back_after_repeated_alignment_errors() {
+        let mut disk = BufferedFallback::new(direct_device(), |direct: &Device| {
+            assert_eq!(direct.refused, DIRECT_ATTEMPTS);
+            Ok(Device {
+                inner: direct.inner.clone(),
+                direct: false,
+                refused: 0,
+            })
+        });
+        disk.seek(SeekFrom::Start(16)).unwrap();
+
+        disk.write_all(&[1u8; 32]).unwrap();
+        assert!(disk.is_buffered());
+
+        let data = disk.into_inner().inner.into_inner();
+        assert!(data[..16].iter().all(|&b| b == 0));
+        assert!(data[16..48].iter().all(|&b| b == 1));
+        assert!(data[48..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_keeps_error_when_reopen_fails() {
+        let mut disk = BufferedFallback::new(direct_device(), |_: &Device| {
+            Err(anyhow::anyhow!("no buffered handle"))
+        });
+
+        let error = disk.write_all(&[1u8; 32]).unwrap_err();
+        assert_eq!(error.raw_os_error(), Some(ALIGNMENT_CODE));
+        assert!(!disk.is_buffered());
+    }
+
+    #[test]
+    fn test_other_errors_are_not_alignment_errors() {
+        assert!(is_alignment_error(&io::Error::from_raw_os_error(
+            ALIGNMENT_CODE
+        )));
+        assert!(!is_alignment_error(&io::Error::from(
+            io::ErrorKind::TimedOut
+        )));
+    }
+}
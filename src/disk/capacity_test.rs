@@ -0,0 +1,293 @@
+/// Finding cards and sticks that claim more capacity than they have
+///
+/// Counterfeit devices report a size their flash doesn't have. Writes beyond the real
+/// capacity are dropped or wrap around onto earlier blocks, so such a device takes an image
+/// and only corrupts it once the system fills it. Like H2testw, the test writes pseudorandom
+/// data derived from each block's offset and only reads it back once everything is written,
+/// so a block that was dropped no longer reads back as written, and blocks that wrapped
+/// around onto the same flash read back as the same block. Rather than the whole device,
+/// blocks spread evenly across it are written, which finds the real capacity to within a
+/// few hundredths of the claimed one in minutes instead of hours.
+///
+/// The test erases the device.
+use super::speed_test::test_pattern;
+use crate::models::CancelToken;
+use anyhow::{Result, anyhow};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom, Write};
+use tracing::{info, warn};
+
+/// Blocks written across the device, besides its last block
+pub const SAMPLES: u64 = 256;
+
+/// Size of each test block
+pub const BLOCK_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapacityReport {
+    /// Size the device claims, in bytes
+    pub claimed: u64,
+    /// Bytes up to the first block that didn't read back as written
+    pub verified: u64,
+    /// Blocks written and read back
+    pub blocks: usize,
+    /// Blocks that didn't read back as written, or failed to write or read
+    pub bad_blocks: usize,
+}
+
+impl CapacityReport {
+    pub fn is_genuine(&self) -> bool {
+        self.bad_blocks == 0
+    }
+
+    /// The outcome in a sentence, for the device list
+    pub fn summary(&self) -> String {
+        let format_size = super::layout::format_size;
+        if self.is_genuine() {
+            format!(
+                "All {} test blocks across the {} read back intact, the capacity is genuine",
+                self.blocks,
+                format_size(self.claimed)
+            )
+        } else {
+            format!(
+                "{} of {} test blocks were lost. Only the first {} of the claimed {} hold data, the device is likely counterfeit.",
+                self.bad_blocks,
+                self.blocks,
+                format_size(self.verified),
+                format_size(self.claimed)
+            )
+        }
+    }
+}
+
+/// Offsets of the blocks tested on a disk of `disk_size` bytes, ending with its last block
+pub fn block_offsets(disk_size: u64) -> Vec<u64> {
+    let blocks = disk_size / BLOCK_SIZE;
+    if blocks == 0 {
+        return Vec::new();
+    }
+    let mut offsets: Vec<u64> = (0..SAMPLES)
+        .map(|i| i * blocks / SAMPLES * BLOCK_SIZE)
+        .collect();
+    offsets.push((blocks - 1) * BLOCK_SIZE);
+    offsets.dedup();
+    offsets
+}
+
+/// Write the test blocks of `disk`, then read them all back
+///
+/// `uncache` drops what the system cached of the disk, so the blocks are read back from the
+/// device. `progress` gets the fraction of the test done after each block.
+pub fn run<D: Read + Write + Seek>(
+    disk: &mut D,
+    disk_size: u64,
+    uncache: impl Fn(&D),
+    cancel_token: &CancelToken,
+    mut progress: impl FnMut(f32),
+) -> Result<CapacityReport> {
+    let offsets = block_offsets(disk_size);
+    if offsets.is_empty() {
+        return Err(anyhow!("The disk is too small to test"));
+    }
+    let steps = offsets.len() as f32 * 2.0;
+    // A write or read failing past the real capacity is as telling as lost data
+    let mut bad = vec![false; offsets.len()];
+
+    for (i, &offset) in offsets.iter().enumerate() {
+        check_cancelled(cancel_token)?;
+        let pattern = test_pattern(offset, BLOCK_SIZE as usize);
+        let written = disk
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| disk.write_all(&pattern));
+        if let Err(e) = written {
+            warn!("Failed to write the test block at offset {}: {}", offset, e);
+            bad[i] = true;
+        }
+        progress((i + 1) as f32 / steps);
+    }
+    disk.flush()?;
+    uncache(disk);
+
+    // Which block's data each block reads back as, found by the data's first word
+    let first_words: HashMap<u64, usize> = offsets
+        .iter()
+        .enumerate()
+        .map(|(i, &offset)| (first_word(&test_pattern(offset, 8)), i))
+        .collect();
+    let mut read_as = vec![None; offsets.len()];
+    let mut block = vec![0u8; BLOCK_SIZE as usize];
+    for (i, &offset) in offsets.iter().enumerate() {
+        check_cancelled(cancel_token)?;
+        let read = disk
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| disk.read_exact(&mut block));
+        match read {
+            Ok(()) => {
+                read_as[i] = first_words
+                    .get(&first_word(&block))
+                    .copied()
+                    .filter(|&j| block == test_pattern(offsets[j], BLOCK_SIZE as usize));
+            }
+            Err(e) => warn!("Failed to read the test block at offset {}: {}", offset, e),
+        }
+        progress((offsets.len() + i + 1) as f32 / steps);
+    }
+
+    // Blocks reading back as the same block share their flash with the first of them
+    let mut holders = HashSet::new();
+    for (bad, read_as) in bad.iter_mut().zip(&read_as) {
+        if !read_as.is_some_and(|holder| holders.insert(holder)) {
+            *bad = true;
+        }
+    }
+
+    let verified = offsets
+        .iter()
+        .zip(&bad)
+        .find(|(_, bad)| **bad)
+        .map_or(disk_size, |(offset, _)| *offset);
+    let report = CapacityReport {
+        claimed: disk_size,
+        verified,
+        blocks: offsets.len(),
+        bad_blocks: bad.iter().filter(|bad| **bad).count(),
+    };
+    info!(
+        "Capacity test: {} of {} blocks bad, {} of {} bytes verified",
+        report.bad_blocks, report.blocks, report.verified, report.claimed
+    );
+    Ok(report)
+}
+
+fn first_word(data: &[u8]) -> u64 {
+    u64::from_le_bytes(data[..8].try_into().unwrap_or_default())
+}
+
+fn check_cancelled(cancel_token: &CancelToken) -> Result<()> {
+    if cancel_token.is_cancelled() {
+        return Err(anyhow!("The capacity test was cancelled"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A device claiming `claimed` bytes that wraps writes and reads around at `real`
+    struct Wrapping {
+        inner: Cursor<Vec<u8>>,
+        position: u64,
+        claimed: u64,
+    }
+
+    impl Wrapping {
+        fn new(real: u64, claimed: u64) -> Self {
+            Wrapping {
+                inner: Cursor::new(vec![0u8; real as usize]),
+                position: 0,
+                claimed,
+            }
+        }
+
+        fn real_position(&mut self) -> std::io::Result<()> {
+            let real = self.inner.get_ref().len() as u64;
+            self.inner.set_position(self.position % real);
+            Ok(())
+        }
+    }
+
+    impl Read for Wrapping {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.real_position()?;
+            let len = buf
+                .len()
+                .min((self.inner.get_ref().len() as u64 - self.inner.position()) as usize);
+            let read = self.inner.read(&mut buf[..len])?;
+            self.position += read as u64;
+            Ok(read)
+        }
+    }
+
+    impl Write for Wrapping {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.real_position()?;
+            let len = buf
+                .len()
+                .min((self.inner.get_ref().len() as u64 - self.inner.position()) as usize);
+            let written = self.inner.write(&buf[..len])?;
+            self.position += written as u64;
+            Ok(written)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for Wrapping {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.position = match pos {
+                SeekFrom::Start(offset) => offset,
+                SeekFrom::End(offset) => (self.claimed as i64 + offset) as u64,
+                SeekFrom::Current(offset) => (self.position as i64 + offset) as u64,
+            };
+            Ok(self.position)
+        }
+    }
+
+    #[test]
+    fn test_block_offsets() {
+        let offsets = block_offsets(1024 * BLOCK_SIZE);
+        assert_eq!(offsets.len(), SAMPLES as usize + 1);
+        assert_eq!(offsets[0], 0);
+        assert_eq!(offsets[1], 4 * BLOCK_SIZE);
+        assert_eq!(*offsets.last().unwrap(), 1023 * BLOCK_SIZE);
+        assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+
+        assert_eq!(
+            block_offsets(3 * BLOCK_SIZE + 5),
+            vec![0, BLOCK_SIZE, 2 * BLOCK_SIZE]
+        );
+        assert!(block_offsets(BLOCK_SIZE - 1).is_empty());
+    }
+
+    #[test]
+    fn test_genuine_device() {
+        let size = 512 * BLOCK_SIZE;
+        let mut disk = Wrapping::new(size, size);
+        let mut last_progress = 0.0;
+        let report = run(
+            &mut disk,
+            size,
+            |_| {},
+            &CancelToken::new(),
+            |p| last_progress = p,
+        )
+        .unwrap();
+        assert!(report.is_genuine());
+        assert_eq!(report.verified, size);
+        assert_eq!(last_progress, 1.0);
+    }
+
+    #[test]
+    fn test_wrapping_device_is_found() {
+        let (real, claimed) = (128 * BLOCK_SIZE, 1024 * BLOCK_SIZE);
+        let mut disk = Wrapping::new(real, claimed);
+        let report = run(&mut disk, claimed, |_| {}, &CancelToken::new(), |_| {}).unwrap();
+        assert!(!report.is_genuine());
+        assert_eq!(report.verified, real);
+        assert_eq!(report.bad_blocks, 224);
+        assert!(report.summary().contains("counterfeit"));
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let token = CancelToken::new();
+        token.cancel();
+        let mut disk = Wrapping::new(4 * BLOCK_SIZE, 4 * BLOCK_SIZE);
+        assert!(run(&mut disk, 4 * BLOCK_SIZE, |_| {}, &token, |_| {}).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 // Common disk operation functionality shared across platforms
 
+use serde::{Deserialize, Serialize};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 #[cfg(windows)]
 use tracing::error;
@@ -24,24 +25,264 @@ pub struct DiskDevice {
     pub model: String,
     /// Whether this disk is a system disk (contains OS)
     pub system: bool,
+    /// Serial number and other hardware identifiers that survive re-enumeration
+    pub identity: crate::disk::DeviceIdentity,
 }
 
-/// Progress message for disk write operations
-#[derive(Debug)]
-pub enum WriteProgress {
-    Start,
-    ClearingPartitions {
+/// Where the image written to the disk comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageSource {
+    /// Compressed image in the local cache or picked by the user
+    File(String),
+    /// Compressed image streamed over HTTPS straight to the disk, without a temp file
+    Url {
+        url: String,
+        /// SHA-256 of the compressed image, checked once the download finished
+        sha256: Option<String>,
+    },
+}
+
+impl std::fmt::Display for ImageSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageSource::File(path) => write!(f, "{}", path),
+            ImageSource::Url { url, .. } => write!(f, "{}", url),
+        }
+    }
+}
+
+/// A partition of an image, as described by the repository manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImagePartition {
+    /// Name shown while the partition is written, e.g. "rootfs"
+    pub name: String,
+    /// Byte offset of the partition in the uncompressed image
+    pub offset: u64,
+    /// Size of the partition in bytes
+    pub size: u64,
+}
+
+/// The image partition being written, e.g. "Writing rootfs (3/5)"
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionProgress {
+    pub name: String,
+    /// Position of the partition in the image, counting from 1
+    pub number: usize,
+    pub count: usize,
+}
+
+impl PartitionProgress {
+    /// The partition of `partitions` holding the image byte at `offset`
+    ///
+    /// Bytes between partitions, such as the partition table, belong to none.
+    pub fn at(partitions: &[ImagePartition], offset: u64) -> Option<Self> {
+        let index = partitions.iter().position(|partition| {
+            offset >= partition.offset && offset - partition.offset < partition.size
+        })?;
+        Some(Self {
+            name: partitions[index].name.clone(),
+            number: index + 1,
+            count: partitions.len(),
+        })
+    }
+}
+
+impl std::fmt::Display for PartitionProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Writing {} ({}/{})", self.name, self.number, self.count)
+    }
+}
+
+/// Phase of flashing a device, reported as progress while an image is written
+///
+/// Each phase carries its own measurements, so consumers can show phase-specific messages
+/// without inferring where the write is from the order of updates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlashPhase {
+    /// Opening the image and running the pre-write checks
+    Preparing,
+    /// Removing existing partitions before the device is locked for writing
+    Clearing {
         progress: f32,
+        /// Human-readable description of the current step
+        message: String,
     },
-    Write {
-        total_written: u64,
-        total_size: u64,
+    /// Copying the decompressed image to the device
+    Writing {
+        /// Decompressed bytes written so far
+        bytes: u64,
+        /// Uncompressed image size, unknown for images streamed without metadata
+        total: Option<u64>,
+        /// Average write speed in bytes per second
+        rate: u64,
+        /// Download progress when the image is streamed from a URL
+        download: Option<DownloadProgress>,
+        /// Partition being written, when the manifest describes the image's partitions
+        partition: Option<PartitionProgress>,
+        /// How long the device took to accept each chunk so far
+        latency: WriteLatency,
     },
+    /// Moving the backup GPT header to the end of the device
+    FixingGpt,
+    /// Writing the configuration partition
+    WritingConfig,
+    /// Reading the image back to check its hash
     Verifying {
-        verified_bytes: u64,
-        total_size: u64,
+        bytes: u64,
+        total: u64,
+        /// Average read speed in bytes per second
+        rate: u64,
     },
-    Finish,
+    /// The image was written and verified
+    Done,
+    /// The image was written, but the user skipped verifying it
+    Unverified,
+}
+
+/// Download side of an image streamed from a URL, which drives overall progress
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownloadProgress {
+    /// Compressed bytes received so far
+    pub downloaded: u64,
+    /// Compressed size reported by the server, if any
+    pub size: Option<u64>,
+}
+
+/// Time the device took per chunk written, to tell a slow card from a slow reader
+///
+/// A card that is slow across the board has close minimum and maximum times, while a bad
+/// reader or cable shows up as occasional chunks that take far longer than the rest.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WriteLatency {
+    /// Chunks written
+    pub chunks: u64,
+    pub min: std::time::Duration,
+    pub max: std::time::Duration,
+    /// Time spent writing all chunks, retries included
+    pub total: std::time::Duration,
+}
+
+impl WriteLatency {
+    /// Count a chunk that took `elapsed` to write
+    pub fn record(&mut self, elapsed: std::time::Duration) {
+        self.min = if self.chunks == 0 {
+            elapsed
+        } else {
+            self.min.min(elapsed)
+        };
+        self.max = self.max.max(elapsed);
+        self.total += elapsed;
+        self.chunks += 1;
+    }
+
+    /// Mean time per chunk, None before the first chunk
+    pub fn average(&self) -> Option<std::time::Duration> {
+        (self.chunks > 0).then(|| self.total / self.chunks as u32)
+    }
+}
+
+impl std::fmt::Display for WriteLatency {
+    /// e.g. "min 1.2 ms, avg 3.4 ms, max 120.5 ms over 512 chunks"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ms = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+        match self.average() {
+            Some(average) => write!(
+                f,
+                "min {:.1} ms, avg {:.1} ms, max {:.1} ms over {} chunks",
+                ms(self.min),
+                ms(average),
+                ms(self.max),
+                self.chunks
+            ),
+            None => write!(f, "no chunks written yet"),
+        }
+    }
+}
+
+impl FlashPhase {
+    /// Progress within the current phase from 0.0 to 1.0, if it can be measured
+    pub fn fraction(&self) -> Option<f32> {
+        let ratio = |done: u64, total: u64| (done as f32 / total as f32).min(1.0);
+        match self {
+            FlashPhase::Clearing { progress, .. } => Some(*progress),
+            FlashPhase::Writing {
+                download: Some(download),
+                ..
+            } => download
+                .size
+                .filter(|&size| size > 0)
+                .map(|size| ratio(download.downloaded, size)),
+            FlashPhase::Writing { bytes, total, .. } => total
+                .filter(|&total| total > 0)
+                .map(|total| ratio(*bytes, total)),
+            FlashPhase::Verifying { bytes, total, .. } if *total > 0 => Some(ratio(*bytes, *total)),
+            FlashPhase::Done | FlashPhase::Unverified => Some(1.0),
+            _ => None,
+        }
+    }
+
+    /// One-line status for the phase, e.g. "1.2 GB of 8.0 GB written (45.3 MB/s)"
+    pub fn description(&self) -> String {
+        use crate::disk::layout::format_size;
+
+        let with_rate = |status: String, rate: u64| {
+            if rate > 0 {
+                format!("{} ({}/s)", status, format_size(rate))
+            } else {
+                status
+            }
+        };
+        match self {
+            FlashPhase::Preparing => "Preparing device...".to_string(),
+            FlashPhase::Clearing { message, .. } => message.clone(),
+            FlashPhase::Writing {
+                bytes,
+                rate,
+                download: Some(download),
+                ..
+            } => with_rate(
+                format!(
+                    "{} downloaded, {} written",
+                    format_size(download.downloaded),
+                    format_size(*bytes)
+                ),
+                *rate,
+            ),
+            FlashPhase::Writing {
+                bytes, total, rate, ..
+            } => {
+                let status = match total {
+                    Some(total) => {
+                        format!("{} of {} written", format_size(*bytes), format_size(*total))
+                    }
+                    None => format!("{} written", format_size(*bytes)),
+                };
+                with_rate(status, *rate)
+            }
+            FlashPhase::FixingGpt => "Fixing partition table...".to_string(),
+            FlashPhase::WritingConfig => "Writing configuration...".to_string(),
+            FlashPhase::Verifying { bytes, total, rate } => with_rate(
+                format!(
+                    "{} of {} verified",
+                    format_size(*bytes),
+                    format_size(*total)
+                ),
+                *rate,
+            ),
+            FlashPhase::Done => "Done".to_string(),
+            FlashPhase::Unverified => "Written, not verified".to_string(),
+        }
+    }
+}
+
+/// Average speed of a transfer of `bytes` that began at `started`
+pub fn bytes_per_second(bytes: u64, started: std::time::Instant) -> u64 {
+    let elapsed = started.elapsed().as_secs_f64();
+    if elapsed > 0.0 {
+        (bytes as f64 / elapsed) as u64
+    } else {
+        0
+    }
 }
 
 /// Proxy for accessing a specific partition on a disk
@@ -532,3 +773,113 @@ impl<T: Read + Write + Seek> Seek for PartitionFileProxy<T> {
         Ok(new_position)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flash_phase_fraction() {
+        let writing = FlashPhase::Writing {
+            bytes: 256,
+            total: Some(1024),
+            rate: 0,
+            download: None,
+            partition: None,
+            latency: WriteLatency::default(),
+        };
+        assert_eq!(writing.fraction(), Some(0.25));
+
+        // A streamed image is as far along as its download
+        let streaming = FlashPhase::Writing {
+            bytes: 900,
+            total: None,
+            rate: 0,
+            download: Some(DownloadProgress {
+                downloaded: 50,
+                size: Some(100),
+            }),
+            partition: None,
+            latency: WriteLatency::default(),
+        };
+        assert_eq!(streaming.fraction(), Some(0.5));
+
+        let unknown = FlashPhase::Writing {
+            bytes: 900,
+            total: None,
+            rate: 0,
+            download: None,
+            partition: None,
+            latency: WriteLatency::default(),
+        };
+        assert_eq!(unknown.fraction(), None);
+        assert_eq!(FlashPhase::FixingGpt.fraction(), None);
+        assert_eq!(FlashPhase::Done.fraction(), Some(1.0));
+    }
+
+    #[test]
+    fn test_partition_progress() {
+        let partitions = [
+            ImagePartition {
+                name: "efi".to_string(),
+                offset: 1024,
+                size: 4096,
+            },
+            ImagePartition {
+                name: "rootfs".to_string(),
+                offset: 8192,
+                size: 8192,
+            },
+        ];
+
+        // The partition table comes before the first partition
+        assert_eq!(PartitionProgress::at(&partitions, 0), None);
+        assert_eq!(
+            PartitionProgress::at(&partitions, 10000).map(|p| p.to_string()),
+            Some("Writing rootfs (2/2)".to_string())
+        );
+        assert_eq!(PartitionProgress::at(&partitions, 1024).unwrap().number, 1);
+        assert_eq!(PartitionProgress::at(&partitions, 5120), None);
+        assert_eq!(PartitionProgress::at(&[], 5120), None);
+    }
+
+    #[test]
+    fn test_flash_phase_description() {
+        let writing = FlashPhase::Writing {
+            bytes: 512 * 1024 * 1024,
+            total: Some(2 * 1024 * 1024 * 1024),
+            rate: 0,
+            download: None,
+            partition: None,
+            latency: WriteLatency::default(),
+        };
+        assert_eq!(writing.description(), "512.0 MB of 2.0 GB written");
+
+        let verifying = FlashPhase::Verifying {
+            bytes: 1024,
+            total: 2048,
+            rate: 1024 * 1024,
+        };
+        assert!(verifying.description().ends_with("verified (1.0 MB/s)"));
+    }
+
+    #[test]
+    fn test_write_latency() {
+        use std::time::Duration;
+
+        let mut latency = WriteLatency::default();
+        assert_eq!(latency.average(), None);
+
+        latency.record(Duration::from_millis(4));
+        latency.record(Duration::from_millis(2));
+        latency.record(Duration::from_millis(12));
+        assert_eq!(latency.chunks, 3);
+        assert_eq!(latency.min, Duration::from_millis(2));
+        assert_eq!(latency.max, Duration::from_millis(12));
+        assert_eq!(latency.average(), Some(Duration::from_millis(6)));
+        assert_eq!(
+            latency.to_string(),
+            "min 2.0 ms, avg 6.0 ms, max 12.0 ms over 3 chunks"
+        );
+    }
+}
@@ -0,0 +1,571 @@
+/// Configuration settings understood by the different Golem image generations
+///
+/// Golem images changed their configuration keys over time: first-generation images don't
+/// know about node names, configuration servers, central network hosts or metrics, and read
+/// the payment network from `YA_PAYMENT_NETWORK`. Each image in the repository manifest can
+/// name the schema it expects, and the manifest can define schemas of its own so images
+/// newer than the imager are still configured correctly. Images that don't name one get the
+/// current schema, which has every field.
+use super::EnvFile;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+/// Id of the schema of current images
+pub const CURRENT_SCHEMA: &str = "current";
+
+/// Id of the schema of first-generation images
+pub const LEGACY_SCHEMA: &str = "legacy";
+
+/// golem.env variables first-generation images read under another name, as
+/// `(current name, legacy name)`
+const LEGACY_ENV_KEYS: [(&str, &str); 1] = [("YA_PAYMENT_NETWORK_GROUP", "YA_PAYMENT_NETWORK")];
+
+/// A setting of the configuration editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigField {
+    GlmAccount,
+    GlmPerHour,
+    GlmNodeName,
+    NonInteractiveInstall,
+    SshKeys,
+    ConfigurationServer,
+    NetworkType,
+    Subnet,
+    PaymentNetwork,
+    CentralNetHost,
+    Metrics,
+}
+
+impl ConfigField {
+    pub const ALL: [ConfigField; 11] = [
+        ConfigField::GlmAccount,
+        ConfigField::GlmPerHour,
+        ConfigField::GlmNodeName,
+        ConfigField::NonInteractiveInstall,
+        ConfigField::SshKeys,
+        ConfigField::ConfigurationServer,
+        ConfigField::NetworkType,
+        ConfigField::Subnet,
+        ConfigField::PaymentNetwork,
+        ConfigField::CentralNetHost,
+        ConfigField::Metrics,
+    ];
+
+    /// Label, help text and expected format of the setting, shared by the editors
+    pub fn help(self) -> FieldHelp {
+        match self {
+            ConfigField::GlmAccount => FieldHelp {
+                label: "Wallet Address",
+                description: "Ethereum address the node's earnings are paid to. Leave empty to use the node's default wallet",
+                optional: true,
+                pattern: Some(r"^0x[0-9a-fA-F]{40}$"),
+                examples: &["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"],
+            },
+            ConfigField::GlmPerHour => FieldHelp {
+                label: "Price per Hour",
+                description: "GLM the node asks for an hour of GPU work",
+                optional: true,
+                pattern: Some(r"^[0-9]+(\.[0-9]+)?$"),
+                examples: &["0.25", "1"],
+            },
+            ConfigField::GlmNodeName => FieldHelp {
+                label: "Node Name",
+                description: "Name the provider node shows on the network - leave empty to let it pick one",
+                optional: true,
+                pattern: None,
+                examples: &["gpu-{index:3}", "rig-{serial}"],
+            },
+            ConfigField::NonInteractiveInstall => FieldHelp {
+                label: "Non-Interactive Mode (Headless)",
+                description: "First OS start will not ask anything - will select available GPUs and data partition without user interaction",
+                optional: false,
+                pattern: None,
+                examples: &[],
+            },
+            ConfigField::SshKeys => FieldHelp {
+                label: "SSH Public Keys",
+                description: "Public keys in OpenSSH format for user 'golem' - leave empty if not needed",
+                optional: true,
+                pattern: Some(
+                    r"^(ssh-rsa|ssh-dss|ssh-ed25519|ecdsa-sha2-nistp(256|384|521)) [A-Za-z0-9+/=]{50,}( .*)?$",
+                ),
+                examples: &[
+                    "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIG4rT3vTt99Ox5kndS4HmgTrKBT8SKzhK4rhGkEVGlCI user@example.com",
+                ],
+            },
+            ConfigField::ConfigurationServer => FieldHelp {
+                label: "Configuration Server",
+                description: "URL to server where to look for configuration updates",
+                optional: true,
+                pattern: Some(r"^https?://[^\s]+\.[^\s]+$"),
+                examples: &["https://config.example.com/golem"],
+            },
+            ConfigField::NetworkType => FieldHelp {
+                label: "Network Type",
+                description: "How the node reaches other nodes of the Golem Network",
+                optional: false,
+                pattern: None,
+                examples: &[],
+            },
+            ConfigField::Subnet => FieldHelp {
+                label: "Subnet",
+                description: "Specify which subnet to connect to on the Golem Network",
+                optional: false,
+                pattern: Some(r"^[A-Za-z0-9._{}:-]{1,64}$"),
+                examples: &["public", "farm-{date}"],
+            },
+            ConfigField::PaymentNetwork => FieldHelp {
+                label: "Payment Network",
+                description: "Whether the node is paid in testnet or real GLM tokens",
+                optional: false,
+                pattern: None,
+                examples: &[],
+            },
+            ConfigField::CentralNetHost => FieldHelp {
+                label: "Central Net Host",
+                description: "Central network coordination server address (leave empty by default)",
+                optional: true,
+                pattern: Some(r"^(([0-9a-z]{56})@)?([^:]*)(:[0-9]{1,4})?$"),
+                examples: &[
+                    "18.185.178.4:7464",
+                    "393479950594e7c676ba121033a677a1316f722460827e217c82d2b3@18.185.178.4:7464",
+                ],
+            },
+            ConfigField::Metrics => FieldHelp {
+                label: "Metrics Server",
+                description: "URL to metrics server push endpoint",
+                optional: true,
+                pattern: Some(r"^https?://[^\s]+\.[^\s]+$"),
+                examples: &["https://metrics.golem.network:9092/"],
+            },
+        }
+    }
+
+    /// Name of the setting in schemas and provisioning files, as it is serialized
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfigField::GlmAccount => "glm_account",
+            ConfigField::GlmPerHour => "glm_per_hour",
+            ConfigField::GlmNodeName => "glm_node_name",
+            ConfigField::NonInteractiveInstall => "non_interactive_install",
+            ConfigField::SshKeys => "ssh_keys",
+            ConfigField::ConfigurationServer => "configuration_server",
+            ConfigField::NetworkType => "network_type",
+            ConfigField::Subnet => "subnet",
+            ConfigField::PaymentNetwork => "payment_network",
+            ConfigField::CentralNetHost => "central_net_host",
+            ConfigField::Metrics => "metrics",
+        }
+    }
+
+    /// Top-level golemwz.toml keys holding the setting
+    fn toml_keys(self) -> &'static [&'static str] {
+        match self {
+            ConfigField::GlmAccount => &["glm_account"],
+            ConfigField::GlmPerHour => &["glm_per_hour"],
+            ConfigField::GlmNodeName => &["glm_node_name"],
+            ConfigField::NonInteractiveInstall => &["non_interactive_install"],
+            ConfigField::SshKeys => &["ssh_keys"],
+            ConfigField::ConfigurationServer => &["configuration_server"],
+            _ => &[],
+        }
+    }
+
+    /// golem.env variables holding the setting, also written to the `[env]` table
+    fn env_keys(self) -> &'static [&'static str] {
+        match self {
+            ConfigField::NetworkType => &["YA_NET_TYPE"],
+            ConfigField::Subnet => &["SUBNET"],
+            ConfigField::PaymentNetwork => &["YA_PAYMENT_NETWORK_GROUP"],
+            ConfigField::CentralNetHost => &["CENTRAL_NET_HOST"],
+            ConfigField::Metrics => &[
+                "YAGNA_METRICS_URL",
+                "YAGNA_METRICS_JOB_NAME",
+                "YAGNA_METRICS_GROUP",
+            ],
+            _ => &[],
+        }
+    }
+}
+
+/// How a setting is presented in the configuration editors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldHelp {
+    pub label: &'static str,
+    /// Shown below the field and in its tooltip
+    pub description: &'static str,
+    /// Whether the setting may be left empty
+    pub optional: bool,
+    /// Regex values of the setting match, checked on top of the field's own validation
+    pub pattern: Option<&'static str>,
+    pub examples: &'static [&'static str],
+}
+
+impl FieldHelp {
+    /// Whether `value` has the expected format; fields without a pattern take any value
+    pub fn matches(&self, value: &str) -> bool {
+        self.pattern.is_none_or(|pattern| {
+            regex::Regex::new(pattern)
+                .map(|re| re.is_match(value))
+                .unwrap_or(false)
+        })
+    }
+}
+
+fn all_fields() -> Vec<ConfigField> {
+    ConfigField::ALL.to_vec()
+}
+
+/// The settings an image understands and the names it reads them under
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigSchema {
+    pub id: String,
+    /// Shown in the configuration editor, e.g. "Images released before 2024"
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "all_fields")]
+    pub fields: Vec<ConfigField>,
+    /// golem.env variables the image reads under another name, current name to written name
+    #[serde(default)]
+    pub env_keys: BTreeMap<String, String>,
+}
+
+static BUNDLED: LazyLock<Vec<ConfigSchema>> = LazyLock::new(|| {
+    vec![
+        ConfigSchema {
+            id: CURRENT_SCHEMA.to_string(),
+            description: String::new(),
+            fields: all_fields(),
+            env_keys: BTreeMap::new(),
+        },
+        ConfigSchema {
+            id: LEGACY_SCHEMA.to_string(),
+            description: "First-generation Golem GPU images".to_string(),
+            fields: vec![
+                ConfigField::GlmAccount,
+                ConfigField::GlmPerHour,
+                ConfigField::NonInteractiveInstall,
+                ConfigField::SshKeys,
+                ConfigField::NetworkType,
+                ConfigField::Subnet,
+                ConfigField::PaymentNetwork,
+            ],
+            env_keys: LEGACY_ENV_KEYS
+                .iter()
+                .map(|(current, legacy)| (current.to_string(), legacy.to_string()))
+                .collect(),
+        },
+    ]
+});
+
+impl Default for ConfigSchema {
+    fn default() -> Self {
+        Self::current().clone()
+    }
+}
+
+impl ConfigSchema {
+    /// The schemas shipped with the imager
+    pub fn bundled() -> &'static [ConfigSchema] {
+        &BUNDLED
+    }
+
+    /// The schema of current images, with every field
+    pub fn current() -> &'static ConfigSchema {
+        &BUNDLED[0]
+    }
+
+    /// The schema called `id`
+    ///
+    /// Schemas from the manifest take precedence over the bundled ones. Images without a
+    /// schema, or with one this imager doesn't know, get the current schema.
+    pub fn resolve(id: Option<&str>, manifest: &[ConfigSchema]) -> ConfigSchema {
+        let Some(id) = id else {
+            return Self::current().clone();
+        };
+        match manifest
+            .iter()
+            .chain(Self::bundled())
+            .find(|schema| schema.id == id)
+        {
+            Some(schema) => schema.clone(),
+            None => {
+                tracing::warn!("Unknown configuration schema {}, using the current one", id);
+                Self::current().clone()
+            }
+        }
+    }
+
+    /// Whether the image understands `field`
+    pub fn supports(&self, field: ConfigField) -> bool {
+        self.fields.contains(&field)
+    }
+
+    /// Whether some settings of the editor are not understood by the image
+    pub fn hides_fields(&self) -> bool {
+        ConfigField::ALL.iter().any(|field| !self.supports(*field))
+    }
+
+    /// Whether files are written as for current images
+    pub(super) fn is_complete(&self) -> bool {
+        !self.hides_fields() && self.env_keys.is_empty()
+    }
+
+    /// Whether a managed golemwz.toml key or golem.env variable is written for the image
+    ///
+    /// Keys that don't belong to a field, like `accepted_terms`, are always written.
+    pub fn writes_key(&self, key: &str) -> bool {
+        ConfigField::ALL
+            .iter()
+            .find(|field| field.toml_keys().contains(&key) || field.env_keys().contains(&key))
+            .is_none_or(|field| self.supports(*field))
+    }
+
+    /// Name the image reads the golem.env variable `key` under
+    fn written_env_key<'a>(&'a self, key: &'a str) -> &'a str {
+        self.env_keys.get(key).map_or(key, String::as_str)
+    }
+
+    /// Current name of a golem.env variable as written for this schema
+    ///
+    /// The names of first-generation images are always recognized, as the schema of a
+    /// device being edited isn't known.
+    pub fn canonical_env_key<'a>(&'a self, key: &'a str) -> &'a str {
+        self.env_keys
+            .iter()
+            .find(|(_, written)| written.as_str() == key)
+            .map(|(current, _)| current.as_str())
+            .or_else(|| {
+                LEGACY_ENV_KEYS
+                    .iter()
+                    .find(|(_, legacy)| *legacy == key)
+                    .map(|(current, _)| *current)
+            })
+            .unwrap_or(key)
+    }
+
+    /// Drop the settings the image doesn't understand from golem.env and rename the rest
+    pub(super) fn restrict_env(&self, env: &mut EnvFile) {
+        if self.is_complete() {
+            return;
+        }
+        for field in ConfigField::ALL {
+            for key in field.env_keys() {
+                let written = self.written_env_key(key);
+                if !self.supports(field) {
+                    env.remove(key);
+                    env.remove(written);
+                    continue;
+                }
+                if written == *key {
+                    continue;
+                }
+                if let Some(value) = env.get(key).map(str::to_string) {
+                    env.remove(key);
+                    env.set(written, &value);
+                }
+            }
+        }
+    }
+
+    /// Drop the settings the image doesn't understand from golemwz.toml and rename the
+    /// variables in its `[env]` table
+    pub(super) fn restrict_toml(&self, doc: &mut toml_edit::DocumentMut) {
+        if self.is_complete() {
+            return;
+        }
+        for field in ConfigField::ALL.into_iter().filter(|f| !self.supports(*f)) {
+            for key in field.toml_keys() {
+                doc.remove(key);
+            }
+        }
+        let Some(env) = doc.get_mut("env").and_then(|item| item.as_table_like_mut()) else {
+            return;
+        };
+        for field in ConfigField::ALL {
+            for key in field.env_keys() {
+                let written = self.written_env_key(key);
+                if !self.supports(field) {
+                    env.remove(key);
+                    env.remove(written);
+                    continue;
+                }
+                if written == *key {
+                    continue;
+                }
+                if let Some(value) = env.remove(key) {
+                    env.insert(written, value);
+                }
+            }
+        }
+    }
+
+    /// golem.env content with the variables written for this schema under their current names
+    pub(super) fn canonical_env(&self, content: &str) -> String {
+        let mut env = EnvFile::parse(content);
+        let renames = self.renames(env.entries().map(|(key, _)| key));
+        if renames.is_empty() {
+            return content.to_string();
+        }
+        for (written, current) in renames {
+            if let Some(value) = env.get(&written).map(str::to_string) {
+                env.remove(&written);
+                env.set(&current, &value);
+            }
+        }
+        env.to_content()
+    }
+
+    /// golemwz.toml content with the `[env]` variables written for this schema under their
+    /// current names; content that isn't valid TOML is returned as-is
+    pub(super) fn canonical_toml(&self, content: &str) -> String {
+        let Ok(mut doc) = content.parse::<toml_edit::DocumentMut>() else {
+            return content.to_string();
+        };
+        let Some(env) = doc.get_mut("env").and_then(|item| item.as_table_like_mut()) else {
+            return content.to_string();
+        };
+        let renames = self.renames(env.iter().map(|(key, _)| key));
+        if renames.is_empty() {
+            return content.to_string();
+        }
+        for (written, current) in renames {
+            if let Some(value) = env.remove(&written) {
+                env.insert(&current, value);
+            }
+        }
+        doc.to_string()
+    }
+
+    /// `(written name, current name)` of the variables among `keys` to rename, skipping
+    /// those whose current name is also present
+    fn renames<'a>(&self, keys: impl Iterator<Item = &'a str>) -> Vec<(String, String)> {
+        let keys: Vec<&str> = keys.collect();
+        keys.iter()
+            .map(|key| (*key, self.canonical_env_key(key)))
+            .filter(|(key, current)| key != current && !keys.contains(current))
+            .map(|(key, current)| (key.to_string(), current.to_string()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy() -> &'static ConfigSchema {
+        ConfigSchema::bundled()
+            .iter()
+            .find(|schema| schema.id == LEGACY_SCHEMA)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_prefers_manifest_schemas() {
+        let manifest: Vec<ConfigSchema> = serde_json::from_str(
+            r#"[{"id": "legacy", "fields": ["glm_account", "subnet"]}, {"id": "gen3"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ConfigSchema::resolve(None, &manifest),
+            *ConfigSchema::current()
+        );
+        assert_eq!(
+            ConfigSchema::resolve(Some("legacy"), &manifest).fields,
+            vec![ConfigField::GlmAccount, ConfigField::Subnet]
+        );
+        // A schema without a field list has every field
+        assert!(!ConfigSchema::resolve(Some("gen3"), &manifest).hides_fields());
+        assert_eq!(
+            ConfigSchema::resolve(Some("unknown"), &[]),
+            *ConfigSchema::current()
+        );
+        assert_eq!(ConfigSchema::resolve(Some("legacy"), &[]), *legacy());
+    }
+
+    #[test]
+    fn test_field_help_examples_match() {
+        for field in ConfigField::ALL {
+            let help = field.help();
+            if let Some(pattern) = help.pattern {
+                assert!(regex::Regex::new(pattern).is_ok(), "{:?}", field);
+            }
+            for example in help.examples {
+                assert!(help.matches(example), "{:?}: {}", field, example);
+            }
+        }
+        assert!(!ConfigField::GlmAccount.help().matches("0x123"));
+        assert!(!ConfigField::Subnet.help().matches("my subnet"));
+        assert!(ConfigField::NetworkType.help().matches("anything"));
+    }
+
+    #[test]
+    fn test_field_names_are_serialized_names() {
+        for field in ConfigField::ALL {
+            assert_eq!(
+                serde_json::to_value(field).unwrap(),
+                serde_json::Value::String(field.name().to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_writes_key() {
+        let schema = legacy();
+        assert!(schema.writes_key("accepted_terms"));
+        assert!(schema.writes_key("glm_account"));
+        assert!(schema.writes_key("YA_PAYMENT_NETWORK_GROUP"));
+        assert!(!schema.writes_key("glm_node_name"));
+        assert!(!schema.writes_key("YAGNA_METRICS_GROUP"));
+        assert!(ConfigSchema::current().writes_key("glm_node_name"));
+    }
+
+    #[test]
+    fn test_restrict_env() {
+        let mut env = EnvFile::parse(
+            "YA_NET_TYPE=central\nYA_PAYMENT_NETWORK_GROUP=mainnet\nYAGNA_METRICS_URL=https://m/\nYA_DEBUG=1\n",
+        );
+        legacy().restrict_env(&mut env);
+
+        assert_eq!(env.get("YA_PAYMENT_NETWORK"), Some("mainnet"));
+        assert_eq!(env.get("YA_PAYMENT_NETWORK_GROUP"), None);
+        assert_eq!(env.get("YAGNA_METRICS_URL"), None);
+        // Variables the imager doesn't manage are left alone
+        assert_eq!(env.get("YA_DEBUG"), Some("1"));
+        assert_eq!(env.get("YA_NET_TYPE"), Some("central"));
+    }
+
+    #[test]
+    fn test_restrict_toml() {
+        let mut doc: toml_edit::DocumentMut = "glm_account = \"0x1\"\nglm_node_name = \"a\"\n\n[env]\nYA_PAYMENT_NETWORK_GROUP = \"testnet\"\nCENTRAL_NET_HOST = \"h:1\"\n"
+            .parse()
+            .unwrap();
+        legacy().restrict_toml(&mut doc);
+
+        assert!(doc.get("glm_node_name").is_none());
+        assert_eq!(doc["glm_account"].as_str(), Some("0x1"));
+        assert_eq!(doc["env"]["YA_PAYMENT_NETWORK"].as_str(), Some("testnet"));
+        assert!(doc["env"].get("YA_PAYMENT_NETWORK_GROUP").is_none());
+        assert!(doc["env"].get("CENTRAL_NET_HOST").is_none());
+    }
+
+    #[test]
+    fn test_canonical_env_key() {
+        let custom = ConfigSchema {
+            env_keys: BTreeMap::from([("SUBNET".to_string(), "YA_SUBNET".to_string())]),
+            ..ConfigSchema::current().clone()
+        };
+        assert_eq!(custom.canonical_env_key("YA_SUBNET"), "SUBNET");
+        assert_eq!(
+            ConfigSchema::current().canonical_env_key("YA_PAYMENT_NETWORK"),
+            "YA_PAYMENT_NETWORK_GROUP"
+        );
+        assert_eq!(
+            ConfigSchema::current().canonical_env_key("YA_DEBUG"),
+            "YA_DEBUG"
+        );
+    }
+}
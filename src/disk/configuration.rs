@@ -1,6 +1,31 @@
 /// Configuration for image writing and partition setup
+use super::ConfigSchema;
+use crate::models::{DeviceConfig, DeviceEnv, ExtraSetting, FirstBootFile};
 use anyhow::Result;
 
+/// Top-level golemwz.toml keys written by the imager itself
+pub const MANAGED_TOML_KEYS: [&str; 8] = [
+    "accepted_terms",
+    "glm_account",
+    "glm_per_hour",
+    "glm_node_name",
+    "non_interactive_install",
+    "ssh_keys",
+    "configuration_server",
+    "env",
+];
+
+/// golem.env variables written by the imager itself
+pub const MANAGED_ENV_KEYS: [&str; 7] = [
+    "YA_NET_TYPE",
+    "SUBNET",
+    "YA_PAYMENT_NETWORK_GROUP",
+    "CENTRAL_NET_HOST",
+    "YAGNA_METRICS_URL",
+    "YAGNA_METRICS_JOB_NAME",
+    "YAGNA_METRICS_GROUP",
+];
+
 #[derive(Debug, Clone)]
 pub struct ImageConfiguration {
     // Main TOML configuration fields
@@ -21,8 +46,18 @@ pub struct ImageConfiguration {
     pub metrics_job_name: Option<String>,
     pub metrics_group: Option<String>,
     
+    // Settings without a dedicated field, for Golem options newer than the imager
+    pub extra_env: Vec<ExtraSetting>,
+    pub extra_toml: Vec<ExtraSetting>,
+    
+    // Provisioning file copied next to the configuration files
+    pub firstboot: Option<FirstBootFile>,
+    
     // Raw server TOML content to preserve original formatting
     pub server_toml_content: Option<String>,
+
+    // Settings and key names understood by the image the files are written for
+    pub schema: ConfigSchema,
 }
 
 impl ImageConfiguration {
@@ -60,7 +95,11 @@ impl ImageConfiguration {
             metrics_server: None,
             metrics_job_name: None,
             metrics_group: None,
+            extra_env: Vec::new(),
+            extra_toml: Vec::new(),
+            firstboot: None,
             server_toml_content: None,
+            schema: ConfigSchema::default(),
         }
     }
 
@@ -120,7 +159,11 @@ impl ImageConfiguration {
             },
             metrics_job_name: None,
             metrics_group: None,
+            extra_env: Vec::new(),
+            extra_toml: Vec::new(),
+            firstboot: None,
             server_toml_content: None,
+            schema: ConfigSchema::default(),
         }
     }
 
@@ -168,201 +211,116 @@ impl ImageConfiguration {
             },
             metrics_job_name: None,
             metrics_group: None,
+            extra_env: Vec::new(),
+            extra_toml: Vec::new(),
+            firstboot: None,
             server_toml_content: None,
+            schema: ConfigSchema::default(),
         }
     }
 
     /// Parse configuration from golemwz.toml content with [env] section support
+    ///
+    /// Variables first-generation images read under another name are read as the setting.
     pub fn from_toml_content(content: &str) -> Result<Self> {
-        use toml::Value;
-        
-        let parsed: Value = toml::from_str(content)
+        let content = ConfigSchema::current().canonical_toml(content);
+        let device: DeviceConfig = toml::from_str(&content)
             .map_err(|e| anyhow::anyhow!("Failed to parse TOML: {}", e))?;
-        
-        let mut config = Self::default();
-        
-        // Parse main configuration fields
-        if let Some(accepted_terms) = parsed.get("accepted_terms") {
-            if let Some(terms_bool) = accepted_terms.as_bool() {
-                config.accepted_terms = terms_bool;
-            }
-        }
-        
-        if let Some(glm_account) = parsed.get("glm_account") {
-            if let Some(account_str) = glm_account.as_str() {
-                config.glm_account = account_str.to_string();
-            }
-        }
-        
-        if let Some(glm_per_hour) = parsed.get("glm_per_hour") {
-            if let Some(rate_str) = glm_per_hour.as_str() {
-                config.glm_per_hour = rate_str.to_string();
-            }
-        }
-        
-        if let Some(glm_node_name) = parsed.get("glm_node_name") {
-            if let Some(name_str) = glm_node_name.as_str() {
-                config.glm_node_name = Some(name_str.to_string());
-            }
-        }
-        
-        if let Some(non_interactive) = parsed.get("non_interactive_install") {
-            if let Some(interactive_bool) = non_interactive.as_bool() {
-                config.non_interactive_install = interactive_bool;
-            }
-        }
-        
-        if let Some(ssh_keys) = parsed.get("ssh_keys") {
-            if let Some(keys_array) = ssh_keys.as_array() {
-                config.ssh_keys = keys_array
-                    .iter()
-                    .filter_map(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .collect();
-            }
+        // The settings without a field, and which variables were written at all
+        let parsed: toml::Table = toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse TOML: {}", e))?;
+        let env_table = parsed.get("env").and_then(toml::Value::as_table);
+
+        let mut config = Self {
+            accepted_terms: device.accepted_terms,
+            glm_account: device.glm_account,
+            glm_per_hour: device.glm_per_hour,
+            glm_node_name: device.glm_node_name,
+            non_interactive_install: device.non_interactive_install,
+            ssh_keys: device.ssh_keys,
+            configuration_server: device.configuration_server.filter(|server| !server.trim().is_empty()),
+            ..Self::default()
+        };
+        config.set_device_env(device.env, |key| env_table.is_some_and(|env| env.contains_key(key)));
+
+        if let Some(env_table) = env_table {
+            config.extra_env = env_table
+                .iter()
+                .filter(|(key, _)| !MANAGED_ENV_KEYS.contains(&key.as_str()))
+                .filter_map(|(key, value)| {
+                    Some(ExtraSetting { key: key.clone(), value: value.as_str()?.to_string() })
+                })
+                .collect();
         }
         
-        if let Some(config_server) = parsed.get("configuration_server") {
-            if let Some(server_str) = config_server.as_str() {
-                if !server_str.trim().is_empty() {
-                    config.configuration_server = Some(server_str.to_string());
-                }
-            }
-        }
-        
-        // Parse [env] section for environment variables
-        if let Some(env_section) = parsed.get("env") {
-            if let Some(env_table) = env_section.as_table() {
-                // Network configuration
-                if let Some(net_type) = env_table.get("YA_NET_TYPE") {
-                    if let Some(type_str) = net_type.as_str() {
-                        config.network_type = match type_str.to_lowercase().as_str() {
-                            "hybrid" => crate::models::NetworkType::Hybrid,
-                            _ => crate::models::NetworkType::Central,
-                        };
-                    }
-                }
-                
-                if let Some(subnet) = env_table.get("SUBNET") {
-                    if let Some(subnet_str) = subnet.as_str() {
-                        config.subnet = subnet_str.to_string();
-                    }
-                }
-                
-                if let Some(payment_net) = env_table.get("YA_PAYMENT_NETWORK_GROUP") {
-                    if let Some(payment_str) = payment_net.as_str() {
-                        config.payment_network = match payment_str.to_lowercase().as_str() {
-                            "mainnet" => crate::models::PaymentNetwork::Mainnet,
-                            _ => crate::models::PaymentNetwork::Testnet,
-                        };
-                    }
-                }
-                
-                if let Some(central_host) = env_table.get("CENTRAL_NET_HOST") {
-                    if let Some(host_str) = central_host.as_str() {
-                        if !host_str.trim().is_empty() {
-                            config.central_net_host = Some(host_str.to_string());
-                        }
-                    }
-                }
-                
-                // Metrics configuration
-                if let Some(metrics_url) = env_table.get("YAGNA_METRICS_URL") {
-                    if let Some(url_str) = metrics_url.as_str() {
-                        if !url_str.trim().is_empty() {
-                            config.metrics_server = Some(url_str.to_string());
-                        }
-                    }
-                }
-                
-                if let Some(job_name) = env_table.get("YAGNA_METRICS_JOB_NAME") {
-                    if let Some(name_str) = job_name.as_str() {
-                        if !name_str.trim().is_empty() {
-                            config.metrics_job_name = Some(name_str.to_string());
-                        }
-                    }
-                }
-                
-                if let Some(group) = env_table.get("YAGNA_METRICS_GROUP") {
-                    if let Some(group_str) = group.as_str() {
-                        if !group_str.trim().is_empty() {
-                            config.metrics_group = Some(group_str.to_string());
-                        }
-                    }
-                }
-            }
-        }
+        // Everything else at the top level, as TOML, so extra settings can be checked
+        config.extra_toml = parsed
+            .iter()
+            .filter(|(key, value)| !MANAGED_TOML_KEYS.contains(&key.as_str()) && !value.is_table())
+            .map(|(key, value)| ExtraSetting { key: key.clone(), value: value.to_string() })
+            .collect();
         
         Ok(config)
     }
     
     /// Parse configuration from ENV content
+    ///
+    /// Variables first-generation images read under another name are read as the setting.
     pub fn from_env_content(content: &str) -> Result<Self> {
+        let env = super::EnvFile::parse(&ConfigSchema::current().canonical_env(content));
+        // golem.env holds the same variables as the [env] table of golemwz.toml
+        let table: toml::Table = env
+            .entries()
+            .map(|(key, value)| (key.to_string(), toml::Value::String(value.to_string())))
+            .collect();
+        let device_env: DeviceEnv = toml::Value::Table(table.clone())
+            .try_into()
+            .map_err(|e| anyhow::anyhow!("Failed to parse golem.env: {}", e))?;
+
         let mut config = Self::default();
-        
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
-            
-            if let Some((key, value)) = line.split_once('=') {
-                let key = key.trim();
-                let value = value.trim();
-                
-                match key {
-                    "YA_NET_TYPE" => {
-                        config.network_type = match value.to_lowercase().as_str() {
-                            "hybrid" => crate::models::NetworkType::Hybrid,
-                            _ => crate::models::NetworkType::Central,
-                        };
-                    }
-                    "SUBNET" => {
-                        config.subnet = value.to_string();
-                    }
-                    "YA_PAYMENT_NETWORK_GROUP" => {
-                        config.payment_network = match value.to_lowercase().as_str() {
-                            "mainnet" => crate::models::PaymentNetwork::Mainnet,
-                            _ => crate::models::PaymentNetwork::Testnet,
-                        };
-                    }
-                    "CENTRAL_NET_HOST" => {
-                        if !value.is_empty() {
-                            config.central_net_host = Some(value.to_string());
-                        }
-                    }
-                    "YAGNA_METRICS_URL" => {
-                        if !value.is_empty() {
-                            config.metrics_server = Some(value.to_string());
-                        }
-                    }
-                    "YAGNA_METRICS_JOB_NAME" => {
-                        if !value.is_empty() {
-                            config.metrics_job_name = Some(value.to_string());
-                        }
-                    }
-                    "YAGNA_METRICS_GROUP" => {
-                        if !value.is_empty() {
-                            config.metrics_group = Some(value.to_string());
-                        }
-                    }
-                    _ => {
-                        // Ignore unknown keys
-                    }
-                }
-            }
-        }
+        config.set_device_env(device_env, |key| table.contains_key(key));
+        config.extra_env = env
+            .entries()
+            .filter(|(key, _)| !MANAGED_ENV_KEYS.contains(key))
+            .map(|(key, value)| ExtraSetting { key: key.to_string(), value: value.to_string() })
+            .collect();
         
         Ok(config)
     }
     
+    /// Take the managed variables from `env`; `written` tells which the file had
+    ///
+    /// Blank or missing variables are left unset, so the other file or the defaults apply.
+    fn set_device_env(&mut self, env: DeviceEnv, written: impl Fn(&str) -> bool) {
+        let set = |key: &str, value: String| {
+            Some(value).filter(|value| written(key) && !value.trim().is_empty())
+        };
+        self.network_type = env.network_type;
+        self.subnet = env.subnet;
+        self.payment_network = env.payment_network;
+        self.central_net_host = env.central_net_host.filter(|host| !host.trim().is_empty());
+        self.metrics_server = set("YAGNA_METRICS_URL", env.metrics_url);
+        self.metrics_job_name = set("YAGNA_METRICS_JOB_NAME", env.metrics_job_name);
+        self.metrics_group = set("YAGNA_METRICS_GROUP", env.metrics_group);
+    }
+    
     /// Parse configuration from both TOML and ENV content, with TOML as single source of truth
     pub fn from_config_files(toml_content: &str, env_content: &str) -> Result<Self> {
+        Self::from_config_files_with_schema(toml_content, env_content, ConfigSchema::current())
+    }
+    
+    /// Parse configuration files written for an image with the given schema
+    pub fn from_config_files_with_schema(
+        toml_content: &str,
+        env_content: &str,
+        schema: &ConfigSchema,
+    ) -> Result<Self> {
         // Start with TOML (single source of truth)
-        let mut config = Self::from_toml_content(toml_content)?;
+        let mut config = Self::from_toml_content(&schema.canonical_toml(toml_content))?;
+        config.schema = schema.clone();
         
         // Only use ENV for fields not present in TOML
-        let env_config = Self::from_env_content(env_content)?;
+        let env_config = Self::from_env_content(&schema.canonical_env(env_content))?;
         
         // Fallback to ENV only for missing environment variables not in TOML [env] section
         if config.central_net_host.is_none() {
@@ -377,131 +335,316 @@ impl ImageConfiguration {
         if config.metrics_group.is_none() {
             config.metrics_group = env_config.metrics_group;
         }
+        for setting in env_config.extra_env {
+            if !config.extra_env.iter().any(|s| s.key == setting.key) {
+                config.extra_env.push(setting);
+            }
+        }
         
         Ok(config)
     }
     
+    /// The managed settings as they are written to golemwz.toml and golem.env
+    pub fn device_config(&self) -> DeviceConfig {
+        DeviceConfig {
+            accepted_terms: self.accepted_terms,
+            glm_account: self.glm_account.clone(),
+            glm_per_hour: self.glm_per_hour.clone(),
+            glm_node_name: self.glm_node_name.clone(),
+            non_interactive_install: self.non_interactive_install,
+            ssh_keys: self.ssh_keys.clone(),
+            configuration_server: self.configuration_server.clone(),
+            env: self.device_env(|_| None),
+        }
+    }
+    
+    /// The managed environment variables, see [`Self::device_config`]
+    ///
+    /// Metrics job name and group are not editable in the UI, so an existing on-device
+    /// value (looked up via `existing`) is kept when the configuration doesn't set one.
+    fn device_env(&self, existing: impl Fn(&str) -> Option<String>) -> DeviceEnv {
+        let defaults = DeviceEnv::default();
+        DeviceEnv {
+            network_type: self.network_type,
+            subnet: self.subnet.clone(),
+            payment_network: self.payment_network,
+            central_net_host: self.central_net_host.clone(),
+            metrics_url: self.metrics_server.clone().unwrap_or(defaults.metrics_url),
+            metrics_job_name: self.metrics_job_name.clone()
+                .or_else(|| existing("YAGNA_METRICS_JOB_NAME"))
+                .unwrap_or(defaults.metrics_job_name),
+            metrics_group: self.metrics_group.clone()
+                .or_else(|| existing("YAGNA_METRICS_GROUP"))
+                .unwrap_or(defaults.metrics_group),
+        }
+    }
+    
     /// Generate golemwz.toml content with unified structure
     pub fn to_toml_content(&self) -> String {
-        let mut content = String::new();
+        use toml_edit::{DocumentMut, Item};
         
-        // Main configuration section
-        content.push_str("# Golem Configuration\n");
-        content.push_str(&format!("accepted_terms = {}\n", self.accepted_terms));
-        content.push_str(&format!("glm_account = \"{}\"\n", self.glm_account));
-        content.push_str(&format!("glm_per_hour = \"{}\"\n", self.glm_per_hour));
+        let content = toml::to_string(&self.device_config()).expect("the configuration is valid TOML");
+        let mut doc = content.parse::<DocumentMut>().expect("serialized TOML parses");
         
-        if let Some(ref node_name) = self.glm_node_name {
-            content.push_str(&format!("glm_node_name = \"{}\"\n", node_name));
+        for (key, value) in self.extra_toml_entries() {
+            doc[key] = Item::Value(extra_toml_value(value));
         }
         
-        content.push_str(&format!("non_interactive_install = {}\n", self.non_interactive_install));
-        
-        // SSH keys array
-        if !self.ssh_keys.is_empty() {
-            let ssh_keys_str = self.ssh_keys
-                .iter()
-                .map(|key| format!("\"{}\"", key))
-                .collect::<Vec<_>>()
-                .join(", ");
-            content.push_str(&format!("ssh_keys = [{}]\n", ssh_keys_str));
-        } else {
-            content.push_str("ssh_keys = []\n");
+        // Environment variables section
+        let env = doc["env"].as_table_mut().expect("[env] is always serialized");
+        env.decor_mut().set_prefix("\n# Environment Variables\n");
+        for (key, value) in self.extra_env_entries() {
+            env[key] = toml_edit::value(value);
         }
         
-        if let Some(ref config_server) = self.configuration_server {
-            content.push_str(&format!("configuration_server = \"{}\"\n", config_server));
+        // Leave out what the image doesn't understand
+        self.schema.restrict_toml(&mut doc);
+        format!("# Golem Configuration\n{}", doc)
+    }
+    
+    /// Generate golem.env content (extracted from [env] section)
+    pub fn to_env_content(&self) -> String {
+        // Let EnvFile quote the values that need it
+        let mut env = super::EnvFile::default();
+        for (key, value) in env_entries(&self.device_env(|_| None)) {
+            env.set(&key, &value);
         }
+        for (key, value) in self.extra_env_entries() {
+            env.set(key, value);
+        }
+        self.schema.restrict_env(&mut env);
+        env.to_content()
+    }
+    
+    /// Merge the managed keys into existing golemwz.toml content
+    ///
+    /// Unknown keys, tables and comments in the existing file are preserved. Falls back to
+    /// a freshly generated file when the existing content is empty or not valid TOML.
+    pub fn merge_into_toml_content(&self, existing: &str) -> String {
+        use toml_edit::{Array, DocumentMut, Item, Table, value};
         
-        // Environment variables section
-        content.push_str("\n# Environment Variables\n");
-        content.push_str("[env]\n");
-        
-        let network_type_str = match self.network_type {
-            crate::models::NetworkType::Hybrid => "hybrid",
-            crate::models::NetworkType::Central => "central",
-        };
+        if existing.trim().is_empty() {
+            return self.to_toml_content();
+        }
         
-        let payment_network_str = match self.payment_network {
-            crate::models::PaymentNetwork::Testnet => "testnet",
-            crate::models::PaymentNetwork::Mainnet => "mainnet",
+        let mut doc = match existing.parse::<DocumentMut>() {
+            Ok(doc) => doc,
+            Err(e) => {
+                tracing::warn!("Existing golemwz.toml is not valid TOML, regenerating: {}", e);
+                return self.to_toml_content();
+            }
         };
         
-        content.push_str(&format!("YA_NET_TYPE = \"{}\"\n", network_type_str));
-        content.push_str(&format!("SUBNET = \"{}\"\n", self.subnet));
-        content.push_str(&format!("YA_PAYMENT_NETWORK_GROUP = \"{}\"\n", payment_network_str));
+        set_toml_bool(doc.as_table_mut(), "accepted_terms", self.accepted_terms);
+        set_toml_string(doc.as_table_mut(), "glm_account", &self.glm_account);
+        set_toml_string(doc.as_table_mut(), "glm_per_hour", &self.glm_per_hour);
+        set_or_remove_toml_string(doc.as_table_mut(), "glm_node_name", self.glm_node_name.as_deref());
+        set_toml_bool(doc.as_table_mut(), "non_interactive_install", self.non_interactive_install);
         
-        if let Some(ref host) = self.central_net_host {
-            content.push_str(&format!("CENTRAL_NET_HOST = \"{}\"\n", host));
+        let existing_keys: Vec<&str> = doc
+            .get("ssh_keys")
+            .and_then(|item| item.as_array())
+            .map(|keys| keys.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+        if existing_keys != self.ssh_keys.iter().map(String::as_str).collect::<Vec<_>>() {
+            let mut keys = Array::new();
+            for key in &self.ssh_keys {
+                keys.push(key.as_str());
+            }
+            doc["ssh_keys"] = value(keys);
         }
         
-        if let Some(ref server) = self.metrics_server {
-            content.push_str(&format!("YAGNA_METRICS_URL = \"{}\"\n", server));
-        } else {
-            content.push_str("YAGNA_METRICS_URL = \"https://metrics.golem.network:9092/\"\n");
+        set_or_remove_toml_string(doc.as_table_mut(), "configuration_server", self.configuration_server.as_deref());
+        
+        for (key, value) in self.extra_toml_entries() {
+            let new_value = extra_toml_value(value);
+            let unchanged = doc.get(key).and_then(Item::as_value).is_some_and(|existing| {
+                let mut existing = existing.clone();
+                existing.decor_mut().clear();
+                existing.to_string() == new_value.to_string()
+            });
+            if !unchanged {
+                doc[key] = Item::Value(new_value);
+            }
         }
         
-        if let Some(ref job_name) = self.metrics_job_name {
-            content.push_str(&format!("YAGNA_METRICS_JOB_NAME = \"{}\"\n", job_name));
-        } else {
-            content.push_str("YAGNA_METRICS_JOB_NAME = \"community.1\"\n");
+        // Make sure [env] is a real table, keeping whatever it already holds
+        if !doc.get("env").is_some_and(Item::is_table) {
+            let mut env = Table::new();
+            if let Some(inline) = doc.get("env").and_then(Item::as_inline_table) {
+                env = inline.clone().into_table();
+            }
+            doc["env"] = Item::Table(env);
         }
         
-        if let Some(ref group) = self.metrics_group {
-            content.push_str(&format!("YAGNA_METRICS_GROUP = \"{}\"\n", group));
-        } else {
-            content.push_str("YAGNA_METRICS_GROUP = \"\"\n");
+        let env = doc["env"].as_table_mut().expect("[env] was just made a table");
+        for (key, managed_value) in self.managed_env_values(|key| {
+            env.get(key).and_then(|item| item.as_str()).map(|s| s.to_string())
+        }) {
+            match managed_value {
+                Some(v) => set_toml_string(env, key, &v),
+                None => {
+                    env.remove(key);
+                }
+            }
+        }
+        for (key, value) in self.extra_env_entries() {
+            set_toml_string(env, key, value);
         }
+        self.schema.restrict_toml(&mut doc);
         
-        content
+        doc.to_string()
     }
     
-    /// Generate golem.env content (extracted from [env] section)
-    pub fn to_env_content(&self) -> String {
-        let mut content = String::new();
-        
-        // Core network configuration
-        let network_type_str = match self.network_type {
-            crate::models::NetworkType::Hybrid => "hybrid",
-            crate::models::NetworkType::Central => "central",
-        };
-        
-        let payment_network_str = match self.payment_network {
-            crate::models::PaymentNetwork::Testnet => "testnet",
-            crate::models::PaymentNetwork::Mainnet => "mainnet",
-        };
-        
-        content.push_str(&format!("YA_NET_TYPE={}\n", network_type_str));
-        content.push_str(&format!("SUBNET={}\n", self.subnet));
-        content.push_str(&format!("YA_PAYMENT_NETWORK_GROUP={}\n", payment_network_str));
-        
-        // Optional network configuration
-        if let Some(ref host) = self.central_net_host {
-            content.push_str(&format!("CENTRAL_NET_HOST={}\n", host));
+    /// Merge the managed keys into existing golem.env content
+    ///
+    /// Variables not managed by the imager (e.g. `YA_DEBUG`) and comments are kept as-is.
+    pub fn merge_into_env_content(&self, existing: &str) -> String {
+        if existing.trim().is_empty() {
+            return self.to_env_content();
         }
         
-        // Metrics configuration
-        if let Some(ref server) = self.metrics_server {
-            content.push_str(&format!("YAGNA_METRICS_URL={}\n", server));
-        } else {
-            content.push_str("YAGNA_METRICS_URL=https://metrics.golem.network:9092/\n");
+        let mut env = super::EnvFile::parse(existing);
+        let managed = self.managed_env_values(|key| env.get(key).map(|s| s.to_string()));
+        for (key, managed_value) in managed {
+            match managed_value {
+                Some(v) => env.set(key, &v),
+                None => env.remove(key),
+            }
         }
-        
-        if let Some(ref job_name) = self.metrics_job_name {
-            content.push_str(&format!("YAGNA_METRICS_JOB_NAME={}\n", job_name));
-        } else {
-            content.push_str("YAGNA_METRICS_JOB_NAME=community.1\n");
+        for (key, value) in self.extra_env_entries() {
+            env.set(key, value);
         }
+        self.schema.restrict_env(&mut env);
         
-        if let Some(ref group) = self.metrics_group {
-            content.push_str(&format!("YAGNA_METRICS_GROUP={}\n", group));
+        env.to_content()
+    }
+    
+    /// Generate both configuration files, merging into existing on-device content if present
+    pub fn merge_config_files(&self, existing_toml: Option<&str>, existing_env: Option<&str>) -> (String, String) {
+        let toml_content = if let Some(ref server_content) = self.server_toml_content {
+            // Server configuration is authoritative and written as-is
+            server_content.clone()
         } else {
-            content.push_str("YAGNA_METRICS_GROUP=\n");
-        }
+            self.merge_into_toml_content(existing_toml.unwrap_or_default())
+        };
         
-        content
+        (toml_content, self.merge_into_env_content(existing_env.unwrap_or_default()))
+    }
+    
+    /// Extra golem.env entries that can be written, as trimmed `(key, value)` pairs
+    ///
+    /// Entries without a key are skipped, as are invalid keys and keys the imager
+    /// manages itself, which would otherwise be overwritten or override a setting.
+    fn extra_env_entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.extra_env.iter().filter_map(|setting| {
+            extra_entry(setting, crate::utils::validation::extra_env_key_error)
+        })
     }
     
+    /// Extra golemwz.toml entries that can be written, see [`Self::extra_env_entries`]
+    fn extra_toml_entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.extra_toml.iter().filter_map(|setting| {
+            extra_entry(setting, crate::utils::validation::extra_toml_key_error)
+        })
+    }
+    
+    /// Values of the environment variables managed by the imager, in file order.
+    ///
+    /// `None` means the key should be removed. See [`Self::device_env`] for `existing`.
+    fn managed_env_values(&self, existing: impl Fn(&str) -> Option<String>) -> Vec<(&'static str, Option<String>)> {
+        let entries = env_entries(&self.device_env(existing));
+        MANAGED_ENV_KEYS
+            .iter()
+            .map(|&key| {
+                let value = entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+                (key, value)
+            })
+            .collect()
+    }
+    
+    /// Names of the fields that differ from a configuration read back from a device
+    ///
+    /// Only values the imager writes for the image's schema are compared. Metrics settings
+    /// left unset here are filled in with defaults when written, so they are only compared
+    /// when set.
+    pub fn mismatched_fields(&self, written: &ImageConfiguration) -> Vec<&'static str> {
+        fn keys(keys: &[String]) -> Vec<&str> {
+            keys.iter()
+                .map(|key| key.trim())
+                .filter(|key| !key.is_empty())
+                .collect()
+        }
+        // Blank optional values are not written at all
+        fn optional(value: &Option<String>) -> &str {
+            value.as_deref().unwrap_or_default().trim()
+        }
+
+        let mut fields = Vec::new();
+        if self.accepted_terms != written.accepted_terms {
+            fields.push("accepted_terms");
+        }
+        if self.glm_account != written.glm_account {
+            fields.push("glm_account");
+        }
+        if self.glm_per_hour != written.glm_per_hour {
+            fields.push("glm_per_hour");
+        }
+        if optional(&self.glm_node_name) != optional(&written.glm_node_name) {
+            fields.push("glm_node_name");
+        }
+        if self.non_interactive_install != written.non_interactive_install {
+            fields.push("non_interactive_install");
+        }
+        if keys(&self.ssh_keys) != keys(&written.ssh_keys) {
+            fields.push("ssh_keys");
+        }
+        if optional(&self.configuration_server) != optional(&written.configuration_server) {
+            fields.push("configuration_server");
+        }
+        if self.payment_network != written.payment_network {
+            fields.push("YA_PAYMENT_NETWORK_GROUP");
+        }
+        if self.network_type != written.network_type {
+            fields.push("YA_NET_TYPE");
+        }
+        if self.subnet != written.subnet {
+            fields.push("SUBNET");
+        }
+        if optional(&self.central_net_host) != optional(&written.central_net_host) {
+            fields.push("CENTRAL_NET_HOST");
+        }
+        if self.metrics_server.is_some() && self.metrics_server != written.metrics_server {
+            fields.push("YAGNA_METRICS_URL");
+        }
+        if self.metrics_job_name.is_some() && self.metrics_job_name != written.metrics_job_name {
+            fields.push("YAGNA_METRICS_JOB_NAME");
+        }
+        if self.metrics_group.is_some() && self.metrics_group != written.metrics_group {
+            fields.push("YAGNA_METRICS_GROUP");
+        }
+        let env_written = self.extra_env_entries().all(|(key, value)| {
+            written
+                .extra_env
+                .iter()
+                .any(|setting| setting.key == key && setting.value.trim() == value)
+        });
+        if !env_written {
+            fields.push("extra golem.env settings");
+        }
+        let toml_written = self.extra_toml_entries().all(|(key, value)| {
+            written.extra_toml.iter().any(|setting| {
+                setting.key == key
+                    && extra_toml_value(&setting.value).to_string() == extra_toml_value(value).to_string()
+            })
+        });
+        if !toml_written {
+            fields.push("extra golemwz.toml settings");
+        }
+        fields.retain(|field| self.schema.writes_key(field));
+        fields
+    }
+
     /// Generate both configuration files as a tuple (toml_content, env_content)
     pub fn generate_config_files(&self) -> (String, String) {
         let toml_content = if let Some(ref server_content) = self.server_toml_content {
@@ -516,6 +659,64 @@ impl ImageConfiguration {
     }
 }
 
+/// The variables of `env` as `(key, value)` pairs, in the order golem.env lists them
+fn env_entries(env: &DeviceEnv) -> Vec<(String, String)> {
+    let content = toml::to_string(env).expect("the variables are valid TOML");
+    let doc = content.parse::<toml_edit::DocumentMut>().expect("serialized TOML parses");
+    doc.iter()
+        .filter_map(|(key, item)| Some((key.to_string(), item.as_str()?.to_string())))
+        .collect()
+}
+
+/// Key and value of an extra setting, `None` when it has no key or `key_error` rejects it
+fn extra_entry<'a>(
+    setting: &'a ExtraSetting,
+    key_error: fn(&str) -> Option<String>,
+) -> Option<(&'a str, &'a str)> {
+    let key = setting.key.trim();
+    if key.is_empty() {
+        return None;
+    }
+    if let Some(error) = key_error(key) {
+        tracing::warn!("Skipping extra setting {}: {}", key, error);
+        return None;
+    }
+    Some((key, setting.value.trim()))
+}
+
+/// TOML value of an extra golemwz.toml entry; text that doesn't parse as TOML is a string
+fn extra_toml_value(value: &str) -> toml_edit::Value {
+    let mut parsed = value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| toml_edit::Value::from(value));
+    parsed.decor_mut().clear();
+    parsed
+}
+
+/// Set a string key, leaving the existing item (and its formatting) alone when unchanged
+fn set_toml_string(table: &mut toml_edit::Table, key: &str, new_value: &str) {
+    if table.get(key).and_then(|item| item.as_str()) != Some(new_value) {
+        table[key] = toml_edit::value(new_value);
+    }
+}
+
+/// Set a bool key, leaving the existing item (and its formatting) alone when unchanged
+fn set_toml_bool(table: &mut toml_edit::Table, key: &str, new_value: bool) {
+    if table.get(key).and_then(|item| item.as_bool()) != Some(new_value) {
+        table[key] = toml_edit::value(new_value);
+    }
+}
+
+/// Set an optional string key, removing it when there is no value
+fn set_or_remove_toml_string(table: &mut toml_edit::Table, key: &str, new_value: Option<&str>) {
+    match new_value {
+        Some(v) => set_toml_string(table, key, v),
+        None => {
+            table.remove(key);
+        }
+    }
+}
+
 impl Default for ImageConfiguration {
     fn default() -> Self {
         Self {
@@ -533,7 +734,11 @@ impl Default for ImageConfiguration {
             metrics_server: None,
             metrics_job_name: None,
             metrics_group: None,
+            extra_env: Vec::new(),
+            extra_toml: Vec::new(),
+            firstboot: None,
             server_toml_content: None,
+            schema: ConfigSchema::default(),
         }
     }
 }
@@ -551,6 +756,7 @@ impl From<ImageConfiguration> for crate::disk::GolemConfig {
             configuration_server: config.configuration_server,
             metrics_server: config.metrics_server,
             central_net_host: config.central_net_host,
+            node_name: config.glm_node_name,
         }
     }
 }
@@ -561,7 +767,7 @@ impl From<super::GolemConfig> for ImageConfiguration {
             accepted_terms: true,
             glm_account: config.wallet_address,
             glm_per_hour: config.glm_per_hour,
-            glm_node_name: None,
+            glm_node_name: config.node_name,
             non_interactive_install: config.non_interactive_install,
             ssh_keys: config.ssh_keys,
             configuration_server: config.configuration_server,
@@ -572,7 +778,11 @@ impl From<super::GolemConfig> for ImageConfiguration {
             metrics_server: config.metrics_server,
             metrics_job_name: None,
             metrics_group: None,
+            extra_env: Vec::new(),
+            extra_toml: Vec::new(),
+            firstboot: None,
             server_toml_content: None,
+            schema: ConfigSchema::default(),
         }
     }
 }
@@ -888,6 +1098,31 @@ YA_PAYMENT_NETWORK_GROUP=testnet
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_mistyped_settings_fall_back_to_defaults() {
+        let toml_content = r#"
+accepted_terms = "yes"
+glm_account = "0x1234567890123456789012345678901234567890"
+glm_per_hour = 0.5
+ssh_keys = ["ssh-ed25519 AAAA", 42]
+
+[env]
+YA_NET_TYPE = "hybrid"
+SUBNET = 7
+"#;
+        // One bad setting doesn't cost the others
+        let config = ImageConfiguration::from_toml_content(toml_content).unwrap();
+        assert!(config.accepted_terms);
+        assert_eq!(config.glm_account, "0x1234567890123456789012345678901234567890");
+        assert_eq!(config.glm_per_hour, "0.25");
+        assert_eq!(config.ssh_keys, vec!["ssh-ed25519 AAAA"]);
+        assert_eq!(config.network_type, NetworkType::Hybrid);
+        assert_eq!(config.subnet, "public");
+
+        let config = ImageConfiguration::from_toml_content("ssh_keys = \"ssh-ed25519 AAAA\"\n").unwrap();
+        assert!(config.ssh_keys.is_empty());
+    }
+
     #[test]
     fn test_empty_content_parsing() {
         // Empty TOML should use defaults
@@ -918,4 +1153,242 @@ YA_PAYMENT_NETWORK_GROUP=mainnet
         assert_eq!(config.subnet, "test-subnet");
         assert_eq!(config.payment_network, PaymentNetwork::Mainnet);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_merge_preserves_unknown_env_keys() {
+        let existing = "# Custom settings\nYA_DEBUG=1\nSUBNET=public\nYA_NET_TYPE=central\nMY_VAR=\"keep me\"\n";
+        
+        let config = ImageConfiguration {
+            subnet: "devnet-beta".to_string(),
+            network_type: NetworkType::Hybrid,
+            ..Default::default()
+        };
+        
+        let merged = config.merge_into_env_content(existing);
+        assert!(merged.starts_with("# Custom settings\nYA_DEBUG=1\nSUBNET=devnet-beta\nYA_NET_TYPE=hybrid\nMY_VAR=\"keep me\"\n"));
+        assert!(merged.contains("YA_PAYMENT_NETWORK_GROUP=testnet\n"));
+        assert_eq!(merged.matches("YAGNA_METRICS_JOB_NAME=").count(), 1);
+        
+        let reparsed = ImageConfiguration::from_env_content(&merged).unwrap();
+        assert_eq!(reparsed.subnet, "devnet-beta");
+        assert_eq!(reparsed.network_type, NetworkType::Hybrid);
+    }
+
+    #[test]
+    fn test_merge_keeps_existing_metrics_job_name() {
+        let existing = "YAGNA_METRICS_JOB_NAME=custom.job\nYAGNA_METRICS_GROUP=my-group\nCENTRAL_NET_HOST=old.example.com\n";
+        let config = ImageConfiguration::default();
+        
+        let merged = config.merge_into_env_content(existing);
+        assert!(merged.contains("YAGNA_METRICS_JOB_NAME=custom.job\n"));
+        assert!(merged.contains("YAGNA_METRICS_GROUP=my-group\n"));
+        // Cleared optional values are removed rather than left stale
+        assert!(!merged.contains("CENTRAL_NET_HOST"));
+    }
+
+    #[test]
+    fn test_mismatched_fields_after_merge() {
+        let config = ImageConfiguration {
+            glm_account: "0x1234567890123456789012345678901234567890".to_string(),
+            ssh_keys: vec!["ssh-ed25519 AAAA user@host".to_string()],
+            subnet: "devnet-beta".to_string(),
+            payment_network: PaymentNetwork::Mainnet,
+            ..Default::default()
+        };
+        let existing_env = "SUBNET=public\nYAGNA_METRICS_JOB_NAME=custom.job\n";
+        let (toml, env) = config.merge_config_files(None, Some(existing_env));
+
+        let written = ImageConfiguration::from_config_files(&toml, &env).unwrap();
+        assert!(config.mismatched_fields(&written).is_empty());
+
+        let stale = ImageConfiguration {
+            subnet: "public".to_string(),
+            ssh_keys: Vec::new(),
+            ..written
+        };
+        assert_eq!(config.mismatched_fields(&stale), vec!["ssh_keys", "SUBNET"]);
+    }
+
+    #[test]
+    fn test_merge_preserves_unknown_toml_keys_and_comments() {
+        let existing = r#"# Provisioned by ops
+accepted_terms = true
+glm_account = "0xold"
+custom_flag = true # user setting
+
+[env]
+YA_NET_TYPE = "central"
+SUBNET = "public"
+YA_DEBUG = "1"
+
+[extra]
+note = "keep"
+"#;
+        
+        let config = ImageConfiguration {
+            glm_account: "0x1234567890abcdef1234567890abcdef12345678".to_string(),
+            subnet: "devnet-beta".to_string(),
+            ..Default::default()
+        };
+        
+        let merged = config.merge_into_toml_content(existing);
+        assert!(merged.contains("# Provisioned by ops"));
+        assert!(merged.contains("custom_flag = true # user setting"));
+        assert!(merged.contains("YA_DEBUG = \"1\""));
+        assert!(merged.contains("[extra]"));
+        
+        let reparsed = ImageConfiguration::from_toml_content(&merged).unwrap();
+        assert_eq!(reparsed.glm_account, "0x1234567890abcdef1234567890abcdef12345678");
+        assert_eq!(reparsed.subnet, "devnet-beta");
+        assert_eq!(reparsed.metrics_job_name, Some("community.1".to_string()));
+    }
+
+    #[test]
+    fn test_merge_with_invalid_toml_regenerates() {
+        let config = ImageConfiguration::default();
+        assert_eq!(config.merge_into_toml_content("invalid [[["), config.to_toml_content());
+        assert_eq!(config.merge_into_toml_content(""), config.to_toml_content());
+    }
+
+    #[test]
+    fn test_extra_settings_round_trip() {
+        let setting = |key: &str, value: &str| ExtraSetting {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let config = ImageConfiguration {
+            extra_env: vec![
+                setting("YA_NEW_FEATURE", "on"),
+                setting("GREETING", "hello world"),
+                // Managed keys can't be overridden
+                setting("SUBNET", "sneaky"),
+                setting("", "ignored"),
+            ],
+            extra_toml: vec![setting("gpu_power_limit", "250"), setting("region", "eu-west")],
+            ..Default::default()
+        };
+        
+        let (toml, env) = config.generate_config_files();
+        assert!(toml.contains("gpu_power_limit = 250\n"));
+        assert!(toml.contains("region = \"eu-west\"\n"));
+        assert!(toml.contains("YA_NEW_FEATURE = \"on\"\n"));
+        assert!(env.contains("YA_NEW_FEATURE=on\n"));
+        assert!(env.contains("GREETING=\"hello world\"\n"));
+        assert!(env.contains("SUBNET=public\n"));
+        assert!(!env.contains("sneaky"));
+        
+        let written = ImageConfiguration::from_config_files(&toml, &env).unwrap();
+        assert!(config.mismatched_fields(&written).is_empty());
+        
+        // Merged into existing files, replacing an older value of the same key
+        let (toml, env) = config.merge_config_files(
+            Some("glm_account = \"0xold\"\ngpu_power_limit = 200\n"),
+            Some("YA_NEW_FEATURE=off\nYA_DEBUG=1\n"),
+        );
+        assert!(toml.contains("gpu_power_limit = 250"));
+        assert!(env.contains("YA_NEW_FEATURE=on\n"));
+        assert!(env.contains("YA_DEBUG=1\n"));
+        let written = ImageConfiguration::from_config_files(&toml, &env).unwrap();
+        assert!(config.mismatched_fields(&written).is_empty());
+        
+        let missing = ImageConfiguration {
+            extra_toml: Vec::new(),
+            ..written
+        };
+        assert_eq!(config.mismatched_fields(&missing), vec!["extra golemwz.toml settings"]);
+    }
+    
+    #[test]
+    fn test_legacy_schema_files() {
+        let legacy = ConfigSchema::resolve(Some(crate::disk::config_schema::LEGACY_SCHEMA), &[]);
+        let config = ImageConfiguration {
+            glm_account: "0xabc".to_string(),
+            glm_node_name: Some("rig-1".to_string()),
+            payment_network: PaymentNetwork::Mainnet,
+            central_net_host: Some("host:7999".to_string()),
+            schema: legacy.clone(),
+            ..Default::default()
+        };
+        
+        let (toml, env) = config.generate_config_files();
+        assert!(!toml.contains("glm_node_name"));
+        assert!(!toml.contains("CENTRAL_NET_HOST"));
+        assert!(toml.contains("YA_PAYMENT_NETWORK = \"mainnet\""));
+        assert!(env.contains("YA_PAYMENT_NETWORK=mainnet\n"));
+        assert!(!env.contains("YA_PAYMENT_NETWORK_GROUP"));
+        assert!(!env.contains("YAGNA_METRICS_URL"));
+        
+        // Settings the image doesn't understand are not expected back
+        let written = ImageConfiguration::from_config_files_with_schema(&toml, &env, &legacy).unwrap();
+        assert_eq!(written.payment_network, PaymentNetwork::Mainnet);
+        assert!(config.mismatched_fields(&written).is_empty());
+        
+        // An older value under the legacy name is replaced when merging
+        let (_, env) = config.merge_config_files(None, Some("YA_PAYMENT_NETWORK=testnet\nYA_DEBUG=1\n"));
+        assert!(env.contains("YA_PAYMENT_NETWORK=mainnet\n"));
+        assert!(env.contains("YA_DEBUG=1\n"));
+        
+        // Legacy names are understood without knowing the schema
+        let read = ImageConfiguration::from_env_content("YA_PAYMENT_NETWORK=mainnet\n").unwrap();
+        assert_eq!(read.payment_network, PaymentNetwork::Mainnet);
+        assert!(read.extra_env.is_empty());
+    }
+    
+    fn quoting_config() -> ImageConfiguration {
+        ImageConfiguration {
+            glm_account: "0x1234567890abcdef1234567890abcdef12345678".to_string(),
+            glm_node_name: Some("rig \"7\" #2".to_string()),
+            ssh_keys: vec!["ssh-ed25519 AAAAC3... admin@bench".to_string()],
+            payment_network: PaymentNetwork::Mainnet,
+            network_type: NetworkType::Hybrid,
+            subnet: "gpu lab".to_string(),
+            metrics_group: Some("gpu-provider".to_string()),
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_device_config_round_trip() {
+        let config = quoting_config();
+        let (toml_content, env_content) = config.generate_config_files();
+        
+        // golemwz.toml deserializes back into what it was generated from
+        let parsed: DeviceConfig = toml::from_str(&toml_content).unwrap();
+        assert_eq!(parsed, config.device_config());
+        assert_eq!(parsed.glm_node_name.as_deref(), Some("rig \"7\" #2"));
+        
+        // golem.env holds the same variables as its [env] table
+        let env: toml::Table = crate::disk::EnvFile::parse(&env_content)
+            .entries()
+            .map(|(key, value)| (key.to_string(), toml::Value::String(value.to_string())))
+            .collect();
+        let parsed_env: DeviceEnv = toml::Value::Table(env).try_into().unwrap();
+        assert_eq!(parsed_env, parsed.env);
+        assert!(env_content.contains("SUBNET=\"gpu lab\"\n"));
+        
+        let read = ImageConfiguration::from_config_files(&toml_content, &env_content).unwrap();
+        assert!(config.mismatched_fields(&read).is_empty());
+    }
+    
+    #[test]
+    fn test_fresh_and_merged_files_agree() {
+        let config = quoting_config();
+        let (fresh_toml, fresh_env) = config.generate_config_files();
+        
+        // Files on the device written from another configuration
+        let previous = ImageConfiguration {
+            subnet: "public".to_string(),
+            central_net_host: Some("central.example.com:7999".to_string()),
+            configuration_server: Some("https://config.example.com".to_string()),
+            ..Default::default()
+        };
+        let (old_toml, old_env) = previous.generate_config_files();
+        let (merged_toml, merged_env) = config.merge_config_files(Some(&old_toml), Some(&old_env));
+        
+        assert_eq!(merged_env, fresh_env);
+        assert_eq!(
+            toml::from_str::<DeviceConfig>(&merged_toml).unwrap(),
+            toml::from_str::<DeviceConfig>(&fresh_toml).unwrap()
+        );
+    }
+}
@@ -0,0 +1,347 @@
+// Decompression of image data on its way to the disk
+//
+// The format is detected from the stream's magic bytes rather than the file name, so the
+// same path works for cached files and for images streamed from a URL. Uncompressed disk
+// and ISO images picked by the user are passed through as they are.
+
+use crate::models::{CancelToken, ImageMetadata};
+use anyhow::{Context, Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::{error, info};
+use xz4rust::XzReader;
+
+/// Magic bytes at the start of an XZ stream
+const XZ_MAGIC: [u8; 6] = [0xFD, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Magic bytes at the start of a Zstandard frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Buffer size for the XZ decoder, a multiple of 4096 for Windows direct I/O
+const XZ_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// Signature at the end of an MBR or protective MBR, also present in hybrid ISOs
+const BOOT_SIGNATURE_OFFSET: usize = 510;
+const BOOT_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+
+/// Identifier of the ISO 9660 primary volume descriptor, after its type byte
+const ISO_MAGIC_OFFSET: u64 = 0x8001;
+const ISO_MAGIC: &[u8; 5] = b"CD001";
+
+/// Extensions of uncompressed images, for raw device dumps without a partition table
+const RAW_EXTENSIONS: [&str; 3] = ["img", "iso", "raw"];
+
+/// Compression format of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the format from the first bytes of the stream
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        if head.starts_with(&XZ_MAGIC) {
+            Some(Compression::Xz)
+        } else if head.starts_with(&ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+/// Format of an image file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Compressed(Compression),
+    /// Disk or ISO image written as it is
+    Raw,
+}
+
+impl ImageFormat {
+    /// Detect the format of an image file from its magic bytes, then from its extension
+    ///
+    /// # Returns
+    /// * `None` if the file is neither a compressed nor a recognizable uncompressed image
+    pub fn detect_file(path: &Path) -> io::Result<Option<Self>> {
+        let mut file = std::fs::File::open(path)?;
+        let mut head = [0u8; BOOT_SIGNATURE_OFFSET + BOOT_SIGNATURE.len()];
+        let head_len = read_up_to(&mut file, &mut head)?;
+        let head = &head[..head_len];
+
+        if let Some(compression) = Compression::detect(head) {
+            return Ok(Some(ImageFormat::Compressed(compression)));
+        }
+        if head.get(BOOT_SIGNATURE_OFFSET..) == Some(&BOOT_SIGNATURE[..]) {
+            return Ok(Some(ImageFormat::Raw));
+        }
+
+        let mut iso_magic = [0u8; ISO_MAGIC.len()];
+        file.seek(SeekFrom::Start(ISO_MAGIC_OFFSET))?;
+        if read_up_to(&mut file, &mut iso_magic)? == ISO_MAGIC.len() && &iso_magic == ISO_MAGIC {
+            return Ok(Some(ImageFormat::Raw));
+        }
+
+        let raw_extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                RAW_EXTENSIONS
+                    .iter()
+                    .any(|raw| extension.eq_ignore_ascii_case(raw))
+            });
+        Ok(raw_extension.then_some(ImageFormat::Raw))
+    }
+}
+
+/// Read until `buffer` is full or the stream ends
+fn read_up_to(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Wrap a compressed image stream in the matching decoder
+///
+/// The magic bytes are read from `reader` and fed back into the decoder, so `reader` may
+/// be a network stream that can't seek. A stream that isn't compressed is passed through
+/// if `allow_raw` is set, i.e. for files `ImageFormat::detect_file` found to be raw images.
+///
+/// # Returns
+/// * `io::Result<Box<dyn Read>>` - Reader producing the decompressed image
+pub fn open_decoder<R: Read + 'static>(
+    mut reader: R,
+    allow_raw: bool,
+) -> io::Result<Box<dyn Read>> {
+    let mut head = [0u8; XZ_MAGIC.len()];
+    let head_len = read_up_to(&mut reader, &mut head)?;
+    let head = &head[..head_len];
+    let stream = Cursor::new(head.to_vec()).chain(reader);
+
+    match Compression::detect(head) {
+        Some(Compression::Xz) => {
+            let buffer_size = std::num::NonZeroUsize::new(XZ_BUFFER_SIZE).unwrap();
+            Ok(Box::new(XzReader::new_with_buffer_size(
+                stream,
+                buffer_size,
+            )))
+        }
+        Some(Compression::Zstd) => {
+            let decoder = ruzstd::decoding::StreamingDecoder::new(stream)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            Ok(Box::new(decoder))
+        }
+        None if allow_raw => Ok(Box::new(stream)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Unsupported image format: expected an XZ or Zstandard compressed image",
+        )),
+    }
+}
+
+/// Decompress an image file and check it against its metadata, without writing anything
+///
+/// Catches a damaged download before the device is erased. `on_progress` is called with
+/// the uncompressed bytes checked so far.
+pub fn check_image_file(
+    path: &Path,
+    metadata: &ImageMetadata,
+    cancel_token: &CancelToken,
+    on_progress: impl FnMut(u64),
+) -> Result<()> {
+    let mut image = open_image_file(path)?;
+    check_image_data(&mut image, metadata, cancel_token, on_progress)
+}
+
+/// Open an image file for reading its uncompressed contents, whatever its format
+pub fn open_image_file(path: &Path) -> Result<Box<dyn Read>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open image file: {}", path.display()))?;
+    let raw = ImageFormat::detect_file(path)? == Some(ImageFormat::Raw);
+    Ok(open_decoder(
+        io::BufReader::with_capacity(XZ_BUFFER_SIZE, file),
+        raw,
+    )?)
+}
+
+/// Check decompressed image data against the uncompressed size and hash in its metadata
+fn check_image_data(
+    image: &mut dyn Read,
+    metadata: &ImageMetadata,
+    cancel_token: &CancelToken,
+    mut on_progress: impl FnMut(u64),
+) -> Result<()> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; XZ_BUFFER_SIZE];
+    let mut total = 0u64;
+    loop {
+        if cancel_token.is_cancelled() {
+            return Err(anyhow!("Image check cancelled"));
+        }
+        let bytes_read = match image.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                error!("Image file can't be decompressed: {}", e);
+                return Err(anyhow!("The image file is corrupt: {}", e));
+            }
+        };
+        hasher.update(&buffer[..bytes_read]);
+        total += bytes_read as u64;
+        on_progress(total);
+    }
+
+    if total != metadata.uncompressed_size {
+        return Err(anyhow!(
+            "The image file is corrupt: it holds {} bytes instead of {}",
+            total,
+            metadata.uncompressed_size
+        ));
+    }
+    let hash = hex::encode(hasher.finalize());
+    if hash != metadata.uncompressed_hash {
+        error!(
+            "Image hash mismatch: expected {}, got {}",
+            metadata.uncompressed_hash, hash
+        );
+        return Err(anyhow!(
+            "The image file is corrupt: its contents don't match the expected hash"
+        ));
+    }
+    info!(
+        "Image file checked, {} bytes match the expected hash",
+        total
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passes_raw_images_through() {
+        let mut data = vec![0u8; 4096];
+        data[..8].copy_from_slice(b"EFI PART");
+        let mut image = open_decoder(Cursor::new(data.clone()), true).unwrap();
+        let mut read = Vec::new();
+        image.read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+
+        // Shorter than the magic bytes, yet still a valid stream
+        let mut image = open_decoder(Cursor::new(vec![1u8, 2]), true).unwrap();
+        let mut read = Vec::new();
+        image.read_to_end(&mut read).unwrap();
+        assert_eq!(read, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_detect_image_file_format() {
+        let dir = std::env::temp_dir().join(format!("golem-image-format-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let detect = |name: &str, data: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, data).unwrap();
+            ImageFormat::detect_file(&path).unwrap()
+        };
+
+        let mut xz = vec![0u8; 1024];
+        xz[..6].copy_from_slice(&XZ_MAGIC);
+        assert_eq!(
+            detect("image.bin", &xz),
+            Some(ImageFormat::Compressed(Compression::Xz))
+        );
+
+        let mut mbr = vec![0u8; 1024];
+        mbr[510..512].copy_from_slice(&BOOT_SIGNATURE);
+        assert_eq!(detect("disk.bin", &mbr), Some(ImageFormat::Raw));
+
+        let mut iso = vec![0u8; 0x8800];
+        iso[0x8001..0x8006].copy_from_slice(ISO_MAGIC);
+        assert_eq!(detect("cdrom", &iso), Some(ImageFormat::Raw));
+
+        assert_eq!(detect("dump.IMG", &[0u8; 1024]), Some(ImageFormat::Raw));
+        assert_eq!(detect("notes.txt", b"hello"), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn metadata_of(data: &[u8]) -> ImageMetadata {
+        ImageMetadata {
+            compressed_hash: String::new(),
+            uncompressed_hash: hex::encode(Sha256::digest(data)),
+            uncompressed_size: data.len() as u64,
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_check_image_data() {
+        let data = vec![0x5au8; 10 * 1024 * 1024];
+        let metadata = metadata_of(&data);
+        let cancel_token = CancelToken::new();
+
+        let mut progress = Vec::new();
+        check_image_data(
+            &mut Cursor::new(data.clone()),
+            &metadata,
+            &cancel_token,
+            |bytes| progress.push(bytes),
+        )
+        .unwrap();
+        assert_eq!(progress.last(), Some(&(data.len() as u64)));
+
+        let mut damaged = data.clone();
+        damaged[1234] = 0;
+        let error = check_image_data(&mut Cursor::new(damaged), &metadata, &cancel_token, |_| {})
+            .unwrap_err();
+        assert!(error.to_string().contains("expected hash"));
+
+        let error = check_image_data(
+            &mut Cursor::new(&data[..4096]),
+            &metadata,
+            &cancel_token,
+            |_| {},
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("4096 bytes"));
+
+        cancel_token.cancel();
+        let error =
+            check_image_data(&mut Cursor::new(data), &metadata, &cancel_token, |_| {}).unwrap_err();
+        assert!(error.to_string().contains("cancelled"));
+    }
+
+    #[test]
+    fn test_detect_compression() {
+        assert_eq!(
+            Compression::detect(&[0xFD, b'7', b'z', b'X', b'Z', 0x00, 0x00]),
+            Some(Compression::Xz)
+        );
+        assert_eq!(
+            Compression::detect(&[0x28, 0xB5, 0x2F, 0xFD, 0x00, 0x00]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::detect(b"EFI PART"), None);
+        assert_eq!(Compression::detect(&[0xFD, b'7']), None);
+    }
+
+    #[test]
+    fn test_rejects_uncompressed_data() {
+        let result = open_decoder(Cursor::new(vec![0u8; 1024]), false);
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidData)
+        );
+    }
+}
@@ -0,0 +1,139 @@
+// Ejecting a disk once it has been written
+//
+// In duplicator mode every written card is ejected, so the operator can pull it and insert
+// the next one without the desktop mounting the fresh partitions in between. On Linux the
+// drive is powered off through UDisks2, like "Safely remove" in the file manager, or its
+// media ejected when it can't be powered off. On Windows the media is ejected from the
+// physical drive.
+
+use anyhow::Result;
+
+/// Eject the disk at `path`
+///
+/// The disk must no longer be open for writing.
+pub async fn eject(path: &str) -> Result<()> {
+    super::access::backend().eject(path.to_string()).await
+}
+
+/// Eject the disk at `path` through the OS, for the native backend
+pub(super) async fn eject_native(path: String) -> Result<()> {
+    platform::eject(&path).await
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::Result;
+    use anyhow::{Context, anyhow};
+    use std::collections::HashMap;
+    use tracing::info;
+    use udisks2::{Client, zbus};
+
+    pub async fn eject(path: &str) -> Result<()> {
+        let client = Client::new()
+            .await
+            .context("UDisks2 is needed to eject a disk")?;
+        let mut spec = HashMap::new();
+        spec.insert("path", path.into());
+        let block_path = client
+            .manager()
+            .resolve_device(spec, HashMap::default())
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("No device found for path: {}", path))?;
+        let drive_path = client.object(block_path)?.block().await?.drive().await?;
+        if drive_path.as_str() == "/" {
+            return Err(anyhow!(
+                "{} doesn't belong to a drive that can be ejected",
+                path
+            ));
+        }
+        let drive = client.object(drive_path)?.drive().await?;
+
+        let options: HashMap<&str, zbus::zvariant::Value<'_>> = [(
+            "auth.no_user_interaction",
+            zbus::zvariant::Value::from(false),
+        )]
+        .into_iter()
+        .collect();
+        if drive.can_power_off().await.unwrap_or(false) {
+            info!("Powering off {}", path);
+            drive
+                .power_off(options)
+                .await
+                .with_context(|| format!("Failed to power off {}", path))
+        } else if drive.ejectable().await.unwrap_or(false) {
+            info!("Ejecting the media in {}", path);
+            drive
+                .eject(options)
+                .await
+                .with_context(|| format!("Failed to eject {}", path))
+        } else {
+            Err(anyhow!(
+                "{} can't be ejected, remove it once it is idle",
+                path
+            ))
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::Result;
+    use anyhow::anyhow;
+    use std::fs::OpenOptions;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use tracing::info;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::Storage::FileSystem::{FILE_SHARE_READ, FILE_SHARE_WRITE};
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+    use windows_sys::Win32::System::Ioctl::IOCTL_STORAGE_EJECT_MEDIA;
+
+    pub async fn eject(path: &str) -> Result<()> {
+        let path = path.to_string();
+        tokio::task::spawn_blocking(move || {
+            let disk = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE)
+                .open(&path)
+                .map_err(|e| anyhow!("Failed to open {}: {}", path, e))?;
+            info!("Ejecting the media in {}", path);
+            let mut bytes_returned = 0u32;
+            let result = unsafe {
+                DeviceIoControl(
+                    disk.as_raw_handle() as HANDLE,
+                    IOCTL_STORAGE_EJECT_MEDIA,
+                    std::ptr::null(),
+                    0,
+                    std::ptr::null_mut(),
+                    0,
+                    &mut bytes_returned,
+                    std::ptr::null_mut(),
+                )
+            };
+            if result == 0 {
+                return Err(anyhow!(
+                    "Failed to eject {}: {}",
+                    path,
+                    std::io::Error::last_os_error()
+                ));
+            }
+            Ok(())
+        })
+        .await?
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::Result;
+    use anyhow::anyhow;
+
+    pub async fn eject(path: &str) -> Result<()> {
+        Err(anyhow!(
+            "Ejecting {} is not supported on this platform",
+            path
+        ))
+    }
+}
@@ -0,0 +1,281 @@
+/// Dotenv-style parser for golem.env that round-trips comments and unknown keys
+use std::fmt;
+
+/// A single line of an env file
+#[derive(Debug, Clone, PartialEq)]
+enum EnvLine {
+    /// `KEY=value` assignment; `raw` is the original text, kept until the value changes
+    Entry {
+        key: String,
+        value: String,
+        raw: String,
+    },
+    /// Comments, blank lines and anything that doesn't parse as an assignment
+    Other(String),
+}
+
+/// Parsed golem.env document
+///
+/// Lines are kept in their original order so that writing the document back only
+/// touches the values that were actually changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvFile {
+    lines: Vec<EnvLine>,
+}
+
+impl EnvFile {
+    /// Parse env file content
+    ///
+    /// Supports `KEY=value`, an optional `export ` prefix, double-quoted values with
+    /// backslash escapes, single-quoted literal values and trailing `# comments` on
+    /// unquoted values. Lines that are not valid assignments are preserved verbatim.
+    pub fn parse(content: &str) -> Self {
+        let lines = content
+            .lines()
+            .map(|line| match parse_assignment(line) {
+                Some((key, value)) => EnvLine::Entry {
+                    key,
+                    value,
+                    raw: line.to_string(),
+                },
+                None => EnvLine::Other(line.to_string()),
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    /// Get the value of a key; when a key is repeated the last assignment wins
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().rev().find_map(|line| match line {
+            EnvLine::Entry { key: k, value, .. } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Set a key, updating the existing assignment in place or appending a new one
+    ///
+    /// Duplicate assignments of the same key are collapsed into the first one.
+    pub fn set(&mut self, key: &str, value: &str) {
+        let mut found = false;
+        self.lines.retain_mut(|line| match line {
+            EnvLine::Entry {
+                key: k,
+                value: v,
+                raw,
+            } if k == key => {
+                if found {
+                    return false;
+                }
+                found = true;
+                if v != value {
+                    *v = value.to_string();
+                    *raw = format_assignment(key, value);
+                }
+                true
+            }
+            _ => true,
+        });
+
+        if !found {
+            self.lines.push(EnvLine::Entry {
+                key: key.to_string(),
+                value: value.to_string(),
+                raw: format_assignment(key, value),
+            });
+        }
+    }
+
+    /// Remove every assignment of a key
+    pub fn remove(&mut self, key: &str) {
+        self.lines
+            .retain(|line| !matches!(line, EnvLine::Entry { key: k, .. } if k == key));
+    }
+
+    /// Iterate over `(key, value)` pairs in file order
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            EnvLine::Entry { key, value, .. } => Some((key.as_str(), value.as_str())),
+            EnvLine::Other(_) => None,
+        })
+    }
+
+    /// Render the document back to text with a trailing newline
+    pub fn to_content(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for EnvFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                EnvLine::Entry { raw, .. } => writeln!(f, "{}", raw)?,
+                EnvLine::Other(text) => writeln!(f, "{}", text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a single `KEY=value` line, returning None for comments and invalid lines
+fn parse_assignment(line: &str) -> Option<(String, String)> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+
+    let trimmed = trimmed.strip_prefix("export ").unwrap_or(trimmed);
+    let (key, value) = trimmed.split_once('=')?;
+    let key = key.trim();
+
+    if !is_valid_key(key) {
+        return None;
+    }
+
+    Some((key.to_string(), parse_value(value.trim_start())?))
+}
+
+/// Parse the right-hand side of an assignment
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut result = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(result),
+                '\\' => match chars.next()? {
+                    'n' => result.push('\n'),
+                    't' => result.push('\t'),
+                    other => result.push(other),
+                },
+                other => result.push(other),
+            }
+        }
+        // Unterminated double quote
+        None
+    } else if let Some(rest) = value.strip_prefix('\'') {
+        rest.find('\'').map(|end| rest[..end].to_string())
+    } else {
+        // Unquoted: an inline comment starts at whitespace followed by '#'
+        let end = value
+            .char_indices()
+            .find(|&(i, c)| c == '#' && i > 0 && value[..i].ends_with(char::is_whitespace))
+            .map(|(i, _)| i)
+            .unwrap_or(value.len());
+        Some(value[..end].trim_end().to_string())
+    }
+}
+
+/// Env keys must look like shell identifiers
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Format an assignment, quoting the value only when it would not round-trip unquoted
+fn format_assignment(key: &str, value: &str) -> String {
+    let needs_quotes = value
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '#' | '\\'));
+
+    if needs_quotes {
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\t', "\\t");
+        format!("{}=\"{}\"", key, escaped)
+    } else {
+        format!("{}={}", key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_basic_and_quoted_values() {
+        let env = EnvFile::parse(
+            "# comment\nSUBNET=public\nexport YA_NET_TYPE=central\nNAME=\"my node\"\nRAW='a#b'\nEMPTY=\n",
+        );
+
+        assert_eq!(env.get("SUBNET"), Some("public"));
+        assert_eq!(env.get("YA_NET_TYPE"), Some("central"));
+        assert_eq!(env.get("NAME"), Some("my node"));
+        assert_eq!(env.get("RAW"), Some("a#b"));
+        assert_eq!(env.get("EMPTY"), Some(""));
+        assert_eq!(env.get("MISSING"), None);
+    }
+
+    #[test]
+    fn test_inline_comments_and_urls() {
+        let env = EnvFile::parse(
+            "YAGNA_METRICS_URL=https://metrics.golem.network:9092/ # default\nANCHOR=http://host/#frag\n",
+        );
+
+        assert_eq!(
+            env.get("YAGNA_METRICS_URL"),
+            Some("https://metrics.golem.network:9092/")
+        );
+        assert_eq!(env.get("ANCHOR"), Some("http://host/#frag"));
+    }
+
+    #[test]
+    fn test_last_assignment_wins() {
+        let env = EnvFile::parse("SUBNET=one\nSUBNET=two\n");
+        assert_eq!(env.get("SUBNET"), Some("two"));
+    }
+
+    #[test]
+    fn test_invalid_lines_are_preserved() {
+        let content = "not an assignment\n1BAD=value\nGOOD=value\n";
+        let env = EnvFile::parse(content);
+
+        assert_eq!(env.entries().count(), 1);
+        assert_eq!(env.to_content(), content);
+    }
+
+    #[test]
+    fn test_round_trip_preserves_comments_and_unknown_keys() {
+        let content = "# Golem env\nYA_DEBUG=1\nSUBNET=public   # keep me\n\nCUSTOM_VAR=\"hello world\"\n";
+        let mut env = EnvFile::parse(content);
+
+        assert_eq!(env.to_content(), content);
+
+        env.set("SUBNET", "devnet-beta");
+        env.set("YA_NET_TYPE", "hybrid");
+
+        assert_eq!(
+            env.to_content(),
+            "# Golem env\nYA_DEBUG=1\nSUBNET=devnet-beta\n\nCUSTOM_VAR=\"hello world\"\nYA_NET_TYPE=hybrid\n"
+        );
+    }
+
+    #[test]
+    fn test_set_unchanged_value_keeps_original_line() {
+        let mut env = EnvFile::parse("SUBNET=public # comment\n");
+        env.set("SUBNET", "public");
+        assert_eq!(env.to_content(), "SUBNET=public # comment\n");
+    }
+
+    #[test]
+    fn test_set_collapses_duplicates_and_remove() {
+        let mut env = EnvFile::parse("A=1\nB=2\nA=3\n");
+        env.set("A", "4");
+        assert_eq!(env.to_content(), "A=4\nB=2\n");
+
+        env.remove("A");
+        assert_eq!(env.to_content(), "B=2\n");
+    }
+
+    #[test]
+    fn test_quoting_round_trips() {
+        let mut env = EnvFile::default();
+        env.set("VALUE", "with \"quotes\" and #hash");
+        let reparsed = EnvFile::parse(&env.to_content());
+        assert_eq!(reparsed.get("VALUE"), Some("with \"quotes\" and #hash"));
+    }
+}
@@ -0,0 +1,400 @@
+// Sector sizes of a disk
+//
+// GPT addresses a disk in logical sectors, while direct I/O has to stay aligned to the
+// physical sectors. Cards report 512-byte logical sectors almost without exception, but
+// 4K-native SSDs behind USB adapters don't, so both sizes are asked from the OS (sysfs on
+// Linux, IOCTL_STORAGE_QUERY_PROPERTY on Windows) the first time a device is used and kept
+// for the session. GPT parsing, aligned I/O and the configuration partition writer all
+// take them from here. Drives come as 512n (512-byte sectors throughout), 512e (512-byte
+// logical sectors emulated on 4K physical ones) or 4Kn; only the logical size changes LBA
+// math, the physical size only the alignment.
+
+use anyhow::{Result, anyhow};
+use std::fmt;
+use std::sync::RwLock;
+#[cfg(any(target_os = "linux", windows))]
+use tracing::debug;
+
+/// Logical sector size assumed when the OS doesn't report one
+pub const DEFAULT_LOGICAL_SECTOR_SIZE: u64 = 512;
+
+/// Physical sector size assumed when the OS doesn't report one
+pub const DEFAULT_PHYSICAL_SECTOR_SIZE: u64 = 4096;
+
+/// Smallest alignment of direct I/O
+///
+/// Windows refuses unbuffered I/O on some USB bridges that report 512-byte sectors but
+/// transfer 4K blocks, so reads and writes are never aligned to less.
+pub const MIN_IO_ALIGNMENT: u64 = 4096;
+
+/// Geometry of every device queried so far, by path
+static GEOMETRY_CACHE: RwLock<Vec<(String, SectorGeometry)>> = RwLock::new(Vec::new());
+
+/// Logical and physical sector size of a disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectorGeometry {
+    /// Unit of LBA addresses in the partition table
+    pub logical: u64,
+    /// Size of the blocks the disk writes internally
+    pub physical: u64,
+}
+
+/// How a drive's logical sectors relate to its physical ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectorFormat {
+    /// 512-byte logical and physical sectors
+    Native512,
+    /// 512-byte logical sectors emulated on larger physical ones
+    Emulated512,
+    /// 4096-byte logical sectors
+    Native4K,
+    /// Any other logical sector size, which GPT images aren't written for
+    Other(u64),
+}
+
+impl fmt::Display for SectorFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SectorFormat::Native512 => write!(f, "512n"),
+            SectorFormat::Emulated512 => write!(f, "512e"),
+            SectorFormat::Native4K => write!(f, "4Kn"),
+            SectorFormat::Other(logical) => write!(f, "{}-byte logical sectors", logical),
+        }
+    }
+}
+
+impl Default for SectorGeometry {
+    fn default() -> Self {
+        SectorGeometry {
+            logical: DEFAULT_LOGICAL_SECTOR_SIZE,
+            physical: DEFAULT_PHYSICAL_SECTOR_SIZE,
+        }
+    }
+}
+
+impl SectorGeometry {
+    /// Geometry from the sizes a device reports, replacing implausible values with defaults
+    pub fn new(logical: u64, physical: u64) -> Self {
+        let logical = if logical >= 512 && logical.is_power_of_two() {
+            logical
+        } else {
+            DEFAULT_LOGICAL_SECTOR_SIZE
+        };
+        let physical = if physical >= logical && physical.is_power_of_two() {
+            physical
+        } else {
+            logical.max(DEFAULT_PHYSICAL_SECTOR_SIZE)
+        };
+        SectorGeometry { logical, physical }
+    }
+
+    /// Alignment of offsets and lengths for direct I/O
+    pub fn io_alignment(&self) -> u64 {
+        self.physical.max(MIN_IO_ALIGNMENT)
+    }
+
+    /// `offset` rounded down to the I/O alignment
+    pub fn align_down(&self, offset: u64) -> u64 {
+        offset / self.io_alignment() * self.io_alignment()
+    }
+
+    /// `length` rounded up to the I/O alignment
+    pub fn align_up(&self, length: u64) -> u64 {
+        length.div_ceil(self.io_alignment()) * self.io_alignment()
+    }
+
+    /// Byte offset of a logical block address
+    pub fn lba_offset(&self, lba: u64) -> u64 {
+        lba * self.logical
+    }
+
+    /// Whether the disk is 512n, 512e or 4Kn
+    pub fn format(&self) -> SectorFormat {
+        match (self.logical, self.physical) {
+            (512, 512) => SectorFormat::Native512,
+            (512, _) => SectorFormat::Emulated512,
+            (4096, _) => SectorFormat::Native4K,
+            (logical, _) => SectorFormat::Other(logical),
+        }
+    }
+
+    /// Block size to open the partition table with
+    ///
+    /// Fails for logical sector sizes the GPT parser has no block size for, rather than
+    /// reading the table in sectors of the wrong size.
+    pub fn gpt_block_size(&self) -> Result<gpt::disk::LogicalBlockSize> {
+        match self.logical {
+            512 => Ok(gpt::disk::LogicalBlockSize::Lb512),
+            4096 => Ok(gpt::disk::LogicalBlockSize::Lb4096),
+            logical => Err(anyhow!(
+                "Partition tables of disks with {}-byte logical sectors are not supported",
+                logical
+            )),
+        }
+    }
+}
+
+/// Sector sizes of the disk at `path`
+///
+/// The OS is asked the first time a path is seen; later calls return the same answer.
+/// This never fails: a disk whose sizes can't be read gets the defaults.
+pub fn query(path: &str) -> SectorGeometry {
+    let cached = match GEOMETRY_CACHE.read() {
+        Ok(cache) => lookup(&cache, path),
+        Err(poisoned) => lookup(&poisoned.into_inner(), path),
+    };
+    if let Some(geometry) = cached {
+        return geometry;
+    }
+
+    let geometry = platform::query(path).unwrap_or_default();
+    remember(path, geometry);
+    geometry
+}
+
+/// Answer later queries of `path` with `geometry`, e.g. for a disk image in place of a device
+pub fn remember(path: &str, geometry: SectorGeometry) {
+    let mut cache = match GEOMETRY_CACHE.write() {
+        Ok(cache) => cache,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    cache.retain(|(cached_path, _)| cached_path != path);
+    cache.push((path.to_string(), geometry));
+}
+
+fn lookup(cache: &[(String, SectorGeometry)], path: &str) -> Option<SectorGeometry> {
+    cache
+        .iter()
+        .find(|(cached_path, _)| cached_path == path)
+        .map(|(_, geometry)| *geometry)
+}
+
+/// The value of a sysfs `queue/*_block_size` attribute
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_block_size(content: &str) -> Option<u64> {
+    content.trim().parse().ok().filter(|&size| size > 0)
+}
+
+/// Logical and physical sector size from a STORAGE_ACCESS_ALIGNMENT_DESCRIPTOR
+#[cfg_attr(not(windows), allow(dead_code))]
+fn parse_alignment_descriptor(buffer: &[u8]) -> Option<(u64, u64)> {
+    let read_u32 = |offset: usize| -> Option<u64> {
+        let bytes = buffer.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?) as u64)
+    };
+    // Version, Size, BytesPerCacheLine and BytesOffsetForCacheAlignment come first
+    let logical = read_u32(16)?;
+    let physical = read_u32(20)?;
+    (logical > 0).then_some((logical, physical))
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{SectorGeometry, debug, parse_block_size};
+    use std::fs;
+    use std::path::Path;
+
+    pub fn query(path: &str) -> Option<SectorGeometry> {
+        let canonical = fs::canonicalize(path)
+            .map_err(|e| debug!("Geometry: cannot resolve {}: {}", path, e))
+            .ok()?;
+        let name = canonical.file_name()?.to_str()?;
+        let queue = Path::new("/sys/class/block").join(name).join("queue");
+        let read = |file: &str| {
+            fs::read_to_string(queue.join(file))
+                .ok()
+                .and_then(|content| parse_block_size(&content))
+        };
+
+        let logical = read("logical_block_size")?;
+        let physical = read("physical_block_size").unwrap_or(logical);
+        let geometry = SectorGeometry::new(logical, physical);
+        debug!("Geometry of {}: {:?}", path, geometry);
+        Some(geometry)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{SectorGeometry, debug, parse_alignment_descriptor};
+    use std::fs::OpenOptions;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::Storage::FileSystem::{FILE_SHARE_READ, FILE_SHARE_WRITE};
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+    use windows_sys::Win32::System::Ioctl::{DISK_GEOMETRY, IOCTL_DISK_GET_DRIVE_GEOMETRY};
+
+    // CTL_CODE(IOCTL_STORAGE_BASE, 0x0500, METHOD_BUFFERED, FILE_ANY_ACCESS)
+    const IOCTL_STORAGE_QUERY_PROPERTY: u32 = 0x002D_1400;
+
+    #[repr(C)]
+    struct StoragePropertyQuery {
+        property_id: u32, // StorageAccessAlignmentProperty = 6
+        query_type: u32,  // PropertyStandardQuery = 0
+        additional_parameters: [u8; 1],
+    }
+
+    pub fn query(path: &str) -> Option<SectorGeometry> {
+        // Device lists use bare drive numbers for physical drives
+        let device_path = if path.parse::<u32>().is_ok() {
+            format!(r"\\.\PhysicalDrive{}", path)
+        } else {
+            path.to_string()
+        };
+
+        // Neither query needs access rights
+        let file = OpenOptions::new()
+            .access_mode(0)
+            .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE)
+            .open(&device_path)
+            .map_err(|e| debug!("Geometry: cannot open {}: {}", device_path, e))
+            .ok()?;
+        let handle = file.as_raw_handle() as HANDLE;
+
+        let geometry = match query_alignment(handle) {
+            Some((logical, physical)) => SectorGeometry::new(logical, physical),
+            // Older drivers only report the logical sector size
+            None => {
+                let logical = query_drive_geometry(handle)?;
+                SectorGeometry::new(logical, logical)
+            }
+        };
+        debug!("Geometry of {}: {:?}", device_path, geometry);
+        Some(geometry)
+    }
+
+    fn query_alignment(handle: HANDLE) -> Option<(u64, u64)> {
+        let query = StoragePropertyQuery {
+            property_id: 6,
+            query_type: 0,
+            additional_parameters: [0],
+        };
+        let mut buffer = [0u8; 28];
+        let mut bytes_returned: u32 = 0;
+        let result = unsafe {
+            DeviceIoControl(
+                handle,
+                IOCTL_STORAGE_QUERY_PROPERTY,
+                &query as *const _ as *const _,
+                std::mem::size_of::<StoragePropertyQuery>() as u32,
+                buffer.as_mut_ptr() as *mut _,
+                buffer.len() as u32,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+        if result == 0 {
+            debug!(
+                "Geometry: access alignment query failed: {}",
+                std::io::Error::last_os_error()
+            );
+            return None;
+        }
+        parse_alignment_descriptor(&buffer[..bytes_returned as usize])
+    }
+
+    fn query_drive_geometry(handle: HANDLE) -> Option<u64> {
+        let mut geometry: DISK_GEOMETRY = unsafe { std::mem::zeroed() };
+        let mut bytes_returned: u32 = 0;
+        let result = unsafe {
+            DeviceIoControl(
+                handle,
+                IOCTL_DISK_GET_DRIVE_GEOMETRY,
+                std::ptr::null(),
+                0,
+                &mut geometry as *mut _ as *mut _,
+                std::mem::size_of::<DISK_GEOMETRY>() as u32,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+        if result == 0 {
+            debug!(
+                "Geometry: drive geometry query failed: {}",
+                std::io::Error::last_os_error()
+            );
+            return None;
+        }
+        (geometry.BytesPerSector > 0).then_some(geometry.BytesPerSector as u64)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::SectorGeometry;
+
+    pub fn query(_path: &str) -> Option<SectorGeometry> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_implausible_sizes_fall_back_to_defaults() {
+        assert_eq!(SectorGeometry::new(512, 512).physical, 512);
+        assert_eq!(SectorGeometry::new(0, 0), SectorGeometry::default());
+        assert_eq!(SectorGeometry::new(520, 4096).logical, 512);
+
+        let native = SectorGeometry::new(4096, 512);
+        assert_eq!((native.logical, native.physical), (4096, 4096));
+    }
+
+    #[test]
+    fn test_alignment() {
+        let card = SectorGeometry::new(512, 512);
+        assert_eq!(card.io_alignment(), MIN_IO_ALIGNMENT);
+        assert_eq!(card.align_down(5000), 4096);
+        assert_eq!(card.align_up(5000), 8192);
+        assert_eq!(card.align_up(8192), 8192);
+        assert_eq!(card.lba_offset(2), 1024);
+
+        let large = SectorGeometry::new(4096, 16384);
+        assert_eq!(large.io_alignment(), 16384);
+        assert_eq!(large.lba_offset(2), 8192);
+    }
+
+    #[test]
+    fn test_sector_format() {
+        assert_eq!(
+            SectorGeometry::new(512, 512).format(),
+            SectorFormat::Native512
+        );
+        assert_eq!(
+            SectorGeometry::new(512, 4096).format(),
+            SectorFormat::Emulated512
+        );
+        assert_eq!(
+            SectorGeometry::new(4096, 4096).format(),
+            SectorFormat::Native4K
+        );
+        assert_eq!(SectorGeometry::default().format().to_string(), "512e");
+
+        // 4Kn drives need the partition table read in 4K blocks
+        assert!(matches!(
+            SectorGeometry::new(4096, 4096).gpt_block_size(),
+            Ok(gpt::disk::LogicalBlockSize::Lb4096)
+        ));
+        assert!(SectorGeometry::new(2048, 2048).gpt_block_size().is_err());
+    }
+
+    #[test]
+    fn test_parse_block_size() {
+        assert_eq!(parse_block_size("4096\n"), Some(4096));
+        assert_eq!(parse_block_size("0\n"), None);
+        assert_eq!(parse_block_size(""), None);
+    }
+
+    #[test]
+    fn test_parse_alignment_descriptor() {
+        let mut descriptor = vec![0u8; 28];
+        descriptor[16..20].copy_from_slice(&512u32.to_le_bytes());
+        descriptor[20..24].copy_from_slice(&4096u32.to_le_bytes());
+        assert_eq!(parse_alignment_descriptor(&descriptor), Some((512, 4096)));
+        assert_eq!(parse_alignment_descriptor(&descriptor[..20]), None);
+        assert_eq!(parse_alignment_descriptor(&[0u8; 28]), None);
+    }
+}
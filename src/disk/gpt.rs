@@ -0,0 +1,14 @@
+// GPT on-disk structures
+//
+// The configuration partition writer and the layout inspector parse the partition table
+// themselves instead of through the gpt crate, which takes ownership of the disk handle.
+// Shared pieces of that parsing live here.
+
+/// GUIDs as GPT headers and partition entries store them
+pub mod guid;
+/// Headers and partition entries, checked before they are used
+pub mod header;
+/// Random disk and partition GUIDs for flashed disks
+pub mod randomize;
+/// Verifying and repairing both headers after a flash
+pub mod repair;
@@ -0,0 +1,88 @@
+// GUIDs as GPT stores them
+//
+// GPT uses the mixed-endian UEFI layout: the first three fields of a GUID (bytes 0-3, 4-5
+// and 6-7) are stored little-endian, the last 8 bytes as they are. Reading them in the
+// RFC 4122 order `Uuid` expects means no partition ever matches, so every parser converts
+// through here.
+
+use uuid::Uuid;
+
+/// The GUID stored in the 16 bytes of a header or partition entry field
+pub fn from_gpt_bytes(bytes: [u8; 16]) -> Uuid {
+    Uuid::from_bytes(swap_fields(bytes))
+}
+
+/// The 16 bytes GPT stores for `uuid`
+pub fn to_gpt_bytes(uuid: &Uuid) -> [u8; 16] {
+    swap_fields(*uuid.as_bytes())
+}
+
+/// Reverse the byte order of the first three fields, which converts in both directions
+fn swap_fields(mut bytes: [u8; 16]) -> [u8; 16] {
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CASES: usize = 10_000;
+
+    /// Deterministic pseudo-random GUIDs (splitmix64), so failures can be reproduced
+    fn guids() -> impl Iterator<Item = Uuid> {
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = move || {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+        let random = std::iter::repeat_with(move || {
+            let high = next() as u128;
+            Uuid::from_u128((high << 64) | next() as u128)
+        });
+        [Uuid::nil(), Uuid::from_bytes([0xFF; 16])]
+            .into_iter()
+            .chain(random)
+            .take(CASES)
+    }
+
+    #[test]
+    fn test_known_guid() {
+        let stored = [
+            0xb8, 0x21, 0xb9, 0x33, 0xc5, 0xed, 0xa0, 0x46, 0x8b, 0xaa, 0xd0, 0xb7, 0xad, 0x84,
+            0xfc, 0x71,
+        ];
+        let uuid = Uuid::parse_str("33b921b8-edc5-46a0-8baa-d0b7ad84fc71").unwrap();
+        assert_eq!(from_gpt_bytes(stored), uuid);
+        assert_eq!(to_gpt_bytes(&uuid), stored);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for uuid in guids() {
+            assert_eq!(from_gpt_bytes(to_gpt_bytes(&uuid)), uuid, "{}", uuid);
+            let stored = to_gpt_bytes(&uuid);
+            assert_eq!(to_gpt_bytes(&from_gpt_bytes(stored)), stored, "{}", uuid);
+        }
+    }
+
+    #[test]
+    fn test_matches_uefi_layout() {
+        for uuid in guids() {
+            assert_eq!(to_gpt_bytes(&uuid), uuid.to_bytes_le(), "{}", uuid);
+
+            // Only the first three fields are reordered
+            let stored = to_gpt_bytes(&uuid);
+            let (time_low, time_mid, time_high, rest) = uuid.as_fields();
+            assert_eq!(stored[0..4], time_low.to_le_bytes());
+            assert_eq!(stored[4..6], time_mid.to_le_bytes());
+            assert_eq!(stored[6..8], time_high.to_le_bytes());
+            assert_eq!(stored[8..16], rest[..]);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+// GPT headers, checked before anything is allocated from them
+//
+// The header says where the partition array is and how large it is, and its entries say
+// where the partitions are. On a corrupt or crafted disk these fields can be anything, so
+// they are checked against the limits of the UEFI specification and against the size of
+// the disk before a buffer is sized or an offset is computed from them.
+
+use std::fmt;
+
+/// "EFI PART"
+pub const SIGNATURE: [u8; 8] = *b"EFI PART";
+
+/// Bytes of a partition entry written by every common partitioner
+pub const MIN_ENTRY_SIZE: u32 = 128;
+
+/// Largest partition entry accepted, the specification allows 128 * 2^n bytes
+pub const MAX_ENTRY_SIZE: u32 = 4096;
+
+/// Most partition entries accepted; 128 is what partitioners write
+pub const MAX_ENTRY_COUNT: u32 = 1024;
+
+/// What is wrong with a GPT header or partition entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GptError {
+    /// The sector doesn't start with the GPT signature
+    NoSignature,
+    /// The sector is shorter than a GPT header
+    Truncated { len: usize },
+    /// The size of a partition entry isn't a power of two from 128 to [`MAX_ENTRY_SIZE`]
+    EntrySize(u32),
+    /// More partition entries than [`MAX_ENTRY_COUNT`]
+    EntryCount(u32),
+    /// The partition array doesn't lie within the disk
+    EntriesOutOfRange {
+        lba: u64,
+        sectors: u64,
+        disk_sectors: u64,
+    },
+    /// A partition ends before it starts or beyond the end of the disk
+    PartitionOutOfRange {
+        index: u32,
+        first_lba: u64,
+        last_lba: u64,
+        disk_sectors: u64,
+    },
+}
+
+impl fmt::Display for GptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GptError::NoSignature => write!(f, "No valid GPT found on disk"),
+            GptError::Truncated { len } => {
+                write!(f, "GPT header is truncated ({} bytes)", len)
+            }
+            GptError::EntrySize(size) => {
+                write!(f, "GPT partition entries of {} bytes are not valid", size)
+            }
+            GptError::EntryCount(count) => write!(
+                f,
+                "GPT claims {} partition entries, at most {} are supported",
+                count, MAX_ENTRY_COUNT
+            ),
+            GptError::EntriesOutOfRange {
+                lba,
+                sectors,
+                disk_sectors,
+            } => write!(
+                f,
+                "GPT partition entries at LBA {} ({} sectors) lie outside the disk of {} sectors",
+                lba, sectors, disk_sectors
+            ),
+            GptError::PartitionOutOfRange {
+                index,
+                first_lba,
+                last_lba,
+                disk_sectors,
+            } => write!(
+                f,
+                "GPT partition {} spans LBA {}-{}, which is not within the disk of {} sectors",
+                index, first_lba, last_lba, disk_sectors
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GptError {}
+
+/// The fields of a GPT header that locate the partition array
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GptHeader {
+    pub my_lba: u64,
+    pub alternate_lba: u64,
+    pub first_usable_lba: u64,
+    pub last_usable_lba: u64,
+    pub entries_lba: u64,
+    pub entry_count: u32,
+    pub entry_size: u32,
+}
+
+impl GptHeader {
+    /// Parse the header in `sector`, checking the signature and the entry fields
+    ///
+    /// The CRCs are not checked, a header whose backup was moved is still usable.
+    pub fn parse(sector: &[u8]) -> Result<Self, GptError> {
+        if sector.len() < 92 {
+            return Err(GptError::Truncated { len: sector.len() });
+        }
+        if sector[0..8] != SIGNATURE {
+            return Err(GptError::NoSignature);
+        }
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(sector[offset..offset + 8].try_into().unwrap());
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(sector[offset..offset + 4].try_into().unwrap());
+
+        let header = GptHeader {
+            my_lba: u64_at(24),
+            alternate_lba: u64_at(32),
+            first_usable_lba: u64_at(40),
+            last_usable_lba: u64_at(48),
+            entries_lba: u64_at(72),
+            entry_count: u32_at(80),
+            entry_size: u32_at(84),
+        };
+        let size = header.entry_size;
+        if !(MIN_ENTRY_SIZE..=MAX_ENTRY_SIZE).contains(&size) || !size.is_power_of_two() {
+            return Err(GptError::EntrySize(size));
+        }
+        if header.entry_count > MAX_ENTRY_COUNT {
+            return Err(GptError::EntryCount(header.entry_count));
+        }
+        Ok(header)
+    }
+
+    /// Bytes of the partition array, at most [`MAX_ENTRY_COUNT`] * [`MAX_ENTRY_SIZE`]
+    pub fn entries_len(&self) -> u64 {
+        self.entry_count as u64 * self.entry_size as u64
+    }
+
+    /// Check that the partition array lies within a disk of `disk_sectors` sectors of
+    /// `sector_size` bytes
+    pub fn check_entries(&self, sector_size: u64, disk_sectors: u64) -> Result<(), GptError> {
+        let sectors = self.entries_len().div_ceil(sector_size);
+        let fits = self.entries_lba > 0
+            && self
+                .entries_lba
+                .checked_add(sectors)
+                .is_some_and(|end| end <= disk_sectors);
+        if !fits {
+            return Err(GptError::EntriesOutOfRange {
+                lba: self.entries_lba,
+                sectors,
+                disk_sectors,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Check that partition `index` spanning `first_lba..=last_lba` lies within a disk of
+/// `disk_sectors` sectors
+pub fn check_partition(
+    index: u32,
+    first_lba: u64,
+    last_lba: u64,
+    disk_sectors: u64,
+) -> Result<(), GptError> {
+    if first_lba == 0 || first_lba > last_lba || last_lba >= disk_sectors {
+        return Err(GptError::PartitionOutOfRange {
+            index,
+            first_lba,
+            last_lba,
+            disk_sectors,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(entries_lba: u64, entry_count: u32, entry_size: u32) -> Vec<u8> {
+        let mut sector = vec![0u8; 512];
+        sector[0..8].copy_from_slice(&SIGNATURE);
+        sector[24..32].copy_from_slice(&1u64.to_le_bytes());
+        sector[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+        sector[80..84].copy_from_slice(&entry_count.to_le_bytes());
+        sector[84..88].copy_from_slice(&entry_size.to_le_bytes());
+        sector
+    }
+
+    #[test]
+    fn test_parse_common_header() {
+        let parsed = GptHeader::parse(&header(2, 128, 128)).unwrap();
+        assert_eq!(parsed.my_lba, 1);
+        assert_eq!(parsed.entries_len(), 16384);
+        assert!(parsed.check_entries(512, 16384).is_ok());
+        assert!(GptHeader::parse(&header(2, 128, 512)).is_ok());
+    }
+
+    #[test]
+    fn test_implausible_fields_are_refused() {
+        assert_eq!(GptHeader::parse(&[0u8; 512]), Err(GptError::NoSignature));
+        assert_eq!(
+            GptHeader::parse(&header(2, 128, 0)),
+            Err(GptError::EntrySize(0))
+        );
+        assert_eq!(
+            GptHeader::parse(&header(2, 128, 200)),
+            Err(GptError::EntrySize(200))
+        );
+        assert_eq!(
+            GptHeader::parse(&header(2, u32::MAX, 128)),
+            Err(GptError::EntryCount(u32::MAX))
+        );
+
+        // The array runs off the end of the disk
+        let beyond = GptHeader::parse(&header(u64::MAX - 1, 128, 128)).unwrap();
+        assert!(matches!(
+            beyond.check_entries(512, 16384),
+            Err(GptError::EntriesOutOfRange { .. })
+        ));
+        let at_mbr = GptHeader::parse(&header(0, 128, 128)).unwrap();
+        assert!(at_mbr.check_entries(512, 16384).is_err());
+    }
+
+    #[test]
+    fn test_partition_range() {
+        assert!(check_partition(0, 2048, 4095, 8192).is_ok());
+        assert!(check_partition(0, 4096, 2048, 8192).is_err());
+        assert!(check_partition(0, 2048, 8192, 8192).is_err());
+        assert!(check_partition(0, 0, u64::MAX, 8192).is_err());
+    }
+}
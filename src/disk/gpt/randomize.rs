@@ -0,0 +1,189 @@
+// New GUIDs for a flashed disk
+//
+// Every disk flashed from an image carries the image's disk GUID and partition GUIDs.
+// Windows takes a second disk with the GUID of one already attached for a signature
+// collision and sets it offline, which the diskpart steps then have to fight with. Turned on
+// in the settings, the disk GUID of a flashed disk is replaced with a random one, and
+// optionally the unique GUIDs of its partitions as well. Only the primary header and array
+// are changed, the GPT repair that follows copies them to the backup.
+
+use super::guid;
+use super::header::{GptError, GptHeader};
+use super::repair::{read_sectors, seal, write_sectors};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Seek, Write};
+use tracing::info;
+use uuid::Uuid;
+
+/// Which GUIDs of a flashed disk are replaced with random ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuidRandomization {
+    /// Keep the GUIDs of the image
+    #[default]
+    Keep,
+    /// Replace the disk GUID
+    Disk,
+    /// Replace the disk GUID and the unique GUIDs of the partitions
+    ///
+    /// Images whose boot loader finds the root partition by `PARTUUID` no longer boot.
+    DiskAndPartitions,
+}
+
+impl fmt::Display for GuidRandomization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuidRandomization::Keep => write!(f, "Keep the image's GUIDs"),
+            GuidRandomization::Disk => write!(f, "New disk GUID"),
+            GuidRandomization::DiskAndPartitions => write!(f, "New disk and partition GUIDs"),
+        }
+    }
+}
+
+/// Replace the GUIDs `mode` names on a disk of `disk_sectors` sectors of `sector_size` bytes
+///
+/// The partition with the GUID `keep`, the configuration partition, keeps it. A disk
+/// without a GPT is left alone. Returns the GUIDs that were replaced and their
+/// replacements.
+pub fn randomize<D: Read + Write + Seek>(
+    disk: &mut D,
+    sector_size: u64,
+    disk_sectors: u64,
+    mode: GuidRandomization,
+    keep: Uuid,
+) -> Result<Vec<(Uuid, Uuid)>> {
+    let mut replaced = Vec::new();
+    if mode == GuidRandomization::Keep {
+        return Ok(replaced);
+    }
+
+    let mut primary = read_sectors(disk, sector_size, 1, 1)?;
+    let header = match GptHeader::parse(&primary) {
+        Ok(header) => header,
+        Err(GptError::NoSignature) => {
+            info!("No GPT signature found - no GUIDs to replace");
+            return Ok(replaced);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    header.check_entries(sector_size, disk_sectors)?;
+
+    let disk_guid = Uuid::new_v4();
+    replaced.push((
+        guid::from_gpt_bytes(primary[56..72].try_into().unwrap()),
+        disk_guid,
+    ));
+    primary[56..72].copy_from_slice(&guid::to_gpt_bytes(&disk_guid));
+
+    if mode == GuidRandomization::DiskAndPartitions {
+        let sectors = header.entries_len().div_ceil(sector_size);
+        let mut entries = read_sectors(disk, sector_size, header.entries_lba, sectors)?;
+        for index in 0..header.entry_count {
+            let entry =
+                &mut entries[(index * header.entry_size) as usize..][..header.entry_size as usize];
+            if entry[0..16].iter().all(|&byte| byte == 0) {
+                continue;
+            }
+            let unique_guid = guid::from_gpt_bytes(entry[16..32].try_into().unwrap());
+            if unique_guid == keep {
+                continue;
+            }
+            let new_guid = Uuid::new_v4();
+            entry[16..32].copy_from_slice(&guid::to_gpt_bytes(&new_guid));
+            replaced.push((unique_guid, new_guid));
+        }
+        let entries_crc = crc32fast::hash(&entries[..header.entries_len() as usize]);
+        primary[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+        write_sectors(disk, sector_size, header.entries_lba, &entries)?;
+    }
+
+    seal(&mut primary);
+    write_sectors(disk, sector_size, 1, &primary)?;
+    disk.flush().context("Failed to flush the new GUIDs")?;
+    Ok(replaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::repair::repair;
+    use super::*;
+    use crate::disk::layout::{GOLEM_CONFIG_PARTITION_GUID, read_layout};
+    use crate::disk::test_support::*;
+    use std::io::Cursor;
+
+    fn config_guid() -> Uuid {
+        Uuid::parse_str(GOLEM_CONFIG_PARTITION_GUID).unwrap()
+    }
+
+    /// The disk after randomizing and repairing it, as after a flash
+    fn flashed(mode: GuidRandomization) -> (Vec<u8>, Vec<(Uuid, Uuid)>) {
+        let mut cursor = Cursor::new(golem_disk(512, &[]));
+        let sectors = GOLEM_DISK_SIZE / 512;
+        let replaced = randomize(&mut cursor, 512, sectors, mode, config_guid()).unwrap();
+        repair(&mut cursor, 512, sectors).unwrap();
+        (cursor.into_inner(), replaced)
+    }
+
+    fn disk_guid(disk: &[u8], lba: u64) -> Uuid {
+        let offset = (lba * 512) as usize + 56;
+        guid::from_gpt_bytes(disk[offset..offset + 16].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_keep_leaves_the_disk_alone() {
+        let (disk, replaced) = flashed(GuidRandomization::Keep);
+        assert!(replaced.is_empty());
+        assert!(disk == golem_disk(512, &[]));
+    }
+
+    #[test]
+    fn test_new_disk_guid_in_both_headers() {
+        let image = golem_disk(512, &[]);
+        let (disk, replaced) = flashed(GuidRandomization::Disk);
+        let last_lba = GOLEM_DISK_SIZE / 512 - 1;
+
+        assert_eq!(replaced.len(), 1);
+        let (old, new) = replaced[0];
+        assert_eq!(old, disk_guid(&image, 1));
+        assert_ne!(new, old);
+        assert_eq!(disk_guid(&disk, 1), new);
+        assert_eq!(disk_guid(&disk, last_lba), new);
+        assert!(read_header(&disk, 1, 512).unwrap().crc_valid);
+        assert!(read_header(&disk, last_lba, 512).unwrap().crc_valid);
+
+        // The partitions keep their GUIDs
+        assert_eq!(read_entries(&disk, 2, 512), read_entries(&image, 2, 512));
+
+        // Two disks flashed from the same image no longer collide
+        let (other, _) = flashed(GuidRandomization::Disk);
+        assert_ne!(disk_guid(&other, 1), new);
+    }
+
+    #[test]
+    fn test_new_partition_guids_but_config_partition() {
+        let (disk, replaced) = flashed(GuidRandomization::DiskAndPartitions);
+        assert_eq!(replaced.len(), 2);
+        let root = Uuid::parse_str(ROOT_PARTITION_GUID).unwrap();
+        assert_eq!(replaced[1].0, root);
+
+        let layout = read_layout(&mut Cursor::new(&disk), 512).unwrap();
+        let guids: Vec<Option<Uuid>> = layout
+            .partitions
+            .iter()
+            .map(|partition| partition.guid)
+            .collect();
+        assert_eq!(guids, vec![Some(config_guid()), Some(replaced[1].1)]);
+
+        let last_lba = GOLEM_DISK_SIZE / 512 - 1;
+        let primary = read_header(&disk, 1, 512).unwrap();
+        let backup = read_header(&disk, last_lba, 512).unwrap();
+        assert!(primary.crc_valid && backup.crc_valid);
+        assert_eq!(crc32(read_entries(&disk, 2, 512)), primary.entries_crc);
+        assert_eq!(
+            read_entries(&disk, backup.entries_lba, 512),
+            read_entries(&disk, 2, 512)
+        );
+    }
+}
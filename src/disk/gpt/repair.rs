@@ -0,0 +1,475 @@
+// Verifying and repairing both GPT headers after a flash
+//
+// An image carries a partition table sized for the image: the backup header and partition
+// array sit in its last sectors and its usable range ends there. Written to a larger card,
+// the backup is left in the middle of the device while firmware and partitioners look for
+// it in the last sector, and the headers disagree with the device about its size. After a
+// flash both headers are rebuilt from the primary one for the actual device: the backup
+// header and array are placed at its end, the usable range is checked against the
+// partitions and extended to the backup array, as `sgdisk -e` does, and the header and
+// array CRCs are recomputed. What had to be changed is reported, and the result is read
+// back and checked before it is trusted.
+
+use super::header::{GptError, GptHeader};
+use anyhow::{Context, Result, anyhow};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom, Write};
+use tracing::info;
+
+/// Bytes of the header fields covered by the header CRC, as every partitioner writes it
+const HEADER_SIZE: usize = 92;
+
+/// One of the two copies of the partition table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderCopy {
+    Primary,
+    Backup,
+}
+
+impl fmt::Display for HeaderCopy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderCopy::Primary => write!(f, "primary"),
+            HeaderCopy::Backup => write!(f, "backup"),
+        }
+    }
+}
+
+/// Something that was wrong with the partition table and was put right
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    /// The backup header and array were not at the end of the device
+    BackupMoved { from: u64, to: u64 },
+    /// The backup header or array didn't match the primary ones
+    BackupRestored,
+    /// The CRC32 of a header didn't match the header
+    HeaderCrc(HeaderCopy),
+    /// The partition array CRC32 in a header didn't match the array
+    EntriesCrc(HeaderCopy),
+    /// The usable range ended before the backup array of the device
+    LastUsableLba { from: u64, to: u64 },
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Repair::BackupMoved { from, to } => {
+                write!(f, "moved the backup header from LBA {} to LBA {}", from, to)
+            }
+            Repair::BackupRestored => {
+                write!(
+                    f,
+                    "rewrote the backup header and array from the primary ones"
+                )
+            }
+            Repair::HeaderCrc(copy) => write!(f, "fixed the {} header CRC", copy),
+            Repair::EntriesCrc(copy) => {
+                write!(f, "fixed the partition array CRC of the {} header", copy)
+            }
+            Repair::LastUsableLba { from, to } => {
+                write!(f, "extended the last usable LBA from {} to {}", from, to)
+            }
+        }
+    }
+}
+
+/// What [`repair`] changed, nothing if the partition table was consistent
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    pub repairs: Vec<Repair>,
+}
+
+impl RepairReport {
+    pub fn is_empty(&self) -> bool {
+        self.repairs.is_empty()
+    }
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.repairs.is_empty() {
+            return write!(f, "nothing to repair");
+        }
+        for (i, repair) in self.repairs.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", repair)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check both GPT headers of a disk of `disk_sectors` sectors of `sector_size` bytes and
+/// rebuild them from the primary one where they are wrong
+///
+/// `disk` must accept reads and writes at any offset, e.g. an [`AlignedDiskIo`]. A disk
+/// without a GPT is left alone. Partitions reaching beyond the usable range of the disk
+/// are an error, as is a table that doesn't read back as written.
+///
+/// [`AlignedDiskIo`]: crate::disk::AlignedDiskIo
+pub fn repair<D: Read + Write + Seek>(
+    disk: &mut D,
+    sector_size: u64,
+    disk_sectors: u64,
+) -> Result<RepairReport> {
+    let mut report = RepairReport::default();
+
+    let mut primary = read_sectors(disk, sector_size, 1, 1)?;
+    let header = match GptHeader::parse(&primary) {
+        Ok(header) => header,
+        Err(GptError::NoSignature) => {
+            info!("No GPT signature found - nothing to repair");
+            return Ok(report);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    header.check_entries(sector_size, disk_sectors)?;
+    let entries_sectors = header.entries_len().div_ceil(sector_size);
+    let entries = read_sectors(disk, sector_size, header.entries_lba, entries_sectors)?;
+    let entries_crc = crc32fast::hash(&entries[..header.entries_len() as usize]);
+    let entries_end = header.entries_lba + entries_sectors;
+
+    // The backup header takes the last sector, with its array right before it
+    let backup_lba = disk_sectors - 1;
+    let backup_entries_lba = backup_lba
+        .checked_sub(entries_sectors)
+        .filter(|&lba| lba > entries_end)
+        .ok_or_else(|| {
+            anyhow!(
+                "The disk of {} sectors is too small for a GPT",
+                disk_sectors
+            )
+        })?;
+    let first_usable_lba = header.first_usable_lba;
+    let last_usable_lba = backup_entries_lba - 1;
+    if first_usable_lba < entries_end || first_usable_lba > last_usable_lba {
+        return Err(anyhow!(
+            "The first usable LBA {} doesn't lie between the partition arrays",
+            first_usable_lba
+        ));
+    }
+    check_partitions(&header, &entries, first_usable_lba, last_usable_lba)?;
+
+    info!(
+        "GPT: backup header at LBA {} (expected {}), usable LBAs {}-{} (expected {}-{})",
+        header.alternate_lba,
+        backup_lba,
+        header.first_usable_lba,
+        header.last_usable_lba,
+        first_usable_lba,
+        last_usable_lba
+    );
+
+    // The primary header, for the size of the device
+    if header_crc(&primary) != u32_at(&primary, 16) {
+        report.repairs.push(Repair::HeaderCrc(HeaderCopy::Primary));
+    }
+    if u32_at(&primary, 88) != entries_crc {
+        report.repairs.push(Repair::EntriesCrc(HeaderCopy::Primary));
+    }
+    if header.last_usable_lba != last_usable_lba {
+        report.repairs.push(Repair::LastUsableLba {
+            from: header.last_usable_lba,
+            to: last_usable_lba,
+        });
+    }
+    primary[12..16].copy_from_slice(&(header_size(&primary) as u32).to_le_bytes());
+    primary[32..40].copy_from_slice(&backup_lba.to_le_bytes());
+    primary[48..56].copy_from_slice(&last_usable_lba.to_le_bytes());
+    primary[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+    seal(&mut primary);
+
+    // The backup header is the primary one with the locations swapped
+    let mut backup = primary.clone();
+    backup[24..32].copy_from_slice(&backup_lba.to_le_bytes());
+    backup[32..40].copy_from_slice(&1u64.to_le_bytes());
+    backup[72..80].copy_from_slice(&backup_entries_lba.to_le_bytes());
+    seal(&mut backup);
+
+    if header.alternate_lba != backup_lba {
+        report.repairs.push(Repair::BackupMoved {
+            from: header.alternate_lba,
+            to: backup_lba,
+        });
+    } else {
+        let current = read_sectors(disk, sector_size, backup_lba, 1)?;
+        let current_entries = read_sectors(disk, sector_size, backup_entries_lba, entries_sectors)?;
+        if let Some(repair) = backup_repair(&current, &current_entries, &backup, &entries) {
+            report.repairs.push(repair);
+        }
+    }
+
+    if report.is_empty() {
+        return Ok(report);
+    }
+
+    info!("Repairing the GPT: {}", report);
+    write_sectors(disk, sector_size, backup_entries_lba, &entries)?;
+    write_sectors(disk, sector_size, backup_lba, &backup)?;
+    write_sectors(disk, sector_size, 1, &primary)?;
+    disk.flush().context("Failed to flush the repaired GPT")?;
+
+    // Read both copies back, a table that isn't on the disk as written is no repair
+    for (copy, lba) in [(HeaderCopy::Primary, 1), (HeaderCopy::Backup, backup_lba)] {
+        let sector = read_sectors(disk, sector_size, lba, 1)?;
+        let written = GptHeader::parse(&sector)?;
+        let array = read_sectors(disk, sector_size, written.entries_lba, entries_sectors)?;
+        let valid = header_crc(&sector) == u32_at(&sector, 16)
+            && crc32fast::hash(&array[..written.entries_len() as usize]) == u32_at(&sector, 88)
+            && written.my_lba == lba;
+        if !valid {
+            return Err(anyhow!(
+                "The {} GPT header at LBA {} doesn't read back as written",
+                copy,
+                lba
+            ));
+        }
+    }
+    Ok(report)
+}
+
+/// What is wrong with the backup header `current` and array `current_entries` that should
+/// be `expected` and `entries`, if anything
+fn backup_repair(
+    current: &[u8],
+    current_entries: &[u8],
+    expected: &[u8],
+    entries: &[u8],
+) -> Option<Repair> {
+    let size = header_size(expected);
+    if current[..size] == expected[..size] && current_entries == entries {
+        return None;
+    }
+    // Only the CRCs are stale when everything else matches
+    let without_crcs = |header: &[u8]| {
+        let mut header = header[..size].to_vec();
+        header[16..20].fill(0);
+        header[88..92].fill(0);
+        header
+    };
+    if current_entries != entries || without_crcs(current) != without_crcs(expected) {
+        Some(Repair::BackupRestored)
+    } else if current[88..92] != expected[88..92] {
+        Some(Repair::EntriesCrc(HeaderCopy::Backup))
+    } else {
+        Some(Repair::HeaderCrc(HeaderCopy::Backup))
+    }
+}
+
+/// Check that every partition in `entries` lies within `first_lba..=last_lba`
+fn check_partitions(
+    header: &GptHeader,
+    entries: &[u8],
+    first_lba: u64,
+    last_lba: u64,
+) -> Result<()> {
+    for index in 0..header.entry_count {
+        let entry = &entries[(index * header.entry_size) as usize..][..header.entry_size as usize];
+        if entry[0..16].iter().all(|&byte| byte == 0) {
+            continue;
+        }
+        let start = u64_at(entry, 32);
+        let end = u64_at(entry, 40);
+        if start < first_lba || start > end || end > last_lba {
+            return Err(anyhow!(
+                "GPT partition {} spans LBA {}-{}, outside the usable LBAs {}-{} of the disk",
+                index,
+                start,
+                end,
+                first_lba,
+                last_lba
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Bytes covered by the header CRC, the header size field if it is plausible
+fn header_size(sector: &[u8]) -> usize {
+    let size = u32_at(sector, 12) as usize;
+    if (HEADER_SIZE..=sector.len()).contains(&size) {
+        size
+    } else {
+        HEADER_SIZE
+    }
+}
+
+/// CRC32 of the header in `sector`, computed with its CRC field zeroed
+fn header_crc(sector: &[u8]) -> u32 {
+    let mut header = sector[..header_size(sector)].to_vec();
+    header[16..20].fill(0);
+    crc32fast::hash(&header)
+}
+
+/// Store the CRC32 of the header in `sector`
+pub(super) fn seal(sector: &mut [u8]) {
+    let crc = header_crc(sector);
+    sector[16..20].copy_from_slice(&crc.to_le_bytes());
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+pub(super) fn read_sectors<D: Read + Seek>(
+    disk: &mut D,
+    sector_size: u64,
+    lba: u64,
+    count: u64,
+) -> Result<Vec<u8>> {
+    let mut data = vec![0u8; (count * sector_size) as usize];
+    disk.seek(SeekFrom::Start(lba * sector_size))?;
+    disk.read_exact(&mut data)
+        .with_context(|| format!("Failed to read {} sectors at LBA {}", count, lba))?;
+    Ok(data)
+}
+
+pub(super) fn write_sectors<D: Write + Seek>(
+    disk: &mut D,
+    sector_size: u64,
+    lba: u64,
+    data: &[u8],
+) -> Result<()> {
+    disk.seek(SeekFrom::Start(lba * sector_size))?;
+    disk.write_all(data)
+        .with_context(|| format!("Failed to write {} bytes at LBA {}", data.len(), lba))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::geometry::SectorGeometry;
+    use crate::disk::test_support::*;
+    use std::io::Cursor;
+
+    fn repaired(disk: &[u8], sector_size: u64) -> (Vec<u8>, RepairReport) {
+        let mut cursor = Cursor::new(disk.to_vec());
+        let sectors = disk.len() as u64 / sector_size;
+        let report = repair(&mut cursor, sector_size, sectors).unwrap();
+        (cursor.into_inner(), report)
+    }
+
+    #[test]
+    fn test_backup_is_moved_to_end_of_device() {
+        // An image written to a device twice its size
+        let mut disk = golem_disk(512, &[("golem.env", "YA_NET_TYPE=central\n")]);
+        disk.resize(2 * GOLEM_DISK_SIZE as usize, 0);
+        let (fixed, report) = repaired(&disk, 512);
+
+        let old_backup_lba = GOLEM_DISK_SIZE / 512 - 1;
+        let last_lba = 2 * GOLEM_DISK_SIZE / 512 - 1;
+        assert_eq!(
+            report.repairs,
+            vec![
+                Repair::LastUsableLba {
+                    from: old_backup_lba - 33,
+                    to: last_lba - 33
+                },
+                Repair::BackupMoved {
+                    from: old_backup_lba,
+                    to: last_lba
+                },
+            ]
+        );
+
+        let primary = read_header(&fixed, 1, 512).unwrap();
+        assert_eq!(primary.backup_lba, last_lba);
+        assert!(primary.crc_valid);
+
+        let backup = read_header(&fixed, last_lba, 512).unwrap();
+        assert_eq!(backup.current_lba, last_lba);
+        assert_eq!(backup.backup_lba, 1);
+        assert_eq!(backup.entries_lba, last_lba - 32);
+        assert_eq!(backup.entries_crc, primary.entries_crc);
+        assert!(backup.crc_valid);
+        assert_eq!(
+            read_entries(&fixed, backup.entries_lba, 512),
+            read_entries(&fixed, 2, 512)
+        );
+        assert_eq!(crc32(read_entries(&fixed, 2, 512)), primary.entries_crc);
+
+        // The partitions themselves are untouched
+        let partitions = CONFIG_PARTITION_OFFSET as usize
+            ..(ROOT_PARTITION_OFFSET + ROOT_PARTITION_SIZE) as usize;
+        assert_eq!(fixed[partitions.clone()], disk[partitions]);
+
+        // And a GPT parser accepts the result
+        let image = DiskImage::new(&fixed);
+        let gpt = ::gpt::GptConfig::new()
+            .writable(false)
+            .logical_block_size(SectorGeometry::new(512, 512).gpt_block_size().unwrap())
+            .open_from_device(Box::new(image.file()))
+            .unwrap();
+        assert_eq!(gpt.partitions().len(), 2);
+
+        // Repairing again finds nothing to do
+        assert!(repaired(&fixed, 512).1.is_empty());
+    }
+
+    #[test]
+    fn test_consistent_table_is_left_alone() {
+        for sector_size in [512, 4096] {
+            let disk = golem_disk(sector_size, &[]);
+            let (fixed, report) = repaired(&disk, sector_size);
+            assert!(report.is_empty(), "{}", report);
+            assert!(fixed == disk);
+        }
+
+        let blank = vec![0u8; GOLEM_DISK_SIZE as usize];
+        let (fixed, report) = repaired(&blank, 512);
+        assert!(report.is_empty());
+        assert!(fixed == blank);
+    }
+
+    #[test]
+    fn test_stale_crcs_are_recomputed() {
+        let mut disk = golem_disk(4096, &[]);
+        let last_lba = GOLEM_DISK_SIZE / 4096 - 1;
+        // A partition renamed in the primary array without updating the headers
+        disk[2 * 4096 + 56] = b'C';
+        let (fixed, report) = repaired(&disk, 4096);
+        assert_eq!(
+            report.repairs,
+            vec![
+                Repair::EntriesCrc(HeaderCopy::Primary),
+                Repair::BackupRestored,
+            ]
+        );
+
+        let primary = read_header(&fixed, 1, 4096).unwrap();
+        let backup = read_header(&fixed, last_lba, 4096).unwrap();
+        assert!(primary.crc_valid && backup.crc_valid);
+        assert_eq!(primary.entries_crc, crc32(read_entries(&fixed, 2, 4096)));
+        assert_eq!(
+            read_entries(&fixed, backup.entries_lba, 4096),
+            read_entries(&fixed, 2, 4096)
+        );
+
+        // Only the backup header CRC is wrong
+        let mut disk = fixed;
+        disk[(last_lba * 4096) as usize + 16] ^= 0xFF;
+        let (_, report) = repaired(&disk, 4096);
+        assert_eq!(report.repairs, vec![Repair::HeaderCrc(HeaderCopy::Backup)]);
+        assert_eq!(report.to_string(), "fixed the backup header CRC");
+    }
+
+    #[test]
+    fn test_partitions_beyond_the_device_are_refused() {
+        // An image larger than the device it was written to
+        let mut disk = golem_disk(512, &[]);
+        disk.truncate(ROOT_PARTITION_OFFSET as usize + MIB as usize);
+        let mut cursor = Cursor::new(disk.clone());
+        let error = repair(&mut cursor, 512, disk.len() as u64 / 512).unwrap_err();
+        assert!(
+            error.to_string().contains("outside the usable LBAs"),
+            "{}",
+            error
+        );
+        assert!(cursor.into_inner() == disk);
+    }
+}
@@ -0,0 +1,456 @@
+// Basic disk health (S.M.A.R.T.) probing
+//
+// Queries a small set of health indicators straight from the device without relying on
+// smartctl: ATA SMART READ DATA via SG_IO / NVMe SMART log via the admin ioctl on Linux,
+// and IOCTL_STORAGE_PREDICT_FAILURE on Windows. Most USB card readers and SD cards don't
+// support any of this, in which case the health is reported as unknown.
+
+use std::fmt;
+#[cfg(any(target_os = "linux", windows))]
+use tracing::debug;
+
+/// Overall health verdict shown in the device selector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    Good,
+    Warning,
+    Failing,
+    Unknown,
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthStatus::Good => write!(f, "Healthy"),
+            HealthStatus::Warning => write!(f, "Worn"),
+            HealthStatus::Failing => write!(f, "Failing"),
+            HealthStatus::Unknown => write!(f, "Health unknown"),
+        }
+    }
+}
+
+/// Health information for a single disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskHealth {
+    pub status: HealthStatus,
+    /// Reallocated sector count (ATA attribute 5) or NVMe media errors
+    pub reallocated_sectors: Option<u64>,
+    /// Pending (unstable) sector count (ATA attribute 197)
+    pub pending_sectors: Option<u64>,
+    /// Percentage of rated endurance used (0-100+, NVMe "percentage used")
+    pub wear_level_percent: Option<u8>,
+    /// The drive itself predicts failure (NVMe critical warning / ATA threshold exceeded)
+    pub predicted_failure: bool,
+}
+
+impl DiskHealth {
+    pub fn unknown() -> Self {
+        Self {
+            status: HealthStatus::Unknown,
+            reallocated_sectors: None,
+            pending_sectors: None,
+            wear_level_percent: None,
+            predicted_failure: false,
+        }
+    }
+
+    /// Derive the overall status from the collected indicators
+    fn evaluate(mut self) -> Self {
+        let has_data = self.reallocated_sectors.is_some()
+            || self.pending_sectors.is_some()
+            || self.wear_level_percent.is_some()
+            || self.predicted_failure;
+
+        self.status = if !has_data {
+            HealthStatus::Unknown
+        } else if self.predicted_failure
+            || self.reallocated_sectors.is_some_and(|n| n >= 100)
+            || self.wear_level_percent.is_some_and(|w| w >= 100)
+        {
+            HealthStatus::Failing
+        } else if self.reallocated_sectors.is_some_and(|n| n > 0)
+            || self.pending_sectors.is_some_and(|n| n > 0)
+            || self.wear_level_percent.is_some_and(|w| w >= 90)
+        {
+            HealthStatus::Warning
+        } else {
+            HealthStatus::Good
+        };
+
+        self
+    }
+
+    /// Short human readable summary, e.g. "12 reallocated sectors, 35% worn"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if self.predicted_failure {
+            parts.push("drive predicts failure".to_string());
+        }
+        if let Some(n) = self.reallocated_sectors.filter(|n| *n > 0) {
+            parts.push(format!("{} reallocated sectors", n));
+        }
+        if let Some(n) = self.pending_sectors.filter(|n| *n > 0) {
+            parts.push(format!("{} pending sectors", n));
+        }
+        if let Some(w) = self.wear_level_percent {
+            parts.push(format!("{}% worn", w));
+        }
+
+        if parts.is_empty() {
+            self.status.to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Query the health of the disk at `path`
+///
+/// This never fails: any error (missing permissions, unsupported bridge, ...) results in
+/// [`HealthStatus::Unknown`]. The call performs blocking I/O and should be run off the UI thread.
+pub fn query_disk_health(path: &str) -> DiskHealth {
+    platform::query(path).map_or_else(DiskHealth::unknown, DiskHealth::evaluate)
+}
+
+/// Parse the 512-byte ATA SMART READ DATA structure
+///
+/// Returns None when none of the attributes we care about are present. Threshold checks
+/// need the separate threshold table, so `predicted_failure` is never set here.
+pub(crate) fn parse_ata_smart_attributes(data: &[u8]) -> Option<DiskHealth> {
+    const ATTRIBUTE_TABLE_OFFSET: usize = 2;
+    const ATTRIBUTE_SIZE: usize = 12;
+    const MAX_ATTRIBUTES: usize = 30;
+
+    if data.len() < ATTRIBUTE_TABLE_OFFSET + ATTRIBUTE_SIZE * MAX_ATTRIBUTES {
+        return None;
+    }
+
+    let mut health = DiskHealth::unknown();
+    let mut found_any = false;
+
+    for i in 0..MAX_ATTRIBUTES {
+        let entry = &data[ATTRIBUTE_TABLE_OFFSET + i * ATTRIBUTE_SIZE..][..ATTRIBUTE_SIZE];
+        let id = entry[0];
+        if id == 0 {
+            continue;
+        }
+
+        let normalized = entry[3];
+        let mut raw_bytes = [0u8; 8];
+        raw_bytes[..6].copy_from_slice(&entry[5..11]);
+        let raw = u64::from_le_bytes(raw_bytes);
+
+        match id {
+            // Reallocated sectors count
+            5 => {
+                health.reallocated_sectors = Some(raw);
+                found_any = true;
+            }
+            // Current pending sector count
+            197 => {
+                health.pending_sectors = Some(raw);
+                found_any = true;
+            }
+            // Wear leveling count (Samsung), SSD life left, media wearout indicator:
+            // the normalized value counts down from 100 as the flash wears out
+            177 | 231 | 233 if normalized <= 100 => {
+                health.wear_level_percent = Some(100 - normalized);
+                found_any = true;
+            }
+            _ => {}
+        }
+    }
+
+    found_any.then_some(health)
+}
+
+/// Parse the NVMe SMART / Health Information log page (log identifier 0x02)
+pub(crate) fn parse_nvme_smart_log(data: &[u8]) -> Option<DiskHealth> {
+    if data.len() < 512 {
+        return None;
+    }
+
+    let critical_warning = data[0];
+    let percentage_used = data[5];
+    let mut media_errors = [0u8; 8];
+    media_errors.copy_from_slice(&data[160..168]);
+
+    Some(DiskHealth {
+        status: HealthStatus::Unknown,
+        reallocated_sectors: Some(u64::from_le_bytes(media_errors)),
+        pending_sectors: None,
+        wear_level_percent: Some(percentage_used),
+        predicted_failure: critical_warning != 0,
+    })
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{DiskHealth, debug, parse_ata_smart_attributes, parse_nvme_smart_log};
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    const SG_IO: libc::c_ulong = 0x2285;
+    const SG_DXFER_FROM_DEV: libc::c_int = -3;
+    // _IOWR('N', 0x41, struct nvme_admin_cmd)
+    const NVME_IOCTL_ADMIN_CMD: libc::c_ulong = 0xC048_4E41;
+
+    #[repr(C)]
+    struct SgIoHdr {
+        interface_id: libc::c_int,
+        dxfer_direction: libc::c_int,
+        cmd_len: u8,
+        mx_sb_len: u8,
+        iovec_count: u16,
+        dxfer_len: u32,
+        dxferp: *mut libc::c_void,
+        cmdp: *mut u8,
+        sbp: *mut u8,
+        timeout: u32,
+        flags: u32,
+        pack_id: libc::c_int,
+        usr_ptr: *mut libc::c_void,
+        status: u8,
+        masked_status: u8,
+        msg_status: u8,
+        sb_len_wr: u8,
+        host_status: u16,
+        driver_status: u16,
+        resid: libc::c_int,
+        duration: u32,
+        info: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct NvmeAdminCmd {
+        opcode: u8,
+        flags: u8,
+        rsvd1: u16,
+        nsid: u32,
+        cdw2: u32,
+        cdw3: u32,
+        metadata: u64,
+        addr: u64,
+        metadata_len: u32,
+        data_len: u32,
+        cdw10: u32,
+        cdw11: u32,
+        cdw12: u32,
+        cdw13: u32,
+        cdw14: u32,
+        cdw15: u32,
+        timeout_ms: u32,
+        result: u32,
+    }
+
+    pub fn query(path: &str) -> Option<DiskHealth> {
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+            .map_err(|e| debug!("Health: cannot open {}: {}", path, e))
+            .ok()?;
+
+        if path.contains("nvme") {
+            query_nvme(file.as_raw_fd(), path)
+        } else {
+            query_ata(file.as_raw_fd(), path)
+        }
+    }
+
+    fn query_nvme(fd: libc::c_int, path: &str) -> Option<DiskHealth> {
+        let mut log = vec![0u8; 512];
+        let mut cmd = NvmeAdminCmd {
+            opcode: 0x02, // Get Log Page
+            nsid: 0xFFFF_FFFF,
+            addr: log.as_mut_ptr() as u64,
+            data_len: log.len() as u32,
+            // NUMDL (dwords - 1) in bits 31:16, log identifier 0x02 (SMART / Health)
+            cdw10: ((log.len() as u32 / 4 - 1) << 16) | 0x02,
+            timeout_ms: 5000,
+            ..Default::default()
+        };
+
+        let result = unsafe { libc::ioctl(fd, NVME_IOCTL_ADMIN_CMD, &mut cmd) };
+        if result != 0 {
+            debug!(
+                "Health: NVMe SMART log not available on {}: {}",
+                path,
+                std::io::Error::last_os_error()
+            );
+            return None;
+        }
+
+        parse_nvme_smart_log(&log)
+    }
+
+    fn query_ata(fd: libc::c_int, path: &str) -> Option<DiskHealth> {
+        // ATA PASS-THROUGH (16): PIO data-in, SMART READ DATA (B0h / D0h)
+        let mut cdb: [u8; 16] = [
+            0x85, 0x08, 0x0e, 0x00, 0xd0, 0x00, 0x01, 0x00, 0x00, 0x00, 0x4f, 0x00, 0xc2, 0x00,
+            0xb0, 0x00,
+        ];
+        let mut data = vec![0u8; 512];
+        let mut sense = [0u8; 32];
+
+        let mut hdr = SgIoHdr {
+            interface_id: b'S' as libc::c_int,
+            dxfer_direction: SG_DXFER_FROM_DEV,
+            cmd_len: cdb.len() as u8,
+            mx_sb_len: sense.len() as u8,
+            iovec_count: 0,
+            dxfer_len: data.len() as u32,
+            dxferp: data.as_mut_ptr() as *mut libc::c_void,
+            cmdp: cdb.as_mut_ptr(),
+            sbp: sense.as_mut_ptr(),
+            timeout: 5000,
+            flags: 0,
+            pack_id: 0,
+            usr_ptr: std::ptr::null_mut(),
+            status: 0,
+            masked_status: 0,
+            msg_status: 0,
+            sb_len_wr: 0,
+            host_status: 0,
+            driver_status: 0,
+            resid: 0,
+            duration: 0,
+            info: 0,
+        };
+
+        let result = unsafe { libc::ioctl(fd, SG_IO, &mut hdr) };
+        if result != 0 || hdr.host_status != 0 || hdr.status != 0 {
+            debug!(
+                "Health: ATA SMART passthrough not supported on {} (ioctl={}, status={}, host_status={})",
+                path, result, hdr.status, hdr.host_status
+            );
+            return None;
+        }
+
+        parse_ata_smart_attributes(&data)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{DiskHealth, debug, parse_ata_smart_attributes};
+    use std::fs::OpenOptions;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    // CTL_CODE(IOCTL_STORAGE_BASE, 0x0440, METHOD_BUFFERED, FILE_ANY_ACCESS)
+    const IOCTL_STORAGE_PREDICT_FAILURE: u32 = 0x002D_1100;
+
+    #[repr(C)]
+    struct StoragePredictFailure {
+        predict_failure: u32,
+        vendor_specific: [u8; 512],
+    }
+
+    pub fn query(path: &str) -> Option<DiskHealth> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(|e| debug!("Health: cannot open {}: {}", path, e))
+            .ok()?;
+
+        let mut prediction = StoragePredictFailure {
+            predict_failure: 0,
+            vendor_specific: [0u8; 512],
+        };
+        let mut bytes_returned: u32 = 0;
+
+        let result = unsafe {
+            DeviceIoControl(
+                file.as_raw_handle() as HANDLE,
+                IOCTL_STORAGE_PREDICT_FAILURE,
+                std::ptr::null_mut(),
+                0,
+                &mut prediction as *mut _ as *mut _,
+                std::mem::size_of::<StoragePredictFailure>() as u32,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+
+        if result == 0 {
+            debug!(
+                "Health: IOCTL_STORAGE_PREDICT_FAILURE not supported on {}: {}",
+                path,
+                std::io::Error::last_os_error()
+            );
+            return None;
+        }
+
+        // For ATA drives the vendor specific data is the SMART attribute table
+        let mut health = parse_ata_smart_attributes(&prediction.vendor_specific)
+            .unwrap_or_else(DiskHealth::unknown);
+        health.predicted_failure = prediction.predict_failure != 0;
+        Some(health)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::DiskHealth;
+
+    pub fn query(_path: &str) -> Option<DiskHealth> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ata_attribute(data: &mut [u8], slot: usize, id: u8, normalized: u8, raw: u64) {
+        let offset = 2 + slot * 12;
+        data[offset] = id;
+        data[offset + 3] = normalized;
+        data[offset + 5..offset + 11].copy_from_slice(&raw.to_le_bytes()[..6]);
+    }
+
+    #[test]
+    fn test_parse_ata_smart_attributes() {
+        let mut data = vec![0u8; 512];
+        ata_attribute(&mut data, 0, 5, 100, 12);
+        ata_attribute(&mut data, 1, 197, 100, 0);
+        ata_attribute(&mut data, 2, 177, 65, 1234);
+
+        let health = parse_ata_smart_attributes(&data).unwrap().evaluate();
+        assert_eq!(health.reallocated_sectors, Some(12));
+        assert_eq!(health.pending_sectors, Some(0));
+        assert_eq!(health.wear_level_percent, Some(35));
+        assert_eq!(health.status, HealthStatus::Warning);
+    }
+
+    #[test]
+    fn test_parse_ata_without_known_attributes() {
+        let mut data = vec![0u8; 512];
+        ata_attribute(&mut data, 0, 9, 100, 5000); // power-on hours only
+        assert!(parse_ata_smart_attributes(&data).is_none());
+        assert!(parse_ata_smart_attributes(&[0u8; 16]).is_none());
+    }
+
+    #[test]
+    fn test_parse_nvme_smart_log() {
+        let mut log = vec![0u8; 512];
+        log[5] = 7;
+        let health = parse_nvme_smart_log(&log).unwrap().evaluate();
+        assert_eq!(health.wear_level_percent, Some(7));
+        assert_eq!(health.reallocated_sectors, Some(0));
+        assert_eq!(health.status, HealthStatus::Good);
+
+        log[0] = 0x04; // reliability degraded
+        let health = parse_nvme_smart_log(&log).unwrap().evaluate();
+        assert_eq!(health.status, HealthStatus::Failing);
+    }
+
+    #[test]
+    fn test_unknown_without_data() {
+        assert_eq!(DiskHealth::unknown().evaluate().status, HealthStatus::Unknown);
+    }
+}
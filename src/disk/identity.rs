@@ -0,0 +1,351 @@
+// Stable device identification
+//
+// PhysicalDrive numbers and /dev/sdX names are handed out in enumeration order, so they
+// can point at a different disk after a device is unplugged or re-enumerates. Serial
+// numbers and WWNs stay with the hardware: on Linux they come from the udev database
+// (falling back to sysfs), on Windows from IOCTL_STORAGE_QUERY_PROPERTY.
+
+#[cfg(any(target_os = "linux", windows))]
+use tracing::debug;
+
+/// Hardware identifiers of a disk, independent of the path it is currently reachable at
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceIdentity {
+    /// Device or media serial number
+    pub serial: Option<String>,
+    /// World Wide Name, mostly available for SATA/SAS/NVMe disks
+    pub wwn: Option<String>,
+    /// Vendor identifier (USB VID on Linux, SCSI vendor string on Windows)
+    pub vendor_id: Option<String>,
+    /// Product identifier (USB PID on Linux, SCSI product string on Windows)
+    pub product_id: Option<String>,
+}
+
+impl DeviceIdentity {
+    /// True when there is at least a serial number or WWN to compare
+    pub fn is_known(&self) -> bool {
+        self.serial.is_some() || self.wwn.is_some()
+    }
+
+    /// Whether two identities describe the same physical device
+    ///
+    /// Returns None when there is not enough information on both sides to tell, in
+    /// which case callers have to fall back to comparing paths and sizes.
+    pub fn same_device(&self, other: &DeviceIdentity) -> Option<bool> {
+        if let (Some(a), Some(b)) = (&self.wwn, &other.wwn) {
+            return Some(a == b);
+        }
+
+        let (Some(a), Some(b)) = (&self.serial, &other.serial) else {
+            return None;
+        };
+
+        // Cheap devices sometimes share a generic serial, the vendor/product ids tell them apart
+        let differs =
+            |x: &Option<String>, y: &Option<String>| matches!((x, y), (Some(x), Some(y)) if x != y);
+        Some(
+            a == b
+                && !differs(&self.vendor_id, &other.vendor_id)
+                && !differs(&self.product_id, &other.product_id),
+        )
+    }
+}
+
+impl std::fmt::Display for DeviceIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.wwn, &self.serial) {
+            (Some(wwn), _) => write!(f, "WWN {}", wwn),
+            (None, Some(serial)) => write!(f, "S/N {}", serial),
+            (None, None) => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Query the hardware identity of the disk at `path`
+///
+/// This never fails: identifiers that can't be read are left empty. The call performs
+/// blocking I/O and should be run off the UI thread.
+pub fn query_device_identity(path: &str) -> DeviceIdentity {
+    platform::query(path).unwrap_or_default()
+}
+
+/// Clean up an identifier read from the device, dropping empty and placeholder values
+#[cfg_attr(not(any(target_os = "linux", windows)), allow(dead_code))]
+fn normalize(value: &str) -> Option<String> {
+    let value = value.trim().trim_matches('\0').trim();
+    if value.is_empty() || value.chars().all(|c| c == '0') {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Parse the identity from a udev database entry (`/run/udev/data/b<major>:<minor>`)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_udev_properties(content: &str) -> DeviceIdentity {
+    let mut identity = DeviceIdentity::default();
+    let mut serial_fallback = None;
+
+    for line in content.lines() {
+        let Some((key, value)) = line.strip_prefix("E:").and_then(|p| p.split_once('=')) else {
+            continue;
+        };
+
+        match key {
+            "ID_SERIAL_SHORT" => identity.serial = normalize(value),
+            "ID_SERIAL" => serial_fallback = normalize(value),
+            "ID_WWN_WITH_EXTENSION" => identity.wwn = normalize(value),
+            "ID_WWN" if identity.wwn.is_none() => identity.wwn = normalize(value),
+            "ID_VENDOR_ID" => identity.vendor_id = normalize(value),
+            "ID_MODEL_ID" => identity.product_id = normalize(value),
+            _ => {}
+        }
+    }
+
+    if identity.serial.is_none() {
+        identity.serial = serial_fallback;
+    }
+    identity
+}
+
+/// Parse a STORAGE_DEVICE_DESCRIPTOR returned by IOCTL_STORAGE_QUERY_PROPERTY
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn parse_storage_device_descriptor(data: &[u8]) -> DeviceIdentity {
+    const VENDOR_ID_OFFSET: usize = 12;
+    const PRODUCT_ID_OFFSET: usize = 16;
+    const SERIAL_NUMBER_OFFSET: usize = 24;
+
+    // Each field holds the offset of a NUL-terminated string, 0 when not present
+    let string_at = |field: usize| -> Option<String> {
+        let bytes = data.get(field..field + 4)?;
+        let offset = u32::from_le_bytes(bytes.try_into().ok()?) as usize;
+        if offset == 0 || offset >= data.len() {
+            return None;
+        }
+        let tail = &data[offset..];
+        let end = tail.iter().position(|&b| b == 0).unwrap_or(tail.len());
+        normalize(&String::from_utf8_lossy(&tail[..end]))
+    };
+
+    DeviceIdentity {
+        serial: string_at(SERIAL_NUMBER_OFFSET),
+        wwn: None,
+        vendor_id: string_at(VENDOR_ID_OFFSET),
+        product_id: string_at(PRODUCT_ID_OFFSET),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{DeviceIdentity, debug, normalize, parse_udev_properties};
+    use std::fs;
+    use std::path::Path;
+
+    pub fn query(path: &str) -> Option<DeviceIdentity> {
+        // Resolve /dev/disk/by-* symlinks to the kernel name
+        let canonical = fs::canonicalize(path)
+            .map_err(|e| debug!("Identity: cannot resolve {}: {}", path, e))
+            .ok()?;
+        let name = canonical.file_name()?.to_str()?;
+        let sysfs = Path::new("/sys/class/block").join(name);
+
+        let mut identity = fs::read_to_string(sysfs.join("dev"))
+            .ok()
+            .and_then(|dev| fs::read_to_string(format!("/run/udev/data/b{}", dev.trim())).ok())
+            .map(|content| parse_udev_properties(&content))
+            .unwrap_or_default();
+
+        // Without udev (containers, minimal systems) sysfs still has the basics
+        let read = |file: &str| {
+            fs::read_to_string(sysfs.join(file))
+                .ok()
+                .and_then(|value| normalize(&value))
+        };
+        if identity.serial.is_none() {
+            identity.serial = read("device/serial").or_else(|| read("serial"));
+        }
+        if identity.wwn.is_none() {
+            identity.wwn = read("device/wwid").or_else(|| read("wwid"));
+        }
+
+        debug!("Identity of {}: {:?}", path, identity);
+        Some(identity)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{DeviceIdentity, debug, parse_storage_device_descriptor};
+    use std::fs::OpenOptions;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::Storage::FileSystem::{FILE_SHARE_READ, FILE_SHARE_WRITE};
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    // CTL_CODE(IOCTL_STORAGE_BASE, 0x0500, METHOD_BUFFERED, FILE_ANY_ACCESS)
+    const IOCTL_STORAGE_QUERY_PROPERTY: u32 = 0x002D_1400;
+
+    #[repr(C)]
+    struct StoragePropertyQuery {
+        property_id: u32, // StorageDeviceProperty = 0
+        query_type: u32,  // PropertyStandardQuery = 0
+        additional_parameters: [u8; 1],
+    }
+
+    pub fn query(path: &str) -> Option<DeviceIdentity> {
+        // Device lists use bare drive numbers for physical drives
+        let device_path = if path.parse::<u32>().is_ok() {
+            format!(r"\\.\PhysicalDrive{}", path)
+        } else if path.len() == 2 && path.ends_with(':') {
+            format!(r"\\.\{}", path)
+        } else {
+            path.to_string()
+        };
+
+        // No access rights are needed for the storage property query
+        let file = OpenOptions::new()
+            .access_mode(0)
+            .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE)
+            .open(&device_path)
+            .map_err(|e| debug!("Identity: cannot open {}: {}", device_path, e))
+            .ok()?;
+
+        let query = StoragePropertyQuery {
+            property_id: 0,
+            query_type: 0,
+            additional_parameters: [0],
+        };
+        let mut buffer = vec![0u8; 1024];
+        let mut bytes_returned: u32 = 0;
+
+        let result = unsafe {
+            DeviceIoControl(
+                file.as_raw_handle() as HANDLE,
+                IOCTL_STORAGE_QUERY_PROPERTY,
+                &query as *const _ as *const _,
+                std::mem::size_of::<StoragePropertyQuery>() as u32,
+                buffer.as_mut_ptr() as *mut _,
+                buffer.len() as u32,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+
+        if result == 0 {
+            debug!(
+                "Identity: IOCTL_STORAGE_QUERY_PROPERTY failed on {}: {}",
+                device_path,
+                std::io::Error::last_os_error()
+            );
+            return None;
+        }
+
+        buffer.truncate(bytes_returned as usize);
+        let identity = parse_storage_device_descriptor(&buffer);
+        debug!("Identity of {}: {:?}", device_path, identity);
+        Some(identity)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::DeviceIdentity;
+
+    pub fn query(_path: &str) -> Option<DeviceIdentity> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(serial: Option<&str>, wwn: Option<&str>, vendor: Option<&str>) -> DeviceIdentity {
+        DeviceIdentity {
+            serial: serial.map(str::to_string),
+            wwn: wwn.map(str::to_string),
+            vendor_id: vendor.map(str::to_string),
+            product_id: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_udev_properties() {
+        let content = "S:disk/by-id/usb-SanDisk_Ultra_4C530001-0:0\n\
+            E:ID_VENDOR_ID=0781\n\
+            E:ID_MODEL_ID=5581\n\
+            E:ID_SERIAL=SanDisk_Ultra_4C530001-0:0\n\
+            E:ID_SERIAL_SHORT=4C530001\n\
+            E:ID_WWN=0x5000000000000001\n";
+        let identity = parse_udev_properties(content);
+
+        assert_eq!(identity.serial.as_deref(), Some("4C530001"));
+        assert_eq!(identity.wwn.as_deref(), Some("0x5000000000000001"));
+        assert_eq!(identity.vendor_id.as_deref(), Some("0781"));
+        assert_eq!(identity.product_id.as_deref(), Some("5581"));
+    }
+
+    #[test]
+    fn test_parse_udev_serial_fallback() {
+        let identity = parse_udev_properties("E:ID_SERIAL=0x1234abcd\nE:ID_SERIAL_SHORT=   \n");
+        assert_eq!(identity.serial.as_deref(), Some("0x1234abcd"));
+        assert!(identity.wwn.is_none());
+    }
+
+    #[test]
+    fn test_parse_storage_device_descriptor() {
+        let mut data = vec![0u8; 128];
+        data[12..16].copy_from_slice(&64u32.to_le_bytes());
+        data[16..20].copy_from_slice(&80u32.to_le_bytes());
+        data[24..28].copy_from_slice(&100u32.to_le_bytes());
+        data[64..72].copy_from_slice(b"Generic ");
+        data[80..94].copy_from_slice(b"STORAGE DEVICE");
+        data[100..112].copy_from_slice(b"  000000001\0");
+
+        let identity = parse_storage_device_descriptor(&data);
+        assert_eq!(identity.vendor_id.as_deref(), Some("Generic"));
+        assert_eq!(identity.product_id.as_deref(), Some("STORAGE DEVICE"));
+        assert_eq!(identity.serial.as_deref(), Some("000000001"));
+
+        // Out of range offsets are ignored
+        data[24..28].copy_from_slice(&4096u32.to_le_bytes());
+        assert!(parse_storage_device_descriptor(&data).serial.is_none());
+    }
+
+    #[test]
+    fn test_same_device() {
+        let a = identity(Some("123"), None, Some("0781"));
+
+        assert_eq!(
+            a.same_device(&identity(Some("123"), None, Some("0781"))),
+            Some(true)
+        );
+        assert_eq!(
+            a.same_device(&identity(Some("456"), None, Some("0781"))),
+            Some(false)
+        );
+        assert_eq!(
+            a.same_device(&identity(Some("123"), None, Some("090c"))),
+            Some(false)
+        );
+        assert_eq!(
+            a.same_device(&identity(Some("123"), None, None)),
+            Some(true)
+        );
+        assert_eq!(a.same_device(&DeviceIdentity::default()), None);
+
+        // WWN takes precedence over the serial number
+        let b = identity(Some("123"), Some("naa.1"), None);
+        assert_eq!(
+            b.same_device(&identity(Some("999"), Some("naa.1"), None)),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_placeholder_serial_is_unknown() {
+        assert!(normalize("0000000000").is_none());
+        assert!(!parse_udev_properties("E:ID_SERIAL_SHORT=000000\n").is_known());
+    }
+}
@@ -0,0 +1,558 @@
+/// Read-only inspection of an existing partition table
+///
+/// Used to show users what is currently on a disk (partition names, sizes and
+/// filesystems) before it is overwritten.
+use anyhow::{Context, Result};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use uuid::Uuid;
+
+use super::gpt::guid;
+use super::gpt::header::GptHeader;
+
+/// Bytes read from the start of each partition when sniffing for a filesystem
+const PROBE_SIZE: usize = 4096;
+
+/// Unique GUID of the Golem configuration partition
+pub const GOLEM_CONFIG_PARTITION_GUID: &str = "33b921b8-edc5-46a0-8baa-d0b7ad84fc71";
+
+/// Partitioning scheme found on a disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionScheme {
+    Gpt,
+    Mbr,
+    /// No partition table; the disk may still hold a filesystem directly
+    None,
+}
+
+impl fmt::Display for PartitionScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionScheme::Gpt => write!(f, "GPT"),
+            PartitionScheme::Mbr => write!(f, "MBR"),
+            PartitionScheme::None => write!(f, "No partition table"),
+        }
+    }
+}
+
+/// A single partition as found on disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionInfo {
+    /// 1-based partition number
+    pub number: u32,
+    /// GPT partition name; empty for MBR partitions
+    pub name: String,
+    /// Unique partition GUID; only GPT partitions have one
+    pub guid: Option<Uuid>,
+    /// Human-readable partition type
+    pub type_name: String,
+    /// Offset of the first byte of the partition
+    pub start_offset: u64,
+    /// Partition size in bytes
+    pub size: u64,
+    /// Filesystem detected from on-disk signatures
+    pub filesystem: Option<String>,
+    /// Volume label reported by the filesystem
+    pub label: Option<String>,
+}
+
+impl PartitionInfo {
+    /// Name to show for this partition, preferring the filesystem label
+    pub fn display_name(&self) -> String {
+        self.label
+            .clone()
+            .or_else(|| (!self.name.is_empty()).then(|| self.name.clone()))
+            .unwrap_or_else(|| format!("Partition {}", self.number))
+    }
+}
+
+/// Existing layout of a disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskLayout {
+    pub scheme: PartitionScheme,
+    pub partitions: Vec<PartitionInfo>,
+    /// Filesystem found at the start of an unpartitioned disk
+    pub filesystem: Option<String>,
+}
+
+impl DiskLayout {
+    /// True when the disk doesn't appear to contain any data structures we recognize
+    pub fn is_blank(&self) -> bool {
+        self.partitions.is_empty() && self.filesystem.is_none()
+    }
+
+    /// Find a GPT partition by its unique GUID
+    pub fn find_partition(&self, guid: &Uuid) -> Option<&PartitionInfo> {
+        self.partitions
+            .iter()
+            .find(|p| p.guid.as_ref() == Some(guid))
+    }
+
+    /// The Golem configuration partition, if the disk has one
+    pub fn golem_config_partition(&self) -> Option<&PartitionInfo> {
+        let guid = Uuid::parse_str(GOLEM_CONFIG_PARTITION_GUID).ok()?;
+        self.find_partition(&guid)
+    }
+
+    /// True when the disk looks like it was previously flashed with a Golem image
+    pub fn is_golem_image(&self) -> bool {
+        self.golem_config_partition().is_some()
+    }
+}
+
+/// Format a byte count using binary units
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Read the partition table and filesystem signatures of a disk
+///
+/// All reads are whole sectors at sector-aligned offsets, so the reader only needs
+/// to handle aligned access (e.g. an `AlignedDiskIo` over a raw Windows disk handle).
+/// LBA values are interpreted in units of `sector_size`, the disk's logical sector size.
+pub fn read_layout<R: Read + Seek>(reader: &mut R, sector_size: u64) -> Result<DiskLayout> {
+    let mbr = read_at(reader, 0, sector_size as usize).context("Failed to read MBR")?;
+
+    let has_mbr_signature = mbr[510] == 0x55 && mbr[511] == 0xAA;
+    let mbr_entries = if has_mbr_signature {
+        parse_mbr_entries(&mbr)
+    } else {
+        Vec::new()
+    };
+
+    // A protective MBR (type 0xEE) means the real table is the GPT
+    let is_protective = mbr_entries.iter().any(|e| e.partition_type == 0xEE);
+    let gpt_partitions = if is_protective || mbr_entries.is_empty() {
+        read_gpt_partitions(reader, sector_size)?
+    } else {
+        None
+    };
+    if let Some(partitions) = gpt_partitions {
+        return Ok(DiskLayout {
+            scheme: PartitionScheme::Gpt,
+            partitions,
+            filesystem: None,
+        });
+    }
+
+    if !mbr_entries.is_empty() && !is_protective {
+        let mut partitions = Vec::with_capacity(mbr_entries.len());
+        for entry in mbr_entries {
+            let start_offset = entry.first_lba as u64 * sector_size;
+            let (filesystem, label) = detect_filesystem(reader, start_offset);
+            partitions.push(PartitionInfo {
+                number: entry.number,
+                name: String::new(),
+                guid: None,
+                type_name: mbr_type_name(entry.partition_type).to_string(),
+                start_offset,
+                size: entry.sector_count as u64 * sector_size,
+                filesystem,
+                label,
+            });
+        }
+        return Ok(DiskLayout {
+            scheme: PartitionScheme::Mbr,
+            partitions,
+            filesystem: None,
+        });
+    }
+
+    // No partition table, but the whole disk may be formatted (superfloppy)
+    let (filesystem, _) = detect_filesystem(reader, 0);
+    Ok(DiskLayout {
+        scheme: PartitionScheme::None,
+        partitions: Vec::new(),
+        filesystem,
+    })
+}
+
+/// Primary MBR partition entry
+struct MbrEntry {
+    number: u32,
+    partition_type: u8,
+    first_lba: u32,
+    sector_count: u32,
+}
+
+fn parse_mbr_entries(mbr: &[u8]) -> Vec<MbrEntry> {
+    (0..4)
+        .filter_map(|i| {
+            let entry = &mbr[446 + i * 16..446 + (i + 1) * 16];
+            let partition_type = entry[4];
+            let first_lba = u32::from_le_bytes(entry[8..12].try_into().unwrap());
+            let sector_count = u32::from_le_bytes(entry[12..16].try_into().unwrap());
+
+            (partition_type != 0 && sector_count != 0).then_some(MbrEntry {
+                number: i as u32 + 1,
+                partition_type,
+                first_lba,
+                sector_count,
+            })
+        })
+        .collect()
+}
+
+/// Parse the primary GPT, returning None when there is no valid GPT header
+fn read_gpt_partitions<R: Read + Seek>(
+    reader: &mut R,
+    sector_size: u64,
+) -> Result<Option<Vec<PartitionInfo>>> {
+    let header = match read_at(reader, sector_size, sector_size as usize) {
+        Ok(header) => header,
+        Err(_) => return Ok(None),
+    };
+
+    // A corrupt header can't trigger huge reads, its entry fields are bounded
+    let Ok(header) = GptHeader::parse(&header) else {
+        return Ok(None);
+    };
+    let entry_count = header.entry_count;
+    let entry_size = header.entry_size as usize;
+
+    let table_len = header.entries_len().div_ceil(sector_size) * sector_size;
+    let table_offset = header
+        .entries_lba
+        .checked_mul(sector_size)
+        .context("GPT partition entries lie beyond the end of the disk")?;
+    let table = read_at(reader, table_offset, table_len as usize)
+        .context("Failed to read GPT partition entries")?;
+
+    let mut partitions = Vec::new();
+    for (index, entry) in table
+        .chunks_exact(entry_size)
+        .take(entry_count as usize)
+        .enumerate()
+    {
+        let type_guid = guid::from_gpt_bytes(entry[0..16].try_into().unwrap());
+        if type_guid.is_nil() {
+            continue;
+        }
+        let unique_guid = guid::from_gpt_bytes(entry[16..32].try_into().unwrap());
+        let first_lba = u64::from_le_bytes(entry[32..40].try_into().unwrap());
+        let last_lba = u64::from_le_bytes(entry[40..48].try_into().unwrap());
+
+        let name_units: Vec<u16> = entry[56..128]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0)
+            .collect();
+        let name = String::from_utf16_lossy(&name_units);
+
+        let type_name = if unique_guid.to_string() == GOLEM_CONFIG_PARTITION_GUID {
+            "Golem configuration".to_string()
+        } else {
+            gpt_type_name(&type_guid)
+        };
+
+        let start_offset = first_lba.saturating_mul(sector_size);
+        let (filesystem, label) = detect_filesystem(reader, start_offset);
+
+        partitions.push(PartitionInfo {
+            number: index as u32 + 1,
+            name,
+            guid: Some(unique_guid),
+            type_name,
+            start_offset,
+            size: last_lba
+                .saturating_sub(first_lba)
+                .saturating_add(1)
+                .saturating_mul(sector_size),
+            filesystem,
+            label,
+        });
+    }
+
+    Ok(Some(partitions))
+}
+
+/// Sniff the filesystem type and label at the given offset
+///
+/// Read errors are treated as "unknown" rather than failing the whole layout.
+fn detect_filesystem<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+) -> (Option<String>, Option<String>) {
+    let block = match read_at(reader, offset, PROBE_SIZE) {
+        Ok(block) => block,
+        Err(_) => return (None, None),
+    };
+
+    if block.starts_with(b"LUKS\xba\xbe") {
+        return (Some("LUKS (encrypted)".to_string()), None);
+    }
+    if block.starts_with(b"hsqs") {
+        return (Some("squashfs".to_string()), None);
+    }
+    if block.starts_with(b"XFSB") {
+        return (Some("XFS".to_string()), fixed_label(&block[108..120]));
+    }
+    if &block[3..11] == b"NTFS    " {
+        return (Some("NTFS".to_string()), None);
+    }
+    if &block[3..11] == b"EXFAT   " {
+        return (Some("exFAT".to_string()), None);
+    }
+    if &block[82..87] == b"FAT32" {
+        return (Some("FAT32".to_string()), fixed_label(&block[71..82]));
+    }
+    if &block[54..58] == b"FAT1" {
+        let kind = String::from_utf8_lossy(&block[54..59]).to_string();
+        return (Some(kind), fixed_label(&block[43..54]));
+    }
+
+    // ext2/3/4 superblock lives 1024 bytes into the partition
+    let superblock = &block[1024..2048];
+    if superblock[56..58] == [0x53, 0xEF] {
+        let compat = u32::from_le_bytes(superblock[92..96].try_into().unwrap());
+        let incompat = u32::from_le_bytes(superblock[96..100].try_into().unwrap());
+        let kind = if incompat & 0x40 != 0 {
+            "ext4"
+        } else if compat & 0x4 != 0 {
+            "ext3"
+        } else {
+            "ext2"
+        };
+        return (Some(kind.to_string()), fixed_label(&superblock[120..136]));
+    }
+
+    if &block[PROBE_SIZE - 10..] == b"SWAPSPACE2" {
+        return (Some("swap".to_string()), None);
+    }
+
+    (None, None)
+}
+
+/// Decode a space- or NUL-padded label field
+fn fixed_label(bytes: &[u8]) -> Option<String> {
+    let label = String::from_utf8_lossy(bytes)
+        .trim_end_matches(['\0', ' '])
+        .to_string();
+    if label.is_empty() || label == "NO NAME" {
+        None
+    } else {
+        Some(label)
+    }
+}
+
+/// Read exactly `len` bytes at `offset`
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: usize) -> Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn gpt_type_name(type_guid: &Uuid) -> String {
+    let name = match type_guid.to_string().as_str() {
+        "c12a7328-f81f-11d2-ba4b-00a0c93ec93b" => "EFI System",
+        "21686148-6449-6e6f-744e-656564454649" => "BIOS boot",
+        "0fc63daf-8483-4772-8e79-3d69d8477de4" => "Linux filesystem",
+        "0657fd6d-a4ab-43c4-84e5-0933c84b4f4f" => "Linux swap",
+        "e6d6d379-f507-44c2-a23c-238f2a3df928" => "Linux LVM",
+        "a19d880f-05fc-4d3b-a006-743f0f84911e" => "Linux RAID",
+        "4f68bce3-e8cd-4db1-96e7-fbcaf984b709" => "Linux root (x86-64)",
+        "ebd0a0a2-b9e5-4433-87c0-68b6b72699c7" => "Microsoft basic data",
+        "e3c9e316-0b5c-4db8-817d-f92df00215ae" => "Microsoft reserved",
+        "de94bba4-06d1-4d40-a16a-bfd50179d6ac" => "Windows recovery",
+        "48465300-0000-11aa-aa11-00306543ecac" => "Apple HFS+",
+        "7c3457ef-0000-11aa-aa11-00306543ecac" => "Apple APFS",
+        other => return format!("Unknown ({})", other),
+    };
+    name.to_string()
+}
+
+fn mbr_type_name(partition_type: u8) -> &'static str {
+    match partition_type {
+        0x01 => "FAT12",
+        0x04 | 0x06 | 0x0E => "FAT16",
+        0x05 | 0x0F | 0x85 => "Extended",
+        0x07 => "NTFS/exFAT",
+        0x0B | 0x0C => "FAT32",
+        0x27 => "Windows recovery",
+        0x82 => "Linux swap",
+        0x83 => "Linux",
+        0x8E => "Linux LVM",
+        0xA5 => "FreeBSD",
+        0xAF => "Apple HFS+",
+        0xEF => "EFI System",
+        0xFD => "Linux RAID",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const MIB: usize = 1024 * 1024;
+    const SECTOR_SIZE: u64 = crate::disk::geometry::DEFAULT_LOGICAL_SECTOR_SIZE;
+
+    fn write_fat32_boot_sector(disk: &mut [u8], offset: usize, label: &[u8; 11]) {
+        disk[offset + 3..offset + 11].copy_from_slice(b"MSDOS5.0");
+        disk[offset + 71..offset + 82].copy_from_slice(label);
+        disk[offset + 82..offset + 90].copy_from_slice(b"FAT32   ");
+        disk[offset + 510] = 0x55;
+        disk[offset + 511] = 0xAA;
+    }
+
+    fn write_ext4_superblock(disk: &mut [u8], offset: usize, label: &str) {
+        let sb = offset + 1024;
+        disk[sb + 56..sb + 58].copy_from_slice(&[0x53, 0xEF]);
+        disk[sb + 92..sb + 96].copy_from_slice(&0x4u32.to_le_bytes());
+        disk[sb + 96..sb + 100].copy_from_slice(&0x40u32.to_le_bytes());
+        disk[sb + 120..sb + 120 + label.len()].copy_from_slice(label.as_bytes());
+    }
+
+    fn write_gpt_entry(
+        disk: &mut [u8],
+        index: usize,
+        type_guid: &str,
+        unique_guid: &str,
+        first_lba: u64,
+        last_lba: u64,
+        name: &str,
+    ) {
+        let entry = 2 * SECTOR_SIZE as usize + index * 128;
+        let type_guid = guid::to_gpt_bytes(&Uuid::parse_str(type_guid).unwrap());
+        let unique_guid = guid::to_gpt_bytes(&Uuid::parse_str(unique_guid).unwrap());
+        disk[entry..entry + 16].copy_from_slice(&type_guid);
+        disk[entry + 16..entry + 32].copy_from_slice(&unique_guid);
+        disk[entry + 32..entry + 40].copy_from_slice(&first_lba.to_le_bytes());
+        disk[entry + 40..entry + 48].copy_from_slice(&last_lba.to_le_bytes());
+        for (i, unit) in name.encode_utf16().enumerate() {
+            let pos = entry + 56 + i * 2;
+            disk[pos..pos + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+    }
+
+    fn gpt_disk() -> Vec<u8> {
+        let mut disk = vec![0u8; 4 * MIB];
+
+        // Protective MBR
+        disk[446 + 4] = 0xEE;
+        disk[446 + 8..446 + 12].copy_from_slice(&1u32.to_le_bytes());
+        disk[446 + 12..446 + 16].copy_from_slice(&u32::MAX.to_le_bytes());
+        disk[510] = 0x55;
+        disk[511] = 0xAA;
+
+        // GPT header at LBA 1 with the entry array at LBA 2
+        let header = SECTOR_SIZE as usize;
+        disk[header..header + 8].copy_from_slice(b"EFI PART");
+        disk[header + 72..header + 80].copy_from_slice(&2u64.to_le_bytes());
+        disk[header + 80..header + 84].copy_from_slice(&128u32.to_le_bytes());
+        disk[header + 84..header + 88].copy_from_slice(&128u32.to_le_bytes());
+
+        write_gpt_entry(
+            &mut disk,
+            0,
+            "ebd0a0a2-b9e5-4433-87c0-68b6b72699c7",
+            GOLEM_CONFIG_PARTITION_GUID,
+            2048,
+            4095,
+            "config",
+        );
+        write_gpt_entry(
+            &mut disk,
+            1,
+            "0fc63daf-8483-4772-8e79-3d69d8477de4",
+            "8f2b9c1e-3c1d-4a4e-9d3b-2d6a1f0e5b7c",
+            4096,
+            8191,
+            "root",
+        );
+
+        write_fat32_boot_sector(&mut disk, 2048 * 512, b"GOLEMCONF  ");
+        write_ext4_superblock(&mut disk, 4096 * 512, "backup");
+        disk
+    }
+
+    #[test]
+    fn test_read_gpt_layout() {
+        let layout = read_layout(&mut Cursor::new(gpt_disk()), SECTOR_SIZE).unwrap();
+
+        assert_eq!(layout.scheme, PartitionScheme::Gpt);
+        assert_eq!(layout.partitions.len(), 2);
+        assert!(layout.is_golem_image());
+
+        let config = &layout.partitions[0];
+        assert_eq!(config.number, 1);
+        assert_eq!(config.name, "config");
+        assert_eq!(config.type_name, "Golem configuration");
+        assert_eq!(config.size, MIB as u64);
+        assert_eq!(config.filesystem.as_deref(), Some("FAT32"));
+        assert_eq!(config.label.as_deref(), Some("GOLEMCONF"));
+
+        let root = &layout.partitions[1];
+        assert_eq!(root.number, 2);
+        assert_eq!(root.type_name, "Linux filesystem");
+        assert_eq!(root.start_offset, 4096 * 512);
+        assert_eq!(root.filesystem.as_deref(), Some("ext4"));
+        assert_eq!(root.display_name(), "backup");
+    }
+
+    #[test]
+    fn test_read_mbr_layout() {
+        let mut disk = vec![0u8; 2 * MIB];
+        disk[446 + 4] = 0x0C;
+        disk[446 + 8..446 + 12].copy_from_slice(&2048u32.to_le_bytes());
+        disk[446 + 12..446 + 16].copy_from_slice(&2048u32.to_le_bytes());
+        disk[510] = 0x55;
+        disk[511] = 0xAA;
+        write_fat32_boot_sector(&mut disk, 2048 * 512, b"NO NAME    ");
+
+        let layout = read_layout(&mut Cursor::new(disk), SECTOR_SIZE).unwrap();
+
+        assert_eq!(layout.scheme, PartitionScheme::Mbr);
+        assert_eq!(layout.partitions.len(), 1);
+        let partition = &layout.partitions[0];
+        assert_eq!(partition.type_name, "FAT32");
+        assert_eq!(partition.filesystem.as_deref(), Some("FAT32"));
+        assert_eq!(partition.label, None);
+        assert_eq!(partition.display_name(), "Partition 1");
+        assert!(!layout.is_golem_image());
+    }
+
+    #[test]
+    fn test_blank_and_superfloppy_disks() {
+        let blank = read_layout(&mut Cursor::new(vec![0u8; MIB]), SECTOR_SIZE).unwrap();
+        assert_eq!(blank.scheme, PartitionScheme::None);
+        assert!(blank.is_blank());
+
+        let mut superfloppy = vec![0u8; MIB];
+        superfloppy[3..11].copy_from_slice(b"EXFAT   ");
+        let layout = read_layout(&mut Cursor::new(superfloppy), SECTOR_SIZE).unwrap();
+        assert_eq!(layout.scheme, PartitionScheme::None);
+        assert_eq!(layout.filesystem.as_deref(), Some("exFAT"));
+        assert!(!layout.is_blank());
+    }
+
+    #[test]
+    fn test_corrupt_gpt_entry_count_is_ignored() {
+        let mut disk = gpt_disk();
+        let header = SECTOR_SIZE as usize;
+        disk[header + 80..header + 84].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let layout = read_layout(&mut Cursor::new(disk), SECTOR_SIZE).unwrap();
+        assert_eq!(layout.scheme, PartitionScheme::None);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1024 * 1024), "1.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024 / 2), "1.5 GB");
+    }
+}
@@ -1,18 +1,20 @@
 // Linux-specific disk operations
 
-use crate::disk::common::{DiskDevice, PartitionFileProxy};
+use crate::disk::access::DiskAccess;
+use crate::disk::common::{DiskDevice, FlashPhase, PartitionFileProxy};
 use anyhow::{Context, Result, anyhow};
 // Keep gpt imported for GptDisk
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, Read, Seek, Write};
 use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
 use tracing::{debug, error, info, warn};
 
 // Linux-specific imports
 use libc::{O_CLOEXEC, O_EXCL, O_SYNC};
 // Removed unused import: use udisks2::filesystem::FilesystemProxy;
-use udisks2::zbus::zvariant::{ObjectPath, OwnedObjectPath};
+use udisks2::zbus::zvariant::OwnedObjectPath;
 use udisks2::{Client, zbus};
 
 /// Linux-specific disk access functionality
@@ -24,6 +26,19 @@ pub struct LinuxDiskAccess {
 }
 
 impl LinuxDiskAccess {
+    /// Clear disk partitions before writing
+    ///
+    /// Nothing needs to be cleared on Linux: `lock_path` unmounts all partitions and
+    /// the image write overwrites the partition table directly.
+    pub async fn clear_disk_partitions(
+        path: String,
+        _cancel_token: crate::models::CancelToken,
+        _progress: tokio::sync::mpsc::UnboundedSender<FlashPhase>,
+    ) -> Result<FlashPhase> {
+        debug!("No partition clearing needed on Linux for {}", path);
+        Ok(FlashPhase::Done)
+    }
+
     /// Open and lock a disk by its path
     ///
     /// # Arguments
@@ -39,16 +54,19 @@ impl LinuxDiskAccess {
             path, edit_mode
         );
 
+        // Without UDisks2 the privileged helper opens the device for us
+        if crate::utils::privileged_helper::is_running() {
+            return Self::lock_path_via_helper(path).await;
+        }
+
         // Create the Linux UDisks2 client
         let client = Client::new().await?;
 
         // Resolve the device path to a UDisks2 object path
         let drive_path = Self::resolve_device(&client, path).await?;
 
-        // Unmount all mounted partitions on this disk
-        Self::umount_all(&client, drive_path.as_ref())
-            .await
-            .context("Failed to unmount partitions")?;
+        // Unmount the partitions and whatever is stacked on them, so O_EXCL can succeed
+        Self::release_disk(&client, path).await?;
 
         // Get the block device interface
         let block = client.object(drive_path)?.block().await?;
@@ -56,15 +74,13 @@ impl LinuxDiskAccess {
         // Set up open flags: O_EXCL for exclusive access, O_SYNC for sync I/O, O_CLOEXEC to close on exec
         let flags = O_EXCL | O_SYNC | O_CLOEXEC;
 
-        // Open the device with read-write access
-        let owned_fd = block
-            .open_device(
-                "rw",
-                [("flags", zbus::zvariant::Value::from(flags))]
-                    .into_iter()
-                    .collect(),
-            )
-            .await?;
+        // Open the device with read-write access; without root UDisks2 asks polkit,
+        // which shows the desktop's authentication prompt
+        let mut options = Self::polkit_options();
+        options.insert("flags", zbus::zvariant::Value::from(flags));
+        let owned_fd = block.open_device("rw", options).await.map_err(|e| {
+            Self::explain_busy_error(Self::explain_authorization_error(e.into(), path), path)
+        })?;
 
         // Convert the file descriptor to a Rust File
         if let zbus::zvariant::Fd::Owned(owned_fd) = owned_fd.into() {
@@ -84,24 +100,22 @@ impl LinuxDiskAccess {
         }
     }
 
-    /// Clone a file handle (uses dup() on Linux)
-    pub fn clone_file_handle(&self, file: &File) -> Result<File> {
-        // Get the raw file descriptor
-        let fd = file.as_raw_fd();
-
-        // Use libc dup to duplicate the file descriptor
-        let new_fd = unsafe { libc::dup(fd) };
-
-        if new_fd < 0 {
-            // An error occurred, get the error code
-            let err = io::Error::last_os_error();
-            return Err(anyhow!("Failed to duplicate file handle: {}", err));
-        }
-
-        // Convert the new file descriptor to a Rust File
-        let new_file = unsafe { File::from_raw_fd(new_fd) };
+    /// Open a disk through the privileged helper
+    ///
+    /// The helper unmounts the partitions and passes back a descriptor opened with the same
+    /// flags as [`LinuxDiskAccess::lock_path`].
+    async fn lock_path_via_helper(path: &str) -> Result<(File, Self)> {
+        let device = path.to_string();
+        let file = tokio::task::spawn_blocking(move || {
+            crate::utils::privileged_helper::open_device(&device, false)
+        })
+        .await??;
+        info!("Opened {} through the privileged helper", path);
 
-        Ok(new_file)
+        let platform = LinuxDiskAccess {
+            path: path.to_string(),
+        };
+        Ok((file, platform))
     }
 
     /// Create a partition file proxy for Linux
@@ -123,10 +137,274 @@ impl LinuxDiskAccess {
         })
     }
 
+    /// Resolve a device path to a UDisks2 object path
+    async fn resolve_device(client: &Client, path: &str) -> Result<OwnedObjectPath> {
+        debug!("Resolving Linux device path: {}", path);
+
+        // Create specification for device lookup
+        let mut spec = HashMap::new();
+        spec.insert("path", path.into());
+
+        // Resolve the device using UDisks2
+        let mut obj = client
+            .manager()
+            .resolve_device(spec, HashMap::default())
+            .await?;
+
+        // Return the first object path found or an error if none were found
+        obj.pop()
+            .ok_or(anyhow!("No device found for path: {}", path))
+    }
+
+    /// Options for UDisks2 calls that may need polkit authorization
+    ///
+    /// Allows polkit to interact with the user, so an unprivileged process gets an
+    /// authentication prompt instead of an immediate "not authorized" error.
+    fn polkit_options() -> HashMap<&'static str, zbus::zvariant::Value<'static>> {
+        [(
+            "auth.no_user_interaction",
+            zbus::zvariant::Value::from(false),
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    /// Replace UDisks2 authorization failures with an explanation of what went wrong
+    fn explain_authorization_error(e: anyhow::Error, path: &str) -> anyhow::Error {
+        let message = e.to_string();
+        if message.contains("NotAuthorized") {
+            anyhow!(
+                "Access to {} was not authorized. Confirm the system authentication prompt, \
+                 or run the application as root.",
+                path
+            )
+        } else {
+            e
+        }
+    }
+
+    /// Replace EBUSY from opening the disk with what is known to still use it
+    fn explain_busy_error(e: anyhow::Error, path: &str) -> anyhow::Error {
+        if !e.to_string().contains("Device or resource busy") {
+            return e;
+        }
+        let holders = remaining_holders(&crate::disk::usage::device_family(path));
+        if holders.is_empty() {
+            anyhow!(
+                "{} is busy: another program has it open. Close programs using the disk and \
+                 try again.",
+                path
+            )
+        } else {
+            anyhow!("{} is still in use by {}", path, holders.join(", "))
+        }
+    }
+
+    /// Release everything on the disk that keeps it from being opened exclusively
+    ///
+    /// Filesystems on the disk, its partitions and the devices stacked on them are
+    /// unmounted and unlocked LUKS containers are locked again, top of the stack first.
+    /// Devices that are left, e.g. active LVM volumes, are reported by name rather than
+    /// failing the open with EBUSY.
+    async fn release_disk(client: &Client, path: &str) -> Result<()> {
+        let family = crate::disk::usage::device_family(path);
+        debug!("Releasing {}: {:?}", path, family);
+
+        // Find the UDisks2 objects of the device nodes
+        let mut objects = HashMap::new();
+        for object_path in client
+            .manager()
+            .get_block_devices(HashMap::default())
+            .await?
+        {
+            let Ok(block) = client.object(object_path.clone())?.block().await else {
+                continue;
+            };
+            if let Ok(device) = block.device().await {
+                objects.insert(PathBuf::from(c_string(&device)), object_path);
+            }
+        }
+
+        for node in family.iter().rev() {
+            let Some(object_path) = objects.get(node) else {
+                continue;
+            };
+            let object = client.object(object_path.clone())?;
+
+            if let Ok(filesystem) = object.filesystem().await {
+                let mount_points = filesystem.mount_points().await.unwrap_or_default();
+                if !mount_points.is_empty() {
+                    let mounts = mount_points
+                        .iter()
+                        .map(|mount_point| c_string(mount_point))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    info!("Unmounting {} from {}", node.display(), mounts);
+                    filesystem
+                        .unmount(Self::polkit_options())
+                        .await
+                        .map_err(|e| {
+                            Self::explain_authorization_error(
+                                anyhow!(
+                                    "{} is mounted at {} and could not be unmounted: {}. Close \
+                                     the programs using it and try again.",
+                                    node.display(),
+                                    mounts,
+                                    e
+                                ),
+                                path,
+                            )
+                        })?;
+                }
+            }
+
+            if let Ok(encrypted) = object.encrypted().await {
+                let unlocked = encrypted
+                    .cleartext_device()
+                    .await
+                    .is_ok_and(|cleartext| cleartext.as_str() != "/");
+                if unlocked {
+                    info!("Locking the encrypted volume on {}", node.display());
+                    encrypted.lock(Self::polkit_options()).await.map_err(|e| {
+                        Self::explain_authorization_error(
+                            anyhow!(
+                                "The encrypted volume on {} could not be locked: {}",
+                                node.display(),
+                                e
+                            ),
+                            path,
+                        )
+                    })?;
+                }
+            }
+        }
+
+        // Volume groups and RAID arrays need the tools that manage them
+        let holders = remaining_holders(&family);
+        if !holders.is_empty() {
+            return Err(anyhow!(
+                "{} is still in use by {}. Deactivate it first, e.g. with `vgchange -an` for \
+                 an LVM volume group or `mdadm --stop` for a RAID array.",
+                path,
+                holders.join(", ")
+            ));
+        }
+
+        debug!("Released all devices on {}", path);
+        Ok(())
+    }
+
+    /// List available disks on Linux
+    #[allow(dead_code)]
+    pub async fn list_available_disks() -> Result<Vec<DiskDevice>> {
+        debug!("Listing available disks on Linux");
+        let mut devices = Vec::new();
+
+        // Use rs-drivelist directly for Linux
+        if let Ok(drives) = rs_drivelist::drive_list() {
+            debug!("Found {} drives with rs-drivelist", drives.len());
+
+            for drive in drives {
+                // Get fields directly from DeviceDescriptor struct
+                let device_path = drive
+                    .devicePath
+                    .as_ref()
+                    .map_or_else(|| drive.device.clone(), |p| p.clone());
+
+                // Format mountpoints for display
+                let mountpoint_str = if !drive.mountpoints.is_empty() {
+                    let mp = &drive.mountpoints[0].path;
+                    format!(" ({})", mp)
+                } else {
+                    String::new()
+                };
+
+                // Create a name for the device
+                let name = format!("{}{}", drive.description, mountpoint_str);
+
+                // Determine system disk
+                let is_system = drive.isSystem;
+
+                // Look up the serial number so the device can be recognized after re-enumeration
+                let identity = crate::disk::identity::query_device_identity(&device_path);
+
+                // Add to our list of devices
+                devices.push(DiskDevice {
+                    path: device_path,
+                    name,
+                    size: drive.size,
+                    removable: drive.isRemovable,
+                    readonly: drive.isReadOnly,
+                    vendor: "Unknown".to_string(), // Not directly available in current version
+                    model: drive.description.clone(),
+                    system: is_system,
+                    identity,
+                });
+            }
+        }
+
+        // If rs-drivelist didn't find any devices or failed, try to find block devices by path pattern
+        if devices.is_empty() {
+            debug!("Using fallback method to find Linux block devices");
+
+            // Simple fallback using direct path enumeration
+            for i in 0..8 {
+                // Try common block device patterns
+                let device_paths = vec![
+                    format!("/dev/sd{}", (b'a' + i) as char),
+                    format!("/dev/hd{}", (b'a' + i) as char),
+                    format!("/dev/nvme{}n1", i),
+                    format!("/dev/mmcblk{}", i),
+                ];
+
+                for path in device_paths {
+                    if std::path::Path::new(&path).exists() {
+                        debug!("Found device: {}", path);
+                        devices.push(DiskDevice {
+                            path: path.clone(),
+                            name: format!("Disk {}", path),
+                            size: 0,          // Unknown size
+                            removable: false, // Unknown
+                            readonly: false,  // Unknown
+                            vendor: "Unknown".to_string(),
+                            model: "Unknown".to_string(),
+                            system: false, // Unknown
+                            identity: crate::disk::identity::query_device_identity(&path),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(devices)
+    }
+}
+
+impl DiskAccess for LinuxDiskAccess {
+    /// Clone a file handle (uses dup() on Linux)
+    fn clone_file_handle(&self, file: &File) -> Result<File> {
+        // Get the raw file descriptor
+        let fd = file.as_raw_fd();
+
+        // Use libc dup to duplicate the file descriptor
+        let new_fd = unsafe { libc::dup(fd) };
+
+        if new_fd < 0 {
+            // An error occurred, get the error code
+            let err = io::Error::last_os_error();
+            return Err(anyhow!("Failed to duplicate file handle: {}", err));
+        }
+
+        // Convert the new file descriptor to a Rust File
+        let new_file = unsafe { File::from_raw_fd(new_fd) };
+
+        Ok(new_file)
+    }
+
     /// Verify disk is ready for writing (Linux implementation)
     /// Note: This accepts the same parameters as the Windows version for compatibility,
     /// but the original_path parameter is unused on Linux as we don't need diskpart.
-    pub fn pre_write_checks(disk_file: &File, original_path: Option<&str>) -> Result<()> {
+    fn pre_write_checks(&self, disk_file: &File, original_path: Option<&str>) -> Result<()> {
         // Log the original path for debugging, but we don't actually use it on Linux
         if let Some(path) = original_path {
             debug!("Linux: path provided for pre_write_checks: {}", path);
@@ -160,7 +438,7 @@ impl LinuxDiskAccess {
     }
 
     /// Handle disk write errors with Linux-specific context
-    pub fn handle_write_error(e: &io::Error) -> Option<anyhow::Error> {
+    fn handle_write_error(&self, e: &io::Error) -> Option<anyhow::Error> {
         let os_error = e.raw_os_error();
 
         // Log error details
@@ -215,8 +493,13 @@ impl LinuxDiskAccess {
         None
     }
 
+    /// Disks are opened without O_DIRECT on Linux, so there is nothing to fall back from
+    fn reopen_buffered(&self, _file: &File) -> Result<File> {
+        Err(anyhow!("The disk is not opened with direct I/O"))
+    }
+
     /// Handle disk flush errors with Linux-specific context
-    pub fn handle_flush_error(e: &io::Error) -> Option<anyhow::Error> {
+    fn handle_flush_error(&self, e: &io::Error) -> Option<anyhow::Error> {
         let os_error = e.raw_os_error();
         if let Some(code) = os_error {
             error!("Linux flush error code: {}, error: {}", code, e);
@@ -232,170 +515,81 @@ impl LinuxDiskAccess {
     }
 
     /// Handle GPT reading errors with Linux-specific solutions
-    pub fn handle_gpt_error(
-        disk: &crate::disk::Disk,
+    fn handle_gpt_error<'a>(
+        &self,
+        disk: &'a crate::disk::Disk,
         error: anyhow::Error,
-    ) -> Result<Option<gpt::GptDisk<'_>>> {
+    ) -> Result<Option<gpt::GptDisk<'a>>> {
         warn!(
             "Failed to parse GPT partition table: {}. Attempting Linux-specific fixes.",
             error
         );
 
-        // For Linux, we try with a different logical block size
+        // For Linux, we try with the other logical block size, in case the image was
+        // written for disks with different sectors than the one it is on
+        let (block_size, block_bytes) = if disk.geometry().logical == 4096 {
+            (gpt::disk::LogicalBlockSize::Lb512, 512)
+        } else {
+            (gpt::disk::LogicalBlockSize::Lb4096, 4096)
+        };
         let cfg = gpt::GptConfig::new()
             .writable(false)
-            .logical_block_size(gpt::disk::LogicalBlockSize::Lb4096);
+            .logical_block_size(block_size);
 
         // Clone the file handle and try again with different block size
         let disk_result = cfg.open_from_device(Box::new(disk.get_cloned_file_handle()?));
 
         if let Ok(disk) = disk_result {
-            info!("Successfully reopened GPT disk with 4096-byte logical blocks");
+            info!(
+                "Successfully reopened GPT disk with {}-byte logical blocks",
+                block_bytes
+            );
             return Ok(Some(disk));
         }
 
         // If that didn't work, try with MBR instead of GPT
-        warn!("Couldn't read as GPT with 4096-byte blocks, checking for MBR format");
+        warn!(
+            "Couldn't read as GPT with {}-byte blocks, checking for MBR format",
+            block_bytes
+        );
 
         // Let the original error propagate
         Ok(None)
     }
+}
 
-    /// Resolve a device path to a UDisks2 object path
-    async fn resolve_device(client: &Client, path: &str) -> Result<OwnedObjectPath> {
-        debug!("Resolving Linux device path: {}", path);
-
-        // Create specification for device lookup
-        let mut spec = HashMap::new();
-        spec.insert("path", path.into());
-
-        // Resolve the device using UDisks2
-        let mut obj = client
-            .manager()
-            .resolve_device(spec, HashMap::default())
-            .await?;
-
-        // Return the first object path found or an error if none were found
-        obj.pop()
-            .ok_or(anyhow!("No device found for path: {}", path))
-    }
-
-    /// Unmount all mounted filesystems on or below the given object path
-    async fn umount_all(client: &Client, path: ObjectPath<'_>) -> Result<()> {
-        debug!("Unmounting all filesystems on Linux device: {:?}", path);
-
-        // Get all block devices from UDisks2
-        let block_devices = client
-            .manager()
-            .get_block_devices(HashMap::default())
-            .await?;
-
-        for dev_path in block_devices {
-            // Check if this device is on our target disk (it starts with the same path)
-            let path_str = path.as_str();
-            if dev_path.as_str().starts_with(path_str) {
-                debug!("Checking device for mounted filesystems: {:?}", dev_path);
-
-                // Clone the device path to avoid ownership issues
-                let dev_path_clone = dev_path.clone();
-
-                // Try to get the filesystem interface for this device
-                if let Ok(d) = client.object(dev_path)?.filesystem().await {
-                    // Check if there are any mount points
-                    if !d.mount_points().await?.is_empty() {
-                        info!("Unmounting filesystem on device: {:?}", dev_path_clone);
-                        // Unmount the filesystem
-                        d.unmount(HashMap::new()).await?;
-                    }
-                }
-            }
-        }
-
-        debug!("Successfully unmounted all filesystems");
-        Ok(())
-    }
-
-    /// List available disks on Linux
-    #[allow(dead_code)]
-    pub async fn list_available_disks() -> Result<Vec<DiskDevice>> {
-        debug!("Listing available disks on Linux");
-        let mut devices = Vec::new();
-
-        // Use rs-drivelist directly for Linux
-        if let Ok(drives) = rs_drivelist::drive_list() {
-            debug!("Found {} drives with rs-drivelist", drives.len());
-
-            for drive in drives {
-                // Get fields directly from DeviceDescriptor struct
-                let device_path = drive
-                    .devicePath
-                    .as_ref()
-                    .map_or_else(|| drive.device.clone(), |p| p.clone());
-
-                // Format mountpoints for display
-                let mountpoint_str = if !drive.mountpoints.is_empty() {
-                    let mp = &drive.mountpoints[0].path;
-                    format!(" ({})", mp)
-                } else {
-                    String::new()
-                };
-
-                // Create a name for the device
-                let name = format!("{}{}", drive.description, mountpoint_str);
-
-                // Determine system disk
-                let is_system = drive.isSystem;
-
-                // Add to our list of devices
-                devices.push(DiskDevice {
-                    path: device_path,
-                    name,
-                    size: drive.size,
-                    removable: drive.isRemovable,
-                    readonly: drive.isReadOnly,
-                    vendor: "Unknown".to_string(), // Not directly available in current version
-                    model: drive.description.clone(),
-                    system: is_system,
-                });
-            }
-        }
-
-        // If rs-drivelist didn't find any devices or failed, try to find block devices by path pattern
-        if devices.is_empty() {
-            debug!("Using fallback method to find Linux block devices");
+// Unlike Windows, Linux doesn't need special handling for read/write operations
+// as it doesn't have the same alignment requirements.
+// The standard implementation in common.rs will work correctly.
 
-            // Simple fallback using direct path enumeration
-            for i in 0..8 {
-                // Try common block device patterns
-                let device_paths = vec![
-                    format!("/dev/sd{}", (b'a' + i) as char),
-                    format!("/dev/hd{}", (b'a' + i) as char),
-                    format!("/dev/nvme{}n1", i),
-                    format!("/dev/mmcblk{}", i),
-                ];
+/// A NUL-terminated byte string from UDisks2, such as a device node or mount point
+fn c_string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .to_string()
+}
 
-                for path in device_paths {
-                    if std::path::Path::new(&path).exists() {
-                        debug!("Found device: {}", path);
-                        devices.push(DiskDevice {
-                            path: path.clone(),
-                            name: format!("Disk {}", path),
-                            size: 0,          // Unknown size
-                            removable: false, // Unknown
-                            readonly: false,  // Unknown
-                            vendor: "Unknown".to_string(),
-                            model: "Unknown".to_string(),
-                            system: false, // Unknown
-                        });
-                    }
-                }
+/// Devices still stacked on any of `nodes`, by their device-mapper name where there is one
+fn remaining_holders(nodes: &[PathBuf]) -> Vec<String> {
+    let mut holders = Vec::new();
+    for node in nodes {
+        let Some(name) = node.file_name() else {
+            continue;
+        };
+        let Ok(entries) =
+            std::fs::read_dir(Path::new("/sys/class/block").join(name).join("holders"))
+        else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let holder = entry.file_name().to_string_lossy().into_owned();
+            let label = std::fs::read_to_string(entry.path().join("dm/name"))
+                .map(|dm_name| format!("{} ({})", dm_name.trim(), holder))
+                .unwrap_or(holder);
+            if !holders.contains(&label) {
+                holders.push(label);
             }
         }
-
-        Ok(devices)
     }
+    holders
 }
-
-// Unlike Windows, Linux doesn't need special handling for read/write operations
-// as it doesn't have the same alignment requirements.
-// The standard implementation in common.rs will work correctly.
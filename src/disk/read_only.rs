@@ -0,0 +1,217 @@
+// The read-only flag of a disk
+//
+// Windows keeps a read-only attribute per disk (diskpart `attributes disk set readonly`),
+// Linux a read-only flag per block device (`blockdev --setro`), which the kernel also sets
+// for cards whose lock switch is on. Writes fail while the flag is set, so it is shown in
+// the device lists and can be cleared before flashing and set again if the flash is
+// cancelled. A lock switch can't be cleared from software; clearing reports that.
+
+use anyhow::{Result, anyhow};
+
+/// Whether the disk at `path` is read-only
+///
+/// The call performs blocking I/O and should be run off the UI thread.
+pub fn is_read_only(path: &str) -> Result<bool> {
+    platform::is_read_only(path)
+}
+
+/// Set or clear the read-only flag of the disk at `path`
+///
+/// Clearing is checked afterwards, since the flag of a write-protected card comes back.
+pub fn set_read_only(path: &str, read_only: bool) -> Result<()> {
+    platform::set_read_only(path, read_only)?;
+    if !read_only && is_read_only(path)? {
+        return Err(anyhow!(
+            "{} is still read-only. Cards with the lock switch on, and some card readers, \
+             are write-protected in hardware: slide the switch to unlock the card.",
+            path
+        ));
+    }
+    Ok(())
+}
+
+/// The value of a sysfs `ro` attribute
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_ro_flag(content: &str) -> Option<bool> {
+    match content.trim() {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{Result, anyhow, parse_ro_flag};
+    use anyhow::Context;
+    use std::os::fd::AsRawFd;
+    use std::path::Path;
+
+    /// `_IO(0x12, 93)` from linux/fs.h
+    const BLKROSET: u64 = 0x125d;
+
+    pub fn is_read_only(path: &str) -> Result<bool> {
+        let node = std::fs::canonicalize(path).with_context(|| format!("{} not found", path))?;
+        let name = node
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("{} is not a block device", path))?;
+        let attribute = Path::new("/sys/class/block").join(name).join("ro");
+        let content = std::fs::read_to_string(&attribute)
+            .with_context(|| format!("Failed to read {}", attribute.display()))?;
+        parse_ro_flag(&content)
+            .ok_or_else(|| anyhow!("Unexpected value in {}: {}", attribute.display(), content))
+    }
+
+    pub fn set_read_only(path: &str, read_only: bool) -> Result<()> {
+        // The flag can be changed through a read-only descriptor
+        let device = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {} to change its read-only flag", path))?;
+        let flag: libc::c_int = read_only.into();
+        let result = unsafe { libc::ioctl(device.as_raw_fd(), BLKROSET as _, &flag) };
+        if result != 0 {
+            let error = std::io::Error::last_os_error();
+            return Err(anyhow!(
+                "Failed to {} the read-only flag of {}: {}. This needs root, \
+                 e.g. `sudo blockdev --{} {}`.",
+                if read_only { "set" } else { "clear" },
+                path,
+                error,
+                if read_only { "setro" } else { "setrw" },
+                path
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{Result, anyhow};
+    use std::fs::{File, OpenOptions};
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::Storage::FileSystem::{FILE_SHARE_READ, FILE_SHARE_WRITE};
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+    use windows_sys::Win32::System::Ioctl::{
+        DISK_ATTRIBUTE_READ_ONLY, GET_DISK_ATTRIBUTES, IOCTL_DISK_GET_DISK_ATTRIBUTES,
+        IOCTL_DISK_SET_DISK_ATTRIBUTES, IOCTL_DISK_UPDATE_PROPERTIES, SET_DISK_ATTRIBUTES,
+    };
+
+    fn open(path: &str, write: bool) -> Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(write)
+            .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE)
+            .open(path)
+            .map_err(|e| anyhow!("Failed to open {}: {}", path, e))
+    }
+
+    pub fn is_read_only(path: &str) -> Result<bool> {
+        let disk = open(path, false)?;
+        let mut attributes: GET_DISK_ATTRIBUTES = unsafe { std::mem::zeroed() };
+        let mut bytes_returned = 0u32;
+        let result = unsafe {
+            DeviceIoControl(
+                disk.as_raw_handle() as HANDLE,
+                IOCTL_DISK_GET_DISK_ATTRIBUTES,
+                std::ptr::null(),
+                0,
+                &mut attributes as *mut _ as *mut _,
+                std::mem::size_of::<GET_DISK_ATTRIBUTES>() as u32,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+        if result == 0 {
+            return Err(anyhow!(
+                "Failed to read the attributes of {}: {}",
+                path,
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(attributes.Attributes & DISK_ATTRIBUTE_READ_ONLY != 0)
+    }
+
+    pub fn set_read_only(path: &str, read_only: bool) -> Result<()> {
+        let disk = open(path, true)?;
+        let handle = disk.as_raw_handle() as HANDLE;
+
+        let mut request: SET_DISK_ATTRIBUTES = unsafe { std::mem::zeroed() };
+        request.Version = std::mem::size_of::<SET_DISK_ATTRIBUTES>() as u32;
+        // Like diskpart, so the attribute survives a reboot
+        request.Persist = 1;
+        request.Attributes = if read_only {
+            DISK_ATTRIBUTE_READ_ONLY
+        } else {
+            0
+        };
+        request.AttributesMask = DISK_ATTRIBUTE_READ_ONLY;
+        let mut bytes_returned = 0u32;
+        let result = unsafe {
+            DeviceIoControl(
+                handle,
+                IOCTL_DISK_SET_DISK_ATTRIBUTES,
+                &request as *const _ as *const _,
+                std::mem::size_of::<SET_DISK_ATTRIBUTES>() as u32,
+                std::ptr::null_mut(),
+                0,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+        if result == 0 {
+            return Err(anyhow!(
+                "Failed to {} the read-only attribute of {}: {}. This needs Administrator rights.",
+                if read_only { "set" } else { "clear" },
+                path,
+                std::io::Error::last_os_error()
+            ));
+        }
+
+        // Volumes on the disk pick up the new attribute when the disk is read again
+        unsafe {
+            DeviceIoControl(
+                handle,
+                IOCTL_DISK_UPDATE_PROPERTIES,
+                std::ptr::null(),
+                0,
+                std::ptr::null_mut(),
+                0,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::{Result, anyhow};
+
+    pub fn is_read_only(_path: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    pub fn set_read_only(path: &str, _read_only: bool) -> Result<()> {
+        Err(anyhow!(
+            "Changing the read-only flag of {} is not supported on this platform",
+            path
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ro_flag() {
+        assert_eq!(parse_ro_flag("0\n"), Some(false));
+        assert_eq!(parse_ro_flag("1\n"), Some(true));
+        assert_eq!(parse_ro_flag(""), None);
+        assert_eq!(parse_ro_flag("yes"), None);
+    }
+}
@@ -0,0 +1,458 @@
+// Streaming an image from a URL straight to the disk
+//
+// Provisioning machines often have system drives too small for a 12 GB compressed image,
+// so the response body is fed directly into the decoder instead of the download cache.
+// Connections that drop mid-transfer are resumed with a Range request from the last byte
+// received, which keeps the decoder's input a single uninterrupted stream. The request is
+// made conditional on the file being the one the first response came from, so a file
+// replaced on the server in between is never spliced onto the start of the old one.
+
+use anyhow::{Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How many times a transfer may be resumed before giving up
+const MAX_RESUMES: u32 = 10;
+
+/// Pause before reconnecting after the connection dropped
+const RESUME_DELAY: Duration = Duration::from_secs(3);
+
+/// A connection that delivers nothing for this long is treated as dropped
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Transfer statistics shared between the reader and the progress reporting
+#[derive(Debug, Default)]
+pub struct StreamStats {
+    /// Compressed bytes received so far
+    pub downloaded: AtomicU64,
+    /// Compressed size reported by the server
+    pub download_size: Option<u64>,
+    /// SHA-256 of the compressed stream, set once it has been read to the end
+    pub sha256: Mutex<Option<String>>,
+}
+
+/// Blocking reader over an HTTPS download that resumes interrupted transfers
+///
+/// Must be read from a blocking thread inside the Tokio runtime.
+pub struct HttpImageReader {
+    client: reqwest::Client,
+    url: String,
+    runtime: tokio::runtime::Handle,
+    response: Option<reqwest::Response>,
+    /// ETag or Last-Modified of the first response, sent as `If-Range` when resuming
+    validator: Option<String>,
+    /// Current chunk of the response body and how much of it was consumed
+    chunk: Vec<u8>,
+    chunk_pos: usize,
+    hasher: Sha256,
+    stats: Arc<StreamStats>,
+    resumes: u32,
+    finished: bool,
+    cancel_token: crate::models::CancelToken,
+}
+
+impl HttpImageReader {
+    /// Start downloading `url`
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The reader, with the server's content length in its stats
+    pub async fn open(url: &str, cancel_token: crate::models::CancelToken) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(30))
+            .build()?;
+        let response = client.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to download image, status: {}",
+                response.status()
+            ));
+        }
+
+        // If-Range only takes strong ETags
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let validator = header(reqwest::header::ETAG)
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| header(reqwest::header::LAST_MODIFIED));
+
+        let stats = StreamStats {
+            download_size: response.content_length(),
+            ..StreamStats::default()
+        };
+        info!(
+            "Streaming image from {} ({:?} bytes)",
+            url, stats.download_size
+        );
+
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            runtime: tokio::runtime::Handle::current(),
+            response: Some(response),
+            validator,
+            chunk: Vec::new(),
+            chunk_pos: 0,
+            hasher: Sha256::new(),
+            stats: Arc::new(stats),
+            resumes: 0,
+            finished: false,
+            cancel_token,
+        })
+    }
+
+    /// Statistics updated while the stream is read
+    pub fn stats(&self) -> Arc<StreamStats> {
+        self.stats.clone()
+    }
+
+    /// Wait for the next chunk of the body
+    ///
+    /// # Returns
+    /// * `io::Result<Option<Vec<u8>>>` - The chunk, `None` at the end of the body, or an
+    ///   error when the connection dropped or stalled
+    fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let Some(response) = self.response.as_mut() else {
+            return Err(io::Error::other("No open connection"));
+        };
+
+        let chunk = self
+            .runtime
+            .block_on(tokio::time::timeout(STALL_TIMEOUT, response.chunk()));
+        match chunk {
+            Ok(Ok(chunk)) => Ok(chunk.map(|bytes| bytes.to_vec())),
+            Ok(Err(e)) => Err(io::Error::other(e)),
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "Download stalled")),
+        }
+    }
+
+    /// Reconnect and continue the transfer from the last byte received
+    fn resume(&mut self, reason: io::Error) -> io::Result<()> {
+        self.response = None;
+        let offset = self.stats.downloaded.load(Ordering::Relaxed);
+        let Some(validator) = self.validator.clone() else {
+            return Err(io::Error::other(format!(
+                "Download interrupted ({}) and the server does not identify the file's version, \
+                 so it can't be resumed safely",
+                reason
+            )));
+        };
+
+        loop {
+            if self.cancel_token.is_cancelled() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "Operation cancelled by user",
+                ));
+            }
+
+            self.resumes += 1;
+            if self.resumes > MAX_RESUMES {
+                return Err(io::Error::other(format!(
+                    "Download interrupted too many times, last error: {}",
+                    reason
+                )));
+            }
+
+            warn!(
+                "Download interrupted at {} bytes ({}), resuming ({}/{})",
+                offset, reason, self.resumes, MAX_RESUMES
+            );
+            std::thread::sleep(RESUME_DELAY);
+
+            let request = self
+                .client
+                .get(&self.url)
+                .header(reqwest::header::RANGE, format!("bytes={}-", offset))
+                .header(reqwest::header::IF_RANGE, &validator)
+                .send();
+            match self.runtime.block_on(request) {
+                Ok(response) if response.status() == reqwest::StatusCode::PARTIAL_CONTENT => {
+                    // Bytes from anywhere else would splice the wrong data into the stream
+                    let content_range = response
+                        .headers()
+                        .get(reqwest::header::CONTENT_RANGE)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string();
+                    let range = parse_content_range(&content_range);
+                    let matches = range.is_some_and(|(start, total)| {
+                        start == offset
+                            && total.is_none_or(|total| {
+                                self.stats.download_size.is_none_or(|size| size == total)
+                            })
+                    });
+                    if !matches {
+                        return Err(io::Error::other(format!(
+                            "Download was interrupted and the server resumed it with the wrong \
+                             range ({:?} instead of bytes {}-)",
+                            content_range, offset
+                        )));
+                    }
+                    info!("Resumed download at {} bytes", offset);
+                    self.response = Some(response);
+                    return Ok(());
+                }
+                Ok(response) if response.status().is_success() => {
+                    // A full response would restart the stream the decoder is halfway through
+                    return Err(io::Error::other(
+                        "Download was interrupted and the server does not support resuming, \
+                         or the file has changed since",
+                    ));
+                }
+                Ok(response) => warn!("Resume request failed, status: {}", response.status()),
+                Err(e) => warn!("Resume request failed: {}", e),
+            }
+        }
+    }
+}
+
+/// Start and total size of a `Content-Range: bytes <start>-<end>/<total>` header
+///
+/// The total is `None` when the server sends `*` for it.
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (range, total) = range.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let start = start.trim().parse().ok()?;
+    let end: u64 = end.trim().parse().ok()?;
+    if end < start {
+        return None;
+    }
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+    Some((start, total))
+}
+
+impl Read for HttpImageReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.chunk_pos < self.chunk.len() {
+                let available = &self.chunk[self.chunk_pos..];
+                let count = available.len().min(buf.len());
+                buf[..count].copy_from_slice(&available[..count]);
+                self.chunk_pos += count;
+                return Ok(count);
+            }
+
+            if self.finished {
+                return Ok(0);
+            }
+
+            match self.next_chunk() {
+                Ok(Some(chunk)) => {
+                    self.hasher.update(&chunk);
+                    self.stats
+                        .downloaded
+                        .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                    self.chunk = chunk;
+                    self.chunk_pos = 0;
+                }
+                Ok(None) => {
+                    let downloaded = self.stats.downloaded.load(Ordering::Relaxed);
+                    let truncated = self
+                        .stats
+                        .download_size
+                        .is_some_and(|size| downloaded < size);
+                    if truncated {
+                        self.resume(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "Connection closed early",
+                        ))?;
+                        continue;
+                    }
+
+                    self.finished = true;
+                    let hash = hex::encode(self.hasher.clone().finalize());
+                    *self.stats.sha256.lock().unwrap() = Some(hash);
+                    info!("Download finished, {} bytes received", downloaded);
+                }
+                Err(e) => self.resume(e)?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// ETag of the file the test server has after the interruption
+    const ETAG: &str = "\"v1\"";
+
+    /// Serve `data` once with `etag`, dropping the connection halfway, then answer the
+    /// Range request
+    ///
+    /// A server not `honouring_range` answers it with a 206 of the whole image. One whose
+    /// `If-Range` doesn't match [`ETAG`] sends the whole file with a 200.
+    fn serve_with_interruption(
+        data: Vec<u8>,
+        honouring_range: bool,
+        etag: Option<&'static str>,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/image.xz", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            for (attempt, stream) in listener.incoming().take(2).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut range_start = 0usize;
+                let mut if_range = None;
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(range) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        range_start = range.trim().trim_end_matches('-').parse().unwrap();
+                    }
+                    if line.to_ascii_lowercase().starts_with("if-range:") {
+                        if_range = Some(line["if-range:".len()..].trim().to_string());
+                    }
+                }
+
+                if !honouring_range {
+                    range_start = 0;
+                }
+                if attempt == 0 {
+                    let etag = etag
+                        .map(|etag| format!("ETag: {}\r\n", etag))
+                        .unwrap_or_default();
+                    let header = format!(
+                        "HTTP/1.1 200 OK\r\n{}Content-Length: {}\r\n\r\n",
+                        etag,
+                        data.len()
+                    );
+                    stream.write_all(header.as_bytes()).unwrap();
+                    stream.write_all(&data[..data.len() / 2]).unwrap();
+                } else if if_range.as_deref() != Some(ETAG) {
+                    let header =
+                        format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", data.len());
+                    stream.write_all(header.as_bytes()).unwrap();
+                    stream.write_all(&data).unwrap();
+                } else {
+                    let header = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                        data.len() - range_start,
+                        range_start,
+                        data.len() - 1,
+                        data.len()
+                    );
+                    stream.write_all(header.as_bytes()).unwrap();
+                    stream.write_all(&data[range_start..]).unwrap();
+                }
+            }
+        });
+
+        url
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_resumes_interrupted_download() {
+        let data: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+        let url = serve_with_interruption(data.clone(), true, Some(ETAG));
+
+        let mut reader = HttpImageReader::open(&url, crate::models::CancelToken::new())
+            .await
+            .unwrap();
+        let stats = reader.stats();
+        let received = tokio::task::spawn_blocking(move || {
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).map(|_| received)
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(received, data);
+        assert_eq!(stats.downloaded.load(Ordering::Relaxed), data.len() as u64);
+        assert_eq!(
+            stats.sha256.lock().unwrap().as_deref(),
+            Some(hex::encode(Sha256::digest(&data)).as_str())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_refuses_resume_at_wrong_offset() {
+        let data: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+        let url = serve_with_interruption(data.clone(), false, Some(ETAG));
+
+        let mut reader = HttpImageReader::open(&url, crate::models::CancelToken::new())
+            .await
+            .unwrap();
+        let error = tokio::task::spawn_blocking(move || {
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).map(|_| received)
+        })
+        .await
+        .unwrap()
+        .unwrap_err();
+
+        assert!(error.to_string().contains("wrong range"), "{}", error);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_refuses_resume_of_changed_file() {
+        let data: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+        // The file was replaced after the first response
+        let url = serve_with_interruption(data.clone(), true, Some("\"v0\""));
+
+        let mut reader = HttpImageReader::open(&url, crate::models::CancelToken::new())
+            .await
+            .unwrap();
+        let error = tokio::task::spawn_blocking(move || {
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).map(|_| received)
+        })
+        .await
+        .unwrap()
+        .unwrap_err();
+
+        assert!(error.to_string().contains("has changed"), "{}", error);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_refuses_resume_without_validator() {
+        let data: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+        let url = serve_with_interruption(data.clone(), true, None);
+
+        let mut reader = HttpImageReader::open(&url, crate::models::CancelToken::new())
+            .await
+            .unwrap();
+        let error = tokio::task::spawn_blocking(move || {
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).map(|_| received)
+        })
+        .await
+        .unwrap()
+        .unwrap_err();
+
+        assert!(error.to_string().contains("resumed safely"), "{}", error);
+    }
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 100-199/200"),
+            Some((100, Some(200)))
+        );
+        assert_eq!(parse_content_range("bytes 100-199/*"), Some((100, None)));
+        assert_eq!(parse_content_range("bytes */200"), None);
+        assert_eq!(parse_content_range("bytes 200-100/300"), None);
+        assert_eq!(parse_content_range(""), None);
+    }
+}
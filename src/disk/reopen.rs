@@ -0,0 +1,209 @@
+// Recovery for devices that drop off the bus and re-enumerate mid-operation
+//
+// USB card readers and some USB-SATA bridges reset during long operations. Windows then
+// gives the disk a new handle (and sometimes a new PhysicalDrive number), so reads on the
+// old handle fail with "device does not exist" or "device not ready". Instead of giving up,
+// we find the device again by what we just wrote to it and reopen it.
+
+use anyhow::{Result, anyhow};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Number of leading bytes compared when looking for the re-enumerated device
+pub const SIGNATURE_LEN: usize = 64 * 1024;
+
+/// How many times a single operation may recover a device before giving up
+///
+/// A device that keeps resetting is most likely failing or badly connected.
+pub const MAX_OPERATION_REOPENS: u32 = 3;
+
+/// How many times to try reopening the device before giving up
+const MAX_REOPEN_ATTEMPTS: u32 = 5;
+
+/// Time given to the OS to re-enumerate the device between attempts
+const REOPEN_DELAY: Duration = Duration::from_secs(2);
+
+/// What identifies the disk we were writing to
+///
+/// The serial number is checked first when the device has one. The partition table and
+/// boot sectors just written are effectively unique to this device among the attached
+/// disks, and the size rules out other disks flashed with the same image.
+#[derive(Debug, Clone)]
+pub struct DeviceSignature {
+    /// Hardware identifiers of the device, may be empty for cheap readers
+    pub identity: super::DeviceIdentity,
+    /// Total size of the device in bytes
+    pub disk_size: u64,
+    /// The first bytes written to the device
+    pub head: Vec<u8>,
+}
+
+impl DeviceSignature {
+    /// Check whether a device starts with the bytes we wrote
+    pub fn matches<R: Read + Seek>(&self, device: &mut R) -> bool {
+        let mut buffer = vec![0u8; self.head.len()];
+        if device.seek(SeekFrom::Start(0)).is_err() {
+            return false;
+        }
+        match device.read_exact(&mut buffer) {
+            Ok(()) => buffer == self.head,
+            Err(_) => false,
+        }
+    }
+}
+
+/// Whether an I/O error means the device went away and may come back
+pub fn is_device_gone(e: &io::Error) -> bool {
+    #[cfg(windows)]
+    const GONE_CODES: &[i32] = &[
+        433,  // ERROR_NO_SUCH_DEVICE - USB disconnected or reassigned
+        21,   // ERROR_NOT_READY
+        1167, // ERROR_DEVICE_NOT_CONNECTED
+    ];
+    #[cfg(not(windows))]
+    const GONE_CODES: &[i32] = &[libc::ENODEV, libc::ENXIO, libc::ENOMEDIUM];
+
+    e.raw_os_error()
+        .is_some_and(|code| GONE_CODES.contains(&code))
+}
+
+/// Find the device again after it re-enumerated and open a new handle to it
+///
+/// The original path is tried first; if it now points at a different disk (or doesn't
+/// exist), attached disks with the same serial number, then any disk of the same size,
+/// are checked against the signature.
+/// Must be called from a blocking thread inside the Tokio runtime.
+///
+/// # Arguments
+/// * `stale` - The handle that failed; closed first so the device can be opened exclusively
+/// * `original_path` - The path the device was opened with
+/// * `signature` - What the device is expected to contain
+/// * `cancel_token` - Stops retrying when the user cancels
+///
+/// # Returns
+/// * `Result<(String, File)>` - The device's current path and a new handle to it
+pub fn reopen_device(
+    stale: File,
+    original_path: &str,
+    signature: &DeviceSignature,
+    cancel_token: &crate::models::CancelToken,
+) -> Result<(String, File)> {
+    drop(stale);
+    let runtime = tokio::runtime::Handle::current();
+
+    if !signature.identity.is_known() {
+        info!("Device has no readable serial number, looking for it by content only");
+    }
+
+    for attempt in 1..=MAX_REOPEN_ATTEMPTS {
+        if cancel_token.is_cancelled() {
+            return Err(anyhow!("Operation cancelled by user"));
+        }
+
+        info!(
+            "Waiting for device to re-enumerate (attempt {}/{})",
+            attempt, MAX_REOPEN_ATTEMPTS
+        );
+        std::thread::sleep(REOPEN_DELAY);
+
+        match runtime.block_on(open_matching(original_path, signature)) {
+            Ok(Some(found)) => return Ok(found),
+            Ok(None) => warn!("Device not found yet (attempt {})", attempt),
+            Err(e) => warn!("Failed to look for device (attempt {}): {}", attempt, e),
+        }
+    }
+
+    Err(anyhow!(
+        "Device did not come back after {} attempts",
+        MAX_REOPEN_ATTEMPTS
+    ))
+}
+
+/// Open the first attached disk that matches the signature
+async fn open_matching(
+    original_path: &str,
+    signature: &DeviceSignature,
+) -> Result<Option<(String, File)>> {
+    let mut candidates = vec![original_path.to_string()];
+    let mut same_size = Vec::new();
+    for disk in super::access::backend().list_disks().await? {
+        if disk.path == original_path {
+            continue;
+        }
+        match disk.identity.same_device(&signature.identity) {
+            Some(true) => candidates.push(disk.path),
+            // A different serial number is a different device, whatever it contains
+            Some(false) => {}
+            None if disk.size == signature.disk_size => same_size.push(disk.path),
+            None => {}
+        }
+    }
+    candidates.extend(same_size);
+
+    for path in candidates {
+        // Edit mode: the device must not be cleaned, we're about to read it back
+        let mut file = match super::access::backend().lock_path(&path, true).await {
+            Ok((file, _)) => file,
+            Err(e) => {
+                warn!("Could not open {}: {}", path, e);
+                continue;
+            }
+        };
+
+        if signature.matches(&mut file) {
+            info!("Found re-enumerated device at {}", path);
+            return Ok(Some((path, file)));
+        }
+        info!("{} does not contain the written image, skipping", path);
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_signature_matches_written_data() {
+        let mut data = vec![0u8; 4 * SIGNATURE_LEN];
+        data[..8].copy_from_slice(b"EFI PART");
+        let signature = DeviceSignature {
+            identity: Default::default(),
+            disk_size: data.len() as u64,
+            head: data[..SIGNATURE_LEN].to_vec(),
+        };
+
+        assert!(signature.matches(&mut Cursor::new(data.clone())));
+
+        data[100] = 0xff;
+        assert!(!signature.matches(&mut Cursor::new(data)));
+    }
+
+    #[test]
+    fn test_signature_does_not_match_short_device() {
+        let signature = DeviceSignature {
+            identity: Default::default(),
+            disk_size: 0,
+            head: vec![1u8; SIGNATURE_LEN],
+        };
+        assert!(!signature.matches(&mut Cursor::new(vec![1u8; 512])));
+    }
+
+    #[test]
+    fn test_is_device_gone() {
+        #[cfg(windows)]
+        let gone = io::Error::from_raw_os_error(433);
+        #[cfg(not(windows))]
+        let gone = io::Error::from_raw_os_error(libc::ENODEV);
+
+        assert!(is_device_gone(&gone));
+        assert!(!is_device_gone(&io::Error::other("checksum mismatch")));
+        assert!(!is_device_gone(&io::Error::from(
+            io::ErrorKind::PermissionDenied
+        )));
+    }
+}
@@ -0,0 +1,243 @@
+/// Measuring how fast a device writes and reads before a long flash
+///
+/// The test writes 32MB in the middle of the disk, away from the partition tables at either
+/// end, and reads it back. The area is read into memory first and written back afterwards,
+/// so the disk is left as it was found. Data that doesn't read back as written says as much
+/// as the speed: counterfeit cards silently drop writes beyond their real capacity.
+use anyhow::{Context, Result, anyhow};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Bytes written and read back by the test
+pub const TEST_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Size of each read and write, as large as the image writer's
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// The test area starts on a boundary of this many bytes
+const AREA_ALIGNMENT: u64 = 1024 * 1024;
+
+/// Slowest sustained write of an SD card of speed class 10, in bytes per second
+pub const SLOW_WRITE_SPEED: f64 = 10_000_000.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeedTestResult {
+    pub write_bytes_per_second: f64,
+    pub read_bytes_per_second: f64,
+    /// Whether the test data read back as it was written
+    pub intact: bool,
+}
+
+impl SpeedTestResult {
+    /// What the user should know about the device before flashing it, if anything
+    pub fn warning(&self) -> Option<String> {
+        if !self.intact {
+            return Some(
+                "The device returned other data than was written to it. It may be counterfeit or failing."
+                    .to_string(),
+            );
+        }
+        (self.write_bytes_per_second < SLOW_WRITE_SPEED).then(|| {
+            format!(
+                "Writes are slower than the {:.0} MB/s of a Class 10 card. The device may be counterfeit or worn, and flashing will take long.",
+                SLOW_WRITE_SPEED / 1e6
+            )
+        })
+    }
+}
+
+/// Offset and length of the area tested on a disk of `disk_size` bytes
+///
+/// Small disks have a quarter of their size tested; none if that is less than 1MB.
+pub fn test_area(disk_size: u64) -> Option<(u64, u64)> {
+    let length = TEST_SIZE.min(disk_size / 4 / AREA_ALIGNMENT * AREA_ALIGNMENT);
+    if length == 0 {
+        return None;
+    }
+    Some((disk_size / 2 / AREA_ALIGNMENT * AREA_ALIGNMENT, length))
+}
+
+/// Write the test area of `disk` and read it back, putting its contents back afterwards
+///
+/// `uncache` drops what the system cached of the disk, so the data is read back from the
+/// device rather than from memory.
+pub fn run<D: Read + Write + Seek>(
+    disk: &mut D,
+    disk_size: u64,
+    uncache: impl Fn(&D),
+) -> Result<SpeedTestResult> {
+    let (offset, length) =
+        test_area(disk_size).ok_or_else(|| anyhow!("The disk is too small to test"))?;
+    let mut original = vec![0u8; length as usize];
+    disk.seek(SeekFrom::Start(offset))?;
+    disk.read_exact(&mut original)
+        .context("Failed to read the test area")?;
+
+    let pattern = test_pattern(offset, length as usize);
+    let measured = measure(disk, offset, &pattern, uncache);
+    // A write that failed halfway has changed the area too
+    write_timed(disk, offset, &original)
+        .with_context(|| format!("Failed to restore {} bytes at offset {}", length, offset))?;
+    let result = measured?;
+
+    info!(
+        "Speed test at offset {}: write {:.1} MB/s, read {:.1} MB/s, data intact: {}",
+        offset,
+        result.write_bytes_per_second / 1e6,
+        result.read_bytes_per_second / 1e6,
+        result.intact
+    );
+    Ok(result)
+}
+
+fn measure<D: Read + Write + Seek>(
+    disk: &mut D,
+    offset: u64,
+    pattern: &[u8],
+    uncache: impl Fn(&D),
+) -> Result<SpeedTestResult> {
+    let write_time = write_timed(disk, offset, pattern).context("Failed to write the test area")?;
+    uncache(disk);
+
+    let mut read_back = vec![0u8; pattern.len()];
+    let started = Instant::now();
+    disk.seek(SeekFrom::Start(offset))?;
+    for chunk in read_back.chunks_mut(CHUNK_SIZE) {
+        disk.read_exact(chunk)
+            .context("Failed to read the test area back")?;
+    }
+    let read_time = started.elapsed();
+
+    let intact = read_back == pattern;
+    if !intact {
+        warn!(
+            "The test area at offset {} read back other data than was written",
+            offset
+        );
+    }
+    Ok(SpeedTestResult {
+        write_bytes_per_second: rate(pattern.len(), write_time),
+        read_bytes_per_second: rate(pattern.len(), read_time),
+        intact,
+    })
+}
+
+fn write_timed<D: Write + Seek>(disk: &mut D, offset: u64, data: &[u8]) -> Result<Duration> {
+    let started = Instant::now();
+    disk.seek(SeekFrom::Start(offset))?;
+    for chunk in data.chunks(CHUNK_SIZE) {
+        disk.write_all(chunk)?;
+    }
+    disk.flush()?;
+    Ok(started.elapsed())
+}
+
+fn rate(bytes: usize, elapsed: Duration) -> f64 {
+    bytes as f64 / elapsed.as_secs_f64().max(1e-6)
+}
+
+/// Data that neither compresses nor repeats, so no controller can take a shortcut writing it
+pub(super) fn test_pattern(seed: u64, length: usize) -> Vec<u8> {
+    // Xorshift never leaves zero, so the seed must not be zero
+    let mut state = seed | 1;
+    let mut data = Vec::with_capacity(length + 8);
+    while data.len() < length {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        data.extend_from_slice(&state.to_le_bytes());
+    }
+    data.truncate(length);
+    data
+}
+
+/// Drop the pages the system cached of the disk behind `file`
+///
+/// Only Linux opens disks with buffered I/O; elsewhere nothing is cached.
+pub fn uncache_file(file: &std::fs::File) {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        // Written pages are clean once an O_SYNC write returns, so they can be dropped
+        let result =
+            unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+        if result != 0 {
+            warn!(
+                "Failed to drop the cached pages of the disk: error {}",
+                result
+            );
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = file;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A counterfeit card, whose writes beyond its real capacity are lost
+    struct Counterfeit {
+        inner: Cursor<Vec<u8>>,
+        capacity: u64,
+    }
+
+    impl Read for Counterfeit {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Write for Counterfeit {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.inner.position() >= self.capacity {
+                self.inner.seek(SeekFrom::Current(buf.len() as i64))?;
+                return Ok(buf.len());
+            }
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for Counterfeit {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_area_is_in_the_middle() {
+        let gb = 1024 * 1024 * 1024;
+        assert_eq!(test_area(16 * gb), Some((8 * gb, TEST_SIZE)));
+        assert_eq!(test_area(16 << 20), Some((8 << 20, 4 << 20)));
+        assert_eq!(test_area(2 << 20), None);
+    }
+
+    #[test]
+    fn test_disk_is_left_as_it_was() {
+        let contents: Vec<u8> = (0..16u32 << 20).map(|i| (i % 251) as u8).collect();
+        let mut disk = Cursor::new(contents.clone());
+        let result = run(&mut disk, contents.len() as u64, |_| {}).unwrap();
+        assert!(result.intact);
+        assert!(result.write_bytes_per_second > 0.0);
+        assert_eq!(result.warning(), None);
+        assert!(disk.into_inner() == contents);
+    }
+
+    #[test]
+    fn test_lost_writes_are_found() {
+        let size = 64u64 << 20;
+        let mut disk = Counterfeit {
+            inner: Cursor::new(vec![0u8; size as usize]),
+            capacity: size / 2 + (1 << 20),
+        };
+        let result = run(&mut disk, size, |_| {}).unwrap();
+        assert!(!result.intact);
+        assert!(result.warning().unwrap().contains("counterfeit"));
+    }
+}
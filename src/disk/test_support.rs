@@ -0,0 +1,461 @@
+// Synthetic disks for unit tests
+//
+// Small GPT disks built in memory the way an image writer leaves them: a protective MBR,
+// primary and backup headers with valid CRCs, and partitions holding whatever the test
+// puts there, usually a FAT filesystem with golem.env and golemwz.toml. They stand in for
+// a flashed card in tests of the partition table and configuration partition code.
+
+use super::Disk;
+use super::access::{self, DiskAccess, DiskBackend};
+use super::common::{DiskDevice, FlashPhase};
+use super::gpt::guid;
+use super::layout::GOLEM_CONFIG_PARTITION_GUID;
+use crate::models::CancelToken;
+use anyhow::{Result, anyhow};
+use crc32fast::Hasher;
+use futures_util::future::BoxFuture;
+use std::fs::File;
+use std::io::{self, Cursor, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
+
+pub const MIB: u64 = 1024 * 1024;
+
+/// Type of the configuration partition in Golem images
+pub const BASIC_DATA_TYPE_GUID: &str = "ebd0a0a2-b9e5-4433-87c0-68b6b72699c7";
+
+/// Type of the root partition in Golem images
+pub const LINUX_FILESYSTEM_TYPE_GUID: &str = "0fc63daf-8483-4772-8e79-3d69d8477de4";
+
+/// Unique GUID of the root partition of [`golem_disk`]
+pub const ROOT_PARTITION_GUID: &str = "8f2b9c1e-3c1d-4a4e-9d3b-2d6a1f0e5b7c";
+
+/// Size of [`golem_disk`], as written by the image
+pub const GOLEM_DISK_SIZE: u64 = 8 * MIB;
+
+/// Byte range of the configuration partition of [`golem_disk`]
+pub const CONFIG_PARTITION_OFFSET: u64 = MIB;
+pub const CONFIG_PARTITION_SIZE: u64 = 2 * MIB;
+
+/// Byte range of the root partition of [`golem_disk`]
+pub const ROOT_PARTITION_OFFSET: u64 = 3 * MIB;
+pub const ROOT_PARTITION_SIZE: u64 = 4 * MIB;
+
+/// Byte the root partition of [`golem_disk`] is filled with
+pub const ROOT_FILL: u8 = 0xA5;
+
+/// Entries in the partition array and bytes per entry, as every common partitioner writes it
+const ENTRY_COUNT: u64 = 128;
+const ENTRY_SIZE: u64 = 128;
+
+const DISK_GUID: &str = "5b2a6e0c-7d1f-4c3e-a8b9-0e4f6d2c1a3b";
+
+struct Partition {
+    type_guid: Uuid,
+    unique_guid: Uuid,
+    first_lba: u64,
+    last_lba: u64,
+    name: String,
+    contents: Vec<u8>,
+}
+
+/// A GPT disk image under construction
+pub struct GptDiskBuilder {
+    size: u64,
+    sector_size: u64,
+    partitions: Vec<Partition>,
+}
+
+impl GptDiskBuilder {
+    /// An empty partition table on a disk of `size` bytes with `sector_size` byte LBAs
+    pub fn new(size: u64, sector_size: u64) -> Self {
+        GptDiskBuilder {
+            size,
+            sector_size,
+            partitions: Vec::new(),
+        }
+    }
+
+    /// Add a partition covering `offset..offset + size` bytes, starting with `contents`
+    pub fn partition(
+        mut self,
+        type_guid: &str,
+        unique_guid: &str,
+        name: &str,
+        offset: u64,
+        size: u64,
+        contents: Vec<u8>,
+    ) -> Self {
+        assert!(
+            contents.len() as u64 <= size,
+            "partition contents too large"
+        );
+        self.partitions.push(Partition {
+            type_guid: Uuid::parse_str(type_guid).unwrap(),
+            unique_guid: Uuid::parse_str(unique_guid).unwrap(),
+            first_lba: offset / self.sector_size,
+            last_lba: (offset + size) / self.sector_size - 1,
+            name: name.to_string(),
+            contents,
+        });
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let sector = self.sector_size;
+        let mut disk = vec![0u8; self.size as usize];
+        let last_lba = self.size / sector - 1;
+        let entries_sectors = (ENTRY_COUNT * ENTRY_SIZE).div_ceil(sector);
+
+        // Protective MBR covering the whole disk
+        let sectors = last_lba.min(u32::MAX as u64) as u32;
+        disk[446 + 4] = 0xEE;
+        disk[446 + 8..446 + 12].copy_from_slice(&1u32.to_le_bytes());
+        disk[446 + 12..446 + 16].copy_from_slice(&sectors.to_le_bytes());
+        disk[510] = 0x55;
+        disk[511] = 0xAA;
+
+        let mut entries = vec![0u8; (ENTRY_COUNT * ENTRY_SIZE) as usize];
+        for (index, partition) in self.partitions.iter().enumerate() {
+            let entry = &mut entries[index * ENTRY_SIZE as usize..][..ENTRY_SIZE as usize];
+            entry[0..16].copy_from_slice(&guid::to_gpt_bytes(&partition.type_guid));
+            entry[16..32].copy_from_slice(&guid::to_gpt_bytes(&partition.unique_guid));
+            entry[32..40].copy_from_slice(&partition.first_lba.to_le_bytes());
+            entry[40..48].copy_from_slice(&partition.last_lba.to_le_bytes());
+            for (i, unit) in partition.name.encode_utf16().enumerate() {
+                entry[56 + i * 2..58 + i * 2].copy_from_slice(&unit.to_le_bytes());
+            }
+
+            let start = (partition.first_lba * sector) as usize;
+            disk[start..start + partition.contents.len()].copy_from_slice(&partition.contents);
+        }
+        let entries_crc = crc32(&entries);
+
+        let backup_entries_lba = last_lba - entries_sectors;
+        let usable = (2 + entries_sectors, backup_entries_lba - 1);
+        let primary = header(1, last_lba, usable, 2, entries_crc);
+        let backup = header(last_lba, 1, usable, backup_entries_lba, entries_crc);
+
+        write_at(&mut disk, sector, &primary);
+        write_at(&mut disk, 2 * sector, &entries);
+        write_at(&mut disk, backup_entries_lba * sector, &entries);
+        write_at(&mut disk, last_lba * sector, &backup);
+        disk
+    }
+}
+
+/// A Golem image of [`GOLEM_DISK_SIZE`] bytes
+///
+/// The configuration partition is a FAT volume holding `files`, the root partition is
+/// filled with [`ROOT_FILL`] so tests can check that it was left alone.
+pub fn golem_disk(sector_size: u64, files: &[(&str, &str)]) -> Vec<u8> {
+    GptDiskBuilder::new(GOLEM_DISK_SIZE, sector_size)
+        .partition(
+            BASIC_DATA_TYPE_GUID,
+            GOLEM_CONFIG_PARTITION_GUID,
+            "config",
+            CONFIG_PARTITION_OFFSET,
+            CONFIG_PARTITION_SIZE,
+            fat_volume(CONFIG_PARTITION_SIZE, files),
+        )
+        .partition(
+            LINUX_FILESYSTEM_TYPE_GUID,
+            ROOT_PARTITION_GUID,
+            "root",
+            ROOT_PARTITION_OFFSET,
+            ROOT_PARTITION_SIZE,
+            vec![ROOT_FILL; ROOT_PARTITION_SIZE as usize],
+        )
+        .build()
+}
+
+/// A freshly formatted GOLEMCONF volume of `size` bytes containing `files`
+pub fn fat_volume(size: u64, files: &[(&str, &str)]) -> Vec<u8> {
+    let mut volume = vec![0u8; size as usize];
+    fatfs::format_volume(
+        Cursor::new(&mut volume[..]),
+        fatfs::FormatVolumeOptions::new().volume_label(*b"GOLEMCONF  "),
+    )
+    .unwrap();
+    {
+        let fs =
+            fatfs::FileSystem::new(Cursor::new(&mut volume[..]), fatfs::FsOptions::new()).unwrap();
+        let root_dir = fs.root_dir();
+        for (name, content) in files {
+            let mut file = root_dir.create_file(name).unwrap();
+            file.write_all(content.as_bytes()).unwrap();
+        }
+    }
+    volume
+}
+
+/// Files in the root of the FAT volume `volume`, `None` for the ones that are missing
+pub fn read_files(volume: &mut [u8], names: &[&str]) -> Vec<Option<String>> {
+    let fs = fatfs::FileSystem::new(Cursor::new(volume), fatfs::FsOptions::new()).unwrap();
+    let root_dir = fs.root_dir();
+    names
+        .iter()
+        .map(|name| super::read_config_file(&root_dir, name))
+        .collect()
+}
+
+/// The fields of a GPT header the tests look at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderFields {
+    pub current_lba: u64,
+    pub backup_lba: u64,
+    pub entries_lba: u64,
+    pub entries_crc: u32,
+    pub crc_valid: bool,
+}
+
+/// The GPT header at `lba`, `None` without an `EFI PART` signature
+pub fn read_header(disk: &[u8], lba: u64, sector_size: u64) -> Option<HeaderFields> {
+    let header = &disk[(lba * sector_size) as usize..][..92];
+    if &header[0..8] != b"EFI PART" {
+        return None;
+    }
+    let u64_at = |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+    let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+
+    let mut zeroed = header.to_vec();
+    zeroed[16..20].fill(0);
+    Some(HeaderFields {
+        current_lba: u64_at(24),
+        backup_lba: u64_at(32),
+        entries_lba: u64_at(72),
+        entries_crc: u32_at(88),
+        crc_valid: crc32(&zeroed) == u32_at(16),
+    })
+}
+
+/// The partition entry array at `lba`
+pub fn read_entries(disk: &[u8], lba: u64, sector_size: u64) -> &[u8] {
+    &disk[(lba * sector_size) as usize..][..(ENTRY_COUNT * ENTRY_SIZE) as usize]
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut hasher = Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// A disk image in a temporary file, for code that takes a `File` or a [`Disk`]
+pub struct DiskImage {
+    file: tempfile::NamedTempFile,
+}
+
+impl DiskImage {
+    pub fn new(contents: &[u8]) -> Self {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents).unwrap();
+        file.flush().unwrap();
+        DiskImage { file }
+    }
+
+    /// A new read-write handle to the image
+    pub fn file(&self) -> File {
+        self.file.reopen().unwrap()
+    }
+
+    pub fn contents(&self) -> Vec<u8> {
+        std::fs::read(self.file.path()).unwrap()
+    }
+
+    /// The image opened like a locked device
+    pub fn disk(&self) -> Disk {
+        Disk {
+            file: self.file(),
+            platform: Arc::new(FakeDiskAccess),
+            original_path: self.file.path().to_string_lossy().into_owned(),
+        }
+    }
+}
+
+/// A [`DiskBackend`] serving disk images instead of devices
+///
+/// Every call is recorded, so tests can check what a workflow did to which disk.
+#[derive(Default)]
+pub struct FakeBackend {
+    disks: Mutex<Vec<(DiskDevice, DiskImage)>>,
+    calls: Mutex<Vec<String>>,
+}
+
+/// Keeps a [`FakeBackend`] installed, serializing the tests that use one
+pub struct InstalledBackend {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Drop for InstalledBackend {
+    fn drop(&mut self) {
+        access::set_backend(None);
+    }
+}
+
+impl FakeBackend {
+    /// Open disks through `backend` until the returned guard is dropped
+    pub fn install(backend: &Arc<FakeBackend>) -> InstalledBackend {
+        static INSTALLED: Mutex<()> = Mutex::new(());
+        let lock = INSTALLED
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        access::set_backend(Some(backend.clone()));
+        InstalledBackend { _lock: lock }
+    }
+
+    /// Attach a removable disk at `path` holding `contents`
+    pub fn attach(&self, path: &str, contents: &[u8]) {
+        let device = DiskDevice {
+            path: path.to_string(),
+            name: format!("Fake disk {}", path),
+            size: contents.len() as u64,
+            removable: true,
+            readonly: false,
+            vendor: "Golem".to_string(),
+            model: "Fake disk".to_string(),
+            system: false,
+            identity: Default::default(),
+        };
+        self.disks
+            .lock()
+            .unwrap()
+            .push((device, DiskImage::new(contents)));
+    }
+
+    /// What the disk at `path` holds now
+    pub fn contents(&self, path: &str) -> Vec<u8> {
+        let disks = self.disks.lock().unwrap();
+        let (_, image) = disks
+            .iter()
+            .find(|(device, _)| device.path == path)
+            .unwrap();
+        image.contents()
+    }
+
+    /// Backend calls so far, e.g. `lock /dev/fake0`
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, call: String) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+impl DiskBackend for FakeBackend {
+    fn lock_path<'a>(
+        &'a self,
+        path: &'a str,
+        _edit_mode: bool,
+    ) -> BoxFuture<'a, Result<(File, Arc<dyn DiskAccess>)>> {
+        self.record(format!("lock {}", path));
+        let file = self
+            .disks
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(device, _)| device.path == path)
+            .map(|(_, image)| image.file());
+        Box::pin(async move {
+            let file = file.ok_or_else(|| anyhow!("{} is not attached", path))?;
+            Ok((file, Arc::new(FakeDiskAccess) as Arc<dyn DiskAccess>))
+        })
+    }
+
+    fn clear_partitions(
+        &self,
+        path: String,
+        _cancel_token: CancelToken,
+        progress: UnboundedSender<FlashPhase>,
+    ) -> BoxFuture<'static, Result<FlashPhase>> {
+        self.record(format!("clear {}", path));
+        Box::pin(async move {
+            let _ = progress.send(FlashPhase::Clearing {
+                progress: 1.0,
+                message: "Device prepared for writing".to_string(),
+            });
+            Ok(FlashPhase::Done)
+        })
+    }
+
+    fn list_disks(&self) -> BoxFuture<'_, Result<Vec<DiskDevice>>> {
+        self.record("list".to_string());
+        let devices = self
+            .disks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(device, _)| device.clone())
+            .collect();
+        Box::pin(async move { Ok(devices) })
+    }
+
+    fn eject(&self, path: String) -> BoxFuture<'static, Result<()>> {
+        self.record(format!("eject {}", path));
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Platform handling of a disk image, which has no quirks
+#[derive(Debug)]
+struct FakeDiskAccess;
+
+impl DiskAccess for FakeDiskAccess {
+    fn clone_file_handle(&self, file: &File) -> Result<File> {
+        Ok(file.try_clone()?)
+    }
+
+    fn pre_write_checks(&self, _disk_file: &File, _original_path: Option<&str>) -> Result<()> {
+        Ok(())
+    }
+
+    fn handle_write_error(&self, _e: &io::Error) -> Option<anyhow::Error> {
+        None
+    }
+
+    fn handle_flush_error(&self, _e: &io::Error) -> Option<anyhow::Error> {
+        None
+    }
+
+    fn reopen_buffered(&self, file: &File) -> Result<File> {
+        Ok(file.try_clone()?)
+    }
+
+    fn handle_gpt_error<'a>(
+        &self,
+        _disk: &'a Disk,
+        _error: anyhow::Error,
+    ) -> Result<Option<gpt::GptDisk<'a>>> {
+        Ok(None)
+    }
+}
+
+fn header(
+    current_lba: u64,
+    backup_lba: u64,
+    (first_usable, last_usable): (u64, u64),
+    entries_lba: u64,
+    entries_crc: u32,
+) -> Vec<u8> {
+    let mut header = vec![0u8; 92];
+    header[0..8].copy_from_slice(b"EFI PART");
+    header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+    header[12..16].copy_from_slice(&92u32.to_le_bytes());
+    header[24..32].copy_from_slice(&current_lba.to_le_bytes());
+    header[32..40].copy_from_slice(&backup_lba.to_le_bytes());
+    header[40..48].copy_from_slice(&first_usable.to_le_bytes());
+    header[48..56].copy_from_slice(&last_usable.to_le_bytes());
+    header[56..72].copy_from_slice(&guid::to_gpt_bytes(&Uuid::parse_str(DISK_GUID).unwrap()));
+    header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+    header[80..84].copy_from_slice(&(ENTRY_COUNT as u32).to_le_bytes());
+    header[84..88].copy_from_slice(&(ENTRY_SIZE as u32).to_le_bytes());
+    header[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+    let crc = crc32(&header);
+    header[16..20].copy_from_slice(&crc.to_le_bytes());
+    header
+}
+
+fn write_at(disk: &mut [u8], offset: u64, data: &[u8]) {
+    disk[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+}
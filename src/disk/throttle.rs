@@ -0,0 +1,92 @@
+/// Limiting the speed the image is written at
+///
+/// A token bucket holding up to a second of writes: chunks are written as fast as the
+/// device takes them until the bucket runs dry, then each chunk waits for its tokens.
+use std::time::{Duration, Instant};
+
+pub struct Throttle {
+    bytes_per_second: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_second: u64) -> Self {
+        let bytes_per_second = bytes_per_second.max(1) as f64;
+        Self {
+            bytes_per_second,
+            tokens: bytes_per_second,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Block until `bytes` may be written
+    pub fn wait(&mut self, bytes: u64) {
+        let delay = self.reserve(bytes, Instant::now());
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Take `bytes` from the bucket, returning how long to wait for them
+    ///
+    /// The bucket may go into debt for chunks larger than a second of writes; the wait
+    /// pays it back.
+    fn reserve(&mut self, bytes: u64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.bytes_per_second).min(self.bytes_per_second);
+        self.refilled = now;
+
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.bytes_per_second)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_burst_then_limited_rate() {
+        let mut throttle = Throttle::new(8 * MB);
+        let start = throttle.refilled;
+
+        // A second of writes goes through right away
+        assert_eq!(throttle.reserve(4 * MB, start), Duration::ZERO);
+        assert_eq!(throttle.reserve(4 * MB, start), Duration::ZERO);
+
+        // Then each chunk waits for its share
+        assert_eq!(throttle.reserve(4 * MB, start), Duration::from_millis(500));
+        let after_wait = start + Duration::from_millis(500);
+        assert_eq!(
+            throttle.reserve(4 * MB, after_wait),
+            Duration::from_millis(500)
+        );
+    }
+
+    #[test]
+    fn test_idle_time_refills_only_one_second() {
+        let mut throttle = Throttle::new(2 * MB);
+        let start = throttle.refilled;
+        assert_eq!(throttle.reserve(2 * MB, start), Duration::ZERO);
+
+        let later = start + Duration::from_secs(60);
+        assert_eq!(throttle.reserve(2 * MB, later), Duration::ZERO);
+        assert_eq!(throttle.reserve(MB, later), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_chunks_larger_than_rate() {
+        let mut throttle = Throttle::new(MB);
+        let start = throttle.refilled;
+        assert_eq!(throttle.reserve(4 * MB, start), Duration::from_secs(3));
+        let after_wait = start + Duration::from_secs(3);
+        assert_eq!(throttle.reserve(4 * MB, after_wait), Duration::from_secs(4));
+    }
+}
@@ -0,0 +1,353 @@
+// Where a block device sits in the disk it belongs to
+//
+// Images are written to whole disks only. On Linux several kinds of block device look like
+// disks but aren't one: partitions (/dev/sdb1, /dev/nvme0n1p1), the boot and RPMB areas
+// of eMMC chips (/dev/mmcblk0boot0) and the individual paths of a dm-multipath device,
+// which sysfs shows as ordinary disks. NVMe namespaces (/dev/nvme0n1) and multipath maps
+// are whole disks. On Windows a drive letter (D:) names a volume, which is looked up on its
+// physical drive.
+
+#[cfg(target_os = "linux")]
+use anyhow::Context;
+use anyhow::{Result, anyhow};
+
+/// How a block device relates to the disk it is part of
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceRole {
+    /// A whole disk, including NVMe namespaces and multipath maps
+    WholeDisk,
+    /// A partition of the disk `parent`, or a volume on it on Windows
+    Partition { parent: String },
+    /// A boot or RPMB area of the eMMC `parent`, outside of its user area
+    MmcBootArea { parent: String },
+    /// One of the paths to the multipath device `map`
+    MultipathPath { map: String },
+}
+
+/// A block device with its parent and children
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTopology {
+    /// Device node, with symlinks such as /dev/disk/by-id/* resolved
+    pub node: String,
+    pub role: DeviceRole,
+    /// Device nodes of the partitions of a whole disk
+    pub partitions: Vec<String>,
+}
+
+impl BlockTopology {
+    /// The device to write to instead, if this isn't a whole disk
+    pub fn whole_disk(&self) -> Option<&str> {
+        match &self.role {
+            DeviceRole::WholeDisk => None,
+            DeviceRole::Partition { parent } | DeviceRole::MmcBootArea { parent } => Some(parent),
+            DeviceRole::MultipathPath { map } => Some(map),
+        }
+    }
+
+    /// Fail unless the device is a whole disk, naming the one to pick instead
+    pub fn require_whole_disk(&self) -> Result<()> {
+        match &self.role {
+            DeviceRole::WholeDisk => Ok(()),
+            DeviceRole::Partition { parent } => Err(anyhow!(
+                "{} is a partition of {}. Images are written to whole disks: writing one to a \
+                 partition would put it in the middle of the disk and corrupt it. Select {} \
+                 instead.",
+                self.node,
+                parent,
+                parent
+            )),
+            DeviceRole::MmcBootArea { parent } => Err(anyhow!(
+                "{} is a boot area of the eMMC {}, not a disk. Select {} instead.",
+                self.node,
+                parent,
+                parent
+            )),
+            DeviceRole::MultipathPath { map } => Err(anyhow!(
+                "{} is one path to the multipath device {}. Select {} instead.",
+                self.node,
+                map,
+                map
+            )),
+        }
+    }
+}
+
+/// Find out what the block device at `path` is part of
+pub fn inspect(path: &str) -> Result<BlockTopology> {
+    platform::inspect(path)
+}
+
+/// The drive letter a path names, e.g. `D` for `D:`, `D:\` or `\\.\D:`
+#[cfg_attr(not(windows), allow(dead_code))]
+fn volume_letter(path: &str) -> Option<char> {
+    let volume = path.strip_prefix(r"\\.\").unwrap_or(path);
+    let volume = volume.strip_suffix('\\').unwrap_or(volume);
+    let mut chars = volume.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(letter), Some(':'), None) if letter.is_ascii_alphabetic() => {
+            Some(letter.to_ascii_uppercase())
+        }
+        _ => None,
+    }
+}
+
+/// The eMMC a boot or RPMB area belongs to, e.g. `mmcblk0` for `mmcblk0boot1`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn mmc_boot_area_parent(name: &str) -> Option<&str> {
+    let rest = name.strip_prefix("mmcblk")?;
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    let area = &rest[digits..];
+    let is_boot_area = area == "rpmb"
+        || area
+            .strip_prefix("boot")
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    is_boot_area.then_some(&name[.."mmcblk".len() + digits])
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{BlockTopology, Context, DeviceRole, Result, anyhow, mmc_boot_area_parent};
+    use std::fs;
+    use std::path::Path;
+
+    pub fn inspect(path: &str) -> Result<BlockTopology> {
+        let canonical =
+            fs::canonicalize(path).with_context(|| format!("{} does not exist", path))?;
+        let name = canonical
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("{} is not a device", path))?
+            .to_string();
+        let node = format!("/dev/{}", name);
+        let sysfs = Path::new("/sys/class/block").join(&name);
+        if !sysfs.exists() {
+            // e.g. /dev/nvme0, the character device of an NVMe controller
+            return Err(anyhow!(
+                "{} is not a block device. NVMe drives are written through a namespace such \
+                 as /dev/nvme0n1.",
+                path
+            ));
+        }
+
+        let role = if sysfs.join("partition").exists() {
+            // The partition's sysfs directory sits inside that of its disk
+            let parent = fs::canonicalize(&sysfs)
+                .ok()
+                .and_then(|real| Some(real.parent()?.file_name()?.to_str()?.to_string()))
+                .ok_or_else(|| anyhow!("Failed to find the disk of partition {}", node))?;
+            DeviceRole::Partition {
+                parent: format!("/dev/{}", parent),
+            }
+        } else if let Some(parent) = dm_partition_parent(&sysfs) {
+            DeviceRole::Partition { parent }
+        } else if let Some(parent) = mmc_boot_area_parent(&name) {
+            DeviceRole::MmcBootArea {
+                parent: format!("/dev/{}", parent),
+            }
+        } else if let Some(map) = multipath_map_holding(&sysfs) {
+            DeviceRole::MultipathPath { map }
+        } else {
+            DeviceRole::WholeDisk
+        };
+
+        let mut partitions = Vec::new();
+        if role == DeviceRole::WholeDisk {
+            if let Ok(entries) = fs::read_dir(&sysfs) {
+                partitions.extend(
+                    entries
+                        .flatten()
+                        .filter(|entry| entry.path().join("partition").exists())
+                        .map(|entry| format!("/dev/{}", entry.file_name().to_string_lossy())),
+                );
+            }
+            // Partitions of a multipath device are device-mapper devices of their own
+            if let Ok(holders) = fs::read_dir(sysfs.join("holders")) {
+                partitions.extend(
+                    holders
+                        .flatten()
+                        .filter(|holder| {
+                            dm_uuid(&holder.path()).is_some_and(|uuid| uuid.starts_with("part"))
+                        })
+                        .filter_map(|holder| dm_name(&holder.path())),
+                );
+            }
+        }
+
+        Ok(BlockTopology {
+            node,
+            role,
+            partitions,
+        })
+    }
+
+    /// Node of the dm-multipath device using this disk as one of its paths
+    fn multipath_map_holding(sysfs: &Path) -> Option<String> {
+        fs::read_dir(sysfs.join("holders"))
+            .ok()?
+            .flatten()
+            .filter(|holder| dm_uuid(&holder.path()).is_some_and(|uuid| uuid.starts_with("mpath-")))
+            .find_map(|holder| dm_name(&holder.path()))
+    }
+
+    /// Node of the multipath device a device-mapper partition (`part1-mpath-*`) belongs to
+    fn dm_partition_parent(sysfs: &Path) -> Option<String> {
+        if !dm_uuid(sysfs)?.starts_with("part") {
+            return None;
+        }
+        fs::read_dir(sysfs.join("slaves"))
+            .ok()?
+            .flatten()
+            .find_map(|slave| dm_name(&slave.path()))
+    }
+
+    fn dm_uuid(sysfs: &Path) -> Option<String> {
+        fs::read_to_string(sysfs.join("dm/uuid")).ok()
+    }
+
+    /// /dev/mapper node of a device-mapper device
+    fn dm_name(sysfs: &Path) -> Option<String> {
+        let name = fs::read_to_string(sysfs.join("dm/name")).ok()?;
+        Some(format!("/dev/mapper/{}", name.trim()))
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{BlockTopology, DeviceRole, Result, anyhow, volume_letter};
+    use std::fs::OpenOptions;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::Storage::FileSystem::{FILE_SHARE_READ, FILE_SHARE_WRITE};
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+    use windows_sys::Win32::System::Ioctl::{
+        IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS, VOLUME_DISK_EXTENTS,
+    };
+
+    pub fn inspect(path: &str) -> Result<BlockTopology> {
+        let Some(letter) = volume_letter(path) else {
+            return Ok(BlockTopology {
+                node: path.to_string(),
+                role: DeviceRole::WholeDisk,
+                partitions: Vec::new(),
+            });
+        };
+
+        let node = format!("{}:", letter);
+        let parent = format!(r"\\.\PhysicalDrive{}", disk_number_of_volume(letter)?);
+        Ok(BlockTopology {
+            node,
+            role: DeviceRole::Partition { parent },
+            partitions: Vec::new(),
+        })
+    }
+
+    /// Number of the physical drive a volume starts on
+    fn disk_number_of_volume(letter: char) -> Result<u32> {
+        let volume_path = format!(r"\\.\{}:", letter);
+        // No access rights are needed to ask for the extents
+        let volume = OpenOptions::new()
+            .access_mode(0)
+            .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE)
+            .open(&volume_path)
+            .map_err(|e| anyhow!("Failed to open volume {}: {}", volume_path, e))?;
+
+        let mut extents: VOLUME_DISK_EXTENTS = unsafe { std::mem::zeroed() };
+        let mut bytes_returned = 0u32;
+        let result = unsafe {
+            DeviceIoControl(
+                volume.as_raw_handle() as HANDLE,
+                IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS,
+                std::ptr::null(),
+                0,
+                &mut extents as *mut _ as *mut _,
+                std::mem::size_of::<VOLUME_DISK_EXTENTS>() as u32,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+        // Volumes spanning several disks fail with ERROR_MORE_DATA, their first disk is enough
+        if result == 0 && extents.NumberOfDiskExtents == 0 {
+            return Err(anyhow!(
+                "Failed to find the disk of volume {}: {}",
+                volume_path,
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(extents.Extents[0].DiskNumber)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::{BlockTopology, DeviceRole, Result};
+
+    pub fn inspect(path: &str) -> Result<BlockTopology> {
+        Ok(BlockTopology {
+            node: path.to_string(),
+            role: DeviceRole::WholeDisk,
+            partitions: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mmc_boot_area_parent() {
+        assert_eq!(mmc_boot_area_parent("mmcblk0boot0"), Some("mmcblk0"));
+        assert_eq!(mmc_boot_area_parent("mmcblk12boot1"), Some("mmcblk12"));
+        assert_eq!(mmc_boot_area_parent("mmcblk0rpmb"), Some("mmcblk0"));
+        assert_eq!(mmc_boot_area_parent("mmcblk0"), None);
+        assert_eq!(mmc_boot_area_parent("mmcblk0p1"), None);
+        assert_eq!(mmc_boot_area_parent("mmcblk0boot"), None);
+        assert_eq!(mmc_boot_area_parent("mmcblkboot0"), None);
+        assert_eq!(mmc_boot_area_parent("sdb"), None);
+    }
+
+    #[test]
+    fn test_volume_letter() {
+        assert_eq!(volume_letter("D:"), Some('D'));
+        assert_eq!(volume_letter("e:\\"), Some('E'));
+        assert_eq!(volume_letter(r"\\.\F:"), Some('F'));
+        assert_eq!(volume_letter(r"\\.\PhysicalDrive1"), None);
+        assert_eq!(volume_letter("1:"), None);
+        assert_eq!(volume_letter("/dev/sdb1"), None);
+    }
+
+    #[test]
+    fn test_whole_disk_is_suggested() {
+        let partition = BlockTopology {
+            node: "/dev/nvme0n1p2".to_string(),
+            role: DeviceRole::Partition {
+                parent: "/dev/nvme0n1".to_string(),
+            },
+            partitions: Vec::new(),
+        };
+        assert_eq!(partition.whole_disk(), Some("/dev/nvme0n1"));
+        let error = partition.require_whole_disk().unwrap_err().to_string();
+        assert!(error.contains("Select /dev/nvme0n1 instead"));
+
+        let path = BlockTopology {
+            node: "/dev/sdc".to_string(),
+            role: DeviceRole::MultipathPath {
+                map: "/dev/mapper/mpatha".to_string(),
+            },
+            partitions: Vec::new(),
+        };
+        assert_eq!(path.whole_disk(), Some("/dev/mapper/mpatha"));
+
+        let disk = BlockTopology {
+            node: "/dev/sdb".to_string(),
+            role: DeviceRole::WholeDisk,
+            partitions: vec!["/dev/sdb1".to_string()],
+        };
+        assert_eq!(disk.whole_disk(), None);
+        assert!(disk.require_whole_disk().is_ok());
+    }
+}
@@ -0,0 +1,293 @@
+/// In-place updates of devices that already carry a Golem image
+///
+/// An update rewrites the whole disk with the new image, which also replaces the
+/// configuration partition. Its files are read into memory beforehand and written back
+/// over the defaults shipped with the new image, so the wallet, SSH keys and anything
+/// else the operator put on GOLEMCONF survive the update.
+use super::layout::DiskLayout;
+use super::{GolemConfig, ImageConfiguration};
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use tracing::{debug, info};
+
+/// Files on the configuration partition that may record the installed image version
+const VERSION_FILES: [&str; 3] = ["golem-image-version", "image-version", "VERSION"];
+
+/// Version numbers such as `v0.1.5` or `1.2.0-rc1`
+const VERSION_PATTERN: &str = r"v?\d+\.\d+(?:\.\d+)*(?:-[0-9A-Za-z.]+)?";
+
+/// Files copied off a configuration partition
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigSnapshot {
+    /// Path relative to the partition root (using `/`) and the file's contents
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+impl ConfigSnapshot {
+    /// Read every file on the partition, including those in subdirectories
+    pub fn capture<T: fatfs::ReadWriteSeek>(root_dir: &fatfs::Dir<'_, T>) -> Result<Self> {
+        let mut files = Vec::new();
+        capture_dir(root_dir, "", &mut files)?;
+        debug!(
+            "Captured {} file(s) from configuration partition",
+            files.len()
+        );
+        Ok(Self { files })
+    }
+
+    /// Write the captured files back, replacing files of the same name
+    ///
+    /// Files that exist on the partition but not in the snapshot are left alone, so new
+    /// defaults shipped with an image are kept unless the device had its own version.
+    pub fn restore<T: fatfs::ReadWriteSeek>(&self, root_dir: &fatfs::Dir<'_, T>) -> Result<()> {
+        for (path, content) in &self.files {
+            let (parent, _) = path.rsplit_once('/').unwrap_or(("", path));
+            if !parent.is_empty() {
+                create_dir_all(root_dir, parent)?;
+            }
+
+            let mut file = root_dir
+                .create_file(path)
+                .with_context(|| format!("Failed to create {}", path))?;
+            file.truncate()
+                .with_context(|| format!("Failed to truncate {}", path))?;
+            file.write_all(content)
+                .with_context(|| format!("Failed to restore {}", path))?;
+            file.flush()?;
+        }
+        info!(
+            "Restored {} file(s) to configuration partition",
+            self.files.len()
+        );
+        Ok(())
+    }
+
+    /// Contents of a file in the partition root, matched case-insensitively like FAT does
+    pub fn file(&self, name: &str) -> Option<&[u8]> {
+        self.files
+            .iter()
+            .find(|(path, _)| path.eq_ignore_ascii_case(name))
+            .map(|(_, content)| content.as_slice())
+    }
+}
+
+/// What the configuration partition gets once an image has been written
+#[derive(Debug, Clone)]
+pub enum ConfigPartitionContents {
+    /// Merge a configuration into the files shipped with the image
+    Configuration(Box<ImageConfiguration>),
+    /// Put back the files of the partition the device had before it was re-imaged
+    Restore(ConfigSnapshot),
+}
+
+/// The Golem image currently installed on a device
+#[derive(Debug, Clone)]
+pub struct InstalledImage {
+    /// Image version, if the device records one
+    pub version: Option<String>,
+    /// Configuration parsed from the partition, if it could be read
+    pub config: Option<GolemConfig>,
+    /// Files to restore after the update
+    pub snapshot: ConfigSnapshot,
+}
+
+/// Find the version of the installed image
+///
+/// A version file on the configuration partition wins; otherwise the partition labels
+/// and GPT names are searched, as images name their root filesystem after the release.
+pub fn detect_image_version(snapshot: &ConfigSnapshot, layout: &DiskLayout) -> Option<String> {
+    let from_file = VERSION_FILES.iter().find_map(|name| {
+        let content = snapshot.file(name)?;
+        parse_version(&String::from_utf8_lossy(content))
+    });
+    if from_file.is_some() {
+        return from_file;
+    }
+
+    layout.partitions.iter().find_map(|partition| {
+        partition
+            .label
+            .as_deref()
+            .and_then(parse_version)
+            .or_else(|| parse_version(&partition.name))
+    })
+}
+
+/// Whether two version strings name the same release, ignoring a leading `v` and case
+pub fn same_version(a: &str, b: &str) -> bool {
+    let normalize = |version: &str| {
+        let version = version.trim();
+        version
+            .strip_prefix(['v', 'V'])
+            .unwrap_or(version)
+            .to_ascii_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// First thing that looks like a version number in `text`
+fn parse_version(text: &str) -> Option<String> {
+    let re = regex::Regex::new(VERSION_PATTERN).unwrap();
+    re.find(text).map(|found| found.as_str().to_string())
+}
+
+fn capture_dir<T: fatfs::ReadWriteSeek>(
+    dir: &fatfs::Dir<'_, T>,
+    prefix: &str,
+    files: &mut Vec<(String, Vec<u8>)>,
+) -> Result<()> {
+    for entry in dir.iter() {
+        let entry = entry.context("Failed to list configuration partition")?;
+        let name = entry.file_name();
+        if name == "." || name == ".." {
+            continue;
+        }
+
+        let path = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        if entry.is_dir() {
+            capture_dir(&entry.to_dir(), &path, files)?;
+        } else {
+            let mut content = Vec::new();
+            entry
+                .to_file()
+                .read_to_end(&mut content)
+                .with_context(|| format!("Failed to read {}", path))?;
+            files.push((path, content));
+        }
+    }
+    Ok(())
+}
+
+fn create_dir_all<T: fatfs::ReadWriteSeek>(root_dir: &fatfs::Dir<'_, T>, path: &str) -> Result<()> {
+    let mut current = String::new();
+    for component in path.split('/') {
+        if !current.is_empty() {
+            current.push('/');
+        }
+        current.push_str(component);
+        if root_dir.open_dir(&current).is_err() {
+            root_dir
+                .create_dir(&current)
+                .with_context(|| format!("Failed to create directory {}", current))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::layout::{PartitionInfo, PartitionScheme};
+    use std::io::Cursor;
+
+    fn fat_volume() -> Vec<u8> {
+        let mut volume = vec![0u8; 4 * 1024 * 1024];
+        fatfs::format_volume(
+            Cursor::new(&mut volume[..]),
+            fatfs::FormatVolumeOptions::new().volume_label(*b"GOLEMCONF  "),
+        )
+        .unwrap();
+        volume
+    }
+
+    fn write_file(volume: &mut [u8], path: &str, content: &[u8]) {
+        let fs = fatfs::FileSystem::new(Cursor::new(volume), fatfs::FsOptions::new()).unwrap();
+        let root_dir = fs.root_dir();
+        let (parent, _) = path.rsplit_once('/').unwrap_or(("", path));
+        if !parent.is_empty() {
+            create_dir_all(&root_dir, parent).unwrap();
+        }
+        let mut file = root_dir.create_file(path).unwrap();
+        file.truncate().unwrap();
+        file.write_all(content).unwrap();
+    }
+
+    fn snapshot_of(volume: &mut [u8]) -> ConfigSnapshot {
+        let fs = fatfs::FileSystem::new(Cursor::new(volume), fatfs::FsOptions::new()).unwrap();
+        ConfigSnapshot::capture(&fs.root_dir()).unwrap()
+    }
+
+    fn layout_with_label(label: &str) -> DiskLayout {
+        DiskLayout {
+            scheme: PartitionScheme::Gpt,
+            partitions: vec![PartitionInfo {
+                number: 2,
+                name: "root".to_string(),
+                guid: None,
+                type_name: "Linux filesystem".to_string(),
+                start_offset: 0,
+                size: 0,
+                filesystem: Some("ext4".to_string()),
+                label: Some(label.to_string()),
+            }],
+            filesystem: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut old = fat_volume();
+        write_file(&mut old, "golem.env", b"YA_NET_TYPE=central\n");
+        write_file(&mut old, "keys/node.key", b"secret");
+        let snapshot = snapshot_of(&mut old);
+        assert_eq!(snapshot.files.len(), 2);
+
+        // The new image ships its own defaults, one of which the device never had
+        let mut new = fat_volume();
+        write_file(&mut new, "golem.env", b"YA_NET_TYPE=hybrid\nEXTRA=1\n");
+        write_file(&mut new, "README.txt", b"defaults");
+        {
+            let fs =
+                fatfs::FileSystem::new(Cursor::new(&mut new[..]), fatfs::FsOptions::new()).unwrap();
+            snapshot.restore(&fs.root_dir()).unwrap();
+        }
+
+        let restored = snapshot_of(&mut new);
+        assert_eq!(
+            restored.file("golem.env"),
+            Some(&b"YA_NET_TYPE=central\n"[..])
+        );
+        assert_eq!(restored.file("readme.txt"), Some(&b"defaults"[..]));
+        assert!(
+            restored
+                .files
+                .iter()
+                .any(|(path, content)| path == "keys/node.key" && content == b"secret")
+        );
+    }
+
+    #[test]
+    fn test_detect_image_version() {
+        let snapshot = ConfigSnapshot {
+            files: vec![("VERSION".to_string(), b"golem-gpu-live v0.1.5\n".to_vec())],
+        };
+        assert_eq!(
+            detect_image_version(&snapshot, &layout_with_label("rootfs")),
+            Some("v0.1.5".to_string())
+        );
+
+        // Without a version file the root filesystem label is used
+        assert_eq!(
+            detect_image_version(
+                &ConfigSnapshot::default(),
+                &layout_with_label("golem-1.2.0-rc1")
+            ),
+            Some("1.2.0-rc1".to_string())
+        );
+        assert_eq!(
+            detect_image_version(&ConfigSnapshot::default(), &layout_with_label("rootfs")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_same_version() {
+        assert!(same_version("v0.1.5", "0.1.5"));
+        assert!(same_version(" V1.0-RC1", "v1.0-rc1"));
+        assert!(!same_version("v0.1.5", "v0.1.6"));
+    }
+}
@@ -0,0 +1,375 @@
+// What is keeping a disk busy
+//
+// Locking a disk for writing fails while its filesystems are in use. On Linux the mounted
+// filesystems of the disk, its partitions and the devices stacked on them (LUKS, LVM) are
+// read from /proc/self/mounts; on Windows the Restart Manager reports the programs holding
+// files open on the disk's volumes. The confirmation dialog lists both, so the user knows
+// what to close before the lock is attempted.
+
+#[cfg(any(target_os = "linux", windows))]
+use tracing::debug;
+
+/// Most files registered with the Restart Manager per disk
+#[cfg_attr(not(windows), allow(dead_code))]
+const MAX_WATCHED_FILES: usize = 4096;
+
+/// Mounts and programs using a disk
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceUsage {
+    /// Where filesystems of the disk are mounted, e.g. `/media/user/boot (/dev/sdb1)`
+    pub mount_points: Vec<String>,
+    /// Programs holding files open on the disk, e.g. `explorer.exe (PID 4242)`
+    pub processes: Vec<String>,
+}
+
+impl DeviceUsage {
+    pub fn is_empty(&self) -> bool {
+        self.mount_points.is_empty() && self.processes.is_empty()
+    }
+}
+
+/// Look up what is using the disk at `path`
+///
+/// This never fails: whatever can't be determined is left out. The call performs
+/// blocking I/O and should be run off the UI thread.
+pub fn query_device_usage(path: &str) -> DeviceUsage {
+    platform::query(path)
+}
+
+/// Mount points in /proc/self/mounts whose source is one of `nodes`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_mounts(content: &str, nodes: &[std::path::PathBuf]) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = unescape_mount_field(fields.next()?);
+            let target = unescape_mount_field(fields.next()?);
+            // /dev/mapper/* and /dev/disk/by-* are links to the kernel's device node
+            let node = std::fs::canonicalize(&source).unwrap_or_else(|_| source.clone().into());
+            nodes
+                .contains(&node)
+                .then(|| format!("{} ({})", target, source))
+        })
+        .collect()
+}
+
+/// Undo the octal escapes of spaces and other special characters in a mount table field
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let code = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(code) => {
+                unescaped.push(code);
+                i += 4;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Files below `root`, breadth first, until `files` holds `limit` entries
+#[cfg_attr(not(windows), allow(dead_code))]
+fn collect_files(root: &std::path::Path, limit: usize, files: &mut Vec<std::path::PathBuf>) {
+    let mut dirs = std::collections::VecDeque::from([root.to_path_buf()]);
+    while let Some(dir) = dirs.pop_front() {
+        // Folders such as "System Volume Information" can't be listed
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if files.len() >= limit {
+                return;
+            }
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => dirs.push_back(entry.path()),
+                Ok(file_type) if file_type.is_file() => files.push(entry.path()),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Device nodes of the disk, its partitions and the devices stacked on them
+///
+/// Every device comes before the partitions and devices stacked on it, so releasing them
+/// in reverse order starts at the top of the stack.
+#[cfg(target_os = "linux")]
+pub(crate) fn device_family(path: &str) -> Vec<std::path::PathBuf> {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    let Some(name) = fs::canonicalize(path)
+        .ok()
+        .and_then(|canonical| Some(canonical.file_name()?.to_str()?.to_string()))
+    else {
+        return vec![PathBuf::from(path)];
+    };
+
+    let mut names: Vec<String> = Vec::new();
+    let mut pending = vec![name];
+    while let Some(name) = pending.pop() {
+        if names.contains(&name) {
+            continue;
+        }
+        let sysfs = Path::new("/sys/class/block").join(&name);
+        // Partitions are listed as subdirectories of the disk
+        if let Ok(entries) = fs::read_dir(&sysfs) {
+            pending.extend(
+                entries
+                    .flatten()
+                    .filter(|entry| entry.path().join("partition").exists())
+                    .filter_map(|entry| entry.file_name().into_string().ok()),
+            );
+        }
+        // dm-crypt and LVM devices using the disk or a partition
+        if let Ok(holders) = fs::read_dir(sysfs.join("holders")) {
+            pending.extend(
+                holders
+                    .flatten()
+                    .filter_map(|entry| entry.file_name().into_string().ok()),
+            );
+        }
+        names.push(name);
+    }
+    names
+        .into_iter()
+        .map(|name| Path::new("/dev").join(name))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{DeviceUsage, debug, device_family, parse_mounts};
+
+    pub fn query(path: &str) -> DeviceUsage {
+        let nodes = device_family(path);
+        let mount_points = std::fs::read_to_string("/proc/self/mounts")
+            .map(|content| parse_mounts(&content, &nodes))
+            .unwrap_or_else(|e| {
+                debug!("Usage: cannot read the mount table: {}", e);
+                Vec::new()
+            });
+        debug!("Mount points of {}: {:?}", path, mount_points);
+        DeviceUsage {
+            mount_points,
+            processes: Vec::new(),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{DeviceUsage, MAX_WATCHED_FILES, collect_files, debug};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use windows_sys::Win32::Foundation::{ERROR_MORE_DATA, ERROR_SUCCESS};
+    use windows_sys::Win32::System::RestartManager::{
+        CCH_RM_SESSION_KEY, RM_PROCESS_INFO, RmEndSession, RmGetList, RmRegisterResources,
+        RmStartSession,
+    };
+
+    pub fn query(path: &str) -> DeviceUsage {
+        let volumes = volumes_of(path);
+        let mut files = Vec::new();
+        for volume in &volumes {
+            collect_files(Path::new(volume), MAX_WATCHED_FILES, &mut files);
+        }
+        let processes = processes_using(&files);
+        debug!(
+            "Volumes of {}: {:?}, programs using them: {:?}",
+            path, volumes, processes
+        );
+        DeviceUsage {
+            mount_points: volumes,
+            processes,
+        }
+    }
+
+    /// Drive letters of the volumes on the physical drive, e.g. `E:\`
+    fn volumes_of(path: &str) -> Vec<String> {
+        rs_drivelist::drive_list()
+            .map_err(|e| debug!("Usage: cannot list drives: {}", e))
+            .ok()
+            .and_then(|drives| {
+                drives
+                    .into_iter()
+                    .find(|drive| drive.device.eq_ignore_ascii_case(path))
+            })
+            .map(|drive| {
+                drive
+                    .mountpoints
+                    .into_iter()
+                    .map(|mountpoint| mountpoint.path)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Programs the Restart Manager reports as holding any of `files` open
+    fn processes_using(files: &[PathBuf]) -> Vec<String> {
+        if files.is_empty() {
+            return Vec::new();
+        }
+
+        let mut session = 0u32;
+        let mut session_key = [0u16; CCH_RM_SESSION_KEY as usize + 1];
+        let result = unsafe { RmStartSession(&mut session, 0, session_key.as_mut_ptr()) };
+        if result != ERROR_SUCCESS {
+            debug!("Usage: RmStartSession failed with {}", result);
+            return Vec::new();
+        }
+
+        let wide_names: Vec<Vec<u16>> = files
+            .iter()
+            .map(|file| {
+                file.as_os_str()
+                    .encode_wide()
+                    .chain(std::iter::once(0))
+                    .collect()
+            })
+            .collect();
+        let name_pointers: Vec<*const u16> = wide_names.iter().map(|name| name.as_ptr()).collect();
+
+        let processes = list_processes(session, &name_pointers);
+        unsafe { RmEndSession(session) };
+        processes
+    }
+
+    fn list_processes(session: u32, names: &[*const u16]) -> Vec<String> {
+        let result = unsafe {
+            RmRegisterResources(
+                session,
+                names.len() as u32,
+                names.as_ptr(),
+                0,
+                std::ptr::null(),
+                0,
+                std::ptr::null(),
+            )
+        };
+        if result != ERROR_SUCCESS {
+            debug!("Usage: RmRegisterResources failed with {}", result);
+            return Vec::new();
+        }
+
+        let mut infos: Vec<RM_PROCESS_INFO> = Vec::new();
+        loop {
+            let mut needed = 0u32;
+            let mut count = infos.len() as u32;
+            let mut reboot_reasons = 0u32;
+            let result = unsafe {
+                RmGetList(
+                    session,
+                    &mut needed,
+                    &mut count,
+                    infos.as_mut_ptr(),
+                    &mut reboot_reasons,
+                )
+            };
+            match result {
+                // Programs may have opened files in between, so ask again
+                ERROR_MORE_DATA => {
+                    infos.resize_with(needed as usize, || unsafe { std::mem::zeroed() })
+                }
+                ERROR_SUCCESS => {
+                    infos.truncate(count as usize);
+                    break;
+                }
+                _ => {
+                    debug!("Usage: RmGetList failed with {}", result);
+                    return Vec::new();
+                }
+            }
+        }
+
+        infos
+            .iter()
+            .map(|info| {
+                let name = &info.strAppName;
+                let length = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+                format!(
+                    "{} (PID {})",
+                    String::from_utf16_lossy(&name[..length]),
+                    info.Process.dwProcessId
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::DeviceUsage;
+
+    pub fn query(_path: &str) -> DeviceUsage {
+        DeviceUsage::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse_mounts() {
+        let content = "sysfs /sys sysfs rw,nosuid 0 0\n\
+                       /dev/golem-test-sdx1 /media/golem/GOLEM\\040CONF vfat rw 0 0\n\
+                       /dev/golem-test-sdy1 /media/golem/other ext4 rw 0 0\n\
+                       /dev/golem-test-sdx2 /mnt/root ext4 rw 0 0\n";
+        let nodes = vec![
+            PathBuf::from("/dev/golem-test-sdx"),
+            PathBuf::from("/dev/golem-test-sdx1"),
+            PathBuf::from("/dev/golem-test-sdx2"),
+        ];
+        assert_eq!(
+            parse_mounts(content, &nodes),
+            vec![
+                "/media/golem/GOLEM CONF (/dev/golem-test-sdx1)".to_string(),
+                "/mnt/root (/dev/golem-test-sdx2)".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unescape_mount_field() {
+        assert_eq!(
+            unescape_mount_field("/media/a\\040b\\011c"),
+            "/media/a b\tc"
+        );
+        assert_eq!(unescape_mount_field("/trailing\\04"), "/trailing\\04");
+        assert_eq!(unescape_mount_field("/not\\x41octal"), "/not\\x41octal");
+    }
+
+    #[test]
+    fn test_collect_files_stops_at_limit() {
+        let root = std::env::temp_dir().join(format!("golem-usage-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+        for name in ["a", "b", "nested/c"] {
+            std::fs::write(root.join(name), b"").unwrap();
+        }
+
+        let mut files = Vec::new();
+        collect_files(&root, 10, &mut files);
+        assert_eq!(files.len(), 3);
+
+        let mut limited = Vec::new();
+        collect_files(&root, 2, &mut limited);
+        assert_eq!(limited.len(), 2);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,12 +1,15 @@
 // Windows-specific disk operations
 
-use crate::disk::common::{DiskDevice, PartitionFileProxy};
+use crate::disk::access::DiskAccess;
+use crate::disk::common::{DiskDevice, FlashPhase, PartitionFileProxy};
+use crate::disk::geometry;
 use anyhow::{Result, anyhow};
 // GptConfig is used in handle_gpt_error implementations
 use std::fs::File;
 use std::io::{self, Read, Seek, Write};
 use std::os::windows::io::{AsRawHandle, FromRawHandle};
 use std::os::windows::process::CommandExt;
+use std::sync::Arc;
 use tracing::{debug, error, info, warn};
 use windows_sys::Win32::Foundation::*;
 use windows_sys::Win32::Storage::FileSystem::*;
@@ -14,233 +17,324 @@ use windows_sys::Win32::System::IO::DeviceIoControl;
 use windows_sys::Win32::System::Ioctl::*;
 use windows_sys::Win32::System::Threading::*;
 
-// Sector size constants for Windows
-// Standard sector size for most disks
-const DEFAULT_SECTOR_SIZE: u32 = 512;
-// For advanced format drives and most modern physical disks
-const PHYSICAL_SECTOR_SIZE: u32 = 4096;
+/// Why Windows usually takes a removable disk offline
+pub const SIGNATURE_COLLISION_HINT: &str = "Windows takes a disk offline when its disk \
+     signature or GPT disk GUID matches another connected disk, which happens when two cards \
+     were flashed with the same image. Flashing gives the disk new identifiers.";
 
 /// Windows-specific disk access functionality
 #[derive(Debug, Clone)]
 pub struct WindowsDiskAccess {
     // Original path used to open the disk
     pub path: String,
-    // Detected sector size of the disk
+    // Alignment of direct I/O on the disk, from its sector sizes
     pub sector_size: u32,
+    // Volumes of the disk, locked and dismounted until the last clone is dropped
+    pub volume_locks: Arc<Vec<VolumeLock>>,
+}
+
+/// A locked and dismounted volume, unlocked when its handle is closed
+#[derive(Debug)]
+pub struct VolumeLock {
+    name: String,
+    handle: HANDLE,
+}
+
+impl Drop for VolumeLock {
+    fn drop(&mut self) {
+        debug!("Unlocking volume {}", self.name);
+        unsafe { CloseHandle(self.handle) };
+    }
 }
 
 impl WindowsDiskAccess {
     /// Clear disk partitions using diskpart with progress reporting
     ///
+    /// Diskpart runs on the blocking thread pool; each attempt and each volume dismount
+    /// is sent as `FlashPhase::Clearing` on `progress_tx`, so the UI can show what is
+    /// happening during this multi-second phase.
+    ///
+    /// A failed diskpart run is not fatal: as with the previous inline cleaning in
+    /// `lock_path`, we continue and let the write itself surface any access errors.
+    ///
     /// # Arguments
     /// * `path` - The path to the disk device
     /// * `cancel_token` - Token to cancel the operation
-    ///
-    /// # Returns
-    /// * A sipper that reports progress updates as the clearing proceeds
-    pub fn clear_disk_partitions(
-        path: &str,
+    /// * `progress_tx` - Receives a `FlashPhase::Clearing` update for every step
+    pub async fn clear_disk_partitions(
+        path: String,
         cancel_token: crate::models::CancelToken,
-    ) -> impl iced::task::Sipper<
-        Result<crate::disk::common::WriteProgress>,
-        crate::disk::common::WriteProgress,
-    > + Send
-    + 'static {
-        use crate::disk::common::WriteProgress;
-        use iced::task;
-
-        let path_owned = path.to_string();
-
-        task::sipper(async move |mut sipper| -> Result<WriteProgress> {
-            sipper
-                .send(WriteProgress::ClearingPartitions { progress: 0.0 })
-                .await;
-
-            // Use blocking task for diskpart operations
-            let r = tokio::task::spawn_blocking(move || -> Result<WriteProgress> {
-                // Check if operation was cancelled before starting
-                if cancel_token.is_cancelled() {
-                    info!("Partition clearing cancelled by user before starting");
-                    return Err(anyhow::anyhow!("Operation cancelled by user"));
+        progress_tx: tokio::sync::mpsc::UnboundedSender<FlashPhase>,
+    ) -> Result<FlashPhase> {
+        // Use blocking task for diskpart operations
+        let handle = tokio::task::spawn_blocking(move || -> Result<FlashPhase> {
+            let report = |progress: f32, message: String| {
+                // The receiver only goes away if the UI stopped listening
+                let _ = progress_tx.send(FlashPhase::Clearing { progress, message });
+            };
+
+            // Check if operation was cancelled before starting
+            if cancel_token.is_cancelled() {
+                info!("Partition clearing cancelled by user before starting");
+                return Err(anyhow::anyhow!("Operation cancelled by user"));
+            }
+
+            info!("Starting disk partition clearing for path: {}", path);
+
+            // Extract disk number from path using robust regex-based approach
+            let disk_num = match Self::extract_disk_number_from_path_robust(&path) {
+                Ok(num) => num,
+                Err(e) => {
+                    error!("Failed to extract disk number from path '{}': {}", path, e);
+                    return Err(anyhow::anyhow!("Invalid disk path: {}", e));
                 }
+            };
 
-                info!("Starting disk partition clearing for path: {}", path_owned);
+            info!("Clearing partitions on PhysicalDrive{}", disk_num);
+            report(0.0, format!("Preparing to clear PhysicalDrive{}", disk_num));
 
-                // Extract disk number from path using robust regex-based approach
-                let disk_num = match Self::extract_disk_number_from_path_robust(&path_owned) {
-                    Ok(num) => num,
+            // Diskpart needs Administrator rights, which only the helper has
+            if crate::utils::privileged_helper::is_running() {
+                report(
+                    0.1,
+                    "Clearing partitions through the privileged helper".to_string(),
+                );
+                let device = format!(r"\\.\PhysicalDrive{}", disk_num);
+                match crate::utils::privileged_helper::clean_disk(&device) {
+                    Ok(()) => report(1.0, "Device prepared for writing".to_string()),
                     Err(e) => {
-                        error!(
-                            "Failed to extract disk number from path '{}': {}",
-                            path_owned, e
+                        warn!("Privileged helper could not clean {}: {}", device, e);
+                        report(
+                            1.0,
+                            "Could not clean the disk, continuing anyway".to_string(),
                         );
-                        return Err(anyhow::anyhow!("Invalid disk path: {}", e));
                     }
-                };
+                }
+                return Ok(FlashPhase::Done);
+            }
 
-                info!("Clearing partitions on PhysicalDrive{}", disk_num);
+            // diskpart can't clean an offline disk
+            match Self::bring_disk_online(disk_num) {
+                Ok(true) => report(
+                    0.05,
+                    format!(
+                        "PhysicalDrive{} was offline, most likely because of a disk \
+                         signature collision, and has been brought online",
+                        disk_num
+                    ),
+                ),
+                Ok(false) => {}
+                Err(e) => return Err(e),
+            }
 
-                // Create enhanced diskpart commands - include online disk to handle offline disks with signature collisions
-                let script_content = format!(
-                    "select disk {}\ndetail disk\nonline disk\ndetail disk\nclean\ndetail disk\nrescan\nexit\n",
-                    disk_num
-                );
+            // Create diskpart commands
+            let script_content = format!(
+                "select disk {}\ndetail disk\nclean\ndetail disk\nrescan\nexit\n",
+                disk_num
+            );
 
-                info!("Diskpart commands: {}", script_content.replace('\n', "; "));
+            info!("Diskpart commands: {}", script_content.replace('\n', "; "));
 
-                // Execute diskpart with enhanced retry logic using stdin
-                let mut success = false;
-                let mut last_error = String::new();
+            // Execute diskpart with enhanced retry logic using stdin
+            let mut success = false;
+            let mut last_error = String::new();
 
-                for attempt in 1..=3 {
-                    // Check for cancellation before each attempt
-                    if cancel_token.is_cancelled() {
-                        info!(
-                            "Partition clearing cancelled by user during attempt {}",
-                            attempt
-                        );
-                        return Err(anyhow::anyhow!("Operation cancelled by user"));
-                    }
+            // Diskpart attempts take the first 60% of the progress bar, dismounting the rest
+            const ATTEMPTS: u32 = 3;
+            const DISKPART_SHARE: f32 = 0.6;
 
+            for attempt in 1..=ATTEMPTS {
+                // Check for cancellation before each attempt
+                if cancel_token.is_cancelled() {
                     info!(
-                        "Diskpart attempt {}/3 for PhysicalDrive{}",
-                        attempt, disk_num
+                        "Partition clearing cancelled by user during attempt {}",
+                        attempt
                     );
+                    return Err(anyhow::anyhow!("Operation cancelled by user"));
+                }
 
-                    // Update progress based on attempt
-                    let _progress = (attempt as f32 - 1.0) / 3.0;
-                    // Note: We can't send progress updates from inside spawn_blocking
-                    // The UI will show progress based on the subscription
+                info!(
+                    "Diskpart attempt {}/{} for PhysicalDrive{}",
+                    attempt, ATTEMPTS, disk_num
+                );
+                report(
+                    (attempt - 1) as f32 / ATTEMPTS as f32 * DISKPART_SHARE,
+                    format!("Running diskpart clean (attempt {}/{})", attempt, ATTEMPTS),
+                );
 
-                    let mut child = match std::process::Command::new("diskpart")
-                        .stdin(std::process::Stdio::piped())
-                        .stdout(std::process::Stdio::piped())
-                        .stderr(std::process::Stdio::piped())
-                        .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                        .spawn()
-                    {
-                        Ok(child) => child,
-                        Err(e) => {
-                            last_error = format!("Failed to spawn diskpart: {}", e);
-                            error!("Diskpart spawn failed on attempt {}: {}", attempt, e);
+                let mut child = match std::process::Command::new("diskpart")
+                    .stdin(std::process::Stdio::piped())
+                    .stdout(std::process::Stdio::piped())
+                    .stderr(std::process::Stdio::piped())
+                    .creation_flags(0x08000000) // CREATE_NO_WINDOW
+                    .spawn()
+                {
+                    Ok(child) => child,
+                    Err(e) => {
+                        last_error = format!("Failed to spawn diskpart: {}", e);
+                        error!("Diskpart spawn failed on attempt {}: {}", attempt, e);
 
-                            if attempt < 3 {
-                                warn!("Retrying partition clearing in 500ms...");
-                                std::thread::sleep(std::time::Duration::from_millis(500));
-                            }
-                            continue;
+                        if attempt < ATTEMPTS {
+                            warn!("Retrying partition clearing in 500ms...");
+                            report(
+                                attempt as f32 / ATTEMPTS as f32 * DISKPART_SHARE,
+                                format!(
+                                    "Could not start diskpart, retrying ({}/{})",
+                                    attempt, ATTEMPTS
+                                ),
+                            );
+                            std::thread::sleep(std::time::Duration::from_millis(500));
                         }
-                    };
+                        continue;
+                    }
+                };
 
-                    // Write commands to stdin
-                    if let Some(stdin) = child.stdin.take() {
-                        use std::io::Write;
-                        let mut stdin = stdin;
-                        if let Err(e) = stdin.write_all(script_content.as_bytes()) {
-                            warn!("Failed to write to diskpart stdin: {}", e);
-                        }
-                        // stdin is automatically closed when dropped
+                // Write commands to stdin
+                if let Some(stdin) = child.stdin.take() {
+                    use std::io::Write;
+                    let mut stdin = stdin;
+                    if let Err(e) = stdin.write_all(script_content.as_bytes()) {
+                        warn!("Failed to write to diskpart stdin: {}", e);
                     }
+                    // stdin is automatically closed when dropped
+                }
 
-                    // Wait for completion and get output
-                    let output = match child.wait_with_output() {
-                        Ok(output) => output,
-                        Err(e) => {
-                            last_error = format!("Failed to get diskpart output: {}", e);
-                            error!("Diskpart output failed on attempt {}: {}", attempt, e);
+                // Wait for completion and get output
+                let output = match child.wait_with_output() {
+                    Ok(output) => output,
+                    Err(e) => {
+                        last_error = format!("Failed to get diskpart output: {}", e);
+                        error!("Diskpart output failed on attempt {}: {}", attempt, e);
 
-                            if attempt < 3 {
-                                warn!("Retrying partition clearing in 500ms...");
-                                std::thread::sleep(std::time::Duration::from_millis(500));
-                            }
-                            continue;
+                        if attempt < ATTEMPTS {
+                            warn!("Retrying partition clearing in 500ms...");
+                            std::thread::sleep(std::time::Duration::from_millis(500));
                         }
-                    };
-
-                    let output_msg = String::from_utf8_lossy(&output.stdout);
-                    let error_msg = String::from_utf8_lossy(&output.stderr);
-
-                    info!("Diskpart attempt {} output: {}", attempt, output_msg);
-                    if !error_msg.is_empty() {
-                        warn!("Diskpart attempt {} stderr: {}", attempt, error_msg);
+                        continue;
                     }
+                };
 
-                    // Enhanced error detection for offline disks and signature collisions
-                    let has_diskpart_error = output_msg.contains("DiskPart has encountered an error");
-                    let has_offline_error = output_msg.contains("Offline") || 
-                                          output_msg.contains("offline") ||
-                                          output_msg.contains("nie jest dozwolona dla dysku w trybie offline"); // Polish
-                    let has_signature_collision = output_msg.contains("Signature Collision") ||
-                                               output_msg.contains("signature collision");
-                    let has_vds_error = output_msg.contains("Virtual Disk Service error");
+                let output_msg = String::from_utf8_lossy(&output.stdout);
+                let error_msg = String::from_utf8_lossy(&output.stderr);
 
-                    if output.status.success() && !has_diskpart_error && !has_offline_error && !has_vds_error {
-                        info!(
-                            "Successfully cleared partitions on disk {} (attempt {})",
-                            disk_num, attempt
+                info!("Diskpart attempt {} output: {}", attempt, output_msg);
+                if !error_msg.is_empty() {
+                    warn!("Diskpart attempt {} stderr: {}", attempt, error_msg);
+                }
+
+                // Enhanced error detection for offline disks and signature collisions
+                let has_diskpart_error = output_msg.contains("DiskPart has encountered an error");
+                let has_offline_error = output_msg.contains("Offline")
+                    || output_msg.contains("offline")
+                    || output_msg.contains("nie jest dozwolona dla dysku w trybie offline"); // Polish
+                let has_signature_collision = output_msg.contains("Signature Collision")
+                    || output_msg.contains("signature collision");
+                let has_vds_error = output_msg.contains("Virtual Disk Service error");
+
+                if output.status.success()
+                    && !has_diskpart_error
+                    && !has_offline_error
+                    && !has_vds_error
+                {
+                    info!(
+                        "Successfully cleared partitions on disk {} (attempt {})",
+                        disk_num, attempt
+                    );
+                    success = true;
+                    break;
+                } else {
+                    // Provide specific error information
+                    let reason = if has_offline_error || has_signature_collision {
+                        warn!(
+                            "Diskpart failed on attempt {} - disk is offline with signature collision",
+                            attempt
                         );
-                        success = true;
-                        break;
+                        warn!(
+                            "This usually happens when Windows detects duplicate disk signatures"
+                        );
+                        "disk is offline"
+                    } else if has_vds_error {
+                        warn!(
+                            "Diskpart failed on attempt {} - Virtual Disk Service error",
+                            attempt
+                        );
+                        "Virtual Disk Service error"
                     } else {
-                        // Provide specific error information
-                        if has_offline_error || has_signature_collision {
-                            warn!("Diskpart failed on attempt {} - disk is offline with signature collision", attempt);
-                            warn!("This usually happens when Windows detects duplicate disk signatures");
-                        } else if has_vds_error {
-                            warn!("Diskpart failed on attempt {} - Virtual Disk Service error", attempt);
-                        }
+                        "diskpart reported an error"
+                    };
 
-                        last_error = format!(
-                            "Diskpart failed - stdout: {}, stderr: {}",
-                            output_msg, error_msg
+                    last_error = format!(
+                        "Diskpart failed - stdout: {}, stderr: {}",
+                        output_msg, error_msg
+                    );
+                    error!("Diskpart attempt {} failed: {}", attempt, last_error);
+
+                    if attempt < ATTEMPTS {
+                        warn!("Retrying partition clearing in 500ms...");
+                        report(
+                            attempt as f32 / ATTEMPTS as f32 * DISKPART_SHARE,
+                            format!(
+                                "Attempt {}/{} failed ({}), retrying",
+                                attempt, ATTEMPTS, reason
+                            ),
                         );
-                        error!("Diskpart attempt {} failed: {}", attempt, last_error);
-
-                        if attempt < 3 {
-                            warn!("Retrying partition clearing in 500ms...");
-                            std::thread::sleep(std::time::Duration::from_millis(500));
-                        }
+                        std::thread::sleep(std::time::Duration::from_millis(500));
                     }
                 }
+            }
 
-                if !success {
-                    error!(
-                        "All diskpart attempts failed for disk {}: {}",
-                        disk_num, last_error
-                    );
-                    return Err(anyhow::anyhow!(
-                        "Failed to clear disk partitions: {}",
-                        last_error
-                    ));
-                } else {
-                    // Add sleep after successful diskpart operations to allow Windows to process changes
-                    info!("Waiting 2 seconds for Windows to process diskpart changes...");
-                    std::thread::sleep(std::time::Duration::from_millis(2000));
-                }
+            if !success {
+                warn!(
+                    "All diskpart attempts failed for disk {}: {}",
+                    disk_num, last_error
+                );
+                warn!("This may lead to access denied errors when writing to the disk.");
+                report(
+                    DISKPART_SHARE,
+                    "Diskpart could not clean the disk, continuing anyway".to_string(),
+                );
+            } else {
+                // Add sleep after successful diskpart operations to allow Windows to process changes
+                info!("Waiting 2 seconds for Windows to process diskpart changes...");
+                report(
+                    DISKPART_SHARE,
+                    "Partitions cleared, waiting for Windows to apply changes".to_string(),
+                );
+                std::thread::sleep(std::time::Duration::from_millis(2000));
+            }
 
-                // Dismount any remaining volumes
-                info!("Dismounting volumes on PhysicalDrive{}", disk_num);
-                let volumes = Self::get_volumes_for_physical_drive(disk_num as usize);
+            // Dismount any remaining volumes
+            info!("Dismounting volumes on PhysicalDrive{}", disk_num);
+            let volumes = Self::get_volumes_for_physical_drive(disk_num as usize);
+            let volume_count = volumes.len();
 
-                for volume in volumes {
-                    if cancel_token.is_cancelled() {
-                        return Err(anyhow::anyhow!("Operation cancelled by user"));
-                    }
+            for (index, volume) in volumes.into_iter().enumerate() {
+                if cancel_token.is_cancelled() {
+                    return Err(anyhow::anyhow!("Operation cancelled by user"));
+                }
 
-                    info!("Dismounting volume {}", volume);
-                    if let Err(e) = Self::dismount_volume_path(&volume) {
-                        warn!("Failed to dismount volume {}: {}", volume, e);
-                        // Continue with other volumes - dismount failures are non-fatal
-                    }
+                info!("Dismounting volume {}", volume);
+                report(
+                    DISKPART_SHARE + (1.0 - DISKPART_SHARE) * index as f32 / volume_count as f32,
+                    format!(
+                        "Dismounting volume {} ({}/{})",
+                        Self::volume_display_name(&volume),
+                        index + 1,
+                        volume_count
+                    ),
+                );
+                if let Err(e) = Self::dismount_volume_path(&volume) {
+                    warn!("Failed to dismount volume {}: {}", volume, e);
+                    // Continue with other volumes - dismount failures are non-fatal
                 }
+            }
 
-                info!("Successfully cleared all partitions on disk {}", disk_num);
-                Ok(WriteProgress::Finish)
-            })
-            .await?;
+            info!("Successfully cleared all partitions on disk {}", disk_num);
+            report(1.0, "Device prepared for writing".to_string());
+            Ok(FlashPhase::Done)
+        });
 
-            r
-        })
+        handle.await?
     }
 
     /// Extract disk number from path using robust regex pattern matching
@@ -317,10 +411,32 @@ impl WindowsDiskAccess {
 
         info!("Formatted Windows disk path: {}", disk_path);
 
+        // Without elevation the privileged helper opens the device for us
+        if crate::utils::privileged_helper::is_running() {
+            return Self::lock_path_via_helper(path, &disk_path, edit_mode).await;
+        }
+
         // Note: We should check if user is admin, but for now we'll just warn that
         // administrator privileges are required for Windows disk operations
         warn!("Windows direct disk access typically requires Administrator privileges");
 
+        let drive_number = if path.contains("PhysicalDrive") || path.parse::<usize>().is_ok() {
+            Some(Self::extract_disk_number_from_path(path)?)
+        } else {
+            None
+        };
+
+        // An offline disk can be neither cleaned nor written
+        if let Some(disk_num) = drive_number {
+            let was_offline = Self::bring_disk_online(disk_num)?;
+            if was_offline {
+                info!(
+                    "PhysicalDrive{} was offline and has been brought online",
+                    disk_num
+                );
+            }
+        }
+
         // Try to dismount all associated volumes
         if path.ends_with(":") {
             // If it's a drive letter (like "C:"), attempt to dismount it
@@ -352,9 +468,9 @@ impl WindowsDiskAccess {
                 // This is important as diskpart can't clean a locked disk
                 info!("Attempting to clean disk {} with diskpart", disk_num);
 
-                // Create diskpart commands - include online disk to handle offline disks with signature collisions
+                // Create diskpart commands
                 let script_content = format!(
-                    "select disk {}\ndetail disk\nonline disk\ndetail disk\nclean\ndetail disk\nrescan\nexit\n",
+                    "select disk {}\ndetail disk\nclean\ndetail disk\nrescan\nexit\n",
                     disk_num
                 );
 
@@ -484,36 +600,6 @@ impl WindowsDiskAccess {
                     info!("Waiting 2 seconds for Windows to process diskpart changes...");
                     std::thread::sleep(std::time::Duration::from_millis(2000));
                 }
-
-                // Now dismount all volumes on this drive before locking it
-                info!(
-                    "Attempting to dismount all volumes on physical drive {}",
-                    disk_num
-                );
-
-                // Try to get all mounted volumes for this physical drive
-                let volumes = Self::get_volumes_for_physical_drive(disk_num as usize);
-                if volumes.is_empty() {
-                    warn!("No volumes found for physical drive {}", disk_num);
-                }
-
-                // Try to dismount each volume
-                for volume in volumes {
-                    info!(
-                        "Attempting to dismount volume {} on physical drive {}",
-                        volume, disk_num
-                    );
-                    match Self::dismount_volume_path(&volume) {
-                        Ok(_) => info!(
-                            "Successfully dismounted volume {} on drive {}",
-                            volume, disk_num
-                        ),
-                        Err(e) => warn!(
-                            "Failed to dismount volume {} on drive {}: {}",
-                            volume, disk_num, e
-                        ),
-                    }
-                }
             } else {
                 warn!("Could not parse drive number from path: {}", path);
                 warn!("This may fail if any volumes on this drive are in use by Windows");
@@ -531,20 +617,18 @@ impl WindowsDiskAccess {
             }
         }
 
-        // Try to get the sector size for this disk for better performance
-        // Note: We don't actually use this immediately, but the struct needs it
-        // and detecting it early can help with error diagnosis
-        let _sector_size = match Self::get_disk_sector_size(path) {
-            Ok(size) => {
-                info!("Detected disk sector size: {} bytes", size);
-                size
-            }
-            Err(e) => {
-                warn!("Failed to detect sector size, using default: {}", e);
-                DEFAULT_SECTOR_SIZE // Default sector size
-            }
+        // Every volume on the drive stays locked while the disk is open, so Windows neither
+        // writes to a volume nor mounts it again in the middle of the write
+        let volume_locks = match drive_number {
+            Some(disk_num) => Self::lock_volumes_on_drive(disk_num as usize)?,
+            None => Vec::new(),
         };
 
+        // Query the sector sizes before the volumes are dismounted, which helps with
+        // error diagnosis; later queries of the same disk get the same answer
+        let geometry = geometry::query(path);
+        info!("Sector sizes of {}: {:?}", disk_path, geometry);
+
         // Convert the path to a wide string for Windows API
         let path_wide: Vec<u16> = disk_path.encode_utf16().chain(std::iter::once(0)).collect();
 
@@ -706,24 +790,14 @@ impl WindowsDiskAccess {
 
         info!("Successfully opened Windows disk device: {}", disk_path);
 
-        // Attempt to get disk sector size for confirmation
-        let detected_sector_size =
-            Self::get_disk_sector_size(&disk_path).unwrap_or(DEFAULT_SECTOR_SIZE);
-
-        info!("Detected disk sector size: {} bytes", detected_sector_size);
-
-        // For ANY disk operations, always use the larger sector size for maximum compatibility
-        // This is critical for Windows direct I/O which requires proper alignment
-        info!(
-            "Using physical sector size ({} bytes) for all disk I/O operations",
-            PHYSICAL_SECTOR_SIZE
-        );
-        let sector_size = PHYSICAL_SECTOR_SIZE;
+        // Direct I/O has to be aligned to the physical sectors, and never less than 4K
+        let sector_size = geometry.io_alignment() as u32;
 
         // Create platform data
         let platform = WindowsDiskAccess {
             path: path.to_string(),
             sector_size,
+            volume_locks: Arc::new(volume_locks),
         };
 
         // Convert Windows HANDLE to Rust File
@@ -742,49 +816,28 @@ impl WindowsDiskAccess {
         Ok((file, platform))
     }
 
-    /// Clone a file handle (uses Windows DuplicateHandle)
-    pub fn clone_file_handle(&self, file: &File) -> Result<File> {
-        // On Windows, creating multiple handles to physical disks can cause access issues
-        // We need to be careful with permissions when duplicating the handle
-        let current_process = unsafe { GetCurrentProcess() };
-        let mut target_handle: HANDLE = 0;
+    /// Open a disk through the privileged helper when the application isn't elevated
+    ///
+    /// The helper cleans the disk unless `edit_mode` is set and hands back a handle opened
+    /// with the same direct I/O flags used by [`WindowsDiskAccess::lock_path`].
+    async fn lock_path_via_helper(
+        path: &str,
+        disk_path: &str,
+        edit_mode: bool,
+    ) -> Result<(File, Self)> {
+        let device = disk_path.to_string();
+        let file = tokio::task::spawn_blocking(move || {
+            crate::utils::privileged_helper::open_device(&device, !edit_mode)
+        })
+        .await??;
+        info!("Opened {} through the privileged helper", disk_path);
 
-        // When duplicating the handle, we need to ensure we preserve the correct access rights
-        let success = unsafe {
-            DuplicateHandle(
-                current_process,
-                file.as_raw_handle() as HANDLE,
-                current_process,
-                &mut target_handle,
-                0,
-                0, // FALSE for inherit handle
-                DUPLICATE_SAME_ACCESS,
-            )
+        let platform = WindowsDiskAccess {
+            path: path.to_string(),
+            sector_size: geometry::query(path).io_alignment() as u32,
+            volume_locks: Arc::default(),
         };
-
-        if success == 0 {
-            let error_code = unsafe { GetLastError() };
-            let error_msg = Self::get_windows_error_message(error_code);
-
-            // Log detailed error information
-            error!(
-                "Failed to duplicate file handle, error code: {} ({})",
-                error_code, error_msg
-            );
-
-            return Err(anyhow!(
-                "Failed to duplicate file handle, error code: {} ({})",
-                error_code,
-                error_msg
-            ));
-        }
-
-        debug!("Successfully duplicated Windows file handle");
-
-        // Convert the Windows HANDLE back to a Rust File
-        let new_file = unsafe { File::from_raw_handle(target_handle as *mut _) };
-
-        Ok(new_file)
+        Ok((file, platform))
     }
 
     /// Create a partition file proxy with Windows-specific considerations
@@ -833,17 +886,12 @@ impl WindowsDiskAccess {
         debug!("Path string from handle: '{}'", path_str);
         debug!("Is physical drive: {}", is_physical_drive);
 
-        // For ANY disk operations, always use the larger sector size for maximum compatibility
-        // This is critical for Windows direct I/O which requires proper alignment
+        // For ANY disk operations, always use the minimum direct I/O alignment
         // Modern drives typically use 4K sectors internally even if they report 512 bytes
-        info!(
-            "Using physical sector size ({} bytes) for all disk I/O operations",
-            PHYSICAL_SECTOR_SIZE
-        );
-        let sector_size = PHYSICAL_SECTOR_SIZE;
+        let sector_size = geometry::MIN_IO_ALIGNMENT as u32;
 
         // Use our aligned I/O implementation for Windows
-        use crate::disk::windows_aligned_io::aligned_disk_io;
+        use crate::disk::aligned_disk_io;
         let aligned_file = match aligned_disk_io(file, sector_size) {
             Ok(aligned) => aligned,
             Err(e) => {
@@ -866,258 +914,127 @@ impl WindowsDiskAccess {
         })
     }
 
-    /// Verify disk is ready for writing and lock for exclusive access
-    /// Note: Disk cleaning is now performed earlier during lock_path to avoid conflicts
-    pub fn pre_write_checks(disk_file: &File, original_path: Option<&str>) -> Result<()> {
-        // Log the original path if provided, but we won't use it for cleaning anymore
-        // since cleaning is now done before the disk is locked
-        if let Some(path) = original_path {
-            info!("Path from original handle for verification: {}", path);
-        }
-
-        // Next, try to lock the volume for exclusive access
-        let handle = disk_file.as_raw_handle() as HANDLE;
-        let mut bytes_returned: u32 = 0;
-
-        info!("Attempting to lock disk volume for exclusive access");
+    /// Extract disk number from a Windows drive path
+    ///
+    /// This function handles various formats of Windows disk paths:
+    /// - \\.\PhysicalDrive0
+    /// - \\.\PHYSICALDRIVE0
+    /// - PhysicalDrive0
+    /// - PHYSICALDRIVE0
+    /// - 0 (just a number)
+    /// - Any string that contains "PhysicalDrive" followed by a number
+    pub fn extract_disk_number_from_path(path_str: &str) -> Result<u32> {
+        // Use the new robust implementation
+        Self::extract_disk_number_from_path_robust(path_str)
+    }
 
-        // First, enable extended DASD I/O (Direct Access Storage Device) like RPI Imager does
-        // This is essential for some operations on Windows
-        info!("Enabling extended DASD I/O access");
-        unsafe {
-            DeviceIoControl(
-                handle,
-                FSCTL_ALLOW_EXTENDED_DASD_IO,
-                std::ptr::null_mut(),
-                0,
-                std::ptr::null_mut(),
-                0,
-                &mut bytes_returned,
+    /// Bring a disk online if Windows took it offline, returning whether it was offline
+    ///
+    /// The offline attribute is cleared with IOCTL_DISK_SET_DISK_ATTRIBUTES, the way
+    /// `online disk` does in diskpart, and the partition table is read again.
+    pub fn bring_disk_online(disk_num: u32) -> Result<bool> {
+        let disk_path = format!(r"\\.\PhysicalDrive{}", disk_num);
+        let path_wide: Vec<u16> = disk_path.encode_utf16().chain(std::iter::once(0)).collect();
+        let handle = unsafe {
+            CreateFileW(
+                path_wide.as_ptr(),
+                GENERIC_READ | GENERIC_WRITE,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
                 std::ptr::null_mut(),
+                OPEN_EXISTING,
+                0,
+                0,
             )
         };
-
-        // Try to lock the volume with multiple attempts
-        let mut locked = false;
-        for attempt in 0..30 {
-            // DeviceIoControl with FSCTL_LOCK_VOLUME
-            info!("Locking volume, attempt {}/30", attempt + 1);
-
-            let lock_result = unsafe {
-                DeviceIoControl(
-                    handle,
-                    FSCTL_LOCK_VOLUME, // Control code for locking a volume
-                    std::ptr::null_mut(),
-                    0,
-                    std::ptr::null_mut(),
-                    0,
-                    &mut bytes_returned,
-                    std::ptr::null_mut(),
-                )
-            };
-
-            if lock_result != 0 {
-                locked = true;
-                info!("Successfully locked disk volume on attempt {}", attempt + 1);
-                break;
-            }
-
+        if handle == INVALID_HANDLE_VALUE {
             let error_code = unsafe { GetLastError() };
-
-            // Progressive delay strategy
-            let delay_ms = match attempt {
-                0..=5 => 100,  // First 5 attempts: 100ms
-                6..=15 => 200, // Next 10 attempts: 200ms
-                _ => 500,      // Final attempts: 500ms
-            };
-
-            info!(
-                "Lock attempt {} failed, error code: {}, waiting {}ms before retrying...",
-                attempt + 1,
+            return Err(anyhow!(
+                "Failed to open {} to check whether it is online: {} ({})",
+                disk_path,
                 error_code,
-                delay_ms
-            );
-
-            std::thread::sleep(std::time::Duration::from_millis(delay_ms));
+                Self::get_windows_error_message(error_code)
+            ));
         }
 
-        // Check if locking was successful after all attempts
-        if !locked {
-            // Get the last error for diagnostic purposes
-            let error_code = unsafe { GetLastError() };
-            let error_msg = Self::get_windows_error_message(error_code);
-
-            error!(
-                "Failed to lock disk after 20 attempts, error code: {} ({})",
-                error_code, error_msg
-            );
-
-            match error_code {
-                32 => {
-                    // ERROR_SHARING_VIOLATION
-                    warn!(
-                        "Disk access is still blocked by another process after multiple retry attempts"
-                    );
-                    warn!("Some volumes might not have been properly dismounted");
-
-                    return Err(anyhow::anyhow!(
-                        "The disk is in use by another process and cannot be locked"
-                    )
-                    .context("Close any programs that might be using this disk")
-                    .context(
-                        "If it's a system disk, you cannot write to it while Windows is running",
-                    ));
-                }
-                5 => {
-                    // ERROR_ACCESS_DENIED
-                    return Err(
-                        anyhow::anyhow!("Access denied when trying to lock the disk")
-                            .context("Make sure you're running with Administrator privileges")
-                            .context("The disk may be write-protected or reserved by the system"),
-                    );
-                }
-                _ => {
-                    warn!(
-                        "Could not lock volume (error code: {}), continuing with caution",
-                        error_code
-                    );
-                    warn!("Write operations may fail or be inconsistent");
-                }
-            }
-        }
+        let result = Self::clear_offline_attribute(handle, &disk_path);
+        unsafe { CloseHandle(handle) };
+        result
+    }
 
-        // Dismount all volumes directly using the physical drive handle
-        let dismount_result = unsafe {
+    fn clear_offline_attribute(handle: HANDLE, disk_path: &str) -> Result<bool> {
+        let mut bytes_returned: u32 = 0;
+        let mut attributes: GET_DISK_ATTRIBUTES = unsafe { std::mem::zeroed() };
+        let result = unsafe {
             DeviceIoControl(
                 handle,
-                FSCTL_DISMOUNT_VOLUME,
-                std::ptr::null_mut(),
-                0,
-                std::ptr::null_mut(),
+                IOCTL_DISK_GET_DISK_ATTRIBUTES,
+                std::ptr::null(),
                 0,
+                &mut attributes as *mut _ as *mut _,
+                std::mem::size_of::<GET_DISK_ATTRIBUTES>() as u32,
                 &mut bytes_returned,
                 std::ptr::null_mut(),
             )
         };
-
-        if dismount_result == 0 {
+        if result == 0 {
+            // Older drivers don't report attributes; diskpart would fail the same way
             let error_code = unsafe { GetLastError() };
-            let error_msg = Self::get_windows_error_message(error_code);
-            info!(
-                "Note: Could not dismount directly from physical device: {} ({})",
-                error_code, error_msg
+            warn!(
+                "Could not read the attributes of {}: {} ({})",
+                disk_path,
+                error_code,
+                Self::get_windows_error_message(error_code)
             );
-            info!("This is often normal when writing to physical drives rather than volumes");
-        } else {
-            info!("Successfully dismounted volumes from physical drive handle");
+            return Ok(false);
         }
-
-        info!("Windows: Disk is ready for writing");
-        Ok(())
-    }
-
-    /// Handle disk write errors with Windows-specific context
-    pub fn handle_write_error(e: &io::Error) -> Option<anyhow::Error> {
-        let os_error = e.raw_os_error();
-
-        // Log error details
-        if let Some(code) = os_error {
-            let error_msg = Self::get_windows_error_message(code as u32);
-            error!("Windows error code: {} ({})", code, error_msg);
-
-            match code {
-                5 => {
-                    error!("Access denied error (code 5) when writing to disk");
-                    error!(
-                        "This error often occurs when disk cleaning via diskpart failed or was skipped."
-                    );
-                    error!("Check the logs for diskpart output or errors above.");
-
-                    return Some(
-                        anyhow::anyhow!(
-                            "Access denied when writing to disk. Error code: 5 ({})",
-                            error_msg
-                        )
-                        .context("Make sure you're running with Administrator privileges")
-                        .context("The disk may be locked by another process or write-protected")
-                        .context("Ensure diskpart is available and successfully cleaned the disk"),
-                    );
-                }
-                1117 => {
-                    error!("I/O device error (code 1117) when writing to disk");
-                    return Some(anyhow::anyhow!("The request could not be performed because of an I/O device error. Error code: 1117 ({})", error_msg)
-                        .context("The disk may be write-protected, damaged, or have hardware issues")
-                        .context("Try using a different USB port or disk"));
-                }
-                112 => {
-                    error!("Not enough space error (code 112) when writing to disk");
-                    return Some(
-                        anyhow::anyhow!(
-                            "There is not enough space on the disk. Error code: 112 ({})",
-                            error_msg
-                        )
-                        .context("Check that the disk has enough free space for the image")
-                        .context("Try using a larger capacity disk"),
-                    );
-                }
-                1224 => {
-                    error!("Removed media error (code 1224) when writing to disk");
-                    return Some(anyhow::anyhow!("The disk was removed during the write operation. Error code: 1224 ({})", error_msg)
-                        .context("The disk was disconnected during the write operation")
-                        .context("Ensure the disk remains connected throughout the process"));
-                }
-                87 => {
-                    error!("Invalid parameter error (code 87) when writing to disk");
-                    return Some(
-                        anyhow::anyhow!(
-                            "The parameter is incorrect. Error code: 87 ({})",
-                            error_msg
-                        )
-                        .context("This may be due to mismatched buffer alignment requirements")
-                        .context("Try restarting the application and using a different USB port"),
-                    );
-                }
-                _ => {
-                    error!("Unrecognized Windows error code: {} ({})", code, error_msg);
-                    return Some(anyhow::anyhow!("Failed to write image to disk. Windows error code: {} ({})", code, error_msg)
-                        .context("An unexpected Windows error occurred during disk write")
-                        .context("Try restarting your computer and running the application as Administrator"));
-                }
-            }
+        if attributes.Attributes & DISK_ATTRIBUTE_OFFLINE == 0 {
+            return Ok(false);
         }
 
-        None
-    }
-
-    /// Handle disk flush errors with Windows-specific context
-    pub fn handle_flush_error(e: &io::Error) -> Option<anyhow::Error> {
-        let os_error = e.raw_os_error();
-        if let Some(code) = os_error {
-            let error_msg = Self::get_windows_error_message(code as u32);
-            error!("Windows flush error code: {} ({})", code, error_msg);
-
-            return Some(
-                anyhow::anyhow!("Failed to flush disk buffer: {} ({})", e, error_msg)
-                    .context("Unable to ensure all data was written to disk")
-                    .context("The disk may have been disconnected or experienced an error"),
-            );
+        warn!("{} is offline, bringing it online", disk_path);
+        let mut request: SET_DISK_ATTRIBUTES = unsafe { std::mem::zeroed() };
+        request.Version = std::mem::size_of::<SET_DISK_ATTRIBUTES>() as u32;
+        request.Persist = 1;
+        request.Attributes = 0;
+        request.AttributesMask = DISK_ATTRIBUTE_OFFLINE;
+        let result = unsafe {
+            DeviceIoControl(
+                handle,
+                IOCTL_DISK_SET_DISK_ATTRIBUTES,
+                &request as *const _ as *const _,
+                std::mem::size_of::<SET_DISK_ATTRIBUTES>() as u32,
+                std::ptr::null_mut(),
+                0,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+        if result == 0 {
+            let error_code = unsafe { GetLastError() };
+            return Err(anyhow!(
+                "{} is offline and could not be brought online: {} ({}). {}",
+                disk_path,
+                error_code,
+                Self::get_windows_error_message(error_code),
+                SIGNATURE_COLLISION_HINT
+            ));
         }
+        warn!("{}", SIGNATURE_COLLISION_HINT);
 
-        None
-    }
-
-    /// Extract disk number from a Windows drive path
-    ///
-    /// This function handles various formats of Windows disk paths:
-    /// - \\.\PhysicalDrive0
-    /// - \\.\PHYSICALDRIVE0
-    /// - PhysicalDrive0
-    /// - PHYSICALDRIVE0
-    /// - 0 (just a number)
-    /// - Any string that contains "PhysicalDrive" followed by a number
-    pub fn extract_disk_number_from_path(path_str: &str) -> Result<u32> {
-        // Use the new robust implementation
-        Self::extract_disk_number_from_path_robust(path_str)
+        // Let Windows pick up the partitions of the disk that is now online
+        unsafe {
+            DeviceIoControl(
+                handle,
+                IOCTL_DISK_UPDATE_PROPERTIES,
+                std::ptr::null(),
+                0,
+                std::ptr::null_mut(),
+                0,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+        Ok(true)
     }
 
     /// Clean the disk by removing all partitions using diskpart (used by older disk-image-writer)
@@ -1390,185 +1307,79 @@ impl WindowsDiskAccess {
         }
     }
 
-    /// Handle GPT reading errors with Windows-specific solutions
-    pub fn handle_gpt_error(
-        disk: &crate::disk::Disk,
-        error: anyhow::Error,
-    ) -> Result<Option<gpt::GptDisk<'_>>> {
-        // On Windows, attempt to reopen the device with different flags
-        warn!(
-            "Failed to parse GPT partition table: {}. This may be due to insufficient permissions or alignment issues.",
-            error
-        );
-
-        // Try a different approach with aligned I/O for Windows
-        info!("Attempting Windows-specific GPT reading with aligned I/O");
-
-        // Get a new file handle
-        let file = match disk.file.try_clone() {
-            Ok(f) => f,
-            Err(e) => {
-                error!("Failed to clone disk file handle: {}", e);
-                return Ok(None); // Let the original error propagate
-            }
-        };
-
-        // Use our AlignedDiskIO implementation for better Windows compatibility
-        use crate::disk::windows_aligned_io::aligned_disk_io;
-
-        // Always use 4KB sector size for maximum compatibility with modern disks
-        let sector_size = 512;
-        // Try to create an aligned disk I/O wrapper
-        let aligned_file = match aligned_disk_io(file, sector_size) {
-            Ok(aligned) => aligned,
-            Err(e) => {
-                error!("Failed to create aligned I/O wrapper: {}", e);
-                return Ok(None); // Let the original error propagate
-            }
-        };
+    /// Dismount a Windows volume by path (not a file handle)
+    ///
+    /// `drive_path` is a drive letter such as `E:` or a volume name such as
+    /// `\\?\Volume{...}`, as listed by `get_volumes_for_physical_drive`.
+    pub fn dismount_volume_path(drive_path: &str) -> Result<()> {
+        info!("Dismounting Windows volume: {}", drive_path);
 
-        // Create a new GptConfig with relaxed validation
-        let cfg = gpt::GptConfig::new()
-            .writable(false)
-            .initialized(true) // Skip checking LBA0 for MBR
-            .logical_block_size(gpt::disk::LogicalBlockSize::Lb512);
+        let (handle, drive_path) = Self::open_volume(drive_path)?;
+        let result = Self::dismount_volume_with_handle(handle, &drive_path);
+        unsafe { CloseHandle(handle) };
+        result
+    }
 
-        // Try to open the GPT disk with our aligned wrapper
-        match cfg.open_from_device(Box::new(aligned_file)) {
-            Ok(disk) => {
-                info!("Successfully read GPT partition table with aligned I/O");
-                Ok(Some(disk))
-            }
+    /// Lock and dismount a volume, keeping it locked until the returned lock is dropped
+    fn lock_volume_path(drive_path: &str) -> Result<VolumeLock> {
+        let (handle, name) = Self::open_volume(drive_path)?;
+        match Self::dismount_volume_with_handle(handle, &name) {
+            Ok(()) => Ok(VolumeLock { name, handle }),
             Err(e) => {
-                error!("Even with aligned I/O, failed to parse GPT: {}", e);
-                Ok(None) // Let the original error propagate
+                unsafe { CloseHandle(handle) };
+                Err(e)
             }
         }
     }
 
-    /// Get the sector size for a Windows disk
-    pub fn get_disk_sector_size(disk_path: &str) -> Result<u32> {
-        debug!("Getting sector size for Windows disk: {}", disk_path);
-
-        // Format the path for Windows API
-        let formatted_path = if disk_path.contains("PhysicalDrive") {
-            format!(r"\\.\{}", disk_path.trim_start_matches(r"\\.\"))
-        } else if disk_path.ends_with(":") {
-            format!(r"\\.\{}", disk_path.trim_end_matches('\\'))
-        } else if disk_path.parse::<usize>().is_ok() {
-            format!(r"\\.\PhysicalDrive{}", disk_path)
+    /// Open a volume for locking, exclusively if possible
+    ///
+    /// Returns the handle and the device path it was opened with.
+    fn open_volume(drive_path: &str) -> Result<(HANDLE, String)> {
+        // Prepare the path for Windows API; volume names already carry a \\?\ prefix
+        let drive_path = drive_path.trim_end_matches('\\');
+        let drive_path = if drive_path.starts_with(r"\\") {
+            drive_path.to_string()
         } else {
-            format!(r"\\.\{}", disk_path)
+            format!(r"\\.\{}", drive_path)
         };
 
-        debug!("Formatted disk path: {}", formatted_path);
-
-        // Convert to wide string for Windows API
-        let path_wide: Vec<u16> = formatted_path
+        // Convert the path to a wide string for Windows API
+        let path_wide: Vec<u16> = drive_path
             .encode_utf16()
             .chain(std::iter::once(0))
             .collect();
 
-        // Open the disk with read access
+        // Open the volume with required privileges - following RPI Imager's approach
+        // Important: exclusive access (no file sharing) and using direct I/O flags
         let handle = unsafe {
             CreateFileW(
                 path_wide.as_ptr(),
-                GENERIC_READ,
-                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                GENERIC_READ | GENERIC_WRITE,
+                0, // No sharing - exclusive access
                 std::ptr::null_mut(),
                 OPEN_EXISTING,
+                FILE_FLAG_NO_BUFFERING | FILE_FLAG_WRITE_THROUGH, // Direct I/O
                 0,
-                0,
-            )
-        };
-
-        if handle == INVALID_HANDLE_VALUE {
-            let error_code = unsafe { GetLastError() };
-            let error_msg = Self::get_windows_error_message(error_code);
-            debug!(
-                "Failed to open disk for sector size query, error code: {} ({})",
-                error_code, error_msg
-            );
-            return Ok(DEFAULT_SECTOR_SIZE); // Return default on error
-        }
-
-        // Structure for disk geometry information
-        #[repr(C)]
-        struct DiskGeometry {
-            cylinders: i64,
-            media_type: u32,
-            tracks_per_cylinder: u32,
-            sectors_per_track: u32,
-            bytes_per_sector: u32,
-        }
-
-        let mut disk_geometry = DiskGeometry {
-            cylinders: 0,
-            media_type: 0,
-            tracks_per_cylinder: 0,
-            sectors_per_track: 0,
-            bytes_per_sector: 0,
-        };
-
-        let mut bytes_returned: u32 = 0;
-
-        // Get disk geometry information
-        let result = unsafe {
-            DeviceIoControl(
-                handle,
-                IOCTL_DISK_GET_DRIVE_GEOMETRY, // 0x70000
-                std::ptr::null_mut(),
-                0,
-                &mut disk_geometry as *mut _ as *mut _,
-                std::mem::size_of::<DiskGeometry>() as u32,
-                &mut bytes_returned,
-                std::ptr::null_mut(),
             )
         };
-
-        // Close the handle
-        unsafe { CloseHandle(handle) };
-
-        if result == 0 || bytes_returned == 0 {
-            let error_code = unsafe { GetLastError() };
-            let error_msg = Self::get_windows_error_message(error_code);
-            debug!(
-                "DeviceIoControl failed, error code: {} ({}), returning default sector size",
-                error_code, error_msg
-            );
-            return Ok(DEFAULT_SECTOR_SIZE);
-        }
-
-        let sector_size = disk_geometry.bytes_per_sector;
-        if sector_size == 0 {
-            debug!("Got zero sector size, using default");
-            Ok(DEFAULT_SECTOR_SIZE)
-        } else {
-            debug!("Detected sector size: {} bytes", sector_size);
-            Ok(sector_size)
+        if handle != INVALID_HANDLE_VALUE {
+            return Ok((handle, drive_path));
         }
-    }
-
-    /// Dismount a Windows volume by path (not a file handle)
-    pub fn dismount_volume_path(drive_path: &str) -> Result<()> {
-        info!("Dismounting Windows volume: {}", drive_path);
-
-        // Prepare the path for Windows API
-        let drive_path = format!(r"\\.\{}", drive_path.trim_end_matches('\\'));
 
-        // Convert the path to a wide string for Windows API
-        let path_wide: Vec<u16> = drive_path
-            .encode_utf16()
-            .chain(std::iter::once(0))
-            .collect();
+        let error_code = unsafe { GetLastError() };
+        let error_msg = Self::get_windows_error_message(error_code);
+        info!(
+            "Could not open volume {} exclusively, trying with shared access: {} ({})",
+            drive_path, error_code, error_msg
+        );
 
-        // Open the volume with required privileges - following RPI Imager's approach
-        // Important: exclusive access (no file sharing) and using direct I/O flags
+        // Try again with shared access as fallback
         let handle = unsafe {
             CreateFileW(
                 path_wide.as_ptr(),
                 GENERIC_READ | GENERIC_WRITE,
-                0, // No sharing - exclusive access
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
                 std::ptr::null_mut(),
                 OPEN_EXISTING,
                 FILE_FLAG_NO_BUFFERING | FILE_FLAG_WRITE_THROUGH, // Direct I/O
@@ -1579,44 +1390,19 @@ impl WindowsDiskAccess {
         if handle == INVALID_HANDLE_VALUE {
             let error_code = unsafe { GetLastError() };
             let error_msg = Self::get_windows_error_message(error_code);
-            info!(
-                "Could not open volume {} exclusively, trying with shared access: {} ({})",
-                drive_path, error_code, error_msg
-            );
-
-            // Try again with shared access as fallback
-            let handle = unsafe {
-                CreateFileW(
-                    path_wide.as_ptr(),
-                    GENERIC_READ | GENERIC_WRITE,
-                    FILE_SHARE_READ | FILE_SHARE_WRITE,
-                    std::ptr::null_mut(),
-                    OPEN_EXISTING,
-                    FILE_FLAG_NO_BUFFERING | FILE_FLAG_WRITE_THROUGH, // Direct I/O
-                    0,
-                )
-            };
-
-            if handle == INVALID_HANDLE_VALUE {
-                let error_code = unsafe { GetLastError() };
-                let error_msg = Self::get_windows_error_message(error_code);
-                return Err(anyhow!(
-                    "Failed to open volume {}, error code: {} ({})",
-                    drive_path,
-                    error_code,
-                    error_msg
-                ));
-            }
-
-            // Continue with the obtained handle
-            Self::dismount_volume_with_handle(handle, &drive_path)
-        } else {
-            // Continue with the exclusively opened handle
-            Self::dismount_volume_with_handle(handle, &drive_path)
+            return Err(anyhow!(
+                "Failed to open volume {}, error code: {} ({})",
+                drive_path,
+                error_code,
+                error_msg
+            ));
         }
+        Ok((handle, drive_path))
     }
 
     /// Helper function to dismount a volume using an already opened handle - with RPI Imager's retry logic
+    ///
+    /// The volume stays locked until the caller closes the handle.
     fn dismount_volume_with_handle(handle: HANDLE, drive_path: &str) -> Result<()> {
         let mut bytes_returned: u32 = 0;
 
@@ -1690,7 +1476,6 @@ impl WindowsDiskAccess {
 
             // For volumes (unlike physical drives), we'll return an error if we can't lock
             // This helps diagnose issues with specific volumes
-            unsafe { CloseHandle(handle) };
             return Err(anyhow!(
                 "Failed to lock volume {} after multiple attempts: {} ({})",
                 drive_path,
@@ -1785,14 +1570,6 @@ impl WindowsDiskAccess {
         // RPI Imager sometimes does this to ensure operations have time to complete
         std::thread::sleep(std::time::Duration::from_millis(100));
 
-        // Close the handle but keep the volume locked
-        // Windows will automatically unlock the volume when all handles are closed
-        info!(
-            "Closing handle but volume {} remains dismounted",
-            drive_path
-        );
-        unsafe { CloseHandle(handle) };
-
         debug!("Successfully processed volume: {}", drive_path);
         Ok(())
     }
@@ -1844,6 +1621,9 @@ impl WindowsDiskAccess {
                             || std::path::Path::new(&format!("{}\\Windows", mp.path)).exists()
                     });
 
+                // Look up the serial number so the device can be recognized after re-enumeration
+                let identity = crate::disk::identity::query_device_identity(&path);
+
                 // Add to our list of devices
                 devices.push(DiskDevice {
                     path,
@@ -1854,6 +1634,7 @@ impl WindowsDiskAccess {
                     vendor: "Unknown".to_string(), // Not directly available in current version
                     model: drive.description.clone(),
                     system: is_system,
+                    identity,
                 });
             }
         }
@@ -1876,6 +1657,7 @@ impl WindowsDiskAccess {
                             vendor: "Unknown".to_string(),
                             model: "Unknown".to_string(),
                             system: i == 0, // Assume disk 0 is system disk
+                            identity: crate::disk::identity::query_device_identity(&path),
                         });
                     }
                     false => {
@@ -1899,6 +1681,7 @@ impl WindowsDiskAccess {
                         vendor: "Unknown".to_string(),
                         model: "Unknown".to_string(),
                         system: letter == b'C', // Assume C: is system drive
+                        identity: crate::disk::DeviceIdentity::default(),
                     });
                 }
             }
@@ -1963,115 +1746,552 @@ impl WindowsDiskAccess {
     }
 }
 
-// NOTE: The specific implementations of Read, Write, and Seek for PartitionFileProxy<File>
-// have been moved to common.rs with conditional compilation for Windows
+impl DiskAccess for WindowsDiskAccess {
+    /// Clone a file handle (uses Windows DuplicateHandle)
+    fn clone_file_handle(&self, file: &File) -> Result<File> {
+        // On Windows, creating multiple handles to physical disks can cause access issues
+        // We need to be careful with permissions when duplicating the handle
+        let current_process = unsafe { GetCurrentProcess() };
+        let mut target_handle: HANDLE = 0;
 
-impl WindowsDiskAccess {
-    /// Get a list of volume drive letters mounted on a physical drive
-    fn get_volumes_for_physical_drive(drive_number: usize) -> Vec<String> {
-        debug!("Getting volumes for physical drive {}", drive_number);
-        let mut volumes = Vec::new();
+        // When duplicating the handle, we need to ensure we preserve the correct access rights
+        let success = unsafe {
+            DuplicateHandle(
+                current_process,
+                file.as_raw_handle() as HANDLE,
+                current_process,
+                &mut target_handle,
+                0,
+                0, // FALSE for inherit handle
+                DUPLICATE_SAME_ACCESS,
+            )
+        };
 
-        // Try to use rs-drivelist to map physical drives to volumes
-        match rs_drivelist::drive_list() {
-            Ok(drives) => {
-                for drive in drives {
-                    // Get device path and check if it matches our physical drive
-                    let device_path = drive
-                        .devicePath
-                        .as_ref()
-                        .map_or_else(|| drive.device.clone(), |p| p.clone());
-
-                    if device_path.contains(&format!("PHYSICALDRIVE{}", drive_number)) {
-                        // This drive matches our physical drive, get all mount points
-                        for mountpoint in &drive.mountpoints {
-                            let mount_path = &mountpoint.path;
-                            if mount_path.ends_with(":") || mount_path.ends_with(":\\") {
-                                // Extract just the drive letter (e.g., "C:")
-                                let drive_letter = mount_path.trim_end_matches('\\').to_string();
-                                debug!(
-                                    "Found volume {} on physical drive {}",
-                                    drive_letter, drive_number
-                                );
-                                volumes.push(drive_letter);
-                            }
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Failed to list drives using rs-drivelist: {}", e);
+        if success == 0 {
+            let error_code = unsafe { GetLastError() };
+            let error_msg = Self::get_windows_error_message(error_code);
 
-                // Fallback approach: try common drive letters and check their physical drive
-                for letter in b'C'..=b'Z' {
-                    let drive_letter = format!("{}:", char::from(letter));
+            // Log detailed error information
+            error!(
+                "Failed to duplicate file handle, error code: {} ({})",
+                error_code, error_msg
+            );
 
-                    // Try to check if this drive letter is on the target physical drive
-                    // This is a simplified approach and may not work in all cases
-                    if let Ok(true) = Self::is_volume_on_physical_drive(&drive_letter, drive_number)
-                    {
-                        debug!(
-                            "Found volume {} on physical drive {}",
-                            drive_letter, drive_number
-                        );
-                        volumes.push(drive_letter);
-                    }
-                }
-            }
+            return Err(anyhow!(
+                "Failed to duplicate file handle, error code: {} ({})",
+                error_code,
+                error_msg
+            ));
         }
 
-        volumes
+        debug!("Successfully duplicated Windows file handle");
+
+        // Convert the Windows HANDLE back to a Rust File
+        let new_file = unsafe { File::from_raw_handle(target_handle as *mut _) };
+
+        Ok(new_file)
     }
 
-    /// Check if a volume (e.g., "C:") is on a specific physical drive
-    /// This is a simplified version that uses a heuristic instead of exact Windows API calls
-    fn is_volume_on_physical_drive(volume: &str, drive_number: usize) -> Result<bool> {
-        // For cross-compilation purposes, we'll use a simplified approach that assumes
-        // information from rs-drivelist is sufficient
+    /// Verify disk is ready for writing and lock for exclusive access
+    /// Note: Disk cleaning is now performed earlier during lock_path to avoid conflicts
+    fn pre_write_checks(&self, disk_file: &File, original_path: Option<&str>) -> Result<()> {
+        // Log the original path if provided, but we won't use it for cleaning anymore
+        // since cleaning is now done before the disk is locked
+        if let Some(path) = original_path {
+            info!("Path from original handle for verification: {}", path);
+        }
 
-        // This is a simpler fallback approach that doesn't use the native Windows IOCTL
-        // We can use this method as a simplified way to check if we think a volume is on a physical drive
+        // Next, try to lock the volume for exclusive access
+        let handle = disk_file.as_raw_handle() as HANDLE;
+        let mut bytes_returned: u32 = 0;
 
-        // Simply try to open the volume and see if we can read from it
-        let volume_path = format!(r"\\.\{}", volume.trim_end_matches('\\'));
-        let path_wide: Vec<u16> = volume_path
-            .encode_utf16()
-            .chain(std::iter::once(0))
-            .collect();
+        info!("Attempting to lock disk volume for exclusive access");
 
-        // Try to open the volume
-        let handle = unsafe {
-            CreateFileW(
-                path_wide.as_ptr(),
-                GENERIC_READ,
-                FILE_SHARE_READ | FILE_SHARE_WRITE,
+        // First, enable extended DASD I/O (Direct Access Storage Device) like RPI Imager does
+        // This is essential for some operations on Windows
+        info!("Enabling extended DASD I/O access");
+        unsafe {
+            DeviceIoControl(
+                handle,
+                FSCTL_ALLOW_EXTENDED_DASD_IO,
                 std::ptr::null_mut(),
-                OPEN_EXISTING,
                 0,
+                std::ptr::null_mut(),
                 0,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
             )
         };
 
-        if handle == INVALID_HANDLE_VALUE {
-            return Ok(false); // Can't access this volume
-        }
+        // Try to lock the volume with multiple attempts
+        let mut locked = false;
+        for attempt in 0..30 {
+            // DeviceIoControl with FSCTL_LOCK_VOLUME
+            info!("Locking volume, attempt {}/30", attempt + 1);
 
-        // If we can open it, we'll check if the volume is accessible
-        // This is a very simple approach - in a real implementation, we'd use the
-        // IOCTL_STORAGE_GET_DEVICE_NUMBER to definitively check the drive number
+            let lock_result = unsafe {
+                DeviceIoControl(
+                    handle,
+                    FSCTL_LOCK_VOLUME, // Control code for locking a volume
+                    std::ptr::null_mut(),
+                    0,
+                    std::ptr::null_mut(),
+                    0,
+                    &mut bytes_returned,
+                    std::ptr::null_mut(),
+                )
+            };
 
-        // For now, we close the handle and log that we're checking
-        unsafe { CloseHandle(handle) };
+            if lock_result != 0 {
+                locked = true;
+                info!("Successfully locked disk volume on attempt {}", attempt + 1);
+                break;
+            }
 
-        // Log this as an assumption rather than a confirmed fact
-        debug!(
-            "Checking volume {} - treating as potentially on physical drive {}",
-            volume, drive_number
-        );
+            let error_code = unsafe { GetLastError() };
 
-        // For safety, let's treat this volume as potentially on the target drive
-        // Better to dismount too many volumes than too few
-        Ok(true)
+            // Progressive delay strategy
+            let delay_ms = match attempt {
+                0..=5 => 100,  // First 5 attempts: 100ms
+                6..=15 => 200, // Next 10 attempts: 200ms
+                _ => 500,      // Final attempts: 500ms
+            };
+
+            info!(
+                "Lock attempt {} failed, error code: {}, waiting {}ms before retrying...",
+                attempt + 1,
+                error_code,
+                delay_ms
+            );
+
+            std::thread::sleep(std::time::Duration::from_millis(delay_ms));
+        }
+
+        // Check if locking was successful after all attempts
+        if !locked {
+            // Get the last error for diagnostic purposes
+            let error_code = unsafe { GetLastError() };
+            let error_msg = Self::get_windows_error_message(error_code);
+
+            error!(
+                "Failed to lock disk after 20 attempts, error code: {} ({})",
+                error_code, error_msg
+            );
+
+            match error_code {
+                32 => {
+                    // ERROR_SHARING_VIOLATION
+                    warn!(
+                        "Disk access is still blocked by another process after multiple retry attempts"
+                    );
+                    warn!("Some volumes might not have been properly dismounted");
+
+                    return Err(anyhow::anyhow!(
+                        "The disk is in use by another process and cannot be locked"
+                    )
+                    .context("Close any programs that might be using this disk")
+                    .context(
+                        "If it's a system disk, you cannot write to it while Windows is running",
+                    ));
+                }
+                5 => {
+                    // ERROR_ACCESS_DENIED
+                    return Err(
+                        anyhow::anyhow!("Access denied when trying to lock the disk")
+                            .context("Make sure you're running with Administrator privileges")
+                            .context("The disk may be write-protected or reserved by the system"),
+                    );
+                }
+                _ => {
+                    warn!(
+                        "Could not lock volume (error code: {}), continuing with caution",
+                        error_code
+                    );
+                    warn!("Write operations may fail or be inconsistent");
+                }
+            }
+        }
+
+        // Dismount all volumes directly using the physical drive handle
+        let dismount_result = unsafe {
+            DeviceIoControl(
+                handle,
+                FSCTL_DISMOUNT_VOLUME,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                0,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+
+        if dismount_result == 0 {
+            let error_code = unsafe { GetLastError() };
+            let error_msg = Self::get_windows_error_message(error_code);
+            info!(
+                "Note: Could not dismount directly from physical device: {} ({})",
+                error_code, error_msg
+            );
+            info!("This is often normal when writing to physical drives rather than volumes");
+        } else {
+            info!("Successfully dismounted volumes from physical drive handle");
+        }
+
+        info!("Windows: Disk is ready for writing");
+        Ok(())
+    }
+
+    /// Handle disk write errors with Windows-specific context
+    fn handle_write_error(&self, e: &io::Error) -> Option<anyhow::Error> {
+        let os_error = e.raw_os_error();
+
+        // Log error details
+        if let Some(code) = os_error {
+            let error_msg = Self::get_windows_error_message(code as u32);
+            error!("Windows error code: {} ({})", code, error_msg);
+
+            match code {
+                5 => {
+                    error!("Access denied error (code 5) when writing to disk");
+                    error!(
+                        "This error often occurs when disk cleaning via diskpart failed or was skipped."
+                    );
+                    error!("Check the logs for diskpart output or errors above.");
+
+                    return Some(
+                        anyhow::anyhow!(
+                            "Access denied when writing to disk. Error code: 5 ({})",
+                            error_msg
+                        )
+                        .context("Make sure you're running with Administrator privileges")
+                        .context("The disk may be locked by another process or write-protected")
+                        .context("Ensure diskpart is available and successfully cleaned the disk"),
+                    );
+                }
+                1117 => {
+                    error!("I/O device error (code 1117) when writing to disk");
+                    return Some(anyhow::anyhow!("The request could not be performed because of an I/O device error. Error code: 1117 ({})", error_msg)
+                        .context("The disk may be write-protected, damaged, or have hardware issues")
+                        .context("Try using a different USB port or disk"));
+                }
+                112 => {
+                    error!("Not enough space error (code 112) when writing to disk");
+                    return Some(
+                        anyhow::anyhow!(
+                            "There is not enough space on the disk. Error code: 112 ({})",
+                            error_msg
+                        )
+                        .context("Check that the disk has enough free space for the image")
+                        .context("Try using a larger capacity disk"),
+                    );
+                }
+                1224 => {
+                    error!("Removed media error (code 1224) when writing to disk");
+                    return Some(anyhow::anyhow!("The disk was removed during the write operation. Error code: 1224 ({})", error_msg)
+                        .context("The disk was disconnected during the write operation")
+                        .context("Ensure the disk remains connected throughout the process"));
+                }
+                87 => {
+                    error!("Invalid parameter error (code 87) when writing to disk");
+                    return Some(
+                        anyhow::anyhow!(
+                            "The parameter is incorrect. Error code: 87 ({})",
+                            error_msg
+                        )
+                        .context("This may be due to mismatched buffer alignment requirements")
+                        .context("Try restarting the application and using a different USB port"),
+                    );
+                }
+                _ => {
+                    error!("Unrecognized Windows error code: {} ({})", code, error_msg);
+                    return Some(anyhow::anyhow!("Failed to write image to disk. Windows error code: {} ({})", code, error_msg)
+                        .context("An unexpected Windows error occurred during disk write")
+                        .context("Try restarting your computer and running the application as Administrator"));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Reopen the disk without FILE_FLAG_NO_BUFFERING, keeping writes on the device
+    fn reopen_buffered(&self, file: &File) -> Result<File> {
+        let handle = unsafe {
+            ReOpenFile(
+                file.as_raw_handle() as HANDLE,
+                GENERIC_READ | GENERIC_WRITE,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                FILE_FLAG_WRITE_THROUGH | FILE_FLAG_SEQUENTIAL_SCAN,
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            let error_code = unsafe { GetLastError() };
+            return Err(anyhow!(
+                "Failed to reopen {} with buffered I/O, error code: {} ({})",
+                self.path,
+                error_code,
+                Self::get_windows_error_message(error_code)
+            ));
+        }
+
+        info!("Reopened {} with buffered I/O", self.path);
+        Ok(unsafe { File::from_raw_handle(handle as *mut _) })
+    }
+
+    /// Handle disk flush errors with Windows-specific context
+    fn handle_flush_error(&self, e: &io::Error) -> Option<anyhow::Error> {
+        let os_error = e.raw_os_error();
+        if let Some(code) = os_error {
+            let error_msg = Self::get_windows_error_message(code as u32);
+            error!("Windows flush error code: {} ({})", code, error_msg);
+
+            return Some(
+                anyhow::anyhow!("Failed to flush disk buffer: {} ({})", e, error_msg)
+                    .context("Unable to ensure all data was written to disk")
+                    .context("The disk may have been disconnected or experienced an error"),
+            );
+        }
+
+        None
+    }
+
+    /// Handle GPT reading errors with Windows-specific solutions
+    fn handle_gpt_error<'a>(
+        &self,
+        disk: &'a crate::disk::Disk,
+        error: anyhow::Error,
+    ) -> Result<Option<gpt::GptDisk<'a>>> {
+        // On Windows, attempt to reopen the device with different flags
+        warn!(
+            "Failed to parse GPT partition table: {}. This may be due to insufficient permissions or alignment issues.",
+            error
+        );
+
+        // Try a different approach with aligned I/O for Windows
+        info!("Attempting Windows-specific GPT reading with aligned I/O");
+
+        // Get a new file handle
+        let file = match disk.file.try_clone() {
+            Ok(f) => f,
+            Err(e) => {
+                error!("Failed to clone disk file handle: {}", e);
+                return Ok(None); // Let the original error propagate
+            }
+        };
+
+        // Use our AlignedDiskIo implementation for better Windows compatibility
+        use crate::disk::aligned_disk_io;
+
+        // Align to the disk's sectors, and read the GPT in its logical sectors
+        let geometry = disk.geometry();
+        // Try to create an aligned disk I/O wrapper
+        let aligned_file = match aligned_disk_io(file, geometry.io_alignment() as u32) {
+            Ok(aligned) => aligned,
+            Err(e) => {
+                error!("Failed to create aligned I/O wrapper: {}", e);
+                return Ok(None); // Let the original error propagate
+            }
+        };
+
+        // Create a new GptConfig with relaxed validation
+        let cfg = gpt::GptConfig::new()
+            .writable(false)
+            .initialized(true) // Skip checking LBA0 for MBR
+            .logical_block_size(geometry.gpt_block_size()?);
+
+        // Try to open the GPT disk with our aligned wrapper
+        match cfg.open_from_device(Box::new(aligned_file)) {
+            Ok(disk) => {
+                info!("Successfully read GPT partition table with aligned I/O");
+                Ok(Some(disk))
+            }
+            Err(e) => {
+                error!("Even with aligned I/O, failed to parse GPT: {}", e);
+                Ok(None) // Let the original error propagate
+            }
+        }
+    }
+}
+
+// NOTE: The specific implementations of Read, Write, and Seek for PartitionFileProxy<File>
+// have been moved to common.rs with conditional compilation for Windows
+
+impl WindowsDiskAccess {
+    /// Get the names of all volumes on a physical drive, e.g. `\\?\Volume{...}`
+    ///
+    /// Volumes are enumerated with FindFirstVolumeW, so volumes without a drive letter
+    /// are found as well, and each is matched to its drives by its disk extents.
+    fn get_volumes_for_physical_drive(drive_number: usize) -> Vec<String> {
+        debug!("Getting volumes for physical drive {}", drive_number);
+        let mut volumes = Vec::new();
+
+        let mut name = [0u16; MAX_PATH as usize];
+        let search = unsafe { FindFirstVolumeW(name.as_mut_ptr(), name.len() as u32) };
+        if search == INVALID_HANDLE_VALUE {
+            let error_code = unsafe { GetLastError() };
+            error!(
+                "Failed to enumerate volumes: {} ({})",
+                error_code,
+                Self::get_windows_error_message(error_code)
+            );
+            return volumes;
+        }
+
+        loop {
+            let length = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+            // Volume names end with a backslash, which would open the root folder
+            let volume = String::from_utf16_lossy(&name[..length])
+                .trim_end_matches('\\')
+                .to_string();
+            match Self::volume_disk_numbers(&volume) {
+                Ok(disks) if disks.contains(&(drive_number as u32)) => {
+                    debug!(
+                        "Found volume {} ({}) on physical drive {}",
+                        volume,
+                        Self::volume_display_name(&volume),
+                        drive_number
+                    );
+                    volumes.push(volume);
+                }
+                Ok(_) => {}
+                // CD drives without media and similar volumes have no extents
+                Err(e) => debug!("Skipping volume {}: {}", volume, e),
+            }
+
+            if unsafe { FindNextVolumeW(search, name.as_mut_ptr(), name.len() as u32) } == 0 {
+                break;
+            }
+        }
+        unsafe { FindVolumeClose(search) };
+
+        volumes
+    }
+
+    /// Numbers of the physical drives a volume has extents on
+    fn volume_disk_numbers(volume: &str) -> Result<Vec<u32>> {
+        // VOLUME_DISK_EXTENTS with room for the extents of spanned and striped volumes
+        #[repr(C)]
+        struct Extents {
+            count: u32,
+            extents: [DISK_EXTENT; 32],
+        }
+
+        let path_wide: Vec<u16> = volume.encode_utf16().chain(std::iter::once(0)).collect();
+        // No access rights are needed to ask for the extents
+        let handle = unsafe {
+            CreateFileW(
+                path_wide.as_ptr(),
+                0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                std::ptr::null_mut(),
+                OPEN_EXISTING,
+                0,
+                0,
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            let error_code = unsafe { GetLastError() };
+            return Err(anyhow!(
+                "cannot open the volume: {} ({})",
+                error_code,
+                Self::get_windows_error_message(error_code)
+            ));
+        }
+
+        let mut extents: Extents = unsafe { std::mem::zeroed() };
+        let mut bytes_returned: u32 = 0;
+        let result = unsafe {
+            DeviceIoControl(
+                handle,
+                IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS,
+                std::ptr::null(),
+                0,
+                &mut extents as *mut _ as *mut _,
+                std::mem::size_of::<Extents>() as u32,
+                &mut bytes_returned,
+                std::ptr::null_mut(),
+            )
+        };
+        let error_code = unsafe { GetLastError() };
+        unsafe { CloseHandle(handle) };
+        if result == 0 {
+            return Err(anyhow!(
+                "cannot read the disk extents: {} ({})",
+                error_code,
+                Self::get_windows_error_message(error_code)
+            ));
+        }
+
+        let count = (extents.count as usize).min(extents.extents.len());
+        Ok(extents.extents[..count]
+            .iter()
+            .map(|extent| extent.DiskNumber)
+            .collect())
+    }
+
+    /// The drive letters and folders of a volume, or its name if it has none
+    fn volume_display_name(volume: &str) -> String {
+        let mount_paths = Self::volume_mount_paths(volume);
+        if mount_paths.is_empty() {
+            volume.to_string()
+        } else {
+            mount_paths.join(", ")
+        }
+    }
+
+    /// Drive letters and folders a volume is mounted at
+    fn volume_mount_paths(volume: &str) -> Vec<String> {
+        let path_wide: Vec<u16> = format!("{}\\", volume)
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect();
+        let mut paths = [0u16; 1024];
+        let mut length = 0u32;
+        let result = unsafe {
+            GetVolumePathNamesForVolumeNameW(
+                path_wide.as_ptr(),
+                paths.as_mut_ptr(),
+                paths.len() as u32,
+                &mut length,
+            )
+        };
+        if result == 0 {
+            return Vec::new();
+        }
+
+        // A list of null-terminated strings, ending with an empty one
+        paths[..(length as usize).min(paths.len())]
+            .split(|&c| c == 0)
+            .filter(|path| !path.is_empty())
+            .map(String::from_utf16_lossy)
+            .collect()
+    }
+
+    /// Lock and dismount every volume on a physical drive
+    ///
+    /// The locks are held until the returned handles are dropped. A volume that can't be
+    /// locked is still in use, and writing underneath it could corrupt the new image.
+    fn lock_volumes_on_drive(drive_number: usize) -> Result<Vec<VolumeLock>> {
+        let volumes = Self::get_volumes_for_physical_drive(drive_number);
+        info!(
+            "Locking {} volume(s) on physical drive {}",
+            volumes.len(),
+            drive_number
+        );
+
+        let mut locks = Vec::with_capacity(volumes.len());
+        for volume in volumes {
+            let lock = Self::lock_volume_path(&volume).map_err(|e| {
+                e.context(format!(
+                    "Volume {} on physical drive {} is in use. Close any programs using it and try again.",
+                    Self::volume_display_name(&volume),
+                    drive_number
+                ))
+            })?;
+            locks.push(lock);
+        }
+        Ok(locks)
     }
 }
 
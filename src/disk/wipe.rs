@@ -0,0 +1,255 @@
+/// Undoing the wipe that precedes an image write
+///
+/// Before the image goes on, the first and last 4MB of the disk are zeroed (and on Windows
+/// diskpart cleans the disk), which destroys the MBR, both GPTs and the partition table at
+/// once. The regions are copied to a private file first, so that a write cancelled before
+/// any image data reached the disk can put the old partition table back.
+use anyhow::{Context, Result, anyhow};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Bytes zeroed at each end of the disk before the image is written
+pub const WIPE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// How long the completion screen offers to undo a cancelled wipe
+pub const UNDO_WINDOW: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Regions of a disk of `disk_size` bytes that are zeroed before writing, as (offset, length)
+///
+/// The end of the disk holds the backup GPT. Disks of up to twice [`WIPE_SIZE`] only have
+/// their start cleared.
+pub fn wiped_regions(disk_size: u64) -> Vec<(u64, u64)> {
+    let head = (0, disk_size.min(WIPE_SIZE));
+    if disk_size > 2 * WIPE_SIZE {
+        vec![head, (disk_size - WIPE_SIZE, WIPE_SIZE)]
+    } else {
+        vec![head]
+    }
+}
+
+/// File for the snapshot of `device_path`, e.g. `golem-wipe-_dev_sdb-1234-1760000000000.bin`
+///
+/// The file lives in the application's own data directory, which is created readable by the
+/// user only, rather than in the shared temporary directory.
+pub fn snapshot_path(device_path: &str) -> Result<PathBuf> {
+    let dir = crate::utils::paths::wipe_snapshot_dir()?;
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(&dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let device: String = device_path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    Ok(dir.join(format!(
+        "golem-wipe-{}-{}-{}.bin",
+        device,
+        std::process::id(),
+        millis
+    )))
+}
+
+/// The wiped regions of a disk as they were before the wipe
+///
+/// The bytes live in a file, the regions in memory; the file holds the regions'
+/// contents back to back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WipeSnapshot {
+    path: PathBuf,
+    disk_size: u64,
+    regions: Vec<(u64, u64)>,
+}
+
+impl WipeSnapshot {
+    /// Copy the regions [`wiped_regions`] names from `disk` to a new file at `path`
+    ///
+    /// The file must not exist yet, so a file or link planted at `path` is never written
+    /// through; it is created readable by the user only.
+    pub fn capture<D: Read + Seek>(disk: &mut D, disk_size: u64, path: &Path) -> Result<Self> {
+        let regions = wiped_regions(disk_size);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+
+        let mut buffer = Vec::new();
+        for &(offset, length) in &regions {
+            buffer.resize(length as usize, 0);
+            disk.seek(SeekFrom::Start(offset))?;
+            disk.read_exact(&mut buffer)
+                .with_context(|| format!("Failed to read {} bytes at offset {}", length, offset))?;
+            file.write_all(&buffer)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        file.sync_all()?;
+
+        info!(
+            "Saved the regions about to be wiped ({:?}) to {}",
+            regions,
+            path.display()
+        );
+        Ok(WipeSnapshot {
+            path: path.to_path_buf(),
+            disk_size,
+            regions,
+        })
+    }
+
+    /// File holding the saved bytes
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the saved regions back to `disk`
+    ///
+    /// Refuses a disk of another size than the one the snapshot was taken of, which is
+    /// most likely another disk.
+    pub fn restore<D: Write + Seek>(&self, disk: &mut D, disk_size: u64) -> Result<()> {
+        if disk_size != self.disk_size {
+            return Err(anyhow!(
+                "The disk is {} bytes, but the saved partition table is of a {} byte disk",
+                disk_size,
+                self.disk_size
+            ));
+        }
+
+        let mut file = std::fs::File::open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        let mut buffer = Vec::new();
+        for &(offset, length) in &self.regions {
+            buffer.resize(length as usize, 0);
+            file.read_exact(&mut buffer)
+                .with_context(|| format!("{} is incomplete", self.path.display()))?;
+            disk.seek(SeekFrom::Start(offset))?;
+            disk.write_all(&buffer)?;
+        }
+        disk.flush()?;
+
+        info!("Restored the wiped regions from {}", self.path.display());
+        Ok(())
+    }
+
+    /// Delete the file, once the wipe can no longer be undone
+    pub fn discard(&self) {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => info!("Removed {}", self.path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to remove {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn disk(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("golem-wipe-test-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_wiped_regions() {
+        let mb = 1024 * 1024;
+        assert_eq!(
+            wiped_regions(32 * mb),
+            vec![(0, WIPE_SIZE), (28 * mb, WIPE_SIZE)]
+        );
+        assert_eq!(wiped_regions(8 * mb), vec![(0, WIPE_SIZE)]);
+        assert_eq!(wiped_regions(mb), vec![(0, mb)]);
+    }
+
+    #[test]
+    fn test_restore_undoes_wipe() {
+        let original = disk(12 * 1024 * 1024);
+        let path = temp_path("restore");
+        let mut device = Cursor::new(original.clone());
+        let snapshot = WipeSnapshot::capture(&mut device, original.len() as u64, &path).unwrap();
+
+        for (offset, length) in wiped_regions(original.len() as u64) {
+            device.get_mut()[offset as usize..(offset + length) as usize].fill(0);
+        }
+        assert_ne!(device.get_ref(), &original);
+
+        snapshot
+            .restore(&mut device, original.len() as u64)
+            .unwrap();
+        assert_eq!(device.get_ref(), &original);
+
+        snapshot.discard();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_restore_refuses_other_disk() {
+        let original = disk(12 * 1024 * 1024);
+        let path = temp_path("other");
+        let snapshot = WipeSnapshot::capture(
+            &mut Cursor::new(&original[..]),
+            original.len() as u64,
+            &path,
+        )
+        .unwrap();
+
+        let mut other = Cursor::new(vec![0u8; 16 * 1024 * 1024]);
+        assert!(snapshot.restore(&mut other, 16 * 1024 * 1024).is_err());
+        assert!(other.get_ref().iter().all(|&b| b == 0));
+        snapshot.discard();
+    }
+
+    #[test]
+    fn test_capture_refuses_existing_file() {
+        let original = disk(1024 * 1024);
+        let path = temp_path("existing");
+        std::fs::write(&path, b"not mine").unwrap();
+
+        let result = WipeSnapshot::capture(
+            &mut Cursor::new(&original[..]),
+            original.len() as u64,
+            &path,
+        );
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"not mine");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_snapshot_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let original = disk(1024 * 1024);
+        let path = temp_path("private");
+        let snapshot = WipeSnapshot::capture(
+            &mut Cursor::new(&original[..]),
+            original.len() as u64,
+            &path,
+        )
+        .unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        snapshot.discard();
+    }
+}
@@ -0,0 +1,188 @@
+/// Retrying writes that fail on transient I/O errors
+///
+/// USB hubs and card readers occasionally fail a write with EIO or "device not ready"
+/// and then carry on as if nothing happened. Rather than failing a flash that may have
+/// run for hours, the chunk is written again after a short, growing pause.
+use std::io::{self, Seek, SeekFrom, Write};
+use std::time::Duration;
+use tracing::warn;
+
+/// How often a chunk is written again and how long to wait in between
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub retries: u32,
+    /// Pause before the first retry, doubled for each further one
+    pub initial_backoff: Duration,
+    /// Longest pause between two attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 5,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Pause before retry number `attempt`, counting from 1
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(self.max_backoff)
+    }
+}
+
+/// Whether an I/O error may go away when the write is repeated
+pub fn is_transient(e: &io::Error) -> bool {
+    #[cfg(windows)]
+    const TRANSIENT_CODES: &[i32] = &[
+        21,   // ERROR_NOT_READY
+        31,   // ERROR_GEN_FAILURE
+        121,  // ERROR_SEM_TIMEOUT
+        1117, // ERROR_IO_DEVICE
+    ];
+    #[cfg(not(windows))]
+    const TRANSIENT_CODES: &[i32] = &[libc::EIO, libc::EAGAIN, libc::EBUSY, libc::ETIMEDOUT];
+
+    matches!(
+        e.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    ) || e
+        .raw_os_error()
+        .is_some_and(|code| TRANSIENT_CODES.contains(&code))
+}
+
+/// Write all of `data` at the current position, retrying transient errors
+///
+/// Before each retry the device is seeked back to where the chunk starts, as a failed
+/// write may have written part of it.
+pub fn write_all_with_retry<W: Write + Seek>(
+    device: &mut W,
+    data: &[u8],
+    policy: &RetryPolicy,
+) -> io::Result<()> {
+    let offset = device.stream_position()?;
+    let mut attempt = 0;
+    loop {
+        match device.write_all(data) {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < policy.retries && is_transient(&e) => {
+                attempt += 1;
+                let delay = policy.backoff(attempt);
+                warn!(
+                    "Write of {} bytes at offset {} failed: {} - retrying in {:?} ({}/{})",
+                    data.len(),
+                    offset,
+                    e,
+                    delay,
+                    attempt,
+                    policy.retries
+                );
+                std::thread::sleep(delay);
+                device.seek(SeekFrom::Start(offset))?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Writes half of the data and then fails the first `failures` writes
+    struct FlakyDevice {
+        inner: Cursor<Vec<u8>>,
+        failures: u32,
+        error: fn() -> io::Error,
+    }
+
+    impl Write for FlakyDevice {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                self.inner.write_all(&vec![0xee; buf.len() / 2])?;
+                return Err((self.error)());
+            }
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for FlakyDevice {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            retries: 3,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    fn transient() -> io::Error {
+        io::Error::from(io::ErrorKind::TimedOut)
+    }
+
+    #[test]
+    fn test_retries_transient_errors_from_chunk_start() {
+        let mut device = FlakyDevice {
+            inner: Cursor::new(vec![0u8; 64]),
+            failures: 3,
+            error: transient,
+        };
+        device.seek(SeekFrom::Start(16)).unwrap();
+
+        write_all_with_retry(&mut device, &[1u8; 32], &policy()).unwrap();
+
+        let data = device.inner.into_inner();
+        assert_eq!(&data[..16], &[0u8; 16]);
+        assert_eq!(&data[16..48], &[1u8; 32]);
+        assert_eq!(&data[48..], &[0u8; 16]);
+    }
+
+    #[test]
+    fn test_gives_up_after_retries() {
+        let mut device = FlakyDevice {
+            inner: Cursor::new(Vec::new()),
+            failures: 4,
+            error: transient,
+        };
+
+        let error = write_all_with_retry(&mut device, &[1u8; 32], &policy()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_permanent_errors_are_not_retried() {
+        let mut device = FlakyDevice {
+            inner: Cursor::new(Vec::new()),
+            failures: 1,
+            error: || io::Error::from(io::ErrorKind::PermissionDenied),
+        };
+
+        let error = write_all_with_retry(&mut device, &[1u8; 32], &policy()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_limit() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_millis(250));
+        assert_eq!(policy.backoff(2), Duration::from_millis(500));
+        assert_eq!(policy.backoff(5), Duration::from_secs(4));
+        assert_eq!(policy.backoff(40), Duration::from_secs(4));
+    }
+}
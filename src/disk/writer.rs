@@ -0,0 +1,535 @@
+// Copying an image to a disk and reading it back
+//
+// The core of a flash without the device around it: the decompressed image is copied in
+// aligned chunks to anything that can be written and seeked, hashed as a whole and block by
+// block on the way, and then read back and compared block by block. `Disk::write_image`
+// runs it on a locked device between clearing it and fixing its partition table, and tests
+// run it on buffers in memory. Nothing here knows about devices, apart from asking the
+// caller to find a disk again that dropped off the bus during verification.
+
+use super::bad_blocks::{self, BadBlockKind, BadBlockReport, BlockHasher};
+use super::common::{self, FlashPhase, ImagePartition, PartitionProgress, WriteLatency};
+use super::geometry::MIN_IO_ALIGNMENT;
+use super::throttle::Throttle;
+use super::write_retry::{self, RetryPolicy};
+use super::{layout, read_full, reopen};
+use crate::models::{CancelToken, ImageMetadata};
+use anyhow::{Result, anyhow};
+use sha2::Digest;
+use std::cmp;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+/// Size of the chunks written to the disk
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// How an image is written and read back
+#[derive(Debug, Clone)]
+pub struct FlashPolicy {
+    /// Alignment of the chunks written and read; the last one is padded with zeros to it
+    pub alignment: u64,
+    /// Size reported in the progress when there is no metadata, e.g. of an uncompressed file
+    pub size_hint: Option<u64>,
+    /// Partitions of the image, to report which one is being written
+    pub partitions: Vec<ImagePartition>,
+    pub retry: RetryPolicy,
+    /// Most bytes per second written, unlimited if `None`
+    pub speed_limit: Option<u64>,
+    /// Cancels the write or the verification
+    pub cancel: CancelToken,
+    /// Stops the verification, keeping the completed write
+    pub skip_verification: CancelToken,
+}
+
+impl Default for FlashPolicy {
+    fn default() -> Self {
+        Self {
+            alignment: MIN_IO_ALIGNMENT,
+            size_hint: None,
+            partitions: Vec::new(),
+            retry: RetryPolicy::default(),
+            speed_limit: None,
+            cancel: CancelToken::new(),
+            skip_verification: CancelToken::new(),
+        }
+    }
+}
+
+/// The image as it was written, to read it back against
+#[derive(Debug, Clone)]
+pub struct Written {
+    /// Bytes of the image, without the padding after it
+    pub bytes: u64,
+    /// SHA-256 of the image, in hex
+    pub hash: String,
+    /// First bytes of the image, to recognize the device by if it re-enumerates
+    pub head: Vec<u8>,
+    /// SHA-256 of each block of the image
+    blocks: Vec<[u8; 32]>,
+}
+
+/// Write `image` to `disk` and read it back, as a flash does
+///
+/// Returns the disk and whether it was verified, which it isn't when the verification was
+/// skipped. A disk that goes away during verification isn't looked for again.
+pub fn flash<R: Read, D: Read + Write + Seek>(
+    image: &mut R,
+    mut disk: D,
+    metadata: Option<&ImageMetadata>,
+    policy: &FlashPolicy,
+    mut progress: impl FnMut(FlashPhase),
+) -> Result<(D, bool)> {
+    let written = write(image, &mut disk, metadata, policy, &mut progress)?;
+    verify(disk, &written, policy, &mut progress, |_| {
+        Err(anyhow!("The disk can't be opened again"))
+    })
+}
+
+/// Copy `image` to `disk` from its current position
+///
+/// With `metadata` exactly its size is written and the image must match its hash,
+/// otherwise `image` is written until it ends.
+pub fn write<R: Read, D: Write + Seek>(
+    image: &mut R,
+    disk: &mut D,
+    metadata: Option<&ImageMetadata>,
+    policy: &FlashPolicy,
+    progress: &mut impl FnMut(FlashPhase),
+) -> Result<Written> {
+    let alignment = policy.alignment as usize;
+    let total = metadata.map(|m| m.uncompressed_size).or(policy.size_hint);
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut hasher = sha2::Sha256::new();
+    let mut blocks = BlockHasher::new();
+    let mut head = Vec::new();
+    let mut written = 0u64;
+    let mut latency = WriteLatency::default();
+    let mut throttle = policy.speed_limit.map(|limit| {
+        info!("Write speed limited to {}/s", layout::format_size(limit));
+        Throttle::new(limit)
+    });
+    let started = Instant::now();
+    info!(
+        "Using aligned intermediate buffer of {} bytes, {}-byte alignment",
+        CHUNK_SIZE, alignment
+    );
+
+    loop {
+        // Check if operation was cancelled before reading the next chunk
+        if policy.cancel.is_cancelled() {
+            info!("Disk write operation cancelled by user");
+            return Err(anyhow!("Operation cancelled by user"));
+        }
+
+        let len = match metadata {
+            Some(metadata) => {
+                let remaining = metadata.uncompressed_size - written;
+                if remaining == 0 {
+                    break;
+                }
+                let len = cmp::min(remaining, CHUNK_SIZE as u64) as usize;
+                image.read_exact(&mut buffer[..len])?;
+                len
+            }
+            // Size unknown: write until the stream ends
+            None => {
+                let len = read_full(image, &mut buffer)?;
+                if len == 0 {
+                    break;
+                }
+                len
+            }
+        };
+
+        hasher.update(&buffer[..len]);
+        blocks.update(&buffer[..len]);
+
+        // Direct I/O needs whole sectors; pad a short final chunk with zeros
+        let padded_len = len.div_ceil(alignment) * alignment;
+        buffer[len..padded_len].fill(0);
+        if let Some(throttle) = &mut throttle {
+            throttle.wait(padded_len as u64);
+        }
+        let chunk_started = Instant::now();
+        write_retry::write_all_with_retry(disk, &buffer[..padded_len], &policy.retry)?;
+        latency.record(chunk_started.elapsed());
+
+        if head.is_empty() {
+            head = buffer[..cmp::min(len, reopen::SIGNATURE_LEN)].to_vec();
+        }
+        written += len as u64;
+        progress(FlashPhase::Writing {
+            bytes: written,
+            total,
+            rate: common::bytes_per_second(written, started),
+            download: None,
+            partition: PartitionProgress::at(&policy.partitions, written - len as u64),
+            latency,
+        });
+    }
+    info!("Successfully copied {} bytes with aligned buffers", written);
+    info!("Chunk write times: {}", latency);
+
+    // The image must be intact before the device is blamed for differences
+    let hash = hex::encode(hasher.finalize());
+    let expected = metadata.map(|m| m.uncompressed_hash.as_str());
+    if expected.is_some_and(|expected| expected != hash) {
+        error!("Image hash verification failed!");
+        error!("Expected: {}", expected.unwrap_or_default());
+        error!("Got:      {}", hash);
+        return Err(anyhow!(
+            "Data verification failed: the image does not match its expected hash. \
+             The image file is corrupt, not the device; download it again."
+        ));
+    }
+    info!("Image hash: {}", &hash[..16]);
+
+    Ok(Written {
+        bytes: written,
+        hash,
+        head,
+        blocks: blocks.finish(),
+    })
+}
+
+/// Read `written` back from the start of `disk` and compare it block by block
+///
+/// Blocks that don't read back as written are read again before they are reported in a
+/// [`BadBlockReport`]. When the disk goes away, `reopen` is handed the stale disk to find
+/// it again, and the verification continues where it was. Returns the disk, which may have
+/// been reopened, and whether it was verified, which it isn't when the verification was
+/// skipped.
+pub fn verify<D: Read + Seek>(
+    mut disk: D,
+    written: &Written,
+    policy: &FlashPolicy,
+    progress: &mut impl FnMut(FlashPhase),
+    mut reopen: impl FnMut(D) -> Result<D>,
+) -> Result<(D, bool)> {
+    info!("Starting written data verification");
+    disk.seek(SeekFrom::Start(0))?;
+
+    // Verify exactly the bytes of the image, not the sector padding after it
+    let total_size = written.bytes;
+    let mut buffer = vec![0u8; bad_blocks::BLOCK_SIZE as usize];
+    let mut read_blocks = BlockHasher::new();
+    let mut unreadable_blocks = Vec::new();
+    let mut verified_bytes = 0u64;
+    let mut reopen_count = 0;
+    let started = Instant::now();
+    info!(
+        "Reading back {} bytes for verification (actual bytes written)",
+        total_size
+    );
+
+    while verified_bytes < total_size {
+        if policy.cancel.is_cancelled() {
+            info!("Verification cancelled by user");
+            return Err(anyhow!("Verification cancelled by user"));
+        }
+        if policy.skip_verification.is_cancelled() {
+            warn!(
+                "Verification skipped by user after {} of {} bytes",
+                verified_bytes, total_size
+            );
+            return Ok((disk, false));
+        }
+
+        // Direct I/O reads whole sectors, so the final chunk is rounded up to one
+        let remaining = total_size - verified_bytes;
+        let aligned_remaining = remaining.div_ceil(policy.alignment) * policy.alignment;
+        let read_size = cmp::min(aligned_remaining, buffer.len() as u64) as usize;
+
+        match disk.read(&mut buffer[..read_size]) {
+            Ok(0) => {
+                warn!(
+                    "Unexpected EOF during verification at {} bytes",
+                    verified_bytes
+                );
+                break;
+            }
+            Ok(bytes_read) => {
+                // Only hash the actual data bytes, not padding
+                let data_bytes = cmp::min(bytes_read as u64, remaining) as usize;
+                read_blocks.update(&buffer[..data_bytes]);
+                verified_bytes += data_bytes as u64;
+                progress(FlashPhase::Verifying {
+                    bytes: verified_bytes,
+                    total: total_size,
+                    rate: common::bytes_per_second(verified_bytes, started),
+                });
+
+                // Log progress every 100MB
+                if verified_bytes % (100 * 1024 * 1024) == 0 || verified_bytes == total_size {
+                    info!(
+                        "Verified {} / {} MB",
+                        verified_bytes / (1024 * 1024),
+                        total_size / (1024 * 1024)
+                    );
+                }
+            }
+            Err(e) => {
+                error!("Error reading data for verification: {}", e);
+
+                if !reopen::is_device_gone(&e) {
+                    // Note the block and carry on with the next one
+                    let block = (verified_bytes / bad_blocks::BLOCK_SIZE) as usize;
+                    unreadable_blocks.push(block);
+                    if unreadable_blocks.len() > bad_blocks::MAX_UNREADABLE_BLOCKS {
+                        let bad: Vec<_> = unreadable_blocks
+                            .iter()
+                            .map(|&block| (block, BadBlockKind::Unreadable))
+                            .collect();
+                        return Err(anyhow::Error::new(BadBlockReport::new(&bad, total_size)));
+                    }
+                    verified_bytes = ((block as u64 + 1) * bad_blocks::BLOCK_SIZE).min(total_size);
+                    read_blocks.skip_to(block + 1);
+                    disk.seek(SeekFrom::Start(verified_bytes))?;
+                    continue;
+                }
+
+                // The device dropped off the bus, typically a USB reset during a long
+                // operation. Find it again and continue from the last verified offset.
+                reopen_count += 1;
+                if reopen_count > reopen::MAX_OPERATION_REOPENS {
+                    return Err(anyhow!(
+                        "Device keeps disconnecting during verification ({}). \
+                         The image was written but could not be verified; check the \
+                         USB connection and flash the device again.",
+                        e
+                    ));
+                }
+
+                warn!(
+                    "Device became unavailable during verification at {} bytes: {} - reopening ({}/{})",
+                    verified_bytes,
+                    e,
+                    reopen_count,
+                    reopen::MAX_OPERATION_REOPENS
+                );
+                disk = reopen(disk).map_err(|reopen_error| {
+                    anyhow!(
+                        "Device became unavailable during verification and could not be reopened: {}. \
+                         The image was written but could not be verified; reconnect the device \
+                         and flash it again to be sure it is correct.",
+                        reopen_error
+                    )
+                })?;
+                disk.seek(SeekFrom::Start(verified_bytes))?;
+                info!("Resuming verification at {} bytes", verified_bytes);
+            }
+        }
+    }
+
+    // Compare block by block, reading the bad blocks again
+    let bad =
+        bad_blocks::find_bad_blocks(&written.blocks, &read_blocks.finish(), &unreadable_blocks);
+    if !bad.is_empty() {
+        warn!(
+            "{} block(s) did not read back as written, reading them again",
+            bad.len()
+        );
+        let bad = bad_blocks::recheck(&mut disk, bad, &written.blocks, total_size, &mut buffer);
+        if !bad.is_empty() {
+            let report = BadBlockReport::new(&bad, total_size);
+            error!("{}", report);
+            return Err(anyhow::Error::new(report));
+        }
+        info!("All blocks read back correctly on the second attempt");
+    }
+
+    info!("Hash verification successful - written data is correct");
+    Ok((disk, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Cursor};
+
+    const MIB: usize = 1024 * 1024;
+
+    /// An image of `len` bytes that isn't the same in every block
+    fn image(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i / 4096 + i % 251) as u8).collect()
+    }
+
+    fn metadata(image: &[u8]) -> ImageMetadata {
+        ImageMetadata {
+            compressed_hash: String::new(),
+            uncompressed_hash: hex::encode(sha2::Sha256::digest(image)),
+            uncompressed_size: image.len() as u64,
+            created_at: String::new(),
+        }
+    }
+
+    /// A disk that returns `byte` wherever `offset` is read
+    struct CorruptDisk {
+        inner: Cursor<Vec<u8>>,
+        offset: u64,
+        byte: u8,
+    }
+
+    impl Read for CorruptDisk {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let start = self.inner.position();
+            let n = self.inner.read(buf)?;
+            if (start..start + n as u64).contains(&self.offset) {
+                buf[(self.offset - start) as usize] = self.byte;
+            }
+            Ok(n)
+        }
+    }
+
+    impl Write for CorruptDisk {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for CorruptDisk {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_flash_to_memory() {
+        // Not a whole number of blocks or sectors
+        let data = image(9 * MIB + 1000);
+        let mut phases = Vec::new();
+        let (disk, verified) = flash(
+            &mut data.as_slice(),
+            Cursor::new(Vec::new()),
+            Some(&metadata(&data)),
+            &FlashPolicy::default(),
+            |phase| phases.push(phase),
+        )
+        .unwrap();
+        assert!(verified);
+
+        // The last chunk is padded to whole sectors
+        let disk = disk.into_inner();
+        assert_eq!(disk.len(), 9 * MIB + 4096);
+        assert!(disk[..data.len()] == data[..]);
+        assert!(disk[data.len()..].iter().all(|&byte| byte == 0));
+
+        let writing = phases
+            .iter()
+            .rev()
+            .find(|phase| matches!(phase, FlashPhase::Writing { .. }));
+        assert!(matches!(
+            writing,
+            Some(FlashPhase::Writing { bytes, total: Some(total), .. })
+                if *bytes == data.len() as u64 && *total == data.len() as u64
+        ));
+        // Every chunk's write was timed
+        assert!(matches!(
+            writing,
+            Some(FlashPhase::Writing { latency, .. })
+                if latency.chunks == data.len().div_ceil(CHUNK_SIZE) as u64
+                    && latency.min <= latency.max
+        ));
+        assert!(matches!(
+            phases.last(),
+            Some(FlashPhase::Verifying { bytes, total, .. })
+                if *bytes == data.len() as u64 && *total == data.len() as u64
+        ));
+    }
+
+    #[test]
+    fn test_stream_without_metadata_is_written_to_its_end() {
+        let data = image(3 * MIB);
+        let mut disk = Cursor::new(Vec::new());
+        let written = write(
+            &mut data.as_slice(),
+            &mut disk,
+            None,
+            &FlashPolicy {
+                size_hint: Some(data.len() as u64),
+                ..FlashPolicy::default()
+            },
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(written.bytes, data.len() as u64);
+        assert_eq!(written.hash, metadata(&data).uncompressed_hash);
+        assert_eq!(written.head, data[..reopen::SIGNATURE_LEN]);
+        assert!(disk.into_inner() == data);
+    }
+
+    #[test]
+    fn test_corrupt_image_is_refused_before_verification() {
+        let data = image(MIB);
+        let mut expected = metadata(&data);
+        expected.uncompressed_hash = "00".repeat(32);
+        let error = flash(
+            &mut data.as_slice(),
+            Cursor::new(Vec::new()),
+            Some(&expected),
+            &FlashPolicy::default(),
+            |_| {},
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("image file is corrupt"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_bad_block_is_reported() {
+        let data = image(10 * MIB);
+        let disk = CorruptDisk {
+            inner: Cursor::new(Vec::new()),
+            offset: 5 * MIB as u64 + 17,
+            byte: 0xFF,
+        };
+        let error = flash(
+            &mut data.as_slice(),
+            disk,
+            Some(&metadata(&data)),
+            &FlashPolicy::default(),
+            |_| {},
+        )
+        .unwrap_err();
+        let report = error.downcast_ref::<BadBlockReport>().unwrap();
+        assert_eq!(report.ranges.len(), 1);
+        assert_eq!(report.ranges[0].kind, BadBlockKind::Mismatch);
+    }
+
+    #[test]
+    fn test_skipped_verification_keeps_the_write() {
+        let data = image(MIB);
+        let policy = FlashPolicy::default();
+        policy.skip_verification.cancel();
+        let (disk, verified) = flash(
+            &mut data.as_slice(),
+            Cursor::new(Vec::new()),
+            Some(&metadata(&data)),
+            &policy,
+            |_| {},
+        )
+        .unwrap();
+        assert!(!verified);
+        assert!(disk.into_inner() == data);
+
+        let policy = FlashPolicy::default();
+        policy.cancel.cancel();
+        let error = flash(
+            &mut data.as_slice(),
+            Cursor::new(Vec::new()),
+            None,
+            &policy,
+            |_| {},
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Operation cancelled by user");
+    }
+}
@@ -4,7 +4,9 @@ use directories::ProjectDirs;
 use iced::window::{Settings, icon};
 use std::path::PathBuf;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, registry, util::SubscriberInitExt};
+use tracing_subscriber::{
+    EnvFilter, fmt, layer::SubscriberExt, registry, reload, util::SubscriberInitExt,
+};
 
 mod disk;
 mod models;
@@ -14,18 +16,18 @@ mod utils;
 mod version;
 
 pub fn main() -> iced::Result {
-    // Initialize tracing with different default levels based on build profile
-    let default_level = if cfg!(debug_assertions) {
-        // In debug mode, show more detailed logs
-        "info,golem_gpu_imager=debug,iced_winit=error"
-    } else {
-        // In release mode, only show info and above
-        "info,golem_gpu_imager=info,iced_winit=error"
-    };
+    // Decide where files go before anything is written, starting with the log
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    utils::paths::init(&args);
+
+    // The log level and retention are set on the settings screen
+    let app_settings = utils::app_settings::AppSettings::load();
+    let log_settings = app_settings.log;
 
-    // Allow overriding via environment variable
-    let filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
+    // Allow overriding via environment variable; otherwise the level can change at runtime
+    let (filter, filter_handle) =
+        reload::Layer::new(utils::logs::initial_filter(log_settings.level));
+    utils::logs::set_filter_handle(filter_handle);
 
     // Check if running from console
     let is_console = is_running_from_console();
@@ -42,35 +44,41 @@ pub fn main() -> iced::Result {
         }
     }
 
-    // Set up logging based on whether we're in a console or not
-    if is_console {
-        // When running from a console, log to stdout
-        tracing_subscriber::fmt()
-            .with_env_filter(filter)
-            .with_file(true)
-            .with_line_number(true)
-            .init();
-    } else {
-        // When not running from a console, log to file
-        // Set up a rolling log file - daily rotation with a max of 5 files
-        let log_dir = get_log_directory();
-        let file_appender =
-            RollingFileAppender::new(Rotation::DAILY, log_dir, "golem-gpu-imager.log");
-        let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-
-        // We need to keep the guard alive for the duration of the program
-        // So we'll store it in a static and intentionally leak it
-        let _guard = Box::leak(Box::new(_guard));
-
-        // Set up subscriber with file logging
-        let file_layer = fmt::layer()
-            .with_writer(non_blocking)
-            .with_ansi(false) // Disable ANSI colors in file
-            .with_file(true)
-            .with_line_number(true);
-
-        registry().with(filter).with(file_layer).init();
+    // Always log to a file, which the log viewer shows and users attach to bug reports
+    // Set up a rolling log file - daily rotation, keeping one file per day of retention
+    let log_dir = get_log_directory();
+    if let Err(e) = utils::logs::prune_logs(&log_dir, log_settings.max_size_bytes()) {
+        eprintln!("Failed to delete old log files: {:#}", e);
     }
+    let file_appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(utils::logs::LOG_FILE_NAME)
+        .max_log_files(log_settings.retention_days as usize)
+        .build(&log_dir)
+        .expect("Failed to create the log file");
+    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
+
+    // We need to keep the guard alive for the duration of the program
+    // So we'll store it in a static and intentionally leak it
+    let _guard = Box::leak(Box::new(_guard));
+
+    let file_layer = fmt::layer()
+        .with_writer(non_blocking)
+        .with_ansi(false) // Disable ANSI colors in file
+        .with_file(true)
+        .with_line_number(true);
+
+    // When running from a console, log to stdout as well
+    let console_layer = is_console.then(|| fmt::layer().with_file(true).with_line_number(true));
+
+    registry()
+        .with(filter)
+        .with(file_layer)
+        .with(console_layer)
+        .init();
+
+    // Panics would otherwise close the window without a trace when there is no console
+    utils::crash_report::install();
 
     tracing::info!(
         "Starting Golem GPU Imager v{} built {} (console mode: {})",
@@ -78,19 +86,64 @@ pub fn main() -> iced::Result {
         version::BUILD_TIME,
         is_console
     );
+    if let Some(root) = utils::paths::portable_root() {
+        tracing::info!("Portable mode, keeping files in {}", root.display());
+    }
 
-    // Check elevation status on Windows
-    #[cfg(windows)]
-    {
-        let elevation_status = utils::get_elevation_status();
-        tracing::info!("Privilege status: {}", elevation_status);
+    // A second imager would fight this one over the disks, it shows this window instead
+    match utils::single_instance::claim(&args) {
+        Ok(utils::single_instance::Claim::Primary) => {}
+        Ok(utils::single_instance::Claim::HandedOff) => {
+            tracing::info!("Another instance is running, exiting");
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!("Failed to check for another instance: {:#}", e);
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Error)
+                .set_title("Golem GPU Imager")
+                .set_description(format!(
+                    "Golem GPU Imager appears to be running already but doesn't respond: {:#}",
+                    e
+                ))
+                .show();
+            return Ok(());
+        }
+    }
 
-        if !utils::is_elevated() {
+    // Shortcuts and scripts may open the flash workflow with its choices made
+    let preseed = match utils::preseed::from_args(&args) {
+        Ok(preseed) => preseed,
+        Err(e) => {
+            tracing::error!("Ignoring the command line choices: {}", e);
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Warning)
+                .set_title("Golem GPU Imager")
+                .set_description(format!("Ignoring the command line choices: {}", e))
+                .show();
+            None
+        }
+    };
+
+    // Let orchestration tools drive the imager while the window shows what is happening
+    if let Some(endpoint) = utils::automation::endpoint_from_args(&args) {
+        if let Err(e) = utils::automation::start(&endpoint) {
+            tracing::error!("Failed to start the automation API: {:#}", e);
+        }
+    }
+
+    // Check how disks can be accessed; the start screen prompts for elevation if needed
+    let elevation_status = utils::get_elevation_status();
+    tracing::info!("Privilege status: {}", elevation_status);
+
+    match utils::privilege_mode() {
+        utils::PrivilegeMode::Elevated | utils::PrivilegeMode::Helper => {}
+        utils::PrivilegeMode::Polkit => {
+            tracing::info!("Not running as root, disk access will be authorized through polkit");
+        }
+        utils::PrivilegeMode::Unprivileged => {
             tracing::warn!(
-                "Application is not running with administrator privileges. Some operations may fail."
-            );
-            tracing::info!(
-                "To run with administrator privileges, right-click the application and select 'Run as administrator'"
+                "Application is not running with sufficient privileges. Disk operations are disabled until it is elevated."
             );
         }
     }
@@ -99,19 +152,34 @@ pub fn main() -> iced::Result {
 
     settings.icon = Some(icon::from_file_data(include_bytes!("./assets/icon.png"), None).unwrap());
 
+    // Open the window the size it was left at; the layouts adapt to any size from the minimum
+    let window_size = app_settings.initial_window_size();
+    let min_size = utils::app_settings::WindowSize::MIN;
+    settings.resizable = true;
+    // A write in progress is stopped before the window closes, see ui::shutdown
+    settings.exit_on_close_request = false;
+    settings.min_size = Some(iced::Size::new(min_size.width, min_size.height));
+
     // Start the application and load repository data
-    iced::application(
-        ui::application::GolemGpuImager::new,
+    let result = iced::application(
+        move || ui::application::GolemGpuImager::boot(preseed.clone()),
         ui::application::GolemGpuImager::update,
         ui::application::GolemGpuImager::view,
     )
     .title(ui::application::GolemGpuImager::title)
+    .subscription(ui::application::GolemGpuImager::subscription)
+    .scale_factor(ui::application::GolemGpuImager::scale_factor)
     .font(ui::ICON_FONT)
     .window(settings)
-    .window_size(iced::Size::new(560f32 + 80f32, 720f32))
+    .window_size(iced::Size::new(window_size.width, window_size.height))
     .theme(|_| style::custom_theme())
     .centered()
-    .run()
+    .run();
+
+    utils::privileged_helper::stop();
+    utils::automation::stop();
+    utils::single_instance::release();
+    result
 }
 
 /// Check if the program is running in a console
@@ -145,14 +213,8 @@ fn is_running_from_console() -> bool {
 
 /// Get the directory for log files
 fn get_log_directory() -> PathBuf {
-    // Use ProjectDirs to get platform-specific data directory
-    // Match the convention used elsewhere in the codebase
-    let project_dirs = ProjectDirs::from("network", "Golem Factory", "GPU Imager")
-        .expect("Failed to determine project directory");
-
-    // Use the data_local_dir (platform-specific)
-    let mut log_dir = project_dirs.data_local_dir().to_path_buf();
-    log_dir.push("logs");
+    // Platform-specific data directory, shared with the log viewer
+    let log_dir = utils::paths::log_dir().expect("Failed to determine project directory");
 
     // Ensure the directory exists
     if !log_dir.exists() {
@@ -27,6 +27,99 @@ pub struct ConfigurationPreset {
     pub metrics_server: Option<String>,
     #[serde(default)]
     pub central_net_host: Option<String>,
+    /// Node name, which may contain template variables such as `{serial}`
+    #[serde(default)]
+    pub node_name: Option<String>,
+    #[serde(default)]
+    pub extra_env: Vec<ExtraSetting>,
+    #[serde(default)]
+    pub extra_toml: Vec<ExtraSetting>,
+    #[serde(default)]
+    pub firstboot: Option<FirstBootFile>,
+    /// From the team's shared collection, read-only and never saved with the local presets
+    #[serde(skip)]
+    pub shared: bool,
+}
+
+/// A setting the imager has no dedicated field for, written to the device as entered
+///
+/// Lets new Golem options be configured before the imager knows about them. Values of
+/// `golemwz.toml` entries are TOML (`42`, `true`, `"text"`); anything that doesn't parse
+/// as TOML is written as a string.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExtraSetting {
+    pub key: String,
+    pub value: String,
+}
+
+/// Largest first-boot file accepted; it has to fit on the configuration partition
+pub const MAX_FIRSTBOOT_FILE_SIZE: usize = 256 * 1024;
+
+/// What a first-boot file is, which decides its name on the configuration partition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FirstBootKind {
+    /// Shell script run once by the image on its first boot
+    Script,
+    /// cloud-init user-data
+    CloudInit,
+}
+
+impl FirstBootKind {
+    /// Name of the file on the configuration partition
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            FirstBootKind::Script => "firstboot.sh",
+            FirstBootKind::CloudInit => "user-data",
+        }
+    }
+}
+
+/// A user-provided provisioning file copied onto the configuration partition when flashing
+///
+/// Used for site-specific setup such as extra packages or monitoring agents without
+/// rebuilding the image. The content is stored in the preset so it travels with it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FirstBootFile {
+    pub kind: FirstBootKind,
+    /// Name of the file it was loaded from, for display
+    pub source_name: String,
+    pub content: String,
+}
+
+impl FirstBootFile {
+    /// Build a first-boot file from the contents of a file picked by the user
+    ///
+    /// Files named `user-data`, YAML files and files starting with `#cloud-config` are
+    /// treated as cloud-init user-data, anything else as a shell script. Windows line
+    /// endings are converted since the image won't run a script with them.
+    pub fn from_file(source_name: &str, data: Vec<u8>) -> Result<Self, String> {
+        if data.len() > MAX_FIRSTBOOT_FILE_SIZE {
+            return Err(format!(
+                "{} is too large ({} KiB, at most {} KiB)",
+                source_name,
+                data.len() / 1024,
+                MAX_FIRSTBOOT_FILE_SIZE / 1024
+            ));
+        }
+        let content = String::from_utf8(data)
+            .map_err(|_| format!("{} is not a text file", source_name))?
+            .replace("\r\n", "\n");
+
+        let lower_name = source_name.to_lowercase();
+        let is_cloud_init = lower_name.starts_with("user-data")
+            || lower_name.ends_with(".yaml")
+            || lower_name.ends_with(".yml")
+            || content.trim_start().starts_with("#cloud-config");
+        Ok(Self {
+            kind: if is_cloud_init {
+                FirstBootKind::CloudInit
+            } else {
+                FirstBootKind::Script
+            },
+            source_name: source_name.to_string(),
+            content,
+        })
+    }
 }
 
 // Implement Display trait so pick_list can properly show the preset
@@ -75,12 +168,45 @@ impl CancelToken {
     }
 }
 
+/// Identifies one run of a long operation, such as a write
+///
+/// Progress and results are tagged with the run they belong to, so a late message from a
+/// cancelled or replaced run isn't mistaken for one of the run shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(transparent)]
+pub struct OperationId(uuid::Uuid);
+
+impl Default for OperationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OperationId {
+    /// A new, unique operation
+    pub fn new() -> Self {
+        Self(uuid::Uuid::new_v4())
+    }
+}
+
+impl std::fmt::Display for OperationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AppMode {
     StartScreen,
     FlashNewImage,
     EditExistingDisk,
+    UpdateExistingDevice,
     ManagePresets,
+    ManageCache,
+    FlashQueue,
+    Kiosk,
+    ViewLogs,
+    Settings,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -114,3 +240,232 @@ impl std::fmt::Display for NetworkType {
         }
     }
 }
+
+impl PaymentNetwork {
+    /// `YA_PAYMENT_NETWORK_GROUP` as written to the configuration files
+    pub fn file_value(&self) -> &'static str {
+        match self {
+            PaymentNetwork::Testnet => "testnet",
+            PaymentNetwork::Mainnet => "mainnet",
+        }
+    }
+
+    /// Read `YA_PAYMENT_NETWORK_GROUP`, anything but mainnet is testnet
+    pub fn from_file_value(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "mainnet" => PaymentNetwork::Mainnet,
+            _ => PaymentNetwork::Testnet,
+        }
+    }
+}
+
+impl NetworkType {
+    /// `YA_NET_TYPE` as written to the configuration files
+    pub fn file_value(&self) -> &'static str {
+        match self {
+            NetworkType::Hybrid => "hybrid",
+            NetworkType::Central => "central",
+        }
+    }
+
+    /// Read `YA_NET_TYPE`, anything but hybrid is central
+    pub fn from_file_value(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "hybrid" => NetworkType::Hybrid,
+            _ => NetworkType::Central,
+        }
+    }
+}
+
+/// Metrics server written when the configuration doesn't name one
+pub const DEFAULT_METRICS_URL: &str = "https://metrics.golem.network:9092/";
+
+/// Metrics job name written when neither the configuration nor the device has one
+pub const DEFAULT_METRICS_JOB_NAME: &str = "community.1";
+
+/// The settings the imager writes to golemwz.toml, fields in file order
+///
+/// Both files are generated by serializing this and read back by deserializing it, so a
+/// fresh write and a merge into the files on a device produce the same values, quoted the
+/// same way. The files may have been edited by hand, so a setting of the wrong type is read
+/// as its default instead of failing the whole file.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DeviceConfig {
+    #[serde(deserialize_with = "lenient::accepted_terms")]
+    pub accepted_terms: bool,
+    #[serde(deserialize_with = "lenient::or_default")]
+    pub glm_account: String,
+    #[serde(deserialize_with = "lenient::glm_per_hour")]
+    pub glm_per_hour: String,
+    #[serde(
+        deserialize_with = "lenient::or_default",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub glm_node_name: Option<String>,
+    #[serde(deserialize_with = "lenient::or_default")]
+    pub non_interactive_install: bool,
+    #[serde(deserialize_with = "lenient::strings")]
+    pub ssh_keys: Vec<String>,
+    #[serde(
+        deserialize_with = "lenient::or_default",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub configuration_server: Option<String>,
+    #[serde(deserialize_with = "lenient::env")]
+    pub env: DeviceEnv,
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            accepted_terms: true,
+            glm_account: String::new(),
+            glm_per_hour: "0.25".to_string(),
+            glm_node_name: None,
+            non_interactive_install: false,
+            ssh_keys: Vec::new(),
+            configuration_server: None,
+            env: DeviceEnv::default(),
+        }
+    }
+}
+
+/// The variables of golem.env, which golemwz.toml repeats in its `[env]` table
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DeviceEnv {
+    #[serde(rename = "YA_NET_TYPE", with = "network_type_value")]
+    pub network_type: NetworkType,
+    #[serde(rename = "SUBNET", deserialize_with = "lenient::subnet")]
+    pub subnet: String,
+    #[serde(rename = "YA_PAYMENT_NETWORK_GROUP", with = "payment_network_value")]
+    pub payment_network: PaymentNetwork,
+    #[serde(
+        rename = "CENTRAL_NET_HOST",
+        deserialize_with = "lenient::or_default",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub central_net_host: Option<String>,
+    // A mistyped metrics setting reads as blank, which leaves it unset like a missing one
+    #[serde(rename = "YAGNA_METRICS_URL", deserialize_with = "lenient::or_default")]
+    pub metrics_url: String,
+    #[serde(
+        rename = "YAGNA_METRICS_JOB_NAME",
+        deserialize_with = "lenient::or_default"
+    )]
+    pub metrics_job_name: String,
+    #[serde(
+        rename = "YAGNA_METRICS_GROUP",
+        deserialize_with = "lenient::or_default"
+    )]
+    pub metrics_group: String,
+}
+
+impl Default for DeviceEnv {
+    fn default() -> Self {
+        Self {
+            network_type: NetworkType::Central,
+            subnet: "public".to_string(),
+            payment_network: PaymentNetwork::Testnet,
+            central_net_host: None,
+            metrics_url: DEFAULT_METRICS_URL.to_string(),
+            metrics_job_name: DEFAULT_METRICS_JOB_NAME.to_string(),
+            metrics_group: String::new(),
+        }
+    }
+}
+
+// The files spell the networks in lowercase, presets keep the variant names
+mod network_type_value {
+    use super::NetworkType;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &NetworkType, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(value.file_value())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<NetworkType, D::Error> {
+        let value: String = super::lenient::or_default(deserializer)?;
+        Ok(NetworkType::from_file_value(&value))
+    }
+}
+
+mod payment_network_value {
+    use super::PaymentNetwork;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &PaymentNetwork,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(value.file_value())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PaymentNetwork, D::Error> {
+        let value: String = super::lenient::or_default(deserializer)?;
+        Ok(PaymentNetwork::from_file_value(&value))
+    }
+}
+
+// Settings of the wrong type fall back to what a missing setting reads as
+mod lenient {
+    use super::{DeviceConfig, DeviceEnv};
+    use serde::de::IgnoredAny;
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Typed<T> {
+        Value(T),
+        Other(IgnoredAny),
+    }
+
+    fn or_else<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+        default: impl FnOnce() -> T,
+    ) -> Result<T, D::Error> {
+        Ok(match Typed::deserialize(deserializer)? {
+            Typed::Value(value) => value,
+            Typed::Other(_) => default(),
+        })
+    }
+
+    pub fn or_default<'de, D: Deserializer<'de>, T: Deserialize<'de> + Default>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        or_else(deserializer, T::default)
+    }
+
+    /// A list of strings, without the entries that are something else
+    pub fn strings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+        let entries: Vec<Typed<String>> = or_default(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| match entry {
+                Typed::Value(value) => Some(value),
+                Typed::Other(_) => None,
+            })
+            .collect())
+    }
+
+    pub fn accepted_terms<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        or_else(deserializer, || DeviceConfig::default().accepted_terms)
+    }
+
+    pub fn glm_per_hour<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        or_else(deserializer, || DeviceConfig::default().glm_per_hour)
+    }
+
+    pub fn env<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DeviceEnv, D::Error> {
+        or_else(deserializer, DeviceEnv::default)
+    }
+
+    pub fn subnet<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        or_else(deserializer, || DeviceEnv::default().subnet)
+    }
+}
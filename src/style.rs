@@ -323,6 +323,76 @@ pub fn mainnet_badge(theme: &Theme) -> container::Style {
     }
 }
 
+// Disk health badge styling for healthy media
+pub fn health_good_badge(_theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(Color::from_rgb(0.7, 0.9, 0.7).into()),
+        border: Border {
+            width: 0.0,
+            radius: 12.0.into(),
+            color: Color::TRANSPARENT,
+        },
+        text_color: Some(Color::from_rgb(0.0, 0.3, 0.0)),
+        ..container::Style::default()
+    }
+}
+
+// Disk health badge styling for worn media
+pub fn health_warning_badge(_theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(Color::from_rgb(0.95, 0.7, 0.3).into()),
+        border: Border {
+            width: 0.0,
+            radius: 12.0.into(),
+            color: Color::TRANSPARENT,
+        },
+        text_color: Some(Color::from_rgb(0.4, 0.2, 0.0)),
+        ..container::Style::default()
+    }
+}
+
+// Disk health badge styling for failing media
+pub fn health_failing_badge(_theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(ERROR.into()),
+        border: Border {
+            width: 0.0,
+            radius: 12.0.into(),
+            color: Color::TRANSPARENT,
+        },
+        text_color: Some(Color::WHITE),
+        ..container::Style::default()
+    }
+}
+
+// Badge for devices that already carry a Golem configuration
+pub fn golem_device_badge(_theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(PRIMARY.into()),
+        border: Border {
+            width: 0.0,
+            radius: 12.0.into(),
+            color: Color::TRANSPARENT,
+        },
+        text_color: Some(Color::WHITE),
+        ..container::Style::default()
+    }
+}
+
+// Badge for read-only presets of the team's shared collection
+pub fn shared_preset_badge(_theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(Color::from_rgb(0.6, 0.75, 0.95).into()),
+        border: Border {
+            width: 0.0,
+            radius: 8.0.into(),
+            color: Color::TRANSPARENT,
+        },
+        text_color: Some(Color::from_rgb(0.05, 0.15, 0.35)),
+        ..container::Style::default()
+    }
+}
+
 // Search input styling
 pub fn search_input(theme: &Theme) -> iced::widget::text_input::Style {
     let palette = theme.extended_palette();
@@ -479,6 +549,22 @@ pub fn modal_overlay(_theme: &Theme) -> container::Style {
     }
 }
 
+// Tooltip - small bordered box floating over the content
+pub fn tooltip_box(theme: &Theme) -> container::Style {
+    let palette = theme.extended_palette();
+
+    container::Style {
+        background: Some(palette.background.base.color.into()),
+        border: Border {
+            width: 1.0,
+            radius: 5.0.into(),
+            color: palette.background.strong.color,
+        },
+        text_color: Some(TEXT),
+        ..container::Style::default()
+    }
+}
+
 // Confirmation dialog - centered dialog box with border and background
 pub fn confirmation_dialog(theme: &Theme) -> container::Style {
     let palette = theme.extended_palette();
@@ -499,3 +585,31 @@ pub fn confirmation_dialog(theme: &Theme) -> container::Style {
         ..container::Style::default()
     }
 }
+
+// Duplicator mode panel once a disk was flashed and verified
+pub fn kiosk_success_panel(_theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(Color::from_rgb(0.0, 0.45, 0.15).into()),
+        border: Border {
+            width: 2.0,
+            radius: 16.0.into(),
+            color: SUCCESS,
+        },
+        text_color: Some(Color::WHITE),
+        ..container::Style::default()
+    }
+}
+
+// Duplicator mode panel once flashing a disk failed
+pub fn kiosk_failure_panel(_theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(Color::from_rgb(0.55, 0.05, 0.05).into()),
+        border: Border {
+            width: 2.0,
+            radius: 16.0.into(),
+            color: ERROR,
+        },
+        text_color: Some(Color::WHITE),
+        ..container::Style::default()
+    }
+}
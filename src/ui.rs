@@ -1,18 +1,36 @@
+pub mod alerts;
+pub mod app_update;
 pub mod application;
+pub mod automation;
 mod icons;
+pub mod kiosk;
+pub mod layout;
+pub mod notifications;
 pub mod preset_editor;
+pub mod shutdown;
 pub mod start_screen;
+pub mod tray;
 
 // New modular workflow modules
+pub mod cache_manager;
 pub mod configuration;
 pub mod device_selection;
 pub mod edit_workflow;
+pub mod flash_monitor;
+pub mod flash_queue;
 pub mod flash_workflow;
+pub mod log_viewer;
 pub mod preset_manager;
+pub mod settings;
+pub mod update_workflow;
 
 // Unified message system
 pub mod messages;
 
+// Scripted runs of the update loop for tests
+#[cfg(test)]
+mod test_support;
+
 #[allow(unused_imports)]
 pub use application::GolemGpuImager;
 #[allow(unused_imports)]
@@ -0,0 +1,156 @@
+/// Alerts at the end of a flash for benches nobody is watching
+///
+/// In a provisioning room the screens are across the bench and the room is loud, so a
+/// notification is easily missed. Turned on in the settings, the end of a flash or device
+/// update is announced by a sound, rising tones on success and a low buzz on failure, and by
+/// flashing the whole window green or red. The window stays tinted until it is clicked, so
+/// the outcome is still there for whoever comes back to it.
+use crate::ui::flash_workflow::FlashMessage;
+use crate::ui::icons;
+use crate::ui::messages::Message;
+use crate::ui::update_workflow::UpdateMessage;
+use iced::widget::{column, container, mouse_area, text};
+use iced::{Alignment, Color, Element, Length};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Number of times the window is flashed before it stays tinted
+const BLINKS: u8 = 6;
+
+/// How an operation ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Failure,
+}
+
+/// The outcome of a flash, for the messages that end one
+pub fn flash_outcome(message: &FlashMessage) -> Option<Outcome> {
+    match message {
+        FlashMessage::WriteImageCompleted(..) => Some(Outcome::Success),
+        FlashMessage::WriteImageFailed(..) => Some(Outcome::Failure),
+        _ => None,
+    }
+}
+
+/// The outcome of a device update, for the messages that end one
+pub fn update_outcome(message: &UpdateMessage) -> Option<Outcome> {
+    match message {
+        UpdateMessage::UpdateCompleted(_) => Some(Outcome::Success),
+        UpdateMessage::UpdateFailed(..) => Some(Outcome::Failure),
+        _ => None,
+    }
+}
+
+/// Play the sound of `outcome`
+///
+/// Opening the audio device can take a moment and the sound is played to its end, so it is
+/// done on its own thread. Stations without audio output only log it.
+pub fn play(outcome: Outcome) {
+    std::thread::spawn(move || {
+        debug!("Playing the {:?} alert", outcome);
+        if let Err(e) = play_tones(outcome) {
+            warn!("Failed to play the alert sound: {}", e);
+        }
+    });
+}
+
+fn play_tones(outcome: Outcome) -> Result<(), String> {
+    use rodio::source::{SineWave, Source, Zero};
+
+    // Frequency in Hz and duration in ms of each tone, a frequency of 0 being a pause
+    let tones: &[(f32, u64)] = match outcome {
+        Outcome::Success => &[(660.0, 120), (880.0, 120), (1320.0, 260)],
+        Outcome::Failure => &[
+            (220.0, 300),
+            (0.0, 120),
+            (220.0, 300),
+            (0.0, 120),
+            (220.0, 300),
+        ],
+    };
+
+    let (_stream, handle) = rodio::OutputStream::try_default().map_err(|e| e.to_string())?;
+    let sink = rodio::Sink::try_new(&handle).map_err(|e| e.to_string())?;
+    for &(frequency, ms) in tones {
+        let duration = Duration::from_millis(ms);
+        if frequency > 0.0 {
+            sink.append(
+                SineWave::new(frequency)
+                    .take_duration(duration)
+                    .amplify(0.3),
+            );
+        } else {
+            sink.append(Zero::<f32>::new(1, 48_000).take_duration(duration));
+        }
+    }
+    sink.sleep_until_end();
+    Ok(())
+}
+
+/// The window flashed at the end of an operation
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowAlert {
+    pub outcome: Outcome,
+    blinks_left: u8,
+    lit: bool,
+}
+
+impl WindowAlert {
+    pub fn new(outcome: Outcome) -> Self {
+        Self {
+            outcome,
+            blinks_left: BLINKS * 2,
+            lit: true,
+        }
+    }
+
+    /// Whether the window is still flashing, rather than staying tinted
+    pub fn is_blinking(&self) -> bool {
+        self.blinks_left > 0
+    }
+
+    /// Switch the color on or off, until it has blinked enough
+    pub fn tick(&mut self) {
+        if self.blinks_left > 0 {
+            self.blinks_left -= 1;
+            self.lit = !self.lit || self.blinks_left == 0;
+        }
+    }
+}
+
+/// The color over the whole window, dismissed by clicking it
+pub fn view_window_alert<'a>(alert: &WindowAlert) -> Element<'a, Message> {
+    let (status_icon, title, color) = match alert.outcome {
+        Outcome::Success => (
+            icons::check_circle(),
+            "Done",
+            Color::from_rgb(0.0, 0.55, 0.2),
+        ),
+        Outcome::Failure => (icons::error(), "Failed", Color::from_rgb(0.7, 0.05, 0.05)),
+    };
+    let background = Color {
+        a: if alert.lit { 0.9 } else { 0.35 },
+        ..color
+    };
+
+    mouse_area(
+        container(
+            column![
+                status_icon.size(120).color(Color::WHITE),
+                text(title).size(56),
+                text("Click anywhere to dismiss").size(16),
+            ]
+            .spacing(15)
+            .align_x(Alignment::Center),
+        )
+        .center(Length::Fill)
+        .style(move |_theme| container::Style {
+            background: Some(background.into()),
+            text_color: Some(Color::WHITE),
+            ..container::Style::default()
+        }),
+    )
+    .on_press(Message::DismissAlert)
+    .into()
+}
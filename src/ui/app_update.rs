@@ -0,0 +1,128 @@
+/// Notice and dialog for new releases of the application
+use crate::style;
+use crate::ui::icons;
+use crate::ui::messages::Message;
+use crate::utils::updater::UpdateInfo;
+use iced::widget::{button, checkbox, column, container, row, scrollable, text};
+use iced::{Alignment, Color, Element, Length};
+
+#[derive(Debug, Clone, Default)]
+pub struct AppUpdateState {
+    pub available: Option<UpdateInfo>,
+    pub checking: bool,
+    pub downloading: bool,
+    pub dialog_open: bool,
+    pub notice_dismissed: bool, // "Later" hides the notice until the next start
+    pub status: Option<String>, // Result of a check the user asked for, or a failed install
+}
+
+impl AppUpdateState {
+    /// Update to announce on the start screen
+    pub fn notice(&self) -> Option<&UpdateInfo> {
+        if self.notice_dismissed {
+            None
+        } else {
+            self.available.as_ref()
+        }
+    }
+}
+
+/// Dialog with the release notes of the available update
+pub fn view_update_dialog<'a>(
+    state: &'a AppUpdateState,
+    check_at_startup: bool,
+) -> Element<'a, Message> {
+    let mut content = column![].spacing(15).width(Length::Fill).max_width(520);
+
+    match &state.available {
+        Some(update) => {
+            content = content.push(
+                row![icons::get_app().size(24), text(&update.title).size(20)]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+            );
+            content = content.push(
+                text(format!(
+                    "Version {} is available, you are running {}",
+                    update.version,
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .size(14)
+                .color(Color::from_rgb(0.8, 0.8, 0.8)),
+            );
+            if !update.changelog.trim().is_empty() {
+                content = content.push(
+                    container(
+                        scrollable(text(update.changelog.trim()).size(13)).height(Length::Fill),
+                    )
+                    .style(style::bordered_box)
+                    .padding(10)
+                    .width(Length::Fill)
+                    .height(Length::Fixed(260.0)),
+                );
+            }
+        }
+        None => {
+            content = content.push(text("Check for Updates").size(20));
+        }
+    }
+
+    if let Some(status) = &state.status {
+        content = content.push(text(status).size(13).color(Color::from_rgb(0.8, 0.8, 0.6)));
+    }
+
+    content = content.push(
+        checkbox("Check for updates at startup", check_at_startup)
+            .on_toggle(Message::SetCheckForUpdates)
+            .size(16),
+    );
+
+    let mut buttons = row![].spacing(10).align_y(Alignment::Center);
+    if let Some(update) = &state.available {
+        if update.installer.is_some() {
+            buttons = buttons.push(
+                button(text(if state.downloading {
+                    "Downloading..."
+                } else {
+                    "Install and Restart"
+                }))
+                .on_press_maybe((!state.downloading).then_some(Message::InstallAppUpdate))
+                .padding(12)
+                .style(button::primary),
+            );
+        }
+        buttons = buttons.push(
+            button(text("Open Release Page"))
+                .on_press(Message::OpenReleasePage)
+                .padding(12)
+                .style(button::secondary),
+        );
+    }
+    buttons = buttons.push(
+        button(text("Close"))
+            .on_press_maybe((!state.downloading).then_some(Message::CloseAppUpdate))
+            .padding(12)
+            .style(button::secondary),
+    );
+    content = content.push(
+        container(buttons)
+            .width(Length::Fill)
+            .align_x(Alignment::End),
+    );
+
+    // Center the dialog on screen
+    container(
+        container(content)
+            .style(style::confirmation_dialog)
+            .padding(25)
+            .width(Length::Shrink)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill),
+    )
+    .style(style::modal_overlay)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .into()
+}
@@ -1,17 +1,34 @@
-use crate::models::AppMode;
+use crate::models::{AppMode, OperationId};
 use crate::ui::{
+    alerts::{Outcome, WindowAlert},
+    app_update::AppUpdateState,
+    automation::{AutomationState, FlashOrigin, PendingFlash, PendingStage},
+    cache_manager::CacheManagerState,
     configuration::ConfigurationState,
-    device_selection::DeviceSelectionState,
+    device_selection::{DeviceMessage, DeviceSelectionState},
     edit_workflow::{EditState, EditWorkflowState},
+    flash_monitor::FlashMonitor,
+    flash_queue::{FlashQueueState, JobStatus},
     flash_workflow::{FlashMessage, FlashState, FlashWorkflowState},
+    kiosk::KioskState,
+    log_viewer::{LogViewerMessage, LogViewerState},
     messages::Message,
     preset_manager::PresetManagerState,
+    settings::SettingsState,
+    shutdown::ExitState,
+    update_workflow::{UpdateMessage, UpdateState, UpdateWorkflowState},
 };
+use crate::utils::app_settings::{self, AppSettings, WindowSize};
+use crate::utils::automation::{Call, Command, FlashEvent, StartFlash};
+use crate::utils::flash_history::FlashHistory;
+use crate::utils::preseed::Preseed;
 use crate::utils::repo::ImageRepo;
+use crate::utils::telemetry::FlashOutcome;
+use crate::utils::updater;
 use crate::utils::{PresetManager, image_metadata::MetadataManager};
-use iced::{Element, Task};
+use iced::{Element, Size, Subscription, Task, keyboard, window};
 use std::sync::Arc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 pub struct GolemGpuImager {
     pub mode: AppMode,
@@ -19,24 +36,41 @@ pub struct GolemGpuImager {
     // Module states
     pub flash_workflow: Option<FlashState>,
     pub edit_workflow: Option<EditState>,
+    pub update_workflow: Option<UpdateState>,
     pub preset_manager: PresetManagerState,
+    pub cache_manager: CacheManagerState,
+    pub log_viewer: LogViewerState,
+    pub settings_screen: SettingsState,
     pub device_selection: DeviceSelectionState,
     pub configuration: ConfigurationState,
 
     // Shared resources
     pub image_repo: Arc<ImageRepo>,
     pub elevation_status: String,
-    pub is_elevated: bool,
+    pub privilege_mode: crate::utils::PrivilegeMode,
     pub metadata_manager: Option<MetadataManager>,
     pub preset_manager_backend: Option<PresetManager>,
+    pub flash_history: FlashHistory, // What has been written to each device, for its wear
     pub is_loading_repo: bool,
     pub error_message: Option<String>,
+    pub in_tray: bool, // The window is hidden and the flash runs in the background
+    pub window_focused: bool, // Results are announced as notifications while it is not
+    pub settings: AppSettings,
+    pub settings_changed: bool, // Saved after a short delay so resizing doesn't write every frame
+    pub app_update: AppUpdateState,
+    pub crash_report: Option<std::path::PathBuf>, // Report of a crash during the last run
+    pub window_size: Size, // In the units the views are laid out in, i.e. after the zoom
+    pub automation: AutomationState, // Automation API requests waiting for the window
+    pub flash_queue: FlashQueueState, // Flashes run one after another
+    pub kiosk: Option<KioskState>, // Flashing every inserted disk
+    pub flash_monitor: FlashMonitor, // Progress of each write, shown over the current screen
+    pub window_alert: Option<WindowAlert>, // Color over the window after a flash ended
+    pub exit_state: ExitState, // Closing the window while a write runs
+    pub preseed: Option<Preseed>, // Command line choices not yet made in the flash workflow
 }
 
 impl GolemGpuImager {
     pub fn new() -> Self {
-        let image_repo = Arc::new(ImageRepo::new());
-
         // Initialize the PresetManager backend
         let preset_manager_backend = match PresetManager::new() {
             Ok(mut manager) => {
@@ -49,11 +83,53 @@ impl GolemGpuImager {
             }
         };
 
+        let settings = AppSettings::load();
+        crate::utils::device_rules::set_active(settings.device_rules.clone());
+        crate::utils::provisioning::set_active(crate::utils::provisioning::Provisioning::load());
+
+        // Initialize the MetadataManager
+        let metadata_manager = match MetadataManager::new() {
+            Ok(manager) => {
+                info!("Successfully initialized metadata manager");
+                Some(manager)
+            }
+            Err(e) => {
+                error!("Failed to initialize metadata manager: {}", e);
+                None
+            }
+        };
+
+        Self::from_parts(
+            settings,
+            preset_manager_backend,
+            metadata_manager,
+            FlashHistory::load(),
+            crate::utils::crash_report::pending_report(),
+        )
+    }
+
+    /// The application on the start screen, with settings and stores already loaded
+    ///
+    /// Tests start from here, without reading or writing the user's files.
+    pub fn from_parts(
+        settings: AppSettings,
+        preset_manager_backend: Option<PresetManager>,
+        metadata_manager: Option<MetadataManager>,
+        flash_history: FlashHistory,
+        crash_report: Option<std::path::PathBuf>,
+    ) -> Self {
+        let image_repo = Arc::new(ImageRepo::new());
+
         // Initialize preset manager state with defaults or from backend
         let preset_manager_state = match &preset_manager_backend {
             Some(manager) => {
                 let mut state = PresetManagerState::new();
                 state.presets = manager.get_presets().clone();
+                state.assignments = manager.get_assignments().clone();
+                state.last_change = manager
+                    .history()
+                    .last()
+                    .map(|snapshot| snapshot.change.clone());
                 // Select the default preset if available
                 state.selected_preset = state.presets.iter().position(|p| p.is_default);
                 state
@@ -67,43 +143,153 @@ impl GolemGpuImager {
         };
 
         let elevation_status = crate::utils::get_elevation_status();
-        let is_elevated = crate::utils::is_elevated();
+        let privilege_mode = crate::utils::privilege_mode();
 
-        // Initialize the MetadataManager
-        let metadata_manager = match MetadataManager::new() {
-            Ok(manager) => {
-                info!("Successfully initialized metadata manager");
-                Some(manager)
-            }
-            Err(e) => {
-                error!("Failed to initialize metadata manager: {}", e);
-                None
-            }
-        };
+        let initial_size = settings.initial_window_size();
+        let window_size = Size::new(
+            initial_size.width / settings.ui_scale as f32,
+            initial_size.height / settings.ui_scale as f32,
+        );
 
         Self {
             mode: AppMode::StartScreen,
             flash_workflow: None,
             edit_workflow: None,
+            update_workflow: None,
             preset_manager: preset_manager_state,
+            cache_manager: CacheManagerState::new(),
+            log_viewer: LogViewerState::new(),
+            settings_screen: SettingsState::new(),
             device_selection: DeviceSelectionState::new(),
             configuration: ConfigurationState::new(),
             image_repo,
             elevation_status,
-            is_elevated,
+            privilege_mode,
             metadata_manager,
             preset_manager_backend,
+            flash_history,
             is_loading_repo: false,
             error_message: None,
+            in_tray: false,
+            window_focused: true,
+            settings,
+            settings_changed: false,
+            app_update: AppUpdateState::default(),
+            crash_report,
+            window_size,
+            automation: AutomationState::default(),
+            flash_queue: FlashQueueState::new(),
+            kiosk: None,
+            flash_monitor: FlashMonitor::new(),
+            window_alert: None,
+            exit_state: ExitState::default(),
+            preseed: None,
         }
     }
 }
 
 impl GolemGpuImager {
+    /// Create the application and start the tasks that run at startup
+    ///
+    /// `preseed` holds the flash workflow choices made on the command line.
+    pub fn boot(preseed: Option<Preseed>) -> (Self, Task<Message>) {
+        let app = Self::new();
+        let mut startup = Vec::new();
+        if let Some(preseed) = preseed {
+            startup.push(Task::done(Message::Preseed(preseed)));
+        }
+        if app.settings.check_for_updates {
+            startup.push(Task::done(Message::CheckForAppUpdate(false)));
+        }
+        if app.settings.shared_presets.is_active() {
+            startup.push(Task::done(Message::LoadSharedPresets));
+        }
+        (app, Task::batch(startup))
+    }
+
     pub fn title(&self) -> String {
         format!("Golem GPU Imager v{}", env!("CARGO_PKG_VERSION"))
     }
 
+    /// Zoom of the user interface, adjusted with Ctrl and +, - or 0
+    pub fn scale_factor(&self) -> f64 {
+        self.settings.ui_scale
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        let window_events = iced::event::listen_with(|event, _status, _id| match event {
+            iced::Event::Window(window::Event::Focused) => Some(Message::WindowFocusChanged(true)),
+            iced::Event::Window(window::Event::Unfocused) => {
+                Some(Message::WindowFocusChanged(false))
+            }
+            iced::Event::Window(window::Event::Resized(size)) => Some(Message::WindowResized(size)),
+            iced::Event::Window(window::Event::CloseRequested) => Some(Message::CloseRequested),
+            iced::Event::Window(window::Event::FileDropped(path)) => {
+                Some(Message::FileDropped(path))
+            }
+            iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. })
+                if modifiers.command() =>
+            {
+                match key.as_ref() {
+                    keyboard::Key::Character("+" | "=") => Some(Message::ZoomIn),
+                    keyboard::Key::Character("-") => Some(Message::ZoomOut),
+                    keyboard::Key::Character("0") => Some(Message::ResetZoom),
+                    _ => None,
+                }
+            }
+            _ => None,
+        });
+
+        let mut subscriptions = vec![window_events];
+        if self.in_tray {
+            // Tray events arrive on a channel of their own, check it a few times a second
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_millis(250)).map(|_| Message::PollTray),
+            );
+        }
+        if crate::utils::automation::is_running() {
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_millis(250))
+                    .map(|_| Message::PollAutomation),
+            );
+        }
+        if crate::utils::single_instance::is_primary() {
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_millis(500))
+                    .map(|_| Message::PollInstance),
+            );
+        }
+        if self.kiosk.as_ref().is_some_and(|kiosk| !kiosk.is_running()) {
+            // Inserted disks are found by scanning for them
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_secs(2)).map(|_| Message::PollKiosk),
+            );
+        }
+        if self
+            .window_alert
+            .as_ref()
+            .is_some_and(|alert| alert.is_blinking())
+        {
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_millis(400))
+                    .map(|_| Message::AlertTick),
+            );
+        }
+        if matches!(self.mode, AppMode::ViewLogs) {
+            // Follow the log while it is shown
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_secs(1))
+                    .map(|_| Message::LogViewer(LogViewerMessage::Refresh)),
+            );
+        }
+        if self.settings_changed {
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_secs(1)).map(|_| Message::SaveSettings),
+            );
+        }
+        Subscription::batch(subscriptions)
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             // App-level messages
@@ -134,18 +320,128 @@ impl GolemGpuImager {
                 ))
             }
 
+            Message::UpdateExistingDevice => {
+                self.mode = AppMode::UpdateExistingDevice;
+                self.update_workflow = Some(UpdateState::new());
+
+                // Golem devices are recognized by the probe that follows the device refresh
+                Task::done(Message::DeviceSelection(
+                    crate::ui::device_selection::DeviceMessage::RefreshDevices,
+                ))
+            }
+
             Message::ManagePresets => {
                 self.mode = AppMode::ManagePresets;
                 self.preset_manager.show_manager = true;
                 Task::none()
             }
 
+            Message::ManageFlashQueue => {
+                self.mode = AppMode::FlashQueue;
+                Task::none()
+            }
+
+            Message::EnqueueFlash => self.enqueue_flash(),
+
+            Message::RunFlashQueue => self.run_flash_queue(),
+
+            Message::StartKiosk => self.start_kiosk(),
+
+            Message::StopKiosk => {
+                if self.kiosk.as_ref().is_some_and(|kiosk| !kiosk.is_running()) {
+                    info!("Duplicator mode stopped");
+                    self.kiosk = None;
+                    self.mode = AppMode::StartScreen;
+                }
+                Task::none()
+            }
+
+            Message::PollKiosk => {
+                if self.is_busy() || self.device_selection.is_refreshing {
+                    Task::none()
+                } else {
+                    Task::done(Message::DeviceSelection(DeviceMessage::RefreshDevices))
+                }
+            }
+
+            Message::KioskEjected(device, result) => {
+                match &result {
+                    Ok(()) => info!("Ejected {}", device),
+                    Err(e) => warn!("{}", e),
+                }
+                if let Some(kiosk) = &mut self.kiosk {
+                    kiosk.ejected(&device, result);
+                }
+                Task::none()
+            }
+
+            Message::OpenFlashMonitor => {
+                self.flash_monitor.open = true;
+                Task::none()
+            }
+
+            Message::CloseFlashMonitor => {
+                self.flash_monitor.open = false;
+                Task::none()
+            }
+
+            Message::SelectMonitorTab(operation) => {
+                self.flash_monitor.selected = operation;
+                Task::none()
+            }
+
+            Message::ManageCache => {
+                self.mode = AppMode::ManageCache;
+                self.cache_manager.refresh(&self.image_repo.cache());
+                Task::none()
+            }
+
+            Message::OpenCrashReport => {
+                let Some(report) = &self.crash_report else {
+                    return Task::none();
+                };
+                if let Err(e) = crate::utils::desktop::open_path(report) {
+                    error!("{:#}", e);
+                    self.error_message = Some(format!("{:#}", e));
+                }
+                Task::none()
+            }
+
+            Message::DismissCrashReport => {
+                self.crash_report = None;
+                if let Err(e) = crate::utils::crash_report::dismiss_pending_report() {
+                    warn!("{:#}", e);
+                }
+                Task::none()
+            }
+
+            Message::ViewLogs => {
+                self.mode = AppMode::ViewLogs;
+                self.log_viewer = LogViewerState::new();
+                self.log_viewer.refresh();
+                Task::none()
+            }
+
+            Message::OpenSettings => {
+                self.mode = AppMode::Settings;
+                self.settings_screen = SettingsState::new();
+                Task::none()
+            }
+
             Message::BackToMainMenu => {
                 self.mode = AppMode::StartScreen;
                 self.flash_workflow = None;
                 self.edit_workflow = None;
+                self.update_workflow = None;
                 self.preset_manager.show_manager = false;
                 self.preset_manager.editor = None;
+                self.cache_manager.confirm_clear = false;
+                if let Some(pending) = self.automation.flash.take() {
+                    self.refuse_pending_flash(
+                        pending,
+                        "The flash was abandoned in the window".to_string(),
+                    );
+                }
                 Task::none()
             }
 
@@ -153,57 +449,592 @@ impl GolemGpuImager {
                 std::process::exit(0);
             }
 
+            Message::CloseRequested => match (self.exit_state, self.running_write()) {
+                (ExitState::Stopping(operation), _) => {
+                    info!("Still waiting for write {} to stop", operation);
+                    Task::none()
+                }
+                (_, Some(operation)) => {
+                    warn!("Window closed during write {}, asking first", operation);
+                    self.exit_state = ExitState::Confirming;
+                    Task::none()
+                }
+                (_, None) => Task::done(Message::Exit),
+            },
+
+            Message::KeepRunning => {
+                self.exit_state = ExitState::Running;
+                Task::none()
+            }
+
+            Message::CancelAndExit => {
+                // The write may have ended while the dialog was open
+                let Some(operation) = self.running_write() else {
+                    return Task::done(Message::Exit);
+                };
+                info!("Cancelling write {} to exit", operation);
+                self.exit_state = ExitState::Stopping(operation);
+                if self
+                    .update_workflow
+                    .as_ref()
+                    .and_then(|state| state.operation)
+                    == Some(operation)
+                {
+                    self.update(Message::Update(UpdateMessage::CancelUpdate))
+                } else {
+                    self.update(Message::Flash(FlashMessage::CancelWrite))
+                }
+            }
+
             Message::ShowError(error) => {
+                // A flash requested through the automation API or the queue was refused
+                if let Some(pending) = self.automation.flash.take() {
+                    self.refuse_pending_flash(pending, error.clone());
+                } else if self.flash_workflow.as_ref().is_some_and(|flash| {
+                    matches!(flash.workflow_state, FlashWorkflowState::Completion(false))
+                }) {
+                    // The duplicator's write stopped before the image was written
+                    let _ = self.finish_kiosk_flash(Err(error.clone()));
+                }
+                // Whatever the command line asked for is left to the user after an error
+                self.preseed = None;
                 self.error_message = Some(error);
                 Task::none()
             }
 
+            Message::HideToTray => match crate::ui::tray::show(&self.tray_tooltip()) {
+                Ok(()) => {
+                    info!("Hiding the window to the system tray");
+                    self.in_tray = true;
+                    window::latest().and_then(|id| window::set_mode(id, window::Mode::Hidden))
+                }
+                Err(e) => {
+                    error!("Failed to show the tray icon: {}", e);
+                    self.error_message = Some(e);
+                    Task::none()
+                }
+            },
+
+            Message::WindowFocusChanged(focused) => {
+                self.window_focused = focused;
+                Task::none()
+            }
+
+            Message::AlertTick => {
+                if let Some(alert) = &mut self.window_alert {
+                    alert.tick();
+                }
+                Task::none()
+            }
+
+            Message::DismissAlert => {
+                self.window_alert = None;
+                Task::none()
+            }
+
+            Message::CheckForAppUpdate(manual) => {
+                if self.app_update.checking {
+                    return Task::none();
+                }
+                self.app_update.checking = true;
+                self.app_update.status = None;
+                Task::perform(
+                    async {
+                        updater::check_for_update()
+                            .await
+                            .map_err(|e| format!("{:#}", e))
+                    },
+                    move |result| Message::AppUpdateChecked(result, manual),
+                )
+            }
+
+            Message::LoadSharedPresets => {
+                let settings = self.settings.shared_presets.clone();
+                if !settings.is_active() {
+                    // Turned off, the presets of the last collection go too
+                    self.preset_manager.shared_status = None;
+                    if let Some(manager) = &mut self.preset_manager_backend {
+                        manager.set_shared_presets(Vec::new());
+                    }
+                    self.preset_manager.set_shared_presets(Vec::new());
+                    return Task::none();
+                }
+                self.preset_manager.shared_status = Some(format!(
+                    "Fetching shared presets from {}",
+                    settings.url.trim()
+                ));
+                Task::perform(
+                    async move {
+                        crate::utils::shared_presets::load(&settings)
+                            .await
+                            .map_err(|e| format!("{:#}", e))
+                    },
+                    Message::SharedPresetsLoaded,
+                )
+            }
+
+            Message::SharedPresetsLoaded(result) => {
+                let shared = match result {
+                    Ok(shared) => shared,
+                    Err(e) => {
+                        // The presets fetched before, if any, stay listed
+                        warn!("Failed to load the shared presets: {}", e);
+                        self.preset_manager.shared_status =
+                            Some(format!("Shared presets unavailable: {}", e));
+                        return Task::none();
+                    }
+                };
+                let url = self.settings.shared_presets.url.trim();
+                self.preset_manager.shared_status = match &shared.offline {
+                    Some(reason) => Some(format!(
+                        "{} shared presets from the cached copy of {}, it couldn't be fetched: {}",
+                        shared.presets.len(),
+                        url,
+                        reason
+                    )),
+                    None => Some(format!(
+                        "{} shared presets from {}",
+                        shared.presets.len(),
+                        url
+                    )),
+                };
+                if let Some(manager) = &mut self.preset_manager_backend {
+                    manager.set_shared_presets(shared.presets.clone());
+                }
+                self.preset_manager.set_shared_presets(shared.presets);
+                Task::none()
+            }
+
+            Message::AppUpdateChecked(result, manual) => {
+                self.app_update.checking = false;
+                match result {
+                    Ok(Some(update)) => {
+                        info!("Version {} is available", update.version);
+                        self.app_update.available = Some(update);
+                    }
+                    Ok(None) => {
+                        debug!("No newer release available");
+                        self.app_update.status =
+                            Some("You are running the latest version".to_string());
+                    }
+                    Err(e) => {
+                        warn!("Failed to check for updates: {}", e);
+                        self.app_update.status =
+                            Some(format!("Failed to check for updates: {}", e));
+                    }
+                }
+                // Automatic checks only show the notice on the start screen
+                if manual {
+                    self.app_update.dialog_open = true;
+                }
+                Task::none()
+            }
+
+            Message::ShowAppUpdate => {
+                self.app_update.dialog_open = true;
+                Task::none()
+            }
+
+            Message::CloseAppUpdate => {
+                self.app_update.dialog_open = false;
+                self.app_update.status = None;
+                Task::none()
+            }
+
+            Message::DismissAppUpdate => {
+                self.app_update.notice_dismissed = true;
+                Task::none()
+            }
+
+            Message::InstallAppUpdate => {
+                let Some(update) = self.app_update.available.clone() else {
+                    return Task::none();
+                };
+                self.app_update.downloading = true;
+                self.app_update.status = None;
+                Task::perform(
+                    async move {
+                        updater::download_installer(&update)
+                            .await
+                            .map_err(|e| format!("{:#}", e))
+                    },
+                    Message::AppUpdateDownloaded,
+                )
+            }
+
+            Message::AppUpdateDownloaded(result) => {
+                self.app_update.downloading = false;
+                let launched = result.and_then(|path| {
+                    updater::launch_installer(&path).map_err(|e| format!("{:#}", e))
+                });
+                match launched {
+                    Ok(()) => {
+                        info!("Update installer started, exiting");
+                        Task::done(Message::Exit)
+                    }
+                    Err(e) => {
+                        error!("Failed to install the update: {}", e);
+                        self.app_update.status =
+                            Some(format!("Failed to install the update: {}", e));
+                        Task::none()
+                    }
+                }
+            }
+
+            Message::OpenReleasePage => {
+                let url = self
+                    .app_update
+                    .available
+                    .as_ref()
+                    .map_or(updater::RELEASES_PAGE_URL, |update| {
+                        update.page_url.as_str()
+                    });
+                if let Err(e) = crate::utils::desktop::open_url(url) {
+                    error!("{:#}", e);
+                    self.app_update.status = Some(format!("{:#}", e));
+                }
+                Task::none()
+            }
+
+            Message::SetCheckForUpdates(enabled) => {
+                self.settings.check_for_updates = enabled;
+                self.settings_changed = true;
+                Task::none()
+            }
+
+            Message::WindowResized(size) => {
+                // Minimized windows report a size of zero on Windows
+                if size.width < 1.0 || size.height < 1.0 {
+                    return Task::none();
+                }
+                self.window_size = size;
+                // Remember the size before the zoom, which is what the window is opened with
+                let scale = self.settings.ui_scale as f32;
+                let window_size = Some(WindowSize {
+                    width: size.width * scale,
+                    height: size.height * scale,
+                });
+                if self.settings.window_size != window_size {
+                    self.settings.window_size = window_size;
+                    self.settings_changed = true;
+                }
+                Task::none()
+            }
+
+            Message::ZoomIn => {
+                self.set_ui_scale(self.settings.ui_scale + app_settings::UI_SCALE_STEP);
+                Task::none()
+            }
+
+            Message::ZoomOut => {
+                self.set_ui_scale(self.settings.ui_scale - app_settings::UI_SCALE_STEP);
+                Task::none()
+            }
+
+            Message::ResetZoom => {
+                self.set_ui_scale(1.0);
+                Task::none()
+            }
+
+            Message::SaveSettings => {
+                self.settings_changed = false;
+                if let Err(e) = self.settings.save() {
+                    warn!("Failed to save application settings: {:#}", e);
+                }
+                Task::none()
+            }
+
+            Message::TelemetryExported(result) => {
+                match result {
+                    Ok(()) => debug!("Sent flash statistics"),
+                    Err(e) => warn!("Failed to send flash statistics: {}", e),
+                }
+                Task::none()
+            }
+
+            Message::PollAutomation => {
+                let calls = crate::utils::automation::take_calls();
+                let tasks: Vec<_> = calls
+                    .into_iter()
+                    .map(|call| self.handle_automation_call(call))
+                    .collect();
+                Task::batch(tasks)
+            }
+
+            Message::PollTray => {
+                if crate::ui::tray::restore_requested() {
+                    Task::done(Message::RestoreFromTray)
+                } else {
+                    Task::none()
+                }
+            }
+
+            Message::PollInstance => Task::batch(
+                crate::utils::single_instance::take_activations()
+                    .into_iter()
+                    .map(|args| Task::done(Message::InstanceActivated(args))),
+            ),
+
+            Message::InstanceActivated(args) => {
+                info!(
+                    "The imager was started again ({:?}), showing this window",
+                    args
+                );
+                let shown = self.update(Message::RestoreFromTray);
+                // A shortcut or script may have started it for a flash
+                let preseed = match crate::utils::preseed::from_args(&args) {
+                    Ok(Some(preseed)) => Task::done(Message::Preseed(preseed)),
+                    Ok(None) => Task::none(),
+                    Err(e) => Task::done(Message::ShowError(e)),
+                };
+                Task::batch([shown, preseed])
+            }
+
+            Message::Preseed(preseed) => {
+                if self.is_busy() || self.flash_queue.running.is_some() || self.kiosk.is_some() {
+                    warn!("Ignoring the command line choices, the imager is busy");
+                    return Task::none();
+                }
+                info!(
+                    "Flash workflow choices from the command line: {:?}",
+                    preseed
+                );
+                // A repository image is selected once the repository is loaded
+                let image = preseed.image.clone();
+                self.preseed = Some(preseed);
+                let open = self.update(Message::FlashNewImage);
+                match image {
+                    Some(path) => Task::batch([
+                        open,
+                        Task::done(Message::Flash(FlashMessage::LocalImageDropped(path))),
+                    ]),
+                    None => open,
+                }
+            }
+
+            Message::FileDropped(path) => {
+                if self.is_busy() || self.flash_queue.running.is_some() || self.kiosk.is_some() {
+                    warn!(
+                        "Ignoring {}, dropped while the imager is busy",
+                        path.display()
+                    );
+                    return Task::none();
+                }
+                info!("Image file dropped onto the window: {}", path.display());
+
+                // An image can still be picked on the first two screens, otherwise start over
+                let picking = self.flash_workflow.as_ref().is_some_and(|flash| {
+                    matches!(
+                        flash.workflow_state,
+                        FlashWorkflowState::SelectOsImage | FlashWorkflowState::SelectTargetDevice
+                    )
+                });
+                let open = if matches!(self.mode, AppMode::FlashNewImage) && picking {
+                    Task::none()
+                } else {
+                    self.update(Message::FlashNewImage)
+                };
+                Task::batch([
+                    open,
+                    Task::done(Message::Flash(FlashMessage::LocalImageDropped(path))),
+                ])
+            }
+
+            Message::RestoreFromTray => {
+                crate::ui::tray::hide();
+                self.in_tray = false;
+                window::latest().and_then(|id| {
+                    Task::batch([
+                        window::set_mode(id, window::Mode::Windowed),
+                        window::gain_focus(id),
+                    ])
+                })
+            }
+
             // Repository management
             Message::RefreshRepoData => self.load_repo_data(),
 
             Message::RepoDataLoaded(images) => {
                 if let Some(flash_state) = &mut self.flash_workflow {
                     flash_state.os_images = images;
+                    flash_state.manifest_status = self.image_repo.manifest_status();
                 }
                 self.is_loading_repo = false;
-                Task::none()
+                self.continue_automation_flash(true, false)
             }
 
             Message::RepoGroupDataLoaded(images, groups) => {
                 if let Some(flash_state) = &mut self.flash_workflow {
                     flash_state.os_images = images;
                     flash_state.os_image_groups = groups;
+                    flash_state.manifest_status = self.image_repo.manifest_status();
                 }
                 self.is_loading_repo = false;
-                Task::none()
+                Task::batch([
+                    self.continue_automation_flash(true, false),
+                    self.continue_preseed(false, true),
+                ])
             }
 
             Message::RepoLoadFailed => {
                 self.is_loading_repo = false;
                 self.error_message = Some("Failed to load repository data".to_string());
+                if let Some(pending) = self.automation.flash.take() {
+                    self.refuse_pending_flash(
+                        pending,
+                        "Failed to load repository data".to_string(),
+                    );
+                }
                 Task::none()
             }
 
             // Delegate module-specific messages
             Message::Flash(flash_msg) => {
-                if let Some(flash_state) = &mut self.flash_workflow {
-                    crate::ui::flash_workflow::handler::handle_message(
-                        flash_state,
-                        &self.image_repo,
-                        &self.device_selection,
-                        &self.configuration,
+                if self
+                    .flash_workflow
+                    .as_ref()
+                    .is_some_and(|flash_state| !flash_state.is_current(&flash_msg))
+                {
+                    debug!("Dropping a message of an earlier write: {:?}", flash_msg);
+                    return Task::none();
+                }
+                if self.in_tray {
+                    self.update_tray(&flash_msg);
+                }
+                if self.in_tray || !self.window_focused {
+                    crate::ui::notifications::notify_flash_result(&flash_msg);
+                }
+                if let Some(event) = crate::ui::automation::flash_event(&flash_msg) {
+                    crate::utils::automation::publish(event);
+                }
+                if let Some(flash_state) = &self.flash_workflow {
+                    self.flash_monitor.record(&flash_msg, flash_state);
+                }
+                let layout_loaded = matches!(flash_msg, FlashMessage::TargetLayoutLoaded(_));
+                let outcome = crate::ui::alerts::flash_outcome(&flash_msg);
+                let write_ended = flash_msg.operation().filter(|_| {
+                    matches!(
                         flash_msg,
+                        FlashMessage::WriteImageCompleted(..) | FlashMessage::WriteImageFailed(..)
                     )
-                } else {
-                    Task::none()
-                }
-            }
+                });
+                let queue_status = match &flash_msg {
+                    FlashMessage::WriteImageCompleted(_, verified) => Some(JobStatus::Succeeded {
+                        verified: *verified,
+                    }),
+                    FlashMessage::WriteImageFailed(_, error) => {
+                        Some(JobStatus::Failed(error.clone()))
+                    }
+                    _ => None,
+                };
+                let kiosk_result = match &queue_status {
+                    Some(JobStatus::Succeeded { verified }) => Some(Ok(*verified)),
+                    Some(JobStatus::Failed(error)) => Some(Err(error.clone())),
+                    _ => None,
+                };
+                if let Some(flash_state) = &mut self.flash_workflow {
+                    let metrics = flash_state.flash_metrics(&flash_msg);
+                    // Every write wears the card, whether it succeeded or not
+                    let serial = flash_state
+                        .flash_report
+                        .as_ref()
+                        .and_then(|report| report.device_serial.as_deref());
+                    let recorded = match (&metrics, serial) {
+                        (Some(metrics), Some(serial)) => self.flash_history.record(
+                            serial,
+                            metrics.bytes_written,
+                            metrics.device_size,
+                            metrics.finished_at,
+                            matches!(metrics.outcome, FlashOutcome::Succeeded)
+                                .then(|| metrics.throughput() as u64),
+                        ),
+                        _ => Ok(()),
+                    };
+                    if let Err(e) = recorded {
+                        error!("Failed to save the flash history: {:#}", e);
+                    }
+                    if matches!(flash_msg, FlashMessage::WriteImageCompleted(..)) {
+                        let recent = flash_state.selected_image().and_then(|image| {
+                            let url = self
+                                .image_repo
+                                .find_version(&image.name, &image.version)
+                                .map(|version| self.image_repo.get_image_url(&version));
+                            image.to_recent(url, chrono::Utc::now().timestamp())
+                        });
+                        if let Some(recent) = recent {
+                            crate::utils::recent_images::remember(
+                                &mut self.settings.recent_images,
+                                recent,
+                            );
+                            self.settings_changed = true;
+                        }
+                    }
+                    let telemetry = match metrics {
+                        Some(metrics) if self.settings.telemetry.is_active() => Task::perform(
+                            crate::utils::telemetry::export(
+                                self.settings.telemetry.clone(),
+                                metrics,
+                            ),
+                            |result| {
+                                Message::TelemetryExported(result.map_err(|e| format!("{:#}", e)))
+                            },
+                        ),
+                        _ => Task::none(),
+                    };
+
+                    // A write whose configuration used {index} takes that number
+                    if matches!(flash_msg, FlashMessage::WriteImageCompleted(..))
+                        && flash_state.template_index_used
+                    {
+                        if let Some(manager) = &mut self.preset_manager_backend {
+                            if let Err(e) = manager.advance_template_index() {
+                                error!("Failed to save the template counter: {}", e);
+                            }
+                            flash_state.template_index = manager.template_index();
+                        } else {
+                            flash_state.template_index += 1;
+                        }
+                        flash_state.template_index_used = false;
+                    }
+
+                    let handled = crate::ui::flash_workflow::handler::handle_message(
+                        flash_state,
+                        &self.image_repo,
+                        &self.device_selection,
+                        &self.configuration,
+                        &self.settings,
+                        flash_msg,
+                    );
+                    // Nothing else is started once the write being waited for has stopped
+                    if self.exits_after(write_ended) {
+                        info!("Write stopped, exiting");
+                        return handled.chain(Task::done(Message::Exit));
+                    }
+                    if let Some(outcome) = outcome {
+                        self.raise_alert(outcome);
+                    }
+                    let automation = self.advance_automation_flash(layout_loaded);
+                    let preseed = self.continue_preseed(false, false);
+                    let queue = match queue_status {
+                        Some(status) => self.finish_queued_flash(status),
+                        None => Task::none(),
+                    };
+                    let kiosk = match kiosk_result {
+                        Some(result) => self.finish_kiosk_flash(result),
+                        None => Task::none(),
+                    };
+                    Task::batch([handled, telemetry, automation, preseed, queue, kiosk])
+                } else {
+                    Task::none()
+                }
+            }
 
             Message::Edit(edit_msg) => {
                 if let Some(edit_state) = &mut self.edit_workflow {
                     crate::ui::edit_workflow::handler::handle_message(
                         edit_state,
                         &self.device_selection,
+                        &self.configuration,
                         edit_msg,
                     )
                 } else {
@@ -211,6 +1042,49 @@ impl GolemGpuImager {
                 }
             }
 
+            Message::Update(update_msg) => {
+                if self
+                    .update_workflow
+                    .as_ref()
+                    .is_some_and(|update_state| !update_state.is_current(&update_msg))
+                {
+                    debug!(
+                        "Dropping a message of an earlier device update: {:?}",
+                        update_msg
+                    );
+                    return Task::none();
+                }
+                if !self.window_focused {
+                    crate::ui::notifications::notify_update_result(&update_msg);
+                }
+                let outcome = crate::ui::alerts::update_outcome(&update_msg);
+                let update_ended = update_msg.operation().filter(|_| {
+                    matches!(
+                        update_msg,
+                        UpdateMessage::UpdateCompleted(_) | UpdateMessage::UpdateFailed(..)
+                    )
+                });
+                if let Some(update_state) = &mut self.update_workflow {
+                    let handled = crate::ui::update_workflow::handler::handle_message(
+                        update_state,
+                        &self.device_selection,
+                        &self.image_repo,
+                        &self.settings,
+                        update_msg,
+                    );
+                    if self.exits_after(update_ended) {
+                        info!("Update stopped, exiting");
+                        return handled.chain(Task::done(Message::Exit));
+                    }
+                    if let Some(outcome) = outcome {
+                        self.raise_alert(outcome);
+                    }
+                    handled
+                } else {
+                    Task::none()
+                }
+            }
+
             Message::PresetManager(preset_msg) => {
                 crate::ui::preset_manager::handler::handle_message(
                     &mut self.preset_manager,
@@ -219,148 +1093,969 @@ impl GolemGpuImager {
                 )
             }
 
+            Message::FlashQueue(queue_msg) => {
+                crate::ui::flash_queue::handler::handle_message(&mut self.flash_queue, queue_msg)
+            }
+
+            Message::CacheManager(cache_msg) => crate::ui::cache_manager::handler::handle_message(
+                &mut self.cache_manager,
+                &self.image_repo,
+                cache_msg,
+            ),
+
+            Message::LogViewer(log_msg) => crate::ui::log_viewer::handler::handle_message(
+                &mut self.log_viewer,
+                &self.elevation_status,
+                log_msg,
+            ),
+
+            Message::Settings(settings_msg) => {
+                let previous = self.settings.clone();
+                let task = crate::ui::settings::handler::handle_message(
+                    &mut self.settings_screen,
+                    &mut self.settings,
+                    settings_msg,
+                );
+                // Saved after a short delay, so typing a URL doesn't write on every key
+                if self.settings != previous {
+                    self.settings_changed = true;
+                }
+                if self.settings.device_nicknames != previous.device_nicknames {
+                    self.device_selection
+                        .apply_nicknames(&self.settings.device_nicknames);
+                }
+                task
+            }
+
             Message::DeviceSelection(device_msg) => {
-                crate::ui::device_selection::handler::handle_message(
+                let scan_result = match &device_msg {
+                    DeviceMessage::DevicesLoaded(_) => Some(Ok(())),
+                    DeviceMessage::DeviceLoadFailed(error) => Some(Err(error.clone())),
+                    _ => None,
+                };
+                let task = crate::ui::device_selection::handler::handle_message(
                     &mut self.device_selection,
                     device_msg,
+                );
+                let Some(scan_result) = scan_result else {
+                    return task;
+                };
+                self.device_selection
+                    .apply_nicknames(&self.settings.device_nicknames);
+
+                // Answer the automation requests that waited for the scan
+                let disks = scan_result
+                    .map(|()| crate::ui::automation::disk_list(&self.device_selection.devices));
+                for call in self.automation.disk_lists.drain(..) {
+                    call.respond(disks.clone());
+                }
+                match disks {
+                    Ok(_) => Task::batch([
+                        task,
+                        self.continue_automation_flash(false, true),
+                        self.kiosk_scan(),
+                        self.continue_preseed(true, false),
+                    ]),
+                    Err(error) => {
+                        if let Some(pending) = self.automation.flash.take() {
+                            self.refuse_pending_flash(pending, error);
+                        }
+                        task
+                    }
+                }
+            }
+
+            Message::Configuration(config_msg) => {
+                crate::ui::configuration::handler::handle_message(
+                    &mut self.configuration,
+                    &self.preset_manager.presets,
+                    config_msg,
                 )
             }
 
-            Message::Configuration(config_msg) => {
-                crate::ui::configuration::handler::handle_message(
-                    &mut self.configuration,
-                    &self.preset_manager.presets,
-                    config_msg,
-                )
+            // Elevation management
+            Message::RequestElevation => {
+                #[cfg(windows)]
+                {
+                    if let Err(e) = crate::utils::request_elevation() {
+                        self.error_message = Some(format!("Failed to request elevation: {}", e));
+                        error!("Failed to request elevation: {}", e);
+                    }
+                }
+                #[cfg(not(windows))]
+                {
+                    self.error_message = Some("Elevation request is only supported on Windows. Please run with sudo, or make sure the UDisks2 service is running.".to_string());
+                }
+                Task::none()
+            }
+
+            Message::CheckElevationStatus => {
+                self.elevation_status = crate::utils::get_elevation_status();
+                self.privilege_mode = crate::utils::privilege_mode();
+                Task::none()
+            }
+
+            Message::StartPrivilegedHelper => {
+                self.error_message = None;
+                Task::perform(
+                    crate::utils::privileged_helper::start(),
+                    Message::PrivilegedHelperStarted,
+                )
+            }
+
+            Message::PrivilegedHelperStarted(result) => {
+                if let Err(e) = result {
+                    error!("Failed to start privileged helper: {}", e);
+                    self.error_message = Some(e);
+                }
+                Task::done(Message::CheckElevationStatus)
+            }
+
+            // Preset management messages
+            Message::SaveAsPreset(preset) => {
+                // Forward to preset manager with the provided configuration
+                crate::ui::preset_manager::handler::handle_message(
+                    &mut self.preset_manager,
+                    &mut self.preset_manager_backend,
+                    crate::ui::preset_manager::PresetManagerMessage::SaveAsPreset(preset),
+                )
+            }
+
+            Message::SelectPreset(index) => {
+                // Apply preset configuration to current workflow
+                if let Some(preset) = self.preset_manager.presets.get(index) {
+                    self.preset_manager.selected_preset = Some(index);
+
+                    // Apply preset to the central configuration if flash workflow is active
+                    if let Some(flash_state) = &mut self.flash_workflow {
+                        if let FlashWorkflowState::ConfigureSettings = &flash_state.workflow_state {
+                            self.configuration =
+                                crate::ui::configuration::ConfigurationState::from_preset(preset);
+                            self.configuration.selected_preset = Some(index);
+                        }
+                    }
+
+                    // Apply preset to edit workflow if active
+                    if let Some(edit_state) = &mut self.edit_workflow {
+                        if let EditWorkflowState::EditConfiguration = &edit_state.workflow_state {
+                            self.configuration =
+                                crate::ui::configuration::ConfigurationState::from_preset(preset);
+                            self.configuration.selected_preset = Some(index);
+                        }
+                    }
+                }
+                Task::none()
+            }
+
+            Message::InitializeFlashConfiguration => {
+                if let Some(flash_state) = &mut self.flash_workflow {
+                    // A preset assigned to the target device wins over the default preset
+                    let assigned_preset = flash_state.selected_target.as_ref().and_then(|device| {
+                        let assignment = crate::utils::device_assignment::find_assignment(
+                            &self.preset_manager.assignments,
+                            &device.assignment_info(),
+                        )?;
+                        info!(
+                            "Device {} matches {}, selecting preset {}",
+                            device.name,
+                            assignment.describe(),
+                            assignment.preset
+                        );
+                        self.preset_manager
+                            .presets
+                            .iter()
+                            .position(|p| p.name == assignment.preset)
+                    });
+
+                    // Initialize the central configuration state with the default preset if available
+                    if let Some(selected_index) =
+                        assigned_preset.or(self.preset_manager.selected_preset)
+                    {
+                        if let Some(preset) = self.preset_manager.presets.get(selected_index) {
+                            self.configuration =
+                                crate::ui::configuration::ConfigurationState::from_preset(preset);
+                            self.configuration.selected_preset = Some(selected_index);
+                        } else {
+                            // Fallback to defaults if preset not found
+                            self.configuration =
+                                crate::ui::configuration::ConfigurationState::new();
+                        }
+                    } else {
+                        // No default preset selected, use hardcoded defaults
+                        self.configuration = crate::ui::configuration::ConfigurationState::new();
+                    }
+
+                    if let Some(manager) = &self.preset_manager_backend {
+                        flash_state.template_index = manager.template_index();
+                    }
+
+                    // Set the workflow state to configuration
+                    flash_state.workflow_state = FlashWorkflowState::ConfigureSettings;
+                }
+
+                // A preset named on the command line wins over both
+                match self.preseed.take().and_then(|preseed| preseed.preset) {
+                    Some(name) => match self.select_preset(&name) {
+                        Ok(()) => Task::none(),
+                        Err(e) => Task::done(Message::ShowError(e)),
+                    },
+                    None => Task::none(),
+                }
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        let mut screen = if self.flash_monitor.open {
+            crate::ui::flash_monitor::view_flash_monitor(&self.flash_monitor)
+        } else {
+            self.view_screen()
+        };
+        if let Some(alert) = &self.window_alert {
+            screen =
+                iced::widget::stack![screen, crate::ui::alerts::view_window_alert(alert)].into();
+        }
+        if self.exit_state == ExitState::Running {
+            screen
+        } else {
+            iced::widget::stack![
+                screen,
+                crate::ui::shutdown::view_exit_dialog(self.exit_state)
+            ]
+            .into()
+        }
+    }
+
+    /// The screen of the current mode
+    fn view_screen(&self) -> Element<Message> {
+        match &self.mode {
+            AppMode::StartScreen => {
+                let start_screen = crate::ui::start_screen::view_start_screen(
+                    self.error_message.as_deref(),
+                    self.privilege_mode,
+                    &self.elevation_status,
+                    self.crash_report.as_deref(),
+                    self.app_update.notice(),
+                    self.flash_queue.queued(),
+                    self.window_size,
+                );
+                if self.app_update.dialog_open {
+                    iced::widget::stack![
+                        start_screen,
+                        crate::ui::app_update::view_update_dialog(
+                            &self.app_update,
+                            self.settings.check_for_updates,
+                        )
+                    ]
+                    .into()
+                } else {
+                    start_screen
+                }
+            }
+            AppMode::FlashNewImage => {
+                if let Some(flash_state) = &self.flash_workflow {
+                    let flash = crate::ui::flash_workflow::view(
+                        flash_state,
+                        &self.device_selection,
+                        &self.configuration,
+                        &self.preset_manager,
+                        &self.flash_history,
+                        self.is_loading_repo,
+                        self.window_size,
+                        self.settings.typed_confirmation,
+                        &self.settings.target_profile,
+                        &self.settings.recent_images,
+                    );
+                    // A queued flash has the others of the batch to look up in the monitor
+                    if self.flash_queue.running.is_some() {
+                        iced::widget::column![
+                            crate::ui::flash_monitor::view_batch_bar(
+                                &self.flash_monitor,
+                                self.flash_queue.queued()
+                            ),
+                            flash
+                        ]
+                        .into()
+                    } else {
+                        flash
+                    }
+                } else {
+                    crate::ui::start_screen::view_start_screen(
+                        self.error_message.as_deref(),
+                        self.privilege_mode,
+                        &self.elevation_status,
+                        self.crash_report.as_deref(),
+                        self.app_update.notice(),
+                        self.flash_queue.queued(),
+                        self.window_size,
+                    )
+                }
+            }
+            AppMode::EditExistingDisk => {
+                if let Some(edit_state) = &self.edit_workflow {
+                    crate::ui::edit_workflow::view(
+                        edit_state,
+                        &self.device_selection,
+                        &self.configuration,
+                        &self.preset_manager,
+                        self.window_size,
+                    )
+                } else {
+                    crate::ui::start_screen::view_start_screen(
+                        self.error_message.as_deref(),
+                        self.privilege_mode,
+                        &self.elevation_status,
+                        self.crash_report.as_deref(),
+                        self.app_update.notice(),
+                        self.flash_queue.queued(),
+                        self.window_size,
+                    )
+                }
+            }
+            AppMode::UpdateExistingDevice => {
+                if let Some(update_state) = &self.update_workflow {
+                    crate::ui::update_workflow::view(update_state, &self.device_selection)
+                } else {
+                    crate::ui::start_screen::view_start_screen(
+                        self.error_message.as_deref(),
+                        self.privilege_mode,
+                        &self.elevation_status,
+                        self.crash_report.as_deref(),
+                        self.app_update.notice(),
+                        self.flash_queue.queued(),
+                        self.window_size,
+                    )
+                }
+            }
+            AppMode::ManagePresets => {
+                crate::ui::preset_manager::view(&self.preset_manager, self.window_size)
+                    .map(Message::PresetManager)
+            }
+            AppMode::ManageCache => {
+                crate::ui::cache_manager::view(&self.cache_manager).map(Message::CacheManager)
+            }
+            AppMode::FlashQueue => crate::ui::flash_queue::view(&self.flash_queue, self.is_busy())
+                .map(Message::FlashQueue),
+            AppMode::Kiosk => {
+                if let Some(kiosk) = &self.kiosk {
+                    crate::ui::kiosk::view_kiosk(kiosk, self.flash_workflow.as_ref())
+                } else {
+                    crate::ui::start_screen::view_start_screen(
+                        self.error_message.as_deref(),
+                        self.privilege_mode,
+                        &self.elevation_status,
+                        self.crash_report.as_deref(),
+                        self.app_update.notice(),
+                        self.flash_queue.queued(),
+                        self.window_size,
+                    )
+                }
+            }
+            AppMode::ViewLogs => {
+                crate::ui::log_viewer::view(&self.log_viewer).map(Message::LogViewer)
+            }
+            AppMode::Settings => crate::ui::settings::view(&self.settings_screen, &self.settings)
+                .map(Message::Settings),
+        }
+    }
+
+    /// Change the zoom of the user interface; the window keeps its size on screen
+    fn set_ui_scale(&mut self, scale: f64) {
+        let scale = app_settings::clamp_ui_scale(scale);
+        if scale == self.settings.ui_scale {
+            return;
+        }
+        let ratio = (self.settings.ui_scale / scale) as f32;
+        self.window_size = Size::new(
+            self.window_size.width * ratio,
+            self.window_size.height * ratio,
+        );
+        self.settings.ui_scale = scale;
+        self.settings_changed = true;
+        debug!("User interface zoom set to {:.0}%", scale * 100.0);
+    }
+
+    /// Tooltip for the tray icon, with the progress of the running flash
+    fn tray_tooltip(&self) -> String {
+        match self
+            .flash_workflow
+            .as_ref()
+            .map(|state| &state.workflow_state)
+        {
+            Some(FlashWorkflowState::Flashing(phase)) => crate::ui::tray::progress_tooltip(phase),
+            Some(FlashWorkflowState::ClearingPartitions { .. }) => {
+                "Golem GPU Imager: preparing".to_string()
+            }
+            _ => "Golem GPU Imager".to_string(),
+        }
+    }
+
+    /// Keep the tray tooltip current while the flash runs in the background
+    fn update_tray(&self, message: &FlashMessage) {
+        use crate::ui::tray;
+
+        match message {
+            FlashMessage::Progress(_, phase) => tray::set_tooltip(&tray::progress_tooltip(phase)),
+            FlashMessage::WriteImageCompleted(..) => {
+                tray::set_tooltip("Golem GPU Imager: flash completed")
+            }
+            FlashMessage::WriteImageFailed(..) => {
+                tray::set_tooltip("Golem GPU Imager: flash failed")
+            }
+            _ => {}
+        }
+    }
+
+    /// Answer a request of the automation API, or keep it until the window can
+    fn handle_automation_call(&mut self, call: Call) -> Task<Message> {
+        match call.command.clone() {
+            Command::ListDisks => {
+                // Answered with a fresh scan, so newly inserted cards show up
+                self.automation.disk_lists.push(call);
+                if self.device_selection.is_refreshing {
+                    Task::none()
+                } else {
+                    Task::done(Message::DeviceSelection(DeviceMessage::RefreshDevices))
+                }
+            }
+            Command::Status => {
+                call.respond(Ok(crate::ui::automation::flash_status(
+                    self.flash_workflow.as_ref(),
+                )));
+                Task::none()
+            }
+            Command::Cancel => {
+                let pending = self.automation.flash.take();
+                if let Some(pending) = &pending {
+                    info!("Automation API cancelled {:?}", pending.request);
+                }
+                let running = self.flash_workflow.as_ref().is_some_and(|flash| {
+                    matches!(
+                        flash.workflow_state,
+                        FlashWorkflowState::ClearingPartitions { .. }
+                            | FlashWorkflowState::Flashing(_)
+                    )
+                });
+                if let Some(pending) = pending {
+                    self.refuse_pending_flash(
+                        pending,
+                        "Cancelled before the flash started".to_string(),
+                    );
+                } else if !running {
+                    call.respond::<()>(Err("No flash is running".to_string()));
+                    return Task::none();
+                }
+                call.respond(Ok(serde_json::json!({ "cancelled": true })));
+                if running {
+                    Task::done(Message::Flash(FlashMessage::CancelWrite))
+                } else {
+                    Task::none()
+                }
+            }
+            Command::StartFlash(request) => self.start_automation_flash(request, call),
+        }
+    }
+
+    /// Open the flash workflow for a `start_flash` request
+    ///
+    /// The request is answered once the write has started, or with the error that stopped it.
+    fn start_automation_flash(&mut self, request: StartFlash, call: Call) -> Task<Message> {
+        if self.is_busy() || self.flash_queue.running.is_some() {
+            call.respond::<()>(Err(
+                "The imager is busy, try again when the current operation has finished".to_string(),
+            ));
+            return Task::none();
+        }
+        if self.kiosk.is_some() {
+            call.respond::<()>(Err(
+                "The imager is in duplicator mode, stop it to flash through the API".to_string(),
+            ));
+            return Task::none();
+        }
+
+        info!("Automation API requested a flash: {:?}", request);
+        self.start_pending_flash(request, FlashOrigin::Api(call))
+    }
+
+    /// The flash or device update that is writing to a disk, if one is
+    fn running_write(&self) -> Option<OperationId> {
+        let flashing = self.flash_workflow.as_ref().filter(|flash| {
+            matches!(
+                flash.workflow_state,
+                FlashWorkflowState::ClearingPartitions { .. } | FlashWorkflowState::Flashing(_)
+            )
+        });
+        let updating = self
+            .update_workflow
+            .as_ref()
+            .filter(|update| matches!(update.workflow_state, UpdateWorkflowState::Updating { .. }));
+        flashing
+            .and_then(|flash| flash.operation)
+            .or_else(|| updating.and_then(|update| update.operation))
+    }
+
+    /// Whether the application exits once the write `ended` has stopped
+    fn exits_after(&self, ended: Option<OperationId>) -> bool {
+        matches!(self.exit_state, ExitState::Stopping(operation) if ended == Some(operation))
+    }
+
+    /// Whether a flash, download or device operation is in progress
+    fn is_busy(&self) -> bool {
+        self.automation.flash.is_some()
+            || matches!(
+                self.mode,
+                AppMode::EditExistingDisk | AppMode::UpdateExistingDevice
+            )
+            || self.flash_workflow.as_ref().is_some_and(|flash| {
+                matches!(
+                    flash.workflow_state,
+                    FlashWorkflowState::ProcessingImage { .. }
+                        | FlashWorkflowState::ClearingPartitions { .. }
+                        | FlashWorkflowState::Flashing(_)
+                )
+            })
+    }
+
+    /// Announce the end of a flash or device update as the settings ask for
+    fn raise_alert(&mut self, outcome: Outcome) {
+        let alerts = self.settings.alerts;
+        if alerts.sound {
+            crate::ui::alerts::play(outcome);
+        }
+        if alerts.flash_window {
+            self.window_alert = Some(WindowAlert::new(outcome));
+        }
+    }
+
+    /// Walk the flash workflow through the steps of `request`
+    fn start_pending_flash(&mut self, request: StartFlash, origin: FlashOrigin) -> Task<Message> {
+        self.automation.flash = Some(PendingFlash {
+            request,
+            origin,
+            stage: PendingStage::Loading {
+                images: false,
+                devices: false,
+            },
+        });
+        // Loads the image list and scans the devices, as from the start screen
+        self.update(Message::FlashNewImage)
+    }
+
+    /// Tell whoever asked for a pending flash why it didn't start
+    fn refuse_pending_flash(&mut self, pending: PendingFlash, error: String) {
+        match pending.origin {
+            FlashOrigin::Api(call) => call.respond::<()>(Err(error)),
+            FlashOrigin::Queue(id) => {
+                warn!("Queued flash {} didn't start: {}", id, error);
+                self.flash_queue.finish(id, JobStatus::Failed(error));
+                // Back to the queue, unless the user left the flash workflow
+                if matches!(self.mode, AppMode::FlashNewImage) {
+                    self.mode = AppMode::FlashQueue;
+                    self.flash_workflow = None;
+                }
+            }
+            FlashOrigin::Kiosk => {
+                let _ = self.finish_kiosk_flash(Err(error));
+            }
+            FlashOrigin::CommandLine => {
+                warn!("The flash from the command line didn't start: {}", error);
+            }
+        }
+    }
+
+    /// The image, device and preset confirmed in the flash workflow, with the device's name
+    ///
+    /// Requests find their image in the repository when they run, so a local image is
+    /// refused with an error saying it can't be `used`.
+    fn confirmed_request(&self, used: &str) -> Result<(StartFlash, String), String> {
+        let (Some(image), Some(device)) = (
+            self.flash_workflow
+                .as_ref()
+                .and_then(|flash_state| flash_state.selected_image()),
+            self.flash_workflow
+                .as_ref()
+                .and_then(|flash_state| flash_state.selected_target.as_ref()),
+        ) else {
+            return Err("Select an image and a disk first".to_string());
+        };
+        if image.local {
+            return Err(format!("Only repository images can be {}", used));
+        }
+
+        let request = StartFlash {
+            device: device.path.clone(),
+            channel: Some(image.name.clone()),
+            version: Some(image.version.clone()),
+            preset: self
+                .configuration
+                .selected_preset
+                .and_then(|index| self.preset_manager.presets.get(index))
+                .map(|preset| preset.name.clone()),
+        };
+        Ok((request, device.display_name()))
+    }
+
+    /// Queue the image, device and preset confirmed in the flash workflow
+    fn enqueue_flash(&mut self) -> Task<Message> {
+        let (request, device_name) = match self.confirmed_request("queued") {
+            Ok(confirmed) => confirmed,
+            Err(error) => return Task::done(Message::ShowError(error)),
+        };
+        info!("Queued a flash: {:?}", request);
+        self.flash_queue.push(request, device_name);
+        self.flash_workflow = None;
+        self.mode = AppMode::FlashQueue;
+        Task::none()
+    }
+
+    /// Flash the image and preset confirmed in the flash workflow to the confirmed disk,
+    /// then to every disk inserted
+    fn start_kiosk(&mut self) -> Task<Message> {
+        let (request, _) = match self.confirmed_request("duplicated") {
+            Ok(confirmed) => confirmed,
+            Err(error) => return Task::done(Message::ShowError(error)),
+        };
+        if let Err(error) = crate::ui::kiosk::check_rules(&self.settings.device_rules) {
+            return Task::done(Message::ShowError(error));
+        }
+
+        info!("Duplicating to every inserted disk: {:?}", request);
+        self.kiosk = Some(KioskState::new(
+            request.clone(),
+            &self.device_selection.devices,
+        ));
+        let task = self.start_pending_flash(request, FlashOrigin::Kiosk);
+        self.mode = AppMode::Kiosk;
+        task
+    }
+
+    /// Flash the disk the duplicator finds in the device scan, if a new one was inserted
+    ///
+    /// Scans while a disk is flashed are left alone; the next one after it finds the disks
+    /// inserted in the meantime.
+    fn kiosk_scan(&mut self) -> Task<Message> {
+        let idle = !self.is_busy() && self.flash_queue.running.is_none();
+        let Some(kiosk) = self
+            .kiosk
+            .as_mut()
+            .filter(|kiosk| idle && !kiosk.is_running())
+        else {
+            return Task::none();
+        };
+        let Some(device) = kiosk.take_inserted(&self.device_selection.devices) else {
+            return Task::none();
+        };
+
+        info!("Duplicating to {} ({})", device.name, device.path);
+        let request = kiosk.start(&device.path);
+        let task = self.start_pending_flash(request, FlashOrigin::Kiosk);
+        self.mode = AppMode::Kiosk;
+        task
+    }
+
+    /// Record how the duplicator's write ended, ejecting the disk once it is written
+    fn finish_kiosk_flash(&mut self, result: Result<bool, String>) -> Task<Message> {
+        let Some(kiosk) = self
+            .kiosk
+            .as_mut()
+            .filter(|kiosk| kiosk.flashing().is_some())
+        else {
+            return Task::none();
+        };
+        if let Err(error) = &result {
+            warn!("Duplicating to {:?} failed: {}", kiosk.flashing(), error);
+        }
+        let eject = kiosk.written(result);
+        self.flash_workflow = None;
+        self.mode = AppMode::Kiosk;
+
+        let Some(device) = eject else {
+            return Task::none();
+        };
+        let path = device.clone();
+        Task::perform(
+            async move {
+                crate::disk::eject::eject(&path)
+                    .await
+                    .map_err(|e| format!("{:#}", e))
+            },
+            move |result| Message::KioskEjected(device, result),
+        )
+    }
+
+    /// Start the next queued flash
+    fn run_flash_queue(&mut self) -> Task<Message> {
+        if self.is_busy() {
+            return Task::done(Message::ShowError(
+                "The imager is busy, try again when the current operation has finished".to_string(),
+            ));
+        }
+        match self.flash_queue.start_next() {
+            Some((id, request)) => {
+                info!("Starting queued flash {}: {:?}", id, request);
+                self.start_pending_flash(request, FlashOrigin::Queue(id))
             }
-
-            // Elevation management
-            Message::RequestElevation => {
-                #[cfg(windows)]
-                {
-                    if let Err(e) = crate::utils::request_elevation() {
-                        self.error_message = Some(format!("Failed to request elevation: {}", e));
-                        error!("Failed to request elevation: {}", e);
-                    }
-                }
-                #[cfg(not(windows))]
-                {
-                    self.error_message = Some("Elevation request is only supported on Windows. Please run with sudo on Unix systems.".to_string());
-                }
+            None => {
+                self.mode = AppMode::FlashQueue;
                 Task::none()
             }
+        }
+    }
 
-            Message::CheckElevationStatus => {
-                self.elevation_status = crate::utils::get_elevation_status();
-                self.is_elevated = crate::utils::is_elevated();
+    /// Record the end of a queued flash and go on with the next one if it can start
+    fn finish_queued_flash(&mut self, status: JobStatus) -> Task<Message> {
+        let Some(id) = self.flash_queue.running else {
+            return Task::none();
+        };
+        info!("Queued flash {} ended: {:?}", id, status);
+        if self.flash_queue.finish(id, status) {
+            Task::done(Message::RunFlashQueue)
+        } else {
+            self.mode = AppMode::FlashQueue;
+            self.flash_workflow = None;
+            Task::none()
+        }
+    }
+
+    /// Select the requested image and device once both lists have loaded
+    fn continue_automation_flash(
+        &mut self,
+        images_loaded: bool,
+        devices_loaded: bool,
+    ) -> Task<Message> {
+        let Some(pending) = &mut self.automation.flash else {
+            return Task::none();
+        };
+        let PendingStage::Loading { images, devices } = &mut pending.stage else {
+            return Task::none();
+        };
+        *images |= images_loaded;
+        *devices |= devices_loaded;
+        if !(*images && *devices) {
+            return Task::none();
+        }
+
+        let Some(mut pending) = self.automation.flash.take() else {
+            return Task::none();
+        };
+        match self.select_automation_flash(&pending.request) {
+            Ok(()) => {
+                // The layout tells whether the configuration has to be backed up first
+                pending.stage = PendingStage::ReadingLayout;
+                self.automation.flash = Some(pending);
+                Task::done(Message::Flash(FlashMessage::ConfirmWrite))
+            }
+            Err(e) => {
+                warn!("Cannot start the requested flash: {}", e);
+                self.refuse_pending_flash(pending, e);
                 Task::none()
             }
+        }
+    }
 
-            // Preset management messages
-            Message::SaveAsPreset(preset) => {
-                // Forward to preset manager with the provided configuration
-                crate::ui::preset_manager::handler::handle_message(
-                    &mut self.preset_manager,
-                    &mut self.preset_manager_backend,
-                    crate::ui::preset_manager::PresetManagerMessage::SaveAsPreset(preset),
-                )
-            }
+    /// Select the image, device and preset of a `start_flash` request in the flash workflow
+    fn select_automation_flash(&mut self, request: &StartFlash) -> Result<(), String> {
+        let Some(flash_state) = &mut self.flash_workflow else {
+            return Err("The flash workflow was closed".to_string());
+        };
+        let (group_idx, version_idx) =
+            crate::ui::automation::find_image(&flash_state.os_image_groups, request)?;
+        let device_idx =
+            crate::ui::automation::find_device(&self.device_selection.devices, &request.device)?;
 
-            Message::SelectPreset(index) => {
-                // Apply preset configuration to current workflow
-                if let Some(preset) = self.preset_manager.presets.get(index) {
-                    self.preset_manager.selected_preset = Some(index);
+        flash_state.select_repository_image(group_idx, version_idx);
+        flash_state.selected_device = Some(device_idx);
+        flash_state.selected_target = Some(self.device_selection.devices[device_idx].clone());
 
-                    // Apply preset to the central configuration if flash workflow is active
-                    if let Some(flash_state) = &mut self.flash_workflow {
-                        if let FlashWorkflowState::ConfigureSettings = &flash_state.workflow_state {
-                            self.configuration =
-                                crate::ui::configuration::ConfigurationState::from_preset(preset);
-                            self.configuration.selected_preset = Some(index);
-                        }
-                    }
+        // The assigned or default preset, unless the request names one
+        let _ = self.update(Message::InitializeFlashConfiguration);
+        match &request.preset {
+            Some(name) => self.select_preset(name),
+            None => Ok(()),
+        }
+    }
 
-                    // Apply preset to edit workflow if active
-                    if let Some(edit_state) = &mut self.edit_workflow {
-                        if let EditWorkflowState::EditConfiguration = &edit_state.workflow_state {
-                            self.configuration =
-                                crate::ui::configuration::ConfigurationState::from_preset(preset);
-                            self.configuration.selected_preset = Some(index);
-                        }
+    /// Configure the image with the preset called `name`
+    fn select_preset(&mut self, name: &str) -> Result<(), String> {
+        let index = self
+            .preset_manager
+            .presets
+            .iter()
+            .position(|preset| preset.name == name)
+            .ok_or_else(|| format!("Preset {} not found", name))?;
+        self.configuration = crate::ui::configuration::ConfigurationState::from_preset(
+            &self.preset_manager.presets[index],
+        );
+        self.configuration.selected_preset = Some(index);
+        Ok(())
+    }
+
+    /// Make the command line's choices in the flash workflow as far as it has got
+    ///
+    /// The device is selected once a scan lists it (`devices_loaded`), a repository image
+    /// named by a link once the repository is loaded (`images_loaded`). Once an image file is
+    /// analyzed too, the workflow moves on to the configuration screen, or with `--auto-start`
+    /// writes the image like an automation request; a device that has to be named before it
+    /// is erased stops on the confirmation screen instead. Without an image or a device the
+    /// user picks it, and the preset is chosen when the configuration screen opens.
+    fn continue_preseed(&mut self, devices_loaded: bool, images_loaded: bool) -> Task<Message> {
+        let (Some(preseed), Some(flash_state)) = (&mut self.preseed, &mut self.flash_workflow)
+        else {
+            return Task::none();
+        };
+
+        let unselected_url = preseed
+            .image_url
+            .as_ref()
+            .filter(|_| images_loaded && flash_state.selected_os_image_group.is_none());
+        if let Some(url) = unselected_url {
+            match crate::ui::automation::find_image_by_url(
+                &flash_state.os_image_groups,
+                &self.image_repo,
+                url,
+            ) {
+                Ok((group_idx, version_idx)) => {
+                    flash_state.select_repository_image(group_idx, version_idx);
+                    if preseed.device.is_none() && flash_state.selected_target.is_none() {
+                        return Task::done(Message::Flash(FlashMessage::GotoSelectTargetDevice));
                     }
                 }
-                Task::none()
+                Err(e) => return Task::done(Message::ShowError(e)),
             }
+        }
 
-            Message::InitializeFlashConfiguration => {
-                if let Some(flash_state) = &mut self.flash_workflow {
-                    // Initialize the central configuration state with the default preset if available
-                    if let Some(selected_index) = self.preset_manager.selected_preset {
-                        if let Some(preset) = self.preset_manager.presets.get(selected_index) {
-                            self.configuration =
-                                crate::ui::configuration::ConfigurationState::from_preset(preset);
-                            self.configuration.selected_preset = Some(selected_index);
-                        } else {
-                            // Fallback to defaults if preset not found
-                            self.configuration =
-                                crate::ui::configuration::ConfigurationState::new();
-                        }
-                    } else {
-                        // No default preset selected, use hardcoded defaults
-                        self.configuration = crate::ui::configuration::ConfigurationState::new();
-                    }
-
-                    // Set the workflow state to configuration
-                    flash_state.workflow_state = FlashWorkflowState::ConfigureSettings;
+        if let Some(device) = preseed.device.take_if(|_| devices_loaded) {
+            let devices = &self.device_selection.devices;
+            match crate::ui::automation::find_device(devices, &device) {
+                Ok(index) => {
+                    flash_state.selected_device = Some(index);
+                    flash_state.selected_target = Some(devices[index].clone());
+                }
+                Err(e) => {
+                    let Some(offer) = crate::ui::automation::listed_whole_disk(devices, &device)
+                    else {
+                        return Task::done(Message::ShowError(e));
+                    };
+                    // The disk is offered to be picked, it is never written without asking
+                    warn!("{}", offer.reason);
+                    preseed.auto_start = false;
+                    flash_state.whole_disk_offer = Some(offer);
+                    return Task::done(Message::Flash(FlashMessage::GotoSelectTargetDevice));
                 }
-                Task::none()
             }
         }
+
+        // Waiting for the device scan, or for the image to be analyzed or found
+        let image_ready = match (&preseed.image, &preseed.image_url) {
+            (Some(_), _) => flash_state
+                .local_image
+                .as_ref()
+                .is_some_and(|image| image.metadata.is_some()),
+            (None, Some(_)) => flash_state.selected_os_image_group.is_some(),
+            (None, None) => false,
+        };
+        if preseed.device.is_some() || !image_ready {
+            return Task::none();
+        }
+        let Some(target) = flash_state.selected_target.clone() else {
+            // Without a device the user picks one
+            return Task::none();
+        };
+
+        let auto_start = preseed.auto_start;
+        let preset = preseed.preset.clone();
+        if let Some(name) = preset
+            .as_ref()
+            .filter(|name| !self.preset_manager.presets.iter().any(|p| p.name == **name))
+        {
+            return Task::done(Message::ShowError(format!("Preset {} not found", name)));
+        }
+        // Selects the preset and leaves the rest to the user
+        let configured = self.update(Message::InitializeFlashConfiguration);
+        if !auto_start {
+            return configured;
+        }
+        if self
+            .settings
+            .typed_confirmation
+            .required(target.is_removable)
+        {
+            warn!(
+                "{} has to be named before it is erased, not starting the flash",
+                target.name
+            );
+            return Task::done(Message::Flash(FlashMessage::ConfirmWrite));
+        }
+
+        info!(
+            "Starting the flash to {} from the command line",
+            target.path
+        );
+        self.automation.flash = Some(PendingFlash {
+            request: StartFlash {
+                device: target.path,
+                channel: None,
+                version: None,
+                preset,
+            },
+            origin: FlashOrigin::CommandLine,
+            stage: PendingStage::ReadingLayout,
+        });
+        Task::done(Message::Flash(FlashMessage::ConfirmWrite))
     }
 
-    pub fn view(&self) -> Element<Message> {
-        match &self.mode {
-            AppMode::StartScreen => crate::ui::start_screen::view_start_screen(
-                self.error_message.as_deref(),
-                self.is_elevated,
-                &self.elevation_status,
-            ),
-            AppMode::FlashNewImage => {
-                if let Some(flash_state) = &self.flash_workflow {
-                    crate::ui::flash_workflow::view(
-                        flash_state,
-                        &self.device_selection,
-                        &self.configuration,
-                        &self.preset_manager,
-                        self.is_loading_repo,
-                    )
-                } else {
-                    crate::ui::start_screen::view_start_screen(
-                        self.error_message.as_deref(),
-                        self.is_elevated,
-                        &self.elevation_status,
-                    )
-                }
+    /// Move a pending automation flash on once the flash workflow handled a message
+    fn advance_automation_flash(&mut self, layout_loaded: bool) -> Task<Message> {
+        let Some(pending) = &mut self.automation.flash else {
+            return Task::none();
+        };
+        match pending.stage {
+            PendingStage::ReadingLayout if layout_loaded => {
+                pending.stage = PendingStage::Starting;
+                Task::done(Message::Flash(FlashMessage::WriteImage))
             }
-            AppMode::EditExistingDisk => {
-                if let Some(edit_state) = &self.edit_workflow {
-                    crate::ui::edit_workflow::view(
-                        edit_state,
-                        &self.device_selection,
-                        &self.configuration,
-                        &self.preset_manager,
-                    )
-                } else {
-                    crate::ui::start_screen::view_start_screen(
-                        self.error_message.as_deref(),
-                        self.is_elevated,
-                        &self.elevation_status,
+            PendingStage::Starting => {
+                let Some(flash_state) = self.flash_workflow.as_ref().filter(|flash| {
+                    matches!(
+                        flash.workflow_state,
+                        FlashWorkflowState::ClearingPartitions { .. }
+                            | FlashWorkflowState::Flashing(_)
                     )
+                }) else {
+                    return Task::none();
+                };
+                let Some(operation) = flash_state.operation else {
+                    return Task::none();
+                };
+                let image =
+                    flash_state
+                        .selected_os_image_group
+                        .and_then(|(group_idx, version_idx)| {
+                            let group = flash_state.os_image_groups.get(group_idx)?;
+                            match version_idx {
+                                0 => Some(&group.latest_version),
+                                idx => group.older_versions.get(idx - 1),
+                            }
+                        });
+                let started = FlashEvent::Started {
+                    operation,
+                    device: pending.request.device.clone(),
+                    channel: image.map(|image| image.name.clone()).unwrap_or_default(),
+                    version: image.map(|image| image.version.clone()).unwrap_or_default(),
+                };
+
+                // Queued jobs are already shown as running
+                if let Some(PendingFlash {
+                    origin: FlashOrigin::Api(call),
+                    ..
+                }) = self.automation.flash.take()
+                {
+                    call.respond(Ok(&started));
                 }
+                crate::utils::automation::publish(started);
+                Task::none()
             }
-            AppMode::ManagePresets => {
-                crate::ui::preset_manager::view(&self.preset_manager).map(Message::PresetManager)
-            }
+            _ => Task::none(),
         }
     }
 
@@ -450,6 +2145,11 @@ impl GolemGpuImager {
                                 sha256: latest_version.sha256.clone(),
                                 is_latest: true,
                                 metadata: load_metadata_for_image(&latest_version.sha256),
+                                config_schema: metadata.config_schema(latest_version),
+                                partitions: latest_version.partitions.clone(),
+                                requirements: latest_version.requirements.clone(),
+                                file_size: None,
+                                local: false,
                             };
 
                             // Create older versions (exclude the latest and sort by creation date, newest first)
@@ -488,6 +2188,11 @@ impl GolemGpuImager {
                                             sha256: version.sha256.clone(),
                                             is_latest: false,
                                             metadata: load_metadata_for_image(&version.sha256),
+                                            config_schema: metadata.config_schema(version),
+                                            partitions: version.partitions.clone(),
+                                            requirements: version.requirements.clone(),
+                                            file_size: None,
+                                            local: false,
                                         }
                                     })
                                     .collect();
@@ -524,6 +2229,11 @@ impl GolemGpuImager {
                                     sha256: version.sha256.clone(),
                                     is_latest: version == latest_version,
                                     metadata: load_metadata_for_image(&version.sha256),
+                                    config_schema: metadata.config_schema(version),
+                                    partitions: version.partitions.clone(),
+                                    requirements: version.requirements.clone(),
+                                    file_size: None,
+                                    local: false,
                                 });
                             }
                         }
@@ -540,3 +2250,1065 @@ impl GolemGpuImager {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::FlashPhase;
+    use crate::disk::test_support::{
+        CONFIG_PARTITION_OFFSET, CONFIG_PARTITION_SIZE, GOLEM_DISK_SIZE, golem_disk, read_files,
+    };
+    use crate::models::OperationId;
+    use crate::ui::configuration::ConfigurationMessage;
+    use crate::ui::edit_workflow::EditMessage;
+    use crate::ui::test_support::{Harness, downloaded_image, repository};
+    use crate::utils::recent_images::RecentImage;
+
+    const DEVICE_ENV: &str =
+        "YA_NET_TYPE=hybrid\nSUBNET=devnet-beta\nYA_PAYMENT_NETWORK_GROUP=mainnet\n";
+
+    /// golem.env on the configuration partition of a [`golem_disk`]
+    fn golem_env(mut disk: Vec<u8>) -> String {
+        let partition = CONFIG_PARTITION_OFFSET as usize
+            ..(CONFIG_PARTITION_OFFSET + CONFIG_PARTITION_SIZE) as usize;
+        let mut files = read_files(&mut disk[partition], &["golem.env"]);
+        files.remove(0).unwrap()
+    }
+
+    // The tests run on a single-threaded runtime, so tasks spawned by a step stay queued
+    // instead of racing the script
+
+    #[tokio::test]
+    async fn test_flash_workflow_write_and_cancel() {
+        let blank = vec![0u8; GOLEM_DISK_SIZE as usize];
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &blank);
+
+        harness.send(Message::FlashNewImage);
+        harness.send(repository(downloaded_image("release", "v1.0")));
+        assert_eq!(
+            harness.snapshot(),
+            "mode: FlashNewImage\nflash: SelectOsImage"
+        );
+
+        harness.send_all([
+            Message::Flash(FlashMessage::SelectOsImageFromGroup(0, 0)),
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+            Message::Flash(FlashMessage::SelectTargetDevice(0)),
+        ]);
+        assert_eq!(
+            harness.snapshot(),
+            "mode: FlashNewImage\n\
+             flash: SelectTargetDevice\n\
+             image: release v1.0\n\
+             target: /dev/fake0"
+        );
+
+        // The default preset fills in the configuration
+        harness.send_all([
+            Message::Flash(FlashMessage::GotoConfigureSettings),
+            Message::Configuration(ConfigurationMessage::SetSubnet("devnet-beta".to_string())),
+        ]);
+        assert_eq!(
+            harness.snapshot(),
+            "mode: FlashNewImage\n\
+             flash: ConfigureSettings\n\
+             image: release v1.0\n\
+             target: /dev/fake0\n\
+             configuration: Testnet Central, subnet devnet-beta, wallet \"\""
+        );
+
+        harness.send(Message::Flash(FlashMessage::ConfirmWrite));
+        assert!(harness.snapshot().contains("flash: ConfirmWrite"));
+
+        // The cached image is checked before anything is erased
+        harness.send(Message::Flash(FlashMessage::WriteImage));
+        assert!(
+            harness
+                .snapshot()
+                .contains("flash: ClearingPartitions (Checking cached image...)")
+        );
+        assert!(
+            !harness
+                .backend
+                .calls()
+                .iter()
+                .any(|call| call.starts_with("clear"))
+        );
+
+        harness.send(Message::Flash(FlashMessage::CachedImageChecked(true)));
+        assert!(
+            harness
+                .snapshot()
+                .contains("flash: ClearingPartitions (Preparing device...)")
+        );
+        assert!(
+            harness
+                .backend
+                .calls()
+                .contains(&"clear /dev/fake0".to_string())
+        );
+        let operation = harness
+            .app
+            .flash_workflow
+            .as_ref()
+            .unwrap()
+            .operation
+            .unwrap();
+
+        harness.send(Message::Flash(FlashMessage::Progress(
+            operation,
+            FlashPhase::Writing {
+                bytes: 4 << 20,
+                total: Some(8 << 20),
+                rate: 0,
+                download: None,
+                partition: None,
+                latency: Default::default(),
+            },
+        )));
+        assert!(
+            harness
+                .snapshot()
+                .contains("flash: Flashing (4.0 MB of 8.0 MB written)")
+        );
+
+        // Messages of another write are not taken for this one's
+        harness.send_all([
+            Message::Flash(FlashMessage::Progress(
+                OperationId::new(),
+                FlashPhase::WritingConfig,
+            )),
+            Message::Flash(FlashMessage::WriteImageCompleted(OperationId::new(), true)),
+        ]);
+        assert!(
+            harness
+                .snapshot()
+                .contains("flash: Flashing (4.0 MB of 8.0 MB written)")
+        );
+
+        // Progress from before the cancellation doesn't bring the write back
+        harness.send_all([
+            Message::Flash(FlashMessage::CancelWrite),
+            Message::Flash(FlashMessage::Progress(
+                operation,
+                FlashPhase::Verifying {
+                    bytes: 0,
+                    total: 8 << 20,
+                    rate: 0,
+                },
+            )),
+            Message::Flash(FlashMessage::WriteImageFailed(
+                operation,
+                "Cancelled".to_string(),
+            )),
+        ]);
+        assert_eq!(
+            harness.snapshot(),
+            "mode: FlashNewImage\n\
+             flash: Completion(false)\n\
+             image: release v1.0\n\
+             target: /dev/fake0\n\
+             configuration: Testnet Central, subnet devnet-beta, wallet \"\"\n\
+             error: Failed to write image: Cancelled"
+        );
+        assert_eq!(harness.backend.contents("/dev/fake0"), blank);
+
+        harness.send(Message::Flash(FlashMessage::BackToMainMenu));
+        assert_eq!(
+            harness.snapshot(),
+            "mode: StartScreen\nerror: Failed to write image: Cancelled"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wipe_of_cancelled_write_can_be_undone() {
+        use crate::ui::flash_workflow::WipeUndo;
+
+        let contents: Vec<u8> = (0..GOLEM_DISK_SIZE).map(|i| (i % 251) as u8).collect();
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &contents);
+        harness.send(Message::FlashNewImage);
+        harness.send_all([
+            repository(downloaded_image("release", "v1.0")),
+            Message::Flash(FlashMessage::SelectOsImageFromGroup(0, 0)),
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+            Message::Flash(FlashMessage::SelectTargetDevice(0)),
+            Message::Flash(FlashMessage::GotoConfigureSettings),
+            Message::Configuration(ConfigurationMessage::SetSubnet("devnet-beta".to_string())),
+            Message::Flash(FlashMessage::ConfirmWrite),
+            Message::Flash(FlashMessage::WriteImage),
+            Message::Flash(FlashMessage::CachedImageChecked(true)),
+        ]);
+        // The partition table was saved before the device was cleared
+        let flash = harness.app.flash_workflow.as_ref().unwrap();
+        let snapshot_path = flash.wipe_snapshot.as_ref().unwrap().path().to_path_buf();
+        assert!(snapshot_path.exists());
+        assert!(
+            harness
+                .snapshot()
+                .contains("flash: ClearingPartitions (Preparing device...)")
+        );
+        let operation = flash.operation.unwrap();
+
+        // Cancelled before any image data was written
+        harness.send_all([
+            Message::Flash(FlashMessage::CancelWrite),
+            Message::Flash(FlashMessage::WriteImageFailed(
+                operation,
+                "Cancelled".to_string(),
+            )),
+        ]);
+        let flash = harness.app.flash_workflow.as_ref().unwrap();
+        assert_eq!(flash.wipe_undo, Some(WipeUndo::Offered(operation)));
+
+        harness.send(Message::Flash(FlashMessage::UndoWipe));
+        let flash = harness.app.flash_workflow.as_ref().unwrap();
+        assert_eq!(flash.wipe_undo, Some(WipeUndo::Restored));
+        assert!(flash.wipe_snapshot.is_none());
+        assert!(!snapshot_path.exists());
+        assert_eq!(harness.backend.contents("/dev/fake0"), contents);
+
+        // Once image data is written, the old partition table is gone for good
+        harness.send_all([
+            Message::Flash(FlashMessage::ConfirmWrite),
+            Message::Flash(FlashMessage::WriteImage),
+            Message::Flash(FlashMessage::CachedImageChecked(true)),
+        ]);
+        let operation = harness
+            .app
+            .flash_workflow
+            .as_ref()
+            .unwrap()
+            .operation
+            .unwrap();
+        harness.send_all([
+            Message::Flash(FlashMessage::Progress(
+                operation,
+                FlashPhase::Writing {
+                    bytes: 4 << 20,
+                    total: Some(8 << 20),
+                    rate: 0,
+                    download: None,
+                    partition: None,
+                    latency: Default::default(),
+                },
+            )),
+            Message::Flash(FlashMessage::CancelWrite),
+            Message::Flash(FlashMessage::WriteImageFailed(
+                operation,
+                "Cancelled".to_string(),
+            )),
+        ]);
+        let flash = harness.app.flash_workflow.as_ref().unwrap();
+        assert!(flash.wipe_undo.is_none());
+        assert!(!snapshot_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_closing_the_window_waits_for_the_write() {
+        let blank = vec![0u8; GOLEM_DISK_SIZE as usize];
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &blank);
+
+        // Nothing is written yet, the window closes at once
+        harness.send(Message::FlashNewImage);
+        harness.send(Message::CloseRequested);
+        assert!(harness.exited);
+
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &blank);
+        harness.send(Message::FlashNewImage);
+        harness.send_all([
+            repository(downloaded_image("release", "v1.0")),
+            Message::Flash(FlashMessage::SelectOsImageFromGroup(0, 0)),
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+            Message::Flash(FlashMessage::SelectTargetDevice(0)),
+            Message::Flash(FlashMessage::GotoConfigureSettings),
+            Message::Configuration(ConfigurationMessage::SetSubnet("devnet-beta".to_string())),
+            Message::Flash(FlashMessage::ConfirmWrite),
+            Message::Flash(FlashMessage::WriteImage),
+            Message::Flash(FlashMessage::CachedImageChecked(true)),
+        ]);
+        let operation = harness
+            .app
+            .flash_workflow
+            .as_ref()
+            .unwrap()
+            .operation
+            .unwrap();
+        harness.send(Message::Flash(FlashMessage::Progress(
+            operation,
+            FlashPhase::WritingConfig,
+        )));
+
+        // The write goes on unless the user cancels it
+        harness.send(Message::CloseRequested);
+        assert!(harness.snapshot().ends_with("exit: Confirming"));
+        harness.send(Message::KeepRunning);
+        let snapshot = harness.snapshot();
+        assert!(snapshot.contains("flash: Flashing (Writing configuration...)"));
+        assert!(!snapshot.contains("exit:"));
+
+        // Cancelling waits for the write to stop
+        harness.send_all([Message::CloseRequested, Message::CancelAndExit]);
+        assert!(
+            harness
+                .app
+                .flash_workflow
+                .as_ref()
+                .unwrap()
+                .cancel_token
+                .is_cancelled()
+        );
+        assert!(harness.snapshot().ends_with("exit: Stopping"));
+        assert!(!harness.exited);
+
+        harness.send(Message::CloseRequested);
+        assert!(!harness.exited);
+        harness.send(Message::Flash(FlashMessage::WriteImageFailed(
+            operation,
+            "Cancelled".to_string(),
+        )));
+        assert!(harness.exited);
+    }
+
+    #[tokio::test]
+    async fn test_mainnet_write_needs_a_confirmed_wallet() {
+        let blank = vec![0u8; GOLEM_DISK_SIZE as usize];
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &blank);
+        harness.send_all([
+            Message::FlashNewImage,
+            repository(downloaded_image("release", "v1.0")),
+            Message::Flash(FlashMessage::SelectOsImageFromGroup(0, 0)),
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+            Message::Flash(FlashMessage::SelectTargetDevice(0)),
+            Message::Flash(FlashMessage::GotoConfigureSettings),
+            Message::Configuration(ConfigurationMessage::SetPaymentNetwork(
+                crate::models::PaymentNetwork::Mainnet,
+            )),
+            Message::Flash(FlashMessage::ConfirmWrite),
+            Message::Flash(FlashMessage::WriteImage),
+        ]);
+        assert!(
+            harness
+                .snapshot()
+                .ends_with("error: Enter the wallet address that receives the mainnet earnings"),
+            "{}",
+            harness.snapshot()
+        );
+
+        // A wallet without its checksum can't be checked for typos
+        let wallet = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        harness.send_all([
+            Message::Configuration(ConfigurationMessage::SetWalletAddress(
+                wallet.to_ascii_lowercase(),
+            )),
+            Message::Flash(FlashMessage::WriteImage),
+        ]);
+        assert!(
+            harness
+                .snapshot()
+                .contains("error: Copy the wallet address")
+        );
+
+        // The confirmation is for the wallet it was given with
+        harness.send_all([
+            Message::Configuration(ConfigurationMessage::ConfirmMainnet(true)),
+            Message::Configuration(ConfigurationMessage::SetWalletAddress(wallet.to_string())),
+            Message::Flash(FlashMessage::WriteImage),
+        ]);
+        assert!(
+            harness
+                .snapshot()
+                .ends_with("error: Confirm that this node is paid in real GLM on mainnet")
+        );
+        assert!(
+            !harness
+                .backend
+                .calls()
+                .iter()
+                .any(|call| call.starts_with("clear"))
+        );
+
+        harness.send_all([
+            Message::Configuration(ConfigurationMessage::ConfirmMainnet(true)),
+            Message::Flash(FlashMessage::WriteImage),
+        ]);
+        assert!(
+            harness
+                .snapshot()
+                .contains("flash: ClearingPartitions (Checking cached image...)")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unverified_image_list_blocks_flashing() {
+        use crate::utils::repo::manifest::ManifestStatus;
+
+        let blank = vec![0u8; GOLEM_DISK_SIZE as usize];
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &blank);
+        harness.send_all([
+            Message::FlashNewImage,
+            repository(downloaded_image("release", "v1.0")),
+            Message::Flash(FlashMessage::SelectOsImageFromGroup(0, 0)),
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+            Message::Flash(FlashMessage::SelectTargetDevice(0)),
+            Message::Flash(FlashMessage::GotoConfigureSettings),
+        ]);
+        harness.app.flash_workflow.as_mut().unwrap().manifest_status =
+            ManifestStatus::Invalid("signature mismatch".to_string());
+
+        harness.send_all([
+            Message::Flash(FlashMessage::ConfirmWrite),
+            Message::Flash(FlashMessage::WriteImage),
+        ]);
+        assert!(
+            harness
+                .snapshot()
+                .ends_with("error: The image list failed signature verification, so its images can't be flashed."),
+            "{}",
+            harness.snapshot()
+        );
+        assert!(
+            !harness
+                .backend
+                .calls()
+                .iter()
+                .any(|call| call.starts_with("clear"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shared_presets_are_read_only() {
+        use crate::ui::preset_manager::PresetManagerMessage;
+
+        let mut harness = Harness::new();
+        let local = harness.app.preset_manager.presets.len();
+        let mut team = harness.app.preset_manager.presets[0].clone();
+        team.name = "Team Rack".to_string();
+        team.is_default = true;
+        harness.send(Message::SharedPresetsLoaded(Ok(
+            crate::utils::shared_presets::SharedPresets {
+                presets: vec![team],
+                offline: None,
+            },
+        )));
+        let presets = &harness.app.preset_manager.presets;
+        assert_eq!(presets.len(), local + 1);
+        assert!(presets[local].shared && !presets[local].is_default);
+
+        harness.send_all([
+            Message::PresetManager(PresetManagerMessage::DeletePreset(local)),
+            Message::PresetManager(PresetManagerMessage::SetDefaultPreset(local)),
+            Message::PresetManager(PresetManagerMessage::EditPreset(local)),
+        ]);
+        let state = &harness.app.preset_manager;
+        assert_eq!(state.presets[local].name, "Team Rack");
+        assert!(!state.presets[local].is_default && state.editor.is_none());
+
+        // A copy is a local preset, listed before the shared ones
+        harness.send(Message::PresetManager(
+            PresetManagerMessage::DuplicatePreset(local),
+        ));
+        let presets = &harness.app.preset_manager.presets;
+        assert_eq!(presets[local].name, "Team Rack Copy");
+        assert!(!presets[local].shared);
+        assert_eq!(presets[local + 1].name, "Team Rack");
+    }
+
+    #[tokio::test]
+    async fn test_duplicator_flashes_inserted_disks() {
+        use crate::ui::kiosk::KioskStatus;
+        use crate::utils::device_rules::{DeviceRule, RuleAction};
+
+        let blank = vec![0u8; GOLEM_DISK_SIZE as usize];
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &blank);
+        harness.send_all([
+            Message::FlashNewImage,
+            repository(downloaded_image("release", "v1.0")),
+            Message::Flash(FlashMessage::SelectOsImageFromGroup(0, 0)),
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+            Message::Flash(FlashMessage::SelectTargetDevice(0)),
+            Message::Flash(FlashMessage::GotoConfigureSettings),
+            Message::Flash(FlashMessage::ConfirmWrite),
+        ]);
+
+        // Every inserted disk is erased, so the device rules have to say which may be
+        harness.send(Message::Flash(FlashMessage::StartKiosk));
+        assert!(harness.app.kiosk.is_none());
+        assert!(
+            harness
+                .snapshot()
+                .contains("error: Duplicator mode erases every disk that is inserted")
+        );
+
+        harness.app.error_message = None;
+        harness.app.settings.device_rules = vec![DeviceRule {
+            action: RuleAction::Allow,
+            removable: Some(true),
+            ..Default::default()
+        }];
+        let run_flash = |harness: &mut Harness| {
+            harness.send_all([
+                repository(downloaded_image("release", "v1.0")),
+                Message::Flash(FlashMessage::TargetLayoutLoaded(Err(
+                    "No partition table".to_string()
+                ))),
+                Message::Flash(FlashMessage::CachedImageChecked(true)),
+            ]);
+            harness
+                .app
+                .flash_workflow
+                .as_ref()
+                .and_then(|flash| flash.operation)
+                .unwrap()
+        };
+
+        // The confirmed disk is flashed first and ejected once it is written
+        harness.send(Message::Flash(FlashMessage::StartKiosk));
+        let operation = run_flash(&mut harness);
+        let snapshot = harness.snapshot();
+        assert!(snapshot.starts_with("mode: Kiosk\nflash: ClearingPartitions"));
+        assert!(snapshot.contains("target: /dev/fake0"));
+        assert!(
+            snapshot.contains("kiosk: Flashing { device: \"/dev/fake0\" }, 0 flashed, 0 failed")
+        );
+        harness.send(Message::Flash(FlashMessage::WriteImageCompleted(
+            operation, true,
+        )));
+        assert_eq!(
+            harness.snapshot(),
+            "mode: Kiosk\n\
+             kiosk: Succeeded { device: \"/dev/fake0\", verified: true, eject_error: None }, \
+             1 flashed, 0 failed"
+        );
+        assert!(
+            harness
+                .backend
+                .calls()
+                .contains(&"eject /dev/fake0".to_string())
+        );
+
+        // Disks that stay inserted are left alone, a new one is flashed
+        harness.send(Message::PollKiosk);
+        assert!(harness.app.automation.flash.is_none());
+        harness.attach("/dev/fake1", "Second card", &blank);
+        harness.send(Message::PollKiosk);
+        let operation = run_flash(&mut harness);
+        assert_eq!(
+            harness.app.kiosk.as_ref().unwrap().status,
+            KioskStatus::Flashing {
+                device: "/dev/fake1".to_string()
+            }
+        );
+        harness.send(Message::Flash(FlashMessage::WriteImageFailed(
+            operation,
+            "Write failed".to_string(),
+        )));
+        assert!(harness.snapshot().starts_with(
+            "mode: Kiosk\n\
+             kiosk: Failed { device: \"/dev/fake1\", error: \"Write failed\" }, 1 flashed, 1 failed"
+        ));
+
+        harness.send(Message::StopKiosk);
+        assert!(harness.app.kiosk.is_none());
+        assert!(harness.snapshot().starts_with("mode: StartScreen"));
+    }
+
+    #[tokio::test]
+    async fn test_duplicator_flashes_disks_inserted_together() {
+        use crate::ui::kiosk::KioskStatus;
+        use crate::utils::device_rules::{DeviceRule, RuleAction};
+
+        let blank = vec![0u8; GOLEM_DISK_SIZE as usize];
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &blank);
+        harness.app.settings.device_rules = vec![DeviceRule {
+            action: RuleAction::Allow,
+            removable: Some(true),
+            ..Default::default()
+        }];
+        harness.send_all([
+            Message::FlashNewImage,
+            repository(downloaded_image("release", "v1.0")),
+            Message::Flash(FlashMessage::SelectOsImageFromGroup(0, 0)),
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+            Message::Flash(FlashMessage::SelectTargetDevice(0)),
+            Message::Flash(FlashMessage::GotoConfigureSettings),
+            Message::Flash(FlashMessage::ConfirmWrite),
+            Message::Flash(FlashMessage::StartKiosk),
+        ]);
+        let finish_flash = |harness: &mut Harness, device: &str| {
+            harness.send_all([
+                repository(downloaded_image("release", "v1.0")),
+                Message::Flash(FlashMessage::TargetLayoutLoaded(Err(
+                    "No partition table".to_string()
+                ))),
+                Message::Flash(FlashMessage::CachedImageChecked(true)),
+            ]);
+            assert_eq!(
+                harness.app.kiosk.as_ref().unwrap().status,
+                KioskStatus::Flashing {
+                    device: device.to_string()
+                }
+            );
+            let operation = harness
+                .app
+                .flash_workflow
+                .as_ref()
+                .and_then(|flash| flash.operation)
+                .unwrap();
+            harness.send(Message::Flash(FlashMessage::WriteImageCompleted(
+                operation, true,
+            )));
+        };
+        finish_flash(&mut harness, "/dev/fake0");
+
+        // Both disks show up in the same scan, the second is flashed after the first
+        harness.attach("/dev/fake1", "Second card", &blank);
+        harness.attach("/dev/fake2", "Third card", &blank);
+        harness.send(Message::PollKiosk);
+        finish_flash(&mut harness, "/dev/fake1");
+        harness.send(Message::PollKiosk);
+        finish_flash(&mut harness, "/dev/fake2");
+
+        harness.send(Message::PollKiosk);
+        assert!(harness.app.automation.flash.is_none());
+        assert_eq!(harness.app.kiosk.as_ref().unwrap().succeeded, 3);
+    }
+
+    #[tokio::test]
+    async fn test_flash_monitor_keeps_a_tab_per_write() {
+        use crate::ui::flash_monitor::WriteStatus;
+        use crate::utils::device_rules::{DeviceRule, RuleAction};
+
+        let blank = vec![0u8; GOLEM_DISK_SIZE as usize];
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &blank);
+        harness.app.settings.device_rules = vec![DeviceRule {
+            action: RuleAction::Allow,
+            removable: Some(true),
+            ..Default::default()
+        }];
+        harness.send_all([
+            Message::FlashNewImage,
+            repository(downloaded_image("release", "v1.0")),
+            Message::Flash(FlashMessage::SelectOsImageFromGroup(0, 0)),
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+            Message::Flash(FlashMessage::SelectTargetDevice(0)),
+            Message::Flash(FlashMessage::GotoConfigureSettings),
+            Message::Flash(FlashMessage::ConfirmWrite),
+            Message::Flash(FlashMessage::StartKiosk),
+        ]);
+        let run_flash = |harness: &mut Harness| {
+            harness.send_all([
+                repository(downloaded_image("release", "v1.0")),
+                Message::Flash(FlashMessage::TargetLayoutLoaded(Err(
+                    "No partition table".to_string()
+                ))),
+                Message::Flash(FlashMessage::CachedImageChecked(true)),
+            ]);
+            harness
+                .app
+                .flash_workflow
+                .as_ref()
+                .and_then(|flash| flash.operation)
+                .unwrap()
+        };
+
+        let first = run_flash(&mut harness);
+        harness.send(Message::Flash(FlashMessage::Progress(
+            first,
+            FlashPhase::WritingConfig,
+        )));
+        let monitor = &harness.app.flash_monitor;
+        assert_eq!(monitor.writes.len(), 1);
+        assert_eq!(monitor.writes[0].device, "/dev/fake0");
+        assert!(matches!(
+            monitor.writes[0].status,
+            WriteStatus::Running { .. }
+        ));
+        harness.send(Message::Flash(FlashMessage::WriteImageCompleted(
+            first, true,
+        )));
+
+        // The next disk gets its own tab, the first keeps how it ended
+        harness.send(Message::PollKiosk);
+        harness.attach("/dev/fake1", "Second card", &blank);
+        harness.send(Message::PollKiosk);
+        let second = run_flash(&mut harness);
+        harness.send(Message::Flash(FlashMessage::WriteImageFailed(
+            second,
+            "Write failed".to_string(),
+        )));
+        let statuses: Vec<(&str, &WriteStatus)> = harness
+            .app
+            .flash_monitor
+            .writes
+            .iter()
+            .map(|write| (write.device.as_str(), &write.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("/dev/fake0", &WriteStatus::Succeeded { verified: true }),
+                (
+                    "/dev/fake1",
+                    &WriteStatus::Failed("Write failed".to_string())
+                ),
+            ]
+        );
+
+        // Late events of the first write don't change the tabs
+        harness.send(Message::Flash(FlashMessage::Progress(
+            first,
+            FlashPhase::WritingConfig,
+        )));
+        assert_eq!(
+            harness.app.flash_monitor.writes[0].status,
+            WriteStatus::Succeeded { verified: true }
+        );
+
+        harness.send_all([
+            Message::OpenFlashMonitor,
+            Message::SelectMonitorTab(Some(second)),
+        ]);
+        assert!(harness.app.flash_monitor.open);
+        assert_eq!(harness.app.flash_monitor.selected, Some(second));
+        harness.send(Message::CloseFlashMonitor);
+        assert!(harness.snapshot().starts_with("mode: Kiosk"));
+    }
+
+    #[tokio::test]
+    async fn test_capacity_test_waits_for_the_device_name() {
+        use crate::ui::flash_workflow::CapacityTestStatus;
+        use crate::utils::app_settings::TypedConfirmation;
+
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &[0u8; 4096]);
+        harness.app.settings.typed_confirmation = TypedConfirmation::Always;
+        harness.send_all([
+            Message::FlashNewImage,
+            repository(downloaded_image("release", "v1.0")),
+            Message::Flash(FlashMessage::SelectOsImageFromGroup(0, 0)),
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+            Message::Flash(FlashMessage::TestDeviceCapacity(0)),
+            Message::Flash(FlashMessage::ConfirmCapacityTest),
+        ]);
+        let status = |harness: &Harness| {
+            harness
+                .app
+                .flash_workflow
+                .as_ref()
+                .and_then(|flash| flash.capacity_test.as_ref())
+                .map(|test| test.status.clone())
+        };
+        assert_eq!(status(&harness), Some(CapacityTestStatus::Confirming));
+
+        harness.send_all([
+            Message::Flash(FlashMessage::SetCapacityTestTypedName(
+                "Other card".to_string(),
+            )),
+            Message::Flash(FlashMessage::ConfirmCapacityTest),
+        ]);
+        assert_eq!(status(&harness), Some(CapacityTestStatus::Confirming));
+
+        harness.send_all([
+            Message::Flash(FlashMessage::SetCapacityTestTypedName(
+                "fake card".to_string(),
+            )),
+            Message::Flash(FlashMessage::ConfirmCapacityTest),
+        ]);
+        assert_eq!(status(&harness), Some(CapacityTestStatus::Running(0.0)));
+    }
+
+    #[tokio::test]
+    async fn test_recent_images_are_selected_again() {
+        let mut harness = Harness::new();
+        harness.send(Message::FlashNewImage);
+        harness.send(repository(downloaded_image("release", "v1.0")));
+
+        let recent = RecentImage {
+            location: "https://repo.example.com/golem-gpu-live-v1.0.img.xz".to_string(),
+            channel: Some("release".to_string()),
+            version: "v1.0".to_string(),
+            sha256: "11".repeat(32),
+            last_used: 1_700_000_000,
+        };
+        harness.send(Message::Flash(FlashMessage::UseRecentImage(recent.clone())));
+        assert_eq!(
+            harness.snapshot(),
+            "mode: FlashNewImage\nflash: SelectOsImage\nimage: release v1.0"
+        );
+
+        // Versions dropped from the repository and deleted files can't be selected
+        harness.send(Message::Flash(FlashMessage::UseRecentImage(RecentImage {
+            version: "v0.9".to_string(),
+            ..recent.clone()
+        })));
+        assert!(
+            harness
+                .snapshot()
+                .contains("error: Version v0.9 is no longer in the release channel")
+        );
+        harness.send(Message::Flash(FlashMessage::UseRecentImage(RecentImage {
+            location: "/nonexistent/custom.img".to_string(),
+            channel: None,
+            ..recent
+        })));
+        assert!(
+            harness
+                .snapshot()
+                .contains("error: /nonexistent/custom.img no longer exists")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dropped_image_goes_to_device_selection() {
+        let mut harness = Harness::new();
+        let path = std::env::temp_dir().join(format!("golem-dropped-{}.img", std::process::id()));
+        std::fs::write(&path, [0u8; 4096]).unwrap();
+
+        // The file is checked in the background before it is used
+        harness.send(Message::FileDropped(path.clone()));
+        assert_eq!(
+            harness.snapshot(),
+            "mode: FlashNewImage\nflash: SelectOsImage"
+        );
+
+        harness.send(Message::Flash(FlashMessage::DroppedImageChecked(Ok(
+            path.clone()
+        ))));
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        assert_eq!(
+            harness.snapshot(),
+            format!(
+                "mode: FlashNewImage\nflash: SelectTargetDevice\nimage: Image file {}",
+                file_name
+            )
+        );
+        let flash = harness.app.flash_workflow.as_ref().unwrap();
+        assert!(flash.local_analysis.is_some());
+        assert_eq!(flash.local_image.as_ref().unwrap().file_size, Some(4096));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_command_line_preseeds_flash_workflow() {
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Golem card", &[0u8; 4096]);
+        let path = std::env::temp_dir().join(format!("golem-preseed-{}.img", std::process::id()));
+        std::fs::write(&path, [0u8; 4096]).unwrap();
+
+        // The device is selected as soon as a scan lists it
+        harness.send(Message::Preseed(Preseed {
+            image: Some(path.clone()),
+            device: Some("/dev/fake0".to_string()),
+            preset: Some("Mainnet Production".to_string()),
+            ..Preseed::default()
+        }));
+        assert_eq!(
+            harness.snapshot(),
+            "mode: FlashNewImage\nflash: SelectOsImage\ntarget: /dev/fake0"
+        );
+
+        // Once the image is analyzed the workflow waits on the configuration screen
+        harness.send(Message::Flash(FlashMessage::DroppedImageChecked(Ok(
+            path.clone()
+        ))));
+        let flash = harness.app.flash_workflow.as_ref().unwrap();
+        let analyzed = flash.local_analysis.as_ref().unwrap().path.clone();
+        harness.send(Message::Flash(FlashMessage::LocalImageAnalyzed(
+            analyzed,
+            Ok(crate::models::ImageMetadata {
+                compressed_hash: String::new(),
+                uncompressed_hash: "11".repeat(32),
+                uncompressed_size: 4096,
+                created_at: "2025-01-01".to_string(),
+            }),
+        )));
+        let flash = harness.app.flash_workflow.as_ref().unwrap();
+        assert!(matches!(
+            flash.workflow_state,
+            FlashWorkflowState::ConfigureSettings
+        ));
+        assert_eq!(
+            flash.selected_target.as_ref().map(|t| t.path.as_str()),
+            Some("/dev/fake0")
+        );
+        let preset = harness.app.configuration.selected_preset.unwrap();
+        assert_eq!(
+            harness.app.preset_manager.presets[preset].name,
+            "Mainnet Production"
+        );
+        assert!(harness.app.preseed.is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_command_line_names_unknown_device() {
+        let mut harness = Harness::new();
+        harness.send(Message::Preseed(Preseed {
+            device: Some("/dev/missing".to_string()),
+            ..Preseed::default()
+        }));
+        assert!(harness.snapshot().contains("error: "));
+        assert!(harness.app.preseed.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_whole_disk_offer_selects_the_disk() {
+        use crate::ui::flash_workflow::WholeDiskOffer;
+
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Fake card", &[0u8; 4096]);
+        harness.send_all([
+            Message::FlashNewImage,
+            Message::Flash(FlashMessage::GotoSelectTargetDevice),
+        ]);
+        harness
+            .app
+            .flash_workflow
+            .as_mut()
+            .unwrap()
+            .whole_disk_offer = Some(WholeDiskOffer {
+            reason: "/dev/fake0p1 is a partition of /dev/fake0".to_string(),
+            disk: "/dev/fake0".to_string(),
+        });
+
+        harness.send(Message::Flash(FlashMessage::UseWholeDisk));
+        let flash = harness.app.flash_workflow.as_ref().unwrap();
+        assert_eq!(
+            flash.selected_target.as_ref().map(|t| t.path.as_str()),
+            Some("/dev/fake0")
+        );
+        assert_eq!(flash.whole_disk_offer, None);
+    }
+
+    #[tokio::test]
+    async fn test_edit_workflow_shows_device_configuration() {
+        let mut harness = Harness::new();
+        harness.attach(
+            "/dev/fake0",
+            "Golem card",
+            &golem_disk(512, &[("golem.env", DEVICE_ENV)]),
+        );
+
+        harness.send_all([
+            Message::EditExistingDisk,
+            Message::Edit(EditMessage::SelectExistingDevice(0)),
+        ]);
+        assert_eq!(
+            harness.snapshot(),
+            "mode: EditExistingDisk\nedit: SelectDevice\ndevice: /dev/fake0"
+        );
+
+        // The form shows what the device holds, not the defaults
+        harness.send(Message::Edit(EditMessage::GotoEditConfiguration));
+        assert_eq!(
+            harness.snapshot(),
+            "mode: EditExistingDisk\n\
+             edit: EditConfiguration\n\
+             device: /dev/fake0\n\
+             configuration: Mainnet Hybrid, subnet devnet-beta, wallet \"\""
+        );
+
+        harness.send_all([
+            Message::Configuration(ConfigurationMessage::SetSubnet("devnet-gamma".to_string())),
+            Message::Edit(EditMessage::SaveConfiguration),
+        ]);
+        assert!(harness.snapshot().contains("edit: ReviewChanges"));
+
+        harness.send(Message::Edit(EditMessage::ConfirmSaveConfiguration));
+        assert!(harness.snapshot().contains("edit: Completion(true)"));
+        let env = golem_env(harness.backend.contents("/dev/fake0"));
+        assert!(env.contains("SUBNET=devnet-gamma"), "{}", env);
+        assert!(env.contains("YA_NET_TYPE=hybrid"), "{}", env);
+    }
+
+    #[tokio::test]
+    async fn test_edit_workflow_unchanged_configuration_is_not_written() {
+        let disk = golem_disk(512, &[("golem.env", DEVICE_ENV)]);
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Golem card", &disk);
+
+        harness.send_all([
+            Message::EditExistingDisk,
+            Message::Edit(EditMessage::SelectExistingDevice(0)),
+            Message::Edit(EditMessage::GotoEditConfiguration),
+            Message::Edit(EditMessage::SaveConfiguration),
+            Message::Edit(EditMessage::ConfirmSaveConfiguration),
+        ]);
+        assert!(harness.snapshot().contains("edit: Completion(true)"));
+        assert_eq!(harness.backend.contents("/dev/fake0"), disk);
+    }
+
+    #[tokio::test]
+    async fn test_edit_workflow_unreadable_device_falls_back_to_defaults() {
+        let mut harness = Harness::new();
+        harness.attach(
+            "/dev/fake0",
+            "Blank card",
+            &vec![0u8; GOLEM_DISK_SIZE as usize],
+        );
+
+        harness.send_all([
+            Message::EditExistingDisk,
+            Message::Edit(EditMessage::SelectExistingDevice(0)),
+            Message::Edit(EditMessage::GotoEditConfiguration),
+        ]);
+        assert_eq!(
+            harness.snapshot(),
+            "mode: EditExistingDisk\n\
+             edit: EditConfiguration\n\
+             device: /dev/fake0\n\
+             configuration: Testnet Central, subnet public, wallet \"\""
+        );
+        assert!(
+            harness
+                .app
+                .edit_workflow
+                .as_ref()
+                .is_some_and(|edit| edit.device_config.is_none())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_device_nicknames_follow_the_serial() {
+        let serial = "4C530001230905114170";
+        let mut harness = Harness::new();
+        harness.attach("/dev/fake0", "Card Reader", &[0u8; 4096]);
+        harness.set_serial("/dev/fake0", serial);
+        harness.send_all([
+            Message::FlashNewImage,
+            Message::Flash(FlashMessage::EditDeviceNickname(0)),
+            Message::Flash(FlashMessage::SetNicknameText(" Bench slot 3 ".to_string())),
+            Message::Flash(FlashMessage::SaveDeviceNickname),
+        ]);
+        assert_eq!(
+            harness.app.settings.device_nicknames.get(serial),
+            Some(&"Bench slot 3".to_string())
+        );
+        assert!(harness.app.settings_changed);
+        assert_eq!(
+            harness.app.device_selection.devices[0].display_name(),
+            "Bench slot 3 (Card Reader)"
+        );
+
+        // A later scan finds the nickname again
+        harness.send(Message::DeviceSelection(DeviceMessage::RefreshDevices));
+        assert_eq!(
+            harness.app.device_selection.devices[0].nickname.as_deref(),
+            Some("Bench slot 3")
+        );
+
+        harness.send(Message::Settings(
+            crate::ui::settings::SettingsMessage::RemoveDeviceNickname(serial.to_string()),
+        ));
+        assert_eq!(harness.app.device_selection.devices[0].nickname, None);
+    }
+}
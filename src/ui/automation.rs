@@ -0,0 +1,212 @@
+/// Answering automation API requests from the window's state
+///
+/// The server in `utils::automation` queues requests for the window. Listing disks waits
+/// for a fresh device scan; starting a flash walks the flash workflow the way a user would,
+/// so the window shows every step and the configuration backup of Golem devices still
+/// happens before they are erased. Jobs of the flash queue are started the same way.
+use crate::disk::FlashPhase;
+use crate::ui::device_selection::{GolemProbe, StorageDevice};
+use crate::ui::flash_workflow::{
+    FlashMessage, FlashState, FlashWorkflowState, OsImageGroup, WholeDiskOffer,
+};
+use crate::utils::automation::{Call, DiskInfo, FlashEvent, FlashStatus, StartFlash};
+use crate::utils::repo::ImageRepo;
+
+/// Requests waiting for something the window is still doing
+#[derive(Debug, Default)]
+pub struct AutomationState {
+    pub disk_lists: Vec<Call>, // Answered when the device scan finishes
+    pub flash: Option<PendingFlash>,
+}
+
+/// A flash that hasn't started writing yet
+#[derive(Debug)]
+pub struct PendingFlash {
+    pub request: StartFlash,
+    pub origin: FlashOrigin,
+    pub stage: PendingStage,
+}
+
+/// Who asked for a pending flash, and is told whether it started
+#[derive(Debug)]
+pub enum FlashOrigin {
+    Api(Call),   // A `start_flash` request, answered once the write started
+    Queue(u64),  // A job of the flash queue, by id
+    Kiosk,       // A disk inserted in duplicator mode
+    CommandLine, // A flash started with `--auto-start`
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PendingStage {
+    /// Waiting for the image list and the device scan
+    Loading { images: bool, devices: bool },
+    /// The target's partition layout is read, as for the confirmation screen
+    ReadingLayout,
+    /// The write was requested and may still be refused
+    Starting,
+}
+
+/// The devices of the last scan
+pub fn disk_list(devices: &[StorageDevice]) -> Vec<DiskInfo> {
+    devices
+        .iter()
+        .map(|device| DiskInfo {
+            path: device.path.clone(),
+            name: device.name.clone(),
+            size_bytes: device.size_bytes,
+            serial: device.assignment_info().serial.map(str::to_string),
+            nickname: device.nickname.clone(),
+            card: device.is_card,
+            usb: device.is_usb,
+            read_only: device.read_only,
+            golem: match device.golem {
+                GolemProbe::Golem(_) => Some(true),
+                GolemProbe::NotGolem => Some(false),
+                GolemProbe::Pending | GolemProbe::Unknown => None,
+            },
+        })
+        .collect()
+}
+
+/// Short name of a flash step for clients
+pub fn phase_name(phase: &FlashPhase) -> &'static str {
+    match phase {
+        FlashPhase::Preparing => "preparing",
+        FlashPhase::Clearing { .. } => "clearing",
+        FlashPhase::Writing { .. } => "writing",
+        FlashPhase::FixingGpt => "fixing_gpt",
+        FlashPhase::WritingConfig => "writing_config",
+        FlashPhase::Verifying { .. } => "verifying",
+        FlashPhase::Done => "done",
+        FlashPhase::Unverified => "unverified",
+    }
+}
+
+/// The state of the flash workflow, if it is open
+pub fn flash_status(flash: Option<&FlashState>) -> FlashStatus {
+    let (state, phase, progress) = match flash.map(|flash| &flash.workflow_state) {
+        None => ("idle", None, None),
+        Some(FlashWorkflowState::ClearingPartitions { progress, .. }) => {
+            ("preparing", None, Some(*progress))
+        }
+        Some(FlashWorkflowState::Flashing(phase)) => {
+            ("flashing", Some(phase_name(phase)), phase.fraction())
+        }
+        Some(FlashWorkflowState::Completion(true)) => ("succeeded", None, None),
+        Some(FlashWorkflowState::Completion(false)) => ("failed", None, None),
+        Some(_) => ("selecting", None, None),
+    };
+    let report = flash.and_then(|flash| flash.flash_report.as_ref());
+    FlashStatus {
+        state: state.to_string(),
+        phase: phase.map(str::to_string),
+        progress,
+        operation: flash.and_then(|flash| flash.operation),
+        device: report.map(|report| report.device_path.clone()),
+        channel: report.map(|report| report.image_channel.clone()),
+        version: report.map(|report| report.image_version.clone()),
+    }
+}
+
+/// Event for subscribers about a flash message, if it is one they are told about
+pub fn flash_event(message: &FlashMessage) -> Option<FlashEvent> {
+    match message {
+        FlashMessage::Progress(operation, phase) => Some(FlashEvent::Progress {
+            operation: *operation,
+            phase: phase_name(phase).to_string(),
+            progress: phase.fraction(),
+        }),
+        FlashMessage::WriteImageCompleted(operation, verified) => Some(FlashEvent::Completed {
+            operation: *operation,
+            verified: *verified,
+        }),
+        FlashMessage::WriteImageFailed(operation, error) => Some(FlashEvent::Failed {
+            operation: *operation,
+            error: error.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// The group and version index of the requested image
+///
+/// A version is looked up in all channels unless a channel is given; without a version
+/// the latest image of the channel is used.
+pub fn find_image(groups: &[OsImageGroup], request: &StartFlash) -> Result<(usize, usize), String> {
+    let in_channel = |group: &OsImageGroup| {
+        request
+            .channel
+            .as_ref()
+            .is_none_or(|channel| group.channel_name.eq_ignore_ascii_case(channel))
+    };
+
+    for (group_idx, group) in groups.iter().enumerate().filter(|(_, g)| in_channel(g)) {
+        let Some(version) = &request.version else {
+            return Ok((group_idx, 0));
+        };
+        let mut versions = std::iter::once(&group.latest_version).chain(&group.older_versions);
+        if let Some(version_idx) = versions.position(|image| &image.version == version) {
+            return Ok((group_idx, version_idx));
+        }
+    }
+
+    Err(match (&request.channel, &request.version) {
+        (Some(channel), Some(version)) => {
+            format!("Version {} not found in channel {}", version, channel)
+        }
+        (Some(channel), None) => format!("Channel {} not found", channel),
+        (None, Some(version)) => format!("Version {} not found", version),
+        (None, None) => "No image was requested".to_string(),
+    })
+}
+
+/// The group and version index of the repository image downloaded from `url`
+///
+/// Links may point at a mirror of the repository, so an image whose file has the same name
+/// matches as well.
+pub fn find_image_by_url(
+    groups: &[OsImageGroup],
+    repo: &ImageRepo,
+    url: &str,
+) -> Result<(usize, usize), String> {
+    let file_name = |url: &str| url.rsplit('/').next().unwrap_or_default().to_string();
+    let wanted = file_name(url);
+    for (group_idx, group) in groups.iter().enumerate() {
+        let mut versions = std::iter::once(&group.latest_version).chain(&group.older_versions);
+        let found = versions.position(|image| {
+            repo.find_version(&group.channel_name, &image.version)
+                .map(|version| repo.get_image_url(&version))
+                .is_some_and(|image_url| image_url == url || file_name(&image_url) == wanted)
+        });
+        if let Some(version_idx) = found {
+            return Ok((group_idx, version_idx));
+        }
+    }
+    Err(format!("{} is not an image of the repository", url))
+}
+
+/// Index of the requested device in the device list
+///
+/// A partition or volume such as `/dev/sdb1` or `D:` is never listed. The error then names
+/// the disk it belongs to, so the client can ask for that disk instead.
+pub fn find_device(devices: &[StorageDevice], device: &str) -> Result<usize, String> {
+    if let Some(index) = devices.iter().position(|listed| listed.path == device) {
+        return Ok(index);
+    }
+    match listed_whole_disk(devices, device) {
+        Some(offer) => Err(offer.reason),
+        None => Err(format!("Device {} not found", device)),
+    }
+}
+
+/// The listed disk holding `device`, if it is a partition or volume of one
+pub fn listed_whole_disk(devices: &[StorageDevice], device: &str) -> Option<WholeDiskOffer> {
+    let topology = crate::disk::topology::inspect(device).ok()?;
+    let disk = topology
+        .whole_disk()
+        .filter(|disk| devices.iter().any(|listed| listed.path == *disk))?;
+    Some(WholeDiskOffer {
+        reason: topology.require_whole_disk().err()?.to_string(),
+        disk: disk.to_string(),
+    })
+}
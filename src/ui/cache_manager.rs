@@ -0,0 +1,21 @@
+pub mod handler;
+pub mod messages;
+pub mod state;
+pub mod ui;
+
+pub use handler::*;
+pub use messages::*;
+pub use state::*;
+pub use ui::*;
+
+use iced::Element;
+
+/// Module-level view function for the image cache manager
+pub fn view<'a>(state: &'a CacheManagerState) -> Element<'a, CacheManagerMessage> {
+    ui::view_cache_manager(
+        &state.entries,
+        state.total_size,
+        state.size_limit,
+        state.confirm_clear,
+    )
+}
@@ -0,0 +1,71 @@
+use super::{CacheManagerMessage, CacheManagerState};
+use crate::ui::messages::Message;
+use crate::utils::repo::ImageRepo;
+use iced::Task;
+use std::sync::Arc;
+use tracing::{error, info};
+
+pub fn handle_message(
+    state: &mut CacheManagerState,
+    image_repo: &Arc<ImageRepo>,
+    message: CacheManagerMessage,
+) -> Task<Message> {
+    let mut cache = image_repo.cache();
+
+    let result = match message {
+        CacheManagerMessage::Refresh => Ok(()),
+
+        CacheManagerMessage::TogglePin(file_name) => {
+            let pinned = state
+                .entries
+                .iter()
+                .any(|entry| entry.file_name == file_name && entry.pinned);
+            cache.set_pinned(&file_name, !pinned)
+        }
+
+        CacheManagerMessage::RemoveImage(file_name) => cache.remove(&file_name),
+
+        CacheManagerMessage::ConfirmClearCache => {
+            state.confirm_clear = true;
+            return Task::none();
+        }
+
+        CacheManagerMessage::CancelClearCache => {
+            state.confirm_clear = false;
+            return Task::none();
+        }
+
+        CacheManagerMessage::ClearCache => {
+            state.confirm_clear = false;
+            cache.clear()
+        }
+
+        CacheManagerMessage::SetSizeLimit(option) => {
+            cache.set_size_limit(option.0).map(|evicted| {
+                if !evicted.is_empty() {
+                    info!(
+                        "Evicted {} image(s) after lowering the limit",
+                        evicted.len()
+                    );
+                }
+            })
+        }
+
+        CacheManagerMessage::BackToMainMenu => {
+            return Task::done(Message::BackToMainMenu);
+        }
+    };
+
+    state.refresh(&cache);
+
+    match result {
+        Ok(()) => Task::none(),
+        Err(e) => {
+            error!("Image cache operation failed: {}", e);
+            Task::done(Message::ShowError(format!(
+                "Image cache operation failed: {}",
+                e
+            )))
+        }
+    }
+}
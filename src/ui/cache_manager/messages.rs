@@ -0,0 +1,13 @@
+use super::SizeLimitOption;
+
+#[derive(Debug, Clone)]
+pub enum CacheManagerMessage {
+    Refresh,                       // Reload the cache index
+    TogglePin(String),             // Pin or unpin an image by file name
+    RemoveImage(String),           // Delete a single cached image
+    ConfirmClearCache,             // Show confirmation before clearing
+    CancelClearCache,              // Dismiss the confirmation
+    ClearCache,                    // Delete all unpinned images
+    SetSizeLimit(SizeLimitOption), // Change the cache size limit
+    BackToMainMenu,                // Return to main menu
+}
@@ -0,0 +1,47 @@
+use crate::utils::image_cache::{CacheEntry, ImageCache};
+use std::fmt;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+/// Size limits offered in the cache screen
+pub static SIZE_LIMIT_OPTIONS: [SizeLimitOption; 5] = [
+    SizeLimitOption(Some(20 * GIB)),
+    SizeLimitOption(Some(40 * GIB)),
+    SizeLimitOption(Some(100 * GIB)),
+    SizeLimitOption(Some(200 * GIB)),
+    SizeLimitOption(None),
+];
+
+/// Cache size limit as shown in the size limit picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimitOption(pub Option<u64>);
+
+impl fmt::Display for SizeLimitOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(limit) => write!(f, "{} GB", limit / GIB),
+            None => write!(f, "Unlimited"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CacheManagerState {
+    pub entries: Vec<CacheEntry>,
+    pub total_size: u64,
+    pub size_limit: Option<u64>,
+    pub confirm_clear: bool,
+}
+
+impl CacheManagerState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reload the entries and totals from the cache
+    pub fn refresh(&mut self, cache: &ImageCache) {
+        self.entries = cache.entries();
+        self.total_size = cache.total_size();
+        self.size_limit = cache.size_limit();
+    }
+}
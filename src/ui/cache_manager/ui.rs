@@ -0,0 +1,226 @@
+use super::{CacheManagerMessage, SIZE_LIMIT_OPTIONS, SizeLimitOption};
+use crate::disk::layout::format_size;
+use crate::style;
+use crate::ui::icons;
+use crate::utils::image_cache::CacheEntry;
+use iced::widget::{button, column, container, pick_list, row, scrollable, stack, text};
+use iced::{Alignment, Color, Element, Length};
+
+/// Main image cache view
+pub fn view_cache_manager<'a>(
+    entries: &'a [CacheEntry],
+    total_size: u64,
+    size_limit: Option<u64>,
+    confirm_clear: bool,
+) -> Element<'a, CacheManagerMessage> {
+    let header = container(
+        column![
+            text("Manage Image Cache").size(28),
+            text("Pinned images are kept when old downloads are removed to free space").size(16)
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(style::page_header);
+
+    let usage = container(
+        row![
+            icons::storage().size(20),
+            text(match size_limit {
+                Some(limit) => format!(
+                    "Using {} of {}",
+                    format_size(total_size),
+                    format_size(limit)
+                ),
+                None => format!("Using {}", format_size(total_size)),
+            })
+            .size(16)
+            .width(Length::Fill),
+            text("Size limit").size(14),
+            pick_list(
+                &SIZE_LIMIT_OPTIONS[..],
+                Some(SizeLimitOption(size_limit)),
+                CacheManagerMessage::SetSizeLimit
+            )
+            .style(style::pick_list_style),
+            button(icons::refresh())
+                .on_press(CacheManagerMessage::Refresh)
+                .padding(8)
+                .style(button::secondary),
+            button(
+                row![icons::delete(), "Clear Cache"]
+                    .spacing(5)
+                    .align_y(Alignment::Center)
+            )
+            .on_press_maybe(
+                entries
+                    .iter()
+                    .any(|entry| !entry.pinned)
+                    .then_some(CacheManagerMessage::ConfirmClearCache)
+            )
+            .padding(8)
+            .style(button::danger)
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+    )
+    .style(style::bordered_box)
+    .padding(15)
+    .width(Length::Fill);
+
+    let entries_section: Element<'a, CacheManagerMessage> = if entries.is_empty() {
+        container(
+            column![
+                icons::storage()
+                    .size(32)
+                    .color(Color::from_rgb(0.6, 0.6, 0.6)),
+                text("The cache is empty").size(16),
+                text("Images appear here after they are downloaded")
+                    .size(12)
+                    .color(Color::from_rgb(0.7, 0.7, 0.7))
+            ]
+            .spacing(10)
+            .align_x(Alignment::Center),
+        )
+        .padding(30)
+        .width(Length::Fill)
+        .into()
+    } else {
+        column(entries.iter().map(view_cache_entry))
+            .spacing(10)
+            .width(Length::Fill)
+            .into()
+    };
+
+    let back_button = button(
+        row![icons::navigate_before(), "Back"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(CacheManagerMessage::BackToMainMenu)
+    .padding(12)
+    .style(style::navigation_back_button);
+
+    let main_view = column![
+        header,
+        usage,
+        scrollable(entries_section).height(Length::Fill),
+        container(back_button).width(Length::Fill).padding([15, 0])
+    ]
+    .spacing(20)
+    .padding(20);
+
+    if confirm_clear {
+        stack![main_view, view_clear_confirmation()].into()
+    } else {
+        main_view.into()
+    }
+}
+
+/// One cached image with its pin and delete actions
+fn view_cache_entry(entry: &CacheEntry) -> Element<'_, CacheManagerMessage> {
+    let last_used = chrono::DateTime::from_timestamp(entry.last_used as i64, 0)
+        .map(|time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let details = column![
+        row![
+            if entry.pinned {
+                icons::star().size(14).color(Color::from_rgb(1.0, 0.8, 0.0))
+            } else {
+                icons::star_border()
+                    .size(14)
+                    .color(Color::from_rgb(0.6, 0.6, 0.6))
+            },
+            text(&entry.file_name).size(15)
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center),
+        text(format!(
+            "{} • last used {}",
+            format_size(entry.size),
+            last_used
+        ))
+        .size(12)
+        .color(Color::from_rgb(0.6, 0.6, 0.6))
+    ]
+    .spacing(4)
+    .width(Length::Fill);
+
+    container(
+        row![
+            details,
+            button(text(if entry.pinned { "Unpin" } else { "Pin" }))
+                .on_press(CacheManagerMessage::TogglePin(entry.file_name.clone()))
+                .padding(8)
+                .style(button::secondary),
+            button(icons::delete())
+                .on_press(CacheManagerMessage::RemoveImage(entry.file_name.clone()))
+                .padding(8)
+                .style(button::danger)
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+    )
+    .style(style::bordered_box)
+    .padding(12)
+    .width(Length::Fill)
+    .into()
+}
+
+/// Confirmation dialog shown before clearing the cache
+fn view_clear_confirmation<'a>() -> Element<'a, CacheManagerMessage> {
+    let dialog_content = column![
+        text("Clear Image Cache").size(20),
+        text("Delete all cached images that aren't pinned?")
+            .size(14)
+            .color(Color::from_rgb(0.8, 0.8, 0.8)),
+        text("They will be downloaded again when needed.")
+            .size(12)
+            .color(Color::from_rgb(0.6, 0.6, 0.6)),
+        container(
+            row![
+                button(text("Cancel"))
+                    .on_press(CacheManagerMessage::CancelClearCache)
+                    .padding(12)
+                    .style(button::secondary),
+                button(
+                    row![icons::delete(), "Clear"]
+                        .spacing(5)
+                        .align_y(Alignment::Center)
+                )
+                .on_press(CacheManagerMessage::ClearCache)
+                .padding(12)
+                .style(button::danger)
+            ]
+            .spacing(15)
+        )
+        .width(Length::Fill)
+        .align_x(Alignment::Center)
+    ]
+    .spacing(15)
+    .width(Length::Fill)
+    .max_width(400)
+    .align_x(Alignment::Center);
+
+    // Center the dialog on screen
+    container(
+        container(dialog_content)
+            .style(style::confirmation_dialog)
+            .padding(25)
+            .width(Length::Shrink)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill),
+    )
+    .style(style::modal_overlay)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .into()
+}
@@ -1,4 +1,5 @@
-use super::{ConfigurationMessage, ConfigurationState};
+use super::{ConfigurationMessage, ConfigurationState, ExtraSettingFile};
+use crate::models::ExtraSetting;
 use iced::Task;
 use tracing::debug;
 
@@ -6,14 +7,35 @@ pub fn handle_message(
     state: &mut ConfigurationState,
     presets: &[crate::models::ConfigurationPreset],
     message: ConfigurationMessage,
+) -> Task<crate::ui::messages::Message> {
+    let task = handle_change(state, presets, message);
+    // Settings locked by the provisioning of the station keep their value whatever changed
+    state.apply_provisioning(&crate::utils::provisioning::active(), false);
+    task
+}
+
+fn handle_change(
+    state: &mut ConfigurationState,
+    presets: &[crate::models::ConfigurationPreset],
+    message: ConfigurationMessage,
 ) -> Task<crate::ui::messages::Message> {
     match message {
         ConfigurationMessage::SetPaymentNetwork(network) => {
+            if network != state.payment_network {
+                state.mainnet_confirmed_wallet = None;
+            }
             state.payment_network = network;
             debug!("Set payment network: {:?}", network);
             Task::none()
         }
 
+        ConfigurationMessage::ConfirmMainnet(confirmed) => {
+            state.mainnet_confirmed_wallet =
+                confirmed.then(|| state.wallet_address.trim().to_string());
+            debug!("Mainnet payouts confirmed: {}", confirmed);
+            Task::none()
+        }
+
         ConfigurationMessage::SetSubnet(subnet) => {
             state.subnet = subnet;
             debug!("Set subnet: {}", state.subnet);
@@ -34,6 +56,88 @@ pub fn handle_message(
                 "Set wallet address: {} (valid: {})",
                 address, state.is_wallet_valid
             );
+            if state.wallet_qr.is_some() {
+                refresh_wallet_qr(state);
+            }
+            Task::none()
+        }
+
+        ConfigurationMessage::PasteWalletAddress(pasted) => {
+            let address = crate::utils::eth::normalize_eth_address(&pasted);
+            // An empty clipboard leaves the field as it is
+            if address.is_empty() {
+                return Task::none();
+            }
+            let note = if address == pasted {
+                None
+            } else if pasted
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("ethereum:")
+            {
+                Some("Taken from the payment link".to_string())
+            } else if address.len() > pasted.trim().len() {
+                Some("Added the missing 0x".to_string())
+            } else {
+                Some("Cleaned up the pasted text".to_string())
+            };
+            state.is_wallet_valid = crate::utils::eth::is_valid_eth_address(&address);
+            debug!(
+                "Pasted wallet address: {} (valid: {})",
+                address, state.is_wallet_valid
+            );
+            state.wallet_paste_note = note.map(|note| (address.clone(), note));
+            state.wallet_address = address;
+            if state.wallet_qr.is_some() {
+                refresh_wallet_qr(state);
+            }
+            Task::none()
+        }
+
+        ConfigurationMessage::PasteWalletFromClipboard => iced::clipboard::read().map(|text| {
+            crate::ui::messages::Message::Configuration(ConfigurationMessage::PasteWalletAddress(
+                text.unwrap_or_default(),
+            ))
+        }),
+
+        ConfigurationMessage::ToggleWalletQr => {
+            if state.wallet_qr.is_some() {
+                state.wallet_qr = None;
+            } else {
+                refresh_wallet_qr(state);
+            }
+            Task::none()
+        }
+
+        ConfigurationMessage::ScanWalletQr => Task::perform(
+            pick_wallet_qr_picture(),
+            |result: Result<Option<String>, String>| {
+                crate::ui::messages::Message::Configuration(ConfigurationMessage::WalletQrScanned(
+                    result,
+                ))
+            },
+        ),
+
+        ConfigurationMessage::WalletQrScanned(result) => match result {
+            Ok(Some(text)) => {
+                state.wallet_qr_error = None;
+                handle_message(
+                    state,
+                    presets,
+                    ConfigurationMessage::PasteWalletAddress(text),
+                )
+            }
+            Ok(None) => Task::none(),
+            Err(e) => {
+                debug!("Wallet QR code not read: {}", e);
+                state.wallet_qr_error = Some(e);
+                Task::none()
+            }
+        },
+
+        ConfigurationMessage::SetNodeName(name) => {
+            state.node_name = name;
+            debug!("Set node name: {}", state.node_name);
             Task::none()
         }
 
@@ -93,6 +197,72 @@ pub fn handle_message(
             Task::none()
         }
 
+        ConfigurationMessage::AddExtraSetting(file) => {
+            state.extra_settings_mut(file).push(ExtraSetting::default());
+            debug!("Added extra {:?} setting", file);
+            Task::none()
+        }
+
+        ConfigurationMessage::RemoveExtraSetting(file, index) => {
+            let settings = state.extra_settings_mut(file);
+            if index < settings.len() {
+                settings.remove(index);
+                debug!("Removed extra {:?} setting at index: {}", file, index);
+            }
+            Task::none()
+        }
+
+        ConfigurationMessage::SetExtraSettingKey(file, index, key) => {
+            if let Some(setting) = state.extra_settings_mut(file).get_mut(index) {
+                setting.key = key;
+            }
+            Task::none()
+        }
+
+        ConfigurationMessage::SetExtraSettingValue(file, index, value) => {
+            if let Some(setting) = state.extra_settings_mut(file).get_mut(index) {
+                setting.value = value;
+            }
+            Task::none()
+        }
+
+        ConfigurationMessage::PickFirstBootFile => Task::perform(
+            pick_firstboot_file(),
+            |result: Result<Option<crate::models::FirstBootFile>, String>| {
+                crate::ui::messages::Message::Configuration(
+                    ConfigurationMessage::FirstBootFilePicked(result),
+                )
+            },
+        ),
+
+        ConfigurationMessage::FirstBootFilePicked(result) => {
+            match result {
+                Ok(Some(firstboot)) => {
+                    debug!(
+                        "Attached {} as {}",
+                        firstboot.source_name,
+                        firstboot.kind.file_name()
+                    );
+                    state.firstboot = Some(firstboot);
+                    state.firstboot_error = None;
+                }
+                // The dialog was cancelled
+                Ok(None) => {}
+                Err(error) => {
+                    debug!("Failed to load first-boot file: {}", error);
+                    state.firstboot_error = Some(error);
+                }
+            }
+            Task::none()
+        }
+
+        ConfigurationMessage::RemoveFirstBootFile => {
+            state.firstboot = None;
+            state.firstboot_error = None;
+            debug!("Removed first-boot file");
+            Task::none()
+        }
+
         ConfigurationMessage::SelectPreset(index) => {
             if let Some(preset) = presets.get(index) {
                 *state = ConfigurationState::from_preset(preset);
@@ -128,8 +298,14 @@ pub fn handle_message(
             state.configuration_server = config.configuration_server.unwrap_or_default();
             state.metrics_server = config.metrics_server.unwrap_or_default();
             state.central_net_host = config.central_net_host.unwrap_or_default();
+            state.node_name = config.node_name.unwrap_or_default();
             state.is_central_net_host_valid = state.central_net_host.is_empty()
                 || crate::utils::validation::is_valid_central_net_host(&state.central_net_host);
+            // Extra settings aren't read back; new ones are added to what the device has
+            state.extra_env.clear();
+            state.extra_toml.clear();
+            state.firstboot = None;
+            state.firstboot_error = None;
             debug!("Loaded configuration from device");
             Task::none()
         }
@@ -151,6 +327,11 @@ pub fn handle_message(
             let configuration_server = state.configuration_server.clone();
             let metrics_server = state.metrics_server.clone();
             let central_net_host = state.central_net_host.clone();
+            let node_name =
+                Some(state.node_name.trim().to_string()).filter(|name| !name.is_empty());
+            let extra_env = state.filled_in_extra_settings(ExtraSettingFile::GolemEnv);
+            let extra_toml = state.filled_in_extra_settings(ExtraSettingFile::GolemwzToml);
+            let firstboot = state.firstboot.clone();
             let server_config_content = state.server_config_content.clone();
 
             debug!("Starting configuration save to device: {}", device_path);
@@ -171,6 +352,10 @@ pub fn handle_message(
                         metrics_server,
                         central_net_host,
                     );
+                    config.glm_node_name = node_name;
+                    config.extra_env = extra_env;
+                    config.extra_toml = extra_toml;
+                    config.firstboot = firstboot;
 
                     // If we have server configuration content, preserve it
                     if let Some(server_content) = server_config_content {
@@ -379,6 +564,10 @@ fn apply_server_configuration_to_state(state: &mut ConfigurationState, config: &
             state.is_wallet_valid = crate::utils::eth::is_valid_eth_address(glm_account);
         }
 
+        if let Some(node_name) = table.get("glm_node_name").and_then(|v| v.as_str()) {
+            state.node_name = node_name.to_string();
+        }
+
         if let Some(non_interactive) = table
             .get("non_interactive_install")
             .and_then(|v| v.as_bool())
@@ -456,3 +645,52 @@ fn apply_server_configuration_to_state(state: &mut ConfigurationState, config: &
             || crate::utils::validation::is_valid_central_net_host(&state.central_net_host);
     }
 }
+
+/// Draw the QR code of the wallet address, or hide it while the address is empty or invalid
+fn refresh_wallet_qr(state: &mut ConfigurationState) {
+    let address = state.wallet_address.trim();
+    state.wallet_qr = if address.is_empty() || !state.is_wallet_valid {
+        None
+    } else {
+        iced::widget::qr_code::Data::new(address)
+            .map(|data| (address.to_string(), std::sync::Arc::new(data)))
+            .ok()
+    };
+}
+
+/// Ask for a screenshot or photo of a wallet QR code and decode it
+async fn pick_wallet_qr_picture() -> Result<Option<String>, String> {
+    let Some(handle) = rfd::AsyncFileDialog::new()
+        .set_title("Scan Wallet QR Code")
+        .add_filter("Pictures", &["png", "jpg", "jpeg"])
+        .pick_file()
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let path = handle.path().to_path_buf();
+    tokio::task::spawn_blocking(move || crate::utils::qr::decode_image_file(&path))
+        .await
+        .map_err(|e| format!("Failed to read the picture: {}", e))?
+        .map(Some)
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Ask for a first-boot script or cloud-init user-data file and read it
+///
+/// No filter is set since cloud-init user-data files usually have no extension.
+async fn pick_firstboot_file() -> Result<Option<crate::models::FirstBootFile>, String> {
+    let Some(handle) = rfd::AsyncFileDialog::new()
+        .set_title("Attach First-Boot File")
+        .pick_file()
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let data = tokio::fs::read(handle.path())
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", handle.file_name(), e))?;
+    crate::models::FirstBootFile::from_file(&handle.file_name(), data).map(Some)
+}
@@ -1,11 +1,19 @@
-use crate::models::{NetworkType, PaymentNetwork};
+use super::ExtraSettingFile;
+use crate::models::{FirstBootFile, NetworkType, PaymentNetwork};
 
 #[derive(Debug, Clone)]
 pub enum ConfigurationMessage {
     SetPaymentNetwork(PaymentNetwork),
+    ConfirmMainnet(bool), // The user checked the wallet that mainnet earnings go to
     SetSubnet(String),
     SetNetworkType(NetworkType),
     SetWalletAddress(String),
+    PasteWalletAddress(String), // Text pasted into the wallet field, normalized before it is set
+    PasteWalletFromClipboard,
+    ToggleWalletQr, // Show the wallet address as a QR code
+    ScanWalletQr,   // Read the address from a picture of a QR code
+    WalletQrScanned(Result<Option<String>, String>), // Text of the code, None if cancelled
+    SetNodeName(String),
     SetNonInteractiveInstall(bool),
     AddSSHKey,
     RemoveSSHKey(usize),
@@ -14,6 +22,13 @@ pub enum ConfigurationMessage {
     SetMetricsServer(String),
     SetCentralNetHost(String),
     ToggleAdvancedOptions,
+    AddExtraSetting(ExtraSettingFile),
+    RemoveExtraSetting(ExtraSettingFile, usize),
+    SetExtraSettingKey(ExtraSettingFile, usize, String),
+    SetExtraSettingValue(ExtraSettingFile, usize, String),
+    PickFirstBootFile,
+    FirstBootFilePicked(Result<Option<FirstBootFile>, String>),
+    RemoveFirstBootFile,
     SelectPreset(usize),
     LoadFromPreset(usize),
     LoadFromDevice(crate::disk::GolemConfig),
@@ -1,4 +1,17 @@
-use crate::models::{ConfigurationPreset, NetworkType, PaymentNetwork};
+use crate::disk::{ConfigField, ConfigSchema};
+use crate::models::{
+    ConfigurationPreset, ExtraSetting, FirstBootFile, NetworkType, PaymentNetwork,
+};
+use crate::utils::provisioning::{self, Provisioning};
+use iced::widget::qr_code;
+use std::sync::Arc;
+
+/// Configuration file an extra setting is written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtraSettingFile {
+    GolemEnv,
+    GolemwzToml,
+}
 
 #[derive(Debug, Clone)]
 pub struct ConfigurationState {
@@ -7,6 +20,10 @@ pub struct ConfigurationState {
     pub network_type: NetworkType,
     pub wallet_address: String,
     pub is_wallet_valid: bool,
+    pub wallet_paste_note: Option<(String, String)>, // Pasted address and what was cleaned off it
+    pub wallet_qr: Option<(String, Arc<qr_code::Data>)>, // Shown address and its QR code
+    pub mainnet_confirmed_wallet: Option<String>,    // Wallet the user confirmed mainnet payouts to
+    pub wallet_qr_error: Option<String>,             // Why the picked picture gave no address
     pub non_interactive_install: bool,
     pub ssh_keys: Vec<String>,
     pub ssh_key_errors: Vec<Option<String>>,
@@ -14,7 +31,12 @@ pub struct ConfigurationState {
     pub metrics_server: String,
     pub central_net_host: String,
     pub is_central_net_host_valid: bool,
+    pub node_name: String,
     pub advanced_options_expanded: bool,
+    pub extra_env: Vec<ExtraSetting>,
+    pub extra_toml: Vec<ExtraSetting>,
+    pub firstboot: Option<FirstBootFile>,
+    pub firstboot_error: Option<String>,
     pub selected_preset: Option<usize>,
     pub server_config_fetching: bool,
     pub server_config_content: Option<String>,
@@ -22,8 +44,9 @@ pub struct ConfigurationState {
 }
 
 impl ConfigurationState {
+    /// A new configuration, with the values the provisioning of the station presets
     pub fn new() -> Self {
-        Self {
+        let mut state = Self {
             payment_network: PaymentNetwork::Testnet,
             subnet: "public".to_string(),
             network_type: NetworkType::Central,
@@ -36,16 +59,28 @@ impl ConfigurationState {
             metrics_server: String::new(),
             central_net_host: String::new(),
             is_central_net_host_valid: true,
+            node_name: String::new(),
             advanced_options_expanded: false,
+            extra_env: Vec::new(),
+            extra_toml: Vec::new(),
+            firstboot: None,
+            firstboot_error: None,
             selected_preset: None,
             server_config_fetching: false,
             server_config_content: None,
             server_config_error: None,
-        }
+            wallet_paste_note: None,
+            wallet_qr: None,
+            wallet_qr_error: None,
+            mainnet_confirmed_wallet: None,
+        };
+        state.apply_provisioning(&provisioning::active(), true);
+        state
     }
 
+    /// The configuration of `preset`, except for settings the provisioning locks
     pub fn from_preset(preset: &ConfigurationPreset) -> Self {
-        Self {
+        let mut state = Self {
             payment_network: preset.payment_network,
             subnet: preset.subnet.clone(),
             network_type: preset.network_type,
@@ -72,11 +107,91 @@ impl ConfigurationState {
             is_central_net_host_valid: preset.central_net_host.as_ref().map_or(true, |host| {
                 host.is_empty() || crate::utils::validation::is_valid_central_net_host(host)
             }),
-            advanced_options_expanded: false,
+            node_name: preset.node_name.clone().unwrap_or_default(),
+            // Show the advanced section when the preset has extra settings in it
+            advanced_options_expanded: !preset.extra_env.is_empty()
+                || !preset.extra_toml.is_empty()
+                || preset.firstboot.is_some(),
+            extra_env: preset.extra_env.clone(),
+            extra_toml: preset.extra_toml.clone(),
+            firstboot: preset.firstboot.clone(),
+            firstboot_error: None,
             selected_preset: None, // Will be set by the caller when loading from a specific preset
             server_config_fetching: false,
             server_config_content: None,
             server_config_error: None,
+            wallet_paste_note: None,
+            wallet_qr: None,
+            wallet_qr_error: None,
+            mainnet_confirmed_wallet: None,
+        };
+        state.apply_provisioning(&provisioning::active(), false);
+        state
+    }
+
+    /// Use the values `provisioning` sets
+    ///
+    /// With `defaults` the values it presets are filled in too, for a new configuration;
+    /// otherwise only the locked ones, which no preset, device or edit can change.
+    pub fn apply_provisioning(&mut self, provisioning: &Provisioning, defaults: bool) {
+        for field in ConfigField::ALL {
+            let value = if defaults {
+                provisioning.value(field)
+            } else {
+                provisioning.locked_value(field)
+            };
+            let Some(value) = value else {
+                continue;
+            };
+            // Values were validated when the provisioning was read
+            match field {
+                ConfigField::PaymentNetwork => {
+                    if let Some(network) = provisioning::payment_network(value) {
+                        self.payment_network = network;
+                    }
+                }
+                ConfigField::NetworkType => {
+                    if let Some(network_type) = provisioning::network_type(value) {
+                        self.network_type = network_type;
+                    }
+                }
+                ConfigField::Subnet => self.subnet = value.to_string(),
+                ConfigField::GlmAccount => {
+                    if self.wallet_address != value {
+                        self.wallet_address = value.to_string();
+                        self.is_wallet_valid = true;
+                        self.wallet_paste_note = None;
+                    }
+                }
+                ConfigField::GlmNodeName => self.node_name = value.to_string(),
+                ConfigField::NonInteractiveInstall => {
+                    self.non_interactive_install = provisioning::flag(value).unwrap_or(false);
+                }
+                ConfigField::SshKeys => {
+                    let keys: Vec<String> = value.lines().map(str::to_string).collect();
+                    let current: Vec<String> = self
+                        .ssh_keys
+                        .iter()
+                        .filter(|key| !key.is_empty())
+                        .cloned()
+                        .collect();
+                    if current != keys {
+                        let rows = keys.len().max(1);
+                        self.ssh_keys = vec![String::new(); rows];
+                        self.ssh_key_errors = vec![None; rows];
+                        for (index, key) in keys.into_iter().enumerate() {
+                            self.update_ssh_key(index, key);
+                        }
+                    }
+                }
+                ConfigField::ConfigurationServer => self.configuration_server = value.to_string(),
+                ConfigField::Metrics => self.metrics_server = value.to_string(),
+                ConfigField::CentralNetHost => {
+                    self.central_net_host = value.to_string();
+                    self.is_central_net_host_valid = true;
+                }
+                ConfigField::GlmPerHour => {}
+            }
         }
     }
 
@@ -112,11 +227,105 @@ impl ConfigurationState {
             } else {
                 Some(self.central_net_host.clone())
             },
+            node_name: if self.node_name.trim().is_empty() {
+                None
+            } else {
+                Some(self.node_name.trim().to_string())
+            },
+            extra_env: self.filled_in_extra_settings(ExtraSettingFile::GolemEnv),
+            extra_toml: self.filled_in_extra_settings(ExtraSettingFile::GolemwzToml),
+            firstboot: self.firstboot.clone(),
+            shared: false,
         }
     }
 
     pub fn is_valid(&self) -> bool {
-        !self.subnet.trim().is_empty() && self.is_wallet_valid && self.are_ssh_keys_valid() && self.is_central_net_host_valid
+        crate::utils::validation::subnet_error(&self.subnet).is_none()
+            && self.is_wallet_valid
+            && self.are_ssh_keys_valid()
+            && self.is_central_net_host_valid
+            && self.are_extra_settings_valid()
+            && self.are_templates_valid()
+    }
+
+    /// Why the configuration can't be written yet on mainnet, `None` if it can or isn't mainnet
+    ///
+    /// Mainnet earnings are paid to the wallet address, so the wallet has to pass
+    /// `mainnet_wallet_error` and the user has to confirm it.
+    pub fn mainnet_interlock(&self, schema: &ConfigSchema) -> Option<&'static str> {
+        if !self.is_mainnet(schema) {
+            return None;
+        }
+        self.mainnet_wallet_error(schema).or_else(|| {
+            (self.mainnet_confirmed_wallet.as_deref() != Some(self.wallet_address.trim()))
+                .then_some("Confirm that this node is paid in real GLM on mainnet")
+        })
+    }
+
+    /// Why the wallet address can't receive mainnet earnings, `None` if it can
+    ///
+    /// When the image takes a wallet it has to be set and written in its checksummed form,
+    /// so a typo is caught instead of paying out to an address nobody owns.
+    pub fn mainnet_wallet_error(&self, schema: &ConfigSchema) -> Option<&'static str> {
+        let wallet = self.wallet_address.trim();
+        if !self.is_mainnet(schema) || !schema.supports(ConfigField::GlmAccount) {
+            None
+        } else if wallet.is_empty() {
+            Some("Enter the wallet address that receives the mainnet earnings")
+        } else if !crate::utils::eth::is_checksummed(wallet) {
+            Some("Copy the wallet address from your wallet app in its checksummed, mixed-case form")
+        } else {
+            None
+        }
+    }
+
+    /// Whether mainnet is selected and written for the image
+    pub fn is_mainnet(&self, schema: &ConfigSchema) -> bool {
+        self.payment_network == PaymentNetwork::Mainnet
+            && schema.supports(ConfigField::PaymentNetwork)
+    }
+
+    /// Whether the fields that are expanded at flash time only use known variables
+    pub fn are_templates_valid(&self) -> bool {
+        [&self.node_name, &self.subnet]
+            .iter()
+            .all(|field| crate::utils::template::unknown_variables(field).is_empty())
+    }
+
+    pub fn are_extra_settings_valid(&self) -> bool {
+        [ExtraSettingFile::GolemEnv, ExtraSettingFile::GolemwzToml]
+            .into_iter()
+            .all(|file| {
+                self.extra_settings(file)
+                    .iter()
+                    .all(|setting| extra_setting_error(file, &setting.key).is_none())
+            })
+    }
+
+    pub fn extra_settings(&self, file: ExtraSettingFile) -> &[ExtraSetting] {
+        match file {
+            ExtraSettingFile::GolemEnv => &self.extra_env,
+            ExtraSettingFile::GolemwzToml => &self.extra_toml,
+        }
+    }
+
+    /// Trimmed extra settings, leaving out rows that were added but not filled in
+    pub fn filled_in_extra_settings(&self, file: ExtraSettingFile) -> Vec<ExtraSetting> {
+        self.extra_settings(file)
+            .iter()
+            .filter(|setting| !setting.key.trim().is_empty())
+            .map(|setting| ExtraSetting {
+                key: setting.key.trim().to_string(),
+                value: setting.value.trim().to_string(),
+            })
+            .collect()
+    }
+
+    pub fn extra_settings_mut(&mut self, file: ExtraSettingFile) -> &mut Vec<ExtraSetting> {
+        match file {
+            ExtraSettingFile::GolemEnv => &mut self.extra_env,
+            ExtraSettingFile::GolemwzToml => &mut self.extra_toml,
+        }
     }
 
     pub fn are_ssh_keys_valid(&self) -> bool {
@@ -150,3 +359,11 @@ impl ConfigurationState {
         }
     }
 }
+
+/// Reason the key of an extra setting can't be written to `file`, if any
+pub fn extra_setting_error(file: ExtraSettingFile, key: &str) -> Option<String> {
+    match file {
+        ExtraSettingFile::GolemEnv => crate::utils::validation::extra_env_key_error(key),
+        ExtraSettingFile::GolemwzToml => crate::utils::validation::extra_toml_key_error(key),
+    }
+}
@@ -1,31 +1,38 @@
 use iced::widget::{
-    button, checkbox, column, container, keyed_column, pick_list, row, scrollable, text, text_input,
+    Column, button, checkbox, column, container, keyed_column, pick_list, qr_code, row, scrollable,
+    text, text_input, tooltip,
 };
 use iced::{Alignment, Color, Element, Length};
 
-use super::{ConfigurationMessage, ConfigurationState};
-use crate::models::{NetworkType, PaymentNetwork};
+use super::{ConfigurationMessage, ConfigurationState, ExtraSettingFile};
+use crate::disk::{ConfigField, ConfigSchema};
+use crate::models::{FirstBootKind, NetworkType, PaymentNetwork};
 use crate::style;
 use crate::ui::{icons, messages::Message};
 
 /// Main configuration view - reusable across all contexts
 pub fn view_configuration<'a, F>(
     state: &'a ConfigurationState,
+    schema: &'a ConfigSchema,
     title: &'a str,
     description: &'a str,
+    wide: bool,
     message_factory: F,
 ) -> Element<'a, Message>
 where
     F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
 {
     let header = view_header(title, description);
-    let form = view_configuration_form(state, message_factory);
+    let form = view_configuration_form(state, schema, wide, message_factory);
 
     column![header, form].spacing(20).width(Length::Fill).into()
 }
 
 /// Configuration header component
-pub fn view_header<'a>(title: &'a str, description: &'a str) -> Element<'a, Message> {
+pub fn view_header<'a>(
+    title: &'a str,
+    description: impl text::IntoFragment<'a>,
+) -> Element<'a, Message> {
     container(column![text(title).size(28), text(description).size(16),].spacing(5))
         .width(Length::Fill)
         .padding(15)
@@ -34,17 +41,54 @@ pub fn view_header<'a>(title: &'a str, description: &'a str) -> Element<'a, Mess
 }
 
 /// Main configuration form component
+///
+/// In a `wide` window the advanced options are shown next to the basic fields. Only the
+/// settings `schema` understands are shown.
 pub fn view_configuration_form<'a, F>(
     state: &'a ConfigurationState,
+    schema: &'a ConfigSchema,
+    wide: bool,
     message_factory: F,
 ) -> Element<'a, Message>
 where
     F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
 {
-    let basic_form = view_basic_configuration(state, message_factory);
-    let advanced_form = view_advanced_configuration(state, message_factory);
+    let basic_form = view_basic_configuration(state, schema, message_factory);
+    let advanced_form = view_advanced_configuration(state, schema, message_factory);
+
+    let fields: Element<'a, Message> = if wide {
+        row![
+            container(basic_form).width(Length::FillPortion(1)),
+            container(advanced_form).width(Length::FillPortion(1)),
+        ]
+        .spacing(30)
+        .into()
+    } else {
+        column![basic_form, advanced_form,].spacing(20).into()
+    };
+
+    let fields: Element<'a, Message> = if schema.hides_fields() {
+        let image = if schema.description.is_empty() {
+            "the selected image"
+        } else {
+            schema.description.as_str()
+        };
+        column![
+            text(format!(
+                "Only the settings understood by {} are shown",
+                image
+            ))
+            .size(12)
+            .color(Color::from_rgb(0.6, 0.6, 0.6)),
+            fields,
+        ]
+        .spacing(15)
+        .into()
+    } else {
+        fields
+    };
 
-    container(column![basic_form, advanced_form,].spacing(20))
+    container(fields)
         .width(Length::Fill)
         .padding(15)
         .style(style::bordered_box)
@@ -54,41 +98,70 @@ where
 /// Basic configuration fields
 pub fn view_basic_configuration<'a, F>(
     state: &'a ConfigurationState,
+    schema: &ConfigSchema,
     message_factory: F,
 ) -> Element<'a, Message>
 where
     F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
 {
-    column![
+    let fields: [(ConfigField, Element<'a, Message>); 8] = [
         // Non-interactive install checkbox
-        column![
-            checkbox("Non-Interactive Mode (Headless)", state.non_interactive_install)
-                .on_toggle(move |checked| message_factory(ConfigurationMessage::SetNonInteractiveInstall(checked)))
+        (
+            ConfigField::NonInteractiveInstall,
+            column![
+                checkbox(
+                    ConfigField::NonInteractiveInstall.help().label,
+                    state.non_interactive_install
+                )
+                .on_toggle_maybe(is_editable(ConfigField::NonInteractiveInstall).then_some(
+                    move |checked| message_factory(ConfigurationMessage::SetNonInteractiveInstall(
+                        checked
+                    ))
+                ))
                 .size(16),
-            text("First OS start will not ask anything - will select available GPUs and data partition without user interaction")
-                .size(12)
-                .color(Color::from_rgb(0.6, 0.6, 0.6)),
-        ]
-        .spacing(5),
-
-        // Payment Network
-        view_payment_network_field(state.payment_network, message_factory),
-
-        // Network Type
-        view_network_type_field(state.network_type, message_factory),
-
-        // Subnet
-        view_subnet_field(&state.subnet, message_factory),
-
-        // Wallet Address
-        view_wallet_address_field(&state.wallet_address, state.is_wallet_valid, message_factory),
-
-        // SSH Keys
-        view_ssh_keys_field(&state.ssh_keys, &state.ssh_key_errors, message_factory),
+                text(ConfigField::NonInteractiveInstall.help().description)
+                    .size(12)
+                    .color(Color::from_rgb(0.6, 0.6, 0.6)),
+            ]
+            .spacing(5)
+            .into(),
+        ),
+        (
+            ConfigField::PaymentNetwork,
+            view_payment_network_field(state.payment_network, message_factory),
+        ),
+        (
+            ConfigField::NetworkType,
+            view_network_type_field(state.network_type, message_factory),
+        ),
+        (
+            ConfigField::Subnet,
+            view_subnet_field(&state.subnet, message_factory),
+        ),
+        (
+            ConfigField::GlmAccount,
+            view_wallet_address_field(state, message_factory),
+        ),
+        (
+            ConfigField::GlmNodeName,
+            view_node_name_field(&state.node_name, message_factory),
+        ),
+        (
+            ConfigField::SshKeys,
+            view_ssh_keys_field(&state.ssh_keys, &state.ssh_key_errors, message_factory),
+        ),
+        (
+            ConfigField::ConfigurationServer,
+            view_configuration_server_field(&state.configuration_server, state, message_factory),
+        ),
+    ];
 
-        // Configuration Server
-        view_configuration_server_field(&state.configuration_server, state, message_factory),
-    ]
+    Column::with_children(
+        fields
+            .into_iter()
+            .filter(|(field, _)| schema.supports(*field))
+            .map(|(_, element)| element),
+    )
     .spacing(20)
     .into()
 }
@@ -96,6 +169,7 @@ where
 /// Advanced configuration options with accordion
 pub fn view_advanced_configuration<'a, F>(
     state: &'a ConfigurationState,
+    schema: &ConfigSchema,
     message_factory: F,
 ) -> Element<'a, Message>
 where
@@ -104,15 +178,34 @@ where
     let toggle_button =
         view_advanced_options_toggle(state.advanced_options_expanded, message_factory);
 
-    let advanced_fields = if state.advanced_options_expanded {
-        column![
-            view_metrics_server_field(&state.metrics_server, message_factory),
-            view_central_net_host_field(&state.central_net_host, state.is_central_net_host_valid, message_factory),
-        ]
-        .spacing(20)
-    } else {
-        column![]
-    };
+    let mut advanced_fields = column![].spacing(20);
+    if state.advanced_options_expanded {
+        if schema.supports(ConfigField::Metrics) {
+            advanced_fields = advanced_fields.push(view_metrics_server_field(
+                &state.metrics_server,
+                message_factory,
+            ));
+        }
+        if schema.supports(ConfigField::CentralNetHost) {
+            advanced_fields = advanced_fields.push(view_central_net_host_field(
+                &state.central_net_host,
+                state.is_central_net_host_valid,
+                message_factory,
+            ));
+        }
+        advanced_fields = advanced_fields
+            .push(view_extra_settings_field(
+                state,
+                ExtraSettingFile::GolemEnv,
+                message_factory,
+            ))
+            .push(view_extra_settings_field(
+                state,
+                ExtraSettingFile::GolemwzToml,
+                message_factory,
+            ))
+            .push(view_firstboot_field(state, message_factory));
+    }
 
     column![toggle_button, advanced_fields].spacing(15).into()
 }
@@ -160,15 +253,22 @@ pub fn view_payment_network_field<'a, F>(
 where
     F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
 {
-    column![
-        text("Payment Network").size(16),
+    let picker: Element<'a, Message> = if is_editable(ConfigField::PaymentNetwork) {
         pick_list(
             &[PaymentNetwork::Testnet, PaymentNetwork::Mainnet][..],
             Some(payment_network),
-            move |network| message_factory(ConfigurationMessage::SetPaymentNetwork(network))
+            move |network| message_factory(ConfigurationMessage::SetPaymentNetwork(network)),
         )
         .width(Length::Fill)
-        .style(style::pick_list_style),
+        .style(style::pick_list_style)
+        .into()
+    } else {
+        view_locked_value(&payment_network.to_string())
+    };
+
+    column![
+        view_field_label(ConfigField::PaymentNetwork),
+        picker,
         text(match payment_network {
             PaymentNetwork::Testnet => "Use testnet GLM tokens for development and testing",
             PaymentNetwork::Mainnet => "Use real GLM tokens for production workloads",
@@ -188,15 +288,22 @@ pub fn view_network_type_field<'a, F>(
 where
     F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
 {
-    column![
-        text("Network Type").size(16),
+    let picker: Element<'a, Message> = if is_editable(ConfigField::NetworkType) {
         pick_list(
             &[NetworkType::Central, NetworkType::Hybrid][..],
             Some(network_type),
-            move |network_type| message_factory(ConfigurationMessage::SetNetworkType(network_type))
+            move |network_type| message_factory(ConfigurationMessage::SetNetworkType(network_type)),
         )
         .width(Length::Fill)
-        .style(style::pick_list_style),
+        .style(style::pick_list_style)
+        .into()
+    } else {
+        view_locked_value(&network_type.to_string())
+    };
+
+    column![
+        view_field_label(ConfigField::NetworkType),
+        picker,
         text(match network_type {
             NetworkType::Central => "Connect through central network infrastructure",
             NetworkType::Hybrid => "Mix of central and peer-to-peer connections",
@@ -209,39 +316,250 @@ where
 }
 
 /// Subnet field component
+///
+/// Known public subnets can be picked from the list, any other name can still be typed.
+/// Names that are one or two letters off a known subnet get a warning, since a provider on
+/// a misspelled subnet never receives work.
 pub fn view_subnet_field<'a, F>(subnet: &'a str, message_factory: F) -> Element<'a, Message>
 where
     F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
 {
-    column![
-        text("Subnet").size(16),
+    use crate::utils::validation::{KNOWN_SUBNETS, similar_known_subnet, subnet_error};
+
+    let known: Vec<&'static str> = KNOWN_SUBNETS.iter().map(|(name, _)| *name).collect();
+    let selected = known.iter().copied().find(|name| *name == subnet.trim());
+    let editable = is_editable(ConfigField::Subnet);
+    let set_subnet = move |name: &str| {
+        editable.then(|| message_factory(ConfigurationMessage::SetSubnet(name.to_string())))
+    };
+
+    let hint: Element<'a, Message> = if let Some(error) = subnet_error(subnet) {
+        row![
+            icons::error().color(style::ERROR),
+            text(error).size(12).color(style::ERROR)
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center)
+        .into()
+    } else if let Some(suggestion) = similar_known_subnet(subnet) {
+        row![
+            icons::warning().color(style::WARNING),
+            text(format!(
+                "Not a known subnet, did you mean '{}'? Nodes on an unused subnet get no tasks",
+                suggestion
+            ))
+            .size(12)
+            .color(style::WARNING),
+            button(text(format!("Use '{}'", suggestion)).size(12))
+                .on_press_maybe(set_subnet(suggestion))
+                .padding([2, 8])
+                .style(button::secondary),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center)
+        .into()
+    } else if let Some((_, description)) = KNOWN_SUBNETS
+        .iter()
+        .find(|(name, _)| Some(*name) == selected)
+    {
+        text(*description)
+            .size(12)
+            .color(Color::from_rgb(0.6, 0.6, 0.6))
+            .into()
+    } else {
+        view_template_hint(subnet, ConfigField::Subnet.help().description)
+    };
+
+    let mut input = row![
         text_input("Enter subnet name (e.g., 'public')", subnet)
-            .on_input(move |subnet| message_factory(ConfigurationMessage::SetSubnet(subnet)))
+            .on_input_maybe(editable.then_some(move |subnet| {
+                message_factory(ConfigurationMessage::SetSubnet(subnet))
+            }))
             .width(Length::Fill)
             .style(style::default_text_input),
-        text("Specify which subnet to connect to on the Golem Network")
-            .size(12)
-            .color(Color::from_rgb(0.6, 0.6, 0.6)),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+    if editable {
+        input = input.push(
+            pick_list(known, selected, move |name: &'static str| {
+                message_factory(ConfigurationMessage::SetSubnet(name.to_string()))
+            })
+            .placeholder("Known subnets")
+            .style(style::pick_list_style),
+        );
+    }
+
+    column![view_field_label(ConfigField::Subnet), input, hint]
+        .spacing(5)
+        .into()
+}
+
+/// Node name field component
+pub fn view_node_name_field<'a, F>(node_name: &'a str, message_factory: F) -> Element<'a, Message>
+where
+    F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
+{
+    column![
+        view_field_label(ConfigField::GlmNodeName),
+        text_input("Enter node name (e.g., 'gpu-{index:3}')", node_name)
+            .on_input_maybe(
+                is_editable(ConfigField::GlmNodeName).then_some(move |name| message_factory(
+                    ConfigurationMessage::SetNodeName(name)
+                ))
+            )
+            .width(Length::Fill)
+            .style(style::default_text_input),
+        view_template_hint(node_name, ConfigField::GlmNodeName.help().description),
     ]
     .spacing(5)
     .into()
 }
 
+/// Title of a field with a tooltip holding its description and examples
+fn view_field_label<'a>(field: ConfigField) -> Element<'a, Message> {
+    let help = field.help();
+    let label = if help.optional {
+        format!("{} (Optional)", help.label)
+    } else {
+        help.label.to_string()
+    };
+
+    let mut details = column![text(help.description).size(13)]
+        .spacing(5)
+        .max_width(360);
+    if !help.examples.is_empty() {
+        details = details.push(
+            text(format!("Examples: {}", help.examples.join(", ")))
+                .size(12)
+                .color(Color::from_rgb(0.6, 0.6, 0.6)),
+        );
+    }
+
+    let mut label = row![
+        text(label).size(16),
+        tooltip(
+            icons::help().size(14).color(Color::from_rgb(0.6, 0.6, 0.6)),
+            container(details).padding(10).style(style::tooltip_box),
+            tooltip::Position::Right,
+        ),
+    ]
+    .spacing(5)
+    .align_y(Alignment::Center);
+    if !is_editable(field) {
+        label = label.push(tooltip(
+            icons::lock().size(14).color(style::WARNING),
+            container(
+                text("Set by the provisioning of this station, it can't be changed").size(13),
+            )
+            .padding(10)
+            .style(style::tooltip_box),
+            tooltip::Position::Right,
+        ));
+    }
+    label.into()
+}
+
+/// Whether operators may change `field`, i.e. the provisioning of the station doesn't lock it
+fn is_editable(field: ConfigField) -> bool {
+    !crate::utils::provisioning::is_locked(field)
+}
+
+/// Value of a locked field that is picked from a list when it can be changed
+fn view_locked_value<'a>(value: &str) -> Element<'a, Message> {
+    text_input("", value)
+        .width(Length::Fill)
+        .style(style::default_text_input)
+        .into()
+}
+
+/// Help text of a field that may contain template variables, with a preview of its expansion
+fn view_template_hint<'a>(value: &str, description: &'a str) -> Element<'a, Message> {
+    use crate::utils::template::{self, TemplateContext};
+
+    let unknown = template::unknown_variables(value);
+    if !unknown.is_empty() {
+        return row![
+            icons::error().color(style::ERROR),
+            text(format!(
+                "Unknown variable: {}. Available: {}",
+                unknown.join(", "),
+                template::VARIABLES.join(", ")
+            ))
+            .size(12)
+            .color(style::ERROR)
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center)
+        .into();
+    }
+
+    let hint = if template::has_variables(value) {
+        let date = chrono::Local::now().format("%Y%m%d").to_string();
+        let example = TemplateContext {
+            serial: Some("4C530001230905114170"),
+            model: "SanDisk Ultra",
+            index: 1,
+            date: &date,
+        };
+        format!(
+            "Expanded for each device when flashing, e.g. {}",
+            template::expand(value, &example)
+        )
+    } else {
+        format!(
+            "{}. May use {}",
+            description,
+            template::VARIABLES.join(", ")
+        )
+    };
+
+    text(hint)
+        .size(12)
+        .color(Color::from_rgb(0.6, 0.6, 0.6))
+        .into()
+}
+
 /// Wallet address field component with validation
+///
+/// Pasted text is cleaned up before it is set, see `normalize_eth_address`. The address can
+/// be shown as a QR code to compare it with the wallet app, or read from a picture of one.
 pub fn view_wallet_address_field<'a, F>(
-    wallet_address: &'a str,
-    is_valid: bool,
+    state: &'a ConfigurationState,
     message_factory: F,
 ) -> Element<'a, Message>
 where
     F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
 {
+    use crate::utils::eth::{AddressCheck, check_eth_address};
+
+    let wallet_address = state.wallet_address.as_str();
+    let is_valid = state.is_wallet_valid;
+    let paste_note = state
+        .wallet_paste_note
+        .as_ref()
+        .filter(|(address, _)| address == wallet_address)
+        .map(|(_, note)| note.as_str());
+
     let validation_message = if !wallet_address.is_empty() {
-        if is_valid {
+        let (valid, message) = match check_eth_address(wallet_address) {
+            AddressCheck::Valid => (true, "Valid Ethereum address".to_string()),
+            AddressCheck::BadChecksum => (
+                false,
+                "The letter case doesn't match the address checksum, check it for typos"
+                    .to_string(),
+            ),
+            AddressCheck::Invalid => (false, "Invalid Ethereum address format".to_string()),
+        };
+        let message = match paste_note {
+            Some(note) => format!("{} ({})", message, note),
+            None => message,
+        };
+        if valid {
             container(
                 row![
                     icons::check_circle().color(style::SUCCESS),
-                    text("Valid Ethereum address").color(style::SUCCESS)
+                    text(message).color(style::SUCCESS)
                 ]
                 .spacing(5)
                 .align_y(Alignment::Center),
@@ -251,7 +569,7 @@ where
             container(
                 row![
                     icons::error().color(style::ERROR),
-                    text("Invalid Ethereum address format").color(style::ERROR)
+                    text(message).color(style::ERROR)
                 ]
                 .spacing(5)
                 .align_y(Alignment::Center),
@@ -266,24 +584,77 @@ where
         )
     };
 
-    column![
-        text("Wallet Address (Optional)").size(16),
-        text_input("Enter Ethereum wallet address (0x...)", wallet_address)
-            .on_input(
-                move |address| message_factory(ConfigurationMessage::SetWalletAddress(address))
-            )
-            .width(Length::Fill)
-            .style(if wallet_address.is_empty() {
-                style::default_text_input
-            } else if is_valid {
-                style::valid_wallet_input
-            } else {
-                style::invalid_wallet_input
-            }),
+    let can_show_qr = !wallet_address.trim().is_empty() && is_valid;
+    let qr_data = state
+        .wallet_qr
+        .as_ref()
+        .filter(|(address, _)| address == wallet_address.trim())
+        .map(|(_, data)| data);
+
+    let editable = is_editable(ConfigField::GlmAccount);
+    let mut field = column![
+        view_field_label(ConfigField::GlmAccount),
+        row![
+            text_input("Enter Ethereum wallet address (0x...)", wallet_address)
+                .on_input_maybe(editable.then_some(move |address| message_factory(
+                    ConfigurationMessage::SetWalletAddress(address)
+                )))
+                .on_paste(
+                    move |pasted| message_factory(ConfigurationMessage::PasteWalletAddress(pasted))
+                )
+                .width(Length::Fill)
+                .style(if wallet_address.is_empty() {
+                    style::default_text_input
+                } else if is_valid {
+                    style::valid_wallet_input
+                } else {
+                    style::invalid_wallet_input
+                }),
+            button(text("Paste"))
+                .on_press_maybe(
+                    editable
+                        .then(|| message_factory(ConfigurationMessage::PasteWalletFromClipboard))
+                )
+                .padding([5, 10])
+                .style(button::secondary),
+            button(text(if qr_data.is_some() { "Hide QR" } else { "QR" }))
+                .on_press_maybe(
+                    can_show_qr.then(|| message_factory(ConfigurationMessage::ToggleWalletQr))
+                )
+                .padding([5, 10])
+                .style(button::secondary),
+            button(text("Scan"))
+                .on_press_maybe(
+                    editable.then(|| message_factory(ConfigurationMessage::ScanWalletQr))
+                )
+                .padding([5, 10])
+                .style(button::secondary),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
         validation_message,
     ]
-    .spacing(5)
-    .into()
+    .spacing(5);
+
+    if let Some(error) = &state.wallet_qr_error {
+        field = field.push(
+            row![
+                icons::error().color(style::ERROR),
+                text(error).size(12).color(style::ERROR)
+            ]
+            .spacing(5)
+            .align_y(Alignment::Center),
+        );
+    }
+    if let Some(data) = qr_data {
+        field = field.push(
+            container(qr_code(data).cell_size(4))
+                .padding(10)
+                .center_x(Length::Fill),
+        );
+    }
+
+    field.into()
 }
 
 fn add_ssh_key_button_text() -> iced::widget::Text<'static> {
@@ -303,25 +674,25 @@ pub fn view_ssh_keys_field<'a, F>(
 where
     F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
 {
-    let title = text("SSH Public Keys").size(16);
-    let description =
-        text("Public keys in OpenSSH format for user 'golem' - leave empty if not needed")
-            .size(12)
-            .color(Color::from_rgb(0.6, 0.6, 0.6));
+    let editable = is_editable(ConfigField::SshKeys);
+    let title = view_field_label(ConfigField::SshKeys);
+    let description = text(ConfigField::SshKeys.help().description)
+        .size(12)
+        .color(Color::from_rgb(0.6, 0.6, 0.6));
 
     let ssh_keys_list: Element<'a, Message> = if ssh_keys.is_empty() {
         column![
             button(add_ssh_key_button_text())
-                .on_press(message_factory(ConfigurationMessage::AddSSHKey))
+                .on_press_maybe(editable.then(|| message_factory(ConfigurationMessage::AddSSHKey)))
                 .style(style::default_button)
         ]
         .into()
     } else {
         let key_fields = keyed_column(ssh_keys.iter().enumerate().map(|(index, key)| {
             let key_input = text_input("Enter SSH public key (ssh-rsa, ssh-ed25519, etc.)", key)
-                .on_input(move |new_key| {
+                .on_input_maybe(editable.then_some(move |new_key| {
                     message_factory(ConfigurationMessage::UpdateSSHKey(index, new_key))
-                })
+                }))
                 .width(Length::Fill)
                 .style(
                     if ssh_key_errors.get(index).and_then(|e| e.as_ref()).is_some() {
@@ -331,7 +702,7 @@ where
                     },
                 );
 
-            let remove_button = if ssh_keys.len() > 1 {
+            let remove_button = if ssh_keys.len() > 1 && editable {
                 Some(
                     button(
                         row![icons::delete(), "Remove"]
@@ -369,7 +740,7 @@ where
         column![
             key_fields,
             button(add_ssh_key_button_text())
-                .on_press(message_factory(ConfigurationMessage::AddSSHKey))
+                .on_press_maybe(editable.then(|| message_factory(ConfigurationMessage::AddSSHKey)))
                 .style(style::default_button)
         ]
         .spacing(10)
@@ -392,9 +763,9 @@ where
 {
     let server_input_row = row![
         text_input("Enter configuration server URL", configuration_server)
-            .on_input(
+            .on_input_maybe(is_editable(ConfigField::ConfigurationServer).then_some(
                 move |server| message_factory(ConfigurationMessage::SetConfigurationServer(server))
-            )
+            ))
             .width(Length::Fill)
             .style(style::default_text_input),
         if state.server_config_fetching {
@@ -436,9 +807,9 @@ where
     .align_y(Alignment::Center);
 
     let mut main_column = column![
-        text("Configuration Server (Optional)").size(16),
+        view_field_label(ConfigField::ConfigurationServer),
         server_input_row,
-        text("URL to server where to look for configuration updates")
+        text(ConfigField::ConfigurationServer.help().description)
             .size(12)
             .color(Color::from_rgb(0.6, 0.6, 0.6)),
     ]
@@ -593,12 +964,18 @@ where
     F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
 {
     column![
-        text("Metrics Server").size(16),
+        view_field_label(ConfigField::Metrics),
         text_input("Enter metrics server URL", metrics_server)
-            .on_input(move |server| message_factory(ConfigurationMessage::SetMetricsServer(server)))
+            .on_input_maybe(is_editable(ConfigField::Metrics).then_some(move |server| {
+                message_factory(ConfigurationMessage::SetMetricsServer(server))
+            }))
             .width(Length::Fill)
             .style(style::default_text_input),
-        text("URL to metrics server push endpoint (default: https://metrics.golem.network:9092/)")
+        text(format!(
+            "{} (default: {})",
+            ConfigField::Metrics.help().description,
+            crate::models::DEFAULT_METRICS_URL
+        ))
             .size(12)
             .color(Color::from_rgb(0.6, 0.6, 0.6)),
     ]
@@ -639,16 +1016,20 @@ where
         }
     } else {
         container(
-            text("Central network coordination server address (leave empty by default)")
+            text(ConfigField::CentralNetHost.help().description)
                 .size(12)
                 .color(Color::from_rgb(0.6, 0.6, 0.6)),
         )
     };
 
     column![
-        text("Central Net Host").size(16),
+        view_field_label(ConfigField::CentralNetHost),
         text_input("Enter central net server address", central_net_host)
-            .on_input(move |host| message_factory(ConfigurationMessage::SetCentralNetHost(host)))
+            .on_input_maybe(
+                is_editable(ConfigField::CentralNetHost).then_some(move |host| message_factory(
+                    ConfigurationMessage::SetCentralNetHost(host)
+                ))
+            )
             .width(Length::Fill)
             .style(if central_net_host.is_empty() {
                 style::default_text_input
@@ -663,6 +1044,157 @@ where
     .into()
 }
 
+/// Key/value editor for settings the imager has no dedicated field for
+pub fn view_extra_settings_field<'a, F>(
+    state: &'a ConfigurationState,
+    file: ExtraSettingFile,
+    message_factory: F,
+) -> Element<'a, Message>
+where
+    F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
+{
+    let (title, description, key_placeholder, value_placeholder) = match file {
+        ExtraSettingFile::GolemEnv => (
+            "Extra golem.env Variables",
+            "Additional environment variables for Golem features not listed above",
+            "VARIABLE_NAME",
+            "value",
+        ),
+        ExtraSettingFile::GolemwzToml => (
+            "Extra golemwz.toml Entries",
+            "Additional top-level entries. Values are TOML (250, true, \"text\"); plain text is written as a string",
+            "key_name",
+            "value",
+        ),
+    };
+    let settings = state.extra_settings(file);
+
+    let rows = keyed_column(settings.iter().enumerate().map(|(index, setting)| {
+        let error = super::extra_setting_error(file, &setting.key);
+
+        let key_input = text_input(key_placeholder, &setting.key)
+            .on_input(move |key| {
+                message_factory(ConfigurationMessage::SetExtraSettingKey(file, index, key))
+            })
+            .width(Length::FillPortion(2))
+            .style(if error.is_some() {
+                style::error_text_input
+            } else {
+                style::default_text_input
+            });
+        let value_input = text_input(value_placeholder, &setting.value)
+            .on_input(move |value| {
+                message_factory(ConfigurationMessage::SetExtraSettingValue(
+                    file, index, value,
+                ))
+            })
+            .width(Length::FillPortion(3))
+            .style(style::default_text_input);
+        let remove_button = button(
+            row![icons::delete(), "Remove"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press(message_factory(ConfigurationMessage::RemoveExtraSetting(
+            file, index,
+        )))
+        .style(style::cancel_button_danger)
+        .padding(8);
+
+        let mut setting_column = column![
+            row![key_input, text("="), value_input, remove_button]
+                .spacing(10)
+                .align_y(Alignment::Center)
+        ]
+        .spacing(5);
+        if let Some(error) = error {
+            setting_column =
+                setting_column.push(text(error).size(12).color(Color::from_rgb(0.8, 0.2, 0.2)));
+        }
+
+        (index, setting_column.into())
+    }))
+    .spacing(10);
+
+    column![
+        text(title).size(16),
+        text(description)
+            .size(12)
+            .color(Color::from_rgb(0.6, 0.6, 0.6)),
+        rows,
+        button(text("Add Setting"))
+            .on_press(message_factory(ConfigurationMessage::AddExtraSetting(file)))
+            .style(style::default_button),
+    ]
+    .spacing(10)
+    .into()
+}
+
+/// Attachment of a first-boot script or cloud-init user-data file
+pub fn view_firstboot_field<'a, F>(
+    state: &'a ConfigurationState,
+    message_factory: F,
+) -> Element<'a, Message>
+where
+    F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
+{
+    let attachment: Element<'a, Message> = match &state.firstboot {
+        Some(firstboot) => {
+            let kind = match firstboot.kind {
+                FirstBootKind::Script => "shell script",
+                FirstBootKind::CloudInit => "cloud-init user-data",
+            };
+            row![
+                column![
+                    text(&firstboot.source_name).size(14),
+                    text(format!(
+                        "{}, {} bytes, written as {}",
+                        kind,
+                        firstboot.content.len(),
+                        firstboot.kind.file_name()
+                    ))
+                    .size(12)
+                    .color(Color::from_rgb(0.6, 0.6, 0.6)),
+                ]
+                .spacing(2)
+                .width(Length::Fill),
+                button(text("Replace"))
+                    .on_press(message_factory(ConfigurationMessage::PickFirstBootFile))
+                    .style(style::default_button)
+                    .padding(8),
+                button(
+                    row![icons::delete(), "Remove"]
+                        .spacing(5)
+                        .align_y(Alignment::Center),
+                )
+                .on_press(message_factory(ConfigurationMessage::RemoveFirstBootFile))
+                .style(style::cancel_button_danger)
+                .padding(8),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+            .into()
+        }
+        None => button(text("Attach File..."))
+            .on_press(message_factory(ConfigurationMessage::PickFirstBootFile))
+            .style(style::default_button)
+            .into(),
+    };
+
+    let mut field = column![
+        text("First-Boot Provisioning").size(16),
+        text("Shell script or cloud-init user-data run once when the node first boots, e.g. to install monitoring agents")
+            .size(12)
+            .color(Color::from_rgb(0.6, 0.6, 0.6)),
+        attachment,
+    ]
+    .spacing(10);
+    if let Some(error) = &state.firstboot_error {
+        field = field.push(text(error).size(12).color(Color::from_rgb(0.8, 0.2, 0.2)));
+    }
+    field.into()
+}
+
 /// Navigation buttons component
 pub fn view_navigation<'a>(
     back_action: Message,
@@ -721,8 +1253,9 @@ pub fn view_navigation<'a>(
 /// Workflow configuration editor with preset management
 pub fn view_configuration_editor<'a, F>(
     configuration_state: &'a ConfigurationState,
+    schema: &'a ConfigSchema,
     title: &'a str,
-    description: &'a str,
+    description: impl text::IntoFragment<'a>,
     back_action: Message,
     next_action: Option<Message>,
     back_label: &'a str,
@@ -730,6 +1263,7 @@ pub fn view_configuration_editor<'a, F>(
     configuration_presets: &'a [crate::models::ConfigurationPreset],
     new_preset_name: &'a str,
     preset_manager_action: Message,
+    wide: bool,
     message_factory: F,
 ) -> Element<'a, Message>
 where
@@ -742,18 +1276,25 @@ where
         preset_manager_action,
         message_factory,
     );
-    let configuration_form =
-        view_configuration(configuration_state, "Configuration", "", message_factory);
+    let configuration_form = view_configuration(
+        configuration_state,
+        schema,
+        "Configuration",
+        "",
+        wide,
+        message_factory,
+    );
     let save_preset_section = view_save_preset_section(new_preset_name, configuration_state);
+    let interlock = configuration_state.mainnet_interlock(schema);
     let navigation = view_navigation(
         back_action,
         next_action,
         back_label,
         next_label,
-        configuration_state.is_valid(),
+        configuration_state.is_valid() && interlock.is_none(),
     );
 
-    let content = column![
+    let mut content = column![
         header,
         scrollable(
             column![preset_section, configuration_form, save_preset_section]
@@ -761,9 +1302,16 @@ where
                 .width(Length::Fill)
         )
         .height(Length::Fill),
-        navigation,
     ]
     .width(Length::Fill);
+    if configuration_state.is_mainnet(schema) {
+        content = content.push(view_mainnet_interlock(
+            configuration_state,
+            schema,
+            message_factory,
+        ));
+    }
+    let content = content.push(navigation);
 
     container(content)
         .width(Length::Fill)
@@ -772,6 +1320,55 @@ where
         .into()
 }
 
+/// Mainnet confirmation shown above the navigation while mainnet is selected
+///
+/// Writing stays disabled until the wallet address is usable and the user has ticked the
+/// confirmation for it; changing the wallet afterwards asks again.
+fn view_mainnet_interlock<'a, F>(
+    state: &'a ConfigurationState,
+    schema: &ConfigSchema,
+    message_factory: F,
+) -> Element<'a, Message>
+where
+    F: Fn(ConfigurationMessage) -> Message + Copy + 'a,
+{
+    let wallet = state.wallet_address.trim();
+    let wallet_error = state.mainnet_wallet_error(schema);
+    let confirmed = state.mainnet_confirmed_wallet.as_deref() == Some(wallet);
+
+    let label = if wallet.is_empty() {
+        "This node runs on mainnet and is paid in real GLM".to_string()
+    } else {
+        format!("This node runs on mainnet and pays real GLM to {}", wallet)
+    };
+    let mut confirmation = checkbox(label, confirmed).size(16);
+    if wallet_error.is_none() {
+        confirmation = confirmation.on_toggle(move |checked| {
+            message_factory(ConfigurationMessage::ConfirmMainnet(checked))
+        });
+    }
+
+    let mut panel = column![
+        row![
+            icons::warning_amber().color(style::WARNING),
+            text("Mainnet").size(16).color(style::WARNING)
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center),
+        confirmation,
+    ]
+    .spacing(8);
+    if let Some(error) = wallet_error {
+        panel = panel.push(text(error).size(12).color(style::WARNING));
+    }
+
+    container(panel)
+        .width(Length::Fill)
+        .padding(15)
+        .style(style::bordered_box)
+        .into()
+}
+
 /// Preset selection section
 fn view_preset_section<'a, F>(
     configuration_presets: &'a [crate::models::ConfigurationPreset],
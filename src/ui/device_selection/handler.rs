@@ -1,6 +1,12 @@
-use super::{DeviceMessage, DeviceSelectionState, StorageDevice};
+use super::{DeviceMessage, DeviceProbe, DeviceSelectionState, GolemProbe, StorageDevice};
+use crate::models::CancelToken;
+use crate::utils::device_inventory::{self, InventoryEntry, InventoryFormat};
+use crate::utils::device_rules;
+use crate::utils::disks::{
+    DEFAULT_PROBE_TIMEOUT, LIST_DEVICES_TIMEOUT, ProbeOutcome, probe_with_timeout,
+};
 use iced::Task;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 pub fn handle_message(
     state: &mut DeviceSelectionState,
@@ -12,42 +18,86 @@ pub fn handle_message(
             state.error_message = None;
             debug!("Starting device refresh");
 
+            // Probes from the previous refresh are no longer interesting
+            state.probe_cancel.cancel();
+            state.probe_cancel = CancelToken::new();
+
             Task::perform(
                 async {
-                    // Run the blocking rs_drivelist call in a blocking task
-                    tokio::task::spawn_blocking(|| {
-                        info!("Getting available storage devices");
-                        match rs_drivelist::drive_list() {
-                            Ok(devices) => {
-                                // Filter to only include removable, non-virtual devices
-                                let storage_devices: Vec<StorageDevice> = devices
-                                    .into_iter()
-                                    .filter(|d| d.isRemovable && !d.isVirtual)
-                                    .map(|d| StorageDevice {
-                                        name: d.description,
-                                        path: d.device,
-                                        size: format!(
-                                            "{:.2} GB",
-                                            d.size as f64 / 1000.0 / 1000.0 / 1000.0
-                                        ),
-                                        is_card: d.isCard,
-                                        is_usb: d.isUSB,
-                                        is_scsi: d.isSCSI,
-                                        is_removable: d.isRemovable,
-                                    })
-                                    .collect();
-
-                                debug!("Found {} available devices", storage_devices.len());
-                                Ok(storage_devices)
-                            }
-                            Err(e) => {
-                                error!("Failed to get drive list: {}", e);
-                                Err(format!("Failed to detect storage devices: {}", e))
+                    let rules = device_rules::active();
+                    // Run the blocking rs_drivelist call in a blocking task with a timeout,
+                    // since enumeration itself can hang on a misbehaving reader
+                    let outcome = probe_with_timeout(
+                        "drive-list",
+                        LIST_DEVICES_TIMEOUT,
+                        &CancelToken::new(),
+                        move || {
+                            info!("Getting available storage devices");
+                            match rs_drivelist::drive_list() {
+                                Ok(devices) => {
+                                    // Filter to only include removable, non-virtual devices
+                                    let storage_devices: Vec<StorageDevice> = devices
+                                        .into_iter()
+                                        .filter(|d| d.isRemovable && !d.isVirtual)
+                                        .filter(|d| is_whole_disk(&d.device))
+                                        .map(|d| StorageDevice {
+                                            identity: crate::disk::identity::query_device_identity(
+                                                &d.device,
+                                            ),
+                                            name: d.description,
+                                            path: d.device,
+                                            size: format!(
+                                                "{:.2} GB",
+                                                d.size as f64 / 1000.0 / 1000.0 / 1000.0
+                                            ),
+                                            size_bytes: d.size,
+                                            is_card: d.isCard,
+                                            is_usb: d.isUSB,
+                                            is_scsi: d.isSCSI,
+                                            is_removable: d.isRemovable,
+                                            read_only: crate::disk::read_only::is_read_only(
+                                                &d.device,
+                                            )
+                                            .unwrap_or(d.isReadOnly),
+                                            health: crate::disk::DiskHealth::unknown(),
+                                            golem: GolemProbe::Pending,
+                                            nickname: None,
+                                        })
+                                        .filter(|device| {
+                                            let target = device.rule_target();
+                                            match device_rules::check(&rules, &target) {
+                                                Ok(()) => true,
+                                                Err(reason) => {
+                                                    info!("{}", reason);
+                                                    false
+                                                }
+                                            }
+                                        })
+                                        .collect();
+
+                                    debug!("Found {} available devices", storage_devices.len());
+                                    Ok(storage_devices)
+                                }
+                                Err(e) => {
+                                    error!("Failed to get drive list: {}", e);
+                                    Err(format!("Failed to detect storage devices: {}", e))
+                                }
                             }
+                        },
+                    )
+                    .await;
+
+                    match outcome {
+                        ProbeOutcome::Completed(result) => result,
+                        ProbeOutcome::TimedOut => {
+                            Err("Timed out while detecting storage devices".to_string())
                         }
-                    })
-                    .await
-                    .unwrap_or_else(|e| Err(format!("Task failed: {}", e)))
+                        ProbeOutcome::Busy => Err(
+                            "A previous device scan is still running, please try again shortly"
+                                .to_string(),
+                        ),
+                        ProbeOutcome::Cancelled => Err("Device scan was cancelled".to_string()),
+                    }
                 },
                 |result| match result {
                     Ok(devices) => crate::ui::messages::Message::DeviceSelection(
@@ -65,6 +115,39 @@ pub fn handle_message(
             state.is_refreshing = false;
             state.selected_device = None;
             info!("Loaded {} devices", state.devices.len());
+
+            // Probe each device separately so the list shows up immediately and a slow
+            // or hung device only delays its own badges
+            Task::batch(state.devices.iter().map(|device| {
+                let path = device.path.clone();
+                let cancel_token = state.probe_cancel.clone();
+                Task::perform(probe_device(path.clone(), cancel_token), move |outcome| {
+                    crate::ui::messages::Message::DeviceSelection(
+                        DeviceMessage::DeviceProbeCompleted(path.clone(), outcome),
+                    )
+                })
+            }))
+        }
+
+        DeviceMessage::DeviceProbeCompleted(path, outcome) => {
+            // The list may have been refreshed while the probe was running
+            let Some(device) = state.devices.iter_mut().find(|d| d.path == path) else {
+                return Task::none();
+            };
+
+            match outcome {
+                ProbeOutcome::Completed(probe) => {
+                    debug!("Probe for {}: {:?}", path, probe);
+                    device.health = probe.health;
+                    device.golem = probe.golem;
+                }
+                ProbeOutcome::TimedOut | ProbeOutcome::Busy => {
+                    warn!("Could not probe {} in time, details unavailable", path);
+                    device.golem = GolemProbe::Unknown;
+                }
+                // Superseded by a newer refresh which probes the device again
+                ProbeOutcome::Cancelled => {}
+            }
             Task::none()
         }
 
@@ -88,5 +171,103 @@ pub fn handle_message(
             debug!("Cleared device selection");
             Task::none()
         }
+
+        DeviceMessage::ExportInventory(format) => {
+            let entries: Vec<InventoryEntry> = state
+                .devices
+                .iter()
+                .map(StorageDevice::inventory_entry)
+                .collect();
+            Task::perform(save_inventory(entries, format), |result| {
+                crate::ui::messages::Message::DeviceSelection(DeviceMessage::InventoryExported(
+                    result,
+                ))
+            })
+        }
+
+        DeviceMessage::InventoryExported(result) => match result {
+            Ok(Some(path)) => {
+                info!("Saved the device inventory to {}", path.display());
+                Task::none()
+            }
+            // The dialog was cancelled
+            Ok(None) => Task::none(),
+            Err(error) => {
+                error!("Failed to save the device inventory: {}", error);
+                Task::done(crate::ui::messages::Message::ShowError(error))
+            }
+        },
+    }
+}
+
+/// Ask where to save the device inventory and write it there
+async fn save_inventory(
+    entries: Vec<InventoryEntry>,
+    format: InventoryFormat,
+) -> Result<Option<std::path::PathBuf>, String> {
+    let (filter_name, title) = match format {
+        InventoryFormat::Csv => ("CSV files", "Export Device Inventory as CSV"),
+        InventoryFormat::Json => ("JSON files", "Export Device Inventory as JSON"),
+    };
+    let Some(handle) = rfd::AsyncFileDialog::new()
+        .set_title(title)
+        .set_file_name(device_inventory::file_name(format, chrono::Local::now()))
+        .add_filter(filter_name, &[format.extension()])
+        .save_file()
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let path = handle.path().to_path_buf();
+    let content = match format {
+        InventoryFormat::Csv => device_inventory::to_csv(&entries),
+        InventoryFormat::Json => device_inventory::to_json(&entries),
+    };
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| format!("Failed to save the inventory to {}: {}", path.display(), e))?;
+    Ok(Some(path))
+}
+
+/// Gather health and Golem details for a single device without locking or unmounting it
+async fn probe_device(path: String, cancel_token: CancelToken) -> ProbeOutcome<DeviceProbe> {
+    let device = path.clone();
+    probe_with_timeout(&path, DEFAULT_PROBE_TIMEOUT, &cancel_token, move || {
+        let golem = match crate::disk::probe_golem_config(&device) {
+            Ok(Some(config)) => GolemProbe::Golem(config),
+            Ok(None) => GolemProbe::NotGolem,
+            Err(e) => {
+                warn!(
+                    "Could not probe {} for a Golem configuration: {}",
+                    device, e
+                );
+                GolemProbe::Unknown
+            }
+        };
+
+        DeviceProbe {
+            health: crate::disk::health::query_disk_health(&device),
+            golem,
+        }
+    })
+    .await
+}
+
+/// Whether a listed device is a whole disk rather than e.g. an eMMC boot area
+fn is_whole_disk(path: &str) -> bool {
+    match crate::disk::topology::inspect(path) {
+        Ok(topology) => match topology.whole_disk() {
+            None => true,
+            Some(disk) => {
+                info!("Not listing {}, which is part of {}", path, disk);
+                false
+            }
+        },
+        // Devices that can't be inspected are left for opening them to report on
+        Err(e) => {
+            debug!("Cannot inspect {}: {:#}", path, e);
+            true
+        }
     }
 }
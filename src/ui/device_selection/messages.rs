@@ -3,6 +3,12 @@ pub enum DeviceMessage {
     RefreshDevices,
     DevicesLoaded(Vec<super::StorageDevice>),
     DeviceLoadFailed(String),
+    DeviceProbeCompleted(
+        String,
+        crate::utils::disks::ProbeOutcome<super::DeviceProbe>,
+    ), // Device path and probe result
     SelectDevice(usize),
     ClearSelection,
+    ExportInventory(crate::utils::device_inventory::InventoryFormat), // Save the listed devices for asset tracking
+    InventoryExported(Result<Option<std::path::PathBuf>, String>), // Saved file, None if the dialog was cancelled
 }
@@ -6,11 +6,42 @@ pub struct StorageDevice {
     pub name: String,
     pub path: String,
     pub size: String,
+    pub size_bytes: u64,
     // rs-drivelist device type flags
     pub is_card: bool,
     pub is_usb: bool,
     pub is_scsi: bool,
     pub is_removable: bool,
+    // Read-only flag of the disk, writes fail until it is cleared
+    pub read_only: bool,
+    // S.M.A.R.T. health, Unknown for most card readers
+    pub health: crate::disk::DiskHealth,
+    // Existing Golem configuration, filled in by a follow-up probe after listing
+    pub golem: GolemProbe,
+    // Serial number / WWN, used to find the device again after the list changes
+    pub identity: crate::disk::DeviceIdentity,
+    // Name the user gave the device, looked up in the settings by its serial after each scan
+    pub nickname: Option<String>,
+}
+
+/// Per-device details gathered by the follow-up probe after listing
+#[derive(Debug, Clone)]
+pub struct DeviceProbe {
+    pub health: crate::disk::DiskHealth,
+    pub golem: GolemProbe,
+}
+
+/// Result of probing a device for an existing Golem configuration partition
+#[derive(Debug, Clone)]
+pub enum GolemProbe {
+    /// Probe has not finished yet
+    Pending,
+    /// Device carries a Golem image with this configuration
+    Golem(crate::disk::GolemConfig),
+    /// Device has no Golem configuration partition
+    NotGolem,
+    /// Device could not be read, e.g. due to missing permissions
+    Unknown,
 }
 
 // Device type for better UI representation
@@ -24,6 +55,57 @@ pub enum DeviceType {
 }
 
 impl StorageDevice {
+    /// Details matched against the preset manager's device assignments
+    pub fn assignment_info(&self) -> crate::utils::device_assignment::DeviceInfo<'_> {
+        crate::utils::device_assignment::DeviceInfo {
+            serial: self
+                .identity
+                .serial
+                .as_deref()
+                .or(self.identity.wwn.as_deref()),
+            model: &self.name,
+            size_bytes: self.size_bytes,
+        }
+    }
+
+    /// Details matched against the device rules of the settings
+    pub fn rule_target(&self) -> crate::utils::device_rules::RuleTarget<'_> {
+        crate::utils::device_rules::RuleTarget {
+            path: &self.path,
+            serial: self
+                .identity
+                .serial
+                .as_deref()
+                .or(self.identity.wwn.as_deref()),
+            model: &self.name,
+            vendor_id: self.identity.vendor_id.as_deref(),
+            size_bytes: self.size_bytes,
+            is_removable: self.is_removable,
+        }
+    }
+
+    /// The nickname and the model, e.g. "Bench slot 3 (SanDisk Extreme)", or only the model
+    pub fn display_name(&self) -> String {
+        match &self.nickname {
+            Some(nickname) => format!("{} ({})", nickname, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Whether `typed` names this device, by its name or its size as listed
+    ///
+    /// Case and spaces don't matter, so "16 gb" confirms a device of "16 GB".
+    pub fn is_named_by(&self, typed: &str) -> bool {
+        let normalize = |s: &str| -> String {
+            s.chars()
+                .filter(|c| !c.is_whitespace())
+                .flat_map(char::to_lowercase)
+                .collect()
+        };
+        let typed = normalize(typed);
+        !typed.is_empty() && (typed == normalize(&self.name) || typed == normalize(&self.size))
+    }
+
     /// Determine device type based on rs-drivelist flags and fallback patterns
     pub fn device_type(&self) -> DeviceType {
         // Use rs-drivelist boolean flags first (most reliable)
@@ -81,6 +163,96 @@ impl StorageDevice {
         }
     }
 
+    /// Health badge for the device card, None when health could not be determined
+    pub fn health_badge<'a, Message: 'a>(&self) -> Option<iced::Element<'a, Message>> {
+        use crate::disk::HealthStatus;
+        use iced::widget::{container, text};
+
+        let style: fn(&iced::Theme) -> container::Style = match self.health.status {
+            HealthStatus::Good => crate::style::health_good_badge,
+            HealthStatus::Warning => crate::style::health_warning_badge,
+            HealthStatus::Failing => crate::style::health_failing_badge,
+            HealthStatus::Unknown => return None,
+        };
+
+        let label = match self.health.status {
+            HealthStatus::Good => self.health.status.to_string(),
+            _ => format!("{}: {}", self.health.status, self.health.summary()),
+        };
+
+        Some(
+            container(text(label).size(12))
+                .padding([2, 8])
+                .style(style)
+                .into(),
+        )
+    }
+
+    /// Badge for the device card of a disk whose read-only flag is set
+    pub fn read_only_badge<'a, Message: 'a>(&self) -> Option<iced::Element<'a, Message>> {
+        use iced::widget::{container, text};
+
+        self.read_only.then(|| {
+            container(text("Read-only").size(12))
+                .padding([2, 8])
+                .style(crate::style::health_warning_badge)
+                .into()
+        })
+    }
+
+    /// Whether this is the same physical device as `other`, even if its path changed
+    ///
+    /// Devices without a readable serial number only match on path and size.
+    pub fn is_same_device(&self, other: &StorageDevice) -> bool {
+        self.identity
+            .same_device(&other.identity)
+            .unwrap_or_else(|| self.path == other.path && self.size == other.size)
+    }
+
+    /// True when the device is known to carry a Golem image
+    pub fn is_golem_device(&self) -> bool {
+        matches!(self.golem, GolemProbe::Golem(_))
+    }
+
+    /// True unless the probe positively found no Golem configuration partition
+    pub fn may_be_golem_device(&self) -> bool {
+        !matches!(self.golem, GolemProbe::NotGolem)
+    }
+
+    /// Short description of the Golem configuration found on the device
+    pub fn golem_summary(&self) -> Option<String> {
+        let GolemProbe::Golem(config) = &self.golem else {
+            return None;
+        };
+
+        let address = &config.wallet_address;
+        let wallet = if address.is_empty() {
+            "no wallet".to_string()
+        } else if address.is_ascii() && address.len() > 12 {
+            format!("wallet {}…{}", &address[..6], &address[address.len() - 4..])
+        } else {
+            format!("wallet {}", address)
+        };
+
+        Some(format!(
+            "Golem device (subnet {}, {})",
+            config.subnet, wallet
+        ))
+    }
+
+    /// Golem badge for the device card, None for non-Golem or unprobed devices
+    pub fn golem_badge<'a, Message: 'a>(&self) -> Option<iced::Element<'a, Message>> {
+        use iced::widget::{container, text};
+
+        let label = self.golem_summary()?;
+        Some(
+            container(text(label).size(12))
+                .padding([2, 8])
+                .style(crate::style::golem_device_badge)
+                .into(),
+        )
+    }
+
     /// Get user-friendly type name
     pub fn type_name(&self) -> &'static str {
         match self.device_type() {
@@ -91,6 +263,36 @@ impl StorageDevice {
             DeviceType::Unknown => "Storage Device",
         }
     }
+
+    /// What the device inventory export records about the device
+    pub fn inventory_entry(&self) -> crate::utils::device_inventory::InventoryEntry {
+        let (golem, config) = match &self.golem {
+            GolemProbe::Golem(config) => (Some(true), Some(config)),
+            GolemProbe::NotGolem => (Some(false), None),
+            GolemProbe::Pending | GolemProbe::Unknown => (None, None),
+        };
+        crate::utils::device_inventory::InventoryEntry {
+            path: self.path.clone(),
+            name: self.name.clone(),
+            nickname: self.nickname.clone(),
+            serial: self.identity.serial.clone(),
+            wwn: self.identity.wwn.clone(),
+            vendor_id: self.identity.vendor_id.clone(),
+            product_id: self.identity.product_id.clone(),
+            size_bytes: self.size_bytes,
+            kind: self.type_name().to_string(),
+            removable: self.is_removable,
+            read_only: self.read_only,
+            health: self.health.status.to_string(),
+            reallocated_sectors: self.health.reallocated_sectors,
+            pending_sectors: self.health.pending_sectors,
+            wear_level_percent: self.health.wear_level_percent,
+            golem,
+            subnet: config.map(|config| config.subnet.clone()),
+            wallet_address: config.map(|config| config.wallet_address.clone()),
+            node_name: config.and_then(|config| config.node_name.clone()),
+        }
+    }
 }
 
 // StorageDevice is now shared across all modules
@@ -101,6 +303,8 @@ pub struct DeviceSelectionState {
     pub selected_device: Option<usize>,
     pub is_refreshing: bool,
     pub error_message: Option<String>,
+    // Cancels outstanding per-device probes when the list is refreshed again
+    pub probe_cancel: crate::models::CancelToken,
 }
 
 impl DeviceSelectionState {
@@ -110,6 +314,26 @@ impl DeviceSelectionState {
             selected_device: None,
             is_refreshing: false,
             error_message: None,
+            probe_cancel: crate::models::CancelToken::new(),
+        }
+    }
+
+    /// Find the current index of a previously selected device
+    ///
+    /// Paths are assigned in enumeration order and can move to another disk after
+    /// hot-plugging, so the device at the old index is only used if it is still the same one.
+    pub fn find_device(&self, device: &StorageDevice) -> Option<usize> {
+        self.devices.iter().position(|d| d.is_same_device(device))
+    }
+
+    /// Give the listed devices their nicknames from the settings
+    pub fn apply_nicknames(&mut self, nicknames: &crate::utils::device_nicknames::DeviceNicknames) {
+        for device in &mut self.devices {
+            device.nickname = device
+                .assignment_info()
+                .serial
+                .and_then(|serial| nicknames.get(serial))
+                .cloned();
         }
     }
 }
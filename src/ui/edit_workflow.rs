@@ -16,20 +16,51 @@ pub fn view<'a>(
     device_selection: &'a crate::ui::device_selection::DeviceSelectionState,
     configuration: &'a crate::ui::configuration::ConfigurationState,
     preset_manager: &'a crate::ui::preset_manager::PresetManagerState,
+    window_size: iced::Size,
 ) -> Element<'a, crate::ui::messages::Message> {
     match &edit_state.workflow_state {
-        EditWorkflowState::SelectDevice => {
-            ui::view_select_existing_device(&device_selection.devices, edit_state.selected_device)
-                .map(crate::ui::messages::Message::Edit)
-        }
+        EditWorkflowState::SelectDevice => ui::view_select_existing_device(
+            &device_selection.devices,
+            edit_state.selected_device,
+            edit_state.backup_status.as_deref(),
+        )
+        .map(crate::ui::messages::Message::Edit),
         EditWorkflowState::LoadingConfiguration => {
             ui::view_loading_configuration().map(crate::ui::messages::Message::Edit)
         }
         EditWorkflowState::EditConfiguration => ui::view_edit_configuration(
             configuration,
+            edit_state.device_version.as_deref(),
             &preset_manager.presets,
             &preset_manager.new_preset_name,
+            crate::ui::layout::is_wide(window_size),
         ),
+        EditWorkflowState::ReviewChanges => ui::view_review_changes(&configuration_changes(
+            edit_state.device_config.as_ref(),
+            configuration,
+        ))
+        .map(crate::ui::messages::Message::Edit),
+        EditWorkflowState::SelectCloneSource => ui::view_select_clone_source(
+            &device_selection.devices,
+            edit_state.selected_device,
+            edit_state.clone_source,
+            edit_state.error_message.as_deref(),
+        )
+        .map(crate::ui::messages::Message::Edit),
+        EditWorkflowState::LoadingCloneSource => {
+            ui::view_loading_configuration().map(crate::ui::messages::Message::Edit)
+        }
+        EditWorkflowState::ReviewClonedConfiguration => match &edit_state.cloned_config {
+            Some(config) => ui::view_cloned_configuration(
+                config,
+                edit_state
+                    .selected_device
+                    .and_then(|index| device_selection.devices.get(index)),
+                &edit_state.clone_preset_name,
+            )
+            .map(crate::ui::messages::Message::Edit),
+            None => ui::view_loading_configuration().map(crate::ui::messages::Message::Edit),
+        },
         EditWorkflowState::Completion(success) => {
             ui::view_edit_completion(*success).map(crate::ui::messages::Message::Edit)
         }
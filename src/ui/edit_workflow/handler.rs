@@ -1,16 +1,21 @@
-use super::{EditMessage, EditState, EditWorkflowState};
+use super::{
+    EditMessage, EditState, EditWorkflowState, has_configuration_changes,
+    preset_from_device_config,
+};
 use iced::Task;
 use tracing::{debug, error, info, warn};
 
 pub fn handle_message(
     state: &mut EditState,
     device_selection: &crate::ui::device_selection::DeviceSelectionState,
+    configuration: &crate::ui::configuration::ConfigurationState,
     message: EditMessage,
 ) -> Task<crate::ui::messages::Message> {
     match message {
         EditMessage::SelectExistingDevice(index) => {
             // Note: Device bounds checking is now handled by the UI layer using shared device state
             state.selected_device = Some(index);
+            state.backup_status = None;
             debug!("Selected device for editing: {}", index);
             Task::none()
         }
@@ -29,42 +34,10 @@ pub fn handle_message(
                     );
 
                     Task::perform(
-                        async move {
-                            // Lock the device for reading
-                            match crate::disk::Disk::lock_path(&device_path, true).await {
-                                Ok(mut disk) => {
-                                    // Read configuration from device
-                                    match disk
-                                        .read_configuration("33b921b8-edc5-46a0-8baa-d0b7ad84fc71")
-                                    {
-                                        Ok(config) => {
-                                            info!(
-                                                "Successfully read configuration from device: {}",
-                                                device_path
-                                            );
-                                            Ok(config)
-                                        }
-                                        Err(e) => {
-                                            warn!(
-                                                "Failed to read configuration from device {}: {}",
-                                                device_path, e
-                                            );
-                                            Err(format!("Failed to read configuration: {}", e))
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    error!(
-                                        "Failed to lock device {} for reading: {}",
-                                        device_path, e
-                                    );
-                                    Err(format!("Failed to lock device: {}", e))
-                                }
-                            }
-                        },
+                        read_device_configuration(device_path),
                         |result| match result {
-                            Ok(config) => crate::ui::messages::Message::Edit(
-                                EditMessage::DeviceConfigurationLoaded(config),
+                            Ok(info) => crate::ui::messages::Message::Edit(
+                                EditMessage::DeviceConfigurationLoaded(info),
                             ),
                             Err(err) => crate::ui::messages::Message::Edit(
                                 EditMessage::DeviceConfigurationLoadFailed(err),
@@ -81,15 +54,23 @@ pub fn handle_message(
             }
         }
 
-        EditMessage::DeviceConfigurationLoaded(config) => {
+        EditMessage::DeviceConfigurationLoaded(info) => {
             // Set the workflow state to configuration mode
             state.workflow_state = EditWorkflowState::EditConfiguration;
 
+            // Keep the on-device values around so the pending edit can be diffed against them
+            let config = info.config;
+            state.device_config = Some(config.clone());
+            state.device_version = info.version;
+
             // Send the loaded configuration to the central configuration state
             info!("Configuration loaded from device successfully");
-            Task::done(crate::ui::messages::Message::Configuration(
+            let task = Task::done(crate::ui::messages::Message::Configuration(
                 crate::ui::configuration::ConfigurationMessage::LoadFromDevice(config),
-            ))
+            ));
+
+            // A cloned configuration replaces the target's values once they are loaded
+            apply_pending_clone(state, task)
         }
 
         EditMessage::DeviceConfigurationLoadFailed(error) => {
@@ -99,14 +80,37 @@ pub fn handle_message(
                 error
             );
             state.workflow_state = EditWorkflowState::EditConfiguration;
+            state.device_config = None;
+            state.device_version = None;
 
             // Reset configuration to defaults
-            Task::done(crate::ui::messages::Message::Configuration(
+            let task = Task::done(crate::ui::messages::Message::Configuration(
                 crate::ui::configuration::ConfigurationMessage::Reset,
-            ))
+            ));
+
+            apply_pending_clone(state, task)
         }
 
         EditMessage::SaveConfiguration => {
+            // Show the diff of on-device vs pending values before anything is written
+            state.workflow_state = EditWorkflowState::ReviewChanges;
+            debug!("Reviewing configuration changes before writing");
+            Task::none()
+        }
+
+        EditMessage::BackToEditConfiguration => {
+            state.workflow_state = EditWorkflowState::EditConfiguration;
+            Task::none()
+        }
+
+        EditMessage::ConfirmSaveConfiguration => {
+            // Skip the partition rewrite entirely for no-op edits
+            if !has_configuration_changes(state.device_config.as_ref(), configuration) {
+                info!("Configuration unchanged, skipping write to device");
+                state.workflow_state = EditWorkflowState::Completion(true);
+                return Task::none();
+            }
+
             // Save configuration to the selected device using central configuration
             if let Some(device_index) = state.selected_device {
                 // Get the device path from the device selection state
@@ -160,6 +164,175 @@ pub fn handle_message(
             Task::none()
         }
 
+        EditMessage::StartCloneConfiguration => {
+            if state.selected_device.is_some() {
+                state.clone_source = None;
+                state.cloned_config = None;
+                state.workflow_state = EditWorkflowState::SelectCloneSource;
+                debug!("Selecting source device to clone configuration from");
+            }
+            Task::none()
+        }
+
+        EditMessage::SelectCloneSource(index) => {
+            // The target device can't be its own clone source
+            if Some(index) != state.selected_device {
+                state.clone_source = Some(index);
+                debug!("Selected clone source device: {}", index);
+            }
+            Task::none()
+        }
+
+        EditMessage::ReadCloneSource => {
+            let Some(device) = state
+                .clone_source
+                .and_then(|index| device_selection.devices.get(index))
+            else {
+                return Task::none();
+            };
+
+            state.workflow_state = EditWorkflowState::LoadingCloneSource;
+            debug!(
+                "Reading configuration to clone from device: {} ({})",
+                device.name, device.path
+            );
+
+            Task::perform(
+                read_device_configuration(device.path.clone()),
+                |result| match result {
+                    Ok(info) => crate::ui::messages::Message::Edit(EditMessage::CloneSourceLoaded(
+                        info.config,
+                    )),
+                    Err(err) => {
+                        crate::ui::messages::Message::Edit(EditMessage::CloneSourceLoadFailed(err))
+                    }
+                },
+            )
+        }
+
+        EditMessage::CloneSourceLoaded(config) => {
+            info!("Configuration read from clone source device");
+            state.cloned_config = Some(config);
+            state.error_message = None;
+            state.workflow_state = EditWorkflowState::ReviewClonedConfiguration;
+            Task::none()
+        }
+
+        EditMessage::CloneSourceLoadFailed(error) => {
+            warn!("Failed to read configuration from clone source: {}", error);
+            state.cloned_config = None;
+            state.error_message = Some(error);
+            state.workflow_state = EditWorkflowState::SelectCloneSource;
+            Task::none()
+        }
+
+        EditMessage::ApplyClonedConfiguration => {
+            if state.cloned_config.is_none() {
+                return Task::none();
+            }
+
+            // Load the target device as usual; the cloned values are applied on top once
+            // its configuration has been read, so the review diff is against the target
+            info!("Applying cloned configuration to target device");
+            Task::done(crate::ui::messages::Message::Edit(
+                EditMessage::GotoEditConfiguration,
+            ))
+        }
+
+        EditMessage::SetClonePresetName(name) => {
+            state.clone_preset_name = name;
+            Task::none()
+        }
+
+        EditMessage::SaveClonedAsPreset => {
+            let name = state.clone_preset_name.trim().to_string();
+            match &state.cloned_config {
+                Some(config) if !name.is_empty() => {
+                    info!("Saving cloned configuration as preset: {}", name);
+                    let preset = preset_from_device_config(name, config);
+                    state.clone_preset_name.clear();
+                    Task::done(crate::ui::messages::Message::SaveAsPreset(preset))
+                }
+                _ => Task::none(),
+            }
+        }
+
+        EditMessage::CancelClone => {
+            state.clone_source = None;
+            state.cloned_config = None;
+            state.clone_preset_name.clear();
+            state.error_message = None;
+            state.workflow_state = EditWorkflowState::SelectDevice;
+            Task::none()
+        }
+
+        EditMessage::BackupConfiguration => {
+            let Some(device) = state
+                .selected_device
+                .and_then(|index| device_selection.devices.get(index))
+            else {
+                return Task::none();
+            };
+
+            debug!("Backing up configuration partition of {}", device.path);
+            state.backup_status = None;
+            Task::perform(
+                backup_device_configuration(
+                    device.path.clone(),
+                    crate::disk::backup::backup_file_name(&device.name),
+                ),
+                |result| crate::ui::messages::Message::Edit(EditMessage::BackupSaved(result)),
+            )
+        }
+
+        EditMessage::BackupSaved(result) => {
+            match result {
+                Ok(Some(path)) => {
+                    info!("Configuration backed up to {}", path.display());
+                    state.backup_status =
+                        Some(format!("Configuration backed up to {}", path.display()));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Failed to back up configuration: {}", e);
+                    state.backup_status = Some(format!("Backup failed: {}", e));
+                }
+            }
+            Task::none()
+        }
+
+        EditMessage::RestoreConfiguration => {
+            let Some(device) = state
+                .selected_device
+                .and_then(|index| device_selection.devices.get(index))
+            else {
+                return Task::none();
+            };
+
+            state.backup_status = None;
+            Task::perform(
+                restore_device_configuration(device.path.clone()),
+                |result| {
+                    crate::ui::messages::Message::Edit(EditMessage::ConfigurationRestored(result))
+                },
+            )
+        }
+
+        EditMessage::ConfigurationRestored(result) => {
+            match result {
+                Ok(Some(path)) => {
+                    info!("Configuration restored from {}", path.display());
+                    state.workflow_state = EditWorkflowState::Completion(true);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Failed to restore configuration: {}", e);
+                    state.backup_status = Some(format!("Restore failed: {}", e));
+                }
+            }
+            Task::none()
+        }
+
         EditMessage::EditAnother => {
             *state = EditState::new();
             Task::none()
@@ -173,3 +346,103 @@ pub fn handle_message(
         }
     }
 }
+
+/// Lock a device and read its Golem configuration and installed image version
+async fn read_device_configuration(
+    device_path: String,
+) -> Result<crate::disk::GolemDeviceInfo, String> {
+    // Lock the device for reading
+    match crate::disk::Disk::lock_path(&device_path, true).await {
+        Ok(mut disk) => {
+            // Read configuration from device
+            match disk.read_device_info("33b921b8-edc5-46a0-8baa-d0b7ad84fc71") {
+                Ok(info) => {
+                    info!(
+                        "Successfully read configuration from device: {}",
+                        device_path
+                    );
+                    Ok(info)
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to read configuration from device {}: {}",
+                        device_path, e
+                    );
+                    Err(format!("Failed to read configuration: {}", e))
+                }
+            }
+        }
+        Err(e) => {
+            error!("Failed to lock device {} for reading: {}", device_path, e);
+            Err(format!("Failed to lock device: {}", e))
+        }
+    }
+}
+
+/// Ask where to save a backup, then copy the device's configuration partition there
+async fn backup_device_configuration(
+    device_path: String,
+    file_name: String,
+) -> Result<Option<std::path::PathBuf>, String> {
+    let mut dialog = rfd::AsyncFileDialog::new()
+        .set_title("Back Up Configuration")
+        .set_file_name(&file_name)
+        .add_filter("Partition images", &[crate::disk::backup::BACKUP_EXTENSION]);
+    let backup_dir = crate::utils::paths::backup_dir()
+        .ok()
+        .filter(|dir| std::fs::create_dir_all(dir).is_ok());
+    if let Some(dir) = backup_dir {
+        dialog = dialog.set_directory(dir);
+    }
+    let Some(handle) = dialog.save_file().await else {
+        return Ok(None);
+    };
+    let path = handle.path().to_path_buf();
+
+    let disk = crate::disk::Disk::lock_path(&device_path, true)
+        .await
+        .map_err(|e| format!("Failed to lock device: {}", e))?;
+    disk.backup_config_partition(&path)
+        .map_err(|e| e.to_string())?;
+    Ok(Some(path))
+}
+
+/// Ask for a backup file and copy its files onto the device's configuration partition
+async fn restore_device_configuration(
+    device_path: String,
+) -> Result<Option<std::path::PathBuf>, String> {
+    let mut dialog = rfd::AsyncFileDialog::new()
+        .set_title("Restore Configuration")
+        .add_filter("Partition images", &[crate::disk::backup::BACKUP_EXTENSION]);
+    if let Ok(dir) = crate::utils::paths::backup_dir() {
+        dialog = dialog.set_directory(dir);
+    }
+    let Some(handle) = dialog.pick_file().await else {
+        return Ok(None);
+    };
+    let path = handle.path().to_path_buf();
+
+    let mut disk = crate::disk::Disk::lock_path(&device_path, true)
+        .await
+        .map_err(|e| format!("Failed to lock device: {}", e))?;
+    disk.restore_config_partition(&path)
+        .map_err(|e| e.to_string())?;
+    Ok(Some(path))
+}
+
+/// Chain loading of a pending cloned configuration after the target's own configuration
+fn apply_pending_clone(
+    state: &mut EditState,
+    task: Task<crate::ui::messages::Message>,
+) -> Task<crate::ui::messages::Message> {
+    match state.cloned_config.take() {
+        Some(cloned) => {
+            info!("Applying cloned configuration over target device configuration");
+            state.clone_source = None;
+            task.chain(Task::done(crate::ui::messages::Message::Configuration(
+                crate::ui::configuration::ConfigurationMessage::LoadFromDevice(cloned),
+            )))
+        }
+        None => task,
+    }
+}
@@ -2,9 +2,24 @@
 pub enum EditMessage {
     SelectExistingDevice(usize),
     GotoEditConfiguration,
-    DeviceConfigurationLoaded(crate::disk::GolemConfig),
+    DeviceConfigurationLoaded(crate::disk::GolemDeviceInfo),
     DeviceConfigurationLoadFailed(String),
     SaveConfiguration,
+    ConfirmSaveConfiguration,
+    BackToEditConfiguration,
+    StartCloneConfiguration,
+    SelectCloneSource(usize),
+    ReadCloneSource,
+    CloneSourceLoaded(crate::disk::GolemConfig),
+    CloneSourceLoadFailed(String),
+    ApplyClonedConfiguration,
+    SetClonePresetName(String),
+    SaveClonedAsPreset,
+    CancelClone,
+    BackupConfiguration,
+    BackupSaved(Result<Option<std::path::PathBuf>, String>), // None if the dialog was cancelled
+    RestoreConfiguration,
+    ConfigurationRestored(Result<Option<std::path::PathBuf>, String>),
     ConfigurationSaved,
     ConfigurationSaveFailed,
     BackToMainMenu,
@@ -1,10 +1,16 @@
+use crate::disk::ConfigField;
 use crate::models::{NetworkType, PaymentNetwork};
+use crate::ui::configuration::ExtraSettingFile;
 
 #[derive(Debug, Clone)]
 pub enum EditWorkflowState {
     SelectDevice,
     LoadingConfiguration, // Loading configuration from selected device
     EditConfiguration,    // Configuration editing (uses centralized ConfigurationState)
+    ReviewChanges,        // Side-by-side diff of on-device vs pending values before writing
+    SelectCloneSource,    // Pick the device to copy the configuration from
+    LoadingCloneSource,   // Reading configuration from the clone source device
+    ReviewClonedConfiguration, // Apply the cloned configuration or save it as a preset
     Completion(bool),     // Success or failure
 }
 
@@ -14,6 +20,12 @@ pub struct EditState {
     pub selected_device: Option<usize>,
     pub locked_disk: Option<crate::disk::Disk>,
     pub error_message: Option<String>,
+    pub device_config: Option<crate::disk::GolemConfig>, // Configuration as read from the device
+    pub device_version: Option<String>, // Image version installed on the device, if it records one
+    pub clone_source: Option<usize>,
+    pub cloned_config: Option<crate::disk::GolemConfig>, // Configuration read from the clone source
+    pub clone_preset_name: String,
+    pub backup_status: Option<String>, // Outcome of the last backup or restore of the selected device
 }
 
 impl EditState {
@@ -23,6 +35,200 @@ impl EditState {
             selected_device: None,
             locked_disk: None,
             error_message: None,
+            device_config: None,
+            device_version: None,
+            clone_source: None,
+            cloned_config: None,
+            clone_preset_name: String::new(),
+            backup_status: None,
         }
     }
 }
+
+/// Build a preset from a configuration read off a device
+pub fn preset_from_device_config(
+    name: String,
+    config: &crate::disk::GolemConfig,
+) -> crate::models::ConfigurationPreset {
+    crate::models::ConfigurationPreset {
+        name,
+        payment_network: config.payment_network,
+        subnet: config.subnet.clone(),
+        network_type: config.network_type,
+        wallet_address: config.wallet_address.clone(),
+        is_default: false,
+        non_interactive_install: config.non_interactive_install,
+        ssh_keys: config.ssh_keys.clone(),
+        configuration_server: config.configuration_server.clone(),
+        metrics_server: config.metrics_server.clone(),
+        central_net_host: config.central_net_host.clone(),
+        node_name: config.node_name.clone(),
+        // Extra settings aren't read back from devices
+        extra_env: Vec::new(),
+        extra_toml: Vec::new(),
+        firstboot: None,
+        shared: false,
+    }
+}
+
+/// A single configuration field compared between the device and the pending edit
+#[derive(Debug, Clone)]
+pub struct ConfigurationChange {
+    pub field: &'static str,
+    pub current: String,
+    pub pending: String,
+}
+
+impl ConfigurationChange {
+    pub fn is_changed(&self) -> bool {
+        self.current != self.pending
+    }
+}
+
+/// Compare the configuration read from the device with the pending configuration state.
+///
+/// Every field is returned so the view can render a full side-by-side table; use
+/// [`ConfigurationChange::is_changed`] to pick out the rows that differ. When the device
+/// configuration could not be read, all current values are shown as unknown.
+pub fn configuration_changes(
+    device_config: Option<&crate::disk::GolemConfig>,
+    pending: &crate::ui::configuration::ConfigurationState,
+) -> Vec<ConfigurationChange> {
+    fn optional(value: &Option<String>) -> String {
+        value.clone().unwrap_or_default()
+    }
+
+    let pending_ssh_keys: Vec<String> = pending
+        .ssh_keys
+        .iter()
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .collect();
+
+    let pending_values = [
+        (
+            ConfigField::PaymentNetwork.help().label,
+            pending.payment_network.to_string(),
+        ),
+        (
+            ConfigField::NetworkType.help().label,
+            pending.network_type.to_string(),
+        ),
+        (
+            ConfigField::Subnet.help().label,
+            pending.subnet.trim().to_string(),
+        ),
+        (
+            ConfigField::GlmAccount.help().label,
+            pending.wallet_address.trim().to_string(),
+        ),
+        (
+            ConfigField::GlmNodeName.help().label,
+            pending.node_name.trim().to_string(),
+        ),
+        (
+            ConfigField::NonInteractiveInstall.help().label,
+            pending.non_interactive_install.to_string(),
+        ),
+        (
+            ConfigField::SshKeys.help().label,
+            pending_ssh_keys.join("\n"),
+        ),
+        (
+            ConfigField::ConfigurationServer.help().label,
+            pending.configuration_server.trim().to_string(),
+        ),
+        (
+            ConfigField::Metrics.help().label,
+            pending.metrics_server.trim().to_string(),
+        ),
+        (
+            ConfigField::CentralNetHost.help().label,
+            pending.central_net_host.trim().to_string(),
+        ),
+    ];
+
+    let current_values: Option<[String; 10]> = device_config.map(|config| {
+        let ssh_keys: Vec<String> = config
+            .ssh_keys
+            .iter()
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect();
+
+        [
+            config.payment_network.to_string(),
+            config.network_type.to_string(),
+            config.subnet.trim().to_string(),
+            config.wallet_address.trim().to_string(),
+            optional(&config.node_name).trim().to_string(),
+            config.non_interactive_install.to_string(),
+            ssh_keys.join("\n"),
+            optional(&config.configuration_server).trim().to_string(),
+            optional(&config.metrics_server).trim().to_string(),
+            optional(&config.central_net_host).trim().to_string(),
+        ]
+    });
+
+    let mut changes: Vec<ConfigurationChange> = pending_values
+        .into_iter()
+        .enumerate()
+        .map(|(i, (field, pending))| ConfigurationChange {
+            field,
+            current: current_values
+                .as_ref()
+                .map(|values| values[i].clone())
+                .unwrap_or_else(|| "(unknown)".to_string()),
+            pending,
+        })
+        .collect();
+
+    // Fetched server configuration replaces golemwz.toml wholesale, so always flag it
+    if pending.server_config_content.is_some() {
+        changes.push(ConfigurationChange {
+            field: "Server Configuration",
+            current: String::new(),
+            pending: "(fetched from configuration server)".to_string(),
+        });
+    }
+
+    // Extra settings aren't read back from the device, so flag them whenever some are set
+    let extra_settings: Vec<String> = pending
+        .filled_in_extra_settings(ExtraSettingFile::GolemEnv)
+        .into_iter()
+        .map(|setting| format!("golem.env: {}={}", setting.key, setting.value))
+        .chain(
+            pending
+                .filled_in_extra_settings(ExtraSettingFile::GolemwzToml)
+                .into_iter()
+                .map(|setting| format!("golemwz.toml: {} = {}", setting.key, setting.value)),
+        )
+        .collect();
+    if !extra_settings.is_empty() {
+        changes.push(ConfigurationChange {
+            field: "Extra Settings",
+            current: String::new(),
+            pending: extra_settings.join("\n"),
+        });
+    }
+
+    if let Some(firstboot) = &pending.firstboot {
+        changes.push(ConfigurationChange {
+            field: "First-Boot File",
+            current: String::new(),
+            pending: format!("{} as {}", firstboot.source_name, firstboot.kind.file_name()),
+        });
+    }
+
+    changes
+}
+
+/// Whether any field differs between the device and the pending configuration
+pub fn has_configuration_changes(
+    device_config: Option<&crate::disk::GolemConfig>,
+    pending: &crate::ui::configuration::ConfigurationState,
+) -> bool {
+    configuration_changes(device_config, pending)
+        .iter()
+        .any(ConfigurationChange::is_changed)
+}
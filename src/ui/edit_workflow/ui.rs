@@ -1,7 +1,10 @@
-use iced::widget::{Column, Container, button, column, container, row, text};
+use iced::widget::{
+    Column, Container, button, column, container, row, scrollable, text, text_input,
+};
 use iced::{Alignment, Color, Element, Length};
 
-use super::EditMessage;
+use super::{ConfigurationChange, EditMessage};
+use crate::disk::ConfigField;
 use crate::models::{NetworkType, PaymentNetwork};
 use crate::ui::{device_selection::StorageDevice, icons, messages::Message};
 
@@ -9,6 +12,7 @@ use crate::ui::{device_selection::StorageDevice, icons, messages::Message};
 pub fn view_select_existing_device<'a>(
     storage_devices: &'a [StorageDevice],
     selected_device: Option<usize>,
+    backup_status: Option<&'a str>,
 ) -> Element<'a, EditMessage> {
     let title = container(
         column![
@@ -21,6 +25,12 @@ pub fn view_select_existing_device<'a>(
     .padding(15)
     .style(crate::style::page_header);
 
+    // Only offer devices that carry (or might carry) a Golem configuration
+    let hidden_devices = storage_devices
+        .iter()
+        .filter(|device| !device.may_be_golem_device())
+        .count();
+
     let device_list: Element<'a, EditMessage> = if storage_devices.is_empty() {
         container(
             column![
@@ -40,97 +50,142 @@ pub fn view_select_existing_device<'a>(
         .padding(20)
         .style(crate::style::bordered_box)
         .into()
+    } else if hidden_devices == storage_devices.len() {
+        container(
+            column![
+                text("No Golem devices found").size(18),
+                text(format!(
+                    "{} connected device(s) do not contain a Golem image. Flash an image first or connect a Golem device.",
+                    hidden_devices
+                ))
+                .size(14),
+                button(
+                    row![icons::refresh(), text("Refresh")]
+                        .spacing(5)
+                        .align_y(Alignment::Center),
+                )
+                .on_press(EditMessage::RefreshDevices)
+                .padding(8)
+                .style(button::primary)
+            ]
+            .spacing(15),
+        )
+        .padding(20)
+        .style(crate::style::bordered_box)
+        .into()
     } else {
-        column(storage_devices.iter().enumerate().map(|(i, device)| {
-            let is_selected = Some(i) == selected_device;
+        column(
+            storage_devices
+                .iter()
+                .enumerate()
+                .filter(|(_, device)| device.may_be_golem_device())
+                .map(|(i, device)| {
+                    let is_selected = Some(i) == selected_device;
 
-            // Device type icon and info
-            let device_header = row![
-                device.type_icon().color(if is_selected {
-                    crate::style::PRIMARY
-                } else {
-                    Color::from_rgb(0.6, 0.6, 0.6)
-                }),
-                column![
-                    text(&device.name).size(18).color(if is_selected {
-                        Color::from_rgb(0.1, 0.1, 0.1) // Dark text on light background
-                    } else {
-                        Color::from_rgb(0.9, 0.9, 0.9)
-                    }),
-                    text(device.type_name()).size(12).color(if is_selected {
-                        crate::style::PRIMARY
-                    } else {
-                        Color::from_rgb(0.7, 0.7, 0.7)
-                    }),
-                ]
-                .spacing(2)
-            ]
-            .spacing(15) // Increased spacing to accommodate larger icon
-            .align_y(Alignment::Center);
-
-            // Device details with better formatting
-            let device_details = column![
-                row![
-                    text("Path:").size(14).color(if is_selected {
-                        Color::from_rgb(0.3, 0.3, 0.3) // Darker gray for better contrast
-                    } else {
-                        Color::from_rgb(0.6, 0.6, 0.6)
-                    }),
-                    text(&device.path).size(14).color(if is_selected {
-                        Color::from_rgb(0.1, 0.1, 0.1) // Dark text on light background
-                    } else {
-                        Color::from_rgb(0.8, 0.8, 0.8)
-                    })
-                ]
-                .spacing(8),
-                row![
-                    text("Size:").size(14).color(if is_selected {
-                        Color::from_rgb(0.3, 0.3, 0.3) // Darker gray for better contrast
-                    } else {
-                        Color::from_rgb(0.6, 0.6, 0.6)
-                    }),
-                    text(&device.size).size(14).color(if is_selected {
-                        Color::from_rgb(0.1, 0.1, 0.1) // Dark text on light background
-                    } else {
-                        Color::from_rgb(0.8, 0.8, 0.8)
-                    })
-                ]
-                .spacing(8),
-            ]
-            .spacing(4);
+                    // Device type icon and info
+                    let device_header = row![
+                        device.type_icon().color(if is_selected {
+                            crate::style::PRIMARY
+                        } else {
+                            Color::from_rgb(0.6, 0.6, 0.6)
+                        }),
+                        column![
+                            text(device.display_name()).size(18).color(if is_selected {
+                                Color::from_rgb(0.1, 0.1, 0.1) // Dark text on light background
+                            } else {
+                                Color::from_rgb(0.9, 0.9, 0.9)
+                            }),
+                            text(device.type_name()).size(12).color(if is_selected {
+                                crate::style::PRIMARY
+                            } else {
+                                Color::from_rgb(0.7, 0.7, 0.7)
+                            }),
+                        ]
+                        .spacing(2)
+                    ]
+                    .spacing(15) // Increased spacing to accommodate larger icon
+                    .align_y(Alignment::Center);
 
-            let device_info = column![device_header, device_details]
-                .spacing(8)
-                .width(Length::Fill);
+                    // Warn about worn or failing media before anything is written to it
+                    let device_header = match device.health_badge() {
+                        Some(badge) => device_header.push(badge),
+                        None => device_header,
+                    };
 
-            let select_button = button(
-                row![icons::edit(), text("Edit")]
-                    .spacing(5)
-                    .align_y(Alignment::Center),
-            )
-            .on_press(EditMessage::SelectExistingDevice(i))
-            .padding(10)
-            .style(if is_selected {
-                button::success
-            } else {
-                button::primary
-            });
+                    let device_header = match device.read_only_badge() {
+                        Some(badge) => device_header.push(badge),
+                        None => device_header,
+                    };
 
-            container(
-                row![device_info, select_button]
-                    .spacing(20)
-                    .padding(15)
+                    let device_header = match device.golem_badge() {
+                        Some(badge) => device_header.push(badge),
+                        None => device_header,
+                    };
+
+                    // Device details with better formatting
+                    let device_details = column![
+                        row![
+                            text("Path:").size(14).color(if is_selected {
+                                Color::from_rgb(0.3, 0.3, 0.3) // Darker gray for better contrast
+                            } else {
+                                Color::from_rgb(0.6, 0.6, 0.6)
+                            }),
+                            text(&device.path).size(14).color(if is_selected {
+                                Color::from_rgb(0.1, 0.1, 0.1) // Dark text on light background
+                            } else {
+                                Color::from_rgb(0.8, 0.8, 0.8)
+                            })
+                        ]
+                        .spacing(8),
+                        row![
+                            text("Size:").size(14).color(if is_selected {
+                                Color::from_rgb(0.3, 0.3, 0.3) // Darker gray for better contrast
+                            } else {
+                                Color::from_rgb(0.6, 0.6, 0.6)
+                            }),
+                            text(&device.size).size(14).color(if is_selected {
+                                Color::from_rgb(0.1, 0.1, 0.1) // Dark text on light background
+                            } else {
+                                Color::from_rgb(0.8, 0.8, 0.8)
+                            })
+                        ]
+                        .spacing(8),
+                    ]
+                    .spacing(4);
+
+                    let device_info = column![device_header, device_details]
+                        .spacing(8)
+                        .width(Length::Fill);
+
+                    let select_button = button(
+                        row![icons::edit(), text("Edit")]
+                            .spacing(5)
+                            .align_y(Alignment::Center),
+                    )
+                    .on_press(EditMessage::SelectExistingDevice(i))
+                    .padding(10)
+                    .style(if is_selected {
+                        button::success
+                    } else {
+                        button::primary
+                    });
+
+                    container(
+                        row![device_info, select_button]
+                            .spacing(20)
+                            .padding(15)
+                            .width(Length::Fill)
+                            .align_y(Alignment::Center),
+                    )
+                    .style(if is_selected {
+                        crate::style::selected_device_card_container
+                    } else {
+                        crate::style::device_card_container
+                    })
                     .width(Length::Fill)
-                    .align_y(Alignment::Center),
-            )
-            .style(if is_selected {
-                crate::style::selected_device_card_container
-            } else {
-                crate::style::device_card_container
-            })
-            .width(Length::Fill)
-            .into()
-        }))
+                    .into()
+                }),
+        )
         .spacing(10)
         .width(Length::Fill)
         .into()
@@ -164,24 +219,66 @@ pub fn view_select_existing_device<'a>(
     .padding(12)
     .style(crate::style::navigation_back_button);
 
+    // Cloning needs a second device to read the configuration from
+    let clone_button = button(
+        row![icons::device_hub(), "Clone From Device"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press_maybe(
+        (selected_device.is_some() && storage_devices.len() > 1)
+            .then_some(EditMessage::StartCloneConfiguration),
+    )
+    .padding(12)
+    .style(button::secondary);
+
+    // Backups are raw copies of the configuration partition, restorable after a re-flash
+    let backup_button = button(
+        row![icons::file_download(), "Back Up"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press_maybe(selected_device.map(|_| EditMessage::BackupConfiguration))
+    .padding(12)
+    .style(button::secondary);
+
+    let restore_button = button(
+        row![icons::file_upload(), "Restore"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press_maybe(selected_device.map(|_| EditMessage::RestoreConfiguration))
+    .padding(12)
+    .style(button::secondary);
+
     // Add a spacer to push buttons to the bottom
     let spacer = Container::new(Column::new())
         .height(Length::Fill)
         .width(Length::Fill);
 
     let buttons = container(
-        row![back_button, next_button]
-            .spacing(15)
-            .width(Length::Fill)
-            .align_y(Alignment::Center),
+        row![
+            back_button,
+            clone_button,
+            backup_button,
+            restore_button,
+            next_button
+        ]
+        .spacing(15)
+        .width(Length::Fill)
+        .align_y(Alignment::Center),
     )
     .width(Length::Fill)
     .padding(15)
     .style(crate::style::bordered_box);
 
-    let content = column![title, device_list, spacer, buttons]
+    let mut content = column![title, device_list, spacer]
         .spacing(20)
         .width(Length::Fill);
+    if let Some(status) = backup_status {
+        content = content.push(text(status).size(14).color(Color::from_rgb(0.8, 0.8, 0.8)));
+    }
+    let content = content.push(buttons);
 
     container(content)
         .width(Length::Fill)
@@ -252,27 +349,448 @@ pub fn view_edit_completion(success: bool) -> Element<'static, EditMessage> {
 }
 
 /// Edit configuration view - now uses centralized configuration system
+///
+/// The header names the image installed on the device when it records its version.
 pub fn view_edit_configuration<'a>(
     configuration: &'a crate::ui::configuration::ConfigurationState,
+    device_version: Option<&str>,
     configuration_presets: &'a [crate::models::ConfigurationPreset],
     new_preset_name: &'a str,
+    wide: bool,
 ) -> Element<'a, Message> {
+    let description = match device_version {
+        Some(version) => format!(
+            "Edit the configuration settings for your device, which runs Golem image {}:",
+            version
+        ),
+        None => "Edit the configuration settings for your device:".to_string(),
+    };
+
     // Use the shared configuration editor from the shared module
     crate::ui::configuration::view::view_configuration_editor(
         configuration,
+        crate::disk::ConfigSchema::current(),
         "Edit Configuration",
-        "Edit the configuration settings for your device:",
+        description,
         Message::Edit(EditMessage::BackToDeviceSelection),
         Some(Message::Edit(EditMessage::SaveConfiguration)),
         "Back to Devices",
-        "Save Changes",
+        "Review Changes",
         configuration_presets,
         new_preset_name,
         Message::ManagePresets,
+        wide,
         |config_msg| Message::Configuration(config_msg),
     )
 }
 
+/// Review pending changes - side-by-side diff of on-device vs pending values
+pub fn view_review_changes<'a>(changes: &[ConfigurationChange]) -> Element<'a, EditMessage> {
+    let has_changes = changes.iter().any(ConfigurationChange::is_changed);
+
+    let title = container(
+        column![
+            text("Review Changes").size(28),
+            text(if has_changes {
+                "Check the changes below before they are written to the device"
+            } else {
+                "The configuration matches what is already on the device"
+            })
+            .size(16)
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::page_header);
+
+    let display_value = |value: &str| {
+        if value.is_empty() {
+            "(not set)".to_string()
+        } else {
+            value.to_string()
+        }
+    };
+
+    let header_row = row![
+        text("Setting").size(14).width(Length::FillPortion(2)),
+        text("On Device").size(14).width(Length::FillPortion(3)),
+        text("Pending").size(14).width(Length::FillPortion(3)),
+    ]
+    .spacing(10);
+
+    let rows = column(changes.iter().map(|change| {
+        let changed = change.is_changed();
+        let (current_color, pending_color) = if changed {
+            (crate::style::ERROR, crate::style::SUCCESS)
+        } else {
+            (Color::from_rgb(0.6, 0.6, 0.6), Color::from_rgb(0.6, 0.6, 0.6))
+        };
+
+        row![
+            text(change.field).size(14).width(Length::FillPortion(2)),
+            text(display_value(&change.current))
+                .size(14)
+                .color(current_color)
+                .width(Length::FillPortion(3)),
+            text(display_value(&change.pending))
+                .size(14)
+                .color(pending_color)
+                .width(Length::FillPortion(3)),
+        ]
+        .spacing(10)
+        .into()
+    }))
+    .spacing(8);
+
+    let diff_table = container(
+        column![header_row, scrollable(rows).height(Length::Fill)].spacing(12),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(15)
+    .style(crate::style::bordered_box);
+
+    let back_button = button(
+        row![icons::navigate_before(), "Back to Editing"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(EditMessage::BackToEditConfiguration)
+    .padding(12)
+    .style(crate::style::navigation_back_button);
+
+    let confirm_button = if has_changes {
+        button(
+            row![icons::save(), "Write Changes"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press(EditMessage::ConfirmSaveConfiguration)
+        .padding(12)
+        .style(crate::style::navigation_action_button)
+    } else {
+        button(
+            row![icons::check(), "Done"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press(EditMessage::ConfirmSaveConfiguration)
+        .padding(12)
+        .style(button::secondary)
+    };
+
+    let buttons = container(
+        row![back_button, confirm_button]
+            .spacing(15)
+            .width(Length::Fill)
+            .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::bordered_box);
+
+    container(
+        column![title, diff_table, buttons]
+            .spacing(20)
+            .width(Length::Fill),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .style(crate::style::main_box)
+    .into()
+}
+
+/// Select the device to clone the configuration from
+pub fn view_select_clone_source<'a>(
+    storage_devices: &'a [StorageDevice],
+    target_device: Option<usize>,
+    clone_source: Option<usize>,
+    error_message: Option<&'a str>,
+) -> Element<'a, EditMessage> {
+    let target_name = target_device
+        .and_then(|index| storage_devices.get(index))
+        .map(|device| device.display_name())
+        .unwrap_or_else(|| "the selected device".to_string());
+
+    let title = container(
+        column![
+            text("Clone Configuration").size(28),
+            text(format!(
+                "Select the device whose configuration should be copied to {}",
+                target_name
+            ))
+            .size(16)
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::page_header);
+
+    let device_list = column(
+        storage_devices
+            .iter()
+            .enumerate()
+            .filter(|(i, device)| Some(*i) != target_device && device.may_be_golem_device())
+            .map(|(i, device)| {
+                let is_selected = Some(i) == clone_source;
+
+                let device_info = row![
+                    device.type_icon().color(if is_selected {
+                        crate::style::PRIMARY
+                    } else {
+                        Color::from_rgb(0.6, 0.6, 0.6)
+                    }),
+                    column![
+                        text(device.display_name()).size(16).color(if is_selected {
+                            Color::from_rgb(0.1, 0.1, 0.1)
+                        } else {
+                            Color::from_rgb(0.9, 0.9, 0.9)
+                        }),
+                        text(format!("{} • {}", device.path, device.size))
+                            .size(12)
+                            .color(if is_selected {
+                                Color::from_rgb(0.3, 0.3, 0.3)
+                            } else {
+                                Color::from_rgb(0.7, 0.7, 0.7)
+                            }),
+                    ]
+                    .spacing(2)
+                    .width(Length::Fill),
+                ]
+                .spacing(15)
+                .align_y(Alignment::Center);
+
+                let select_button = button(text(if is_selected { "Selected" } else { "Select" }))
+                    .on_press(EditMessage::SelectCloneSource(i))
+                    .padding(10)
+                    .style(if is_selected {
+                        button::success
+                    } else {
+                        button::primary
+                    });
+
+                container(
+                    row![device_info, select_button]
+                        .spacing(20)
+                        .padding(15)
+                        .width(Length::Fill)
+                        .align_y(Alignment::Center),
+                )
+                .style(if is_selected {
+                    crate::style::selected_device_card_container
+                } else {
+                    crate::style::device_card_container
+                })
+                .width(Length::Fill)
+                .into()
+            }),
+    )
+    .spacing(10)
+    .width(Length::Fill);
+
+    let mut content = column![title].spacing(20).width(Length::Fill);
+
+    if let Some(error) = error_message {
+        content = content.push(
+            container(
+                row![icons::error(), text(error).size(14)]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+            )
+            .padding(10)
+            .width(Length::Fill)
+            .style(crate::style::invalid_message_container),
+        );
+    }
+
+    let back_button = button(
+        row![icons::navigate_before(), "Cancel"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(EditMessage::CancelClone)
+    .padding(12)
+    .style(crate::style::navigation_back_button);
+
+    let next_button = button(
+        row!["Read Configuration", icons::navigate_next()]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press_maybe(clone_source.map(|_| EditMessage::ReadCloneSource))
+    .padding(12)
+    .style(crate::style::navigation_action_button);
+
+    let buttons = container(
+        row![back_button, next_button]
+            .spacing(15)
+            .width(Length::Fill)
+            .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::bordered_box);
+
+    container(
+        content
+            .push(scrollable(device_list).height(Length::Fill))
+            .push(buttons),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .style(crate::style::main_box)
+    .into()
+}
+
+/// Cloned configuration summary - apply to the target device or keep as a preset
+pub fn view_cloned_configuration<'a>(
+    config: &'a crate::disk::GolemConfig,
+    target_device: Option<&'a StorageDevice>,
+    preset_name: &'a str,
+) -> Element<'a, EditMessage> {
+    let title = container(
+        column![
+            text("Cloned Configuration").size(28),
+            text("Configuration read from the source device").size(16)
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::page_header);
+
+    let field = |setting: ConfigField, value: String| {
+        row![
+            text(setting.help().label)
+                .size(14)
+                .width(Length::FillPortion(2)),
+            text(if value.is_empty() {
+                "(not set)".to_string()
+            } else {
+                value
+            })
+            .size(14)
+            .width(Length::FillPortion(3)),
+        ]
+        .spacing(10)
+    };
+
+    let summary = container(
+        column![
+            field(
+                ConfigField::PaymentNetwork,
+                config.payment_network.to_string()
+            ),
+            field(ConfigField::NetworkType, config.network_type.to_string()),
+            field(ConfigField::Subnet, config.subnet.clone()),
+            field(ConfigField::GlmAccount, config.wallet_address.clone()),
+            field(
+                ConfigField::NonInteractiveInstall,
+                config.non_interactive_install.to_string()
+            ),
+            field(
+                ConfigField::SshKeys,
+                format!("{} key(s)", config.ssh_keys.len())
+            ),
+            field(
+                ConfigField::ConfigurationServer,
+                config.configuration_server.clone().unwrap_or_default()
+            ),
+            field(
+                ConfigField::Metrics,
+                config.metrics_server.clone().unwrap_or_default()
+            ),
+            field(
+                ConfigField::CentralNetHost,
+                config.central_net_host.clone().unwrap_or_default()
+            ),
+        ]
+        .spacing(8),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::bordered_box);
+
+    let save_preset = container(
+        row![
+            text_input("Preset name", preset_name)
+                .on_input(EditMessage::SetClonePresetName)
+                .on_submit(EditMessage::SaveClonedAsPreset)
+                .padding(8)
+                .style(crate::style::default_text_input),
+            button(
+                row![icons::save(), "Save as Preset"]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press_maybe(
+                (!preset_name.trim().is_empty()).then_some(EditMessage::SaveClonedAsPreset),
+            )
+            .padding(8)
+            .style(button::secondary),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::bordered_box);
+
+    let back_button = button(
+        row![icons::navigate_before(), "Cancel"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(EditMessage::CancelClone)
+    .padding(12)
+    .style(crate::style::navigation_back_button);
+
+    let apply_label = match target_device {
+        Some(device) => format!("Apply to {}", device.display_name()),
+        None => "Apply to Device".to_string(),
+    };
+
+    let apply_button = button(
+        row![text(apply_label), icons::navigate_next()]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press_maybe(
+        target_device.map(|_| EditMessage::ApplyClonedConfiguration),
+    )
+    .padding(12)
+    .style(crate::style::navigation_action_button);
+
+    let buttons = container(
+        row![back_button, apply_button]
+            .spacing(15)
+            .width(Length::Fill)
+            .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::bordered_box);
+
+    let spacer = Container::new(Column::new())
+        .height(Length::Fill)
+        .width(Length::Fill);
+
+    container(
+        column![title, summary, save_preset, spacer, buttons]
+            .spacing(20)
+            .width(Length::Fill),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .style(crate::style::main_box)
+    .into()
+}
+
 /// Loading configuration from device - shows progress indicator
 pub fn view_loading_configuration<'a>() -> Element<'a, EditMessage> {
     let loading_content = container(
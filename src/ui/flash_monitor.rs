@@ -0,0 +1,338 @@
+/// Following every write of a batch at a glance
+///
+/// Queued and duplicated flashes take turns in the flash workflow, which only ever shows
+/// the disk being written. The monitor keeps a tab for each write, fed by the progress
+/// events the write tags with its operation ID, so an operator at a bench can look up how
+/// each target went while the next one is written. The first tab shows all of them side by
+/// side.
+use crate::models::OperationId;
+use crate::style;
+use crate::ui::flash_workflow::{FlashMessage, FlashState};
+use crate::ui::icons;
+use crate::ui::messages::Message;
+use iced::widget::{button, column, container, progress_bar, row, scrollable, text};
+use iced::{Alignment, Color, Element, Length};
+
+/// Writes kept, the oldest is dropped beyond this
+pub const MAX_WRITES: usize = 100;
+
+/// Tabs, and cards of the overview, side by side
+const PER_ROW: usize = 4;
+
+/// One write, as its progress events report it
+#[derive(Debug, Clone)]
+pub struct MonitoredWrite {
+    pub operation: OperationId,
+    pub device: String,      // Path of the disk written
+    pub device_name: String, // Name of the disk, with its nickname
+    pub started_at: chrono::DateTime<chrono::Local>,
+    pub status: WriteStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteStatus {
+    Running { fraction: f32, description: String },
+    Succeeded { verified: bool },
+    Failed(String),
+}
+
+/// The writes of this session and the tab shown of them
+#[derive(Debug, Clone, Default)]
+pub struct FlashMonitor {
+    pub writes: Vec<MonitoredWrite>,
+    pub open: bool,
+    pub selected: Option<OperationId>, // Write whose tab is shown, None for all of them
+}
+
+impl FlashMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take in the progress or the end of a write; `flash` tells the disk of a new one
+    pub fn record(&mut self, message: &FlashMessage, flash: &FlashState) {
+        let status = match message {
+            FlashMessage::Progress(_, phase) => WriteStatus::Running {
+                fraction: phase.fraction().unwrap_or(0.0),
+                description: phase.description(),
+            },
+            FlashMessage::WriteImageCompleted(_, verified) => WriteStatus::Succeeded {
+                verified: *verified,
+            },
+            FlashMessage::WriteImageFailed(_, error) => WriteStatus::Failed(error.clone()),
+            _ => return,
+        };
+        let Some(operation) = message.operation() else {
+            return;
+        };
+
+        if let Some(write) = self
+            .writes
+            .iter_mut()
+            .find(|write| write.operation == operation)
+        {
+            write.status = status;
+            return;
+        }
+        let Some(device) = &flash.selected_target else {
+            return;
+        };
+        self.writes.push(MonitoredWrite {
+            operation,
+            device: device.path.clone(),
+            device_name: device.display_name(),
+            started_at: chrono::Local::now(),
+            status,
+        });
+        if self.writes.len() > MAX_WRITES {
+            let dropped = self.writes.remove(0);
+            if self.selected == Some(dropped.operation) {
+                self.selected = None;
+            }
+        }
+    }
+
+    /// Number of writes that ended with `succeeded`
+    fn ended(&self, succeeded: bool) -> usize {
+        self.writes
+            .iter()
+            .filter(|write| match write.status {
+                WriteStatus::Running { .. } => false,
+                WriteStatus::Succeeded { .. } => succeeded,
+                WriteStatus::Failed(_) => !succeeded,
+            })
+            .count()
+    }
+}
+
+/// The monitor, with a tab for all writes and one for each
+pub fn view_flash_monitor(monitor: &FlashMonitor) -> Element<'_, Message> {
+    let header = container(
+        column![
+            text("Flash Monitor").size(28),
+            text(format!(
+                "{} writes • {} succeeded • {} failed",
+                monitor.writes.len(),
+                monitor.ended(true),
+                monitor.ended(false)
+            ))
+            .size(16)
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(style::page_header);
+
+    let tab = |label: Element<'static, Message>, selected: Option<OperationId>| {
+        let style: fn(&iced::Theme, button::Status) -> button::Style =
+            if monitor.selected == selected {
+                button::primary
+            } else {
+                button::secondary
+            };
+        button(label)
+            .on_press(Message::SelectMonitorTab(selected))
+            .padding([6, 12])
+            .style(style)
+    };
+    let mut tabs: Vec<Element<'_, Message>> = vec![
+        tab(
+            row![icons::storage().size(14), text("All devices").size(14)]
+                .spacing(6)
+                .align_y(Alignment::Center)
+                .into(),
+            None,
+        )
+        .into(),
+    ];
+    tabs.extend(monitor.writes.iter().map(|write| {
+        tab(
+            row![
+                status_icon(&write.status).size(14),
+                text(write.device_name.clone()).size(14)
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center)
+            .into(),
+            Some(write.operation),
+        )
+        .into()
+    }));
+    let mut tab_rows = column![].spacing(8);
+    let mut tabs = tabs.into_iter().peekable();
+    while tabs.peek().is_some() {
+        tab_rows = tab_rows.push(row(tabs.by_ref().take(PER_ROW)).spacing(8));
+    }
+
+    let body: Element<'_, Message> = match monitor
+        .selected
+        .and_then(|selected| monitor.writes.iter().find(|w| w.operation == selected))
+    {
+        Some(write) => view_write(write),
+        None if monitor.writes.is_empty() => container(
+            column![
+                icons::downloading()
+                    .size(32)
+                    .color(Color::from_rgb(0.6, 0.6, 0.6)),
+                text("Nothing was written yet").size(16),
+                text("Each flash of the queue or the duplicator gets a tab here once it starts")
+                    .size(12)
+                    .color(Color::from_rgb(0.7, 0.7, 0.7))
+            ]
+            .spacing(10)
+            .align_x(Alignment::Center),
+        )
+        .padding(30)
+        .width(Length::Fill)
+        .into(),
+        None => column(monitor.writes.chunks(PER_ROW).map(|writes| {
+            row(writes.iter().map(view_card))
+                .spacing(10)
+                .width(Length::Fill)
+                .into()
+        }))
+        .spacing(10)
+        .width(Length::Fill)
+        .into(),
+    };
+
+    let navigation = row![
+        button(
+            row![icons::navigate_before(), "Back"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press(Message::CloseFlashMonitor)
+        .padding(12)
+        .style(style::navigation_back_button),
+    ];
+
+    column![
+        header,
+        tab_rows,
+        scrollable(body).height(Length::Fill),
+        container(navigation).width(Length::Fill).padding([15, 0]),
+    ]
+    .spacing(20)
+    .padding(20)
+    .into()
+}
+
+/// Bar above the flash workflow while a batch is written, to open the monitor from
+pub fn view_batch_bar<'a>(monitor: &FlashMonitor, remaining: usize) -> Element<'a, Message> {
+    container(
+        row![
+            icons::queue().color(Color::from_rgb(0.3, 0.6, 1.0)),
+            text(format!(
+                "Batch flash: {} written, {} more queued",
+                monitor.ended(true) + monitor.ended(false),
+                remaining
+            ))
+            .size(14)
+            .width(Length::Fill),
+            button(text("Open Monitor").size(14))
+                .on_press(Message::OpenFlashMonitor)
+                .padding([6, 12])
+                .style(button::secondary),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+    )
+    .style(style::bordered_box)
+    .padding([8, 20])
+    .width(Length::Fill)
+    .into()
+}
+
+/// A write in the overview tab, selecting its own tab when pressed
+fn view_card(write: &MonitoredWrite) -> Element<'_, Message> {
+    button(
+        column![
+            row![
+                status_icon(&write.status),
+                text(write.device_name.clone()).size(15)
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+            text(write.device.clone())
+                .size(12)
+                .color(Color::from_rgb(0.6, 0.6, 0.6)),
+            view_status(&write.status),
+        ]
+        .spacing(6),
+    )
+    .on_press(Message::SelectMonitorTab(Some(write.operation)))
+    .padding(12)
+    .width(Length::FillPortion(1))
+    .style(button::secondary)
+    .into()
+}
+
+/// Tab of one write
+fn view_write(write: &MonitoredWrite) -> Element<'_, Message> {
+    let detail = |label: &'static str, value: String| {
+        row![
+            text(label).size(14).width(Length::Fixed(120.0)),
+            text(value).size(14)
+        ]
+        .spacing(10)
+    };
+    container(
+        column![
+            row![
+                status_icon(&write.status).size(32),
+                text(write.device_name.clone()).size(24)
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            detail("Device", write.device.clone()),
+            detail(
+                "Started",
+                write.started_at.format("%Y-%m-%d %H:%M:%S").to_string()
+            ),
+            detail("Operation", write.operation.to_string()),
+            view_status(&write.status),
+        ]
+        .spacing(12),
+    )
+    .style(style::bordered_box)
+    .padding(20)
+    .width(Length::Fill)
+    .into()
+}
+
+fn view_status(status: &WriteStatus) -> Element<'_, Message> {
+    match status {
+        WriteStatus::Running {
+            fraction,
+            description,
+        } => column![
+            progress_bar(0.0..=1.0, *fraction).style(progress_bar::primary),
+            text(description.clone()).size(12),
+        ]
+        .spacing(4)
+        .into(),
+        WriteStatus::Succeeded { verified: true } => text("Flashed and verified").size(12).into(),
+        WriteStatus::Succeeded { verified: false } => {
+            text("Flashed without verification").size(12).into()
+        }
+        WriteStatus::Failed(error) => text(format!("Failed: {}", error))
+            .size(12)
+            .color(Color::from_rgb(0.9, 0.3, 0.3))
+            .into(),
+    }
+}
+
+fn status_icon(status: &WriteStatus) -> iced::widget::Text<'static> {
+    match status {
+        WriteStatus::Running { .. } => icons::downloading().color(Color::from_rgb(0.3, 0.6, 1.0)),
+        WriteStatus::Succeeded { verified: true } => {
+            icons::check_circle().color(Color::from_rgb(0.0, 0.8, 0.3))
+        }
+        WriteStatus::Succeeded { verified: false } => {
+            icons::check_circle().color(Color::from_rgb(0.9, 0.6, 0.0))
+        }
+        WriteStatus::Failed(_) => icons::error().color(Color::from_rgb(0.9, 0.3, 0.3)),
+    }
+}
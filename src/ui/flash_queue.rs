@@ -0,0 +1,16 @@
+pub mod handler;
+pub mod messages;
+pub mod state;
+pub mod ui;
+
+pub use handler::*;
+pub use messages::*;
+pub use state::*;
+pub use ui::*;
+
+use iced::Element;
+
+/// Module-level view function for the flash queue
+pub fn view<'a>(state: &'a FlashQueueState, busy: bool) -> Element<'a, FlashQueueMessage> {
+    ui::view_flash_queue(&state.jobs, state.swap_device.as_deref(), busy)
+}
@@ -0,0 +1,19 @@
+use super::{FlashQueueMessage, FlashQueueState};
+use crate::ui::messages::Message;
+use iced::Task;
+
+pub fn handle_message(state: &mut FlashQueueState, message: FlashQueueMessage) -> Task<Message> {
+    match message {
+        // Jobs are started by the application, which owns the flash workflow
+        FlashQueueMessage::Start => return Task::done(Message::RunFlashQueue),
+        FlashQueueMessage::MoveUp(id) => state.move_job(id, -1),
+        FlashQueueMessage::MoveDown(id) => state.move_job(id, 1),
+        FlashQueueMessage::Retry(id) => state.retry(id),
+        FlashQueueMessage::Remove(id) => state.remove(id),
+        FlashQueueMessage::ClearFinished => state.clear_finished(),
+        FlashQueueMessage::AddJobs => return Task::done(Message::FlashNewImage),
+        FlashQueueMessage::OpenMonitor => return Task::done(Message::OpenFlashMonitor),
+        FlashQueueMessage::BackToMainMenu => return Task::done(Message::BackToMainMenu),
+    }
+    Task::none()
+}
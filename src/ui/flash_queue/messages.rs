@@ -0,0 +1,12 @@
+#[derive(Debug, Clone)]
+pub enum FlashQueueMessage {
+    Start,          // Flash the queued jobs one after another
+    MoveUp(u64),    // Run a job earlier, by id
+    MoveDown(u64),  // Run a job later, by id
+    Retry(u64),     // Queue a finished or failed job again
+    Remove(u64),    // Drop a job that isn't running
+    ClearFinished,  // Drop all succeeded jobs
+    AddJobs,        // Pick another image and device to queue
+    OpenMonitor,    // Show the progress of each write in tabs
+    BackToMainMenu, // Return to main menu, keeping the queue
+}
@@ -0,0 +1,128 @@
+use crate::utils::automation::StartFlash;
+
+/// A flash waiting in, or run from, the queue
+#[derive(Debug, Clone)]
+pub struct QueueJob {
+    pub id: u64,
+    pub request: StartFlash, // Device, image and preset, selected as for the automation API
+    pub device_name: String, // Name of the device when the job was queued
+    pub status: JobStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded { verified: bool },
+    Failed(String),
+}
+
+/// Flashes run one after another, e.g. through the machine's only fast USB port
+#[derive(Debug, Clone, Default)]
+pub struct FlashQueueState {
+    pub jobs: Vec<QueueJob>,
+    pub running: Option<u64>,        // Job whose flash is in progress
+    pub swap_device: Option<String>, // The next job waits for a new card in this device
+    next_id: u64,
+}
+
+impl FlashQueueState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, request: StartFlash, device_name: String) {
+        self.next_id += 1;
+        self.jobs.push(QueueJob {
+            id: self.next_id,
+            request,
+            device_name,
+            status: JobStatus::Queued,
+        });
+    }
+
+    /// Jobs that haven't been flashed yet
+    pub fn queued(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Queued)
+            .count()
+    }
+
+    /// Mark the first queued job as running and return what to flash
+    pub fn start_next(&mut self) -> Option<(u64, StartFlash)> {
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.status == JobStatus::Queued)?;
+        job.status = JobStatus::Running;
+        self.running = Some(job.id);
+        self.swap_device = None;
+        Some((job.id, job.request.clone()))
+    }
+
+    /// Record how the running job ended
+    ///
+    /// Returns whether the next job can start right away: the queue stops after a failure,
+    /// and before writing the same device again, so its card can be swapped first.
+    pub fn finish(&mut self, id: u64, status: JobStatus) -> bool {
+        if self.running == Some(id) {
+            self.running = None;
+        }
+        let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) else {
+            return false;
+        };
+        let succeeded = matches!(status, JobStatus::Succeeded { .. });
+        job.status = status;
+        let device = job.request.device.clone();
+
+        let Some(next) = self.jobs.iter().find(|job| job.status == JobStatus::Queued) else {
+            return false;
+        };
+        if !succeeded {
+            return false;
+        }
+        if next.request.device == device {
+            self.swap_device = Some(device);
+            return false;
+        }
+        true
+    }
+
+    /// Swap a job with its neighbour, `offset` places away
+    pub fn move_job(&mut self, id: u64, offset: isize) {
+        let Some(index) = self.jobs.iter().position(|job| job.id == id) else {
+            return;
+        };
+        let Some(other) = index
+            .checked_add_signed(offset)
+            .filter(|&other| other < self.jobs.len())
+        else {
+            return;
+        };
+        self.jobs.swap(index, other);
+    }
+
+    pub fn retry(&mut self, id: u64) {
+        if let Some(job) = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.id == id && job.status != JobStatus::Running)
+        {
+            job.status = JobStatus::Queued;
+        }
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.jobs
+            .retain(|job| job.id != id || job.status == JobStatus::Running);
+        if self.queued() == 0 {
+            self.swap_device = None;
+        }
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs
+            .retain(|job| !matches!(job.status, JobStatus::Succeeded { .. }));
+    }
+}
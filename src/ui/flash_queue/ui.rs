@@ -0,0 +1,220 @@
+use super::{FlashQueueMessage, JobStatus, QueueJob};
+use crate::style;
+use crate::ui::icons;
+use iced::widget::{button, column, container, row, scrollable, text};
+use iced::{Alignment, Color, Element, Length};
+
+/// Main flash queue view
+pub fn view_flash_queue<'a>(
+    jobs: &'a [QueueJob],
+    swap_device: Option<&'a str>,
+    busy: bool,
+) -> Element<'a, FlashQueueMessage> {
+    let queued = jobs
+        .iter()
+        .filter(|job| job.status == JobStatus::Queued)
+        .count();
+    let running = jobs.iter().any(|job| job.status == JobStatus::Running);
+
+    let header = container(
+        column![
+            text("Flash Queue").size(28),
+            text("Queued flashes run one after another, in this order").size(16)
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(style::page_header);
+
+    let jobs_section: Element<'a, FlashQueueMessage> = if jobs.is_empty() {
+        container(
+            column![
+                icons::queue()
+                    .size(32)
+                    .color(Color::from_rgb(0.6, 0.6, 0.6)),
+                text("The queue is empty").size(16),
+                text("Choose \"Add to Queue\" when confirming a flash to queue it")
+                    .size(12)
+                    .color(Color::from_rgb(0.7, 0.7, 0.7))
+            ]
+            .spacing(10)
+            .align_x(Alignment::Center),
+        )
+        .padding(30)
+        .width(Length::Fill)
+        .into()
+    } else {
+        column(
+            jobs.iter()
+                .enumerate()
+                .map(|(index, job)| view_job(index, job, jobs.len())),
+        )
+        .spacing(10)
+        .width(Length::Fill)
+        .into()
+    };
+
+    let mut content = column![header].spacing(20);
+    if let (Some(device), true) = (swap_device, queued > 0) {
+        content = content.push(
+            container(
+                row![
+                    icons::sd_storage().color(Color::from_rgb(0.3, 0.6, 1.0)),
+                    text(format!(
+                        "Insert the next card into {} and press Continue",
+                        device
+                    ))
+                    .size(14)
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+            .style(style::bordered_box)
+            .padding(12)
+            .width(Length::Fill),
+        );
+    }
+
+    let start_label = if swap_device.is_some() {
+        "Continue"
+    } else {
+        "Start Queue"
+    };
+    let navigation = row![
+        button(
+            row![icons::navigate_before(), "Back"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press(FlashQueueMessage::BackToMainMenu)
+        .padding(12)
+        .style(style::navigation_back_button),
+        container(row![]).width(Length::Fill),
+        button(
+            row![icons::queue(), "Monitor"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press(FlashQueueMessage::OpenMonitor)
+        .padding(12)
+        .style(button::secondary),
+        button(
+            row![icons::add_to_queue(), "Add Jobs"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press_maybe((!running).then_some(FlashQueueMessage::AddJobs))
+        .padding(12)
+        .style(button::secondary),
+        button(text("Clear Finished"))
+            .on_press_maybe(
+                jobs.iter()
+                    .any(|job| matches!(job.status, JobStatus::Succeeded { .. }))
+                    .then_some(FlashQueueMessage::ClearFinished)
+            )
+            .padding(12)
+            .style(button::secondary),
+        button(
+            row![icons::start(), start_label]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press_maybe((queued > 0 && !running && !busy).then_some(FlashQueueMessage::Start))
+        .padding(12)
+        .style(style::navigation_action_button),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+
+    content
+        .push(scrollable(jobs_section).height(Length::Fill))
+        .push(container(navigation).width(Length::Fill).padding([15, 0]))
+        .padding(20)
+        .into()
+}
+
+/// One job with its status and the actions it allows
+fn view_job(index: usize, job: &QueueJob, job_count: usize) -> Element<'_, FlashQueueMessage> {
+    let (status_icon, status_text) = match &job.status {
+        JobStatus::Queued => (icons::timer(), "Queued".to_string()),
+        JobStatus::Running => (
+            icons::downloading().color(Color::from_rgb(0.3, 0.6, 1.0)),
+            "Flashing...".to_string(),
+        ),
+        JobStatus::Succeeded { verified: true } => (
+            icons::check_circle().color(Color::from_rgb(0.0, 0.8, 0.3)),
+            "Flashed and verified".to_string(),
+        ),
+        JobStatus::Succeeded { verified: false } => (
+            icons::check_circle().color(Color::from_rgb(0.9, 0.6, 0.0)),
+            "Flashed without verification".to_string(),
+        ),
+        JobStatus::Failed(error) => (
+            icons::error().color(Color::from_rgb(0.9, 0.3, 0.3)),
+            format!("Failed: {}", error),
+        ),
+    };
+
+    let request = &job.request;
+    let details = column![
+        text(format!(
+            "{}. {} {}",
+            index + 1,
+            request.channel.as_deref().unwrap_or_default(),
+            request.version.as_deref().unwrap_or("(latest)")
+        ))
+        .size(15),
+        text(format!(
+            "{} ({}) • preset: {}",
+            job.device_name,
+            request.device,
+            request.preset.as_deref().unwrap_or("assigned or default")
+        ))
+        .size(12)
+        .color(Color::from_rgb(0.6, 0.6, 0.6)),
+        text(status_text).size(12),
+    ]
+    .spacing(4)
+    .width(Length::Fill);
+
+    let running = job.status == JobStatus::Running;
+    let mut actions = row![
+        button(icons::expand_less())
+            .on_press_maybe((index > 0).then_some(FlashQueueMessage::MoveUp(job.id)))
+            .padding(8)
+            .style(button::secondary),
+        button(icons::expand_more())
+            .on_press_maybe((index + 1 < job_count).then_some(FlashQueueMessage::MoveDown(job.id)))
+            .padding(8)
+            .style(button::secondary),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+    if !running && job.status != JobStatus::Queued {
+        actions = actions.push(
+            button(icons::refresh())
+                .on_press(FlashQueueMessage::Retry(job.id))
+                .padding(8)
+                .style(button::secondary),
+        );
+    }
+    if !running {
+        actions = actions.push(
+            button(icons::delete())
+                .on_press(FlashQueueMessage::Remove(job.id))
+                .padding(8)
+                .style(button::danger),
+        );
+    }
+
+    container(
+        row![status_icon, details, actions]
+            .spacing(10)
+            .align_y(Alignment::Center),
+    )
+    .style(style::bordered_box)
+    .padding(12)
+    .width(Length::Fill)
+    .into()
+}
@@ -16,15 +16,32 @@ pub fn view<'a>(
     device_selection: &'a crate::ui::device_selection::DeviceSelectionState,
     configuration: &'a crate::ui::configuration::ConfigurationState,
     preset_manager: &'a crate::ui::preset_manager::PresetManagerState,
+    flash_history: &'a crate::utils::flash_history::FlashHistory,
     is_loading_repo: bool,
+    window_size: iced::Size,
+    typed_confirmation: crate::utils::app_settings::TypedConfirmation,
+    target_profile: &crate::utils::compatibility::TargetProfile,
+    recent_images: &'a [crate::utils::recent_images::RecentImage],
 ) -> Element<'a, crate::ui::messages::Message> {
+    let manifest_warning = flash_state.manifest_status.warning();
+
     match &flash_state.workflow_state {
         FlashWorkflowState::SelectOsImage => {
             if !flash_state.os_image_groups.is_empty() {
                 ui::view_select_os_image_groups(
                     &flash_state.os_image_groups,
                     flash_state.selected_os_image_group,
+                    flash_state.local_image.as_ref(),
+                    flash_state.local_analysis.as_ref(),
+                    flash_history.measured_speed(
+                        flash_state
+                            .selected_device
+                            .and_then(|idx| device_selection.devices.get(idx))
+                            .and_then(|device| device.assignment_info().serial),
+                    ),
+                    recent_images,
                     is_loading_repo,
+                    manifest_warning.as_deref(),
                 )
                 .map(crate::ui::messages::Message::Flash)
             } else {
@@ -56,31 +73,101 @@ pub fn view<'a>(
             *uncompressed_size,
         )
         .map(crate::ui::messages::Message::Flash),
-        FlashWorkflowState::SelectTargetDevice => {
-            ui::view_select_target_device(&device_selection.devices, flash_state.selected_device)
-                .map(crate::ui::messages::Message::Flash)
-        }
+        FlashWorkflowState::SelectTargetDevice => ui::view_select_target_device(
+            &device_selection.devices,
+            flash_state.selected_device,
+            flash_state.source_check.as_ref(),
+            flash_state.local_analysis.as_ref(),
+            flash_state.speed_test.as_ref(),
+            flash_state.capacity_test.as_ref(),
+            flash_state.nickname_edit.as_ref(),
+            flash_state.whole_disk_offer.as_ref(),
+        )
+        .map(crate::ui::messages::Message::Flash),
         FlashWorkflowState::ConfigureSettings => {
             // Use shared configuration editor with preset support
             // Return app messages directly (no mapping) to match edit workflow pattern
             ui::view_flash_configure_settings(
                 configuration,
+                flash_state
+                    .selected_image()
+                    .map_or(crate::disk::ConfigSchema::current(), |image| {
+                        &image.config_schema
+                    }),
                 &preset_manager.presets,
                 &preset_manager.new_preset_name,
                 preset_manager.show_manager,
                 preset_manager.editor.as_ref(),
+                crate::ui::layout::is_wide(window_size),
             )
         }
-        FlashWorkflowState::WritingImage(progress) => {
-            ui::view_writing_process(*progress, "Writing Image")
-                .map(crate::ui::messages::Message::Flash)
+        FlashWorkflowState::ConfirmWrite => {
+            let device = flash_state
+                .selected_device
+                .and_then(|idx| device_selection.devices.get(idx));
+            ui::view_confirm_write(
+                device,
+                flash_state.target_layout.as_ref(),
+                flash_state.target_usage.as_ref(),
+                device
+                    .and_then(|device| device.assignment_info().serial)
+                    .and_then(|serial| flash_history.wear(serial)),
+                flash_state.target_read_only(device_selection),
+                manifest_warning.as_deref(),
+                flash_state.image_untrusted(),
+                flash_state.preserve_config,
+                // Queued jobs look their image up in the repository
+                flash_state
+                    .selected_image()
+                    .is_some_and(|image| !image.local),
+                device
+                    .filter(|device| typed_confirmation.required(device.is_removable))
+                    .map(|_| flash_state.typed_device_name.as_str()),
+                flash_state
+                    .selected_image()
+                    .map(|image| {
+                        crate::utils::compatibility::incompatibilities(
+                            &image.requirements,
+                            &target_profile.resolve(),
+                        )
+                    })
+                    .unwrap_or_default(),
+            )
+            .map(crate::ui::messages::Message::Flash)
         }
-        FlashWorkflowState::VerifyingImage(progress) => {
-            ui::view_writing_process(*progress, "Verifying Image")
+        FlashWorkflowState::ClearingPartitions { progress, message } => {
+            ui::view_clearing_partitions(*progress, message)
                 .map(crate::ui::messages::Message::Flash)
         }
-        FlashWorkflowState::Completion(success) => {
-            ui::view_flash_completion(*success, None).map(crate::ui::messages::Message::Flash)
+        FlashWorkflowState::Flashing(crate::disk::FlashPhase::Writing {
+            bytes,
+            download: Some(download),
+            latency,
+            ..
+        }) => ui::view_streaming_image(
+            download.downloaded,
+            download.size,
+            *bytes,
+            latency,
+            flash_state.show_write_details,
+        )
+        .map(crate::ui::messages::Message::Flash),
+        FlashWorkflowState::Flashing(phase) => {
+            ui::view_writing_process(phase, flash_state.show_write_details)
+                .map(crate::ui::messages::Message::Flash)
         }
+        FlashWorkflowState::Completion(success) => ui::view_flash_completion(
+            *success,
+            flash_state.write_verified,
+            None,
+            flash_state
+                .config_backup
+                .as_ref()
+                .map(|(path, _)| path.as_path()),
+            flash_state.flash_report.is_some(),
+            flash_state.report_path.as_deref(),
+            flash_state.wipe_undo.as_ref(),
+        )
+        .map(crate::ui::messages::Message::Flash),
     }
 }
@@ -1,7 +1,14 @@
-use super::{FlashMessage, FlashState, FlashWorkflowState};
-use crate::disk::{Disk, WriteProgress};
-use crate::models::CancelToken;
+use super::{
+    CapacityTest, CapacityTestStatus, FlashMessage, FlashState, FlashWorkflowState, LocalAnalysis,
+    NicknameEdit, SourceCheck, SourceCheckStatus, SpeedTest, SpeedTestStatus,
+};
+use crate::disk::{Disk, FlashPhase, ImageFormat, ImageSource};
+use crate::models::{CancelToken, OperationId};
+use crate::ui::configuration::ExtraSettingFile;
+use crate::utils::app_settings::AppSettings;
+use crate::utils::flash_report::{FlashReport, ReportFormat};
 use crate::utils::repo::ImageRepo;
+use crate::utils::template::{self, TemplateContext};
 use crate::utils::validation::{is_valid_central_net_host, is_valid_url, validate_ssh_keys};
 use iced::Task;
 use std::sync::Arc;
@@ -12,12 +19,14 @@ pub fn handle_message(
     image_repo: &Arc<ImageRepo>,
     device_selection: &crate::ui::device_selection::DeviceSelectionState,
     configuration: &crate::ui::configuration::ConfigurationState,
+    settings: &AppSettings,
     message: FlashMessage,
 ) -> Task<crate::ui::messages::Message> {
     match message {
         FlashMessage::SelectOsImage(index) => {
             if let Some(image) = state.os_images.get(index) {
                 state.selected_os_image = Some(index);
+                state.clear_local_image();
                 debug!("Selected OS image: {}", image.name);
             }
             Task::none()
@@ -34,6 +43,8 @@ pub fn handle_message(
                 };
 
                 state.selected_os_image_group = Some((group_index, version_index));
+                state.clear_local_image();
+                state.stream_image = false;
                 debug!(
                     "Selected OS image from group: {} version {}",
                     image.name, image.version
@@ -53,14 +64,147 @@ pub fn handle_message(
             Task::none()
         }
 
+        FlashMessage::PickLocalImage => Task::perform(pick_local_image(), |result| {
+            crate::ui::messages::Message::Flash(FlashMessage::LocalImagePicked(result))
+        }),
+
+        FlashMessage::LocalImagePicked(result) => match result {
+            Ok(Some(path)) => {
+                info!("Selected image file {}", path.display());
+                state.local_image = Some(super::OsImage::from_local_file(&path));
+                state.selected_os_image = None;
+                state.selected_os_image_group = None;
+                state.stream_image = false;
+                start_local_analysis(state, path)
+            }
+            Ok(None) => Task::none(),
+            Err(e) => {
+                error!("{}", e);
+                Task::done(crate::ui::messages::Message::ShowError(e))
+            }
+        },
+
+        FlashMessage::LocalImageDropped(path) => Task::perform(check_image_file(path), |result| {
+            crate::ui::messages::Message::Flash(FlashMessage::DroppedImageChecked(result))
+        }),
+
+        // A dropped image goes straight on to the device selection while it is analyzed
+        FlashMessage::DroppedImageChecked(result) => match result {
+            Ok(path) => {
+                let mut handle = |message| {
+                    handle_message(
+                        state,
+                        image_repo,
+                        device_selection,
+                        configuration,
+                        settings,
+                        message,
+                    )
+                };
+                let analysis = handle(FlashMessage::LocalImagePicked(Ok(Some(path))));
+                Task::batch([analysis, handle(FlashMessage::GotoSelectTargetDevice)])
+            }
+            Err(e) => {
+                error!("{}", e);
+                Task::done(crate::ui::messages::Message::ShowError(e))
+            }
+        },
+
+        FlashMessage::UseRecentImage(recent) => {
+            if !recent.is_available() {
+                return Task::done(crate::ui::messages::Message::ShowError(format!(
+                    "{} no longer exists",
+                    recent.location
+                )));
+            }
+            let Some(channel) = &recent.channel else {
+                return Task::done(crate::ui::messages::Message::Flash(
+                    FlashMessage::LocalImagePicked(Ok(Some(recent.location.into()))),
+                ));
+            };
+            let found = state
+                .os_image_groups
+                .iter()
+                .enumerate()
+                .find(|(_, group)| &group.channel_name == channel)
+                .and_then(|(group_index, group)| {
+                    std::iter::once(&group.latest_version)
+                        .chain(&group.older_versions)
+                        .position(|image| image.version == recent.version)
+                        .map(|version_index| (group_index, version_index))
+                });
+            match found {
+                Some((group_index, version_index)) => {
+                    Task::done(crate::ui::messages::Message::Flash(
+                        FlashMessage::SelectOsImageFromGroup(group_index, version_index),
+                    ))
+                }
+                None => Task::done(crate::ui::messages::Message::ShowError(format!(
+                    "Version {} is no longer in the {} channel",
+                    recent.version, channel
+                ))),
+            }
+        }
+
+        FlashMessage::LocalImageProgress(path, bytes_read) => {
+            if let Some(analysis) = state
+                .local_analysis
+                .as_mut()
+                .filter(|analysis| analysis.path == path)
+            {
+                analysis.bytes_read = bytes_read;
+            }
+            Task::none()
+        }
+
+        FlashMessage::LocalImageAnalyzed(path, result) => {
+            if !state
+                .local_analysis
+                .as_ref()
+                .is_some_and(|analysis| analysis.path == path)
+            {
+                return Task::none();
+            }
+            state.local_analysis = None;
+            match result {
+                Ok(metadata) => {
+                    if let Some(image) = state
+                        .local_image
+                        .as_mut()
+                        .filter(|image| image.path.as_ref() == Some(&path))
+                    {
+                        image.created = metadata.created_at.clone();
+                        image.metadata = Some(metadata);
+                    }
+                    Task::none()
+                }
+                Err(e) => {
+                    error!("Failed to analyze {}: {}", path, e);
+                    state.local_image = None;
+                    if matches!(state.workflow_state, FlashWorkflowState::SelectTargetDevice) {
+                        state.workflow_state = FlashWorkflowState::SelectOsImage;
+                    }
+                    Task::done(crate::ui::messages::Message::ShowError(e))
+                }
+            }
+        }
+
         FlashMessage::GotoSelectTargetDevice => {
             state.workflow_state = FlashWorkflowState::SelectTargetDevice;
             debug!(
                 "Entering target device selection - delegating device refresh to DeviceSelection module"
             );
-            Task::done(crate::ui::messages::Message::DeviceSelection(
-                crate::ui::device_selection::DeviceMessage::RefreshDevices,
-            ))
+            let source_check = if settings.check_image_before_writing && !state.stream_image {
+                start_source_check(state)
+            } else {
+                Task::none()
+            };
+            Task::batch([
+                Task::done(crate::ui::messages::Message::DeviceSelection(
+                    crate::ui::device_selection::DeviceMessage::RefreshDevices,
+                )),
+                source_check,
+            ])
         }
 
         FlashMessage::GotoConfigureSettings => {
@@ -69,11 +213,44 @@ pub fn handle_message(
         }
 
         FlashMessage::SelectTargetDevice(index) => {
+            if state.selected_device != Some(index) {
+                state.failed_attempts = 0;
+            }
             state.selected_device = Some(index);
+            state.selected_target = device_selection.devices.get(index).cloned();
+            state.whole_disk_offer = None;
             debug!("Selected target device: {}", index);
             Task::none()
         }
 
+        FlashMessage::UseWholeDisk => {
+            let Some(offer) = state.whole_disk_offer.take() else {
+                return Task::none();
+            };
+            // The list may have been refreshed since the disk was offered
+            match device_selection
+                .devices
+                .iter()
+                .position(|device| device.path == offer.disk)
+            {
+                Some(index) => {
+                    info!("Using the whole disk {} instead", offer.disk);
+                    Task::done(crate::ui::messages::Message::Flash(
+                        FlashMessage::SelectTargetDevice(index),
+                    ))
+                }
+                None => Task::done(crate::ui::messages::Message::ShowError(format!(
+                    "{} is no longer listed",
+                    offer.disk
+                ))),
+            }
+        }
+
+        FlashMessage::DismissWholeDisk => {
+            state.whole_disk_offer = None;
+            Task::none()
+        }
+
         FlashMessage::RefreshTargetDevices => {
             debug!("Delegating target device refresh to DeviceSelection module");
             Task::done(crate::ui::messages::Message::DeviceSelection(
@@ -81,6 +258,211 @@ pub fn handle_message(
             ))
         }
 
+        FlashMessage::ExportDeviceInventory(format) => {
+            Task::done(crate::ui::messages::Message::DeviceSelection(
+                crate::ui::device_selection::DeviceMessage::ExportInventory(format),
+            ))
+        }
+
+        FlashMessage::TestDeviceSpeed(index) => {
+            let Some(device) = device_selection.devices.get(index) else {
+                return Task::none();
+            };
+            if state.speed_test.as_ref().is_some_and(SpeedTest::is_running)
+                || state.is_testing(&device.path)
+            {
+                return Task::none();
+            }
+            let path = device.path.clone();
+            if device.read_only {
+                state.speed_test = Some(SpeedTest {
+                    path,
+                    status: SpeedTestStatus::Failed(
+                        "The device is read-only, clear its read-only flag to test it".to_string(),
+                    ),
+                });
+                return Task::none();
+            }
+
+            info!("Testing the speed of {}", path);
+            state.speed_test = Some(SpeedTest {
+                path: path.clone(),
+                status: SpeedTestStatus::Running,
+            });
+            Task::perform(test_device_speed(path.clone()), move |result| {
+                crate::ui::messages::Message::Flash(FlashMessage::DeviceSpeedTested(
+                    path.clone(),
+                    result,
+                ))
+            })
+        }
+
+        FlashMessage::DeviceSpeedTested(path, result) => {
+            if let Some(test) = state.speed_test.as_mut().filter(|test| test.path == path) {
+                test.status = match result {
+                    Ok(result) => SpeedTestStatus::Done(result),
+                    Err(e) => {
+                        error!("Speed test of {} failed: {}", path, e);
+                        SpeedTestStatus::Failed(e)
+                    }
+                };
+            }
+            Task::none()
+        }
+
+        FlashMessage::TestDeviceCapacity(index) => {
+            let Some(device) = device_selection.devices.get(index) else {
+                return Task::none();
+            };
+            if state
+                .capacity_test
+                .as_ref()
+                .is_some_and(CapacityTest::is_running)
+                || state.is_testing(&device.path)
+            {
+                return Task::none();
+            }
+            let status = if device.read_only {
+                CapacityTestStatus::Failed(
+                    "The device is read-only, clear its read-only flag to test it".to_string(),
+                )
+            } else {
+                CapacityTestStatus::Confirming
+            };
+            // The test erases the device just like a flash, so it asks for the same name
+            let typed_name = settings
+                .typed_confirmation
+                .required(device.is_removable)
+                .then(String::new);
+            state.capacity_test = Some(CapacityTest {
+                path: device.path.clone(),
+                status,
+                cancel_token: CancelToken::new(),
+                typed_name,
+            });
+            Task::none()
+        }
+
+        FlashMessage::SetCapacityTestTypedName(typed) => {
+            if let Some(typed_name) = state
+                .capacity_test
+                .as_mut()
+                .and_then(|test| test.typed_name.as_mut())
+            {
+                *typed_name = typed;
+            }
+            Task::none()
+        }
+
+        FlashMessage::ConfirmCapacityTest => {
+            let Some(test) = state
+                .capacity_test
+                .as_mut()
+                .filter(|test| test.status == CapacityTestStatus::Confirming)
+            else {
+                return Task::none();
+            };
+            let Some(device) = device_selection
+                .devices
+                .iter()
+                .find(|device| device.path == test.path)
+            else {
+                return Task::none();
+            };
+            let required = settings.typed_confirmation.required(device.is_removable);
+            if required
+                && !test
+                    .typed_name
+                    .as_deref()
+                    .is_some_and(|typed| device.is_named_by(typed))
+            {
+                warn!(
+                    "Not testing the capacity of {}, its name wasn't typed",
+                    test.path
+                );
+                return Task::none();
+            }
+            info!("Testing the capacity of {}, erasing it", test.path);
+            test.status = CapacityTestStatus::Running(0.0);
+            start_capacity_test(test.path.clone(), test.cancel_token.clone())
+        }
+
+        FlashMessage::CancelCapacityTest => {
+            // A test still asking for confirmation is dropped, a running one stops at its next block
+            let confirming = state
+                .capacity_test
+                .take_if(|test| test.status == CapacityTestStatus::Confirming);
+            if let (None, Some(test)) = (confirming, &state.capacity_test) {
+                info!("Cancelling the capacity test of {}", test.path);
+                test.cancel_token.cancel();
+            }
+            Task::none()
+        }
+
+        FlashMessage::CapacityTestProgress(path, fraction) => {
+            if let Some(test) = state
+                .capacity_test
+                .as_mut()
+                .filter(|test| test.path == path && test.is_running())
+            {
+                test.status = CapacityTestStatus::Running(fraction);
+            }
+            Task::none()
+        }
+
+        FlashMessage::DeviceCapacityTested(path, result) => {
+            if let Some(test) = state
+                .capacity_test
+                .as_mut()
+                .filter(|test| test.path == path)
+            {
+                test.status = match result {
+                    Ok(report) => CapacityTestStatus::Done(report),
+                    Err(e) => {
+                        error!("Capacity test of {} failed: {}", path, e);
+                        CapacityTestStatus::Failed(e)
+                    }
+                };
+            }
+            // The device's partitions are gone
+            Task::done(crate::ui::messages::Message::Flash(
+                FlashMessage::RefreshTargetDevices,
+            ))
+        }
+
+        FlashMessage::EditDeviceNickname(index) => {
+            let Some(device) = device_selection.devices.get(index) else {
+                return Task::none();
+            };
+            // Without a serial number the nickname couldn't be found again
+            if let Some(serial) = device.assignment_info().serial {
+                state.nickname_edit = Some(NicknameEdit {
+                    serial: serial.to_string(),
+                    nickname: device.nickname.clone().unwrap_or_default(),
+                });
+            }
+            Task::none()
+        }
+
+        FlashMessage::SetNicknameText(nickname) => {
+            if let Some(edit) = &mut state.nickname_edit {
+                edit.nickname = nickname;
+            }
+            Task::none()
+        }
+
+        FlashMessage::SaveDeviceNickname => match state.nickname_edit.take() {
+            Some(edit) => Task::done(crate::ui::messages::Message::Settings(
+                crate::ui::settings::SettingsMessage::SetDeviceNickname(edit.serial, edit.nickname),
+            )),
+            None => Task::none(),
+        },
+
+        FlashMessage::CancelNicknameEdit => {
+            state.nickname_edit = None;
+            Task::none()
+        }
+
         FlashMessage::ProcessingProgress(version_id, progress) => {
             // Update download progress for specific version
             if let Some(download) = state
@@ -128,6 +510,15 @@ pub fn handle_message(
                 .downloads_in_progress
                 .retain(|(id, _)| id != &version_id);
 
+            // A fresh download was just hashed, a check of an earlier file no longer applies
+            let downloaded_path = path.to_string_lossy();
+            if let Some(check) = state
+                .source_check
+                .take_if(|check| check.path == downloaded_path)
+            {
+                check.cancel_token.cancel();
+            }
+
             // Update the image metadata
             if let Some(image) = state
                 .os_images
@@ -186,14 +577,144 @@ pub fn handle_message(
             Task::none()
         }
 
+        FlashMessage::BackToConfigureSettings => {
+            state.workflow_state = FlashWorkflowState::ConfigureSettings;
+            state.target_layout = None;
+            state.target_usage = None;
+            restore_read_only(state)
+        }
+
+        FlashMessage::ConfirmWrite => {
+            if let Err(e) = resolve_target_device(state, device_selection) {
+                error!("{}", e);
+                return Task::done(crate::ui::messages::Message::ShowError(e));
+            }
+            let Some(device) = state
+                .selected_device
+                .and_then(|idx| device_selection.devices.get(idx))
+            else {
+                error!("No target device selected for writing");
+                return Task::done(crate::ui::messages::Message::ShowError(
+                    "No target device selected for writing".to_string(),
+                ));
+            };
+
+            state.workflow_state = FlashWorkflowState::ConfirmWrite;
+            state.target_layout = None;
+            state.target_usage = None;
+            state.typed_device_name.clear();
+            state.preserve_config = false;
+            state.config_backed_up = false;
+            state.config_backup = None;
+            state.wipe_snapshot_taken = false;
+            state.discard_wipe_snapshot();
+
+            let device_path = device.path.clone();
+            debug!("Reading current partition layout of {}", device_path);
+            // Mounts are looked up first, since opening the disk unmounts them on Linux
+            Task::perform(query_target_usage(device_path.clone()), |usage| {
+                crate::ui::messages::Message::Flash(FlashMessage::TargetUsageLoaded(usage))
+            })
+            .chain(Task::perform(read_target_layout(device_path), |result| {
+                crate::ui::messages::Message::Flash(FlashMessage::TargetLayoutLoaded(result))
+            }))
+        }
+
+        FlashMessage::TargetUsageLoaded(usage) => {
+            if matches!(state.workflow_state, FlashWorkflowState::ConfirmWrite) {
+                if !usage.is_empty() {
+                    info!(
+                        "Target disk is in use: mounted at {:?}, open in {:?}",
+                        usage.mount_points, usage.processes
+                    );
+                }
+                state.target_usage = Some(usage);
+            }
+            Task::none()
+        }
+
+        FlashMessage::TargetLayoutLoaded(result) => {
+            // Ignore late results if the user already left the confirmation screen
+            if matches!(state.workflow_state, FlashWorkflowState::ConfirmWrite) {
+                if let Err(e) = &result {
+                    warn!("Failed to read target disk layout: {}", e);
+                }
+                state.target_layout = Some(result);
+            }
+            Task::none()
+        }
+
+        FlashMessage::AddToQueue => Task::done(crate::ui::messages::Message::EnqueueFlash),
+        FlashMessage::StartKiosk => Task::done(crate::ui::messages::Message::StartKiosk),
+
+        FlashMessage::TogglePreserveConfig(preserve) => {
+            state.preserve_config = preserve;
+            Task::none()
+        }
+
+        FlashMessage::SetTypedDeviceName(typed) => {
+            state.typed_device_name = typed;
+            Task::none()
+        }
+
+        FlashMessage::ClearReadOnly => {
+            let Some(device) = state
+                .selected_device
+                .and_then(|idx| device_selection.devices.get(idx))
+            else {
+                return Task::none();
+            };
+            let device_path = device.path.clone();
+            info!("Clearing the read-only flag of {}", device_path);
+            Task::perform(
+                async move {
+                    change_read_only(device_path.clone(), false)
+                        .await
+                        .map(|()| device_path)
+                },
+                |result| crate::ui::messages::Message::Flash(FlashMessage::ReadOnlyCleared(result)),
+            )
+        }
+
+        FlashMessage::ReadOnlyCleared(result) => match result {
+            Ok(device_path) => {
+                info!("Cleared the read-only flag of {}", device_path);
+                state.read_only_cleared = Some(device_path);
+                Task::none()
+            }
+            Err(error) => {
+                error!("Failed to clear the read-only flag: {}", error);
+                Task::done(crate::ui::messages::Message::ShowError(error))
+            }
+        },
+
+        FlashMessage::ReadOnlyRestored(result) => match result {
+            Ok(()) => {
+                info!("Read-only flag restored");
+                Task::none()
+            }
+            Err(error) => {
+                error!("Failed to restore the read-only flag: {}", error);
+                Task::done(crate::ui::messages::Message::ShowError(format!(
+                    "The disk was left writable: {}",
+                    error
+                )))
+            }
+        },
+
         FlashMessage::FlashAnother => {
+            state.discard_wipe_snapshot();
+            let manifest_status = state.manifest_status.clone();
             *state = FlashState::new();
+            state.manifest_status = manifest_status;
             Task::none()
         }
 
         // App-level navigation messages that need to be forwarded
         FlashMessage::BackToMainMenu => Task::done(crate::ui::messages::Message::BackToMainMenu),
 
+        FlashMessage::HideToTray => Task::done(crate::ui::messages::Message::HideToTray),
+
         FlashMessage::RefreshRepoData => Task::done(crate::ui::messages::Message::RefreshRepoData),
 
         FlashMessage::DownloadOsImage(image_index) => {
@@ -203,13 +724,20 @@ pub fn handle_message(
             state.cancel_token = CancelToken::new();
 
             if let Some(os_image) = state.os_images.get(image_index) {
-                // Create a Version struct that matches what ImageRepo expects
-                let repo_version = crate::utils::repo::Version {
-                    id: os_image.version.clone(),
-                    path: format!("golem-gpu-live-{}.img.xz", os_image.version),
-                    sha256: os_image.sha256.clone(),
-                    created: os_image.created.clone(),
-                };
+                // Prefer the published version, which also lists its peer-to-peer sources
+                let repo_version = image_repo
+                    .find_version("release", &os_image.version)
+                    .unwrap_or_else(|| crate::utils::repo::Version {
+                        id: os_image.version.clone(),
+                        path: format!("golem-gpu-live-{}.img.xz", os_image.version),
+                        sha256: os_image.sha256.clone(),
+                        created: os_image.created.clone(),
+                        ipfs: None,
+                        magnet: None,
+                        config_schema: None,
+                        partitions: Vec::new(),
+                        requirements: Default::default(),
+                    });
 
                 // Start the download using ImageRepo
                 let repo_clone = Arc::clone(image_repo);
@@ -425,16 +953,23 @@ pub fn handle_message(
                 };
 
                 if let Some(os_image) = version {
-                    // Create a Version struct that matches what ImageRepo expects
-                    let repo_version = crate::utils::repo::Version {
-                        id: os_image.version.clone(),
-                        path: format!(
-                            "golem-gpu-live-{}-{}.img.xz",
-                            group.channel_name, os_image.version
-                        ),
-                        sha256: os_image.sha256.clone(),
-                        created: os_image.created.clone(),
-                    };
+                    // Prefer the published version, which also lists its peer-to-peer sources
+                    let repo_version = image_repo
+                        .find_version(&group.channel_name, &os_image.version)
+                        .unwrap_or_else(|| crate::utils::repo::Version {
+                            id: os_image.version.clone(),
+                            path: format!(
+                                "golem-gpu-live-{}-{}.img.xz",
+                                group.channel_name, os_image.version
+                            ),
+                            sha256: os_image.sha256.clone(),
+                            created: os_image.created.clone(),
+                            ipfs: None,
+                            magnet: None,
+                            config_schema: None,
+                            partitions: Vec::new(),
+                            requirements: Default::default(),
+                        });
 
                     // Start the download using ImageRepo
                     let repo_clone = Arc::clone(image_repo);
@@ -650,27 +1185,35 @@ pub fn handle_message(
             Task::none()
         }
 
+        FlashMessage::StreamOsImageFromGroup(group_index, version_index) => {
+            let Some(group) = state.os_image_groups.get(group_index) else {
+                return Task::none();
+            };
+            let image = if version_index == 0 {
+                &group.latest_version
+            } else if let Some(older_image) = group.older_versions.get(version_index - 1) {
+                older_image
+            } else {
+                return Task::none();
+            };
+
+            info!(
+                "Selected OS image {} version {} for streaming",
+                image.name, image.version
+            );
+            state.selected_os_image = None;
+            state.selected_os_image_group = Some((group_index, version_index));
+            state.stream_image = true;
+            Task::done(crate::ui::messages::Message::Flash(
+                FlashMessage::GotoSelectTargetDevice,
+            ))
+        }
+
         FlashMessage::WriteImage => {
             debug!("Starting image write process");
 
             // Make sure we have both an image and device selected
-            let selected_image_option = if let Some(image_idx) = state.selected_os_image {
-                state.os_images.get(image_idx).cloned()
-            } else if let Some((group_idx, version_idx)) = state.selected_os_image_group {
-                if let Some(group) = state.os_image_groups.get(group_idx) {
-                    if version_idx == 0 {
-                        Some(group.latest_version.clone())
-                    } else if let Some(older_image) = group.older_versions.get(version_idx - 1) {
-                        Some(older_image.clone())
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
+            let selected_image_option = state.selected_image().cloned();
 
             if selected_image_option.is_none() {
                 error!("No OS image selected for writing");
@@ -686,6 +1229,31 @@ pub fn handle_message(
                 ));
             }
 
+            if state.image_untrusted() {
+                warn!(
+                    "Cannot proceed, the image list is not trusted: {:?}",
+                    state.manifest_status
+                );
+                return Task::done(crate::ui::messages::Message::ShowError(
+                    "The image list failed signature verification, so its images can't be flashed."
+                        .to_string(),
+                ));
+            }
+
+            // The device list may have changed since the device was selected
+            if let Err(e) = resolve_target_device(state, device_selection) {
+                error!("{}", e);
+                return Task::done(crate::ui::messages::Message::ShowError(e));
+            }
+
+            if state.target_read_only(device_selection) {
+                warn!("Cannot proceed, the target device is read-only");
+                return Task::done(crate::ui::messages::Message::ShowError(
+                    "The target device is read-only. Clear its read-only flag before flashing it."
+                        .to_string(),
+                ));
+            }
+
             // Validate configuration from the central configuration state
             // Check if wallet address is valid before proceeding
             if !configuration.wallet_address.is_empty() && !configuration.is_wallet_valid {
@@ -698,6 +1266,11 @@ pub fn handle_message(
                 ));
             }
 
+            if let Some(reason) = configuration.mainnet_interlock(&state.config_schema) {
+                warn!("Cannot proceed, mainnet configuration not confirmed: {}", reason);
+                return Task::done(crate::ui::messages::Message::ShowError(reason.to_string()));
+            }
+
             // Validate SSH keys
             let ssh_keys_string = configuration.ssh_keys.join("\n");
             let ssh_key_errors = validate_ssh_keys(&ssh_keys_string);
@@ -740,21 +1313,162 @@ pub fn handle_message(
                 ));
             }
 
+            if !configuration.are_extra_settings_valid() {
+                warn!("Cannot proceed, an extra setting has an invalid key");
+                return Task::done(crate::ui::messages::Message::ShowError(
+                    "Invalid key in the extra settings".to_string(),
+                ));
+            }
+
             // Get the selected OS image and device
             if let (Some(image), Some(device_idx)) = (selected_image_option, state.selected_device)
             {
                 if let Some(device) = device_selection.devices.get(device_idx) {
-                    // Make sure the image is downloaded
-                    if let Some(image_path) = &image.path {
-                        // Start the write process with initial 0% progress for image writing
-                        state.workflow_state = FlashWorkflowState::WritingImage(0.0);
+                    // Write from the download cache, or stream from the repository if chosen
+                    let image_source = match &image.path {
+                        Some(image_path) => Some(ImageSource::File(image_path.clone())),
+                        None if state.stream_image => image_repo
+                            .find_version(&image.name, &image.version)
+                            .map(|version| ImageSource::Url {
+                                url: image_repo.get_image_url(&version),
+                                sha256: Some(version.sha256),
+                            }),
+                        None => None,
+                    };
+
+                    // Cached and local images are always analyzed before they can be selected
+                    if let (Some(ImageSource::File(_)), None) = (&image_source, &image.metadata) {
+                        error!("Cannot write - image metadata missing: {}", image.name);
+                        state.workflow_state = FlashWorkflowState::Completion(false);
+                        return Task::done(crate::ui::messages::Message::ShowError(
+                            "Image metadata is required for writing".to_string(),
+                        ));
+                    }
+
+                    // Keep a copy of the configuration of a Golem device before it is erased
+                    let is_golem_target = matches!(
+                        &state.target_layout,
+                        Some(Ok(layout)) if layout.is_golem_image()
+                    );
+                    if is_golem_target && !state.config_backed_up {
+                        state.workflow_state = FlashWorkflowState::ClearingPartitions {
+                            progress: 0.0,
+                            message: "Backing up device configuration...".to_string(),
+                        };
+                        return Task::perform(
+                            backup_target_config(device.path.clone(), device.name.clone()),
+                            |result| {
+                                crate::ui::messages::Message::Flash(FlashMessage::ConfigBackedUp(
+                                    result,
+                                ))
+                            },
+                        );
+                    }
+
+                    // With the contents check turned on, nothing is erased before it passed
+                    if let (Some(ImageSource::File(image_path)), true, Some(_)) = (
+                        &image_source,
+                        settings.check_image_before_writing,
+                        &image.metadata,
+                    ) {
+                        let status = state
+                            .source_check
+                            .as_ref()
+                            .filter(|check| &check.path == image_path)
+                            .map(|check| check.status.clone());
+                        match status {
+                            Some(SourceCheckStatus::Valid) => {}
+                            Some(SourceCheckStatus::Corrupt(error)) => {
+                                state.workflow_state = FlashWorkflowState::Completion(false);
+                                return Task::done(crate::ui::messages::Message::ShowError(error));
+                            }
+                            Some(SourceCheckStatus::Checking(progress)) => {
+                                state.write_after_source_check = true;
+                                state.workflow_state = FlashWorkflowState::ClearingPartitions {
+                                    progress,
+                                    message: "Checking image file...".to_string(),
+                                };
+                                return Task::none();
+                            }
+                            None => {
+                                state.write_after_source_check = true;
+                                state.workflow_state = FlashWorkflowState::ClearingPartitions {
+                                    progress: 0.0,
+                                    message: "Checking image file...".to_string(),
+                                };
+                                return start_source_check(state);
+                            }
+                        }
+                    }
+
+                    // A cached image may have been damaged since it was downloaded
+                    if let (Some(ImageSource::File(image_path)), false, false) =
+                        (&image_source, state.cached_image_checked, image.local)
+                    {
+                        state.workflow_state = FlashWorkflowState::ClearingPartitions {
+                            progress: 0.0,
+                            message: "Checking cached image...".to_string(),
+                        };
+                        let repo = Arc::clone(image_repo);
+                        let file_name = cached_file_name(image_path);
+                        let sha256 = image.sha256.clone();
+                        return Task::perform(
+                            async move {
+                                tokio::task::spawn_blocking(move || {
+                                    repo.validate_cached_image(&file_name, &sha256)
+                                })
+                                .await
+                                .unwrap_or(false)
+                            },
+                            |valid| {
+                                crate::ui::messages::Message::Flash(
+                                    FlashMessage::CachedImageChecked(valid),
+                                )
+                            },
+                        );
+                    }
+                    // Save the partition table first, so a write cancelled in time can put it back
+                    if image_source.is_some() && !state.wipe_snapshot_taken {
+                        state.workflow_state = FlashWorkflowState::ClearingPartitions {
+                            progress: 0.0,
+                            message: "Saving partition table...".to_string(),
+                        };
+                        return Task::perform(snapshot_target(device.path.clone()), |result| {
+                            crate::ui::messages::Message::Flash(FlashMessage::WipeSnapshotTaken(
+                                result,
+                            ))
+                        });
+                    }
+                    state.cached_image_checked = false;
+                    state.wipe_snapshot_taken = false;
+
+                    if let Some(image_source) = image_source {
+                        if let (ImageSource::File(image_path), false) = (&image_source, image.local)
+                        {
+                            image_repo.touch_cached_image(&cached_file_name(image_path));
+                        }
+
+                        // Updates of earlier writes still on their way are told apart from this one's
+                        let operation = OperationId::new();
+                        state.operation = Some(operation);
+                        debug!("Write {} to {}", operation, device.path);
+
+                        // Start by preparing the device; image writing follows once it's cleared
+                        state.workflow_state = FlashWorkflowState::ClearingPartitions {
+                            progress: 0.0,
+                            message: "Preparing device...".to_string(),
+                        };
 
                         // Get device path, image path, and metadata
                         let device_path = device.path.clone();
-                        let image_path_val = image_path.clone();
                         let image_metadata = image.metadata.clone();
+                        let image_partitions = image.partitions.clone();
+                        let write_speed_limit = settings.write_speed_limit();
+                        let randomize_guids = settings.randomize_guids;
                         // Create a clone of the cancel token that we can pass to the task
                         let cancel_token_clone = state.cancel_token.clone();
+                        state.skip_verification = CancelToken::new();
+                        let skip_verification = state.skip_verification.clone();
 
                         // Extract configuration before creating async closure
                         let mut config_instance = crate::disk::ImageConfiguration::new_with_options(
@@ -770,7 +1484,87 @@ pub fn handle_message(
                         );
                         // Ensure accepted_terms is always true for new installations
                         config_instance.ensure_accepted_terms();
-                        let config = Some(config_instance);
+                        // Give batch-provisioned nodes their own names
+                        let date = chrono::Local::now().format("%Y%m%d").to_string();
+                        let template_context = TemplateContext::for_device(
+                            &device.assignment_info(),
+                            state.template_index,
+                            &date,
+                        );
+                        config_instance.subnet =
+                            template::expand(&configuration.subnet, &template_context);
+                        config_instance.glm_node_name = Some(configuration.node_name.trim())
+                            .filter(|name| !name.is_empty())
+                            .map(|name| template::expand(name, &template_context));
+                        state.template_index_used = template::uses_index(&configuration.subnet)
+                            || template::uses_index(&configuration.node_name);
+                        if template::has_variables(&configuration.subnet)
+                            || template::has_variables(&configuration.node_name)
+                        {
+                            info!(
+                                "Expanded templates for {}: subnet {}, node name {:?}",
+                                device_path, config_instance.subnet, config_instance.glm_node_name
+                            );
+                        }
+                        config_instance.extra_env =
+                            configuration.filled_in_extra_settings(ExtraSettingFile::GolemEnv);
+                        config_instance.extra_toml =
+                            configuration.filled_in_extra_settings(ExtraSettingFile::GolemwzToml);
+                        config_instance.firstboot = configuration.firstboot.clone();
+                        config_instance.schema = image.config_schema.clone();
+                        let (config, config_summary) =
+                            match (&state.config_backup, state.preserve_config) {
+                                (Some((_, snapshot)), true) => {
+                                    info!(
+                                        "Re-applying the configuration backed up from {}",
+                                        device_path
+                                    );
+                                    (
+                                        Some(crate::disk::ConfigPartitionContents::Restore(
+                                            snapshot.clone(),
+                                        )),
+                                        vec![(
+                                            "Configuration".to_string(),
+                                            "Restored from the device's previous configuration"
+                                                .to_string(),
+                                        )],
+                                    )
+                                }
+                                _ => {
+                                    let summary =
+                                        FlashReport::describe_configuration(&config_instance);
+                                    (
+                                        Some(crate::disk::ConfigPartitionContents::Configuration(
+                                            Box::new(config_instance),
+                                        )),
+                                        summary,
+                                    )
+                                }
+                            };
+
+                        let device_info = device.assignment_info();
+                        state.report_path = None;
+                        state.bytes_written = 0;
+                        state.flash_report = Some(FlashReport {
+                            image_channel: image.name.clone(),
+                            image_version: image.version.clone(),
+                            compressed_sha256: image.sha256.clone(),
+                            uncompressed_sha256: image_metadata
+                                .as_ref()
+                                .map(|metadata| metadata.uncompressed_hash.clone()),
+                            uncompressed_size: image_metadata
+                                .as_ref()
+                                .map(|metadata| metadata.uncompressed_size),
+                            device_name: device.name.clone(),
+                            device_nickname: device.nickname.clone(),
+                            device_path: device_path.clone(),
+                            device_serial: device_info.serial.map(|serial| serial.to_string()),
+                            device_size: device.size_bytes,
+                            configuration: config_summary,
+                            started_at: chrono::Local::now(),
+                            finished_at: None,
+                            verified: false,
+                        });
 
                         info!(
                             "Starting flash with config: {:?} {:?} {} {} to device {}",
@@ -781,120 +1575,93 @@ pub fn handle_message(
                             device_path
                         );
 
-                        // Pass the cancel token clone into the future task
-                        return Task::future(async move {
-                            info!("Starting disk image write to {}", device_path);
-                            // Store the device_path for use throughout the process
-                            // When writing an image, set edit_mode to false to allow disk cleaning
-                            let locked_disk = Disk::lock_path(&device_path, false).await;
-                            // Log whether we successfully locked the disk
-                            match &locked_disk {
-                                Ok(_) => info!("Successfully locked disk: {}", device_path),
-                                Err(e) => {
-                                    error!("Failed to lock disk {}: {}", device_path, e)
+                        // Clear existing partitions first, streaming progress so the UI doesn't
+                        // look frozen during the multi-second diskpart phase on Windows
+                        let clear_task = Task::sip(
+                            Disk::clear_partitions(&device_path, cancel_token_clone.clone()),
+                            PrepareStep::Progress,
+                            PrepareStep::Cleared,
+                        );
+
+                        return clear_task.then(move |step| {
+                            let result = match step {
+                                PrepareStep::Progress(phase) => {
+                                    return Task::done(crate::ui::messages::Message::Flash(
+                                        FlashMessage::Progress(operation, phase),
+                                    ));
                                 }
+                                PrepareStep::Cleared(result) => result,
+                            };
+
+                            if let Err(e) = result {
+                                error!("Failed to clear partitions on {}: {}", device_path, e);
+                                return Task::done(crate::ui::messages::Message::Flash(
+                                    FlashMessage::WriteImageFailed(operation, format!("{:?}", e)),
+                                ));
                             }
-                            locked_disk
-                        })
-                        .and_then(move |disk| {
-                            // Now write the image and handle progress
-                            // Note: write_image now takes ownership of disk
-                            // Clone the cancel token again for this specific closure
-                            let task_cancel_token = cancel_token_clone.clone();
-
-                            let write_task = match &image_metadata {
-                                Some(metadata) => Task::sip(
+
+                            let device_path = device_path.clone();
+                            let image_source = image_source.clone();
+                            let image_metadata = image_metadata.clone();
+                            let image_partitions = image_partitions.clone();
+                            let cancel_token_clone = cancel_token_clone.clone();
+                            let skip_verification = skip_verification.clone();
+                            let config = config.clone();
+
+                            Task::future(async move {
+                                info!("Starting disk image write to {}", device_path);
+                                // Store the device_path for use throughout the process
+                                // Partitions were already cleared above, so skip the inline diskpart pass
+                                let locked_disk = Disk::lock_path(&device_path, true).await;
+                                // Log whether we successfully locked the disk
+                                match &locked_disk {
+                                    Ok(_) => info!("Successfully locked disk: {}", device_path),
+                                    Err(e) => {
+                                        error!("Failed to lock disk {}: {}", device_path, e)
+                                    }
+                                }
+                                locked_disk
+                            })
+                            .and_then(move |disk| {
+                                // Now write the image and handle progress
+                                // Note: write_image now takes ownership of disk
+                                // Clone the cancel token again for this specific closure
+                                let task_cancel_token = cancel_token_clone.clone();
+
+                                let write_task = Task::sip(
                                     disk.write_image(
-                                        &image_path_val,
-                                        metadata.clone(),
+                                        image_source,
+                                        image_metadata,
+                                        image_partitions,
                                         task_cancel_token,
+                                        skip_verification.clone(),
                                         config.clone(),
+                                        write_speed_limit,
+                                        randomize_guids,
                                     ),
-                                    |message| match message {
-                                        WriteProgress::Start => {
-                                            crate::ui::messages::Message::Flash(
-                                                FlashMessage::WriteImageProgress(0.0),
-                                            )
-                                        }
-                                        WriteProgress::ClearingPartitions { progress: _ } => {
-                                            // ClearPartitions progress removed - use generic write progress
-                                            crate::ui::messages::Message::Flash(
-                                                FlashMessage::WriteImageProgress(0.0),
-                                            )
-                                        }
-                                        WriteProgress::Write {
-                                            total_written,
-                                            total_size,
-                                        } => {
-                                            // Calculate progress based on actual metadata size or fallback to 16GB
-                                            let size_for_calculation = if total_size > 0 {
-                                                total_size as f32
-                                            } else {
-                                                16.0 * 1024.0 * 1024.0 * 1024.0 // 16GB fallback
-                                            };
-
-                                            // Calculate progress percentage (0.0-1.0)
-                                            let progress =
-                                                total_written as f32 / size_for_calculation;
-
-                                            // Clamp to make sure we don't go over 100%
-                                            let clamped_progress = progress.min(1.0);
-
-                                            crate::ui::messages::Message::Flash(
-                                                FlashMessage::WriteImageProgress(clamped_progress),
-                                            )
-                                        }
-                                        WriteProgress::Verifying {
-                                            verified_bytes,
-                                            total_size,
-                                        } => {
-                                            // Calculate verification progress (0.0-1.0)
-                                            let progress = if total_size > 0 {
-                                                verified_bytes as f32 / total_size as f32
-                                            } else {
-                                                0.0
-                                            };
-
-                                            // Use a separate message for verification progress
-                                            crate::ui::messages::Message::Flash(
-                                                FlashMessage::VerificationProgress(
-                                                    progress.min(1.0),
-                                                ),
-                                            )
-                                        }
-                                        WriteProgress::Finish => {
-                                            crate::ui::messages::Message::Flash(
-                                                FlashMessage::WriteImageProgress(1.0),
-                                            )
-                                        }
+                                    move |phase| {
+                                        crate::ui::messages::Message::Flash(FlashMessage::Progress(
+                                            operation, phase,
+                                        ))
                                     },
-                                    |result| match result {
-                                        Ok(WriteProgress::Finish) => {
-                                            // When image writing is complete, we'll need to reacquire the disk
-                                            // because write_image now consumes the disk
-                                            crate::ui::messages::Message::Flash(
-                                                FlashMessage::WriteImageCompleted,
-                                            )
-                                        }
-                                        Ok(_) => crate::ui::messages::Message::Flash(
-                                            FlashMessage::WriteImageCompleted,
+                                    move |result| match result {
+                                        Ok(phase) => crate::ui::messages::Message::Flash(
+                                            FlashMessage::WriteImageCompleted(
+                                                operation,
+                                                phase != FlashPhase::Unverified,
+                                            ),
                                         ),
                                         Err(e) => crate::ui::messages::Message::Flash(
-                                            FlashMessage::WriteImageFailed(format!("{:?}", e)),
+                                            FlashMessage::WriteImageFailed(
+                                                operation,
+                                                format!("{:?}", e),
+                                            ),
                                         ),
                                     },
-                                ),
-                                None => {
-                                    // This should never happen in practice, but handle gracefully
-                                    Task::done(crate::ui::messages::Message::Flash(
-                                        FlashMessage::WriteImageFailed(
-                                            "Image metadata is required for writing".to_string(),
-                                        ),
-                                    ))
-                                }
-                            };
+                                );
 
-                            write_task
+                                write_task
+                            })
                         });
                     } else {
                         // Image not downloaded
@@ -922,43 +1689,328 @@ pub fn handle_message(
             }
         }
 
-        FlashMessage::WriteImageCompleted => {
+        FlashMessage::ConfigBackedUp(result) => {
+            // Ignore the result if the write was cancelled meanwhile
+            if !matches!(
+                state.workflow_state,
+                FlashWorkflowState::ClearingPartitions { .. }
+            ) {
+                return Task::none();
+            }
+
+            match result {
+                Ok(backup) => {
+                    info!("Target configuration backed up to {}", backup.0.display());
+                    state.config_backup = Some(backup);
+                }
+                Err(e) if state.preserve_config => {
+                    // The configuration to keep would be lost, so don't erase anything
+                    error!("Failed to back up target configuration: {}", e);
+                    state.workflow_state = FlashWorkflowState::Completion(false);
+                    return Task::done(crate::ui::messages::Message::ShowError(format!(
+                        "Failed to back up the device's configuration, nothing was erased: {}",
+                        e
+                    )));
+                }
+                Err(e) => warn!(
+                    "Failed to back up target configuration, continuing without a backup: {}",
+                    e
+                ),
+            }
+
+            state.config_backed_up = true;
+            Task::done(crate::ui::messages::Message::Flash(
+                FlashMessage::WriteImage,
+            ))
+        }
+
+        FlashMessage::WipeSnapshotTaken(result) => {
+            // Ignore the result if the write was cancelled meanwhile
+            if !matches!(
+                state.workflow_state,
+                FlashWorkflowState::ClearingPartitions { .. }
+            ) {
+                if let Ok(snapshot) = result {
+                    snapshot.discard();
+                }
+                return Task::none();
+            }
+
+            match result {
+                Ok(snapshot) => {
+                    if let Some(previous) = state.wipe_snapshot.replace(snapshot) {
+                        previous.discard();
+                    }
+                }
+                Err(e) => warn!(
+                    "Failed to save the partition table of the target, the wipe can't be undone: {}",
+                    e
+                ),
+            }
+
+            state.wipe_snapshot_taken = true;
+            Task::done(crate::ui::messages::Message::Flash(
+                FlashMessage::WriteImage,
+            ))
+        }
+
+        FlashMessage::SourceCheckProgress(path, progress) => {
+            if let Some(check) = state
+                .source_check
+                .as_mut()
+                .filter(|check| check.path == path)
+            {
+                check.status = SourceCheckStatus::Checking(progress);
+                let waiting = state.write_after_source_check;
+                match &mut state.workflow_state {
+                    FlashWorkflowState::ClearingPartitions {
+                        progress: shown, ..
+                    } if waiting => *shown = progress,
+                    _ => {}
+                }
+            }
+            Task::none()
+        }
+
+        FlashMessage::SourceChecked(path, result) => {
+            let Some(check) = state
+                .source_check
+                .as_mut()
+                .filter(|check| check.path == path)
+            else {
+                return Task::none();
+            };
+            check.status = match result {
+                Ok(()) => SourceCheckStatus::Valid,
+                Err(error) => {
+                    error!("Image file check failed: {}", error);
+                    SourceCheckStatus::Corrupt(error)
+                }
+            };
+
+            // Carry on with the write that was waiting for the check
+            let waiting = std::mem::take(&mut state.write_after_source_check);
+            if waiting
+                && matches!(
+                    state.workflow_state,
+                    FlashWorkflowState::ClearingPartitions { .. }
+                )
+            {
+                Task::done(crate::ui::messages::Message::Flash(
+                    FlashMessage::WriteImage,
+                ))
+            } else {
+                Task::none()
+            }
+        }
+
+        FlashMessage::CachedImageChecked(valid) => {
+            // Ignore the result if the write was cancelled meanwhile
+            if !matches!(
+                state.workflow_state,
+                FlashWorkflowState::ClearingPartitions { .. }
+            ) {
+                return Task::none();
+            }
+
+            if valid {
+                state.cached_image_checked = true;
+                Task::done(crate::ui::messages::Message::Flash(
+                    FlashMessage::WriteImage,
+                ))
+            } else {
+                error!("Cached image failed hash verification");
+                state.workflow_state = FlashWorkflowState::Completion(false);
+                Task::batch([
+                    Task::done(crate::ui::messages::Message::ShowError(
+                        "The downloaded image is damaged and was removed. Download it again."
+                            .to_string(),
+                    )),
+                    Task::done(crate::ui::messages::Message::RefreshRepoData),
+                ])
+            }
+        }
+
+        FlashMessage::WriteImageCompleted(_, verified) => {
             // Reset the cancel token for future operations
-            debug!("Image writing completed, flashing successful");
+            if verified {
+                debug!("Image writing completed, flashing successful");
+            } else {
+                info!("Image written, verification was skipped");
+            }
+            state.write_verified = verified;
+            state.failed_attempts = 0;
+            state.discard_wipe_snapshot();
+            // The freshly written disk stays writable
+            state.read_only_cleared = None;
+            if let Some(report) = &mut state.flash_report {
+                report.finished_at = Some(chrono::Local::now());
+                report.verified = verified;
+            }
             state.workflow_state = FlashWorkflowState::Completion(true);
             Task::none()
         }
 
-        FlashMessage::WriteImageFailed(error) => {
+        FlashMessage::ExportReport(format) => match state.flash_report.clone() {
+            Some(report) => Task::perform(save_flash_report(report, format), |result| {
+                crate::ui::messages::Message::Flash(FlashMessage::ReportExported(result))
+            }),
+            None => Task::none(),
+        },
+
+        FlashMessage::ReportExported(result) => match result {
+            Ok(Some(path)) => {
+                info!("Saved flash report to {}", path.display());
+                state.report_path = Some(path);
+                Task::none()
+            }
+            // The dialog was cancelled
+            Ok(None) => Task::none(),
+            Err(error) => {
+                error!("Failed to save flash report: {}", error);
+                Task::done(crate::ui::messages::Message::ShowError(error))
+            }
+        },
+
+        FlashMessage::WriteImageFailed(operation, error) => {
             error!("Image writing failed: {}", error);
+            // A cancelled write ends up here too, after it has already been marked as ended
+            if matches!(
+                state.workflow_state,
+                FlashWorkflowState::ClearingPartitions { .. } | FlashWorkflowState::Flashing(_)
+            ) {
+                state.failed_attempts += 1;
+            }
             state.workflow_state = FlashWorkflowState::Completion(false);
-            Task::done(crate::ui::messages::Message::ShowError(format!(
-                "Failed to write image: {}",
-                error
-            )))
+            let cancelled = state.cancel_token.is_cancelled();
+            let restore = if cancelled {
+                restore_read_only(state)
+            } else {
+                Task::none()
+            };
+
+            // The snapshot is dropped once image data was written, so the wipe is all that happened
+            let undo_window = if cancelled && state.wipe_snapshot.is_some() {
+                info!(
+                    "Write cancelled before the image was written, offering to undo the wipe for {:?}",
+                    crate::disk::wipe::UNDO_WINDOW
+                );
+                state.wipe_undo = Some(super::WipeUndo::Offered(operation));
+                Task::perform(
+                    tokio::time::sleep(crate::disk::wipe::UNDO_WINDOW),
+                    move |()| {
+                        crate::ui::messages::Message::Flash(FlashMessage::UndoWipeExpired(
+                            operation,
+                        ))
+                    },
+                )
+            } else {
+                state.discard_wipe_snapshot();
+                Task::none()
+            };
+            Task::batch([
+                Task::done(crate::ui::messages::Message::ShowError(format!(
+                    "Failed to write image: {}",
+                    error
+                ))),
+                restore,
+                undo_window,
+            ])
+        }
+
+        FlashMessage::UndoWipe => {
+            if !matches!(state.wipe_undo, Some(super::WipeUndo::Offered(_))) {
+                return Task::none();
+            }
+            let (Some(snapshot), Some(device)) = (
+                state.wipe_snapshot.clone(),
+                state
+                    .selected_device
+                    .and_then(|idx| device_selection.devices.get(idx)),
+            ) else {
+                state.discard_wipe_snapshot();
+                return Task::none();
+            };
+
+            info!("Undoing the wipe of {}", device.path);
+            state.wipe_undo = Some(super::WipeUndo::Restoring);
+            Task::perform(undo_wipe(device.path.clone(), snapshot), |result| {
+                crate::ui::messages::Message::Flash(FlashMessage::WipeUndone(result))
+            })
         }
 
-        FlashMessage::WriteImageProgress(progress) => {
-            if let FlashWorkflowState::WritingImage(_) = &mut state.workflow_state {
-                debug!("Image write progress: {:.1}%", progress * 100.0);
-                state.workflow_state = FlashWorkflowState::WritingImage(progress);
+        FlashMessage::WipeUndone(result) => {
+            state.discard_wipe_snapshot();
+            match result {
+                Ok(()) => {
+                    state.wipe_undo = Some(super::WipeUndo::Restored);
+                    Task::none()
+                }
+                Err(error) => {
+                    error!("Failed to undo the wipe: {}", error);
+                    Task::done(crate::ui::messages::Message::ShowError(format!(
+                        "Failed to restore the partition table: {}",
+                        error
+                    )))
+                }
+            }
+        }
+
+        FlashMessage::UndoWipeExpired(operation) => {
+            // A later write may be offering its own undo by now
+            if state.wipe_undo == Some(super::WipeUndo::Offered(operation)) {
+                info!("Undo window closed, the wipe can no longer be undone");
+                state.discard_wipe_snapshot();
             }
             Task::none()
         }
 
-        FlashMessage::VerificationProgress(progress) => {
-            match &mut state.workflow_state {
-                FlashWorkflowState::WritingImage(_) => {
-                    // When we receive verification progress, transition to verifying state
-                    debug!("Verification progress: {:.1}%", progress * 100.0);
-                    state.workflow_state = FlashWorkflowState::VerifyingImage(progress);
+        FlashMessage::Progress(_, FlashPhase::Clearing { progress, message }) => {
+            if let FlashWorkflowState::ClearingPartitions { .. } = &state.workflow_state {
+                debug!(
+                    "Clearing partitions: {:.0}% - {}",
+                    progress * 100.0,
+                    message
+                );
+                state.workflow_state = FlashWorkflowState::ClearingPartitions { progress, message };
+            }
+            Task::none()
+        }
+
+        FlashMessage::Progress(_, phase) => {
+            // Updates are sent without waiting, so a late one from an earlier phase is dropped
+            let advances = match &state.workflow_state {
+                FlashWorkflowState::ClearingPartitions { .. } => true,
+                FlashWorkflowState::Flashing(current) => {
+                    phase_order(&phase) >= phase_order(current)
+                }
+                _ => false,
+            };
+            if advances {
+                debug!("{}", phase.description());
+                if let FlashPhase::Writing { bytes, .. } = &phase {
+                    state.bytes_written = *bytes;
                 }
-                FlashWorkflowState::VerifyingImage(_) => {
-                    // Update verification progress
-                    debug!("Verification progress: {:.1}%", progress * 100.0);
-                    state.workflow_state = FlashWorkflowState::VerifyingImage(progress);
+                // Image data is on the disk, the old partition table no longer matches it
+                if phase != FlashPhase::Preparing {
+                    state.discard_wipe_snapshot();
                 }
-                _ => {}
+                state.workflow_state = FlashWorkflowState::Flashing(phase);
+            }
+            Task::none()
+        }
+
+        FlashMessage::ToggleWriteDetails => {
+            state.show_write_details = !state.show_write_details;
+            Task::none()
+        }
+
+        FlashMessage::SkipVerification => {
+            if let FlashWorkflowState::Flashing(FlashPhase::Verifying { .. }) =
+                &state.workflow_state
+            {
+                info!("Skipping verification of the written image");
+                state.skip_verification.cancel();
             }
             Task::none()
         }
@@ -968,6 +2020,7 @@ pub fn handle_message(
 
             // Cancel the current operation
             state.cancel_token.cancel();
+            state.write_after_source_check = false;
 
             // Reset state based on what was being cancelled
             match &state.workflow_state {
@@ -978,7 +2031,7 @@ pub fn handle_message(
                     state.downloads_in_progress.clear();
                     info!("Download/analysis cancelled, returning to image selection");
                 }
-                FlashWorkflowState::WritingImage(_) | FlashWorkflowState::VerifyingImage(_) => {
+                FlashWorkflowState::ClearingPartitions { .. } | FlashWorkflowState::Flashing(_) => {
                     // Cancel write process - go to completion with failed status
                     state.workflow_state = FlashWorkflowState::Completion(false);
                     info!("Write process cancelled");
@@ -998,3 +2051,401 @@ pub fn handle_message(
         }
     }
 }
+
+/// Make sure `selected_device` still points at the device the user picked
+///
+/// Device paths follow enumeration order, so after hot-plugging the same index (or even
+/// the same path) can belong to a different disk. The selection is moved to wherever the
+/// device is now; if it's gone, the user is sent back to pick the target again.
+fn resolve_target_device(
+    state: &mut FlashState,
+    device_selection: &crate::ui::device_selection::DeviceSelectionState,
+) -> Result<(), String> {
+    let Some(target) = &state.selected_target else {
+        return Ok(());
+    };
+
+    match device_selection.find_device(target) {
+        Some(index) => {
+            if state.selected_device != Some(index) {
+                warn!(
+                    "Target device {} ({}) moved to {}, following it",
+                    target.name, target.identity, device_selection.devices[index].path
+                );
+                state.selected_device = Some(index);
+            }
+            Ok(())
+        }
+        None => {
+            let message = format!(
+                "The selected device {} is no longer connected. Please select the target device again.",
+                target.name
+            );
+            state.selected_device = None;
+            state.selected_target = None;
+            state.workflow_state = FlashWorkflowState::SelectTargetDevice;
+            Err(message)
+        }
+    }
+}
+
+/// Steps of the device preparation that runs before the image write
+enum PrepareStep {
+    Progress(FlashPhase),
+    Cleared(anyhow::Result<FlashPhase>),
+}
+
+/// Position of a phase in the write, so stale progress can't move the screen backwards
+fn phase_order(phase: &FlashPhase) -> u8 {
+    match phase {
+        FlashPhase::Preparing | FlashPhase::Clearing { .. } => 0,
+        FlashPhase::Writing { .. } => 1,
+        FlashPhase::Verifying { .. } => 2,
+        FlashPhase::FixingGpt => 3,
+        FlashPhase::WritingConfig => 4,
+        FlashPhase::Done | FlashPhase::Unverified => 5,
+    }
+}
+
+/// Back up the configuration partition of a device about to be re-flashed
+///
+/// The backup goes to the application's data directory and is read back, which also
+/// checks that it can be restored.
+async fn backup_target_config(
+    device_path: String,
+    device_name: String,
+) -> Result<(std::path::PathBuf, crate::disk::ConfigSnapshot), String> {
+    let backup_dir = crate::utils::paths::backup_dir().map_err(|e| e.to_string())?;
+    let path = backup_dir.join(crate::disk::backup::backup_file_name(&device_name));
+
+    let disk = Disk::lock_path(&device_path, true)
+        .await
+        .map_err(|e| format!("Failed to open device: {}", e))?;
+    disk.backup_config_partition(&path)
+        .map_err(|e| e.to_string())?;
+    drop(disk);
+
+    let snapshot = crate::disk::backup::read_backup(&path).map_err(|e| e.to_string())?;
+    Ok((path, snapshot))
+}
+
+/// Save the regions of a device about to be wiped to a private file
+async fn snapshot_target(device_path: String) -> Result<crate::disk::WipeSnapshot, String> {
+    let path = crate::disk::wipe::snapshot_path(&device_path).map_err(|e| e.to_string())?;
+    let disk = Disk::lock_path(&device_path, true)
+        .await
+        .map_err(|e| format!("Failed to open device: {}", e))?;
+    disk.snapshot_wipe_regions(&path)
+        .map_err(|e| e.to_string())
+}
+
+/// Write the regions saved before a cancelled write back to the device
+async fn undo_wipe(device_path: String, snapshot: crate::disk::WipeSnapshot) -> Result<(), String> {
+    let mut disk = Disk::lock_path(&device_path, true)
+        .await
+        .map_err(|e| format!("Failed to open device: {}", e))?;
+    disk.restore_wipe_regions(&snapshot)
+        .map_err(|e| e.to_string())
+}
+
+/// Ask where to save the flash report and write it there
+async fn save_flash_report(
+    report: FlashReport,
+    format: ReportFormat,
+) -> Result<Option<std::path::PathBuf>, String> {
+    let (filter_name, title) = match format {
+        ReportFormat::Html => ("HTML files", "Export Flash Report as HTML"),
+        ReportFormat::Pdf => ("PDF files", "Export Flash Report as PDF"),
+    };
+    let Some(handle) = rfd::AsyncFileDialog::new()
+        .set_title(title)
+        .set_file_name(report.file_name(format))
+        .add_filter(filter_name, &[format.extension()])
+        .save_file()
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let path = handle.path().to_path_buf();
+    let content = match format {
+        ReportFormat::Html => report.to_html().into_bytes(),
+        ReportFormat::Pdf => report.to_pdf(),
+    };
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| format!("Failed to save the report to {}: {}", path.display(), e))?;
+    Ok(Some(path))
+}
+
+/// Look up what is using the target disk, which would keep it from being locked
+async fn query_target_usage(device_path: String) -> crate::disk::DeviceUsage {
+    tokio::task::spawn_blocking(move || crate::disk::usage::query_device_usage(&device_path))
+        .await
+        .unwrap_or_default()
+}
+
+/// Open a disk the flash workflow lists for a test, refusing it while it is mounted
+///
+/// The mounted filesystem could write to the tested areas meanwhile. `edit_mode` keeps the
+/// disk from being cleaned on Windows.
+async fn lock_unmounted_disk(device_path: &str, edit_mode: bool) -> Result<Disk, String> {
+    let usage = query_target_usage(device_path.to_string()).await;
+    if !usage.mount_points.is_empty() {
+        return Err(format!(
+            "{} is mounted at {}, unmount it to test it",
+            device_path,
+            usage.mount_points.join(", ")
+        ));
+    }
+    Disk::lock_path(device_path, edit_mode)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", device_path, e))
+}
+
+/// Measure the write and read speed of a disk the flash workflow lists
+async fn test_device_speed(device_path: String) -> Result<crate::disk::SpeedTestResult, String> {
+    let mut disk = lock_unmounted_disk(&device_path, true).await?;
+    tokio::task::spawn_blocking(move || disk.speed_test())
+        .await
+        .map_err(|e| format!("Speed test failed: {}", e))?
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Erase a disk the flash workflow lists to check its real capacity
+fn start_capacity_test(
+    path: String,
+    cancel_token: CancelToken,
+) -> Task<crate::ui::messages::Message> {
+    let progress_path = path.clone();
+    let result_path = path.clone();
+    Task::sip(
+        iced::task::sipper(async move |sipper| {
+            let mut disk = lock_unmounted_disk(&path, false).await?;
+            tokio::task::spawn_blocking(move || {
+                // Only report whole percents, like the image check
+                let mut reported = 0;
+                disk.capacity_test(&cancel_token, |fraction| {
+                    let percent = (fraction * 100.0) as u32;
+                    if percent != reported {
+                        reported = percent;
+                        let mut sipper = sipper.clone();
+                        std::mem::drop(tokio::spawn(async move { sipper.send(fraction).await }));
+                    }
+                })
+                .map_err(|e| format!("{:#}", e))
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("Capacity test failed: {}", e)))
+        }),
+        move |fraction| {
+            crate::ui::messages::Message::Flash(FlashMessage::CapacityTestProgress(
+                progress_path.clone(),
+                fraction,
+            ))
+        },
+        move |result| {
+            crate::ui::messages::Message::Flash(FlashMessage::DeviceCapacityTested(
+                result_path.clone(),
+                result,
+            ))
+        },
+    )
+}
+
+/// Set or clear the read-only flag of the target disk
+async fn change_read_only(device_path: String, read_only: bool) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        crate::disk::read_only::set_read_only(&device_path, read_only)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Set the read-only flag again on the disk it was cleared on for the flash
+fn restore_read_only(state: &mut FlashState) -> Task<crate::ui::messages::Message> {
+    let Some(device_path) = state.read_only_cleared.take() else {
+        return Task::none();
+    };
+    info!("Setting the read-only flag of {} again", device_path);
+    Task::perform(change_read_only(device_path, true), |result| {
+        crate::ui::messages::Message::Flash(FlashMessage::ReadOnlyRestored(result))
+    })
+}
+
+/// Read the partition layout of the target device for the pre-write confirmation
+async fn read_target_layout(device_path: String) -> Result<crate::disk::DiskLayout, String> {
+    // Open in edit mode so nothing is cleaned or dismounted just to look at the disk
+    let disk = Disk::lock_path(&device_path, true)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", device_path, e))?;
+    disk.read_layout()
+        .map_err(|e| format!("Failed to read partition table: {}", e))
+}
+
+/// Start checking the contents of the selected image file, unless it is already checked
+fn start_source_check(state: &mut FlashState) -> Task<crate::ui::messages::Message> {
+    let Some(image) = state.selected_image() else {
+        return Task::none();
+    };
+    let (Some(path), Some(metadata)) = (image.path.clone(), image.metadata.clone()) else {
+        return Task::none();
+    };
+    if state
+        .source_check
+        .as_ref()
+        .is_some_and(|check| check.path == path)
+    {
+        return Task::none();
+    }
+    if let Some(previous) = state.source_check.take() {
+        previous.cancel_token.cancel();
+    }
+
+    info!("Checking the contents of {}", path);
+    let cancel_token = CancelToken::new();
+    state.source_check = Some(SourceCheck {
+        path: path.clone(),
+        status: SourceCheckStatus::Checking(0.0),
+        cancel_token: cancel_token.clone(),
+    });
+
+    let total = metadata.uncompressed_size.max(1);
+    let progress_path = path.clone();
+    let result_path = path.clone();
+    Task::sip(
+        iced::task::sipper(async move |sipper| {
+            tokio::task::spawn_blocking(move || {
+                // Only report whole percents, the image may be split into thousands of reads
+                let mut reported = 0;
+                crate::disk::check_image_file(
+                    std::path::Path::new(&path),
+                    &metadata,
+                    &cancel_token,
+                    |bytes| {
+                        let percent = bytes * 100 / total;
+                        if percent != reported {
+                            reported = percent;
+                            let mut sipper = sipper.clone();
+                            std::mem::drop(tokio::spawn(async move { sipper.send(bytes).await }));
+                        }
+                    },
+                )
+                .map_err(|e| format!("{:#}", e))
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("Image check failed: {}", e)))
+        }),
+        move |bytes| {
+            crate::ui::messages::Message::Flash(FlashMessage::SourceCheckProgress(
+                progress_path.clone(),
+                bytes as f32 / total as f32,
+            ))
+        },
+        move |result| {
+            crate::ui::messages::Message::Flash(FlashMessage::SourceChecked(
+                result_path.clone(),
+                result,
+            ))
+        },
+    )
+}
+
+/// Find the uncompressed size and hash of a picked image file, so its write is verified
+fn start_local_analysis(
+    state: &mut FlashState,
+    path: std::path::PathBuf,
+) -> Task<crate::ui::messages::Message> {
+    if let Some(previous) = state.local_analysis.take() {
+        previous.cancel_token.cancel();
+    }
+
+    let path_string = path.display().to_string();
+    let cancel_token = CancelToken::new();
+    state.local_analysis = Some(LocalAnalysis {
+        path: path_string.clone(),
+        bytes_read: 0,
+        cancel_token: cancel_token.clone(),
+    });
+
+    let progress_path = path_string.clone();
+    let result_path = path_string;
+    Task::sip(
+        iced::task::sipper(async move |sipper| {
+            tokio::task::spawn_blocking(move || {
+                // Report every 64 MB, the image may be split into thousands of reads
+                let mut reported = 0;
+                crate::utils::image_meta::analyze(&path, false, &cancel_token, |bytes| {
+                    let step = bytes / (64 * 1024 * 1024);
+                    if step != reported {
+                        reported = step;
+                        let mut sipper = sipper.clone();
+                        std::mem::drop(tokio::spawn(async move { sipper.send(bytes).await }));
+                    }
+                })
+                .map(|analysis| analysis.metadata)
+                .map_err(|e| format!("{:#}", e))
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("Image analysis failed: {}", e)))
+        }),
+        move |bytes| {
+            crate::ui::messages::Message::Flash(FlashMessage::LocalImageProgress(
+                progress_path.clone(),
+                bytes,
+            ))
+        },
+        move |result| {
+            crate::ui::messages::Message::Flash(FlashMessage::LocalImageAnalyzed(
+                result_path.clone(),
+                result,
+            ))
+        },
+    )
+}
+
+/// Ask for an image file to write and check that it is a disk image
+async fn pick_local_image() -> Result<Option<std::path::PathBuf>, String> {
+    let Some(handle) = rfd::AsyncFileDialog::new()
+        .set_title("Select Image File")
+        .add_filter("Disk images", &["img", "iso", "raw", "xz", "zst"])
+        .add_filter("All files", &["*"])
+        .pick_file()
+        .await
+    else {
+        return Ok(None);
+    };
+
+    check_image_file(handle.path().to_path_buf())
+        .await
+        .map(Some)
+}
+
+/// `path`, if the file is a disk image that can be written
+async fn check_image_file(path: std::path::PathBuf) -> Result<std::path::PathBuf, String> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+    let detect_path = path.clone();
+    let format = tokio::task::spawn_blocking(move || ImageFormat::detect_file(&detect_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to read {}: {}", file_name, e))?;
+    match format {
+        Some(_) => Ok(path),
+        None => Err(format!(
+            "{} is not a disk image. Select an .img or .iso file or an XZ or Zstandard compressed image.",
+            file_name
+        )),
+    }
+}
+
+/// Name of a cached image inside the download cache directory
+fn cached_file_name(image_path: &str) -> String {
+    std::path::Path::new(image_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| image_path.to_string())
+}
@@ -1,4 +1,4 @@
-use crate::models::ImageMetadata;
+use crate::models::{ImageMetadata, OperationId};
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
@@ -9,7 +9,15 @@ pub enum FlashMessage {
     SelectOsImageFromGroup(usize, usize), // Group index, version index (0 = latest, 1+ = older)
     DownloadOsImageFromGroup(usize, usize), // Group index, version index
     AnalyzeOsImageFromGroup(usize, usize), // Group index, version index - analyze downloaded image
+    StreamOsImageFromGroup(usize, usize), // Group index, version index - write without downloading
     ToggleVersionHistory(usize), // Toggle expanded state for a group
+    PickLocalImage,        // Ask for an image file instead of a repository image
+    LocalImagePicked(Result<Option<PathBuf>, String>), // The picked file, None if cancelled
+    LocalImageDropped(PathBuf), // A file dropped onto the window, checked before it is used
+    DroppedImageChecked(Result<PathBuf, String>), // The dropped file, if it is a disk image
+    LocalImageProgress(String, u64), // Image path and uncompressed bytes analyzed so far
+    LocalImageAnalyzed(String, Result<ImageMetadata, String>), // Image path and its metadata
+    UseRecentImage(crate::utils::recent_images::RecentImage), // Select an image flashed before
     ProcessingProgress(
         String,
         crate::utils::streaming_hash_calculator::ProcessingProgress,
@@ -19,16 +27,67 @@ pub enum FlashMessage {
     GotoSelectTargetDevice, // Go to storage device selection screen
     GotoConfigureSettings, // Go to image configuration screen
     SelectTargetDevice(usize),
-    RefreshTargetDevices, // Delegate device refresh to DeviceSelection module
+    RefreshTargetDevices,   // Delegate device refresh to DeviceSelection module
+    TestDeviceSpeed(usize), // Measure the write and read speed of a listed device
+    DeviceSpeedTested(String, Result<crate::disk::SpeedTestResult, String>), // Device path and result
+    TestDeviceCapacity(usize), // Ask to erase a listed device to check its real capacity
+    ConfirmCapacityTest,       // Erase the device and start its capacity test
+    CancelCapacityTest,        // Stop the capacity test, or don't start it
+    SetCapacityTestTypedName(String), // Name typed to confirm the capacity test
+    CapacityTestProgress(String, f32), // Device path and fraction of the test done
+    DeviceCapacityTested(String, Result<crate::disk::CapacityReport, String>), // Device path and report
+    ExportDeviceInventory(crate::utils::device_inventory::InventoryFormat), // Delegate the inventory export to DeviceSelection module
+    EditDeviceNickname(usize), // Start typing a nickname for a listed device
+    SetNicknameText(String),   // The nickname typed so far
+    SaveDeviceNickname,        // Keep the typed nickname in the settings
+    CancelNicknameEdit,        // Leave the device's nickname as it was
+    UseWholeDisk,              // Select the disk offered for a requested partition
+    DismissWholeDisk,          // Leave the target to the user instead
+    ConfirmWrite,              // Read the target disk's layout and ask for confirmation
+    TargetLayoutLoaded(Result<crate::disk::DiskLayout, String>),
+    TargetUsageLoaded(crate::disk::DeviceUsage), // Mounts and programs using the target disk
+    TogglePreserveConfig(bool), // Keep the target's current configuration when re-flashing
+    SetTypedDeviceName(String), // Name or size of the target, typed to enable erasing it
+    ClearReadOnly,              // Clear the read-only flag of the target disk
+    ReadOnlyCleared(Result<String, String>), // Path of the disk whose flag was cleared
+    ReadOnlyRestored(Result<(), String>), // The flag was set again after a cancelled flash
+    AddToQueue,                 // Flash the confirmed image and device later, from the queue
+    StartKiosk,                 // Flash the confirmed disk and then every one inserted
     WriteImage,
+    ConfigBackedUp(Result<(PathBuf, crate::disk::ConfigSnapshot), String>),
+    WipeSnapshotTaken(Result<crate::disk::WipeSnapshot, String>), // The regions the write wipes, saved
+    CachedImageChecked(bool), // Whether the cached image still matches its hash
+    SourceCheckProgress(String, f32), // Image path and fraction of its contents checked
+    SourceChecked(String, Result<(), String>), // Image path and whether its contents are intact
     CancelWrite,
+    UndoWipe,                       // Put back the partition table of a cancelled write
+    WipeUndone(Result<(), String>), // Whether the partition table was put back
+    UndoWipeExpired(OperationId),   // The undo window of the cancelled write has closed
+    SkipVerification,               // Keep the completed write without reading it back
+    ToggleWriteDetails,             // Show or hide the chunk write times of the current write
+    HideToTray,                     // Keep flashing with the window hidden to the system tray
     FlashAnother,
-    WriteImageProgress(f32),   // Update the image writing progress
-    VerificationProgress(f32), // Update the verification progress
-    WriteImageCompleted,       // Image write completed successfully
-    WriteImageFailed(String),  // Image write failed with error message
-    BackToSelectOsImage,       // Go back to the OS image selection screen
-    BackToSelectTargetDevice,  // Go back to target device selection screen
-    BackToMainMenu,            // Navigation: go back to main menu
-    RefreshRepoData,           // App action: refresh repository data
+    ExportReport(crate::utils::flash_report::ReportFormat), // Save the flash report of the finished write
+    ReportExported(Result<Option<PathBuf>, String>), // Where the report was saved, None if cancelled
+    // Write the update belongs to, and the phase of the write and its progress
+    Progress(OperationId, crate::disk::FlashPhase),
+    WriteImageCompleted(OperationId, bool), // Image write completed; whether it was verified
+    WriteImageFailed(OperationId, String),  // Image write failed with error message
+    BackToSelectOsImage,                    // Go back to the OS image selection screen
+    BackToSelectTargetDevice,               // Go back to target device selection screen
+    BackToConfigureSettings,                // Go back to configuration without resetting it
+    BackToMainMenu,                         // Navigation: go back to main menu
+    RefreshRepoData,                        // App action: refresh repository data
+}
+
+impl FlashMessage {
+    /// The write this message reports on, if it comes from one
+    pub fn operation(&self) -> Option<OperationId> {
+        match self {
+            FlashMessage::Progress(operation, _)
+            | FlashMessage::WriteImageCompleted(operation, _)
+            | FlashMessage::WriteImageFailed(operation, _) => Some(*operation),
+            _ => None,
+        }
+    }
 }
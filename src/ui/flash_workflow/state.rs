@@ -1,4 +1,8 @@
+use crate::disk::{ConfigSchema, ImagePartition};
 pub use crate::models::CancelToken;
+use crate::models::OperationId;
+use crate::utils::compatibility::ImageRequirements;
+use crate::utils::recent_images::RecentImage;
 
 #[derive(Debug, Clone)]
 pub struct OsImage {
@@ -11,6 +15,60 @@ pub struct OsImage {
     pub sha256: String,                  // SHA256 hash for verification
     pub is_latest: bool,                 // Whether this is the latest version in the channel
     pub metadata: Option<ImageMetadata>, // Uncompressed image metadata
+    pub config_schema: ConfigSchema,     // Configuration settings the image understands
+    pub partitions: Vec<ImagePartition>, // Partitions listed in the manifest, for write progress
+    pub requirements: ImageRequirements, // Hardware the manifest says the image is built for
+    pub file_size: Option<u64>,          // Size of the (compressed) image file, if known
+    pub local: bool,                     // Picked from a file instead of the repository
+}
+
+impl OsImage {
+    /// An image file picked by the user, written without a manifest entry
+    pub fn from_local_file(path: &std::path::Path) -> Self {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        Self {
+            name: "Image file".to_string(),
+            version: file_name,
+            description: path.display().to_string(),
+            downloaded: true,
+            path: Some(path.display().to_string()),
+            created: String::new(),
+            sha256: String::new(),
+            is_latest: false,
+            metadata: None,
+            config_schema: ConfigSchema::default(),
+            partitions: Vec::new(),
+            requirements: ImageRequirements::default(),
+            file_size: std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+            local: true,
+        }
+    }
+
+    /// The image as the list of recently used images keeps it
+    ///
+    /// `url` is where a repository image is downloaded from; without it the cached file's
+    /// path is kept, and without either there is nothing to remember.
+    pub fn to_recent(&self, url: Option<String>, last_used: i64) -> Option<RecentImage> {
+        let location = if self.local {
+            self.path.clone()?
+        } else {
+            url.or_else(|| self.path.clone())?
+        };
+        Some(RecentImage {
+            location,
+            channel: (!self.local).then(|| self.name.clone()),
+            version: self.version.clone(),
+            sha256: self
+                .metadata
+                .as_ref()
+                .map(|metadata| metadata.uncompressed_hash.clone())
+                .unwrap_or_default(),
+            last_used,
+        })
+    }
 }
 
 pub use crate::models::ImageMetadata;
@@ -24,6 +82,94 @@ pub struct OsImageGroup {
     pub expanded: bool,               // Whether older versions are shown
 }
 
+/// Check of the picked image file's contents, run before any device is erased
+#[derive(Debug, Clone)]
+pub struct SourceCheck {
+    pub path: String,              // Image file being checked
+    pub status: SourceCheckStatus, // Progress or outcome of the check
+    pub cancel_token: CancelToken, // Stops the check when another image is picked
+}
+
+/// Analysis of a picked image file, finding the size and hash to verify its write against
+#[derive(Debug, Clone)]
+pub struct LocalAnalysis {
+    pub path: String,              // Image file being analyzed
+    pub bytes_read: u64,           // Uncompressed bytes hashed so far
+    pub cancel_token: CancelToken, // Stops the analysis when another image is picked
+}
+
+/// Speed test of a device in the target list, the last one started
+#[derive(Debug, Clone)]
+pub struct SpeedTest {
+    pub path: String,            // Device being tested
+    pub status: SpeedTestStatus, // Whether it is still running, and what it measured
+}
+
+impl SpeedTest {
+    pub fn is_running(&self) -> bool {
+        self.status == SpeedTestStatus::Running
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpeedTestStatus {
+    Running,
+    Done(crate::disk::SpeedTestResult),
+    Failed(String),
+}
+
+/// Nickname being typed on a card of the target list
+#[derive(Debug, Clone, PartialEq)]
+pub struct NicknameEdit {
+    pub serial: String,   // Serial number or WWN the nickname is kept under
+    pub nickname: String, // What was typed so far, the nickname is removed if left blank
+}
+
+/// A partition or volume was asked for, the disk holding it is offered instead
+#[derive(Debug, Clone, PartialEq)]
+pub struct WholeDiskOffer {
+    pub reason: String, // Why the requested device can't be written
+    pub disk: String,   // Path of the listed whole disk
+}
+
+/// Capacity test of a device in the target list, the last one asked for
+#[derive(Debug, Clone)]
+pub struct CapacityTest {
+    pub path: String,               // Device being tested
+    pub status: CapacityTestStatus, // Whether it is confirmed, running, or what it found
+    pub cancel_token: CancelToken,  // Stops the test, leaving the device partly erased
+    pub typed_name: Option<String>, // Name typed to confirm, None if the device needs none
+}
+
+impl CapacityTest {
+    pub fn is_running(&self) -> bool {
+        matches!(self.status, CapacityTestStatus::Running(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapacityTestStatus {
+    Confirming,   // The user is asked to confirm erasing the device
+    Running(f32), // Fraction of the test done
+    Done(crate::disk::CapacityReport),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceCheckStatus {
+    Checking(f32),   // Fraction of the uncompressed image checked
+    Valid,           // The contents match the image's hash
+    Corrupt(String), // Why the file can't be written
+}
+
+/// Undoing the wipe of a write cancelled before the image was written
+#[derive(Debug, Clone, PartialEq)]
+pub enum WipeUndo {
+    Offered(OperationId), // The cancelled write, offered until its undo window closes
+    Restoring,            // The saved regions are being written back
+    Restored,             // The disk has its previous partition table again
+}
+
 #[derive(Debug, Clone)]
 pub enum FlashWorkflowState {
     SelectOsImage,
@@ -39,9 +185,13 @@ pub enum FlashWorkflowState {
     },
     SelectTargetDevice,
     ConfigureSettings,
-    WritingImage(f32),   // Progress 0.0 - 1.0 for image writing
-    VerifyingImage(f32), // Progress 0.0 - 1.0 for image verification
-    Completion(bool),    // Success or failure
+    ConfirmWrite,        // Review the target disk's current layout before it is erased
+    ClearingPartitions {
+        progress: f32,   // Progress 0.0 - 1.0 for removing existing partitions
+        message: String, // Current preparation step
+    },
+    Flashing(crate::disk::FlashPhase), // Writing, verifying and finishing the image
+    Completion(bool),                  // Success or failure
 }
 
 #[derive(Debug, Clone)]
@@ -51,9 +201,41 @@ pub struct FlashState {
     pub os_image_groups: Vec<OsImageGroup>,
     pub selected_os_image: Option<usize>,
     pub selected_os_image_group: Option<(usize, usize)>,
+    pub local_image: Option<OsImage>, // Image file picked instead of a repository image
+    pub local_analysis: Option<LocalAnalysis>, // Metadata of the picked image file being found
     pub selected_device: Option<usize>,
+    pub selected_target: Option<crate::ui::device_selection::StorageDevice>, // Device as selected, to re-find it after a refresh
     pub downloads_in_progress: Vec<(String, f32)>, // (version_id, progress)
     pub cancel_token: CancelToken, // Cancellation token for this workflow's operations
+    pub skip_verification: CancelToken, // Stops verification of the current write, keeping the write
+    pub write_verified: bool,           // The finished write was read back and checked
+    pub target_layout: Option<Result<crate::disk::DiskLayout, String>>, // None while the layout is being read
+    pub target_usage: Option<crate::disk::DeviceUsage>, // Mounts and programs using the target, None while looked up
+    pub typed_device_name: String, // What was typed to confirm erasing the target
+    pub read_only_cleared: Option<String>, // Target whose read-only flag we cleared, set again if the flash is cancelled
+    pub stream_image: bool, // Write straight from the repository instead of the download cache
+    pub manifest_status: crate::utils::repo::manifest::ManifestStatus, // Signature check of the image list
+    pub cached_image_checked: bool, // The cached image's hash was checked for the pending write
+    pub source_check: Option<SourceCheck>, // Contents check of the picked image file
+    pub write_after_source_check: bool, // The pending write waits for the contents check
+    pub speed_test: Option<SpeedTest>, // Speed test of a device in the target list
+    pub capacity_test: Option<CapacityTest>, // Capacity test of a device in the target list
+    pub nickname_edit: Option<NicknameEdit>, // Nickname being given to a device in the target list
+    pub whole_disk_offer: Option<WholeDiskOffer>, // Disk offered for a requested partition
+    pub preserve_config: bool, // Re-apply the target's current configuration instead of the entered one
+    pub config_backed_up: bool, // The target's configuration partition was backed up for the pending write
+    pub config_backup: Option<(std::path::PathBuf, crate::disk::ConfigSnapshot)>, // Backup file and its files
+    pub wipe_snapshot_taken: bool, // The regions wiped before the pending write were saved, or couldn't be
+    pub wipe_snapshot: Option<crate::disk::WipeSnapshot>, // The target's partition table before the wipe
+    pub wipe_undo: Option<WipeUndo>, // Undo of the wipe offered after a cancelled write
+    pub template_index: u64,         // Value of {index} in preset templates for the next write
+    pub template_index_used: bool,   // The pending write's configuration used {index}
+    pub flash_report: Option<crate::utils::flash_report::FlashReport>, // Record of the current write
+    pub report_path: Option<std::path::PathBuf>, // Where the report was last exported to
+    pub bytes_written: u64,                      // Image bytes written by the current write so far
+    pub show_write_details: bool,                // The progress view shows the chunk write times
+    pub failed_attempts: u32, // Failed writes to the selected device since the last success
+    pub operation: Option<OperationId>, // The current or last write, its updates are the ones shown
 }
 
 impl FlashState {
@@ -64,9 +246,163 @@ impl FlashState {
             os_image_groups: Vec::new(),
             selected_os_image: None,
             selected_os_image_group: None,
+            local_image: None,
+            local_analysis: None,
             selected_device: None,
+            selected_target: None,
             downloads_in_progress: Vec::new(),
             cancel_token: CancelToken::new(),
+            skip_verification: CancelToken::new(),
+            write_verified: true,
+            target_layout: None,
+            target_usage: None,
+            typed_device_name: String::new(),
+            read_only_cleared: None,
+            stream_image: false,
+            manifest_status: crate::utils::repo::manifest::ManifestStatus::Unknown,
+            cached_image_checked: false,
+            source_check: None,
+            write_after_source_check: false,
+            speed_test: None,
+            capacity_test: None,
+            nickname_edit: None,
+            whole_disk_offer: None,
+            preserve_config: false,
+            config_backed_up: false,
+            config_backup: None,
+            wipe_snapshot_taken: false,
+            wipe_snapshot: None,
+            wipe_undo: None,
+            template_index: 1,
+            template_index_used: false,
+            flash_report: None,
+            report_path: None,
+            bytes_written: 0,
+            show_write_details: false,
+            failed_attempts: 0,
+            operation: None,
+        }
+    }
+
+    /// Forget the picked image file, stopping its analysis
+    pub fn clear_local_image(&mut self) {
+        self.local_image = None;
+        if let Some(analysis) = self.local_analysis.take() {
+            analysis.cancel_token.cancel();
+        }
+    }
+
+    /// Select a version of an image group, written from the download cache if it is there
+    pub fn select_repository_image(&mut self, group_idx: usize, version_idx: usize) {
+        let group = &self.os_image_groups[group_idx];
+        let downloaded = match version_idx {
+            0 => group.latest_version.downloaded,
+            idx => group.older_versions[idx - 1].downloaded,
+        };
+        self.clear_local_image();
+        self.selected_os_image = None;
+        self.selected_os_image_group = Some((group_idx, version_idx));
+        self.stream_image = !downloaded;
+    }
+
+    /// Whether the selected device's read-only flag is set and hasn't been cleared yet
+    pub fn target_read_only(
+        &self,
+        device_selection: &crate::ui::device_selection::DeviceSelectionState,
+    ) -> bool {
+        self.selected_device
+            .and_then(|idx| device_selection.devices.get(idx))
+            .is_some_and(|device| {
+                device.read_only && self.read_only_cleared.as_deref() != Some(device.path.as_str())
+            })
+    }
+
+    /// Whether a speed or capacity test is running on the device at `path`
+    pub fn is_testing(&self, path: &str) -> bool {
+        self.speed_test
+            .as_ref()
+            .is_some_and(|test| test.path == path && test.is_running())
+            || self
+                .capacity_test
+                .as_ref()
+                .is_some_and(|test| test.path == path && test.is_running())
+    }
+
+    /// Delete the saved partition table, the wipe can't be undone from now on
+    pub fn discard_wipe_snapshot(&mut self) {
+        if let Some(snapshot) = self.wipe_snapshot.take() {
+            snapshot.discard();
+        }
+        self.wipe_undo = None;
+    }
+
+    /// The image picked in either image list
+    pub fn selected_image(&self) -> Option<&OsImage> {
+        if let Some(image) = &self.local_image {
+            return Some(image);
+        }
+        if let Some(image_idx) = self.selected_os_image {
+            return self.os_images.get(image_idx);
+        }
+        let (group_idx, version_idx) = self.selected_os_image_group?;
+        let group = self.os_image_groups.get(group_idx)?;
+        match version_idx {
+            0 => Some(&group.latest_version),
+            _ => group.older_versions.get(version_idx - 1),
+        }
+    }
+
+    /// Whether the selected image comes from a repository whose image list failed verification
+    pub fn image_untrusted(&self) -> bool {
+        self.manifest_status.blocks_flashing()
+            && self.selected_image().is_some_and(|image| !image.local)
+    }
+
+    /// Whether `message` belongs to the current write, or to no write at all
+    ///
+    /// A cancelled write keeps sending updates until it notices, and they would otherwise be
+    /// taken for the write started after it.
+    pub fn is_current(&self, message: &super::FlashMessage) -> bool {
+        message
+            .operation()
+            .is_none_or(|operation| self.operation == Some(operation))
+    }
+
+    /// Statistics of the write that `message` ends, if it ends one that was started
+    pub fn flash_metrics(
+        &self,
+        message: &super::FlashMessage,
+    ) -> Option<crate::utils::telemetry::FlashMetrics> {
+        use crate::utils::telemetry::{ErrorCategory, FlashMetrics, FlashOutcome};
+
+        if !matches!(
+            self.workflow_state,
+            FlashWorkflowState::ClearingPartitions { .. } | FlashWorkflowState::Flashing(_)
+        ) {
+            return None;
         }
+        let (outcome, error_category) = match message {
+            super::FlashMessage::WriteImageCompleted(_, _) => (FlashOutcome::Succeeded, None),
+            super::FlashMessage::WriteImageFailed(_, error) => {
+                (FlashOutcome::Failed, Some(ErrorCategory::classify(error)))
+            }
+            super::FlashMessage::CancelWrite => (FlashOutcome::Cancelled, None),
+            _ => return None,
+        };
+        let report = self.flash_report.as_ref()?;
+        let finished_at = chrono::Local::now();
+
+        Some(FlashMetrics {
+            outcome,
+            duration_secs: (finished_at - report.started_at).num_milliseconds() as f64 / 1000.0,
+            bytes_written: self.bytes_written,
+            verified: matches!(message, super::FlashMessage::WriteImageCompleted(_, true)),
+            error_category,
+            retries: self.failed_attempts,
+            image_channel: report.image_channel.clone(),
+            image_version: report.image_version.clone(),
+            device_size: report.device_size,
+            finished_at: finished_at.timestamp(),
+        })
     }
 }
@@ -1,10 +1,20 @@
-use super::{FlashMessage, OsImage, OsImageGroup};
+use super::{
+    CapacityTest, CapacityTestStatus, FlashMessage, LocalAnalysis, NicknameEdit, OsImage,
+    OsImageGroup, SourceCheck, SourceCheckStatus, SpeedTest, SpeedTestStatus, WholeDiskOffer,
+    WipeUndo,
+};
+use crate::disk::{FlashPhase, WriteLatency};
 use crate::style;
 use crate::ui::device_selection::StorageDevice;
 use crate::ui::{LOGO_SVG, icons};
+use crate::utils::device_inventory::InventoryFormat;
+use crate::utils::flash_history::{DeviceWear, MeasuredSpeed};
+use crate::utils::flash_report::ReportFormat;
+use crate::utils::recent_images::RecentImage;
 use iced::alignment::Horizontal;
 use iced::widget::{
-    Column, Container, button, column, container, progress_bar, row, scrollable, svg, text,
+    Column, Container, button, checkbox, column, container, progress_bar, row, scrollable, svg,
+    text, text_input,
 };
 use iced::{Alignment, Color, Element, Length};
 use iced::{Border, Theme};
@@ -240,7 +250,12 @@ pub fn view_select_os_image<'a>(
 pub fn view_select_os_image_groups<'a>(
     os_image_groups: &'a [OsImageGroup],
     selected_os_image_group: Option<(usize, usize)>,
+    local_image: Option<&'a OsImage>,
+    local_analysis: Option<&'a LocalAnalysis>,
+    measured_speed: Option<MeasuredSpeed>,
+    recent_images: &'a [RecentImage],
     is_loading: bool,
+    manifest_warning: Option<&str>,
 ) -> Element<'a, FlashMessage> {
     // Page header
     let header = container(text("Select OS Image").size(28))
@@ -411,9 +426,25 @@ pub fn view_select_os_image_groups<'a>(
                         })
                     };
 
+                    // Images that aren't cached can also be written straight from the repository
+                    let mut latest_actions = row![].spacing(8).align_y(Alignment::Center);
+                    if !group.latest_version.downloaded {
+                        latest_actions = latest_actions.push(
+                            button(
+                                row![icons::send(), text("Stream")]
+                                    .spacing(5)
+                                    .align_y(Alignment::Center),
+                            )
+                            .on_press(FlashMessage::StreamOsImageFromGroup(group_idx, 0))
+                            .padding(10)
+                            .style(button::secondary),
+                        );
+                    }
+                    let latest_actions = latest_actions.push(latest_action_button);
+
                     // Create latest version container
                     let latest_container = container(
-                        row![latest_image_info, latest_action_button]
+                        row![latest_image_info, latest_actions]
                             .spacing(15)
                             .align_y(Alignment::Center),
                     )
@@ -581,8 +612,27 @@ pub fn view_select_os_image_groups<'a>(
                                                 })
                                             };
 
+                                        let mut older_actions =
+                                            row![].spacing(8).align_y(Alignment::Center);
+                                        if !older_image.downloaded {
+                                            older_actions = older_actions.push(
+                                                button(
+                                                    row![icons::send(), text("Stream")]
+                                                        .spacing(5)
+                                                        .align_y(Alignment::Center),
+                                                )
+                                                .on_press(FlashMessage::StreamOsImageFromGroup(
+                                                    group_idx,
+                                                    actual_version_idx,
+                                                ))
+                                                .padding(8)
+                                                .style(button::secondary),
+                                            );
+                                        }
+                                        let older_actions = older_actions.push(older_action_button);
+
                                         container(
-                                            row![older_image_info, older_action_button]
+                                            row![older_image_info, older_actions]
                                                 .spacing(15)
                                                 .align_y(Alignment::Center),
                                         )
@@ -637,7 +687,9 @@ pub fn view_select_os_image_groups<'a>(
         };
 
     // Navigation buttons
-    let has_selection = selected_os_image_group.is_some();
+    // A local image can only be written once its size and hash are known
+    let has_selection = selected_os_image_group.is_some()
+        || local_image.is_some_and(|image| image.metadata.is_some());
     let next_button = if has_selection {
         button(
             row!["Select Target Device", icons::navigate_next()]
@@ -666,8 +718,17 @@ pub fn view_select_os_image_groups<'a>(
     .padding(12)
     .style(style::navigation_back_button);
 
+    let local_image_button = button(
+        row![icons::file_upload(), "Use Image File"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(FlashMessage::PickLocalImage)
+    .padding(12)
+    .style(button::secondary);
+
     let navigation = container(
-        row![back_button, next_button]
+        row![back_button, local_image_button, next_button]
             .spacing(15)
             .width(Length::Fill)
             .align_y(Alignment::Center),
@@ -677,7 +738,22 @@ pub fn view_select_os_image_groups<'a>(
     .style(crate::style::bordered_box);
 
     // Main content
-    let content = column![header, scrollable_content, navigation,].width(Length::Fill);
+    let mut content = column![header].width(Length::Fill);
+    if let Some(warning) = manifest_warning {
+        content = content.push(
+            container(view_manifest_warning(warning))
+                .width(Length::Fill)
+                .padding(10),
+        );
+    }
+    let mut content = content.push(scrollable_content);
+    if !recent_images.is_empty() {
+        content = content.push(view_recent_images(recent_images));
+    }
+    if let Some(image) = local_image {
+        content = content.push(view_local_image(image, local_analysis, measured_speed));
+    }
+    let content = content.push(navigation);
 
     container(content)
         .width(Length::Fill)
@@ -686,6 +762,141 @@ pub fn view_select_os_image_groups<'a>(
         .into()
 }
 
+/// Images flashed before, each selected again with one click
+///
+/// Image files that were moved or deleted are listed, but can't be selected.
+fn view_recent_images(recent_images: &[RecentImage]) -> Element<'_, FlashMessage> {
+    let entries = recent_images.iter().map(|recent| {
+        let source = recent.channel.as_deref().unwrap_or("Image file");
+        let last_used = chrono::DateTime::from_timestamp(recent.last_used, 0)
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let available = recent.is_available();
+        let label = row![
+            icons::history(),
+            text(&recent.version).size(14).width(Length::Fill),
+            text(if available { source } else { "File missing" })
+                .size(12)
+                .color(Color::from_rgb(0.7, 0.7, 0.7)),
+            text(last_used)
+                .size(12)
+                .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        button(label)
+            .on_press_maybe(available.then(|| FlashMessage::UseRecentImage(recent.clone())))
+            .width(Length::Fill)
+            .padding(6)
+            .style(button::text)
+            .into()
+    });
+
+    container(
+        column![text("Recently used").size(14)]
+            .extend(entries)
+            .spacing(2),
+    )
+    .width(Length::Fill)
+    .padding(10)
+    .style(crate::style::bordered_box)
+    .into()
+}
+
+/// The picked image file: its sizes, hash and how long flashing it will take
+///
+/// The estimate goes by the speed measured on earlier flashes, see
+/// [`crate::utils::flash_history::FlashHistory::measured_speed`].
+fn view_local_image<'a>(
+    image: &'a OsImage,
+    analysis: Option<&LocalAnalysis>,
+    measured_speed: Option<MeasuredSpeed>,
+) -> Element<'a, FlashMessage> {
+    let format_size = crate::disk::layout::format_size;
+    let file_size = image
+        .file_size
+        .map_or_else(|| "Unknown".to_string(), format_size);
+    let uncompressed_size = match (&image.metadata, analysis) {
+        (Some(metadata), _) => format_size(metadata.uncompressed_size),
+        (None, Some(analysis)) => format!("{} so far", format_size(analysis.bytes_read)),
+        (None, None) => "Not known yet".to_string(),
+    };
+    let (status_icon, hash) = match (analysis, &image.metadata) {
+        (Some(_), _) => (icons::timer(), "Computing...".to_string()),
+        (None, Some(metadata)) => (
+            icons::check_circle().color(crate::style::PRIMARY),
+            format!(
+                "{} (computed from the file)",
+                &metadata.uncompressed_hash[..16.min(metadata.uncompressed_hash.len())]
+            ),
+        ),
+        (None, None) => (icons::timer(), "Waiting for analysis...".to_string()),
+    };
+    let flash_time = match (&image.metadata, measured_speed) {
+        (Some(metadata), Some(speed)) => format!(
+            "{} at {}/s, as measured on {}",
+            format_estimate(speed.estimate(metadata.uncompressed_size)),
+            format_size(speed.bytes_per_second),
+            if speed.of_target {
+                "the selected device"
+            } else {
+                "the last device flashed"
+            }
+        ),
+        (Some(_), None) => "Not known until a device has been flashed".to_string(),
+        (None, _) => "Known once the image is analyzed".to_string(),
+    };
+
+    let detail = |label: &'static str, value: String| {
+        row![
+            text(label)
+                .size(12)
+                .width(Length::Fixed(140.0))
+                .color(Color::from_rgb(0.7, 0.7, 0.7)),
+            text(value).size(12),
+        ]
+        .spacing(10)
+    };
+
+    container(
+        row![
+            status_icon,
+            column![
+                text(&image.version).size(16),
+                text(&image.description)
+                    .size(12)
+                    .color(Color::from_rgb(0.7, 0.7, 0.7)),
+                detail("File size", file_size),
+                detail("Uncompressed size", uncompressed_size),
+                detail("SHA-256", hash),
+                detail("Estimated flash time", flash_time),
+            ]
+            .spacing(2)
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::bordered_box)
+    .into()
+}
+
+/// A rough duration such as "about 4 min", precise estimates of flash times mislead
+fn format_estimate(duration: std::time::Duration) -> String {
+    let minutes = duration.as_secs().div_ceil(60);
+    match minutes {
+        0 | 1 => "Under a minute".to_string(),
+        2..60 => format!("About {} min", minutes),
+        _ => format!("About {} h {} min", minutes / 60, minutes % 60),
+    }
+}
+
 pub fn view_processing_image(
     version_id: &str,
     download_progress: f32,
@@ -876,7 +1087,20 @@ pub fn view_processing_image(
 pub fn view_select_target_device<'a>(
     storage_devices: &'a [StorageDevice],
     selected_device: Option<usize>,
+    source_check: Option<&'a SourceCheck>,
+    local_analysis: Option<&'a LocalAnalysis>,
+    speed_test: Option<&'a SpeedTest>,
+    capacity_test: Option<&'a CapacityTest>,
+    nickname_edit: Option<&'a NicknameEdit>,
+    whole_disk_offer: Option<&'a WholeDiskOffer>,
 ) -> Element<'a, FlashMessage> {
+    let speed_testing = speed_test.is_some_and(SpeedTest::is_running);
+    let capacity_testing = capacity_test.is_some_and(CapacityTest::is_running);
+    // Either test has the device open until it is done
+    let testing = |path: &str| {
+        speed_test.is_some_and(|test| test.path == path && test.is_running())
+            || capacity_test.is_some_and(|test| test.path == path && test.is_running())
+    };
     let title = text("Select Target Device")
         .size(30)
         .width(Length::Fill)
@@ -922,11 +1146,25 @@ pub fn view_select_target_device<'a>(
                     Color::from_rgb(0.6, 0.6, 0.6)
                 }),
                 column![
-                    text(&device.name).size(18).color(if is_selected {
-                        Color::from_rgb(0.1, 0.1, 0.1) // Dark text on light background
-                    } else {
-                        Color::from_rgb(0.9, 0.9, 0.9)
-                    }),
+                    row![
+                        text(device.display_name()).size(18).color(if is_selected {
+                            Color::from_rgb(0.1, 0.1, 0.1) // Dark text on light background
+                        } else {
+                            Color::from_rgb(0.9, 0.9, 0.9)
+                        }),
+                        // Nicknames are kept by serial number
+                        button(icons::edit().size(14))
+                            .on_press_maybe(
+                                device
+                                    .assignment_info()
+                                    .serial
+                                    .map(|_| FlashMessage::EditDeviceNickname(i)),
+                            )
+                            .padding(2)
+                            .style(button::text),
+                    ]
+                    .spacing(6)
+                    .align_y(Alignment::Center),
                     text(device.type_name()).size(12).color(if is_selected {
                         crate::style::PRIMARY
                     } else {
@@ -938,6 +1176,22 @@ pub fn view_select_target_device<'a>(
             .spacing(15) // Increased spacing to accommodate larger icon
             .align_y(Alignment::Center);
 
+            // Warn about worn or failing media before anything is written to it
+            let device_header = match device.health_badge() {
+                Some(badge) => device_header.push(badge),
+                None => device_header,
+            };
+
+            let device_header = match device.read_only_badge() {
+                Some(badge) => device_header.push(badge),
+                None => device_header,
+            };
+
+            let device_header = match device.golem_badge() {
+                Some(badge) => device_header.push(badge),
+                None => device_header,
+            };
+
             // Device details with better formatting
             let device_details = column![
                 row![
@@ -969,6 +1223,40 @@ pub fn view_select_target_device<'a>(
             ]
             .spacing(4);
 
+            let device_details = match speed_test.filter(|test| test.path == device.path) {
+                Some(test) => device_details.extend(view_speed_test(&test.status, is_selected)),
+                None => device_details,
+            };
+            let device_details = match capacity_test.filter(|test| test.path == device.path) {
+                Some(test) => device_details.extend(view_capacity_test(test, device, is_selected)),
+                None => device_details,
+            };
+            let device_details = match nickname_edit
+                .filter(|edit| device.assignment_info().serial == Some(edit.serial.as_str()))
+            {
+                Some(edit) => device_details.push(
+                    row![
+                        text_input("Nickname, e.g. Bench slot 3", &edit.nickname)
+                            .on_input(FlashMessage::SetNicknameText)
+                            .on_submit(FlashMessage::SaveDeviceNickname)
+                            .padding(6)
+                            .size(14)
+                            .width(Length::Fill),
+                        button(text("Save").size(14))
+                            .on_press(FlashMessage::SaveDeviceNickname)
+                            .padding([4, 10])
+                            .style(button::primary),
+                        button(text("Cancel").size(14))
+                            .on_press(FlashMessage::CancelNicknameEdit)
+                            .padding([4, 10])
+                            .style(button::secondary),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                ),
+                None => device_details,
+            };
+
             let device_info = column![device_header, device_details]
                 .spacing(8)
                 .width(Length::Fill);
@@ -990,8 +1278,34 @@ pub fn view_select_target_device<'a>(
                 button::primary
             });
 
+            // Short enough to run on a card before committing to a long flash
+            let speed_button = button(
+                row![icons::speed(), text("Test speed")]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press_maybe(
+                (!speed_testing && !testing(&device.path))
+                    .then_some(FlashMessage::TestDeviceSpeed(i)),
+            )
+            .padding(10)
+            .style(button::secondary);
+
+            // Erases the device, so it asks for confirmation on the card first
+            let capacity_button = button(
+                row![icons::sd_card(), text("Test capacity")]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press_maybe(
+                (!capacity_testing && !testing(&device.path))
+                    .then_some(FlashMessage::TestDeviceCapacity(i)),
+            )
+            .padding(10)
+            .style(button::secondary);
+
             container(
-                row![device_info, select_button]
+                row![device_info, speed_button, capacity_button, select_button]
                     .spacing(20)
                     .padding(15)
                     .width(Length::Fill)
@@ -1024,8 +1338,27 @@ pub fn view_select_target_device<'a>(
     .padding(12)
     .style(style::navigation_back_button);
 
-    // Only enable the next button if a device is selected
-    let next_button = if selected_device.is_some() {
+    // Only enable the next button if a device is selected and the image is analyzed
+    let next_button = if local_analysis.is_some() {
+        button(
+            row![text("Analyzing the image..."), icons::navigate_next()]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .padding(12)
+        .style(button::secondary)
+    } else if selected_device
+        .and_then(|i| storage_devices.get(i))
+        .is_some_and(|device| testing(&device.path))
+    {
+        button(
+            row![text("Testing the device..."), icons::navigate_next()]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .padding(12)
+        .style(button::secondary)
+    } else if selected_device.is_some() {
         button(
             row![text("Configure Settings"), icons::navigate_next()]
                 .spacing(5)
@@ -1049,11 +1382,80 @@ pub fn view_select_target_device<'a>(
         .width(Length::Fill)
         .align_y(Alignment::Center);
 
-    let content = column![title, warning, device_list, spacer, buttons]
+    let mut content = column![title, warning]
         .spacing(20)
         .padding(20)
         .width(Length::Fill);
 
+    if let Some(offer) = whole_disk_offer {
+        content = content.push(view_whole_disk_offer(offer));
+    }
+    if let Some(check) = source_check {
+        content = content.push(view_source_check(&check.status));
+    }
+    if let Some(analysis) = local_analysis {
+        content = content.push(
+            row![
+                icons::timer().color(Color::from_rgb(0.7, 0.7, 0.8)),
+                text(format!(
+                    "Analyzing the image file... {} read",
+                    crate::disk::layout::format_size(analysis.bytes_read)
+                ))
+                .size(14)
+                .color(Color::from_rgb(0.7, 0.7, 0.8)),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    // Make it obvious when the selected device is an existing Golem node
+    if let Some(summary) = selected_device
+        .and_then(|i| storage_devices.get(i))
+        .and_then(|device| device.golem_summary())
+    {
+        content = content.push(
+            row![
+                icons::warning_amber().color(Color::from_rgb(0.95, 0.7, 0.3)),
+                text(format!(
+                    "The selected device is an existing {}. Flashing will replace its image and configuration.",
+                    summary
+                ))
+                .size(14)
+                .color(Color::from_rgb(0.95, 0.7, 0.3)),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    content = content.push(device_list);
+
+    // Larger deployments track their devices in a spreadsheet or an inventory system
+    if !storage_devices.is_empty() {
+        let export_button = |label: &'static str, format: InventoryFormat| {
+            button(
+                row![icons::file_download(), text(label).size(14)]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press(FlashMessage::ExportDeviceInventory(format))
+            .padding(8)
+            .style(button::secondary)
+        };
+        content = content.push(
+            row![
+                text("Device inventory:").size(14),
+                export_button("Export CSV", InventoryFormat::Csv),
+                export_button("Export JSON", InventoryFormat::Json),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    let content = content.push(spacer).push(buttons);
+
     container(content)
         .width(Length::Fill)
         .height(Length::Fill)
@@ -1061,134 +1463,722 @@ pub fn view_select_target_device<'a>(
         .into()
 }
 
-pub fn view_writing_process(progress: f32, title: &'static str) -> Element<'static, FlashMessage> {
-    // Page header with a more welcoming title with improved contrast
-    let header =
-        container(
-            text(title)
-                .size(28)
-                .style(|_theme: &iced::Theme| iced::widget::text::Style {
-                    color: Some(iced::Color::WHITE), // Full white for maximum contrast
-                    ..iced::widget::text::Style::default()
-                }),
-        )
-        .width(Length::Fill)
-        .padding(15)
-        .style(|theme: &iced::Theme| {
-            let palette = theme.extended_palette();
-            container::Style {
-                background: Some(crate::style::PRIMARY.into()),
-                border: iced::Border {
-                    width: 1.0,
-                    radius: 5.0.into(),
-                    color: palette.primary.strong.color,
-                },
-                ..container::Style::default()
+/// Lines of a device card telling the speed measured, or why it couldn't be
+fn view_speed_test<'a>(
+    status: &SpeedTestStatus,
+    is_selected: bool,
+) -> Vec<Element<'a, FlashMessage>> {
+    let muted = muted_card_color(is_selected);
+    match status {
+        SpeedTestStatus::Running => vec![card_line(
+            icons::timer(),
+            "Testing the speed, the tested area is put back afterwards...".to_string(),
+            muted,
+        )],
+        SpeedTestStatus::Done(result) => {
+            let mut lines = vec![card_line(
+                icons::speed(),
+                format!(
+                    "Write {:.1} MB/s, read {:.1} MB/s",
+                    result.write_bytes_per_second / 1e6,
+                    result.read_bytes_per_second / 1e6
+                ),
+                muted,
+            )];
+            if let Some(warning) = result.warning() {
+                lines.push(card_line(
+                    icons::warning_amber(),
+                    warning,
+                    Color::from_rgb(0.95, 0.7, 0.3),
+                ));
             }
-        });
-
-    // Create an icon to represent the writing process
-    let writing_icon = svg::Svg::new(svg::Handle::from_memory(LOGO_SVG))
-        .width(80)
-        .height(80);
-
-    // Calculate more precise progress information
-    let progress_percentage = (progress * 100.0) as i32;
-
-    // Calculate approximate megabytes processed (assuming 16GB image size)
-    // Adjust this value based on your actual image size
-    const TOTAL_MB: u32 = 16 * 1024; // 16GB in MB
-
-    // Progress now represents both read and written operations combined
-    // so we calculate IO processed as a percentage of total work (read+write)
-    let mb_processed = (progress * TOTAL_MB as f32) as u32;
-    let mb_total = TOTAL_MB;
+            lines
+        }
+        SpeedTestStatus::Failed(error) => vec![card_line(
+            icons::error(),
+            format!("Speed test failed: {}", error),
+            Color::from_rgb(0.95, 0.4, 0.4),
+        )],
+    }
+}
 
-    // Create a nice styled progress bar with a pulse animation for low progress
-    // This gives better feedback when progress seems stalled
-    let progress_value = if progress < 0.02 {
-        progress_bar(0.0..=1.0, progress).style(progress_bar::primary)
-    } else {
-        progress_bar(0.0..=1.0, progress).style(progress_bar::secondary)
+/// Lines of a device card asking to erase it for the capacity test, or telling its outcome
+fn view_capacity_test<'a>(
+    test: &'a CapacityTest,
+    device: &StorageDevice,
+    is_selected: bool,
+) -> Vec<Element<'a, FlashMessage>> {
+    let cancel_button = || {
+        button(text("Cancel").size(14))
+            .on_press(FlashMessage::CancelCapacityTest)
+            .padding([4, 10])
+            .style(button::secondary)
     };
+    match &test.status {
+        CapacityTestStatus::Confirming => {
+            // Disks that need their name typed can't be erased with a stray click
+            let named = test
+                .typed_name
+                .as_deref()
+                .is_none_or(|typed| device.is_named_by(typed));
+            let mut lines = vec![card_line(
+                icons::warning_amber(),
+                "The capacity test erases everything on the device and takes a few minutes"
+                    .to_string(),
+                Color::from_rgb(0.95, 0.7, 0.3),
+            )];
+            if let Some(typed) = &test.typed_name {
+                lines.push(
+                    text_input(
+                        &format!("Type {} or {} to confirm", device.name, device.size),
+                        typed,
+                    )
+                    .on_input(FlashMessage::SetCapacityTestTypedName)
+                    .on_submit_maybe(named.then_some(FlashMessage::ConfirmCapacityTest))
+                    .padding(6)
+                    .size(14)
+                    .into(),
+                );
+            }
+            lines.push(
+                row![
+                    button(text("Erase and test").size(14))
+                        .on_press_maybe(named.then_some(FlashMessage::ConfirmCapacityTest))
+                        .padding([4, 10])
+                        .style(button::danger),
+                    cancel_button(),
+                ]
+                .spacing(10)
+                .into(),
+            );
+            lines
+        }
+        CapacityTestStatus::Running(progress) => vec![
+            row![
+                card_line(
+                    icons::timer(),
+                    format!("Testing the capacity... {:.0}%", progress * 100.0),
+                    muted_card_color(is_selected),
+                ),
+                cancel_button(),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+            .into(),
+        ],
+        CapacityTestStatus::Done(report) if report.is_genuine() => vec![card_line(
+            icons::verified(),
+            report.summary(),
+            Color::from_rgb(0.4, 0.8, 0.4),
+        )],
+        CapacityTestStatus::Done(report) => vec![card_line(
+            icons::error(),
+            report.summary(),
+            Color::from_rgb(0.95, 0.4, 0.4),
+        )],
+        CapacityTestStatus::Failed(error) => vec![card_line(
+            icons::error(),
+            format!("Capacity test failed: {}", error),
+            Color::from_rgb(0.95, 0.4, 0.4),
+        )],
+    }
+}
 
-    // Display progress percentage with larger text and MB processed
-    let progress_text = row![
-        text(format!("{}%", progress_percentage)).size(28),
-        text(format!("({} MB / {} MB)", mb_processed, mb_total)).size(16)
-    ]
-    .spacing(10)
-    .align_y(Alignment::Center);
-
-    // Simple progress description
-    let step_text = "Writing Image Data";
-    let step_description = "Copying image data to device...";
-
-    // Create a simple progress indicator
-    let step_header = text(step_text).size(18).style(text::primary);
-    let step_detail = text(step_description).size(14);
-
-    // Estimated time remaining calculation (simple approximation)
-    // Assume a complete write takes about 10 minutes (600 seconds)
-    let estimated_seconds_left = if progress > 0.05 {
-        ((1.0 - progress) * 600.0) as i32
+/// Color of secondary text on a device card
+fn muted_card_color(is_selected: bool) -> Color {
+    if is_selected {
+        Color::from_rgb(0.3, 0.3, 0.3)
     } else {
-        // Don't show estimate when just starting
-        0
-    };
+        Color::from_rgb(0.7, 0.7, 0.8)
+    }
+}
 
-    let time_remaining = if progress > 0.05 && progress < 0.98 {
-        if estimated_seconds_left > 60 {
-            let minutes = estimated_seconds_left / 60;
-            let seconds = estimated_seconds_left % 60;
-            text(format!(
-                "Estimated time remaining: {} min {} sec",
-                minutes, seconds
-            ))
-            .size(12)
-        } else {
-            text(format!(
-                "Estimated time remaining: {} seconds",
-                estimated_seconds_left
-            ))
-            .size(12)
-        }
-    } else if progress >= 0.98 {
-        text("Finishing up, almost done...").size(12)
-    } else {
-        text("Calculating estimated time remaining...").size(12)
+/// A line of a device card telling the state of a test
+fn card_line<'a>(
+    icon: iced::widget::Text<'static>,
+    message: String,
+    color: Color,
+) -> Element<'a, FlashMessage> {
+    row![icon.color(color), text(message).size(14).color(color)]
+        .spacing(8)
+        .align_y(Alignment::Center)
+        .into()
+}
+
+/// Progress or outcome of the image file's contents check
+fn view_source_check(status: &SourceCheckStatus) -> Element<'_, FlashMessage> {
+    let (icon, message, color) = match status {
+        SourceCheckStatus::Checking(progress) => (
+            icons::timer(),
+            format!("Checking the image file... {:.0}%", progress * 100.0),
+            Color::from_rgb(0.7, 0.7, 0.8),
+        ),
+        SourceCheckStatus::Valid => (
+            icons::check_circle(),
+            "The image file is intact".to_string(),
+            Color::from_rgb(0.4, 0.8, 0.4),
+        ),
+        SourceCheckStatus::Corrupt(error) => (
+            icons::error(),
+            format!("{}. Download the image again.", error),
+            Color::from_rgb(0.95, 0.4, 0.4),
+        ),
     };
+    row![icon.color(color), text(message).size(14).color(color)]
+        .spacing(8)
+        .align_y(Alignment::Center)
+        .into()
+}
 
-    // Information container with improved visual hierarchy and spacing
-    let info_container = container(
-        row![
-            writing_icon,
-            column![
-                text("Installing Golem GPU OS").size(20),
-                row![progress_text],
-                progress_value,
-                row![step_header.width(Length::Fill), time_remaining],
-                step_detail,
+/// The requested device isn't a whole disk, with a button selecting the disk holding it
+fn view_whole_disk_offer(offer: &WholeDiskOffer) -> Element<'_, FlashMessage> {
+    let warning_color = Color::from_rgb(0.95, 0.7, 0.3);
+    container(
+        column![
+            row![
+                icons::warning_amber().color(warning_color),
+                text(&offer.reason).size(14).color(warning_color),
             ]
-            .spacing(5)
-            .width(Length::Fill)
+            .spacing(8)
+            .align_y(Alignment::Center),
+            row![
+                button(text(format!("Use {}", offer.disk)).size(14))
+                    .on_press(FlashMessage::UseWholeDisk)
+                    .padding([6, 12])
+                    .style(button::primary),
+                button(text("Pick Another Device").size(14))
+                    .on_press(FlashMessage::DismissWholeDisk)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ]
+            .spacing(10),
         ]
-        .spacing(15)
-        .align_y(Alignment::Center),
+        .spacing(10),
     )
-    .width(Length::Fill)
     .padding(15)
-    .style(|theme: &Theme| {
-        let palette = theme.extended_palette();
-
-        container::Style::default()
-            .background(palette.background.weak.color)
-            .border(Border {
-                radius: 8.0.into(),
-                width: 1.0,
-                color: palette.primary.base.color,
-            })
-    });
+    .width(Length::Fill)
+    .style(crate::style::bordered_box)
+    .into()
+}
+
+/// Warning that the image list could not be verified as signed by Golem
+fn view_manifest_warning(warning: &str) -> Element<'static, FlashMessage> {
+    row![
+        icons::warning_amber().color(Color::from_rgb(0.95, 0.7, 0.3)),
+        text(warning.to_string())
+            .size(14)
+            .color(Color::from_rgb(0.95, 0.7, 0.3)),
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center)
+    .into()
+}
+
+/// The image isn't built for the target's hardware, according to the manifest
+fn view_incompatible_image(issues: Vec<String>) -> Element<'static, FlashMessage> {
+    let warning_color = Color::from_rgb(0.95, 0.7, 0.3);
+    let mut content = column![
+        row![
+            icons::warning_amber().color(warning_color),
+            text("The image may not run on the target")
+                .size(14)
+                .color(warning_color),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center),
+    ]
+    .spacing(5);
+    for issue in issues {
+        content = content.push(text(issue).size(12).color(Color::from_rgb(0.7, 0.7, 0.7)));
+    }
+    content
+        .push(
+            text("The target can be changed in the settings.")
+                .size(12)
+                .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        )
+        .into()
+}
+
+/// How often the target was flashed before, warning once it should be replaced
+fn view_device_wear(wear: &DeviceWear) -> Element<'static, FlashMessage> {
+    if !wear.is_worn() {
+        return text(wear.describe())
+            .size(12)
+            .color(Color::from_rgb(0.7, 0.7, 0.7))
+            .into();
+    }
+    let warning_color = Color::from_rgb(0.95, 0.7, 0.3);
+    column![
+        row![
+            icons::warning_amber().color(warning_color),
+            text(wear.describe()).size(14).color(warning_color),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center),
+        text(
+            "Cards flashed this often are likely to fail soon. Consider replacing it before \
+             it goes into a node."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+    ]
+    .spacing(5)
+    .into()
+}
+
+/// Filesystems and programs that keep the target disk busy
+/// The target's read-only flag is set, with a button to clear it
+fn view_read_only_warning<'a>() -> Element<'a, FlashMessage> {
+    let warning_color = Color::from_rgb(0.9, 0.6, 0.0);
+    let content = column![
+        row![
+            icons::warning_amber().color(warning_color),
+            text("The disk is read-only").size(14).color(warning_color)
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center),
+        text(
+            "Its read-only flag has to be cleared before it can be flashed. The flag is set \
+             again if you cancel.",
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        button(text("Clear Read-Only Flag"))
+            .on_press(FlashMessage::ClearReadOnly)
+            .padding([6, 12])
+            .style(button::secondary),
+    ]
+    .spacing(8);
+
+    container(content)
+        .padding(12)
+        .width(Length::Fill)
+        .style(crate::style::bordered_box)
+        .into()
+}
+
+fn view_device_usage<'a>(usage: &crate::disk::DeviceUsage) -> Element<'a, FlashMessage> {
+    let warning_color = Color::from_rgb(0.9, 0.6, 0.0);
+    let mut content = column![
+        row![
+            icons::warning_amber().color(warning_color),
+            text("The disk is in use").size(14).color(warning_color)
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center)
+    ]
+    .spacing(5);
+
+    if !usage.mount_points.is_empty() {
+        content = content.push(
+            text("Mounted at the following places, which are unmounted before writing:")
+                .size(12)
+                .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        );
+        for mount_point in &usage.mount_points {
+            content = content.push(text(format!("• {}", mount_point)).size(12));
+        }
+    }
+    if !usage.processes.is_empty() {
+        content = content.push(
+            text("Close these programs first, or the disk can't be locked for writing:")
+                .size(12)
+                .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        );
+        for process in &usage.processes {
+            content = content.push(text(format!("• {}", process)).size(12));
+        }
+    } else if !usage.mount_points.is_empty() {
+        content = content.push(
+            text(
+                "Close files and folders of the disk that are open in other programs, or \
+                 unmounting fails.",
+            )
+            .size(12)
+            .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        );
+    }
+
+    container(content)
+        .padding(12)
+        .width(Length::Fill)
+        .style(crate::style::bordered_box)
+        .into()
+}
+
+/// Confirmation dialog listing what is currently on the target disk before it is erased
+pub fn view_confirm_write<'a>(
+    device: Option<&'a StorageDevice>,
+    layout: Option<&'a Result<crate::disk::DiskLayout, String>>,
+    usage: Option<&'a crate::disk::DeviceUsage>,
+    wear: Option<&'a DeviceWear>,
+    read_only: bool,
+    manifest_warning: Option<&str>,
+    untrusted_image: bool,
+    preserve_config: bool,
+    can_queue: bool,
+    typed_name: Option<&'a str>,
+    incompatibilities: Vec<String>,
+) -> Element<'a, FlashMessage> {
+    use crate::disk::layout::format_size;
+
+    let device_line = match device {
+        Some(device) => format!(
+            "{} ({}, {})",
+            device.display_name(),
+            device.path,
+            device.size
+        ),
+        None => "Unknown device".to_string(),
+    };
+
+    let layout_view: Element<'a, FlashMessage> = match layout {
+        None => text("Reading current partition table...")
+            .size(14)
+            .color(Color::from_rgb(0.7, 0.7, 0.7))
+            .into(),
+        Some(Err(error)) => column![
+            row![
+                icons::warning_amber(),
+                text("Could not read the current layout").size(14)
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+            text(error).size(12).color(Color::from_rgb(0.7, 0.7, 0.7)),
+        ]
+        .spacing(5)
+        .into(),
+        Some(Ok(layout)) if layout.partitions.is_empty() => {
+            let summary = if layout.is_blank() {
+                "No partitions or filesystems found - the disk appears to be empty".to_string()
+            } else {
+                format!(
+                    "No partition table, whole disk formatted as {}",
+                    layout
+                        .filesystem
+                        .as_deref()
+                        .unwrap_or("an unknown filesystem")
+                )
+            };
+            text(summary)
+                .size(14)
+                .color(Color::from_rgb(0.8, 0.8, 0.8))
+                .into()
+        }
+        Some(Ok(layout)) => {
+            let rows = column(layout.partitions.iter().map(|partition| {
+                let details = match &partition.filesystem {
+                    Some(fs) => format!("{} - {}", partition.type_name, fs),
+                    None => partition.type_name.clone(),
+                };
+                row![
+                    text(format!("#{}", partition.number))
+                        .size(13)
+                        .width(Length::Fixed(30.0)),
+                    column![
+                        text(partition.display_name()).size(14),
+                        text(details).size(12).color(Color::from_rgb(0.7, 0.7, 0.7)),
+                    ]
+                    .spacing(2)
+                    .width(Length::Fill),
+                    text(format_size(partition.size)).size(13),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+                .into()
+            }))
+            .spacing(8);
+
+            let mut content = column![
+                text(format!(
+                    "{} partition table with {} partition(s):",
+                    layout.scheme,
+                    layout.partitions.len()
+                ))
+                .size(14)
+                .color(Color::from_rgb(0.8, 0.8, 0.8)),
+                scrollable(rows).height(Length::Shrink),
+            ]
+            .spacing(10);
+
+            if layout.is_golem_image() {
+                content = content.push(
+                    text("This disk already contains a Golem image.")
+                        .size(12)
+                        .color(Color::from_rgb(0.6, 0.8, 0.6)),
+                );
+                content = content.push(
+                    column![
+                        checkbox("Keep this device's current configuration", preserve_config)
+                            .on_toggle(FlashMessage::TogglePreserveConfig)
+                            .size(16),
+                        text("Its configuration partition is backed up before erasing either way. When kept, the settings from the previous step are not used.")
+                            .size(12)
+                            .color(Color::from_rgb(0.6, 0.6, 0.6)),
+                    ]
+                    .spacing(5),
+                );
+            }
+
+            content.into()
+        }
+    };
+
+    // Disks that need their name typed can't be erased with a stray click
+    let named =
+        typed_name.is_none_or(|typed| device.is_some_and(|device| device.is_named_by(typed)));
+    let can_erase = layout.is_some() && !read_only && !untrusted_image && named;
+
+    // Only allow confirming once we know (or failed to learn) what is on the disk
+    let confirm_button = button(
+        row![icons::delete(), "Erase and Flash"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press_maybe(can_erase.then_some(FlashMessage::WriteImage))
+    .padding(12)
+    .style(button::danger);
+
+    let mut dialog_content = column![
+        row![icons::warning(), text("Erase Target Disk?").size(20)]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        text(device_line)
+            .size(14)
+            .color(Color::from_rgb(0.8, 0.8, 0.8)),
+        container(layout_view)
+            .padding(15)
+            .width(Length::Fill)
+            .style(crate::style::bordered_box),
+        text("Everything listed above will be permanently erased.")
+            .size(12)
+            .color(Color::from_rgb(1.0, 0.4, 0.4)),
+    ]
+    .spacing(15)
+    .width(Length::Fill)
+    .max_width(520);
+
+    if read_only {
+        dialog_content = dialog_content.push(view_read_only_warning());
+    }
+
+    if let Some(usage) = usage.filter(|usage| !usage.is_empty()) {
+        dialog_content = dialog_content.push(view_device_usage(usage));
+    }
+
+    if let Some(warning) = manifest_warning {
+        dialog_content = dialog_content.push(view_manifest_warning(warning));
+    }
+
+    if let Some(wear) = wear {
+        dialog_content = dialog_content.push(view_device_wear(wear));
+    }
+
+    if !incompatibilities.is_empty() {
+        dialog_content = dialog_content.push(view_incompatible_image(incompatibilities));
+    }
+
+    if let (Some(typed), Some(device)) = (typed_name, device) {
+        dialog_content = dialog_content.push(
+            column![
+                text(format!(
+                    "Type the device's name ({}) or size ({}) to confirm:",
+                    device.name, device.size
+                ))
+                .size(14),
+                text_input(&device.name, typed)
+                    .on_input(FlashMessage::SetTypedDeviceName)
+                    .on_submit_maybe(can_erase.then_some(FlashMessage::WriteImage))
+                    .padding(8)
+                    .style(crate::style::default_text_input),
+            ]
+            .spacing(8),
+        );
+    }
+
+    let dialog_content = dialog_content.push(
+        container(
+            row![
+                button(text("Cancel"))
+                    .on_press(FlashMessage::BackToConfigureSettings)
+                    .padding(12)
+                    .style(button::secondary),
+                button(
+                    row![icons::add_to_queue(), "Add to Queue"]
+                        .spacing(5)
+                        .align_y(Alignment::Center)
+                )
+                .on_press_maybe(can_queue.then_some(FlashMessage::AddToQueue))
+                .padding(12)
+                .style(button::secondary),
+                button(
+                    row![icons::content_copy(), "Duplicate"]
+                        .spacing(5)
+                        .align_y(Alignment::Center)
+                )
+                .on_press_maybe((can_queue && can_erase).then_some(FlashMessage::StartKiosk))
+                .padding(12)
+                .style(button::secondary),
+                confirm_button,
+            ]
+            .spacing(15),
+        )
+        .width(Length::Fill)
+        .align_x(Alignment::Center),
+    );
+
+    // Center the dialog on screen
+    container(
+        container(dialog_content)
+            .style(style::confirmation_dialog)
+            .padding(25)
+            .width(Length::Shrink)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill),
+    )
+    .style(style::modal_overlay)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .into()
+}
+
+pub fn view_writing_process(
+    phase: &FlashPhase,
+    show_details: bool,
+) -> Element<'static, FlashMessage> {
+    let (title, step_text) = match phase {
+        FlashPhase::Preparing | FlashPhase::Clearing { .. } => {
+            ("Writing Image", "Preparing Device")
+        }
+        FlashPhase::Writing { .. } => ("Writing Image", "Writing Image Data"),
+        FlashPhase::Verifying { .. } => ("Verifying Image", "Checking Written Data"),
+        FlashPhase::FixingGpt => ("Finishing Up", "Fixing Partition Table"),
+        FlashPhase::WritingConfig => ("Finishing Up", "Writing Configuration"),
+        FlashPhase::Done | FlashPhase::Unverified => ("Finishing Up", "Done"),
+    };
+    // Name the partition being written when the manifest describes them
+    let step_text = match phase {
+        FlashPhase::Writing {
+            partition: Some(partition),
+            ..
+        } => partition.to_string(),
+        _ => step_text.to_string(),
+    };
+
+    // Page header with a more welcoming title with improved contrast
+    let header =
+        container(
+            text(title)
+                .size(28)
+                .style(|_theme: &iced::Theme| iced::widget::text::Style {
+                    color: Some(iced::Color::WHITE), // Full white for maximum contrast
+                    ..iced::widget::text::Style::default()
+                }),
+        )
+        .width(Length::Fill)
+        .padding(15)
+        .style(|theme: &iced::Theme| {
+            let palette = theme.extended_palette();
+            container::Style {
+                background: Some(crate::style::PRIMARY.into()),
+                border: iced::Border {
+                    width: 1.0,
+                    radius: 5.0.into(),
+                    color: palette.primary.strong.color,
+                },
+                ..container::Style::default()
+            }
+        });
+
+    // Create an icon to represent the writing process
+    let writing_icon = svg::Svg::new(svg::Handle::from_memory(LOGO_SVG))
+        .width(80)
+        .height(80);
+
+    // The short steps after verification can't be measured and count as complete
+    let progress = phase.fraction().unwrap_or(match phase {
+        FlashPhase::Preparing | FlashPhase::Clearing { .. } | FlashPhase::Writing { .. } => 0.0,
+        _ => 1.0,
+    });
+    let progress_percentage = (progress * 100.0) as i32;
+
+    // Create a nice styled progress bar with a pulse animation for low progress
+    // This gives better feedback when progress seems stalled
+    let progress_value = if progress < 0.02 {
+        progress_bar(0.0..=1.0, progress).style(progress_bar::primary)
+    } else {
+        progress_bar(0.0..=1.0, progress).style(progress_bar::secondary)
+    };
+
+    let progress_text = text(format!("{}%", progress_percentage)).size(28);
+
+    // Create a simple progress indicator
+    let step_header = text(step_text).size(18).style(text::primary);
+    let step_detail = text(phase.description()).size(14);
+
+    // Time remaining from the measured speed of the current phase
+    let seconds_left = match phase {
+        FlashPhase::Writing {
+            bytes,
+            total: Some(total),
+            rate,
+            ..
+        }
+        | FlashPhase::Verifying { bytes, total, rate }
+            if *rate > 0 && progress > 0.05 =>
+        {
+            Some(total.saturating_sub(*bytes) / rate)
+        }
+        _ => None,
+    };
+
+    let time_remaining = match seconds_left {
+        _ if progress >= 0.98 => text("Finishing up, almost done...").size(12),
+        Some(seconds) if seconds > 60 => text(format!(
+            "Estimated time remaining: {} min {} sec",
+            seconds / 60,
+            seconds % 60
+        ))
+        .size(12),
+        Some(seconds) => text(format!("Estimated time remaining: {} seconds", seconds)).size(12),
+        None => text("Calculating estimated time remaining...").size(12),
+    };
+
+    let mut progress_column = column![
+        text("Installing Golem GPU OS").size(20),
+        progress_text,
+        progress_value,
+        row![step_header.width(Length::Fill), time_remaining],
+        step_detail,
+    ]
+    .spacing(5)
+    .width(Length::Fill);
+    if let FlashPhase::Writing { latency, .. } = phase {
+        progress_column = progress_column.push(view_write_details(latency, show_details));
+    }
+
+    // Information container with improved visual hierarchy and spacing
+    let info_container = container(
+        row![writing_icon, progress_column]
+            .spacing(15)
+            .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(|theme: &Theme| {
+        let palette = theme.extended_palette();
+
+        container::Style::default()
+            .background(palette.background.weak.color)
+            .border(Border {
+                radius: 8.0.into(),
+                width: 1.0,
+                color: palette.primary.base.color,
+            })
+    });
 
     // Add a spacer to push the button to the bottom
     let spacer = Container::new(Column::new())
@@ -1214,9 +2204,28 @@ pub fn view_writing_process(progress: f32, title: &'static str) -> Element<'stat
     .width(180)
     .style(style::cancel_button_danger);
 
+    // The write is already complete while verifying, so only the read-back can be skipped
+    let mut buttons = row![cancel_button].spacing(10);
+    if let FlashPhase::Verifying { .. } = phase {
+        buttons = buttons.push(
+            button(
+                row![icons::navigate_next(), text("Skip Verification")]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press(FlashMessage::SkipVerification)
+            .padding(12)
+            .width(180)
+            .style(style::cancel_button_secondary),
+        );
+    }
+    if crate::ui::tray::is_supported() {
+        buttons = buttons.push(view_hide_to_tray_button());
+    }
+
     // Button container with warning
     let button_container = container(
-        column![warning_text, cancel_button]
+        column![warning_text, buttons]
             .spacing(10)
             .align_x(Alignment::Center),
     )
@@ -1245,35 +2254,311 @@ pub fn view_writing_process(progress: f32, title: &'static str) -> Element<'stat
         .into()
 }
 
+/// Expandable chunk write times, to tell a slow card from a bad reader
+fn view_write_details(latency: &WriteLatency, expanded: bool) -> Element<'static, FlashMessage> {
+    let (icon, label) = if expanded {
+        (icons::expand_less(), "Hide details")
+    } else {
+        (icons::expand_more(), "Show details")
+    };
+    let toggle = button(
+        row![icon, text(label).size(12)]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(FlashMessage::ToggleWriteDetails)
+    .padding(4)
+    .style(button::text);
+
+    if !expanded {
+        return toggle.into();
+    }
+    let ms = |duration: std::time::Duration| format!("{:.1} ms", duration.as_secs_f64() * 1000.0);
+    let details = match latency.average() {
+        Some(average) => column![
+            text(format!("Chunks written: {}", latency.chunks)).size(12),
+            text(format!(
+                "Chunk write time: min {} • avg {} • max {}",
+                ms(latency.min),
+                ms(average),
+                ms(latency.max)
+            ))
+            .size(12),
+            text("A few slow chunks point at the reader, all of them at the card")
+                .size(11)
+                .style(text::secondary),
+        ],
+        None => column![text("No chunks written yet").size(12)],
+    };
+    column![toggle, details.spacing(2).padding([0, 10])]
+        .spacing(2)
+        .into()
+}
+
+/// Button that hides the window to the system tray while the flash continues
+fn view_hide_to_tray_button() -> Element<'static, FlashMessage> {
+    button(
+        row![icons::expand_more(), text("Hide to Tray")]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(FlashMessage::HideToTray)
+    .padding(12)
+    .width(180)
+    .style(style::cancel_button_secondary)
+    .into()
+}
+
+/// Progress screen for removing existing partitions before the image is written
+pub fn view_clearing_partitions(progress: f32, message: &str) -> Element<'_, FlashMessage> {
+    let header = container(
+        text("Preparing Device")
+            .size(28)
+            .style(|_theme: &Theme| text::Style {
+                color: Some(iced::Color::WHITE),
+                ..text::Style::default()
+            }),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(|theme: &Theme| {
+        let palette = theme.extended_palette();
+        container::Style {
+            background: Some(crate::style::PRIMARY.into()),
+            border: Border {
+                width: 1.0,
+                radius: 5.0.into(),
+                color: palette.primary.strong.color,
+            },
+            ..container::Style::default()
+        }
+    });
+
+    let writing_icon = svg::Svg::new(svg::Handle::from_memory(LOGO_SVG))
+        .width(80)
+        .height(80);
+
+    let info_container = container(
+        row![
+            writing_icon,
+            column![
+                text("Removing existing partitions").size(20),
+                text(format!("{}%", (progress * 100.0) as i32)).size(28),
+                progress_bar(0.0..=1.0, progress).style(progress_bar::primary),
+                text(message).size(14),
+            ]
+            .spacing(5)
+            .width(Length::Fill)
+        ]
+        .spacing(15)
+        .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(|theme: &Theme| {
+        let palette = theme.extended_palette();
+
+        container::Style::default()
+            .background(palette.background.weak.color)
+            .border(Border {
+                radius: 8.0.into(),
+                width: 1.0,
+                color: palette.primary.base.color,
+            })
+    });
+
+    let cancel_button = button(
+        row![icons::cancel(), text("Cancel Installation")]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(FlashMessage::CancelWrite)
+    .padding(12)
+    .width(180)
+    .style(style::cancel_button_danger);
+
+    let content = column![
+        header,
+        container(column![
+            Container::new(Column::new()).height(15),
+            info_container,
+            Container::new(Column::new())
+                .height(Length::Fill)
+                .width(Length::Fill),
+            container(cancel_button)
+                .width(Length::Fill)
+                .align_x(Horizontal::Center)
+                .padding(10),
+        ])
+        .padding(15)
+        .width(Length::Fill)
+        .height(Length::Fill),
+    ]
+    .width(Length::Fill);
+
+    Container::new(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+pub fn view_streaming_image(
+    downloaded: u64,
+    download_size: Option<u64>,
+    written: u64,
+    latency: &WriteLatency,
+    show_details: bool,
+) -> Element<'static, FlashMessage> {
+    let header = container(
+        text("Streaming Image")
+            .size(28)
+            .style(|_theme: &Theme| text::Style {
+                color: Some(iced::Color::WHITE),
+                ..text::Style::default()
+            }),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(|theme: &Theme| {
+        let palette = theme.extended_palette();
+        container::Style {
+            background: Some(crate::style::PRIMARY.into()),
+            border: Border {
+                width: 1.0,
+                radius: 5.0.into(),
+                color: palette.primary.strong.color,
+            },
+            ..container::Style::default()
+        }
+    });
+
+    let writing_icon = svg::Svg::new(svg::Handle::from_memory(LOGO_SVG))
+        .width(80)
+        .height(80);
+
+    // Download and write advance together, so the download drives a single bar
+    let progress = match download_size {
+        Some(size) if size > 0 => (downloaded as f32 / size as f32).min(1.0),
+        _ => 0.0,
+    };
+    let megabytes = |bytes: u64| bytes / (1024 * 1024);
+    let percentage = match download_size {
+        Some(_) => format!("{}%", (progress * 100.0) as i32),
+        None => format!("{} MB", megabytes(downloaded)),
+    };
+
+    let info_container = container(
+        row![
+            writing_icon,
+            column![
+                text("Downloading and writing to the device").size(20),
+                text(percentage).size(28),
+                progress_bar(0.0..=1.0, progress).style(progress_bar::primary),
+                text(format!(
+                    "{} MB downloaded • {} MB written",
+                    megabytes(downloaded),
+                    megabytes(written)
+                ))
+                .size(14),
+                view_write_details(latency, show_details),
+            ]
+            .spacing(5)
+            .width(Length::Fill)
+        ]
+        .spacing(15)
+        .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(|theme: &Theme| {
+        let palette = theme.extended_palette();
+
+        container::Style::default()
+            .background(palette.background.weak.color)
+            .border(Border {
+                radius: 8.0.into(),
+                width: 1.0,
+                color: palette.primary.base.color,
+            })
+    });
+
+    let cancel_button = button(
+        row![icons::cancel(), text("Cancel Installation")]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(FlashMessage::CancelWrite)
+    .padding(12)
+    .width(180)
+    .style(style::cancel_button_danger);
+    let mut buttons = row![cancel_button].spacing(10);
+    if crate::ui::tray::is_supported() {
+        buttons = buttons.push(view_hide_to_tray_button());
+    }
+
+    let content = column![
+        header,
+        container(column![
+            Container::new(Column::new()).height(15),
+            info_container,
+            Container::new(Column::new())
+                .height(Length::Fill)
+                .width(Length::Fill),
+            container(buttons)
+                .width(Length::Fill)
+                .align_x(Horizontal::Center)
+                .padding(10),
+        ])
+        .padding(15)
+        .width(Length::Fill)
+        .height(Length::Fill),
+    ]
+    .width(Length::Fill);
+
+    Container::new(content)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
 pub fn view_flash_configure_settings<'a>(
     configuration: &'a crate::ui::configuration::ConfigurationState,
+    schema: &'a crate::disk::ConfigSchema,
     configuration_presets: &'a [crate::models::ConfigurationPreset],
     new_preset_name: &'a str,
     _show_preset_manager: bool,
     _preset_editor: Option<&'a crate::ui::preset_manager::PresetEditor>,
+    wide: bool,
 ) -> Element<'a, crate::ui::messages::Message> {
     // Use the shared configuration editor from the shared module
     crate::ui::configuration::view::view_configuration_editor(
         configuration,
+        schema,
         "Configure Settings",
         "Configure your Golem Network settings before flashing:",
         crate::ui::messages::Message::Flash(FlashMessage::BackToSelectTargetDevice),
         Some(crate::ui::messages::Message::Flash(
-            FlashMessage::WriteImage,
+            FlashMessage::ConfirmWrite,
         )),
         "Back to Device Selection",
         "Start Flashing",
         configuration_presets,
         new_preset_name,
         crate::ui::messages::Message::ManagePresets,
+        wide,
         |config_msg| crate::ui::messages::Message::Configuration(config_msg),
     )
 }
 
-pub fn view_flash_completion(
+pub fn view_flash_completion<'a>(
     success: bool,
-    error_message: Option<&str>,
-) -> Element<'_, FlashMessage> {
+    verified: bool,
+    error_message: Option<&'a str>,
+    config_backup: Option<&'a std::path::Path>,
+    has_report: bool,
+    report_path: Option<&'a std::path::Path>,
+    wipe_undo: Option<&'a WipeUndo>,
+) -> Element<'a, FlashMessage> {
     // Page header with success/error status with improved styling
     let header_text = if success {
         "Installation Successful"
@@ -1307,7 +2592,9 @@ pub fn view_flash_completion(
         icons::error().style(text::danger)
     };
 
-    let status_text = text(if success {
+    let status_text = text(if success && !verified {
+        "Written, Not Verified"
+    } else if success {
         "Operation Completed Successfully!"
     } else {
         "Operation Failed"
@@ -1445,6 +2732,105 @@ pub fn view_flash_completion(
         status_message,
     ];
 
+    if success && !verified {
+        info_column = info_column.push(
+            row![
+                icons::warning_amber().color(Color::from_rgb(0.95, 0.7, 0.3)),
+                text(
+                    "Verification was skipped, so the written data was not checked. \
+                    If the device doesn't boot, flash it again and let verification finish."
+                )
+                .size(14)
+                .color(Color::from_rgb(0.95, 0.7, 0.3)),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    // Tell where the device's previous configuration went, it can be restored from there
+    if let Some(path) = config_backup {
+        info_column = info_column.push(
+            row![
+                icons::save(),
+                text(format!(
+                    "The device's previous configuration was saved to {}",
+                    path.display()
+                ))
+                .size(14),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    // A misclicked write cancelled before the image went on can get its partition table back
+    match wipe_undo {
+        Some(WipeUndo::Offered(_)) => {
+            info_column = info_column.push(
+                row![
+                    icons::warning_amber().color(Color::from_rgb(0.95, 0.7, 0.3)),
+                    text(format!(
+                        "The device's partition table was already wiped. It can be restored for {} seconds.",
+                        crate::disk::wipe::UNDO_WINDOW.as_secs()
+                    ))
+                    .size(14),
+                    button(
+                        row![icons::undo(), text("Undo Wipe").size(14)]
+                            .spacing(5)
+                            .align_y(Alignment::Center),
+                    )
+                    .on_press(FlashMessage::UndoWipe)
+                    .padding(8)
+                    .style(button::primary),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+        Some(WipeUndo::Restoring) => {
+            info_column = info_column.push(text("Restoring the partition table...").size(14));
+        }
+        Some(WipeUndo::Restored) => {
+            info_column = info_column.push(
+                text("The device's previous partition table was restored")
+                    .size(14)
+                    .style(text::success),
+            );
+        }
+        None => {}
+    }
+
+    // Operators deploying commercially keep a report of every device they flash
+    if success && has_report {
+        let export_button = |label: &'static str, format: ReportFormat| {
+            button(
+                row![icons::file_download(), text(label).size(14)]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press(FlashMessage::ExportReport(format))
+            .padding(8)
+            .style(button::secondary)
+        };
+        info_column = info_column.push(
+            row![
+                text("Flash report:").size(14),
+                export_button("Export HTML", ReportFormat::Html),
+                export_button("Export PDF", ReportFormat::Pdf),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+        if let Some(path) = report_path {
+            info_column = info_column.push(
+                text(format!("Report saved to {}", path.display()))
+                    .size(14)
+                    .style(text::success),
+            );
+        }
+    }
+
     // Add error message if present
     if let Some(error_widget) = error_container {
         info_column = info_column.push(column![].height(15)); // Add spacer
@@ -123,11 +123,14 @@ pub fn check_circle() -> iced::widget::Text<'static> {
     icon('\u{E86C}')
 }
 
-#[allow(dead_code)]
 pub fn help() -> iced::widget::Text<'static> {
     icon('\u{E887}')
 }
 
+pub fn lock() -> iced::widget::Text<'static> {
+    icon('\u{E897}') // Material Icons lock
+}
+
 #[allow(dead_code)]
 pub fn warning_amber() -> iced::widget::Text<'static> {
     icon('\u{E002}')
@@ -202,3 +205,45 @@ pub fn file_download() -> iced::widget::Text<'static> {
 pub fn file_upload() -> iced::widget::Text<'static> {
     icon('\u{E2C6}') // Material Icons file_upload
 }
+
+// Flash queue icons
+pub fn queue() -> iced::widget::Text<'static> {
+    icon('\u{E03C}') // Material Icons queue
+}
+
+pub fn add_to_queue() -> iced::widget::Text<'static> {
+    icon('\u{E05C}') // Material Icons add_to_queue
+}
+
+// Duplicator mode icons
+pub fn content_copy() -> iced::widget::Text<'static> {
+    icon('\u{E14D}') // Material Icons content_copy
+}
+
+// Device rule icons
+pub fn add() -> iced::widget::Text<'static> {
+    icon('\u{E145}') // Material Icons add
+}
+
+pub fn block() -> iced::widget::Text<'static> {
+    icon('\u{E14B}') // Material Icons block
+}
+
+// Preset manager icons
+pub fn undo() -> iced::widget::Text<'static> {
+    icon('\u{E166}') // Material Icons undo
+}
+
+pub fn cloud() -> iced::widget::Text<'static> {
+    icon('\u{E2BD}') // Material Icons cloud
+}
+
+// Recently used image icons
+pub fn history() -> iced::widget::Text<'static> {
+    icon('\u{E889}') // Material Icons history
+}
+
+// Device speed test icons
+pub fn speed() -> iced::widget::Text<'static> {
+    icon('\u{E9E4}') // Material Icons speed
+}
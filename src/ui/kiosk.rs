@@ -0,0 +1,319 @@
+/// Duplicator mode: flashing every disk that is inserted
+///
+/// Started from the confirmation of a flash, it writes the confirmed image and preset to
+/// that disk and then to each removable disk that shows up in the device scan, cleaning,
+/// writing, verifying and ejecting it as a flash from the window does, so a laptop can run
+/// as a card duplicator. The outcome is shown large enough to be read from across the
+/// bench. Only disks an allow device rule lets through are taken, so a disk that happens to
+/// be plugged in is never erased.
+use crate::style;
+use crate::ui::device_selection::StorageDevice;
+use crate::ui::flash_workflow::{FlashMessage, FlashState, FlashWorkflowState};
+use crate::ui::icons;
+use crate::ui::messages::Message;
+use crate::utils::automation::StartFlash;
+use crate::utils::device_rules::{DeviceRule, RuleAction};
+use iced::widget::{button, column, container, progress_bar, row, text};
+use iced::{Alignment, Color, Element, Length};
+use std::collections::BTreeSet;
+
+/// The duplicator and the disks it has seen
+#[derive(Debug, Clone)]
+pub struct KioskState {
+    pub request: StartFlash, // Image and preset written; the device is that of each disk
+    pub status: KioskStatus,
+    pub succeeded: usize,
+    pub failed: usize,
+    present: BTreeSet<String>, // Disks of the last scan, flashed again only once re-inserted
+}
+
+/// What the duplicator is doing, or how the last disk went
+#[derive(Debug, Clone, PartialEq)]
+pub enum KioskStatus {
+    Flashing {
+        device: String,
+    },
+    Ejecting {
+        device: String,
+        verified: bool,
+    },
+    Succeeded {
+        device: String,
+        verified: bool,
+        eject_error: Option<String>, // The disk was written but has to be removed by hand
+    },
+    Failed {
+        device: String,
+        error: String,
+    },
+}
+
+impl KioskState {
+    /// Duplicate `request`, starting with its own device; the other disks of `devices` are
+    /// left alone until they are inserted again
+    pub fn new(request: StartFlash, devices: &[StorageDevice]) -> Self {
+        Self {
+            status: KioskStatus::Flashing {
+                device: request.device.clone(),
+            },
+            request,
+            succeeded: 0,
+            failed: 0,
+            present: devices.iter().map(|device| device.path.clone()).collect(),
+        }
+    }
+
+    /// Whether a disk is being flashed or ejected
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self.status,
+            KioskStatus::Flashing { .. } | KioskStatus::Ejecting { .. }
+        )
+    }
+
+    /// The disk being flashed, if one is
+    pub fn flashing(&self) -> Option<&str> {
+        match &self.status {
+            KioskStatus::Flashing { device } => Some(device),
+            _ => None,
+        }
+    }
+
+    /// Return the first removable disk of a scan that is new
+    ///
+    /// Only the returned disk is remembered, so the other new ones are returned by the next
+    /// scans; disks missing from the scan are forgotten and flashed again once re-inserted.
+    pub fn take_inserted(&mut self, devices: &[StorageDevice]) -> Option<StorageDevice> {
+        self.present
+            .retain(|path| devices.iter().any(|device| &device.path == path));
+        let inserted = devices
+            .iter()
+            .find(|device| {
+                !self.present.contains(&device.path)
+                    && (device.is_removable || device.is_card || device.is_usb)
+            })
+            .cloned()?;
+        self.present.insert(inserted.path.clone());
+        Some(inserted)
+    }
+
+    /// Flash `device` next
+    pub fn start(&mut self, device: &str) -> StartFlash {
+        self.status = KioskStatus::Flashing {
+            device: device.to_string(),
+        };
+        StartFlash {
+            device: device.to_string(),
+            ..self.request.clone()
+        }
+    }
+
+    /// The write of the disk being flashed has ended; returns the disk to eject
+    pub fn written(&mut self, result: Result<bool, String>) -> Option<String> {
+        let device = self.flashing()?.to_string();
+        match result {
+            Ok(verified) => {
+                self.status = KioskStatus::Ejecting {
+                    device: device.clone(),
+                    verified,
+                };
+                Some(device)
+            }
+            Err(error) => {
+                self.failed += 1;
+                self.status = KioskStatus::Failed { device, error };
+                None
+            }
+        }
+    }
+
+    /// The written disk `device` was ejected, or not
+    pub fn ejected(&mut self, device: &str, result: Result<(), String>) {
+        let KioskStatus::Ejecting {
+            device: ejecting,
+            verified,
+        } = &self.status
+        else {
+            return;
+        };
+        if ejecting != device {
+            return;
+        }
+        self.succeeded += 1;
+        self.status = KioskStatus::Succeeded {
+            device: device.to_string(),
+            verified: *verified,
+            eject_error: result.err(),
+        };
+    }
+}
+
+/// Why the duplicator can't run with `rules`, if it can't
+///
+/// Every inserted disk is erased without asking, so an allow rule has to say which disks
+/// may be, e.g. "removable disks up to 256 GB".
+pub fn check_rules(rules: &[DeviceRule]) -> Result<(), String> {
+    if rules
+        .iter()
+        .any(|rule| rule.action == RuleAction::Allow && rule.has_criteria())
+    {
+        Ok(())
+    } else {
+        Err(
+            "Duplicator mode erases every disk that is inserted. Add a device rule in the \
+             settings that only allows the disks to flash, e.g. removable disks up to 256 GB."
+                .to_string(),
+        )
+    }
+}
+
+/// The duplicator screen
+pub fn view_kiosk<'a>(
+    state: &'a KioskState,
+    flash: Option<&'a FlashState>,
+) -> Element<'a, Message> {
+    let request = &state.request;
+    let header = container(
+        column![
+            text("Duplicator Mode").size(28),
+            text(format!(
+                "{} {} • preset: {}",
+                request.channel.as_deref().unwrap_or_default(),
+                request.version.as_deref().unwrap_or("(latest)"),
+                request.preset.as_deref().unwrap_or("assigned or default")
+            ))
+            .size(16)
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(style::page_header);
+
+    let (status_icon, title, details, panel_style): (
+        _,
+        String,
+        String,
+        fn(&iced::Theme) -> container::Style,
+    ) = match &state.status {
+        KioskStatus::Flashing { device } => (
+            icons::downloading().color(Color::from_rgb(0.3, 0.6, 1.0)),
+            format!("Flashing {}", device),
+            "Don't remove the disk".to_string(),
+            style::bordered_box,
+        ),
+        KioskStatus::Ejecting { device, .. } => (
+            icons::downloading().color(Color::from_rgb(0.3, 0.6, 1.0)),
+            format!("Ejecting {}", device),
+            "Don't remove the disk".to_string(),
+            style::bordered_box,
+        ),
+        KioskStatus::Succeeded {
+            device,
+            verified,
+            eject_error,
+        } => (
+            icons::check_circle(),
+            format!("{} Done", device),
+            match (verified, eject_error) {
+                (true, None) => "Flashed and verified, remove the disk".to_string(),
+                (false, None) => "Flashed without verification, remove the disk".to_string(),
+                (_, Some(error)) => format!("Flashed, remove the disk when idle: {}", error),
+            },
+            style::kiosk_success_panel,
+        ),
+        KioskStatus::Failed { device, error } => (
+            icons::error(),
+            format!("{} Failed", device),
+            error.clone(),
+            style::kiosk_failure_panel,
+        ),
+    };
+
+    let mut panel = column![
+        status_icon.size(96),
+        text(title).size(40),
+        text(details).size(18),
+    ]
+    .spacing(15)
+    .align_x(Alignment::Center)
+    .max_width(640);
+    if let Some((fraction, description)) = state
+        .flashing()
+        .and(flash)
+        .and_then(|flash| progress(&flash.workflow_state))
+    {
+        panel = panel.push(progress_bar(0.0..=1.0, fraction).style(progress_bar::primary));
+        panel = panel.push(text(description).size(14));
+    }
+
+    let mut counters = format!("{} flashed • {} failed", state.succeeded, state.failed);
+    if !state.is_running() {
+        counters.push_str(" • insert the next disk to flash it");
+    }
+    let counters = text(counters)
+        .size(16)
+        .color(Color::from_rgb(0.7, 0.7, 0.7));
+
+    let action = if state.flashing().is_some() {
+        button(
+            row![icons::cancel(), "Cancel Write"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press(Message::Flash(FlashMessage::CancelWrite))
+        .padding(12)
+        .style(button::danger)
+    } else {
+        button(
+            row![icons::navigate_before(), "Stop Duplicating"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press_maybe((!state.is_running()).then_some(Message::StopKiosk))
+        .padding(12)
+        .style(style::navigation_back_button)
+    };
+
+    column![
+        header,
+        container(panel)
+            .padding(40)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(panel_style),
+        row![
+            counters,
+            container(row![]).width(Length::Fill),
+            button(
+                row![icons::queue(), "Monitor"]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press(Message::OpenFlashMonitor)
+            .padding(12)
+            .style(button::secondary),
+            action
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+    ]
+    .spacing(20)
+    .padding(20)
+    .into()
+}
+
+/// How far the write of the disk is, once it has started
+fn progress(state: &FlashWorkflowState) -> Option<(f32, String)> {
+    match state {
+        FlashWorkflowState::ClearingPartitions { progress, message } => {
+            Some((*progress, message.clone()))
+        }
+        FlashWorkflowState::Flashing(phase) => {
+            Some((phase.fraction().unwrap_or(0.0), phase.description()))
+        }
+        _ => None,
+    }
+}
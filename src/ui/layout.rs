@@ -0,0 +1,27 @@
+/// Layout decisions that depend on the size of the window
+///
+/// Views get the current size of the window so they can use the room on large screens and
+/// fall back to a single column with less decoration on small ones. Sizes are in the units
+/// the views are laid out in, i.e. after the zoom of the user interface is applied.
+use iced::Size;
+
+/// Width from which forms show two columns side by side
+const WIDE_WIDTH: f32 = 960.0;
+
+/// Height below which screens drop their decorative spacing
+const COMPACT_HEIGHT: f32 = 640.0;
+
+/// Whether there is room for two columns of form fields
+pub fn is_wide(window_size: Size) -> bool {
+    window_size.width >= WIDE_WIDTH
+}
+
+/// Whether the window is too low for the full-size layout, e.g. on a netbook
+pub fn is_compact(window_size: Size) -> bool {
+    window_size.height < COMPACT_HEIGHT
+}
+
+/// Number of columns at least `min_width` wide that fit into the window, from 1 to `max`
+pub fn columns(window_size: Size, min_width: f32, max: usize) -> usize {
+    ((window_size.width / min_width).floor() as usize).clamp(1, max.max(1))
+}
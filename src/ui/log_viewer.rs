@@ -0,0 +1,23 @@
+pub mod handler;
+pub mod messages;
+pub mod state;
+pub mod ui;
+
+pub use handler::*;
+pub use messages::*;
+pub use state::*;
+pub use ui::*;
+
+use iced::Element;
+
+/// Module-level view function for the log viewer
+pub fn view<'a>(state: &'a LogViewerState) -> Element<'a, LogViewerMessage> {
+    ui::view_log_viewer(
+        state.log_file.as_deref(),
+        &state.lines,
+        state.level_filter,
+        &state.search,
+        state.error.as_deref(),
+        state.copied,
+    )
+}
@@ -0,0 +1,42 @@
+use super::{LogViewerMessage, LogViewerState};
+use crate::ui::messages::Message;
+use iced::Task;
+use tracing::info;
+
+pub fn handle_message(
+    state: &mut LogViewerState,
+    elevation_status: &str,
+    message: LogViewerMessage,
+) -> Task<Message> {
+    match message {
+        LogViewerMessage::Refresh => {
+            state.refresh();
+            Task::none()
+        }
+
+        LogViewerMessage::SetLevelFilter(filter) => {
+            state.level_filter = filter;
+            Task::none()
+        }
+
+        LogViewerMessage::SetSearch(search) => {
+            state.search = search;
+            Task::none()
+        }
+
+        LogViewerMessage::CopyDiagnostics => {
+            // Refresh first so the copy includes what was logged up to now
+            state.refresh();
+            let diagnostics = crate::utils::logs::diagnostics(
+                state.log_file.as_deref(),
+                elevation_status,
+                &state.lines,
+            );
+            info!("Copied diagnostics to the clipboard");
+            state.copied = true;
+            iced::clipboard::write(diagnostics)
+        }
+
+        LogViewerMessage::BackToMainMenu => Task::done(Message::BackToMainMenu),
+    }
+}
@@ -0,0 +1,10 @@
+use super::LevelFilter;
+
+#[derive(Debug, Clone)]
+pub enum LogViewerMessage {
+    Refresh,                     // Re-read the end of the log file
+    SetLevelFilter(LevelFilter), // Only show lines of this level and above
+    SetSearch(String),           // Only show lines containing the text
+    CopyDiagnostics,             // Copy system details and the end of the log to the clipboard
+    BackToMainMenu,              // Return to main menu
+}
@@ -0,0 +1,82 @@
+use crate::utils::logs::{self, LogLevel, LogLine};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Level filters offered in the log viewer
+pub static LEVEL_FILTER_OPTIONS: [LevelFilter; 5] = [
+    LevelFilter(LogLevel::Trace),
+    LevelFilter(LogLevel::Debug),
+    LevelFilter(LogLevel::Info),
+    LevelFilter(LogLevel::Warn),
+    LevelFilter(LogLevel::Error),
+];
+
+/// Lowest level shown, as listed in the level picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelFilter(pub LogLevel);
+
+impl LevelFilter {
+    pub fn matches(&self, line: &LogLine) -> bool {
+        line.level >= self.0
+    }
+}
+
+impl fmt::Display for LevelFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            LogLevel::Trace => write!(f, "All levels"),
+            LogLevel::Debug => write!(f, "Debug and above"),
+            LogLevel::Info => write!(f, "Info and above"),
+            LogLevel::Warn => write!(f, "Warnings and errors"),
+            LogLevel::Error => write!(f, "Errors only"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogViewerState {
+    pub log_file: Option<PathBuf>,
+    pub lines: Vec<LogLine>,
+    pub level_filter: LevelFilter,
+    pub search: String,
+    pub error: Option<String>,
+    pub copied: bool, // Diagnostics were copied since the screen was opened
+}
+
+impl Default for LogViewerState {
+    fn default() -> Self {
+        Self {
+            log_file: None,
+            lines: Vec::new(),
+            level_filter: LevelFilter(LogLevel::Info),
+            search: String::new(),
+            error: None,
+            copied: false,
+        }
+    }
+}
+
+impl LogViewerState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the end of the newest log file again
+    pub fn refresh(&mut self) {
+        let result = logs::latest_log_file().and_then(|file| {
+            let lines = match &file {
+                Some(path) => logs::read_tail(path)?,
+                None => Vec::new(),
+            };
+            Ok((file, lines))
+        });
+        match result {
+            Ok((file, lines)) => {
+                self.log_file = file;
+                self.lines = lines;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("{:#}", e)),
+        }
+    }
+}
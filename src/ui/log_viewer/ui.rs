@@ -0,0 +1,168 @@
+use super::{LEVEL_FILTER_OPTIONS, LevelFilter, LogViewerMessage};
+use crate::style;
+use crate::ui::icons;
+use crate::utils::logs::{LogLevel, LogLine};
+use iced::widget::{button, column, container, pick_list, row, scrollable, text, text_input};
+use iced::{Alignment, Color, Element, Font, Length};
+use std::path::Path;
+
+/// Most lines shown at once; rendering the whole tail of a busy log is slow
+const MAX_SHOWN_LINES: usize = 500;
+
+/// Main log viewer view
+pub fn view_log_viewer<'a>(
+    log_file: Option<&'a Path>,
+    lines: &'a [LogLine],
+    level_filter: LevelFilter,
+    search: &'a str,
+    error: Option<&'a str>,
+    copied: bool,
+) -> Element<'a, LogViewerMessage> {
+    let header = container(
+        column![
+            text("Application Log").size(28),
+            text(match log_file {
+                Some(path) => format!("{}", path.display()),
+                None => "No log file has been written yet".to_string(),
+            })
+            .size(14)
+            .color(Color::from_rgb(0.7, 0.7, 0.8))
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(style::page_header);
+
+    let toolbar = container(
+        row![
+            pick_list(
+                &LEVEL_FILTER_OPTIONS[..],
+                Some(level_filter),
+                LogViewerMessage::SetLevelFilter
+            )
+            .style(style::pick_list_style),
+            text_input("Filter lines...", search)
+                .on_input(LogViewerMessage::SetSearch)
+                .padding(8)
+                .width(Length::Fill),
+            button(icons::refresh())
+                .on_press(LogViewerMessage::Refresh)
+                .padding(8)
+                .style(button::secondary),
+            button(
+                row![
+                    if copied {
+                        icons::check()
+                    } else {
+                        icons::file_upload()
+                    },
+                    if copied { "Copied" } else { "Copy Diagnostics" }
+                ]
+                .spacing(5)
+                .align_y(Alignment::Center)
+            )
+            .on_press(LogViewerMessage::CopyDiagnostics)
+            .padding(8)
+            .style(button::primary)
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center),
+    )
+    .style(style::bordered_box)
+    .padding(15)
+    .width(Length::Fill);
+
+    let search = search.trim().to_lowercase();
+    let filtered: Vec<&LogLine> = lines
+        .iter()
+        .filter(|line| level_filter.matches(line))
+        .filter(|line| search.is_empty() || line.text.to_lowercase().contains(&search))
+        .collect();
+    let shown = &filtered[filtered.len().saturating_sub(MAX_SHOWN_LINES)..];
+
+    let log_section: Element<'a, LogViewerMessage> = if let Some(error) = error {
+        container(
+            row![
+                icons::error().color(Color::from_rgb(0.8, 0.2, 0.2)),
+                text(format!("Failed to read the log: {}", error)).size(14)
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        )
+        .padding(30)
+        .width(Length::Fill)
+        .into()
+    } else if shown.is_empty() {
+        container(
+            text(if lines.is_empty() {
+                "The log is empty"
+            } else {
+                "No lines match the filter"
+            })
+            .size(14)
+            .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        )
+        .padding(30)
+        .width(Length::Fill)
+        .center_x(Length::Fill)
+        .into()
+    } else {
+        let mut log = column(shown.iter().map(|line| {
+            text(line.text.as_str())
+                .size(12)
+                .font(Font::MONOSPACE)
+                .color(level_color(line.level))
+                .into()
+        }))
+        .spacing(2)
+        .width(Length::Fill);
+        if filtered.len() > shown.len() {
+            log = log.push(
+                text(format!(
+                    "Showing the last {} of {} lines",
+                    shown.len(),
+                    filtered.len()
+                ))
+                .size(12)
+                .color(Color::from_rgb(0.6, 0.6, 0.6)),
+            );
+        }
+
+        // Keep the newest lines in view as the log grows
+        container(scrollable(log).anchor_bottom().height(Length::Fill))
+            .style(style::bordered_box)
+            .padding(10)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    };
+
+    let back_button = button(
+        row![icons::navigate_before(), "Back"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(LogViewerMessage::BackToMainMenu)
+    .padding(12)
+    .style(style::navigation_back_button);
+
+    column![
+        header,
+        toolbar,
+        container(log_section).height(Length::Fill),
+        container(back_button).width(Length::Fill).padding([15, 0])
+    ]
+    .spacing(20)
+    .padding(20)
+    .into()
+}
+
+fn level_color(level: LogLevel) -> Color {
+    match level {
+        LogLevel::Error => Color::from_rgb(1.0, 0.45, 0.45),
+        LogLevel::Warn => Color::from_rgb(1.0, 0.8, 0.3),
+        LogLevel::Info => Color::from_rgb(0.85, 0.85, 0.85),
+        LogLevel::Debug | LogLevel::Trace => Color::from_rgb(0.6, 0.6, 0.65),
+    }
+}
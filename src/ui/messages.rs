@@ -1,6 +1,9 @@
 use crate::ui::{
-    configuration::ConfigurationMessage, device_selection::DeviceMessage,
-    edit_workflow::EditMessage, flash_workflow::FlashMessage, preset_manager::PresetManagerMessage,
+    cache_manager::CacheManagerMessage, configuration::ConfigurationMessage,
+    device_selection::DeviceMessage, edit_workflow::EditMessage, flash_queue::FlashQueueMessage,
+    flash_workflow::FlashMessage, log_viewer::LogViewerMessage,
+    preset_manager::PresetManagerMessage, settings::SettingsMessage,
+    update_workflow::UpdateMessage,
 };
 
 #[derive(Debug, Clone)]
@@ -8,11 +11,83 @@ pub enum Message {
     // App-level messages
     FlashNewImage,
     EditExistingDisk,
+    UpdateExistingDevice,
     ManagePresets,
+    ManageCache,
+    ViewLogs,
+    OpenSettings,
+    OpenCrashReport,
+    DismissCrashReport,
     BackToMainMenu,
     Exit,
     ShowError(String),
 
+    // Closing the window, which waits for a running write to stop
+    CloseRequested,
+    KeepRunning,   // Close the exit dialog and let the write finish
+    CancelAndExit, // Cancel the write and exit once it has stopped
+
+    // Running a flash in the background
+    HideToTray,
+    PollTray,
+    RestoreFromTray,
+    WindowFocusChanged(bool),
+    FileDropped(std::path::PathBuf), // A file dropped onto the window, flashed as the image
+
+    // The window flashed at the end of a flash
+    AlertTick,
+    DismissAlert,
+
+    // Requests of the automation API, queued like the tray events
+    PollAutomation,
+
+    // Later starts of the imager, which hand over to this window
+    PollInstance,
+    InstanceActivated(Vec<String>), // Command line of the later start
+    Preseed(crate::utils::preseed::Preseed), // Open the flash workflow with these choices made
+
+    // Flashes run one after another
+    ManageFlashQueue,
+    EnqueueFlash,  // Queue the flash confirmed in the flash workflow
+    RunFlashQueue, // Start the next queued flash
+
+    // Flashing every inserted disk with the confirmed image and preset
+    StartKiosk,
+    StopKiosk,
+    PollKiosk,                                // Scan for inserted disks
+    KioskEjected(String, Result<(), String>), // Path of the written disk
+
+    // A tab for each write of a batch
+    OpenFlashMonitor,
+    CloseFlashMonitor,
+    SelectMonitorTab(Option<crate::models::OperationId>), // None for all writes
+
+    // Updates of the application itself
+    CheckForAppUpdate(bool), // true when the user asked for the check
+    AppUpdateChecked(
+        Result<Option<crate::utils::updater::UpdateInfo>, String>,
+        bool,
+    ),
+    ShowAppUpdate,
+    CloseAppUpdate,
+    DismissAppUpdate,
+    InstallAppUpdate,
+    AppUpdateDownloaded(Result<std::path::PathBuf, String>),
+    OpenReleasePage,
+    SetCheckForUpdates(bool),
+
+    // Read-only presets of the team, fetched from the URL in the settings
+    LoadSharedPresets,
+    SharedPresetsLoaded(Result<crate::utils::shared_presets::SharedPresets, String>),
+
+    // Window size and zoom, remembered between runs
+    WindowResized(iced::Size),
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    SaveSettings,
+    TelemetryExported(Result<(), String>),
+
     // Repository management
     RepoDataLoaded(Vec<crate::ui::flash_workflow::OsImage>),
     RepoGroupDataLoaded(
@@ -25,6 +100,8 @@ pub enum Message {
     // Elevation management (Windows)
     RequestElevation,
     CheckElevationStatus,
+    StartPrivilegedHelper,
+    PrivilegedHelperStarted(Result<(), String>),
 
     // Preset management
     SaveAsPreset(crate::models::ConfigurationPreset),
@@ -36,7 +113,12 @@ pub enum Message {
     // Module-specific message variants
     Flash(FlashMessage),
     Edit(EditMessage),
+    Update(UpdateMessage),
     PresetManager(PresetManagerMessage),
+    CacheManager(CacheManagerMessage),
+    FlashQueue(FlashQueueMessage),
+    LogViewer(LogViewerMessage),
+    Settings(SettingsMessage),
     DeviceSelection(DeviceMessage),
     Configuration(ConfigurationMessage),
 }
@@ -0,0 +1,55 @@
+/// Desktop notifications for events the user may have missed with the window hidden
+///
+/// Flashing and updating take long enough that users switch to other windows. When the
+/// window isn't focused, the end of the operation is announced through the platform's
+/// notification service (D-Bus on Linux, toasts on Windows, Notification Center on macOS).
+use crate::ui::flash_workflow::FlashMessage;
+use crate::ui::update_workflow::UpdateMessage;
+use tracing::{debug, warn};
+
+/// Show a desktop notification
+///
+/// Sending it can block on the notification service, so it is done on its own thread.
+pub fn notify(summary: &str, body: &str) {
+    let summary = summary.to_string();
+    let body = body.to_string();
+    std::thread::spawn(move || {
+        debug!("Showing notification: {}", summary);
+        if let Err(e) = notify_rust::Notification::new()
+            .appname("Golem GPU Imager")
+            .summary(&summary)
+            .body(&body)
+            .show()
+        {
+            warn!("Failed to show notification: {}", e);
+        }
+    });
+}
+
+/// Announce the end of a flash, ignoring messages that don't end one
+pub fn notify_flash_result(message: &FlashMessage) {
+    match message {
+        FlashMessage::WriteImageCompleted(_, true) => notify(
+            "Flash completed",
+            "The image was written and verified. The device can be removed.",
+        ),
+        FlashMessage::WriteImageCompleted(_, false) => notify(
+            "Flash completed",
+            "The image was written, verification was skipped.",
+        ),
+        FlashMessage::WriteImageFailed(_, error) => notify("Flash failed", error),
+        _ => {}
+    }
+}
+
+/// Announce the end of a device update, ignoring messages that don't end one
+pub fn notify_update_result(message: &UpdateMessage) {
+    match message {
+        UpdateMessage::UpdateCompleted(_) => notify(
+            "Update completed",
+            "The device was updated and its configuration restored.",
+        ),
+        UpdateMessage::UpdateFailed(_, error) => notify("Update failed", error),
+        _ => {}
+    }
+}
@@ -11,12 +11,25 @@ pub use ui::*;
 use iced::Element;
 
 /// Module-level view function for preset manager
-pub fn view<'a>(state: &'a PresetManagerState) -> Element<'a, PresetManagerMessage> {
+pub fn view<'a>(
+    state: &'a PresetManagerState,
+    window_size: iced::Size,
+) -> Element<'a, PresetManagerMessage> {
     ui::view_preset_manager(
         &state.presets,
         None, // Don't show any preset as selected in management view
         &state.new_preset_name,
         state.editor.as_ref(),
         state.deletion_confirmation.as_ref(),
+        state.renaming.as_ref().map(|(index, name)| Renaming {
+            index: *index,
+            name,
+            error: state.rename_error(),
+        }),
+        state.last_change.as_deref(),
+        state.shared_status.as_deref(),
+        &state.assignments,
+        &state.assignment_draft,
+        window_size,
     )
 }
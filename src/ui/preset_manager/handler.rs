@@ -9,6 +9,19 @@ pub fn handle_message(
     state: &mut PresetManagerState,
     preset_manager: &mut Option<PresetManager>,
     message: PresetManagerMessage,
+) -> Task<crate::ui::messages::Message> {
+    let task = handle_change(state, preset_manager, message);
+    state.last_change = preset_manager
+        .as_ref()
+        .and_then(|manager| manager.history().last())
+        .map(|snapshot| snapshot.change.clone());
+    task
+}
+
+fn handle_change(
+    state: &mut PresetManagerState,
+    preset_manager: &mut Option<PresetManager>,
+    message: PresetManagerMessage,
 ) -> Task<crate::ui::messages::Message> {
     match message {
         PresetManagerMessage::ToggleManager => {
@@ -26,10 +39,15 @@ pub fn handle_message(
         }
 
         PresetManagerMessage::DeletePreset(index) => {
-            if index < state.presets.len() {
+            if state.is_local(index) {
                 let preset_name = state.presets[index].name.clone();
                 state.presets.remove(index);
 
+                // Assignments that selected the preset go with it
+                state
+                    .assignments
+                    .retain(|assignment| assignment.preset != preset_name);
+
                 // Update preset manager if available
                 if let Some(manager) = preset_manager {
                     let _ = manager.delete_preset(index);
@@ -46,6 +64,7 @@ pub fn handle_message(
 
                 // Clear deletion confirmation
                 state.deletion_confirmation = None;
+                state.renaming = None;
 
                 info!("Deleted preset: {}", preset_name);
             }
@@ -53,7 +72,7 @@ pub fn handle_message(
         }
 
         PresetManagerMessage::ConfirmDeletePreset(index) => {
-            if index < state.presets.len() {
+            if state.is_local(index) {
                 let preset_name = state.presets[index].name.clone();
                 state.deletion_confirmation = Some((index, preset_name));
             }
@@ -66,7 +85,7 @@ pub fn handle_message(
         }
 
         PresetManagerMessage::SetDefaultPreset(index) => {
-            if index < state.presets.len() {
+            if state.is_local(index) {
                 // Clear all default flags first
                 for preset in &mut state.presets {
                     preset.is_default = false;
@@ -98,7 +117,7 @@ pub fn handle_message(
                 new_preset.is_default = false; // New presets are not default by default
 
                 let preset_name = new_preset.name.clone();
-                state.presets.push(new_preset.clone());
+                state.insert_local(new_preset.clone());
 
                 // Update preset manager if available
                 if let Some(manager) = preset_manager {
@@ -112,7 +131,7 @@ pub fn handle_message(
                 new_preset.is_default = false; // Imported presets are not default by default
 
                 let preset_name = new_preset.name.clone();
-                state.presets.push(new_preset.clone());
+                state.insert_local(new_preset.clone());
 
                 // Update preset manager if available
                 if let Some(manager) = preset_manager {
@@ -165,7 +184,7 @@ pub fn handle_message(
         }
 
         PresetManagerMessage::EditPreset(index) => {
-            if let Some(preset) = state.presets.get(index) {
+            if let Some(preset) = state.presets.get(index).filter(|preset| !preset.shared) {
                 state.editor = Some(PresetEditor::new(index, preset));
             }
             Task::none()
@@ -178,8 +197,9 @@ pub fn handle_message(
 
                     if let Some(index) = editor.editing_index {
                         // Update existing preset
-                        if index < state.presets.len() {
+                        if state.is_local(index) {
                             let preset_name = preset.name.clone();
+                            rename_assignments(state, index, &preset_name);
                             state.presets[index] = preset.clone();
                             if let Some(manager) = preset_manager {
                                 let _ = manager.update_preset(index, preset);
@@ -188,7 +208,7 @@ pub fn handle_message(
                         }
                     } else {
                         // Create new preset
-                        state.presets.push(preset.clone());
+                        state.insert_local(preset.clone());
                         if let Some(manager) = preset_manager {
                             let _ = manager.add_preset(preset.clone());
                         }
@@ -211,9 +231,10 @@ pub fn handle_message(
         PresetManagerMessage::DuplicatePreset(index) => {
             if let Some(preset) = state.presets.get(index) {
                 let mut duplicated = preset.clone();
-                duplicated.name = format!("{} Copy", preset.name);
+                duplicated.name = state.unique_preset_name(&format!("{} Copy", preset.name));
                 duplicated.is_default = false; // Duplicates are never default
-                state.presets.push(duplicated.clone());
+                duplicated.shared = false; // A copy of a shared preset can be changed
+                state.insert_local(duplicated.clone());
 
                 if let Some(manager) = preset_manager {
                     let _ = manager.add_preset(duplicated.clone());
@@ -224,6 +245,96 @@ pub fn handle_message(
             Task::none()
         }
 
+        PresetManagerMessage::StartRenamePreset(index) => {
+            if let Some(preset) = state.presets.get(index).filter(|preset| !preset.shared) {
+                state.renaming = Some((index, preset.name.clone()));
+            }
+            Task::none()
+        }
+
+        PresetManagerMessage::SetRenameText(name) => {
+            if let Some((_, entered)) = &mut state.renaming {
+                *entered = name;
+            }
+            Task::none()
+        }
+
+        PresetManagerMessage::ConfirmRenamePreset => {
+            if state.rename_error().is_some() {
+                return Task::none();
+            }
+            let Some((index, name)) = state.renaming.take() else {
+                return Task::none();
+            };
+            let name = name.trim().to_string();
+            if state.is_local(index) && state.presets[index].name != name {
+                rename_assignments(state, index, &name);
+                let old_name = std::mem::replace(&mut state.presets[index].name, name.clone());
+                if let Some(manager) = preset_manager {
+                    let _ = manager.update_preset(index, state.presets[index].clone());
+                }
+                info!("Renamed preset {} to {}", old_name, name);
+            }
+            Task::none()
+        }
+
+        PresetManagerMessage::CancelRenamePreset => {
+            state.renaming = None;
+            Task::none()
+        }
+
+        PresetManagerMessage::MovePreset(from, to) => {
+            if state.is_local(from) && state.is_local(to) && from != to {
+                let preset = state.presets.remove(from);
+                state.presets.insert(to, preset);
+
+                // The selection follows the presets that moved
+                state.selected_preset = state.selected_preset.map(|selected| {
+                    if selected == from {
+                        to
+                    } else if from < selected && selected <= to {
+                        selected - 1
+                    } else if to <= selected && selected < from {
+                        selected + 1
+                    } else {
+                        selected
+                    }
+                });
+                state.renaming = None;
+
+                if let Some(manager) = preset_manager {
+                    let _ = manager.move_preset(from, to);
+                }
+                debug!("Moved preset from position {} to {}", from, to);
+            }
+            Task::none()
+        }
+
+        PresetManagerMessage::UndoChange => {
+            let Some(manager) = preset_manager else {
+                return Task::none();
+            };
+            match manager.undo() {
+                Ok(Some(change)) => info!("Undid: {}", change),
+                Ok(None) => return Task::none(),
+                Err(e) => error!("Failed to save the presets after undoing: {}", e),
+            }
+            reload_presets(state, manager);
+            Task::none()
+        }
+
+        PresetManagerMessage::RestoreBuiltinPresets => {
+            let Some(manager) = preset_manager else {
+                return Task::none();
+            };
+            match manager.restore_builtin_presets() {
+                Ok(()) => info!("Restored the built-in presets"),
+                Err(e) => error!("Failed to save the restored presets: {}", e),
+            }
+            reload_presets(state, manager);
+            Task::none()
+        }
+
         PresetManagerMessage::ExportPreset(index) => {
             if let Some(preset) = state.presets.get(index) {
                 let preset_name = preset.name.clone();
@@ -281,6 +392,59 @@ pub fn handle_message(
             }
         }
 
+        PresetManagerMessage::SetAssignmentPreset(preset) => {
+            state.assignment_draft.preset = Some(preset);
+            Task::none()
+        }
+
+        PresetManagerMessage::SetAssignmentSerial(serial) => {
+            state.assignment_draft.serial = serial;
+            Task::none()
+        }
+
+        PresetManagerMessage::SetAssignmentModel(model) => {
+            state.assignment_draft.model = model;
+            Task::none()
+        }
+
+        PresetManagerMessage::SetAssignmentSize(size) => {
+            state.assignment_draft.size_gb = size;
+            Task::none()
+        }
+
+        PresetManagerMessage::AddAssignment => {
+            let Some(assignment) = state.assignment_draft.to_assignment() else {
+                return Task::none();
+            };
+
+            let saved = match preset_manager {
+                Some(manager) => manager.add_assignment(assignment.clone()),
+                None => Ok(()),
+            };
+            if let Err(e) = saved {
+                error!("Failed to add device assignment: {}", e);
+                return Task::none();
+            }
+            info!(
+                "Assigned preset {} to devices matching {}",
+                assignment.preset,
+                assignment.describe()
+            );
+            state.assignments.push(assignment);
+            state.assignment_draft = Default::default();
+            Task::none()
+        }
+
+        PresetManagerMessage::DeleteAssignment(index) => {
+            if index < state.assignments.len() {
+                state.assignments.remove(index);
+                if let Some(manager) = preset_manager {
+                    let _ = manager.delete_assignment(index);
+                }
+            }
+            Task::none()
+        }
+
         PresetManagerMessage::ImportPresetFromFile(path) => {
             Task::perform(load_preset_from_file(path), |result| {
                 match result {
@@ -309,7 +473,7 @@ fn handle_editor_message(
 ) -> Task<crate::ui::messages::Message> {
     match message {
         PresetEditorMessage::Start(index) => {
-            if let Some(preset) = state.presets.get(index) {
+            if let Some(preset) = state.presets.get(index).filter(|preset| !preset.shared) {
                 state.editor = Some(PresetEditor::new(index, preset));
             }
             Task::none()
@@ -326,7 +490,8 @@ fn handle_editor_message(
                     let updated_preset = editor.to_preset();
 
                     if let Some(index) = editor.editing_index {
-                        if index < state.presets.len() {
+                        if state.is_local(index) {
+                            rename_assignments(state, index, &updated_preset.name);
                             state.presets[index] = updated_preset.clone();
 
                             // Update in preset manager if available
@@ -375,6 +540,26 @@ fn handle_editor_message(
     }
 }
 
+/// Show the presets of the store after it changed them as a whole
+fn reload_presets(state: &mut PresetManagerState, manager: &PresetManager) {
+    state.presets = manager.get_presets().clone();
+    state.assignments = manager.get_assignments().clone();
+    state.selected_preset = state.presets.iter().position(|p| p.is_default);
+    state.editor = None;
+    state.renaming = None;
+    state.deletion_confirmation = None;
+}
+
+/// Keep device assignments pointing at a preset that is saved under a new name
+fn rename_assignments(state: &mut PresetManagerState, index: usize, new_name: &str) {
+    let old_name = &state.presets[index].name;
+    for assignment in &mut state.assignments {
+        if assignment.preset == *old_name {
+            assignment.preset = new_name.to_string();
+        }
+    }
+}
+
 // Preset export/import file format
 #[derive(serde::Serialize, serde::Deserialize)]
 struct PresetFileFormat {
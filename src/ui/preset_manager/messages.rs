@@ -27,10 +27,23 @@ pub enum PresetManagerMessage {
     SavePreset,                                       // Save preset being edited
     CancelEdit,                                       // Cancel editing
     DuplicatePreset(usize),                           // Duplicate an existing preset
+    StartRenamePreset(usize),                         // Edit the name of a preset in its card
+    SetRenameText(String),                            // Name being entered for the preset
+    ConfirmRenamePreset,                              // Save the entered name
+    CancelRenamePreset,                               // Keep the current name
+    MovePreset(usize, usize),                         // Move a preset from one position to another
+    UndoChange,                                       // Undo the last change to the presets
+    RestoreBuiltinPresets,                            // Reset the presets the imager ships with
     ConfirmDeletePreset(usize),                       // Show confirmation dialog for deletion
     CancelDeleteConfirmation,                         // Cancel deletion confirmation
     ExportPreset(usize),                              // Export specific preset by index
     ImportPreset,                                     // Import single preset from file
     ExportPresetToFile(usize, std::path::PathBuf),    // Save specific preset to file
     ImportPresetFromFile(std::path::PathBuf),         // Load preset from file
+    SetAssignmentPreset(String),                      // Preset for the new device assignment
+    SetAssignmentSerial(String),                      // Serial number to match
+    SetAssignmentModel(String),                       // Model pattern to match
+    SetAssignmentSize(String),                        // Size in GB to match
+    AddAssignment,                                    // Save the device assignment being added
+    DeleteAssignment(usize),                          // Delete a device assignment by index
 }
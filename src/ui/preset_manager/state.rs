@@ -1,5 +1,6 @@
 use crate::models::{ConfigurationPreset, NetworkType, PaymentNetwork};
 use crate::ui::configuration::ConfigurationState;
+use crate::utils::device_assignment::DeviceAssignment;
 
 #[derive(Debug, Clone)]
 pub struct PresetEditor {
@@ -38,6 +39,33 @@ impl PresetEditor {
     }
 }
 
+/// Device assignment being entered in the preset manager
+#[derive(Debug, Clone, Default)]
+pub struct AssignmentDraft {
+    pub preset: Option<String>,
+    pub serial: String,
+    pub model: String,
+    pub size_gb: String,
+}
+
+impl AssignmentDraft {
+    /// The assignment, if a preset and at least one valid criterion were entered
+    pub fn to_assignment(&self) -> Option<DeviceAssignment> {
+        let non_empty = |value: &str| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        let size_gb = match non_empty(&self.size_gb) {
+            Some(size) => Some(size.parse().ok()?),
+            None => None,
+        };
+        let assignment = DeviceAssignment {
+            preset: self.preset.clone()?,
+            serial: non_empty(&self.serial),
+            model: non_empty(&self.model),
+            size_gb,
+        };
+        assignment.has_criteria().then_some(assignment)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PresetManagerState {
     pub presets: Vec<ConfigurationPreset>,
@@ -46,6 +74,11 @@ pub struct PresetManagerState {
     pub show_manager: bool,
     pub editor: Option<PresetEditor>,
     pub deletion_confirmation: Option<(usize, String)>, // (Index, name) of preset being confirmed for deletion
+    pub assignments: Vec<DeviceAssignment>,             // Presets pre-selected for matching devices
+    pub assignment_draft: AssignmentDraft,
+    pub renaming: Option<(usize, String)>, // (Index, entered name) of preset being renamed
+    pub last_change: Option<String>,       // Change the undo button reverts
+    pub shared_status: Option<String>,     // Where the shared presets came from, or why they didn't
 }
 
 impl PresetManagerState {
@@ -57,6 +90,83 @@ impl PresetManagerState {
             show_manager: false,
             editor: None,
             deletion_confirmation: None,
+            assignments: Vec::new(),
+            assignment_draft: AssignmentDraft::default(),
+            renaming: None,
+            last_change: None,
+            shared_status: None,
+        }
+    }
+
+    /// Number of local presets, which are listed before the shared ones
+    pub fn local_count(&self) -> usize {
+        self.presets.iter().filter(|preset| !preset.shared).count()
+    }
+
+    /// Whether `index` is a preset that can be changed, i.e. not a shared one
+    pub fn is_local(&self, index: usize) -> bool {
+        self.presets.get(index).is_some_and(|preset| !preset.shared)
+    }
+
+    /// Add a local preset after the other local ones, before the shared presets
+    pub fn insert_local(&mut self, mut preset: ConfigurationPreset) {
+        preset.shared = false;
+        let index = self.local_count();
+        if let Some(selected) = self.selected_preset.filter(|&selected| selected >= index) {
+            self.selected_preset = Some(selected + 1);
+        }
+        self.presets.insert(index, preset);
+    }
+
+    /// Replace the shared presets, keeping the selected preset selected
+    ///
+    /// A shared preset named like a local one is left out, as `PresetManager` does.
+    pub fn set_shared_presets(&mut self, shared: Vec<ConfigurationPreset>) {
+        let selected = self
+            .selected_preset
+            .and_then(|index| self.presets.get(index))
+            .map(|preset| preset.name.clone());
+        self.presets.retain(|preset| !preset.shared);
+        for mut preset in shared {
+            if self.presets.iter().any(|p| p.name == preset.name) {
+                continue;
+            }
+            preset.shared = true;
+            preset.is_default = false;
+            self.presets.push(preset);
+        }
+        self.selected_preset = selected
+            .and_then(|name| self.presets.iter().position(|preset| preset.name == name))
+            .or_else(|| self.presets.iter().position(|preset| preset.is_default));
+    }
+
+    /// `base`, or `base` with a number added if a preset already has that name
+    pub fn unique_preset_name(&self, base: &str) -> String {
+        let taken = |name: &str| self.presets.iter().any(|preset| preset.name == name);
+        if !taken(base) {
+            return base.to_string();
+        }
+        (2..)
+            .map(|n| format!("{} {}", base, n))
+            .find(|name| !taken(name))
+            .unwrap_or_default()
+    }
+
+    /// Why the name being entered can't be given to the preset being renamed, `None` if it can
+    pub fn rename_error(&self) -> Option<&'static str> {
+        let (index, name) = self.renaming.as_ref()?;
+        let name = name.trim();
+        if name.is_empty() {
+            Some("Enter a name")
+        } else if self
+            .presets
+            .iter()
+            .enumerate()
+            .any(|(i, preset)| i != *index && preset.name == name)
+        {
+            Some("Another preset has this name")
+        } else {
+            None
         }
     }
 
@@ -75,6 +185,11 @@ impl PresetManagerState {
                 configuration_server: None,
                 metrics_server: None,
                 central_net_host: None,
+                node_name: None,
+                extra_env: Vec::new(),
+                extra_toml: Vec::new(),
+                firstboot: None,
+                shared: false,
             },
             ConfigurationPreset {
                 name: "Mainnet Production".to_string(),
@@ -88,6 +203,11 @@ impl PresetManagerState {
                 configuration_server: None,
                 metrics_server: None,
                 central_net_host: None,
+                node_name: None,
+                extra_env: Vec::new(),
+                extra_toml: Vec::new(),
+                firstboot: None,
+                shared: false,
             },
         ];
         state
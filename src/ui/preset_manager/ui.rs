@@ -1,9 +1,23 @@
-use super::{PresetEditor, PresetEditorMessage, PresetManagerMessage};
+use super::{AssignmentDraft, PresetEditor, PresetEditorMessage, PresetManagerMessage};
 use crate::models::{ConfigurationPreset, NetworkType, PaymentNetwork};
 use crate::style;
-use crate::ui::icons;
-use iced::widget::{button, column, container, row, scrollable, stack, text, text_input};
-use iced::{Alignment, Border, Color, Element, Length};
+use crate::ui::{icons, layout};
+use crate::utils::device_assignment::DeviceAssignment;
+use iced::widget::{
+    button, column, container, pick_list, row, scrollable, stack, text, text_input,
+};
+use iced::{Alignment, Border, Color, Element, Length, Size};
+
+/// Narrowest a preset card gets before the grid drops a column
+const PRESET_CARD_MIN_WIDTH: f32 = 200.0;
+
+/// Preset whose name is being edited in its card
+#[derive(Debug, Clone, Copy)]
+pub struct Renaming<'a> {
+    pub index: usize,
+    pub name: &'a str,
+    pub error: Option<&'static str>,
+}
 
 /// Main preset manager view
 pub fn view_preset_manager<'a>(
@@ -12,6 +26,12 @@ pub fn view_preset_manager<'a>(
     new_preset_name: &'a str,
     editor: Option<&'a PresetEditor>,
     deletion_confirmation: Option<&'a (usize, String)>,
+    renaming: Option<Renaming<'a>>,
+    last_change: Option<&'a str>,
+    shared_status: Option<&'a str>,
+    assignments: &'a [DeviceAssignment],
+    assignment_draft: &'a AssignmentDraft,
+    window_size: Size,
 ) -> Element<'a, PresetManagerMessage> {
     let header = container(
         column![
@@ -26,10 +46,20 @@ pub fn view_preset_manager<'a>(
 
     let content = if let Some(preset_editor) = editor {
         // Show preset editor
-        view_preset_editor(preset_editor)
+        view_preset_editor(preset_editor, layout::is_wide(window_size))
     } else {
         // Show preset list
-        view_preset_list(presets, selected_preset, new_preset_name)
+        view_preset_list(
+            presets,
+            selected_preset,
+            renaming,
+            last_change,
+            shared_status,
+            new_preset_name,
+            assignments,
+            assignment_draft,
+            layout::columns(window_size, PRESET_CARD_MIN_WIDTH, 4),
+        )
     };
 
     let back_button = if editor.is_some() {
@@ -84,10 +114,33 @@ pub fn view_preset_manager<'a>(
 fn view_preset_list<'a>(
     presets: &'a [ConfigurationPreset],
     selected_preset: Option<usize>,
+    renaming: Option<Renaming<'a>>,
+    last_change: Option<&'a str>,
+    shared_status: Option<&'a str>,
     new_preset_name: &'a str,
+    assignments: &'a [DeviceAssignment],
+    assignment_draft: &'a AssignmentDraft,
+    columns: usize,
 ) -> Element<'a, PresetManagerMessage> {
+    // Undo of the last change, named in the button's label
+    let undo_button = button(
+        row![
+            icons::undo(),
+            text(match last_change {
+                Some(change) => format!("Undo: {}", change),
+                None => "Undo".to_string(),
+            })
+            .size(13)
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center),
+    )
+    .on_press_maybe(last_change.map(|_| PresetManagerMessage::UndoChange))
+    .padding(6)
+    .style(button::secondary);
+
     // Simple header with title and count
-    let header = container(
+    let mut header = column![
         row![
             text("Configuration Presets").size(24),
             container(
@@ -96,13 +149,31 @@ fn view_preset_list<'a>(
                     .color(Color::from_rgb(0.6, 0.6, 0.6))
             )
             .width(Length::Fill)
-            .align_x(Alignment::End)
+            .align_x(Alignment::End),
+            undo_button,
+            button(text("Restore Built-in Presets").size(13))
+                .on_press(PresetManagerMessage::RestoreBuiltinPresets)
+                .padding(6)
+                .style(button::secondary),
         ]
+        .spacing(10)
         .align_y(Alignment::Center)
-        .width(Length::Fill),
-    )
-    .padding(10)
-    .width(Length::Fill);
+        .width(Length::Fill)
+    ]
+    .spacing(6);
+    if let Some(status) = shared_status {
+        header = header.push(
+            row![
+                icons::cloud()
+                    .size(14)
+                    .color(Color::from_rgb(0.6, 0.75, 0.95)),
+                text(status).size(12).color(Color::from_rgb(0.7, 0.7, 0.7))
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center),
+        );
+    }
+    let header = container(header).padding(10).width(Length::Fill);
 
     // Simple create section with import button
     let quick_create = container(
@@ -155,26 +226,126 @@ fn view_preset_list<'a>(
     } else {
         // Grid layout for preset cards
         let all_presets: Vec<(usize, &ConfigurationPreset)> = presets.iter().enumerate().collect();
-        let preset_grid = create_preset_grid(all_presets, selected_preset);
+        let preset_grid = create_preset_grid(
+            all_presets,
+            presets.iter().filter(|preset| !preset.shared).count(),
+            selected_preset,
+            renaming,
+            columns,
+        );
 
         container(preset_grid).padding(5).width(Length::Fill).into()
     };
 
     scrollable(
-        column![header, quick_create, presets_section]
-            .spacing(20)
-            .width(Length::Fill),
+        column![
+            header,
+            quick_create,
+            presets_section,
+            view_device_assignments(presets, assignments, assignment_draft)
+        ]
+        .spacing(20)
+        .width(Length::Fill),
     )
     .height(Length::Fill)
     .into()
 }
 
-/// Create responsive grid layout for preset cards
+/// Presets pre-selected in the flash workflow for matching devices
+fn view_device_assignments<'a>(
+    presets: &'a [ConfigurationPreset],
+    assignments: &'a [DeviceAssignment],
+    draft: &'a AssignmentDraft,
+) -> Element<'a, PresetManagerMessage> {
+    let header = column![
+        text("Device Assignments").size(20),
+        text(
+            "Pre-select a preset when flashing devices that match a serial number, \
+            model (use * as a wildcard) or size"
+        )
+        .size(12)
+        .color(Color::from_rgb(0.6, 0.6, 0.6)),
+    ]
+    .spacing(4);
+
+    let mut list = column![].spacing(6);
+    if assignments.is_empty() {
+        list = list.push(
+            text("No device assignments")
+                .size(12)
+                .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        );
+    }
+    for (index, assignment) in assignments.iter().enumerate() {
+        list = list.push(
+            row![
+                icons::sd_card(),
+                text(assignment.describe()).size(14).width(Length::Fill),
+                icons::navigate_next(),
+                text(&assignment.preset).size(14).width(Length::Fill),
+                button(icons::delete())
+                    .on_press(PresetManagerMessage::DeleteAssignment(index))
+                    .padding(6)
+                    .style(button::danger),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    let preset_names: Vec<String> = presets.iter().map(|p| p.name.clone()).collect();
+    let add_form = row![
+        pick_list(
+            preset_names,
+            draft.preset.clone(),
+            PresetManagerMessage::SetAssignmentPreset
+        )
+        .placeholder("Preset")
+        .style(style::pick_list_style)
+        .width(Length::FillPortion(2)),
+        text_input("Serial number", &draft.serial)
+            .on_input(PresetManagerMessage::SetAssignmentSerial)
+            .padding(8)
+            .width(Length::FillPortion(2)),
+        text_input("Model, e.g. SanDisk*", &draft.model)
+            .on_input(PresetManagerMessage::SetAssignmentModel)
+            .padding(8)
+            .width(Length::FillPortion(2)),
+        text_input("Size (GB)", &draft.size_gb)
+            .on_input(PresetManagerMessage::SetAssignmentSize)
+            .padding(8)
+            .width(Length::FillPortion(1)),
+        button("Assign")
+            .on_press_maybe(
+                draft
+                    .to_assignment()
+                    .map(|_| PresetManagerMessage::AddAssignment)
+            )
+            .padding(8)
+            .style(button::primary),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+
+    container(column![header, list, add_form].spacing(12))
+        .style(style::bordered_box)
+        .padding(15)
+        .width(Length::Fill)
+        .into()
+}
+
+/// Create responsive grid layout for preset cards, `columns` cards per row
+///
+/// Cards are laid out in the order of the preset list; presets are moved within the first
+/// `local_count`, the shared presets after them stay where they are.
 fn create_preset_grid<'a>(
     filtered_presets: Vec<(usize, &'a ConfigurationPreset)>,
+    local_count: usize,
     selected_preset: Option<usize>,
+    renaming: Option<Renaming<'a>>,
+    columns: usize,
 ) -> Element<'a, PresetManagerMessage> {
-    // Create rows of cards (3 cards per row)
+    // Create rows of cards
     let mut rows = Vec::new();
     let mut current_row = Vec::new();
 
@@ -182,11 +353,13 @@ fn create_preset_grid<'a>(
         let card = create_compact_preset_card(
             preset,
             original_index,
+            local_count,
             selected_preset == Some(original_index),
+            renaming.filter(|renaming| renaming.index == original_index),
         );
         current_row.push(card);
 
-        if current_row.len() == 3 {
+        if current_row.len() == columns {
             let row_element = row(current_row).spacing(12).width(Length::Fill);
             rows.push(row_element.into());
             current_row = Vec::new();
@@ -196,7 +369,7 @@ fn create_preset_grid<'a>(
     // Add remaining cards in the last row
     if !current_row.is_empty() {
         // Pad with empty space to maintain alignment
-        while current_row.len() < 3 {
+        while current_row.len() < columns {
             current_row.push(container("").width(Length::Fill).into());
         }
         let row_element = row(current_row).spacing(12).width(Length::Fill);
@@ -207,19 +380,63 @@ fn create_preset_grid<'a>(
 }
 
 /// Create compact preset card for grid layout
+///
+/// While the preset is renamed its name is an input, saved with Enter. Shared presets can
+/// only be duplicated and exported.
 fn create_compact_preset_card<'a>(
     preset: &'a ConfigurationPreset,
     index: usize,
+    local_count: usize,
     is_selected: bool,
+    renaming: Option<Renaming<'a>>,
 ) -> Element<'a, PresetManagerMessage> {
-    // Header with name and default badge
-    let header = row![
-        column![
-            text(&preset.name).size(15).color(if is_selected {
+    let name: Element<'a, PresetManagerMessage> = match renaming {
+        Some(renaming) => {
+            let mut editor = column![
+                row![
+                    text_input("Preset name", renaming.name)
+                        .on_input(PresetManagerMessage::SetRenameText)
+                        .on_submit(PresetManagerMessage::ConfirmRenamePreset)
+                        .padding(4)
+                        .size(14)
+                        .width(Length::Fill),
+                    button(icons::check())
+                        .on_press_maybe(
+                            renaming
+                                .error
+                                .is_none()
+                                .then_some(PresetManagerMessage::ConfirmRenamePreset)
+                        )
+                        .padding(4)
+                        .style(button::success),
+                    button(icons::cancel())
+                        .on_press(PresetManagerMessage::CancelRenamePreset)
+                        .padding(4)
+                        .style(button::secondary),
+                ]
+                .spacing(4)
+                .align_y(Alignment::Center)
+            ]
+            .spacing(2);
+            if let Some(error) = renaming.error {
+                editor = editor.push(text(error).size(10).color(style::ERROR));
+            }
+            editor.into()
+        }
+        None => text(&preset.name)
+            .size(15)
+            .color(if is_selected {
                 Color::from_rgb(0.1, 0.1, 0.1)
             } else {
                 Color::from_rgb(0.9, 0.9, 0.9)
-            }),
+            })
+            .into(),
+    };
+
+    // Header with name and default badge
+    let header = row![
+        column![
+            name,
             if preset.is_default {
                 container(
                     row![icons::star().size(12), text("DEFAULT").size(10)]
@@ -237,6 +454,14 @@ fn create_compact_preset_card<'a>(
                     text_color: Some(Color::from_rgb(0.4, 0.2, 0.0)),
                     ..container::Style::default()
                 })
+            } else if preset.shared {
+                container(
+                    row![icons::cloud().size(12), text("SHARED").size(10)]
+                        .spacing(3)
+                        .align_y(Alignment::Center),
+                )
+                .padding(6)
+                .style(style::shared_preset_badge)
             } else {
                 container("").height(Length::Fixed(0.0))
             }
@@ -284,10 +509,11 @@ fn create_compact_preset_card<'a>(
     ]
     .spacing(2);
 
-    // Compact action buttons in two rows
+    // Compact action buttons in two rows, only copies of shared presets can be changed
+    let local = !preset.shared;
     let top_actions = row![
         button(icons::edit())
-            .on_press(PresetManagerMessage::EditPreset(index))
+            .on_press_maybe(local.then_some(PresetManagerMessage::EditPreset(index)))
             .padding(6)
             .style(button::secondary),
         button(icons::save())
@@ -298,20 +524,37 @@ fn create_compact_preset_card<'a>(
             .on_press(PresetManagerMessage::ExportPreset(index))
             .padding(6)
             .style(button::secondary),
+        button(text("Rename").size(11))
+            .on_press_maybe(local.then_some(PresetManagerMessage::StartRenamePreset(index)))
+            .padding(6)
+            .style(button::secondary),
     ]
     .spacing(4);
 
     let bottom_actions = row![
+        button(icons::navigate_before())
+            .on_press_maybe(
+                (local && index > 0).then(|| PresetManagerMessage::MovePreset(index, index - 1))
+            )
+            .padding(6)
+            .style(button::secondary),
+        button(icons::navigate_next())
+            .on_press_maybe(
+                (index + 1 < local_count)
+                    .then(|| PresetManagerMessage::MovePreset(index, index + 1))
+            )
+            .padding(6)
+            .style(button::secondary),
         if !preset.is_default {
             button(icons::star_border())
-                .on_press(PresetManagerMessage::SetDefaultPreset(index))
+                .on_press_maybe(local.then_some(PresetManagerMessage::SetDefaultPreset(index)))
                 .padding(6)
                 .style(button::primary)
         } else {
             button(icons::star()).padding(6).style(button::success)
         },
         button(icons::delete())
-            .on_press(PresetManagerMessage::ConfirmDeletePreset(index))
+            .on_press_maybe(local.then_some(PresetManagerMessage::ConfirmDeletePreset(index)))
             .padding(6)
             .style(button::danger)
     ]
@@ -339,7 +582,10 @@ fn create_compact_preset_card<'a>(
 }
 
 /// Enhanced preset editor view using modular configuration components
-fn view_preset_editor<'a>(editor: &'a PresetEditor) -> Element<'a, PresetManagerMessage> {
+fn view_preset_editor<'a>(
+    editor: &'a PresetEditor,
+    wide: bool,
+) -> Element<'a, PresetManagerMessage> {
     let title = text(if editor.editing_index.is_some() {
         "Edit Preset"
     } else {
@@ -359,18 +605,22 @@ fn view_preset_editor<'a>(editor: &'a PresetEditor) -> Element<'a, PresetManager
     .spacing(5);
 
     // Use the modular configuration form directly (without header)
-    let configuration_form =
-        crate::ui::configuration::view_configuration_form(&editor.configuration, |config_msg| {
+    let configuration_form = crate::ui::configuration::view_configuration_form(
+        &editor.configuration,
+        crate::disk::ConfigSchema::current(),
+        wide,
+        |config_msg| {
             crate::ui::messages::Message::PresetManager(PresetManagerMessage::Editor(
                 PresetEditorMessage::Configuration(config_msg),
             ))
-        })
-        .map(|msg| {
-            match msg {
-                crate::ui::messages::Message::PresetManager(preset_msg) => preset_msg,
-                _ => PresetManagerMessage::CancelEdit, // Fallback, should not happen
-            }
-        });
+        },
+    )
+    .map(|msg| {
+        match msg {
+            crate::ui::messages::Message::PresetManager(preset_msg) => preset_msg,
+            _ => PresetManagerMessage::CancelEdit, // Fallback, should not happen
+        }
+    });
 
     // Navigation buttons at bottom
     let actions = row![
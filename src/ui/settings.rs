@@ -0,0 +1,20 @@
+pub mod handler;
+pub mod messages;
+pub mod state;
+pub mod ui;
+
+pub use handler::*;
+pub use messages::*;
+pub use state::*;
+pub use ui::*;
+
+use crate::utils::app_settings::AppSettings;
+use iced::Element;
+
+/// Module-level view function for the settings screen
+pub fn view<'a>(
+    state: &'a SettingsState,
+    settings: &'a AppSettings,
+) -> Element<'a, SettingsMessage> {
+    ui::view_settings(settings, &state.rule_draft, state.status.as_deref())
+}
@@ -0,0 +1,230 @@
+use super::{SettingsMessage, SettingsState};
+use crate::ui::messages::Message;
+use crate::utils::app_settings::AppSettings;
+use crate::utils::{device_nicknames, device_rules, logs, paths};
+use iced::Task;
+use tracing::{error, info};
+
+/// Handle a message of the settings screen
+///
+/// The application saves `settings` shortly after they have been changed.
+pub fn handle_message(
+    state: &mut SettingsState,
+    settings: &mut AppSettings,
+    message: SettingsMessage,
+) -> Task<Message> {
+    match message {
+        SettingsMessage::SetLogLevel(option) => {
+            settings.log.level = option.0;
+            state.status = match logs::set_level(option.0) {
+                Ok(()) => {
+                    info!("Log level set to {}", option.0);
+                    None
+                }
+                Err(e) => {
+                    error!("{:#}", e);
+                    Some(format!("{:#}", e))
+                }
+            };
+            Task::none()
+        }
+
+        SettingsMessage::SetLogRetention(option) => {
+            settings.log.retention_days = option.0;
+            state.status = Some("The new retention applies from the next start".to_string());
+            Task::none()
+        }
+
+        SettingsMessage::SetMaxLogSize(option) => {
+            settings.log.max_size_mb = option.0;
+            let pruned = paths::log_dir()
+                .and_then(|dir| logs::prune_logs(&dir, settings.log.max_size_bytes()));
+            state.status = match pruned {
+                Ok(0) => None,
+                Ok(count) => Some(format!("Deleted {} old log files", count)),
+                Err(e) => {
+                    error!("{:#}", e);
+                    Some(format!("{:#}", e))
+                }
+            };
+            Task::none()
+        }
+
+        SettingsMessage::SetTelemetryEnabled(enabled) => {
+            settings.telemetry.enabled = enabled;
+            info!(
+                "Flash statistics {}",
+                if enabled { "turned on" } else { "turned off" }
+            );
+            Task::none()
+        }
+
+        SettingsMessage::SetTelemetryEndpoint(endpoint) => {
+            settings.telemetry.endpoint = endpoint;
+            Task::none()
+        }
+
+        SettingsMessage::SetTelemetryFormat(format) => {
+            settings.telemetry.format = format;
+            Task::none()
+        }
+
+        SettingsMessage::SetTelemetryStation(station) => {
+            settings.telemetry.station = station;
+            Task::none()
+        }
+
+        SettingsMessage::SetWriteSpeedLimit(option) => {
+            settings.max_write_speed_mb = option.0;
+            info!("Write speed limit set to {}", option);
+            Task::none()
+        }
+
+        SettingsMessage::SetCheckImageBeforeWriting(enabled) => {
+            settings.check_image_before_writing = enabled;
+            Task::none()
+        }
+
+        SettingsMessage::SetNewGuids(mode) => {
+            settings.randomize_guids = mode;
+            info!("GUIDs of flashed disks: {}", mode);
+            Task::none()
+        }
+
+        SettingsMessage::SetConfirmation(mode) => {
+            settings.typed_confirmation = mode;
+            info!("Typed confirmation before erasing: {}", mode);
+            Task::none()
+        }
+
+        SettingsMessage::SetTargetArch(arch) => {
+            settings.target_profile.arch = arch;
+            Task::none()
+        }
+
+        SettingsMessage::SetTargetGpu(family) => {
+            settings.target_profile.gpu_family = family;
+            Task::none()
+        }
+
+        SettingsMessage::SetAlertSound(enabled) => {
+            settings.alerts.sound = enabled;
+            if enabled {
+                // So the volume can be set while the setting is in view
+                crate::ui::alerts::play(crate::ui::alerts::Outcome::Success);
+            }
+            Task::none()
+        }
+
+        SettingsMessage::SetAlertFlash(enabled) => {
+            settings.alerts.flash_window = enabled;
+            Task::none()
+        }
+
+        SettingsMessage::SetRuleAction(action) => {
+            state.rule_draft.action = action;
+            Task::none()
+        }
+
+        SettingsMessage::SetRuleSerial(serial) => {
+            state.rule_draft.serial = serial;
+            Task::none()
+        }
+
+        SettingsMessage::SetRuleVendor(vendor) => {
+            state.rule_draft.vendor = vendor;
+            Task::none()
+        }
+
+        SettingsMessage::SetRulePath(path) => {
+            state.rule_draft.path = path;
+            Task::none()
+        }
+
+        SettingsMessage::SetRuleMinSize(size) => {
+            state.rule_draft.min_size_gb = size;
+            Task::none()
+        }
+
+        SettingsMessage::SetRuleMaxSize(size) => {
+            state.rule_draft.max_size_gb = size;
+            Task::none()
+        }
+
+        SettingsMessage::SetRuleRemovable(option) => {
+            state.rule_draft.removable = option.0;
+            Task::none()
+        }
+
+        SettingsMessage::AddDeviceRule => {
+            match state.rule_draft.to_rule() {
+                Ok(rule) => {
+                    info!("Added device rule: {} {}", rule.action, rule.describe());
+                    settings.device_rules.push(rule);
+                    device_rules::set_active(settings.device_rules.clone());
+                    state.rule_draft = Default::default();
+                    state.status = Some("Device rules apply from the next device scan".to_string());
+                }
+                Err(e) => state.status = Some(e),
+            }
+            Task::none()
+        }
+
+        SettingsMessage::RemoveDeviceRule(index) => {
+            if index < settings.device_rules.len() {
+                let rule = settings.device_rules.remove(index);
+                info!("Removed device rule: {} {}", rule.action, rule.describe());
+                device_rules::set_active(settings.device_rules.clone());
+                state.status = Some("Device rules apply from the next device scan".to_string());
+            }
+            Task::none()
+        }
+
+        SettingsMessage::SetDeviceNickname(serial, nickname) => {
+            device_nicknames::set_nickname(&mut settings.device_nicknames, &serial, &nickname);
+            info!("Device {} is now called {:?}", serial, nickname.trim());
+            Task::none()
+        }
+
+        SettingsMessage::RemoveDeviceNickname(serial) => {
+            if let Some(nickname) = settings.device_nicknames.remove(&serial) {
+                info!("Forgot the nickname {} of device {}", nickname, serial);
+            }
+            Task::none()
+        }
+
+        SettingsMessage::SetCheckForUpdates(enabled) => {
+            Task::done(Message::SetCheckForUpdates(enabled))
+        }
+
+        SettingsMessage::SetSharedPresetsUrl(url) => {
+            settings.shared_presets.url = url;
+            Task::none()
+        }
+
+        SettingsMessage::SetSharedPresetsSha256(sha256) => {
+            settings.shared_presets.sha256 = sha256;
+            Task::none()
+        }
+
+        SettingsMessage::SetSharedPresetsKey(key) => {
+            settings.shared_presets.public_key = key;
+            Task::none()
+        }
+
+        SettingsMessage::FetchSharedPresets => {
+            Task::done(Message::LoadSharedPresets)
+        }
+
+        SettingsMessage::OpenLogFolder => {
+            if let Err(e) = paths::log_dir().and_then(|dir| crate::utils::desktop::open_path(&dir))
+            {
+                error!("{:#}", e);
+                state.status = Some(format!("{:#}", e));
+            }
+            Task::none()
+        }
+
+        SettingsMessage::BackToMainMenu => Task::done(Message::BackToMainMenu),
+    }
+}
@@ -0,0 +1,42 @@
+use super::{LogSizeOption, RemovableOption, RetentionOption, VerbosityOption, WriteSpeedOption};
+use crate::disk::GuidRandomization;
+use crate::utils::app_settings::TypedConfirmation;
+use crate::utils::device_rules::RuleAction;
+use crate::utils::telemetry::TelemetryFormat;
+
+#[derive(Debug, Clone)]
+pub enum SettingsMessage {
+    SetLogLevel(VerbosityOption),         // Applied right away
+    SetLogRetention(RetentionOption),     // Takes effect at the next start
+    SetMaxLogSize(LogSizeOption),         // Older log files are deleted right away
+    SetTelemetryEnabled(bool),            // Send statistics of finished flashes
+    SetTelemetryEndpoint(String),         // URL the statistics are sent to
+    SetTelemetryFormat(TelemetryFormat),  // Plain JSON or OTLP metrics
+    SetTelemetryStation(String),          // Name of this station in the statistics
+    SetCheckForUpdates(bool),             // Look for new releases at startup
+    SetSharedPresetsUrl(String),          // Where the team's presets are fetched from
+    SetSharedPresetsSha256(String),       // SHA-256 the collection is pinned to
+    SetSharedPresetsKey(String),          // Minisign key the collection is signed with
+    FetchSharedPresets,                   // Fetch the collection again now
+    SetWriteSpeedLimit(WriteSpeedOption), // Applies from the next flash
+    SetCheckImageBeforeWriting(bool),     // Hash picked image files before erasing a device
+    SetNewGuids(GuidRandomization),       // GUIDs replaced on flashed disks
+    SetConfirmation(TypedConfirmation),   // Devices to name before erasing them
+    SetTargetArch(String),                // Architecture images are checked against
+    SetTargetGpu(String),                 // GPU family images are checked against
+    SetAlertSound(bool),                  // Play a sound at the end of a flash
+    SetAlertFlash(bool),                  // Flash the window at the end of a flash
+    SetRuleAction(RuleAction),            // Hide matching devices or only allow them
+    SetRuleSerial(String),                // Serial number of the rule being entered
+    SetRuleVendor(String),                // Vendor or model pattern of the rule
+    SetRulePath(String),                  // Device path pattern of the rule
+    SetRuleMinSize(String),               // Smallest size in GB of the rule
+    SetRuleMaxSize(String),               // Largest size in GB of the rule
+    SetRuleRemovable(RemovableOption),    // Kind of device the rule is limited to
+    AddDeviceRule,                        // Save the rule being entered
+    RemoveDeviceRule(usize),              // Delete the rule at this index
+    SetDeviceNickname(String, String),    // Serial number and its nickname, removed if blank
+    RemoveDeviceNickname(String),         // Forget the nickname of this serial number
+    OpenLogFolder,                        // Show the log files in the file manager
+    BackToMainMenu,                       // Return to main menu
+}
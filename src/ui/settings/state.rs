@@ -0,0 +1,195 @@
+use crate::disk::GuidRandomization;
+use crate::utils::app_settings::TypedConfirmation;
+use crate::utils::device_rules::{DeviceRule, RuleAction};
+use crate::utils::logs::LogLevel;
+use crate::utils::telemetry::TelemetryFormat;
+use std::fmt;
+
+/// Formats flash statistics can be sent in
+pub static TELEMETRY_FORMAT_OPTIONS: [TelemetryFormat; 2] =
+    [TelemetryFormat::Json, TelemetryFormat::Otlp];
+
+/// GUIDs that can be replaced on flashed disks
+pub static GUID_RANDOMIZATION_OPTIONS: [GuidRandomization; 3] = [
+    GuidRandomization::Keep,
+    GuidRandomization::Disk,
+    GuidRandomization::DiskAndPartitions,
+];
+
+/// Devices that can be made to need their name typed before erasing
+pub static TYPED_CONFIRMATION_OPTIONS: [TypedConfirmation; 3] = [
+    TypedConfirmation::Never,
+    TypedConfirmation::NonRemovable,
+    TypedConfirmation::Always,
+];
+
+/// Actions of device rules
+pub static RULE_ACTION_OPTIONS: [RuleAction; 2] = [RuleAction::Deny, RuleAction::Allow];
+
+/// Kinds of device a rule can be limited to
+pub static REMOVABLE_OPTIONS: [RemovableOption; 3] = [
+    RemovableOption(None),
+    RemovableOption(Some(true)),
+    RemovableOption(Some(false)),
+];
+
+/// Log levels offered on the settings screen
+pub static VERBOSITY_OPTIONS: [VerbosityOption; 5] = [
+    VerbosityOption(LogLevel::Error),
+    VerbosityOption(LogLevel::Warn),
+    VerbosityOption(LogLevel::Info),
+    VerbosityOption(LogLevel::Debug),
+    VerbosityOption(LogLevel::Trace),
+];
+
+/// Number of days the log files are kept for
+pub static RETENTION_OPTIONS: [RetentionOption; 6] = [
+    RetentionOption(1),
+    RetentionOption(3),
+    RetentionOption(5),
+    RetentionOption(7),
+    RetentionOption(14),
+    RetentionOption(30),
+];
+
+/// Limits of the space taken by the log files, in MB
+pub static LOG_SIZE_OPTIONS: [LogSizeOption; 6] = [
+    LogSizeOption(10),
+    LogSizeOption(50),
+    LogSizeOption(100),
+    LogSizeOption(250),
+    LogSizeOption(500),
+    LogSizeOption(1024),
+];
+
+/// Write speed limits in MB/s
+pub static WRITE_SPEED_OPTIONS: [WriteSpeedOption; 7] = [
+    WriteSpeedOption(None),
+    WriteSpeedOption(Some(5)),
+    WriteSpeedOption(Some(10)),
+    WriteSpeedOption(Some(20)),
+    WriteSpeedOption(Some(50)),
+    WriteSpeedOption(Some(100)),
+    WriteSpeedOption(Some(200)),
+];
+
+/// Log level as shown in the level picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerbosityOption(pub LogLevel);
+
+impl fmt::Display for VerbosityOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            LogLevel::Error => write!(f, "Errors only"),
+            LogLevel::Warn => write!(f, "Warnings and errors"),
+            LogLevel::Info => write!(f, "Normal"),
+            LogLevel::Debug => write!(f, "Detailed"),
+            LogLevel::Trace => write!(f, "Everything"),
+        }
+    }
+}
+
+/// Log retention as shown in the retention picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionOption(pub u32);
+
+impl fmt::Display for RetentionOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            1 => write!(f, "1 day"),
+            days => write!(f, "{} days", days),
+        }
+    }
+}
+
+/// Log size limit in MB as shown in the size picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogSizeOption(pub u64);
+
+impl fmt::Display for LogSizeOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 >= 1024 && self.0 % 1024 == 0 {
+            write!(f, "{} GB", self.0 / 1024)
+        } else {
+            write!(f, "{} MB", self.0)
+        }
+    }
+}
+
+/// Write speed limit in MB/s as shown in the speed picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteSpeedOption(pub Option<u64>);
+
+impl fmt::Display for WriteSpeedOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => write!(f, "Unlimited"),
+            Some(mb) => write!(f, "{} MB/s", mb),
+        }
+    }
+}
+
+/// Removable criterion as shown in the device kind picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemovableOption(pub Option<bool>);
+
+impl fmt::Display for RemovableOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => write!(f, "Any device"),
+            Some(true) => write!(f, "Removable only"),
+            Some(false) => write!(f, "Fixed only"),
+        }
+    }
+}
+
+/// Device rule being entered, as typed
+#[derive(Debug, Clone, Default)]
+pub struct RuleDraft {
+    pub action: RuleAction,
+    pub serial: String,
+    pub vendor: String,
+    pub path: String,
+    pub min_size_gb: String,
+    pub max_size_gb: String,
+    pub removable: Option<bool>,
+}
+
+impl RuleDraft {
+    /// The rule the draft describes, with empty fields left out
+    pub fn to_rule(&self) -> Result<DeviceRule, String> {
+        let text = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+        let size = |value: &str, name: &str| match value.trim() {
+            "" => Ok(None),
+            value => value
+                .parse::<u64>()
+                .map(Some)
+                .map_err(|_| format!("The {} size must be a whole number of GB", name)),
+        };
+        let rule = DeviceRule {
+            action: self.action,
+            serial: text(&self.serial),
+            vendor: text(&self.vendor),
+            path: text(&self.path),
+            min_size_gb: size(&self.min_size_gb, "smallest")?,
+            max_size_gb: size(&self.max_size_gb, "largest")?,
+            removable: self.removable,
+        };
+        if !rule.has_criteria() {
+            return Err("Enter at least one criterion for the rule".to_string());
+        }
+        Ok(rule)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SettingsState {
+    pub status: Option<String>, // Outcome of the last change, e.g. deleted log files
+    pub rule_draft: RuleDraft,  // Device rule being entered
+}
+
+impl SettingsState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,530 @@
+use super::{
+    GUID_RANDOMIZATION_OPTIONS, LOG_SIZE_OPTIONS, LogSizeOption, REMOVABLE_OPTIONS,
+    RETENTION_OPTIONS, RULE_ACTION_OPTIONS, RemovableOption, RetentionOption, RuleDraft,
+    SettingsMessage, TELEMETRY_FORMAT_OPTIONS, TYPED_CONFIRMATION_OPTIONS, VERBOSITY_OPTIONS,
+    VerbosityOption, WRITE_SPEED_OPTIONS, WriteSpeedOption,
+};
+use crate::style;
+use crate::ui::icons;
+use crate::utils::app_settings::AppSettings;
+use crate::utils::compatibility::TargetProfile;
+use crate::utils::device_rules::RuleAction;
+use crate::utils::{logs, paths};
+use iced::widget::{
+    button, checkbox, column, container, pick_list, row, scrollable, text, text_input,
+};
+use iced::{Alignment, Color, Element, Length};
+
+/// Main settings view
+pub fn view_settings<'a>(
+    settings: &'a AppSettings,
+    rule_draft: &'a RuleDraft,
+    status: Option<&'a str>,
+) -> Element<'a, SettingsMessage> {
+    let header = container(
+        column![
+            text("Settings").size(28),
+            text(match paths::portable_root() {
+                Some(root) => format!("Portable mode, files are kept in {}", root.display()),
+                None => "Preferences of the imager itself, saved as they are changed".to_string(),
+            })
+            .size(14)
+            .color(Color::from_rgb(0.7, 0.7, 0.8))
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(style::page_header);
+
+    let mut logging = column![
+        text("Logging").size(18),
+        setting_row(
+            "Log level",
+            pick_list(
+                &VERBOSITY_OPTIONS[..],
+                Some(VerbosityOption(settings.log.level)),
+                SettingsMessage::SetLogLevel
+            )
+            .style(style::pick_list_style)
+            .into()
+        ),
+        setting_row(
+            "Keep log files for",
+            pick_list(
+                &RETENTION_OPTIONS[..],
+                Some(RetentionOption(settings.log.retention_days)),
+                SettingsMessage::SetLogRetention
+            )
+            .style(style::pick_list_style)
+            .into()
+        ),
+        setting_row(
+            "Space for log files",
+            pick_list(
+                &LOG_SIZE_OPTIONS[..],
+                Some(LogSizeOption(settings.log.max_size_mb)),
+                SettingsMessage::SetMaxLogSize
+            )
+            .style(style::pick_list_style)
+            .into()
+        ),
+    ]
+    .spacing(12);
+    if logs::level_overridden() {
+        logging = logging.push(
+            row![
+                icons::info().color(Color::from_rgb(0.8, 0.8, 0.6)),
+                text("RUST_LOG is set and takes precedence over the log level")
+                    .size(12)
+                    .color(Color::from_rgb(0.8, 0.8, 0.6))
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        );
+    }
+    logging = logging.push(
+        button(
+            row![icons::storage(), "Open Log Folder"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press(SettingsMessage::OpenLogFolder)
+        .padding(8)
+        .style(button::secondary),
+    );
+
+    let telemetry_settings = &settings.telemetry;
+    let mut telemetry = column![
+        text("Flash Statistics").size(18),
+        text(
+            "For monitoring fleet provisioning: the duration, speed and outcome of each flash \
+             are sent to your own collector. Wallet addresses, serial numbers and other \
+             configuration are never included."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        checkbox("Send flash statistics", telemetry_settings.enabled)
+            .on_toggle(SettingsMessage::SetTelemetryEnabled)
+            .size(16),
+    ]
+    .spacing(12);
+    if telemetry_settings.enabled {
+        telemetry = telemetry
+            .push(setting_row(
+                "Endpoint",
+                text_input(
+                    "https://collector.example.com:4318",
+                    &telemetry_settings.endpoint,
+                )
+                .on_input(SettingsMessage::SetTelemetryEndpoint)
+                .padding(8)
+                .width(Length::FillPortion(2))
+                .into(),
+            ))
+            .push(setting_row(
+                "Format",
+                pick_list(
+                    &TELEMETRY_FORMAT_OPTIONS[..],
+                    Some(telemetry_settings.format),
+                    SettingsMessage::SetTelemetryFormat,
+                )
+                .style(style::pick_list_style)
+                .into(),
+            ))
+            .push(setting_row(
+                "Station name",
+                text_input("Host name", &telemetry_settings.station)
+                    .on_input(SettingsMessage::SetTelemetryStation)
+                    .padding(8)
+                    .width(Length::FillPortion(2))
+                    .into(),
+            ));
+        if telemetry_settings.endpoint.trim().is_empty() {
+            telemetry = telemetry.push(
+                text("Nothing is sent until an endpoint is entered")
+                    .size(12)
+                    .color(Color::from_rgb(0.8, 0.8, 0.6)),
+            );
+        }
+    }
+
+    // Placeholders show what an empty field stands for
+    let local_target = TargetProfile::default().resolve();
+    let local_gpus = match local_target.gpu_families.join(", ") {
+        families if families.is_empty() => "Any".to_string(),
+        families => families,
+    };
+
+    let writing = column![
+        text("Writing").size(18),
+        setting_row(
+            "Maximum write speed",
+            pick_list(
+                &WRITE_SPEED_OPTIONS[..],
+                Some(WriteSpeedOption(settings.max_write_speed_mb)),
+                SettingsMessage::SetWriteSpeedLimit
+            )
+            .style(style::pick_list_style)
+            .into()
+        ),
+        text(
+            "Limiting the speed keeps fragile USB hubs stable and leaves bandwidth for other \
+             work, but makes flashing take longer."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        checkbox(
+            "Check the image file before erasing the device",
+            settings.check_image_before_writing
+        )
+        .on_toggle(SettingsMessage::SetCheckImageBeforeWriting)
+        .size(16),
+        text(
+            "The downloaded image is decompressed and hashed in the background once it is \
+             picked, so a damaged file is found before anything is erased."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        setting_row(
+            "GPT GUIDs",
+            pick_list(
+                &GUID_RANDOMIZATION_OPTIONS[..],
+                Some(settings.randomize_guids),
+                SettingsMessage::SetNewGuids
+            )
+            .style(style::pick_list_style)
+            .into()
+        ),
+        text(
+            "Disks flashed from the same image share its disk GUID, and Windows sets a second \
+             one offline while the first is attached. New partition GUIDs stop images that \
+             find their root partition by PARTUUID from booting; the configuration partition \
+             always keeps its GUID."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        setting_row(
+            "Type the device name to erase",
+            pick_list(
+                &TYPED_CONFIRMATION_OPTIONS[..],
+                Some(settings.typed_confirmation),
+                SettingsMessage::SetConfirmation
+            )
+            .style(style::pick_list_style)
+            .into()
+        ),
+        text(
+            "The erase button stays disabled until the device's name or size is typed, so a \
+             wrong disk isn't erased with a single click."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        setting_row(
+            "Target architecture",
+            text_input(&local_target.arch, &settings.target_profile.arch)
+                .on_input(SettingsMessage::SetTargetArch)
+                .padding(8)
+                .width(Length::FillPortion(2))
+                .into()
+        ),
+        setting_row(
+            "Target GPU family",
+            text_input(&local_gpus, &settings.target_profile.gpu_family)
+                .on_input(SettingsMessage::SetTargetGpu)
+                .padding(8)
+                .width(Length::FillPortion(2))
+                .into()
+        ),
+        text(
+            "Images whose manifest names another architecture or GPU family are warned about \
+             before flashing. Leave a field empty to check against this computer."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+    ]
+    .spacing(12);
+
+    let mut device_rules = column![
+        text("Device Rules").size(18),
+        text(
+            "Keep devices such as internal drives out of the device lists. Once there is an \
+             \"Only allow\" rule, only devices matching one of them are listed. Hidden devices \
+             are also refused when a flash or edit names them."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+    ]
+    .spacing(12);
+    for (index, rule) in settings.device_rules.iter().enumerate() {
+        let icon = match rule.action {
+            RuleAction::Deny => icons::block().color(Color::from_rgb(0.9, 0.3, 0.3)),
+            RuleAction::Allow => icons::check_circle().color(Color::from_rgb(0.0, 0.8, 0.3)),
+        };
+        device_rules = device_rules.push(
+            row![
+                icon,
+                text(format!("{}: {}", rule.action, rule.describe()))
+                    .size(14)
+                    .width(Length::Fill),
+                button(icons::delete())
+                    .on_press(SettingsMessage::RemoveDeviceRule(index))
+                    .padding(6)
+                    .style(button::danger),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+    }
+    device_rules = device_rules
+        .push(setting_row(
+            "Action",
+            pick_list(
+                &RULE_ACTION_OPTIONS[..],
+                Some(rule_draft.action),
+                SettingsMessage::SetRuleAction,
+            )
+            .style(style::pick_list_style)
+            .into(),
+        ))
+        .push(setting_row(
+            "Serial number",
+            text_input("Any", &rule_draft.serial)
+                .on_input(SettingsMessage::SetRuleSerial)
+                .padding(8)
+                .width(Length::FillPortion(2))
+                .into(),
+        ))
+        .push(setting_row(
+            "Vendor or model",
+            text_input("e.g. Samsung*", &rule_draft.vendor)
+                .on_input(SettingsMessage::SetRuleVendor)
+                .padding(8)
+                .width(Length::FillPortion(2))
+                .into(),
+        ))
+        .push(setting_row(
+            "Device path",
+            text_input("e.g. /dev/nvme*", &rule_draft.path)
+                .on_input(SettingsMessage::SetRulePath)
+                .padding(8)
+                .width(Length::FillPortion(2))
+                .into(),
+        ))
+        .push(setting_row(
+            "Size in GB",
+            row![
+                text_input("From", &rule_draft.min_size_gb)
+                    .on_input(SettingsMessage::SetRuleMinSize)
+                    .padding(8),
+                text("to").size(14),
+                text_input("To", &rule_draft.max_size_gb)
+                    .on_input(SettingsMessage::SetRuleMaxSize)
+                    .padding(8),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center)
+            .width(Length::FillPortion(2))
+            .into(),
+        ))
+        .push(setting_row(
+            "Device kind",
+            pick_list(
+                &REMOVABLE_OPTIONS[..],
+                Some(RemovableOption(rule_draft.removable)),
+                SettingsMessage::SetRuleRemovable,
+            )
+            .style(style::pick_list_style)
+            .into(),
+        ))
+        .push(
+            button(
+                row![icons::add(), "Add Rule"]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press(SettingsMessage::AddDeviceRule)
+            .padding(8)
+            .style(button::secondary),
+        );
+
+    let mut nicknames = column![
+        text("Device Nicknames").size(18),
+        text(
+            "Devices are renamed on their card in the flash workflow and keep their nickname \
+             by serial number, whatever path they are found at."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+    ]
+    .spacing(12);
+    for (serial, nickname) in &settings.device_nicknames {
+        nicknames = nicknames.push(
+            row![
+                icons::edit(),
+                text(nickname).size(14).width(Length::FillPortion(2)),
+                text(format!("S/N {}", serial))
+                    .size(12)
+                    .color(Color::from_rgb(0.7, 0.7, 0.7))
+                    .width(Length::FillPortion(2)),
+                button(icons::delete())
+                    .on_press(SettingsMessage::RemoveDeviceNickname(serial.clone()))
+                    .padding(6)
+                    .style(button::danger),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    let shared = &settings.shared_presets;
+    let mut shared_presets = column![
+        text("Shared Presets").size(18),
+        text(
+            "Presets published by your team are fetched from this HTTPS URL at startup and \
+             listed read-only next to your own. Pin the collection's SHA-256 or the minisign \
+             key it is signed with so only the collection your team published is used."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        setting_row(
+            "Collection URL",
+            text_input("https://example.com/presets.json", &shared.url)
+                .on_input(SettingsMessage::SetSharedPresetsUrl)
+                .padding(8)
+                .width(Length::FillPortion(2))
+                .into(),
+        ),
+        setting_row(
+            "SHA-256 pin",
+            text_input("Optional", &shared.sha256)
+                .on_input(SettingsMessage::SetSharedPresetsSha256)
+                .padding(8)
+                .width(Length::FillPortion(2))
+                .into(),
+        ),
+        setting_row(
+            "Signing key",
+            text_input("Optional minisign public key", &shared.public_key)
+                .on_input(SettingsMessage::SetSharedPresetsKey)
+                .padding(8)
+                .width(Length::FillPortion(2))
+                .into(),
+        ),
+    ]
+    .spacing(12);
+    if let Some(error) = shared.error() {
+        shared_presets = shared_presets.push(text(error).size(12).color(style::ERROR));
+    }
+    shared_presets = shared_presets.push(
+        button(
+            row![icons::refresh(), "Fetch Now"]
+                .spacing(5)
+                .align_y(Alignment::Center),
+        )
+        .on_press_maybe(
+            shared
+                .error()
+                .is_none()
+                .then_some(SettingsMessage::FetchSharedPresets),
+        )
+        .padding(8)
+        .style(button::secondary),
+    );
+
+    let alerts = column![
+        text("Completion Alerts").size(18),
+        text(
+            "For benches nobody is watching: the end of each flash or device update is \
+             announced even when the window is in view."
+        )
+        .size(12)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+        checkbox("Play a sound", settings.alerts.sound)
+            .on_toggle(SettingsMessage::SetAlertSound)
+            .size(16),
+        checkbox(
+            "Flash the window green or red until it is clicked",
+            settings.alerts.flash_window
+        )
+        .on_toggle(SettingsMessage::SetAlertFlash)
+        .size(16),
+    ]
+    .spacing(12);
+
+    let updates = column![
+        text("Updates").size(18),
+        checkbox("Check for updates at startup", settings.check_for_updates)
+            .on_toggle(SettingsMessage::SetCheckForUpdates)
+            .size(16),
+    ]
+    .spacing(12);
+
+    let mut sections = column![
+        container(logging)
+            .style(style::bordered_box)
+            .padding(15)
+            .width(Length::Fill),
+        container(writing)
+            .style(style::bordered_box)
+            .padding(15)
+            .width(Length::Fill),
+        container(alerts)
+            .style(style::bordered_box)
+            .padding(15)
+            .width(Length::Fill),
+        container(device_rules)
+            .style(style::bordered_box)
+            .padding(15)
+            .width(Length::Fill),
+        container(nicknames)
+            .style(style::bordered_box)
+            .padding(15)
+            .width(Length::Fill),
+        container(telemetry)
+            .style(style::bordered_box)
+            .padding(15)
+            .width(Length::Fill),
+        container(shared_presets)
+            .style(style::bordered_box)
+            .padding(15)
+            .width(Length::Fill),
+        container(updates)
+            .style(style::bordered_box)
+            .padding(15)
+            .width(Length::Fill),
+    ]
+    .spacing(15);
+    if let Some(status) = status {
+        sections = sections.push(text(status).size(13).color(Color::from_rgb(0.8, 0.8, 0.6)));
+    }
+
+    let back_button = button(
+        row![icons::navigate_before(), "Back"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(SettingsMessage::BackToMainMenu)
+    .padding(12)
+    .style(style::navigation_back_button);
+
+    column![
+        header,
+        scrollable(sections).height(Length::Fill),
+        container(back_button).width(Length::Fill).padding([15, 0])
+    ]
+    .spacing(20)
+    .padding(20)
+    .into()
+}
+
+/// A label with the control that changes the setting
+fn setting_row<'a>(
+    label: &'a str,
+    control: Element<'a, SettingsMessage>,
+) -> Element<'a, SettingsMessage> {
+    row![text(label).size(14).width(Length::Fill), control]
+        .spacing(10)
+        .align_y(Alignment::Center)
+        .into()
+}
@@ -0,0 +1,85 @@
+/// Closing the window while a disk is being written
+///
+/// The window close request is handled by the application instead of closing the window
+/// at once. With a write running the user is asked first; if they go ahead the write is
+/// cancelled and the application exits only once the write has stopped and the disk has
+/// been released, so a card is never left half-written without the app noticing.
+use crate::models::OperationId;
+use crate::style;
+use crate::ui::icons;
+use crate::ui::messages::Message;
+use iced::widget::{button, column, container, row, text};
+use iced::{Alignment, Color, Element, Length};
+
+/// Where closing the application is at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExitState {
+    #[default]
+    Running,
+    Confirming,            // Asking whether to cancel the running write
+    Stopping(OperationId), // Exiting once this write has stopped
+}
+
+/// Dialog shown when the window is closed during a write
+pub fn view_exit_dialog<'a>(state: ExitState) -> Element<'a, Message> {
+    let stopping = matches!(state, ExitState::Stopping(_));
+    let (title, details) = if stopping {
+        (
+            "Stopping the Write",
+            "The imager closes as soon as the write has stopped and the disk is released.",
+        )
+    } else {
+        (
+            "A Write Is in Progress",
+            "Closing now cancels the write and leaves the disk unusable until it is flashed again.",
+        )
+    };
+
+    let mut buttons = row![].spacing(15);
+    if !stopping {
+        buttons = buttons.push(
+            button(text("Keep Writing"))
+                .on_press(Message::KeepRunning)
+                .padding(12)
+                .style(button::secondary),
+        );
+        buttons = buttons.push(
+            button(
+                row![icons::cancel(), "Cancel Write and Exit"]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press(Message::CancelAndExit)
+            .padding(12)
+            .style(button::danger),
+        );
+    }
+
+    let dialog_content = column![
+        text(title).size(20),
+        text(details).size(14).color(Color::from_rgb(0.8, 0.8, 0.8)),
+        container(buttons)
+            .width(Length::Fill)
+            .align_x(Alignment::Center)
+    ]
+    .spacing(15)
+    .width(Length::Fill)
+    .max_width(420)
+    .align_x(Alignment::Center);
+
+    // Center the dialog on screen
+    container(
+        container(dialog_content)
+            .style(style::confirmation_dialog)
+            .padding(25)
+            .width(Length::Shrink)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill),
+    )
+    .style(style::modal_overlay)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .into()
+}
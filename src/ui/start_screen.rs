@@ -1,10 +1,11 @@
 use iced::alignment::Horizontal;
 use iced::gradient;
-use iced::widget::{button, column, container, row, svg, text};
-use iced::{Alignment, Background, Border, Color, Element, Length, Shadow, Theme, Vector};
+use iced::widget::{button, column, container, row, scrollable, svg, text};
+use iced::{Alignment, Background, Border, Color, Element, Length, Shadow, Size, Theme, Vector};
 
 use crate::ui::messages::Message;
-use crate::ui::{LOGO_SVG, icons};
+use crate::ui::{LOGO_SVG, icons, layout};
+use crate::utils::updater::UpdateInfo;
 
 // Elegant gradient background styling
 fn elegant_gradient_background() -> impl Fn(&Theme) -> container::Style {
@@ -168,7 +169,9 @@ fn elevation_info_text_style() -> Color {
 }
 
 // Create elevation hero card component
-fn create_elevation_hero_card<'a>() -> Element<'a, Message> {
+fn create_elevation_hero_card<'a>(
+    privilege_mode: crate::utils::PrivilegeMode,
+) -> Element<'a, Message> {
     let shield_icon = icons::shield()
         .size(48)
         .color(Color::from_rgb(0.3, 0.6, 1.0));
@@ -178,7 +181,7 @@ fn create_elevation_hero_card<'a>() -> Element<'a, Message> {
         .color(Color::WHITE)
         .align_x(Horizontal::Center);
 
-    let explanation = text("Administrator access is required to safely write to storage devices")
+    let explanation = text(privilege_mode.explanation())
         .size(14)
         .color(elevation_info_text_style())
         .align_x(Horizontal::Center);
@@ -205,9 +208,16 @@ fn create_elevation_hero_card<'a>() -> Element<'a, Message> {
         .align_x(Horizontal::Center);
 
     container(
-        column![shield_icon, title, explanation, elevation_button, subtext]
-            .spacing(20)
-            .align_x(Alignment::Center),
+        column![
+            shield_icon,
+            title,
+            explanation,
+            elevation_button,
+            subtext,
+            create_helper_button()
+        ]
+        .spacing(20)
+        .align_x(Alignment::Center),
     )
     .style(elevation_hero_card())
     .padding(40)
@@ -219,12 +229,20 @@ fn create_elevation_hero_card<'a>() -> Element<'a, Message> {
 fn create_button_card<'a>(
     flash_button: button::Button<'a, Message>,
     edit_button: button::Button<'a, Message>,
+    update_button: button::Button<'a, Message>,
     presets_button: button::Button<'a, Message>,
+    cache_button: button::Button<'a, Message>,
 ) -> Element<'a, Message> {
     container(
-        column![flash_button, edit_button, presets_button,]
-            .spacing(12)
-            .align_x(Alignment::Center),
+        column![
+            flash_button,
+            edit_button,
+            update_button,
+            presets_button,
+            cache_button,
+        ]
+        .spacing(12)
+        .align_x(Alignment::Center),
     )
     .style(elegant_button_card())
     .padding(20)
@@ -233,7 +251,9 @@ fn create_button_card<'a>(
 }
 
 // Create non-Windows sudo instruction card
-fn create_sudo_instruction_card<'a>() -> Element<'a, Message> {
+fn create_sudo_instruction_card<'a>(
+    privilege_mode: crate::utils::PrivilegeMode,
+) -> Element<'a, Message> {
     let info_icon = icons::info().size(32).color(Color::from_rgb(0.3, 0.6, 1.0));
 
     let title = text("Root Access Required")
@@ -241,10 +261,13 @@ fn create_sudo_instruction_card<'a>() -> Element<'a, Message> {
         .color(Color::WHITE)
         .align_x(Horizontal::Center);
 
-    let explanation = text("Please run this application with sudo to enable disk operations")
-        .size(14)
-        .color(elevation_info_text_style())
-        .align_x(Horizontal::Center);
+    let explanation = text(format!(
+        "{} Please run this application with sudo to enable disk operations.",
+        privilege_mode.explanation()
+    ))
+    .size(14)
+    .color(elevation_info_text_style())
+    .align_x(Horizontal::Center);
 
     let command_text = text("sudo ./golem-gpu-imager")
         .size(16)
@@ -252,9 +275,15 @@ fn create_sudo_instruction_card<'a>() -> Element<'a, Message> {
         .align_x(Horizontal::Center);
 
     container(
-        column![info_icon, title, explanation, command_text]
-            .spacing(16)
-            .align_x(Alignment::Center),
+        column![
+            info_icon,
+            title,
+            explanation,
+            command_text,
+            create_helper_button()
+        ]
+        .spacing(16)
+        .align_x(Alignment::Center),
     )
     .style(elevation_hero_card())
     .padding(32)
@@ -262,18 +291,156 @@ fn create_sudo_instruction_card<'a>() -> Element<'a, Message> {
     .into()
 }
 
+// Create the button that starts the privileged disk helper instead of elevating everything
+fn create_helper_button<'a>() -> button::Button<'a, Message> {
+    button(
+        container(
+            row![
+                icons::shield().size(16),
+                text("Use Privileged Helper").size(14)
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        )
+        .center_x(Length::Fill),
+    )
+    .width(320)
+    .padding(12)
+    .style(elegant_secondary_button())
+    .on_press(Message::StartPrivilegedHelper)
+}
+
+// Create the notice about a newer release
+fn create_update_notice<'a>(update: &'a UpdateInfo) -> Element<'a, Message> {
+    container(
+        row![
+            icons::get_app()
+                .size(24)
+                .color(Color::from_rgb(0.3, 0.6, 1.0)),
+            text(format!("Version {} is available", update.version))
+                .size(14)
+                .color(Color::WHITE)
+                .width(Length::Fill),
+            button(text("What's New").size(12))
+                .on_press(Message::ShowAppUpdate)
+                .padding([6, 10])
+                .style(elegant_secondary_button()),
+            button(text("Later").size(12))
+                .on_press(Message::DismissAppUpdate)
+                .padding([6, 10])
+                .style(button::text),
+        ]
+        .spacing(12)
+        .align_y(Alignment::Center),
+    )
+    .style(elevation_hero_card())
+    .padding(12)
+    .width(Length::Fill)
+    .into()
+}
+
+// Create the notice about flashes waiting in the queue
+fn create_queue_notice<'a>(queued_flashes: usize) -> Element<'a, Message> {
+    container(
+        row![
+            icons::queue()
+                .size(24)
+                .color(Color::from_rgb(0.3, 0.6, 1.0)),
+            text(match queued_flashes {
+                1 => "1 flash is queued".to_string(),
+                n => format!("{} flashes are queued", n),
+            })
+            .size(14)
+            .color(Color::WHITE)
+            .width(Length::Fill),
+            button(text("Open Queue").size(12))
+                .on_press(Message::ManageFlashQueue)
+                .padding([6, 10])
+                .style(elegant_secondary_button()),
+        ]
+        .spacing(12)
+        .align_y(Alignment::Center),
+    )
+    .style(elevation_hero_card())
+    .padding(12)
+    .width(Length::Fill)
+    .into()
+}
+
+// Create the notice about a crash during the last run
+fn create_crash_report_notice<'a>(report: &'a std::path::Path) -> Element<'a, Message> {
+    container(
+        row![
+            icons::warning_amber()
+                .size(24)
+                .color(Color::from_rgb(0.9, 0.6, 0.0)),
+            column![
+                text("Golem GPU Imager closed unexpectedly last time")
+                    .size(14)
+                    .color(Color::WHITE),
+                text(format!("A crash report was saved to {}", report.display()))
+                    .size(12)
+                    .color(elevation_info_text_style()),
+            ]
+            .spacing(4)
+            .width(Length::Fill),
+            button(text("Open Report").size(12))
+                .on_press(Message::OpenCrashReport)
+                .padding([6, 10])
+                .style(elegant_secondary_button()),
+            button(text("Dismiss").size(12))
+                .on_press(Message::DismissCrashReport)
+                .padding([6, 10])
+                .style(button::text),
+        ]
+        .spacing(12)
+        .align_y(Alignment::Center),
+    )
+    .style(elevation_hero_card())
+    .padding(12)
+    .width(Length::Fill)
+    .into()
+}
+
+// Create the note shown when disk access goes through polkit
+fn create_polkit_notice<'a>(privilege_mode: crate::utils::PrivilegeMode) -> Element<'a, Message> {
+    container(
+        row![
+            icons::shield()
+                .size(16)
+                .color(Color::from_rgb(0.3, 0.6, 1.0)),
+            text(privilege_mode.explanation())
+                .size(12)
+                .color(elevation_info_text_style())
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center),
+    )
+    .max_width(420)
+    .padding(8)
+    .into()
+}
+
 pub fn view_start_screen<'a>(
     error_message: Option<&'a str>,
-    is_elevated: bool,
+    privilege_mode: crate::utils::PrivilegeMode,
     _elevation_status: &'a str,
+    crash_report: Option<&'a std::path::Path>,
+    update_notice: Option<&'a UpdateInfo>,
+    queued_flashes: usize,
+    window_size: Size,
 ) -> Element<'a, Message> {
+    // Low windows get a smaller logo and scroll instead of spacing out the content
+    let compact = layout::is_compact(window_size);
+
     // Create the logo widget with subtle direct glow
+    let logo_size = if compact { 96 } else { 160 };
     let logo = svg::Svg::new(svg::Handle::from_memory(LOGO_SVG))
-        .width(160)
-        .height(160);
+        .width(logo_size)
+        .height(logo_size);
 
     let title = text("Golem GPU Imager")
-        .size(38)
+        .size(if compact { 30 } else { 38 })
         .width(Length::Fill)
         .align_x(Horizontal::Center)
         .color(Color::from_rgb(0.95, 0.95, 0.95));
@@ -286,8 +453,8 @@ pub fn view_start_screen<'a>(
     .align_x(Horizontal::Center)
     .color(Color::from_rgb(0.7, 0.7, 0.8));
 
-    // Create buttons - hide on Windows if not elevated
-    let buttons_enabled = if cfg!(windows) { is_elevated } else { true };
+    // Create buttons - hide when disks can't be accessed at all
+    let buttons_enabled = privilege_mode.can_access_disks();
 
     // Only create buttons when they will be functional
     let flash_button = if buttons_enabled {
@@ -326,6 +493,27 @@ pub fn view_start_screen<'a>(
         button(text(""))
     };
 
+    let update_button = if buttons_enabled {
+        button(
+            container(
+                iced::widget::row![
+                    icons::get_app().size(20),
+                    text("Update Golem Device").size(16)
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+            .center_x(Length::Fill),
+        )
+        .width(320)
+        .padding(16)
+        .style(elegant_secondary_button())
+        .on_press(Message::UpdateExistingDevice)
+    } else {
+        // Placeholder button that won't be used
+        button(text(""))
+    };
+
     let presets_button = if buttons_enabled {
         button(
             container(
@@ -344,6 +532,27 @@ pub fn view_start_screen<'a>(
         button(text(""))
     };
 
+    let cache_button = if buttons_enabled {
+        button(
+            container(
+                iced::widget::row![
+                    icons::storage().size(20),
+                    text("Manage Image Cache").size(16)
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+            .center_x(Length::Fill),
+        )
+        .width(320)
+        .padding(16)
+        .style(elegant_secondary_button())
+        .on_press(Message::ManageCache)
+    } else {
+        // Placeholder button that won't be used
+        button(text(""))
+    };
+
     // Error message container (only shown if error_message is Some)
     let error_container = if let Some(error) = error_message {
         let error_column = column![
@@ -397,13 +606,28 @@ pub fn view_start_screen<'a>(
     // Conditional main action area
     let main_action_area = if buttons_enabled {
         // Show normal button card
-        create_button_card(flash_button, edit_button, presets_button)
+        let card = create_button_card(
+            flash_button,
+            edit_button,
+            update_button,
+            presets_button,
+            cache_button,
+        );
+        if privilege_mode == crate::utils::PrivilegeMode::Polkit {
+            // Let the user know why a password prompt will show up
+            column![card, create_polkit_notice(privilege_mode)]
+                .spacing(12)
+                .align_x(Alignment::Center)
+                .into()
+        } else {
+            card
+        }
     } else if cfg!(windows) {
         // Show elevation hero card (replaces button area)
-        create_elevation_hero_card()
+        create_elevation_hero_card(privilege_mode)
     } else {
         // Non-Windows fallback - show sudo message
-        create_sudo_instruction_card()
+        create_sudo_instruction_card(privilege_mode)
     };
 
     // Add version and build time info with elegant styling
@@ -412,9 +636,26 @@ pub fn view_start_screen<'a>(
         crate::version::VERSION,
         crate::version::BUILD_TIME
     );
-    let version_text = text(version_info)
-        .size(12)
-        .color(Color::from_rgb(0.5, 0.5, 0.6));
+    let version_text = row![
+        text(version_info)
+            .size(12)
+            .color(Color::from_rgb(0.5, 0.5, 0.6)),
+        // Always available, so problems with disk access can be looked into
+        button(text("View Logs").size(12))
+            .on_press(Message::ViewLogs)
+            .padding([2, 6])
+            .style(button::text),
+        button(text("Check for Updates").size(12))
+            .on_press(Message::CheckForAppUpdate(true))
+            .padding([2, 6])
+            .style(button::text),
+        button(text("Settings").size(12))
+            .on_press(Message::OpenSettings)
+            .padding([2, 6])
+            .style(button::text),
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center);
 
     // Main content column
     let mut content_items = vec![
@@ -428,18 +669,39 @@ pub fn view_start_screen<'a>(
         content_items.push(error_container.into());
     }
 
-    content_items.extend([
-        container(iced::widget::row![]).height(Length::Fill).into(),
-        main_action_area,
-        container(column![]).height(Length::Fill).into(),
-        version_text.into(),
-    ]);
+    if let Some(update) = update_notice {
+        content_items.push(create_update_notice(update));
+    }
+
+    if let Some(report) = crash_report {
+        content_items.push(create_crash_report_notice(report));
+    }
+
+    if queued_flashes > 0 && buttons_enabled {
+        content_items.push(create_queue_notice(queued_flashes));
+    }
+
+    if compact {
+        content_items.extend([main_action_area, version_text.into()]);
+    } else {
+        content_items.extend([
+            container(iced::widget::row![]).height(Length::Fill).into(),
+            main_action_area,
+            container(column![]).height(Length::Fill).into(),
+            version_text.into(),
+        ]);
+    }
 
     let content = column(content_items)
         .width(Length::Fill)
         .spacing(16)
         .align_x(Alignment::Center)
         .padding(20);
+    let content: Element<'a, Message> = if compact {
+        scrollable(content).height(Length::Fill).into()
+    } else {
+        content.into()
+    };
 
     // Main container with elegant gradient background
     container(content)
@@ -0,0 +1,240 @@
+// Scripted runs of the application's update loop
+//
+// A `Harness` hands messages to `GolemGpuImager::update` the way the iced runtime does and
+// feeds the output of the returned tasks back in. Only tasks that finish without waiting
+// are run: reading and writing the disks of the fake disk backend completes at once, while
+// downloads, spawned threads and the sippers of a write would wait and are dropped. The
+// test plays their part by sending the messages they would have produced, such as write
+// progress. Device scans are answered with the attached disks, exiting is recorded instead
+// of ending the test, and `snapshot` renders the state the screens are drawn from, so a test
+// reads as a script of steps and screens.
+
+use crate::disk::test_support::{FakeBackend, InstalledBackend};
+use crate::ui::GolemGpuImager;
+use crate::ui::device_selection::{DeviceMessage, GolemProbe, StorageDevice};
+use crate::ui::edit_workflow::EditWorkflowState;
+use crate::ui::flash_workflow::{FlashWorkflowState, ImageMetadata, OsImage, OsImageGroup};
+use crate::ui::messages::Message;
+use crate::ui::shutdown::ExitState;
+use crate::utils::app_settings::AppSettings;
+use crate::utils::flash_history::FlashHistory;
+use futures_util::{FutureExt, StreamExt};
+use iced::Task;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// The application with fake disks, driven one message at a time
+pub struct Harness {
+    pub app: GolemGpuImager,
+    pub backend: Arc<FakeBackend>,
+    pub exited: bool, // The application asked to exit, nothing is handled after that
+    devices: Vec<StorageDevice>,
+    _installed: InstalledBackend,
+}
+
+impl Harness {
+    /// The application on the start screen with default settings and presets
+    pub fn new() -> Self {
+        let backend = Arc::new(FakeBackend::default());
+        let installed = FakeBackend::install(&backend);
+        Harness {
+            app: GolemGpuImager::from_parts(
+                AppSettings::default(),
+                None,
+                None,
+                FlashHistory::default(),
+                None,
+            ),
+            backend,
+            exited: false,
+            devices: Vec::new(),
+            _installed: installed,
+        }
+    }
+
+    /// Attach a removable card holding `contents`, listed from the next device scan on
+    pub fn attach(&mut self, path: &str, name: &str, contents: &[u8]) {
+        self.backend.attach(path, contents);
+        self.devices.push(StorageDevice {
+            name: name.to_string(),
+            path: path.to_string(),
+            size: format!("{:.2} GB", contents.len() as f64 / 1e9),
+            size_bytes: contents.len() as u64,
+            is_card: true,
+            is_usb: false,
+            is_scsi: false,
+            is_removable: true,
+            read_only: false,
+            health: crate::disk::DiskHealth::unknown(),
+            golem: GolemProbe::Pending,
+            identity: Default::default(),
+            nickname: None,
+        });
+    }
+
+    /// Give the attached card at `path` a serial number, from the next device scan on
+    pub fn set_serial(&mut self, path: &str, serial: &str) {
+        for device in self.devices.iter_mut().filter(|device| device.path == path) {
+            device.identity.serial = Some(serial.to_string());
+        }
+    }
+
+    /// Handle `message` and the messages its tasks produce without waiting
+    pub fn send(&mut self, message: Message) {
+        let mut queue = VecDeque::from([message]);
+        while let Some(message) = queue.pop_front() {
+            if self.exited {
+                return;
+            }
+            let message = match message {
+                Message::DeviceSelection(DeviceMessage::RefreshDevices) => {
+                    Message::DeviceSelection(DeviceMessage::DevicesLoaded(self.devices.clone()))
+                }
+                Message::Exit => {
+                    self.exited = true;
+                    return;
+                }
+                message => message,
+            };
+            queue.extend(ready_messages(self.app.update(message)));
+        }
+    }
+
+    /// Handle each of `messages` in turn
+    pub fn send_all(&mut self, messages: impl IntoIterator<Item = Message>) {
+        for message in messages {
+            self.send(message);
+        }
+    }
+
+    /// The current screen and the state shown on it, one `name: value` line each
+    pub fn snapshot(&self) -> String {
+        let app = &self.app;
+        let mut lines = vec![format!("mode: {:?}", app.mode)];
+
+        if let Some(flash) = &app.flash_workflow {
+            let screen = match &flash.workflow_state {
+                FlashWorkflowState::ProcessingImage { version_id, .. } => {
+                    format!("ProcessingImage ({})", version_id)
+                }
+                FlashWorkflowState::ClearingPartitions { message, .. } => {
+                    format!("ClearingPartitions ({})", message)
+                }
+                FlashWorkflowState::Flashing(phase) => {
+                    format!("Flashing ({})", phase.description())
+                }
+                screen => format!("{:?}", screen),
+            };
+            lines.push(format!("flash: {}", screen));
+            if let Some(image) = flash.selected_image() {
+                lines.push(format!("image: {} {}", image.name, image.version));
+            }
+            if let Some(device) = flash
+                .selected_device
+                .and_then(|index| app.device_selection.devices.get(index))
+            {
+                lines.push(format!("target: {}", device.path));
+            }
+        }
+
+        if let Some(edit) = &app.edit_workflow {
+            lines.push(format!("edit: {:?}", edit.workflow_state));
+            if let Some(device) = edit
+                .selected_device
+                .and_then(|index| app.device_selection.devices.get(index))
+            {
+                lines.push(format!("device: {}", device.path));
+            }
+        }
+
+        let configuring = app.flash_workflow.as_ref().is_some_and(|flash| {
+            !matches!(
+                flash.workflow_state,
+                FlashWorkflowState::SelectOsImage
+                    | FlashWorkflowState::ProcessingImage { .. }
+                    | FlashWorkflowState::SelectTargetDevice
+            )
+        }) || app.edit_workflow.as_ref().is_some_and(|edit| {
+            !matches!(
+                edit.workflow_state,
+                EditWorkflowState::SelectDevice | EditWorkflowState::LoadingConfiguration
+            )
+        });
+        if configuring {
+            let config = &app.configuration;
+            lines.push(format!(
+                "configuration: {} {}, subnet {}, wallet {:?}",
+                config.payment_network, config.network_type, config.subnet, config.wallet_address
+            ));
+        }
+
+        if let Some(kiosk) = &app.kiosk {
+            lines.push(format!(
+                "kiosk: {:?}, {} flashed, {} failed",
+                kiosk.status, kiosk.succeeded, kiosk.failed
+            ));
+        }
+
+        if let Some(error) = &app.error_message {
+            lines.push(format!("error: {}", error));
+        }
+        match app.exit_state {
+            ExitState::Running => {}
+            ExitState::Confirming => lines.push("exit: Confirming".to_string()),
+            ExitState::Stopping(_) => lines.push("exit: Stopping".to_string()),
+        }
+        lines.join("\n")
+    }
+}
+
+/// A downloaded repository image, as the image list shows it after loading the repository
+pub fn downloaded_image(channel: &str, version: &str) -> OsImage {
+    OsImage {
+        name: channel.to_string(),
+        version: version.to_string(),
+        description: format!("{} image", channel),
+        downloaded: true,
+        path: Some(format!("/nonexistent/{}-{}.img.xz", channel, version)),
+        created: "2025-01-01".to_string(),
+        sha256: "00".repeat(32),
+        is_latest: true,
+        metadata: Some(ImageMetadata {
+            compressed_hash: "00".repeat(32),
+            uncompressed_hash: "11".repeat(32),
+            uncompressed_size: 8 << 20,
+            created_at: "2025-01-01".to_string(),
+        }),
+        config_schema: Default::default(),
+        partitions: Vec::new(),
+        requirements: Default::default(),
+        file_size: None,
+        local: false,
+    }
+}
+
+/// The image list of a repository with a single channel
+pub fn repository(image: OsImage) -> Message {
+    let group = OsImageGroup {
+        channel_name: image.name.clone(),
+        description: image.description.clone(),
+        latest_version: image,
+        older_versions: Vec::new(),
+        expanded: false,
+    };
+    Message::RepoGroupDataLoaded(Vec::new(), vec![group])
+}
+
+/// The output of `task` that is ready without waiting
+fn ready_messages(task: Task<Message>) -> Vec<Message> {
+    let Some(mut stream) = iced_runtime::task::into_stream(task) else {
+        return Vec::new();
+    };
+    let mut messages = Vec::new();
+    while let Some(Some(action)) = stream.next().now_or_never() {
+        // Window and clipboard actions have no effect without a window
+        if let iced_runtime::Action::Output(message) = action {
+            messages.push(message);
+        }
+    }
+    messages
+}
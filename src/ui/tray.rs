@@ -0,0 +1,254 @@
+/// System tray icon for running a flash in the background
+///
+/// Writing and verifying an image takes up to an hour, so the window can be hidden while
+/// a flash runs. The tray icon shows the progress in its tooltip and brings the window
+/// back when clicked.
+///
+/// The icon lives on its own thread for the whole run of the application: GTK has to be
+/// initialized once on the thread that owns the icon on Linux, and Windows delivers the
+/// icon's messages to the thread that created it. macOS only allows tray icons on the main
+/// thread, which iced owns, so the tray isn't available there.
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use tracing::{debug, error, warn};
+
+/// Whether the window can be hidden to the tray on this platform
+pub fn is_supported() -> bool {
+    cfg!(any(target_os = "linux", windows))
+}
+
+#[cfg_attr(not(any(target_os = "linux", windows)), allow(dead_code))]
+enum TrayCommand {
+    Show(String),
+    SetTooltip(String),
+    Hide,
+}
+
+#[derive(Default)]
+struct TrayHandle {
+    /// Channel to the tray thread, started the first time the tray is shown
+    sender: Option<Sender<TrayCommand>>,
+    /// Current tooltip, so progress updates that don't change it aren't sent
+    tooltip: String,
+}
+
+static TRAY: OnceLock<Mutex<TrayHandle>> = OnceLock::new();
+
+/// Show the tray icon with `tooltip`
+pub fn show(tooltip: &str) -> Result<(), String> {
+    if !is_supported() {
+        return Err("The system tray is not supported on this platform".to_string());
+    }
+
+    let tray = TRAY.get_or_init(Mutex::default);
+    let mut handle = tray.lock().map_err(|_| "Tray is unavailable".to_string())?;
+    if handle.sender.is_none() {
+        handle.sender = Some(spawn_tray_thread()?);
+    }
+    handle.tooltip = tooltip.to_string();
+    send(&handle, TrayCommand::Show(tooltip.to_string()))
+}
+
+/// Update the tooltip of the tray icon, e.g. with the progress of the flash
+pub fn set_tooltip(tooltip: &str) {
+    let Some(Ok(mut handle)) = TRAY.get().map(|tray| tray.lock()) else {
+        return;
+    };
+    if handle.tooltip == tooltip {
+        return;
+    }
+    handle.tooltip = tooltip.to_string();
+    if let Err(e) = send(&handle, TrayCommand::SetTooltip(tooltip.to_string())) {
+        debug!("Failed to update tray tooltip: {}", e);
+    }
+}
+
+/// Remove the tray icon; the thread keeps running so it can be shown again
+pub fn hide() {
+    let Some(Ok(handle)) = TRAY.get().map(|tray| tray.lock()) else {
+        return;
+    };
+    if let Err(e) = send(&handle, TrayCommand::Hide) {
+        debug!("Failed to hide tray icon: {}", e);
+    }
+}
+
+/// Tooltip for the progress of a flash, e.g. "Golem GPU Imager: writing 45%"
+///
+/// Only whole percents are shown so the tooltip changes rarely.
+pub fn progress_tooltip(phase: &crate::disk::FlashPhase) -> String {
+    use crate::disk::FlashPhase;
+
+    let step = match phase {
+        FlashPhase::Preparing | FlashPhase::Clearing { .. } => "preparing",
+        FlashPhase::Writing { .. } => "writing",
+        FlashPhase::FixingGpt | FlashPhase::WritingConfig => "writing configuration",
+        FlashPhase::Verifying { .. } => "verifying",
+        FlashPhase::Done | FlashPhase::Unverified => "done",
+    };
+    match phase.fraction() {
+        Some(fraction) if !matches!(phase, FlashPhase::Done | FlashPhase::Unverified) => {
+            format!("Golem GPU Imager: {} {:.0}%", step, fraction * 100.0)
+        }
+        _ => format!("Golem GPU Imager: {}", step),
+    }
+}
+
+/// Whether the tray icon was clicked or "Show Window" was picked since the last call
+pub fn restore_requested() -> bool {
+    use tray_icon::menu::MenuEvent;
+    use tray_icon::{MouseButton, MouseButtonState, TrayIconEvent};
+
+    let mut requested = false;
+    while let Ok(event) = TrayIconEvent::receiver().try_recv() {
+        if let TrayIconEvent::Click {
+            button: MouseButton::Left,
+            button_state: MouseButtonState::Up,
+            ..
+        } = event
+        {
+            requested = true;
+        }
+    }
+    // The menu has a single item, so any menu event means "Show Window"
+    while MenuEvent::receiver().try_recv().is_ok() {
+        requested = true;
+    }
+    requested
+}
+
+fn send(handle: &TrayHandle, command: TrayCommand) -> Result<(), String> {
+    handle
+        .sender
+        .as_ref()
+        .ok_or_else(|| "Tray is not running".to_string())?
+        .send(command)
+        .map_err(|_| "The tray thread has stopped".to_string())
+}
+
+/// Start the thread that owns the tray icon, waiting until it is ready
+fn spawn_tray_thread() -> Result<Sender<TrayCommand>, String> {
+    let (sender, commands) = mpsc::channel();
+    let (ready_sender, ready) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("tray".to_string())
+        .spawn(move || run_tray(commands, ready_sender))
+        .map_err(|e| format!("Failed to start the tray thread: {}", e))?;
+
+    ready
+        .recv()
+        .map_err(|_| "The tray thread stopped unexpectedly".to_string())??;
+    Ok(sender)
+}
+
+#[cfg(any(target_os = "linux", windows))]
+fn build_tray_icon() -> Result<tray_icon::TrayIcon, String> {
+    use tray_icon::menu::{Menu, MenuItem};
+
+    let image = image::load_from_memory(include_bytes!("../assets/icon.png"))
+        .map_err(|e| format!("Failed to load the tray icon: {}", e))?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    let icon = tray_icon::Icon::from_rgba(image.into_raw(), width, height)
+        .map_err(|e| format!("Failed to load the tray icon: {}", e))?;
+
+    let menu = Menu::new();
+    menu.append(&MenuItem::new("Show Window", true, None))
+        .map_err(|e| format!("Failed to create the tray menu: {}", e))?;
+
+    let tray = tray_icon::TrayIconBuilder::new()
+        .with_icon(icon)
+        .with_menu(Box::new(menu))
+        .with_tooltip("Golem GPU Imager")
+        .build()
+        .map_err(|e| format!("Failed to create the tray icon: {}", e))?;
+    // The icon is only shown while the window is hidden
+    let _ = tray.set_visible(false);
+    Ok(tray)
+}
+
+#[cfg(any(target_os = "linux", windows))]
+fn apply_command(tray: &tray_icon::TrayIcon, command: TrayCommand) {
+    let result = match command {
+        TrayCommand::Show(tooltip) => tray
+            .set_tooltip(Some(tooltip))
+            .and_then(|_| tray.set_visible(true)),
+        TrayCommand::SetTooltip(tooltip) => tray.set_tooltip(Some(tooltip)),
+        TrayCommand::Hide => tray.set_visible(false),
+    };
+    if let Err(e) = result {
+        warn!("Failed to update tray icon: {}", e);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn run_tray(commands: Receiver<TrayCommand>, ready: Sender<Result<(), String>>) {
+    if let Err(e) = gtk::init() {
+        error!("Failed to initialize GTK for the tray icon: {}", e);
+        let _ = ready.send(Err(format!("Failed to initialize GTK: {}", e)));
+        return;
+    }
+    let tray = match build_tray_icon() {
+        Ok(tray) => tray,
+        Err(e) => {
+            error!("{}", e);
+            let _ = ready.send(Err(e));
+            return;
+        }
+    };
+    let _ = ready.send(Ok(()));
+
+    gtk::glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
+        loop {
+            match commands.try_recv() {
+                Ok(command) => apply_command(&tray, command),
+                Err(mpsc::TryRecvError::Empty) => return gtk::glib::ControlFlow::Continue,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    gtk::main_quit();
+                    return gtk::glib::ControlFlow::Break;
+                }
+            }
+        }
+    });
+    gtk::main();
+}
+
+#[cfg(windows)]
+fn run_tray(commands: Receiver<TrayCommand>, ready: Sender<Result<(), String>>) {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        DispatchMessageW, MSG, PM_REMOVE, PeekMessageW, TranslateMessage,
+    };
+
+    let tray = match build_tray_icon() {
+        Ok(tray) => tray,
+        Err(e) => {
+            error!("{}", e);
+            let _ = ready.send(Err(e));
+            return;
+        }
+    };
+    let _ = ready.send(Ok(()));
+
+    loop {
+        // The icon's window receives clicks through this thread's message queue
+        unsafe {
+            let mut msg: MSG = std::mem::zeroed();
+            while PeekMessageW(&mut msg, 0, 0, 0, PM_REMOVE) != 0 {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        match commands.recv_timeout(std::time::Duration::from_millis(50)) {
+            Ok(command) => apply_command(&tray, command),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn run_tray(_commands: Receiver<TrayCommand>, ready: Sender<Result<(), String>>) {
+    let _ = ready.send(Err(
+        "The system tray is not supported on this platform".to_string()
+    ));
+}
@@ -0,0 +1,40 @@
+pub mod handler;
+pub mod messages;
+pub mod state;
+pub mod ui;
+
+pub use handler::*;
+pub use messages::*;
+pub use state::*;
+pub use ui::*;
+
+use iced::Element;
+
+/// Module-level view function that delegates to appropriate UI functions based on workflow state
+pub fn view<'a>(
+    update_state: &'a UpdateState,
+    device_selection: &'a crate::ui::device_selection::DeviceSelectionState,
+) -> Element<'a, crate::ui::messages::Message> {
+    let view = match &update_state.workflow_state {
+        UpdateWorkflowState::SelectDevice => ui::view_select_update_device(
+            &device_selection.devices,
+            update_state.selected_device,
+            update_state.error_message.as_deref(),
+        ),
+        UpdateWorkflowState::Inspecting => ui::view_inspecting_device(),
+        UpdateWorkflowState::ReviewUpdate => ui::view_review_update(
+            update_state,
+            update_state
+                .selected_device
+                .and_then(|index| device_selection.devices.get(index)),
+        ),
+        UpdateWorkflowState::Updating { progress, message } => {
+            ui::view_updating(*progress, message)
+        }
+        UpdateWorkflowState::Completion(success) => {
+            ui::view_update_completion(*success, update_state.error_message.as_deref())
+        }
+    };
+
+    view.map(crate::ui::messages::Message::Update)
+}
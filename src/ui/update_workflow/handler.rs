@@ -0,0 +1,333 @@
+use super::{UpdateMessage, UpdateState, UpdateWorkflowState};
+use crate::disk::{
+    ConfigPartitionContents, ConfigSnapshot, Disk, FlashPhase, GuidRandomization, ImagePartition,
+    ImageSource,
+};
+use crate::models::{CancelToken, ImageMetadata, OperationId};
+use crate::ui::messages::Message;
+use crate::utils::app_settings::AppSettings;
+use crate::utils::image_metadata::MetadataManager;
+use crate::utils::repo::{ImageRepo, Version};
+use iced::Task;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+pub fn handle_message(
+    state: &mut UpdateState,
+    device_selection: &crate::ui::device_selection::DeviceSelectionState,
+    image_repo: &Arc<ImageRepo>,
+    settings: &AppSettings,
+    message: UpdateMessage,
+) -> Task<Message> {
+    match message {
+        UpdateMessage::SelectDevice(index) => {
+            state.selected_device = Some(index);
+            state.error_message = None;
+            debug!("Selected device for update: {}", index);
+            Task::none()
+        }
+
+        UpdateMessage::CheckForUpdate => {
+            let Some(device) = state
+                .selected_device
+                .and_then(|index| device_selection.devices.get(index))
+            else {
+                return Task::none();
+            };
+
+            state.workflow_state = UpdateWorkflowState::Inspecting;
+            state.error_message = None;
+            debug!(
+                "Checking installed image on device: {} ({})",
+                device.name, device.path
+            );
+
+            let device_path = device.path.clone();
+            let repo = Arc::clone(image_repo);
+            Task::perform(
+                async move {
+                    let installed = read_installed_image(device_path).await?;
+                    let metadata = repo
+                        .fetch_metadata()
+                        .await
+                        .map_err(|e| format!("Failed to load available images: {}", e))?;
+                    Ok((installed, metadata))
+                },
+                |result| Message::Update(UpdateMessage::DeviceInspected(result)),
+            )
+        }
+
+        UpdateMessage::DeviceInspected(result) => {
+            match result {
+                Ok((installed, metadata)) => {
+                    info!(
+                        "Device runs image version {}, {} configuration file(s) to preserve",
+                        installed.version.as_deref().unwrap_or("unknown"),
+                        installed.snapshot.files.len()
+                    );
+                    state.set_inspection(installed, metadata.channels);
+                    state.workflow_state = UpdateWorkflowState::ReviewUpdate;
+                }
+                Err(e) => {
+                    warn!("Failed to inspect device for update: {}", e);
+                    state.error_message = Some(e);
+                    state.workflow_state = UpdateWorkflowState::SelectDevice;
+                }
+            }
+            Task::none()
+        }
+
+        UpdateMessage::SelectChannel(channel) => {
+            state.selected_channel = Some(channel);
+            Task::none()
+        }
+
+        UpdateMessage::StartUpdate => {
+            let Some(device) = state
+                .selected_device
+                .and_then(|index| device_selection.devices.get(index))
+            else {
+                return Task::none();
+            };
+            let (Some(version), Some(installed)) =
+                (state.target_version().cloned(), state.installed.as_ref())
+            else {
+                return Task::none();
+            };
+
+            info!(
+                "Updating {} from {} to {}",
+                device.path,
+                installed.version.as_deref().unwrap_or("unknown version"),
+                version.id
+            );
+
+            let snapshot = installed.snapshot.clone();
+            let partitions = version.partitions.clone();
+            let device_path = device.path.clone();
+            let write_speed_limit = settings.write_speed_limit();
+            let randomize_guids = settings.randomize_guids;
+            state.cancel_token = CancelToken::new();
+            let cancel_token = state.cancel_token.clone();
+            let operation = OperationId::new();
+            state.operation = Some(operation);
+            state.error_message = None;
+            state.workflow_state = UpdateWorkflowState::Updating {
+                progress: 0.0,
+                message: "Preparing update...".to_string(),
+            };
+
+            Task::perform(
+                resolve_image_source(Arc::clone(image_repo), version),
+                |source| source,
+            )
+            .then(move |(image_source, metadata)| {
+                write_update(
+                    operation,
+                    device_path.clone(),
+                    image_source,
+                    metadata,
+                    partitions.clone(),
+                    snapshot.clone(),
+                    cancel_token.clone(),
+                    write_speed_limit,
+                    randomize_guids,
+                )
+            })
+        }
+
+        UpdateMessage::UpdateProgress(_, progress, message) => {
+            if matches!(state.workflow_state, UpdateWorkflowState::Updating { .. }) {
+                state.workflow_state = UpdateWorkflowState::Updating { progress, message };
+            }
+            Task::none()
+        }
+
+        UpdateMessage::UpdateCompleted(_) => {
+            info!("Device updated successfully");
+            state.workflow_state = UpdateWorkflowState::Completion(true);
+            Task::none()
+        }
+
+        UpdateMessage::UpdateFailed(_, e) => {
+            error!("Device update failed: {}", e);
+            state.error_message = Some(e);
+            state.workflow_state = UpdateWorkflowState::Completion(false);
+            Task::none()
+        }
+
+        UpdateMessage::CancelUpdate => {
+            info!("Cancelling device update");
+            state.cancel_token.cancel();
+            if let UpdateWorkflowState::Updating { progress, .. } = state.workflow_state {
+                state.workflow_state = UpdateWorkflowState::Updating {
+                    progress,
+                    message: "Cancelling...".to_string(),
+                };
+            }
+            Task::none()
+        }
+
+        UpdateMessage::UpdateAnother => {
+            *state = UpdateState::new();
+            Task::done(Message::Update(UpdateMessage::RefreshDevices))
+        }
+
+        UpdateMessage::BackToDeviceSelection => {
+            state.installed = None;
+            state.channels.clear();
+            state.selected_channel = None;
+            state.workflow_state = UpdateWorkflowState::SelectDevice;
+            Task::none()
+        }
+
+        // App-level navigation messages that need to be forwarded
+        UpdateMessage::BackToMainMenu => Task::done(Message::BackToMainMenu),
+
+        UpdateMessage::RefreshDevices => {
+            debug!("Delegating device refresh to DeviceSelection module");
+            Task::done(Message::DeviceSelection(
+                crate::ui::device_selection::DeviceMessage::RefreshDevices,
+            ))
+        }
+    }
+}
+
+/// Steps of the device preparation that runs before the image write
+enum PrepareStep {
+    Progress(FlashPhase),
+    Cleared(anyhow::Result<FlashPhase>),
+}
+
+/// Lock a device and read the installed image and its configuration partition
+async fn read_installed_image(device_path: String) -> Result<crate::disk::InstalledImage, String> {
+    let disk = Disk::lock_path(&device_path, true)
+        .await
+        .map_err(|e| format!("Failed to lock device: {}", e))?;
+    disk.read_installed_image()
+        .map_err(|e| format!("Failed to read the installed image: {}", e))
+}
+
+/// Use the cached image when it is intact and its metadata is known, otherwise stream it
+async fn resolve_image_source(
+    repo: Arc<ImageRepo>,
+    version: Version,
+) -> (ImageSource, Option<ImageMetadata>) {
+    let streamed = ImageSource::Url {
+        url: repo.get_image_url(&version),
+        sha256: Some(version.sha256.clone()),
+    };
+
+    let cached = tokio::task::spawn_blocking(move || {
+        if !repo.is_image_downloaded(&version) {
+            return None;
+        }
+        let metadata = MetadataManager::new()
+            .ok()?
+            .load_metadata(&version.sha256)
+            .ok()??;
+        if !repo.validate_cached_image(&version.path, &version.sha256) {
+            return None;
+        }
+
+        repo.touch_cached_image(&version.path);
+        let path = repo.get_image_path(&version);
+        Some((
+            ImageSource::File(path.to_string_lossy().to_string()),
+            Some(metadata),
+        ))
+    })
+    .await
+    .ok()
+    .flatten();
+
+    cached.unwrap_or_else(|| {
+        info!("Image is not cached, streaming it to the device");
+        (streamed, None)
+    })
+}
+
+/// Rewrite the device with the new image and put the old configuration files back
+fn write_update(
+    operation: OperationId,
+    device_path: String,
+    image_source: ImageSource,
+    metadata: Option<ImageMetadata>,
+    partitions: Vec<ImagePartition>,
+    snapshot: ConfigSnapshot,
+    cancel_token: CancelToken,
+    write_speed_limit: Option<u64>,
+    randomize_guids: GuidRandomization,
+) -> Task<Message> {
+    let clear_task = Task::sip(
+        Disk::clear_partitions(&device_path, cancel_token.clone()),
+        PrepareStep::Progress,
+        PrepareStep::Cleared,
+    );
+
+    clear_task.then(move |step| {
+        let result = match step {
+            PrepareStep::Progress(progress) => {
+                return Task::done(progress_message(operation, progress));
+            }
+            PrepareStep::Cleared(result) => result,
+        };
+        if let Err(e) = result {
+            return Task::done(Message::Update(UpdateMessage::UpdateFailed(
+                operation,
+                format!("Failed to prepare device: {}", e),
+            )));
+        }
+
+        let device_path = device_path.clone();
+        let image_source = image_source.clone();
+        let metadata = metadata.clone();
+        let partitions = partitions.clone();
+        let contents = ConfigPartitionContents::Restore(snapshot.clone());
+        let cancel_token = cancel_token.clone();
+
+        Task::future(async move { Disk::lock_path(&device_path, true).await }).then(move |disk| {
+            match disk {
+                Ok(disk) => Task::sip(
+                    disk.write_image(
+                        image_source.clone(),
+                        metadata.clone(),
+                        partitions.clone(),
+                        cancel_token.clone(),
+                        // Updates are always verified before the device is reported as updated
+                        CancelToken::new(),
+                        Some(contents.clone()),
+                        write_speed_limit,
+                        randomize_guids,
+                    ),
+                    move |phase| progress_message(operation, phase),
+                    move |result| match result {
+                        Ok(_) => Message::Update(UpdateMessage::UpdateCompleted(operation)),
+                        Err(e) => Message::Update(UpdateMessage::UpdateFailed(
+                            operation,
+                            format!("{:?}", e),
+                        )),
+                    },
+                ),
+                Err(e) => Task::done(Message::Update(UpdateMessage::UpdateFailed(
+                    operation,
+                    format!("Failed to lock device: {}", e),
+                ))),
+            }
+        })
+    })
+}
+
+/// Translate disk write progress into the update's single progress bar
+fn progress_message(operation: OperationId, phase: FlashPhase) -> Message {
+    let progress = phase.fraction().unwrap_or(match phase {
+        FlashPhase::Preparing | FlashPhase::Writing { .. } => 0.0,
+        _ => 1.0,
+    });
+    let message = match phase {
+        FlashPhase::Done | FlashPhase::Unverified => "Finishing update...".to_string(),
+        phase => phase.description(),
+    };
+
+    Message::Update(UpdateMessage::UpdateProgress(operation, progress, message))
+}
@@ -0,0 +1,32 @@
+use crate::disk::InstalledImage;
+use crate::models::OperationId;
+use crate::utils::repo::RepoMetadata;
+
+#[derive(Debug, Clone)]
+pub enum UpdateMessage {
+    SelectDevice(usize),
+    CheckForUpdate,
+    DeviceInspected(Result<(InstalledImage, RepoMetadata), String>),
+    SelectChannel(String),
+    StartUpdate,
+    UpdateProgress(OperationId, f32, String), // Update, progress (0.0-1.0) and the current step
+    UpdateCompleted(OperationId),
+    UpdateFailed(OperationId, String),
+    CancelUpdate,
+    UpdateAnother,
+    BackToDeviceSelection,
+    BackToMainMenu,
+    RefreshDevices,
+}
+
+impl UpdateMessage {
+    /// The update this message reports on, if it comes from one
+    pub fn operation(&self) -> Option<OperationId> {
+        match self {
+            UpdateMessage::UpdateProgress(operation, _, _)
+            | UpdateMessage::UpdateCompleted(operation)
+            | UpdateMessage::UpdateFailed(operation, _) => Some(*operation),
+            _ => None,
+        }
+    }
+}
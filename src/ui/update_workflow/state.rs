@@ -0,0 +1,109 @@
+use crate::disk::InstalledImage;
+use crate::disk::update::same_version;
+use crate::models::{CancelToken, OperationId};
+use crate::utils::repo::{Channel, Version};
+
+#[derive(Debug, Clone)]
+pub enum UpdateWorkflowState {
+    SelectDevice,
+    Inspecting,   // Reading the installed image and the repository
+    ReviewUpdate, // Installed vs available version
+    Updating { progress: f32, message: String },
+    Completion(bool), // Success or failure
+}
+
+/// How the installed image compares with the newest one in the selected channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateStatus {
+    UpToDate,
+    UpdateAvailable,
+    /// The device doesn't record its version, or the version isn't in the repository
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateState {
+    pub workflow_state: UpdateWorkflowState,
+    pub selected_device: Option<usize>,
+    pub installed: Option<InstalledImage>,
+    pub channels: Vec<Channel>,
+    pub selected_channel: Option<String>,
+    pub cancel_token: CancelToken,
+    pub operation: Option<OperationId>, // The running or last update, its progress is the one shown
+    pub error_message: Option<String>,
+}
+
+impl UpdateState {
+    pub fn new() -> Self {
+        Self {
+            workflow_state: UpdateWorkflowState::SelectDevice,
+            selected_device: None,
+            installed: None,
+            channels: Vec::new(),
+            selected_channel: None,
+            cancel_token: CancelToken::new(),
+            operation: None,
+            error_message: None,
+        }
+    }
+
+    /// Whether `message` belongs to the running update, or to no update at all
+    pub fn is_current(&self, message: &super::UpdateMessage) -> bool {
+        message
+            .operation()
+            .is_none_or(|operation| self.operation == Some(operation))
+    }
+
+    /// Remember what was found on the device and preselect the channel it came from
+    pub fn set_inspection(&mut self, installed: InstalledImage, channels: Vec<Channel>) {
+        self.selected_channel = installed
+            .version
+            .as_deref()
+            .and_then(|version| channel_of_version(&channels, version))
+            .or_else(|| channels.first().map(|channel| channel.name.clone()));
+        self.installed = Some(installed);
+        self.channels = channels;
+    }
+
+    /// Newest version in the selected channel
+    pub fn target_version(&self) -> Option<&Version> {
+        let channel_name = self.selected_channel.as_deref()?;
+        self.channels
+            .iter()
+            .find(|channel| channel.name == channel_name)?
+            .versions
+            .iter()
+            .max_by(|a, b| a.created.cmp(&b.created))
+    }
+
+    pub fn update_status(&self) -> UpdateStatus {
+        let installed = self
+            .installed
+            .as_ref()
+            .and_then(|installed| installed.version.as_deref());
+        let (Some(installed), Some(target)) = (installed, self.target_version()) else {
+            return UpdateStatus::Unknown;
+        };
+
+        if same_version(installed, &target.id) {
+            UpdateStatus::UpToDate
+        } else if channel_of_version(&self.channels, installed).is_some() {
+            UpdateStatus::UpdateAvailable
+        } else {
+            UpdateStatus::Unknown
+        }
+    }
+}
+
+/// Name of the channel that publishes `version`
+pub fn channel_of_version(channels: &[Channel], version: &str) -> Option<String> {
+    channels
+        .iter()
+        .find(|channel| {
+            channel
+                .versions
+                .iter()
+                .any(|candidate| same_version(&candidate.id, version))
+        })
+        .map(|channel| channel.name.clone())
+}
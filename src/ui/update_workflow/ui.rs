@@ -0,0 +1,464 @@
+use iced::widget::{
+    Column, Container, button, column, container, pick_list, progress_bar, row, text,
+};
+use iced::{Alignment, Color, Element, Length};
+
+use super::{UpdateMessage, UpdateState, UpdateStatus};
+use crate::ui::{device_selection::StorageDevice, icons};
+
+/// Pick the Golem device to update
+pub fn view_select_update_device<'a>(
+    storage_devices: &'a [StorageDevice],
+    selected_device: Option<usize>,
+    error_message: Option<&'a str>,
+) -> Element<'a, UpdateMessage> {
+    let title = container(
+        column![
+            text("Select Device to Update").size(28),
+            text("Update the Golem image on a device while keeping its configuration").size(16)
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::page_header);
+
+    let golem_devices: Vec<(usize, &StorageDevice)> = storage_devices
+        .iter()
+        .enumerate()
+        .filter(|(_, device)| device.may_be_golem_device())
+        .collect();
+
+    let device_list: Element<'a, UpdateMessage> = if golem_devices.is_empty() {
+        container(
+            column![
+                text("No Golem devices found").size(18),
+                text("Connect a device that was flashed with a Golem image and try again").size(14),
+                button(
+                    row![icons::refresh(), text("Refresh")]
+                        .spacing(5)
+                        .align_y(Alignment::Center),
+                )
+                .on_press(UpdateMessage::RefreshDevices)
+                .padding(8)
+                .style(button::primary)
+            ]
+            .spacing(15),
+        )
+        .padding(20)
+        .style(crate::style::bordered_box)
+        .into()
+    } else {
+        column(golem_devices.into_iter().map(|(i, device)| {
+            let is_selected = Some(i) == selected_device;
+
+            let device_header = row![
+                device.type_icon().color(if is_selected {
+                    crate::style::PRIMARY
+                } else {
+                    Color::from_rgb(0.6, 0.6, 0.6)
+                }),
+                column![
+                    text(device.display_name()).size(18).color(if is_selected {
+                        Color::from_rgb(0.1, 0.1, 0.1)
+                    } else {
+                        Color::from_rgb(0.9, 0.9, 0.9)
+                    }),
+                    text(format!("{} • {}", device.path, device.size))
+                        .size(12)
+                        .color(Color::from_rgb(0.6, 0.6, 0.6)),
+                ]
+                .spacing(2)
+            ]
+            .spacing(15)
+            .align_y(Alignment::Center);
+
+            let device_header = match device.golem_badge() {
+                Some(badge) => device_header.push(badge),
+                None => device_header,
+            };
+
+            let select_button = button(
+                row![icons::get_app(), text("Select")]
+                    .spacing(5)
+                    .align_y(Alignment::Center),
+            )
+            .on_press(UpdateMessage::SelectDevice(i))
+            .padding(10)
+            .style(if is_selected {
+                button::success
+            } else {
+                button::primary
+            });
+
+            container(
+                row![device_header.width(Length::Fill), select_button]
+                    .spacing(20)
+                    .padding(15)
+                    .width(Length::Fill)
+                    .align_y(Alignment::Center),
+            )
+            .style(if is_selected {
+                crate::style::selected_device_card_container
+            } else {
+                crate::style::device_card_container
+            })
+            .width(Length::Fill)
+            .into()
+        }))
+        .spacing(10)
+        .width(Length::Fill)
+        .into()
+    };
+
+    let error: Element<'a, UpdateMessage> = match error_message {
+        Some(error) => view_warning(error),
+        None => container("").height(Length::Fixed(0.0)).into(),
+    };
+
+    let back_button = button(
+        row![icons::navigate_before(), "Back"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(UpdateMessage::BackToMainMenu)
+    .padding(12)
+    .style(crate::style::navigation_back_button);
+
+    let next_button = button(
+        row!["Check for Update", icons::navigate_next()]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press_maybe(
+        selected_device
+            .is_some()
+            .then_some(UpdateMessage::CheckForUpdate),
+    )
+    .padding(12)
+    .style(crate::style::navigation_action_button);
+
+    let buttons = container(
+        row![
+            back_button,
+            Container::new(Column::new()).width(Length::Fill),
+            next_button
+        ]
+        .spacing(15)
+        .width(Length::Fill)
+        .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::bordered_box);
+
+    let spacer = Container::new(Column::new())
+        .height(Length::Fill)
+        .width(Length::Fill);
+
+    container(
+        column![title, error, device_list, spacer, buttons]
+            .spacing(20)
+            .width(Length::Fill),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .style(crate::style::main_box)
+    .into()
+}
+
+/// Shown while the device and the repository are read
+pub fn view_inspecting_device<'a>() -> Element<'a, UpdateMessage> {
+    container(
+        column![
+            icons::timer(),
+            text("Checking Device...").size(20),
+            text("Reading the installed image and looking for updates").size(16),
+        ]
+        .spacing(20)
+        .align_x(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(50)
+    .style(crate::style::bordered_box)
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .into()
+}
+
+/// Installed vs available version, and what will be kept
+pub fn view_review_update<'a>(
+    state: &'a UpdateState,
+    device: Option<&'a StorageDevice>,
+) -> Element<'a, UpdateMessage> {
+    let title = container(
+        column![
+            text("Update Golem Device").size(28),
+            text(
+                device
+                    .map(|device| format!("{} ({})", device.display_name(), device.path))
+                    .unwrap_or_default()
+            )
+            .size(16)
+        ]
+        .spacing(5),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::page_header);
+
+    let installed_version = state
+        .installed
+        .as_ref()
+        .and_then(|installed| installed.version.as_deref())
+        .unwrap_or("Unknown");
+    let target = state.target_version();
+    let status = state.update_status();
+
+    let channels: Vec<String> = state
+        .channels
+        .iter()
+        .map(|channel| channel.name.clone())
+        .collect();
+
+    let label = |name: &'a str| {
+        text(name)
+            .size(14)
+            .color(Color::from_rgb(0.6, 0.6, 0.6))
+            .width(Length::Fixed(160.0))
+    };
+
+    let versions = column![
+        row![label("Installed version"), text(installed_version).size(16)]
+            .align_y(Alignment::Center),
+        row![
+            label("Channel"),
+            pick_list(
+                channels,
+                state.selected_channel.clone(),
+                UpdateMessage::SelectChannel
+            )
+            .style(crate::style::pick_list_style)
+        ]
+        .align_y(Alignment::Center),
+        row![
+            label("Available version"),
+            text(
+                target
+                    .map(|version| format!("{} ({})", version.id, version.created))
+                    .unwrap_or_else(|| "No images in this channel".to_string())
+            )
+            .size(16)
+        ]
+        .align_y(Alignment::Center),
+    ]
+    .spacing(12);
+
+    let status_row = match status {
+        UpdateStatus::UpToDate => row![
+            icons::check_circle().color(Color::from_rgb(0.3, 0.8, 0.4)),
+            text("The device already runs the newest image in this channel").size(14)
+        ],
+        UpdateStatus::UpdateAvailable => row![
+            icons::get_app().color(crate::style::PRIMARY),
+            text("A newer image is available").size(14)
+        ],
+        UpdateStatus::Unknown => row![
+            icons::help().color(Color::from_rgb(0.95, 0.7, 0.3)),
+            text("The installed version could not be matched with the repository").size(14)
+        ],
+    }
+    .spacing(8)
+    .align_y(Alignment::Center);
+
+    let preserved_files = state
+        .installed
+        .as_ref()
+        .map_or(0, |installed| installed.snapshot.files.len());
+    let wallet = state
+        .installed
+        .as_ref()
+        .and_then(|installed| installed.config.as_ref())
+        .map(|config| config.wallet_address.as_str())
+        .filter(|address| !address.is_empty())
+        .unwrap_or("not set");
+
+    let preserved = column![
+        text("Kept from the current installation").size(16),
+        text(format!(
+            "{} file(s) on the configuration partition, wallet {}",
+            preserved_files, wallet
+        ))
+        .size(14)
+        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+    ]
+    .spacing(5);
+
+    let details = container(column![versions, status_row, preserved].spacing(20))
+        .padding(20)
+        .width(Length::Fill)
+        .style(crate::style::bordered_box);
+
+    let warning = view_warning(
+        "The whole device is rewritten. Do not disconnect it until the update has finished.",
+    );
+
+    let back_button = button(
+        row![icons::navigate_before(), "Back"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(UpdateMessage::BackToDeviceSelection)
+    .padding(12)
+    .style(crate::style::navigation_back_button);
+
+    let update_label = match (status, target) {
+        (UpdateStatus::UpToDate, Some(version)) => format!("Reinstall {}", version.id),
+        (_, Some(version)) => format!("Update to {}", version.id),
+        (_, None) => "Update".to_string(),
+    };
+    let update_button = button(
+        row![icons::get_app(), text(update_label)]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press_maybe(target.map(|_| UpdateMessage::StartUpdate))
+    .padding(12)
+    .style(crate::style::navigation_action_button);
+
+    let buttons = container(
+        row![
+            back_button,
+            Container::new(Column::new()).width(Length::Fill),
+            update_button
+        ]
+        .spacing(15)
+        .width(Length::Fill)
+        .align_y(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .padding(15)
+    .style(crate::style::bordered_box);
+
+    let spacer = Container::new(Column::new())
+        .height(Length::Fill)
+        .width(Length::Fill);
+
+    container(
+        column![title, details, warning, spacer, buttons]
+            .spacing(20)
+            .width(Length::Fill),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .style(crate::style::main_box)
+    .into()
+}
+
+/// Progress of the image write and configuration restore
+pub fn view_updating<'a>(progress: f32, message: &'a str) -> Element<'a, UpdateMessage> {
+    let cancel_button = button(
+        row![icons::cancel(), text("Cancel Update")]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(UpdateMessage::CancelUpdate)
+    .padding(12)
+    .style(button::danger);
+
+    container(
+        column![
+            text("Updating Golem Device").size(24),
+            progress_bar(0.0..=1.0, progress),
+            row![
+                text(message).size(14).width(Length::Fill),
+                text(format!("{}%", (progress * 100.0) as i32)).size(14)
+            ],
+            text("Cancelling leaves the device unbootable until it is flashed again")
+                .size(12)
+                .color(crate::style::WARNING),
+            cancel_button
+        ]
+        .spacing(15)
+        .max_width(600)
+        .align_x(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(50)
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .into()
+}
+
+/// Update result
+pub fn view_update_completion<'a>(
+    success: bool,
+    error_message: Option<&'a str>,
+) -> Element<'a, UpdateMessage> {
+    let (icon, title, message) = if success {
+        (
+            icons::check_circle(),
+            text("Device Updated Successfully").size(24),
+            text("The new image is installed and the configuration was restored.")
+                .size(16)
+                .color(Color::from_rgb(0.0, 0.7, 0.0)),
+        )
+    } else {
+        (
+            icons::error(),
+            text("Update Failed").size(24),
+            text(error_message.unwrap_or("There was an error updating the device."))
+                .size(16)
+                .color(Color::from_rgb(0.8, 0.0, 0.0)),
+        )
+    };
+
+    let update_another_button = button(
+        row![icons::get_app(), "Update Another Device"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(UpdateMessage::UpdateAnother)
+    .padding(12)
+    .style(button::primary);
+
+    let back_button = button(
+        row![icons::house(), "Back"]
+            .spacing(5)
+            .align_y(Alignment::Center),
+    )
+    .on_press(UpdateMessage::BackToMainMenu)
+    .padding(12)
+    .style(crate::style::navigation_back_button);
+
+    container(
+        column![
+            row![icon, title].spacing(10).align_y(Alignment::Center),
+            message,
+            row![update_another_button, back_button].spacing(15)
+        ]
+        .spacing(20)
+        .align_x(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x(Length::Fill)
+    .center_y(Length::Fill)
+    .into()
+}
+
+fn view_warning<'a>(message: &'a str) -> Element<'a, UpdateMessage> {
+    row![
+        icons::warning_amber().color(Color::from_rgb(0.95, 0.7, 0.3)),
+        text(message)
+            .size(14)
+            .color(Color::from_rgb(0.95, 0.7, 0.3))
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center)
+    .into()
+}
@@ -1,10 +1,37 @@
+pub mod app_settings;
+pub mod automation;
+pub mod compatibility;
+pub mod crash_report;
+pub mod desktop;
+pub mod device_assignment;
+pub mod device_inventory;
+pub mod device_nicknames;
+pub mod device_rules;
+pub mod disks;
 pub mod elevation;
 pub mod eth;
+pub mod flash_history;
+pub mod flash_report;
+pub mod image_cache;
+pub mod image_meta;
 pub mod image_metadata;
+pub mod logs;
 pub mod metadata_calculator;
+pub mod paths;
+pub mod preseed;
 pub mod preset_manager;
+pub mod privileged_helper;
+pub mod provisioning;
+#[cfg(feature = "gui")]
+pub mod qr;
+pub mod recent_images;
 pub mod repo;
+pub mod shared_presets;
+pub mod single_instance;
 pub mod streaming_hash_calculator;
+pub mod telemetry;
+pub mod template;
+pub mod updater;
 pub mod validation;
 
 pub use elevation::*;
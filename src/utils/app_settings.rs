@@ -0,0 +1,431 @@
+/// Preferences of the application itself, as opposed to the configuration presets
+///
+/// The settings are kept in `settings.toml` next to the presets and remember how the user
+/// left the window, i.e. its size and the zoom of the user interface, as well as whether
+/// new releases are looked for at startup, how fast images are written, whether flashed
+/// disks get new GUIDs, when a device has to be named before it is erased, which machine
+/// images are checked against, how much is logged, where flash statistics are sent, which
+/// devices are hidden, where the team's shared presets come from, how the end of a flash
+/// is announced, which images were flashed recently and what the user named their devices.
+use super::compatibility::TargetProfile;
+use super::device_nicknames::DeviceNicknames;
+use super::device_rules::DeviceRule;
+use super::logs::LogLevel;
+use super::recent_images::RecentImage;
+use super::shared_presets::SharedPresetsSettings;
+use super::telemetry::TelemetrySettings;
+use crate::disk::GuidRandomization;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// Zoom steps and limits of the user interface
+pub const UI_SCALE_STEP: f64 = 0.1;
+pub const MIN_UI_SCALE: f64 = 0.5;
+pub const MAX_UI_SCALE: f64 = 3.0;
+
+/// Size of the window in logical pixels, i.e. before the display's scale factor is applied
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl WindowSize {
+    /// Size of the window on the first start
+    pub const DEFAULT: Self = Self {
+        width: 640.0,
+        height: 720.0,
+    };
+
+    /// Smallest size the layouts still work with, which fits netbook screens
+    pub const MIN: Self = Self {
+        width: 480.0,
+        height: 420.0,
+    };
+
+    /// Largest size that is restored, so a corrupted file can't open a huge window
+    const MAX: Self = Self {
+        width: 7680.0,
+        height: 4320.0,
+    };
+
+    /// The size limited to what can be restored; sizes that aren't numbers become the default
+    pub fn clamped(self) -> Self {
+        if !self.width.is_finite() || !self.height.is_finite() {
+            return Self::DEFAULT;
+        }
+        Self {
+            width: self.width.clamp(Self::MIN.width, Self::MAX.width),
+            height: self.height.clamp(Self::MIN.height, Self::MAX.height),
+        }
+    }
+}
+
+/// How much is logged and how much of it is kept
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LogSettings {
+    /// Least severe events of this application that are logged
+    #[serde(default = "default_log_level")]
+    pub level: LogLevel,
+    /// Number of daily log files kept
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+    /// Space all log files together may take up; older files are deleted first
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u64,
+}
+
+impl LogSettings {
+    pub const MAX_RETENTION_DAYS: u32 = 365;
+    pub const MAX_SIZE_MB: u64 = 10 * 1024;
+
+    /// Limit of the size of the log files in bytes
+    pub fn max_size_bytes(&self) -> u64 {
+        self.max_size_mb * 1024 * 1024
+    }
+
+    fn sanitized(self) -> Self {
+        Self {
+            level: self.level,
+            retention_days: self.retention_days.clamp(1, Self::MAX_RETENTION_DAYS),
+            max_size_mb: self.max_size_mb.clamp(1, Self::MAX_SIZE_MB),
+        }
+    }
+}
+
+fn default_log_level() -> LogLevel {
+    // Development builds log more detail
+    if cfg!(debug_assertions) {
+        LogLevel::Debug
+    } else {
+        LogLevel::Info
+    }
+}
+
+fn default_retention_days() -> u32 {
+    5
+}
+
+fn default_max_size_mb() -> u64 {
+    100
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            retention_days: default_retention_days(),
+            max_size_mb: default_max_size_mb(),
+        }
+    }
+}
+
+/// How the end of a flash or device update is announced, for benches nobody is watching
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlertSettings {
+    /// Play rising tones on success and a low buzz on failure
+    #[serde(default)]
+    pub sound: bool,
+    /// Flash the whole window green or red until it is clicked
+    #[serde(default)]
+    pub flash_window: bool,
+}
+
+/// Devices whose name or size has to be typed before they can be erased
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TypedConfirmation {
+    /// A click on the erase button is enough
+    Never,
+    /// Internal and other fixed disks, which are rarely the intended target
+    #[default]
+    NonRemovable,
+    /// Every device
+    Always,
+}
+
+impl TypedConfirmation {
+    /// Whether a device that is `removable` or not has to be named
+    pub fn required(self, removable: bool) -> bool {
+        match self {
+            TypedConfirmation::Never => false,
+            TypedConfirmation::NonRemovable => !removable,
+            TypedConfirmation::Always => true,
+        }
+    }
+}
+
+impl std::fmt::Display for TypedConfirmation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypedConfirmation::Never => write!(f, "Never"),
+            TypedConfirmation::NonRemovable => write!(f, "For non-removable disks"),
+            TypedConfirmation::Always => write!(f, "For every device"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppSettings {
+    /// Size of the window when the application was last used
+    #[serde(default)]
+    pub window_size: Option<WindowSize>,
+    /// Zoom of the user interface on top of the display's scale factor
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f64,
+    /// Look for a newer release when the application starts
+    #[serde(default = "default_check_for_updates")]
+    pub check_for_updates: bool,
+    /// Most MB per second written to a device, e.g. for fragile USB hubs
+    #[serde(default)]
+    pub max_write_speed_mb: Option<u64>,
+    /// Decompress and hash a picked image file before any device is erased
+    #[serde(default)]
+    pub check_image_before_writing: bool,
+    /// GPT GUIDs replaced on flashed disks, so Windows doesn't take clones for one disk
+    #[serde(default)]
+    pub randomize_guids: GuidRandomization,
+    /// Devices that have to be named before the erase button is enabled
+    #[serde(default)]
+    pub typed_confirmation: TypedConfirmation,
+    /// Machine images are checked against before flashing, this computer by default
+    #[serde(default)]
+    pub target_profile: TargetProfile,
+    #[serde(default)]
+    pub log: LogSettings,
+    /// Statistics about finished flashes, only sent once turned on
+    #[serde(default)]
+    pub telemetry: TelemetrySettings,
+    /// Devices hidden from the device lists and refused when opened
+    #[serde(default)]
+    pub device_rules: Vec<DeviceRule>,
+    /// Read-only presets fetched from a URL at startup
+    #[serde(default)]
+    pub shared_presets: SharedPresetsSettings,
+    /// Sound and window flash at the end of a flash
+    #[serde(default)]
+    pub alerts: AlertSettings,
+    /// Images of the last flashes, newest first, to pick them again with one click
+    #[serde(default)]
+    pub recent_images: Vec<RecentImage>,
+    /// Names given to devices, by their serial number or WWN
+    #[serde(default)]
+    pub device_nicknames: DeviceNicknames,
+}
+
+fn default_ui_scale() -> f64 {
+    1.0
+}
+
+fn default_check_for_updates() -> bool {
+    true
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            window_size: None,
+            ui_scale: default_ui_scale(),
+            check_for_updates: default_check_for_updates(),
+            max_write_speed_mb: None,
+            check_image_before_writing: false,
+            randomize_guids: GuidRandomization::Keep,
+            typed_confirmation: TypedConfirmation::NonRemovable,
+            target_profile: TargetProfile::default(),
+            log: LogSettings::default(),
+            telemetry: TelemetrySettings::default(),
+            device_rules: Vec::new(),
+            shared_presets: SharedPresetsSettings::default(),
+            alerts: AlertSettings::default(),
+            recent_images: Vec::new(),
+            device_nicknames: DeviceNicknames::new(),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Load the settings, falling back to the defaults if there are none or they can't be read
+    pub fn load() -> Self {
+        let path = match settings_path() {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("{}", e);
+                return Self::default();
+            }
+        };
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load_from(&path).unwrap_or_else(|e| {
+            warn!("Ignoring application settings: {:#}", e);
+            Self::default()
+        })
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let settings: Self = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(settings.sanitized())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&settings_path()?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let content = toml::to_string(self).context("Failed to serialize the settings")?;
+        std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Limit of the write speed in bytes per second
+    pub fn write_speed_limit(&self) -> Option<u64> {
+        self.max_write_speed_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    /// Size to open the window with
+    pub fn initial_window_size(&self) -> WindowSize {
+        self.window_size.unwrap_or(WindowSize::DEFAULT).clamped()
+    }
+
+    /// Limit the values to what the user interface can show
+    fn sanitized(mut self) -> Self {
+        self.window_size = self.window_size.map(WindowSize::clamped);
+        self.ui_scale = clamp_ui_scale(self.ui_scale);
+        self.log = self.log.sanitized();
+        self.max_write_speed_mb = self.max_write_speed_mb.filter(|&mb| mb > 0);
+        self
+    }
+}
+
+/// Limit a zoom factor to the supported range
+///
+/// The factor is rounded to a tenth so zooming in and out again returns to the same value.
+pub fn clamp_ui_scale(scale: f64) -> f64 {
+    if !scale.is_finite() {
+        return default_ui_scale();
+    }
+    ((scale * 10.0).round() / 10.0).clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+}
+
+/// Location of the settings file, in the same directory as the presets
+pub fn settings_path() -> Result<PathBuf> {
+    Ok(super::paths::config_dir()?.join(SETTINGS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::device_rules::RuleAction;
+    use crate::utils::telemetry::TelemetryFormat;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "golem-settings-{}/{}",
+            std::process::id(),
+            SETTINGS_FILE
+        ));
+        let settings = AppSettings {
+            window_size: Some(WindowSize {
+                width: 1280.0,
+                height: 960.0,
+            }),
+            ui_scale: 1.5,
+            check_for_updates: false,
+            max_write_speed_mb: Some(20),
+            check_image_before_writing: true,
+            randomize_guids: GuidRandomization::DiskAndPartitions,
+            typed_confirmation: TypedConfirmation::Always,
+            target_profile: TargetProfile {
+                arch: "aarch64".to_string(),
+                gpu_family: String::new(),
+            },
+            log: LogSettings {
+                level: LogLevel::Warn,
+                retention_days: 14,
+                max_size_mb: 500,
+            },
+            telemetry: TelemetrySettings {
+                enabled: true,
+                endpoint: "https://metrics.example.com/v1/metrics".to_string(),
+                format: TelemetryFormat::Otlp,
+                station: "rack-3".to_string(),
+            },
+            device_rules: vec![DeviceRule {
+                action: RuleAction::Allow,
+                max_size_gb: Some(256),
+                removable: Some(true),
+                ..DeviceRule::default()
+            }],
+            shared_presets: SharedPresetsSettings {
+                url: "https://presets.example.com/team.json".to_string(),
+                sha256: "ab".repeat(32),
+                public_key: String::new(),
+            },
+            alerts: AlertSettings {
+                sound: true,
+                flash_window: false,
+            },
+            recent_images: vec![RecentImage {
+                location: "/images/golem-gpu-live-custom.img.xz".to_string(),
+                channel: None,
+                version: "golem-gpu-live-custom.img.xz".to_string(),
+                sha256: "cd".repeat(32),
+                last_used: 1_700_000_000,
+            }],
+            device_nicknames: DeviceNicknames::from([(
+                "4C530001230905114170".to_string(),
+                "Bench slot 3".to_string(),
+            )]),
+        };
+        settings.save_to(&path).unwrap();
+        assert_eq!(AppSettings::load_from(&path).unwrap(), settings);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_missing_values_use_defaults() {
+        let settings: AppSettings = toml::from_str("").unwrap();
+        assert_eq!(settings, AppSettings::default());
+        assert_eq!(settings.initial_window_size(), WindowSize::DEFAULT);
+    }
+
+    #[test]
+    fn test_values_are_clamped() {
+        let tiny = WindowSize {
+            width: 10.0,
+            height: f32::MAX,
+        };
+        assert_eq!(
+            tiny.clamped(),
+            WindowSize {
+                width: WindowSize::MIN.width,
+                height: 4320.0
+            }
+        );
+        let broken = WindowSize {
+            width: f32::NAN,
+            height: 600.0,
+        };
+        assert_eq!(broken.clamped(), WindowSize::DEFAULT);
+
+        assert_eq!(clamp_ui_scale(1.234), 1.2);
+        assert_eq!(clamp_ui_scale(10.0), MAX_UI_SCALE);
+        assert_eq!(clamp_ui_scale(f64::NAN), 1.0);
+
+        let log: LogSettings =
+            toml::from_str("retention_days = 0\nmax_size_mb = 99999999").unwrap();
+        assert_eq!(log.sanitized().retention_days, 1);
+        assert_eq!(log.sanitized().max_size_mb, LogSettings::MAX_SIZE_MB);
+    }
+}
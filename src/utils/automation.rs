@@ -0,0 +1,668 @@
+/// Scriptable control of the application over a local socket
+///
+/// Started with `--automation`, the application listens on a Unix socket (a named pipe on
+/// Windows) for JSON-RPC 2.0 requests, one JSON document per line. Orchestration tools can
+/// list the disks, start and cancel a flash and subscribe to its progress, while the window
+/// keeps showing what is going on. Requests that need the window's state are queued for it
+/// and picked up a few times a second, like the tray events; the connection waits for the
+/// answer.
+///
+/// Methods: `list_disks`, `start_flash` (`device`, `channel` and/or `version`, optional
+/// `preset`), `status`, `cancel` and `subscribe`, after which `flash_event` notifications
+/// are sent for every progress update, finished and failed flash. Each event carries the
+/// `operation` ID of the flash it is about, which `status` reports for the current one.
+use crate::models::OperationId;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::sync::{Mutex, OnceLock};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+/// Command line flag that starts the server, optionally followed by `=<endpoint>`
+pub const AUTOMATION_FLAG: &str = "--automation";
+
+/// Progress updates kept for subscribers that fall behind
+const EVENT_BUFFER: usize = 256;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The request was understood but the application refused or failed it
+const APPLICATION_ERROR: i64 = -32000;
+
+/// Parameters of `start_flash`
+///
+/// Without a `version` the latest image of `channel` is written. Without a `preset` the
+/// preset assigned to the device, or the default preset, configures the image.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StartFlash {
+    /// Path of the target disk, as returned by `list_disks`
+    pub device: String,
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub preset: Option<String>,
+}
+
+/// A request the window has to answer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    ListDisks,
+    StartFlash(StartFlash),
+    Status,
+    Cancel,
+}
+
+/// A disk as reported by `list_disks`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiskInfo {
+    pub path: String,
+    pub name: String,
+    pub size_bytes: u64,
+    pub serial: Option<String>,
+    /// Name the user gave the disk in the imager
+    pub nickname: Option<String>,
+    pub card: bool,
+    pub usb: bool,
+    /// Whether the disk's read-only flag is set, which keeps it from being flashed
+    pub read_only: bool,
+    /// Whether the disk carries a Golem image, None until the disk has been probed
+    pub golem: Option<bool>,
+}
+
+/// What the window is doing, as reported by `status`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlashStatus {
+    /// `idle`, `selecting`, `preparing`, `flashing`, `succeeded` or `failed`
+    pub state: String,
+    /// Step of a running flash, e.g. `writing` or `verifying`
+    pub phase: Option<String>,
+    /// Progress of the current step, 0.0 - 1.0
+    pub progress: Option<f32>,
+    /// ID of the current or last flash, as sent with its events
+    pub operation: Option<OperationId>,
+    pub device: Option<String>,
+    pub channel: Option<String>,
+    pub version: Option<String>,
+}
+
+/// Sent to subscribers as `flash_event` notifications
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum FlashEvent {
+    Started {
+        operation: OperationId,
+        device: String,
+        channel: String,
+        version: String,
+    },
+    Progress {
+        operation: OperationId,
+        phase: String,
+        progress: Option<f32>,
+    },
+    Completed {
+        operation: OperationId,
+        verified: bool,
+    },
+    Failed {
+        operation: OperationId,
+        error: String,
+    },
+}
+
+/// A queued request and the connection waiting for its answer
+#[derive(Debug)]
+pub struct Call {
+    pub command: Command,
+    reply: oneshot::Sender<Result<Value, String>>,
+}
+
+impl Call {
+    /// Answer the request; errors are sent to the client as their message
+    pub fn respond<T: Serialize>(self, result: Result<T, String>) {
+        let result = result.and_then(|value| {
+            serde_json::to_value(value).map_err(|e| format!("Failed to encode the result: {}", e))
+        });
+        // The client may have disconnected meanwhile
+        let _ = self.reply.send(result);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    jsonrpc: Option<String>,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+/// The window's side of the server
+struct Server {
+    calls: Mutex<mpsc::UnboundedReceiver<Call>>,
+    events: broadcast::Sender<FlashEvent>,
+    endpoint: String,
+}
+
+static SERVER: OnceLock<Server> = OnceLock::new();
+
+/// The endpoint to listen on if the command line asks for the server
+///
+/// # Arguments
+/// * `args` - The command line arguments, without the program name
+pub fn endpoint_from_args(args: &[String]) -> Option<String> {
+    args.iter().find_map(|arg| {
+        if arg == AUTOMATION_FLAG {
+            Some(default_endpoint())
+        } else {
+            arg.strip_prefix(AUTOMATION_FLAG)
+                .and_then(|rest| rest.strip_prefix('='))
+                .filter(|endpoint| !endpoint.is_empty())
+                .map(str::to_string)
+        }
+    })
+}
+
+/// Socket path or pipe name used when none is given
+pub fn default_endpoint() -> String {
+    #[cfg(windows)]
+    {
+        r"\\.\pipe\golem-gpu-imager".to_string()
+    }
+
+    #[cfg(not(windows))]
+    {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        dir.join("golem-gpu-imager.sock")
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Start listening on `endpoint`; the server runs on its own thread until the application
+/// exits
+pub fn start(endpoint: &str) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to create the automation runtime")?;
+    let listener = runtime.block_on(platform::bind(endpoint))?;
+
+    let (calls_tx, calls_rx) = mpsc::unbounded_channel();
+    let (events, _) = broadcast::channel(EVENT_BUFFER);
+    let server_events = events.clone();
+    std::thread::Builder::new()
+        .name("automation".to_string())
+        .spawn(move || {
+            runtime.block_on(platform::run(listener, calls_tx, server_events));
+        })
+        .context("Failed to start the automation thread")?;
+
+    let _ = SERVER.set(Server {
+        calls: Mutex::new(calls_rx),
+        events,
+        endpoint: endpoint.to_string(),
+    });
+    info!("Automation API listening on {}", endpoint);
+    Ok(())
+}
+
+/// Remove the socket file; call when the application exits
+pub fn stop() {
+    if let Some(server) = SERVER.get() {
+        platform::cleanup(&server.endpoint);
+    }
+}
+
+/// Whether the server was started
+pub fn is_running() -> bool {
+    SERVER.get().is_some()
+}
+
+/// Requests that arrived since the last call
+pub fn take_calls() -> Vec<Call> {
+    let Some(Ok(mut calls)) = SERVER.get().map(|server| server.calls.lock()) else {
+        return Vec::new();
+    };
+    let mut taken = Vec::new();
+    while let Ok(call) = calls.try_recv() {
+        taken.push(call);
+    }
+    taken
+}
+
+/// Send `event` to all subscribed clients
+pub fn publish(event: FlashEvent) {
+    if let Some(server) = SERVER.get() {
+        // Fails only when nobody is subscribed
+        let _ = server.events.send(event);
+    }
+}
+
+/// Turn a method and its parameters into a command for the window
+fn parse_command(method: &str, params: Option<Value>) -> Result<Command, RpcError> {
+    let params = params.unwrap_or(Value::Null);
+    match method {
+        "list_disks" => Ok(Command::ListDisks),
+        "status" => Ok(Command::Status),
+        "cancel" => Ok(Command::Cancel),
+        "start_flash" => {
+            let request: StartFlash = serde_json::from_value(params)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid parameters: {}", e)))?;
+            if request.channel.is_none() && request.version.is_none() {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    "Either a channel or a version is required",
+                ));
+            }
+            Ok(Command::StartFlash(request))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method: {}", method),
+        )),
+    }
+}
+
+/// Answer one line of a client
+///
+/// # Returns
+/// * `(Option<Value>, bool)` - The response, None for notifications, and whether the client
+///   subscribed to flash events
+async fn handle_line(line: &str, calls: &mpsc::UnboundedSender<Call>) -> (Option<Value>, bool) {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => {
+            let error = RpcError::new(PARSE_ERROR, format!("Invalid JSON: {}", e));
+            return (Some(error_response(Value::Null, error)), false);
+        }
+    };
+    let request: Request = match serde_json::from_value(value) {
+        Ok(request) => request,
+        Err(e) => {
+            let error = RpcError::new(INVALID_REQUEST, format!("Invalid request: {}", e));
+            return (Some(error_response(Value::Null, error)), false);
+        }
+    };
+    let id = request.id.clone().unwrap_or(Value::Null);
+    if request.jsonrpc.as_deref() != Some("2.0") {
+        let error = RpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 is supported");
+        return (Some(error_response(id, error)), false);
+    }
+
+    let subscribe = request.method == "subscribe";
+    let result = if subscribe {
+        Ok(json!({ "subscribed": true }))
+    } else {
+        match parse_command(&request.method, request.params) {
+            Ok(command) => call_window(calls, command).await,
+            Err(error) => Err(error),
+        }
+    };
+
+    // Requests without an id are notifications and get no answer
+    let response = request.id.map(|_| match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => error_response(id, error),
+    });
+    (response, subscribe)
+}
+
+/// Queue `command` for the window and wait for its answer
+async fn call_window(
+    calls: &mpsc::UnboundedSender<Call>,
+    command: Command,
+) -> Result<Value, RpcError> {
+    let (reply, answer) = oneshot::channel();
+    calls
+        .send(Call { command, reply })
+        .map_err(|_| RpcError::new(APPLICATION_ERROR, "The application is shutting down"))?;
+    match answer.await {
+        Ok(result) => result.map_err(|message| RpcError::new(APPLICATION_ERROR, message)),
+        Err(_) => Err(RpcError::new(
+            APPLICATION_ERROR,
+            "The application dropped the request",
+        )),
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+fn notification(event: &FlashEvent) -> Value {
+    json!({ "jsonrpc": "2.0", "method": "flash_event", "params": event })
+}
+
+/// Serve one client until it disconnects
+async fn serve_connection<S>(
+    stream: S,
+    calls: mpsc::UnboundedSender<Call>,
+    events: broadcast::Sender<FlashEvent>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    let mut subscription: Option<broadcast::Receiver<FlashEvent>> = None;
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Ok(());
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let (response, subscribe) = handle_line(&line, &calls).await;
+                if subscribe && subscription.is_none() {
+                    subscription = Some(events.subscribe());
+                }
+                if let Some(response) = response {
+                    write_message(&mut writer, &response).await?;
+                }
+            }
+            event = next_event(&mut subscription) => {
+                write_message(&mut writer, &notification(&event)).await?;
+            }
+        }
+    }
+}
+
+/// The next event for a subscribed client; never returns for other clients
+async fn next_event(subscription: &mut Option<broadcast::Receiver<FlashEvent>>) -> FlashEvent {
+    loop {
+        let Some(receiver) = subscription.as_mut() else {
+            return std::future::pending().await;
+        };
+        match receiver.recv().await {
+            Ok(event) => return event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                debug!("Automation client fell behind, skipped {} events", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => *subscription = None,
+        }
+    }
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> Result<()> {
+    let mut line = message.to_string();
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::UnixListener;
+
+    /// Listen on the socket, which only the current user may connect to
+    pub async fn bind(endpoint: &str) -> Result<UnixListener> {
+        // A socket left behind by a previous run would make binding fail
+        let _ = std::fs::remove_file(endpoint);
+        let listener = UnixListener::bind(endpoint)
+            .with_context(|| format!("Failed to listen on {}", endpoint))?;
+        std::fs::set_permissions(endpoint, std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to restrict access to {}", endpoint))?;
+        Ok(listener)
+    }
+
+    pub async fn run(
+        listener: UnixListener,
+        calls: mpsc::UnboundedSender<Call>,
+        events: broadcast::Sender<FlashEvent>,
+    ) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    debug!("Automation client connected");
+                    let calls = calls.clone();
+                    let events = events.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = serve_connection(stream, calls, events).await {
+                            warn!("Automation client failed: {:#}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    warn!("Failed to accept an automation client: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+                }
+            }
+        }
+    }
+
+    pub fn cleanup(endpoint: &str) {
+        let _ = std::fs::remove_file(endpoint);
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+
+    /// The pipe instance waiting for the next client
+    pub struct Listener {
+        server: NamedPipeServer,
+        endpoint: String,
+    }
+
+    /// Create the first instance of the pipe; remote clients are rejected
+    pub async fn bind(endpoint: &str) -> Result<Listener> {
+        let server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .reject_remote_clients(true)
+            .create(endpoint)
+            .with_context(|| format!("Failed to create the pipe {}", endpoint))?;
+        Ok(Listener {
+            server,
+            endpoint: endpoint.to_string(),
+        })
+    }
+
+    pub async fn run(
+        mut listener: Listener,
+        calls: mpsc::UnboundedSender<Call>,
+        events: broadcast::Sender<FlashEvent>,
+    ) {
+        loop {
+            if let Err(e) = listener.server.connect().await {
+                warn!("Failed to accept an automation client: {}", e);
+                tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+                continue;
+            }
+            debug!("Automation client connected");
+
+            // Each client needs a pipe instance of its own
+            let next = match ServerOptions::new()
+                .reject_remote_clients(true)
+                .create(&listener.endpoint)
+            {
+                Ok(next) => next,
+                Err(e) => {
+                    warn!("Failed to create the next automation pipe: {}", e);
+                    return;
+                }
+            };
+            let client = std::mem::replace(&mut listener.server, next);
+
+            let calls = calls.clone();
+            let events = events.clone();
+            tokio::spawn(async move {
+                if let Err(e) = serve_connection(client, calls, events).await {
+                    warn!("Automation client failed: {:#}", e);
+                }
+            });
+        }
+    }
+
+    // Pipes disappear with the process
+    pub fn cleanup(_endpoint: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_from_args() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(endpoint_from_args(&args(&["--portable"])), None);
+        assert_eq!(
+            endpoint_from_args(&args(&["--automation"])),
+            Some(default_endpoint())
+        );
+        assert_eq!(
+            endpoint_from_args(&args(&["--automation=/tmp/imager.sock"])),
+            Some("/tmp/imager.sock".to_string())
+        );
+        assert_eq!(endpoint_from_args(&args(&["--automation="])), None);
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("list_disks", None), Ok(Command::ListDisks));
+        assert_eq!(
+            parse_command(
+                "start_flash",
+                Some(json!({ "device": "/dev/sdb", "channel": "stable" }))
+            ),
+            Ok(Command::StartFlash(StartFlash {
+                device: "/dev/sdb".to_string(),
+                channel: Some("stable".to_string()),
+                version: None,
+                preset: None,
+            }))
+        );
+        let code = |result: Result<Command, RpcError>| result.unwrap_err().code;
+        assert_eq!(
+            code(parse_command(
+                "start_flash",
+                Some(json!({ "device": "/dev/sdb" }))
+            )),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(parse_command(
+                "start_flash",
+                Some(json!({ "channel": "stable" }))
+            )),
+            INVALID_PARAMS
+        );
+        assert_eq!(code(parse_command("format_disk", None)), METHOD_NOT_FOUND);
+    }
+
+    async fn send<W: AsyncWrite + Unpin>(writer: &mut W, line: &str) {
+        writer
+            .write_all(format!("{}\n", line).as_bytes())
+            .await
+            .unwrap();
+    }
+
+    async fn receive<R: tokio::io::AsyncBufRead + Unpin>(lines: &mut tokio::io::Lines<R>) -> Value {
+        let line = lines.next_line().await.unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_connection_answers_and_streams_events() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (calls_tx, mut calls_rx) = mpsc::unbounded_channel::<Call>();
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        tokio::spawn(serve_connection(server, calls_tx, events.clone()));
+
+        // Stands in for the window
+        tokio::spawn(async move {
+            while let Some(call) = calls_rx.recv().await {
+                match call.command {
+                    Command::ListDisks => call.respond(Ok(vec![DiskInfo {
+                        path: "/dev/sdb".to_string(),
+                        name: "SD Card Reader".to_string(),
+                        size_bytes: 64_000_000_000,
+                        serial: None,
+                        nickname: None,
+                        card: true,
+                        usb: true,
+                        read_only: false,
+                        golem: Some(false),
+                    }])),
+                    _ => call.respond::<Value>(Err("No flash is running".to_string())),
+                }
+            }
+        });
+
+        let (reader, mut writer) = tokio::io::split(client);
+        let mut lines = BufReader::new(reader).lines();
+
+        send(
+            &mut writer,
+            r#"{"jsonrpc":"2.0","id":1,"method":"list_disks"}"#,
+        )
+        .await;
+        let response = receive(&mut lines).await;
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"][0]["path"], "/dev/sdb");
+
+        send(&mut writer, r#"{"jsonrpc":"2.0","id":2,"method":"cancel"}"#).await;
+        let response = receive(&mut lines).await;
+        assert_eq!(response["error"]["code"], APPLICATION_ERROR);
+        assert_eq!(response["error"]["message"], "No flash is running");
+
+        send(&mut writer, "not json").await;
+        assert_eq!(receive(&mut lines).await["error"]["code"], PARSE_ERROR);
+
+        send(
+            &mut writer,
+            r#"{"jsonrpc":"2.0","id":"sub","method":"subscribe"}"#,
+        )
+        .await;
+        assert_eq!(receive(&mut lines).await["result"]["subscribed"], true);
+
+        let operation = OperationId::new();
+        events
+            .send(FlashEvent::Progress {
+                operation,
+                phase: "writing".to_string(),
+                progress: Some(0.5),
+            })
+            .unwrap();
+        let event = receive(&mut lines).await;
+        assert_eq!(event["method"], "flash_event");
+        assert_eq!(event["params"]["event"], "progress");
+        assert_eq!(event["params"]["progress"], 0.5);
+        assert_eq!(event["params"]["operation"], operation.to_string());
+    }
+}
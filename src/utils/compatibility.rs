@@ -0,0 +1,217 @@
+/// Checking the hardware an image is built for against the machine it is flashed for
+///
+/// The manifest may say which CPU architecture an image is built for and which GPU families
+/// its drivers support. The target is this computer, unless a target profile is set in the
+/// settings, as stations usually flash cards for other machines. An image that doesn't fit
+/// the target is only warned about, the manifest may well be wrong about a new board.
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Hardware an image is built for, as the manifest declares it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ImageRequirements {
+    /// CPU architecture, e.g. "x86_64" or "aarch64"; any when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+    /// GPU families the image has drivers for, e.g. "nvidia" or "amd-rdna3"; any when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gpu_families: Vec<String>,
+}
+
+impl ImageRequirements {
+    pub fn is_empty(&self) -> bool {
+        self.arch.is_none() && self.gpu_families.is_empty()
+    }
+}
+
+/// Machine the images are flashed for, as set in the settings
+///
+/// Empty fields stand for this computer's hardware.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TargetProfile {
+    #[serde(default)]
+    pub arch: String,
+    #[serde(default)]
+    pub gpu_family: String,
+}
+
+/// Hardware of the target machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub arch: String,
+    /// GPU families of the target, unknown when empty
+    pub gpu_families: Vec<String>,
+}
+
+impl TargetProfile {
+    /// The target, with this computer's hardware for the fields left empty
+    pub fn resolve(&self) -> Target {
+        let arch = match self.arch.trim() {
+            "" => std::env::consts::ARCH.to_string(),
+            arch => arch.to_string(),
+        };
+        let gpu_families = match self.gpu_family.trim() {
+            "" => local_gpu_families().to_vec(),
+            family => vec![family.to_string()],
+        };
+        Target { arch, gpu_families }
+    }
+}
+
+/// Why `requirements` don't fit `target`, empty if they do
+///
+/// GPU families are only compared when the target's are known.
+pub fn incompatibilities(requirements: &ImageRequirements, target: &Target) -> Vec<String> {
+    let mut issues = Vec::new();
+    let other_arch = requirements
+        .arch
+        .as_ref()
+        .filter(|arch| normalize_arch(arch) != normalize_arch(&target.arch));
+    if let Some(arch) = other_arch {
+        issues.push(format!(
+            "The image is built for {}, the target is {}",
+            arch, target.arch
+        ));
+    }
+
+    let supported = |family: &String| {
+        requirements
+            .gpu_families
+            .iter()
+            .any(|required| families_match(required, family))
+    };
+    if !requirements.gpu_families.is_empty()
+        && !target.gpu_families.is_empty()
+        && !target.gpu_families.iter().any(supported)
+    {
+        issues.push(format!(
+            "The image supports {} GPUs, the target has {}",
+            requirements.gpu_families.join(", "),
+            target.gpu_families.join(", ")
+        ));
+    }
+    issues
+}
+
+/// Architecture names as the Rust toolchain spells them, e.g. "amd64" as "x86_64"
+fn normalize_arch(arch: &str) -> String {
+    match arch.trim().to_ascii_lowercase().as_str() {
+        "amd64" | "x64" | "x86-64" => "x86_64".to_string(),
+        "arm64" => "aarch64".to_string(),
+        arch => arch.to_string(),
+    }
+}
+
+/// Whether two GPU families are the same or one is a generation of the other
+///
+/// Local detection only finds the vendor, e.g. "nvidia", which matches "nvidia-ampere".
+fn families_match(a: &str, b: &str) -> bool {
+    let (a, b) = (a.trim().to_ascii_lowercase(), b.trim().to_ascii_lowercase());
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    longer == shorter || longer.starts_with(&format!("{}-", shorter))
+}
+
+/// GPU vendors of this computer, empty where they can't be found
+///
+/// Only Linux is looked at, through the PCI display controllers in sysfs.
+pub fn local_gpu_families() -> &'static [String] {
+    static FAMILIES: OnceLock<Vec<String>> = OnceLock::new();
+    FAMILIES.get_or_init(detect_gpu_families)
+}
+
+#[cfg(target_os = "linux")]
+fn detect_gpu_families() -> Vec<String> {
+    let Ok(devices) = std::fs::read_dir("/sys/bus/pci/devices") else {
+        return Vec::new();
+    };
+    let read = |path: std::path::PathBuf| std::fs::read_to_string(path).unwrap_or_default();
+
+    let mut families = Vec::new();
+    for device in devices.flatten() {
+        // Display controllers are PCI class 0x03
+        if !read(device.path().join("class")).trim().starts_with("0x03") {
+            continue;
+        }
+        let vendor = read(device.path().join("vendor"));
+        if let Some(family) = gpu_vendor(vendor.trim()) {
+            families.push(family.to_string());
+        }
+    }
+    families.sort();
+    families.dedup();
+    families
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_gpu_families() -> Vec<String> {
+    Vec::new()
+}
+
+/// GPU family of a PCI vendor id such as "0x10de"
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn gpu_vendor(vendor_id: &str) -> Option<&'static str> {
+    match vendor_id.to_ascii_lowercase().as_str() {
+        "0x10de" => Some("nvidia"),
+        "0x1002" => Some("amd"),
+        "0x8086" => Some("intel"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(arch: &str, gpu_families: &[&str]) -> Target {
+        Target {
+            arch: arch.to_string(),
+            gpu_families: gpu_families.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    fn requirements(arch: Option<&str>, gpu_families: &[&str]) -> ImageRequirements {
+        ImageRequirements {
+            arch: arch.map(str::to_string),
+            gpu_families: gpu_families.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_compatible_images() {
+        let rig = target("x86_64", &["nvidia"]);
+        assert!(incompatibilities(&ImageRequirements::default(), &rig).is_empty());
+        assert!(incompatibilities(&requirements(Some("amd64"), &[]), &rig).is_empty());
+        assert!(incompatibilities(&requirements(None, &["amd", "nvidia-ampere"]), &rig).is_empty());
+
+        // Without known GPUs only the architecture is compared
+        assert!(
+            incompatibilities(&requirements(None, &["amd"]), &target("x86_64", &[])).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_incompatible_images() {
+        let rig = target("x86_64", &["amd"]);
+        assert_eq!(
+            incompatibilities(&requirements(Some("aarch64"), &["nvidia"]), &rig),
+            vec![
+                "The image is built for aarch64, the target is x86_64".to_string(),
+                "The image supports nvidia GPUs, the target has amd".to_string(),
+            ]
+        );
+        assert!(!families_match("nvidia", "nvidiax"));
+    }
+
+    #[test]
+    fn test_profile_overrides_local_hardware() {
+        let profile = TargetProfile {
+            arch: "aarch64".to_string(),
+            gpu_family: "nvidia-hopper".to_string(),
+        };
+        assert_eq!(profile.resolve(), target("aarch64", &["nvidia-hopper"]));
+        assert_eq!(
+            TargetProfile::default().resolve().arch,
+            std::env::consts::ARCH
+        );
+    }
+}
@@ -0,0 +1,270 @@
+/// Crash reports for panics and, on Windows, unhandled exceptions
+///
+/// Without a console a panic closes the window without a trace. The panic hook writes a
+/// report with the panic message, a backtrace and the end of the log into the data directory
+/// and leaves a marker, so the next launch can point the user to the report. On Windows an
+/// unhandled exception filter additionally writes a minidump for crashes outside Rust code,
+/// e.g. in a graphics driver.
+use super::paths::crash_dir;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// File that holds the path of the last report until the user has seen it
+const PENDING_MARKER: &str = "pending";
+
+/// Number of log lines included in a report
+const LOG_LINES: usize = 100;
+
+/// Set while a report is being written, so a panic while writing it doesn't recurse
+static WRITING_REPORT: AtomicBool = AtomicBool::new(false);
+
+/// Install the panic hook and, on Windows, the unhandled exception filter
+///
+/// The previous panic hook still runs afterwards, so panics are printed to the console too.
+pub fn install() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if !WRITING_REPORT.swap(true, Ordering::SeqCst) {
+            let message = panic_message(info);
+            let location = info
+                .location()
+                .map(|location| location.to_string())
+                .unwrap_or_else(|| "unknown location".to_string());
+            tracing::error!("Panic at {}: {}", location, message);
+
+            let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+            match write_pending_report(&describe_panic(&message, &location, &backtrace)) {
+                Ok(path) => eprintln!("Crash report written to {}", path.display()),
+                Err(e) => eprintln!("Failed to write crash report: {:#}", e),
+            }
+            WRITING_REPORT.store(false, Ordering::SeqCst);
+        }
+        default_hook(info);
+    }));
+
+    #[cfg(windows)]
+    windows::install_exception_filter();
+}
+
+/// The report of a crash during the last run that the user hasn't been shown yet
+pub fn pending_report() -> Option<PathBuf> {
+    pending_report_in(&crash_dir().ok()?)
+}
+
+/// Forget the pending report; the report itself is kept
+pub fn dismiss_pending_report() -> Result<()> {
+    dismiss_pending_report_in(&crash_dir()?)
+}
+
+fn pending_report_in(dir: &Path) -> Option<PathBuf> {
+    let path = std::fs::read_to_string(dir.join(PENDING_MARKER)).ok()?;
+    let path = PathBuf::from(path.trim());
+    path.exists().then_some(path)
+}
+
+fn dismiss_pending_report_in(dir: &Path) -> Result<()> {
+    let marker = dir.join(PENDING_MARKER);
+    if marker.exists() {
+        std::fs::remove_file(&marker)
+            .with_context(|| format!("Failed to remove {}", marker.display()))?;
+    }
+    Ok(())
+}
+
+fn panic_message(info: &std::panic::PanicHookInfo) -> String {
+    if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Text of the report for a panic
+fn describe_panic(message: &str, location: &str, backtrace: &str) -> String {
+    let thread = std::thread::current();
+    format!(
+        "Panic in thread '{}' at {}:\n{}\n\nBacktrace:\n{}\n",
+        thread.name().unwrap_or("unnamed"),
+        location,
+        message,
+        backtrace
+    )
+}
+
+/// Write a report with `details` and mark it as pending
+fn write_pending_report(details: &str) -> Result<PathBuf> {
+    let dir = crash_dir()?;
+    let path = write_report_in(&dir, details, &recent_log_lines())?;
+    std::fs::write(dir.join(PENDING_MARKER), path.display().to_string())
+        .context("Failed to mark the crash report")?;
+    Ok(path)
+}
+
+fn write_report_in(dir: &Path, details: &str, log_lines: &[String]) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(report_file_name("txt"));
+    std::fs::write(&path, format_report(details, log_lines))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// File name for a new report, e.g. `crash-20250101-120000.txt`
+fn report_file_name(extension: &str) -> String {
+    format!(
+        "crash-{}.{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        extension
+    )
+}
+
+fn format_report(details: &str, log_lines: &[String]) -> String {
+    let mut report = format!(
+        "Golem GPU Imager v{} crashed on {}\nSystem: {} ({})\n\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        details.trim_end()
+    );
+    if !log_lines.is_empty() {
+        report.push_str("\nLast log lines:\n");
+        for line in log_lines {
+            report.push_str(line);
+            report.push('\n');
+        }
+    }
+    report
+}
+
+/// The end of the current log file; lines still buffered by the logger are missing
+fn recent_log_lines() -> Vec<String> {
+    let Ok(Some(file)) = super::logs::latest_log_file() else {
+        return Vec::new();
+    };
+    let lines = super::logs::read_tail(&file).unwrap_or_default();
+    let start = lines.len().saturating_sub(LOG_LINES);
+    lines[start..]
+        .iter()
+        .map(|line| line.text.clone())
+        .collect()
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::*;
+    use anyhow::anyhow;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::System::Diagnostics::Debug::{
+        EXCEPTION_POINTERS, MINIDUMP_EXCEPTION_INFORMATION, MiniDumpNormal, MiniDumpWriteDump,
+        SetUnhandledExceptionFilter,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId,
+    };
+
+    /// Let the default handling (Windows Error Reporting) continue after the filter
+    const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+    pub fn install_exception_filter() {
+        unsafe {
+            SetUnhandledExceptionFilter(Some(exception_filter));
+        }
+    }
+
+    unsafe extern "system" fn exception_filter(pointers: *const EXCEPTION_POINTERS) -> i32 {
+        if WRITING_REPORT.swap(true, Ordering::SeqCst) {
+            return EXCEPTION_CONTINUE_SEARCH;
+        }
+
+        let (code, address) = unsafe {
+            match pointers.as_ref().and_then(|p| p.ExceptionRecord.as_ref()) {
+                Some(record) => (
+                    record.ExceptionCode as u32,
+                    record.ExceptionAddress as usize,
+                ),
+                None => (0, 0),
+            }
+        };
+        let minidump = write_minidump(pointers);
+        let details = format!(
+            "Unhandled exception 0x{:08X} at address 0x{:X}\nMinidump: {}\n",
+            code,
+            address,
+            match &minidump {
+                Ok(path) => path.display().to_string(),
+                Err(e) => format!("not written ({:#})", e),
+            }
+        );
+        let _ = write_pending_report(&details);
+        EXCEPTION_CONTINUE_SEARCH
+    }
+
+    fn write_minidump(pointers: *const EXCEPTION_POINTERS) -> Result<PathBuf> {
+        let dir = crash_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(report_file_name("dmp"));
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+
+        let exception = MINIDUMP_EXCEPTION_INFORMATION {
+            ThreadId: unsafe { GetCurrentThreadId() },
+            ExceptionPointers: pointers as *mut EXCEPTION_POINTERS,
+            ClientPointers: 0,
+        };
+        let written = unsafe {
+            MiniDumpWriteDump(
+                GetCurrentProcess(),
+                GetCurrentProcessId(),
+                file.as_raw_handle() as HANDLE,
+                MiniDumpNormal,
+                &exception,
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        if written == 0 {
+            return Err(anyhow!(
+                "MiniDumpWriteDump failed: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_report() {
+        let details = describe_panic("index out of bounds", "src/disk.rs:10:5", "0: main\n");
+        let report = format_report(&details, &["INFO Starting".to_string()]);
+        assert!(report.starts_with(&format!(
+            "Golem GPU Imager v{} crashed on ",
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert!(report.contains("at src/disk.rs:10:5:\nindex out of bounds\n"));
+        assert!(report.contains("Backtrace:\n0: main"));
+        assert!(report.ends_with("Last log lines:\nINFO Starting\n"));
+    }
+
+    #[test]
+    fn test_pending_report() {
+        let dir = std::env::temp_dir().join(format!("golem-crashes-{}", std::process::id()));
+        assert_eq!(pending_report_in(&dir), None);
+
+        let path = write_report_in(&dir, "Panic", &[]).unwrap();
+        std::fs::write(dir.join(PENDING_MARKER), path.display().to_string()).unwrap();
+        assert_eq!(pending_report_in(&dir), Some(path.clone()));
+
+        dismiss_pending_report_in(&dir).unwrap();
+        assert_eq!(pending_report_in(&dir), None);
+        assert!(path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
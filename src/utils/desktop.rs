@@ -0,0 +1,27 @@
+/// Opening files and links with the application the desktop associates with them
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+
+/// Open `path` with its default application, e.g. a text editor for a crash report
+pub fn open_path(path: &Path) -> Result<()> {
+    open(path.as_os_str()).with_context(|| format!("Failed to open {}", path.display()))
+}
+
+/// Open `url` in the default browser
+pub fn open_url(url: &str) -> Result<()> {
+    open(OsStr::new(url)).with_context(|| format!("Failed to open {}", url))
+}
+
+fn open(target: &OsStr) -> std::io::Result<()> {
+    let program = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(program).arg(target).spawn()?;
+    Ok(())
+}
@@ -0,0 +1,195 @@
+/// Assignments of configuration presets to devices
+///
+/// Fleet operators flash different subnets or wallets onto different hardware batches.
+/// An assignment matches devices by serial number, model name pattern or size, and the
+/// flash workflow pre-selects the assigned preset when it sees a matching device.
+use serde::{Deserialize, Serialize};
+
+/// Rule that maps matching devices to a preset
+///
+/// Every criterion that is set must match. An assignment without criteria matches nothing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceAssignment {
+    /// Name of the preset to select
+    pub preset: String,
+    /// Exact serial number or WWN, compared case-insensitively
+    #[serde(default)]
+    pub serial: Option<String>,
+    /// Model name pattern where `*` matches any text, e.g. `SanDisk Extreme*`
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Capacity in whole gigabytes as printed on the device, e.g. 64
+    #[serde(default)]
+    pub size_gb: Option<u64>,
+}
+
+/// What an assignment is matched against
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceInfo<'a> {
+    /// Serial number or WWN, if the device reports one
+    pub serial: Option<&'a str>,
+    /// Model name as listed by the OS
+    pub model: &'a str,
+    /// Capacity in bytes
+    pub size_bytes: u64,
+}
+
+impl DeviceAssignment {
+    pub fn has_criteria(&self) -> bool {
+        self.serial.is_some() || self.model.is_some() || self.size_gb.is_some()
+    }
+
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        if !self.has_criteria() {
+            return false;
+        }
+
+        let serial_matches = self.serial.as_deref().is_none_or(|serial| {
+            device
+                .serial
+                .is_some_and(|actual| actual.trim().eq_ignore_ascii_case(serial.trim()))
+        });
+        let model_matches = self
+            .model
+            .as_deref()
+            .is_none_or(|pattern| wildcard_match(pattern.trim(), device.model.trim()));
+        let size_matches = self
+            .size_gb
+            .is_none_or(|size_gb| marketed_size_gb(device.size_bytes) == size_gb);
+
+        serial_matches && model_matches && size_matches
+    }
+
+    /// Short description of the criteria, e.g. `model "SanDisk*", 64 GB`
+    pub fn describe(&self) -> String {
+        let mut criteria = Vec::new();
+        if let Some(serial) = &self.serial {
+            criteria.push(format!("serial {}", serial));
+        }
+        if let Some(model) = &self.model {
+            criteria.push(format!("model \"{}\"", model));
+        }
+        if let Some(size_gb) = self.size_gb {
+            criteria.push(format!("{} GB", size_gb));
+        }
+        criteria.join(", ")
+    }
+}
+
+/// First assignment that matches the device
+///
+/// Assignments by serial number win over model and size patterns, so a single device
+/// can be singled out from a batch; otherwise the order in the list decides.
+pub fn find_assignment<'a>(
+    assignments: &'a [DeviceAssignment],
+    device: &DeviceInfo,
+) -> Option<&'a DeviceAssignment> {
+    assignments
+        .iter()
+        .filter(|assignment| assignment.matches(device))
+        .find(|assignment| assignment.serial.is_some())
+        .or_else(|| {
+            assignments
+                .iter()
+                .find(|assignment| assignment.matches(device))
+        })
+}
+
+/// Capacity the way it is printed on the device, in decimal gigabytes
+///
+/// Flash media are sold in powers of two but lose some capacity to spare blocks, so a
+/// 64 GB card typically reports a little under 64 * 10^9 bytes. Round to the nearest size.
+pub(super) fn marketed_size_gb(size_bytes: u64) -> u64 {
+    (size_bytes as f64 / 1_000_000_000.0).round() as u64
+}
+
+/// Case-insensitive match where `*` in the pattern stands for any run of characters
+pub(super) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let text = text.to_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(preset: &str) -> DeviceAssignment {
+        DeviceAssignment {
+            preset: preset.to_string(),
+            serial: None,
+            model: None,
+            size_gb: None,
+        }
+    }
+
+    const CARD: DeviceInfo<'static> = DeviceInfo {
+        serial: Some("4C530001230905114170"),
+        model: "SanDisk Extreme Pro",
+        size_bytes: 63_864_569_856,
+    };
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("sandisk*", "SanDisk Extreme Pro"));
+        assert!(wildcard_match("*extreme*", "SanDisk Extreme Pro"));
+        assert!(wildcard_match("San*Pro", "SanDisk Extreme Pro"));
+        assert!(wildcard_match("SanDisk Extreme Pro", "sandisk extreme pro"));
+        assert!(!wildcard_match("Kingston*", "SanDisk Extreme Pro"));
+        assert!(!wildcard_match("San*Plus", "SanDisk Extreme Pro"));
+        assert!(!wildcard_match("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_assignment_criteria() {
+        let mut by_model = assignment("batch-a");
+        by_model.model = Some("SanDisk*".to_string());
+        by_model.size_gb = Some(64);
+        assert!(by_model.matches(&CARD));
+
+        by_model.size_gb = Some(128);
+        assert!(!by_model.matches(&CARD));
+
+        // Without criteria nothing matches
+        assert!(!assignment("empty").matches(&CARD));
+    }
+
+    #[test]
+    fn test_serial_assignment_wins() {
+        let mut by_model = assignment("batch-a");
+        by_model.model = Some("SanDisk*".to_string());
+        let mut by_serial = assignment("spare");
+        by_serial.serial = Some("4c530001230905114170".to_string());
+
+        let assignments = vec![by_model, by_serial];
+        assert_eq!(
+            find_assignment(&assignments, &CARD).map(|a| a.preset.as_str()),
+            Some("spare")
+        );
+
+        let other = DeviceInfo {
+            serial: None,
+            ..CARD
+        };
+        assert_eq!(
+            find_assignment(&assignments, &other).map(|a| a.preset.as_str()),
+            Some("batch-a")
+        );
+    }
+}
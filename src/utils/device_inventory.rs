@@ -0,0 +1,199 @@
+/// Exporting the listed devices for asset tracking
+///
+/// Larger deployments keep track of their cards and drives in a spreadsheet or an inventory
+/// system. The export lists each device of the target device list with its serial number,
+/// size, health and what the Golem probe found on it, as CSV for spreadsheets or as JSON
+/// for scripts. Devices whose probe hasn't finished are exported without the Golem details.
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+/// Format the inventory is saved in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryFormat {
+    Csv,
+    Json,
+}
+
+impl InventoryFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            InventoryFormat::Csv => "csv",
+            InventoryFormat::Json => "json",
+        }
+    }
+}
+
+/// Everything exported about one device
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InventoryEntry {
+    pub path: String,
+    /// Model as the system lists it
+    pub name: String,
+    /// Name the user gave the device, see [`crate::utils::device_nicknames`]
+    pub nickname: Option<String>,
+    pub serial: Option<String>,
+    pub wwn: Option<String>,
+    pub vendor_id: Option<String>,
+    pub product_id: Option<String>,
+    pub size_bytes: u64,
+    /// Kind of device, e.g. `SD Card`
+    pub kind: String,
+    pub removable: bool,
+    pub read_only: bool,
+    /// Health as the device list shows it, e.g. `Healthy`
+    pub health: String,
+    pub reallocated_sectors: Option<u64>,
+    pub pending_sectors: Option<u64>,
+    pub wear_level_percent: Option<u8>,
+    /// Whether the device carries a Golem image, `None` if it couldn't be told
+    pub golem: Option<bool>,
+    /// Configuration found on a Golem device
+    pub subnet: Option<String>,
+    pub wallet_address: Option<String>,
+    pub node_name: Option<String>,
+}
+
+/// Column names of the CSV export, in the order of [`InventoryEntry::csv_values`]
+const CSV_COLUMNS: [&str; 19] = [
+    "path",
+    "name",
+    "nickname",
+    "serial",
+    "wwn",
+    "vendor_id",
+    "product_id",
+    "size_bytes",
+    "kind",
+    "removable",
+    "read_only",
+    "health",
+    "reallocated_sectors",
+    "pending_sectors",
+    "wear_level_percent",
+    "golem",
+    "subnet",
+    "wallet_address",
+    "node_name",
+];
+
+impl InventoryEntry {
+    /// Fields of the entry as CSV cells, empty where nothing is known
+    fn csv_values(&self) -> [String; 19] {
+        let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+        let number = |value: Option<u64>| value.map(|n| n.to_string()).unwrap_or_default();
+        [
+            self.path.clone(),
+            self.name.clone(),
+            optional(&self.nickname),
+            optional(&self.serial),
+            optional(&self.wwn),
+            optional(&self.vendor_id),
+            optional(&self.product_id),
+            self.size_bytes.to_string(),
+            self.kind.clone(),
+            self.removable.to_string(),
+            self.read_only.to_string(),
+            self.health.clone(),
+            number(self.reallocated_sectors),
+            number(self.pending_sectors),
+            number(self.wear_level_percent.map(u64::from)),
+            self.golem
+                .map(|golem| golem.to_string())
+                .unwrap_or_default(),
+            optional(&self.subnet),
+            optional(&self.wallet_address),
+            optional(&self.node_name),
+        ]
+    }
+}
+
+/// The inventory as CSV, a header line and a line per device
+pub fn to_csv(entries: &[InventoryEntry]) -> String {
+    let mut csv = csv_line(CSV_COLUMNS.map(str::to_string));
+    for entry in entries {
+        csv.push_str(&csv_line(entry.csv_values()));
+    }
+    csv
+}
+
+/// The inventory as a JSON array of devices
+pub fn to_json(entries: &[InventoryEntry]) -> String {
+    serde_json::to_string_pretty(entries).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Suggested file name, e.g. `device-inventory-20250101-120000.csv`
+pub fn file_name(format: InventoryFormat, now: DateTime<Local>) -> String {
+    format!(
+        "device-inventory-{}.{}",
+        now.format("%Y%m%d-%H%M%S"),
+        format.extension()
+    )
+}
+
+fn csv_line(cells: impl IntoIterator<Item = String>) -> String {
+    let cells: Vec<String> = cells.into_iter().map(|cell| csv_cell(&cell)).collect();
+    format!("{}\r\n", cells.join(","))
+}
+
+/// Quote a cell holding a separator, a quote or a line break, doubling its quotes
+fn csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry() -> InventoryEntry {
+        InventoryEntry {
+            path: "/dev/sdb".to_string(),
+            name: "SanDisk \"Extreme\", 64GB".to_string(),
+            nickname: Some("Bench slot 3".to_string()),
+            serial: Some("4C5300012309".to_string()),
+            size_bytes: 63_864_569_856,
+            kind: "SD Card".to_string(),
+            removable: true,
+            health: "Healthy".to_string(),
+            golem: Some(true),
+            subnet: Some("public".to_string()),
+            ..InventoryEntry::default()
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = to_csv(&[entry()]);
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("path,name,nickname,serial,"));
+        assert_eq!(
+            lines[1],
+            "/dev/sdb,\"SanDisk \"\"Extreme\"\", 64GB\",Bench slot 3,4C5300012309,,,,63864569856,SD Card,true,false,Healthy,,,,true,public,,"
+        );
+        assert_eq!(lines[2], "");
+        assert_eq!(to_csv(&[]).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_json() {
+        let json: serde_json::Value = serde_json::from_str(&to_json(&[entry()])).unwrap();
+        assert_eq!(json[0]["serial"], "4C5300012309");
+        assert_eq!(json[0]["size_bytes"], 63_864_569_856u64);
+        assert_eq!(json[0]["golem"], true);
+        assert!(json[0]["wwn"].is_null());
+    }
+
+    #[test]
+    fn test_file_name() {
+        let now = Local.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            file_name(InventoryFormat::Json, now),
+            "device-inventory-20250101-120000.json"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+/// Names given to devices, so identical cards on a bench can be told apart
+///
+/// A nickname such as "Bench slot 3" or "Rig 12 boot SSD" is kept in the settings under the
+/// device's serial number or WWN, so it follows the device to whatever path it is found at
+/// next. The device lists show it next to the model name, and the flash queue and flash
+/// reports record it, which keeps batch flashes traceable. Devices that don't report a
+/// serial number can't be given one, there is no telling them apart.
+use std::collections::BTreeMap;
+
+/// Longest nickname kept, in characters
+pub const MAX_NICKNAME_LENGTH: usize = 48;
+
+/// Nicknames by the serial number or WWN of their device
+pub type DeviceNicknames = BTreeMap<String, String>;
+
+/// Name the device with `serial` `nickname`, or forget its nickname if that is blank
+pub fn set_nickname(nicknames: &mut DeviceNicknames, serial: &str, nickname: &str) {
+    let nickname: String = nickname.trim().chars().take(MAX_NICKNAME_LENGTH).collect();
+    if nickname.is_empty() {
+        nicknames.remove(serial);
+    } else {
+        nicknames.insert(serial.to_string(), nickname.trim_end().to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_nickname() {
+        let mut nicknames = DeviceNicknames::new();
+        set_nickname(&mut nicknames, "4C530001230905114170", "  Bench slot 3 ");
+        assert_eq!(
+            nicknames.get("4C530001230905114170").map(String::as_str),
+            Some("Bench slot 3")
+        );
+
+        set_nickname(&mut nicknames, "4C530001230905114170", &"x".repeat(100));
+        assert_eq!(
+            nicknames["4C530001230905114170"].chars().count(),
+            MAX_NICKNAME_LENGTH
+        );
+
+        set_nickname(&mut nicknames, "4C530001230905114170", " ");
+        assert!(nicknames.is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+/// Rules that hide devices from the device lists and keep them from being opened
+///
+/// Stations usually have an internal or data disk next to the card readers. A deny rule
+/// hides every device it matches, e.g. "never show NVMe drives"; once there is an allow
+/// rule, only devices matching one of the allow rules are shown, e.g. "only removable
+/// disks up to 256 GB". Besides filtering the lists, the rules are checked again by
+/// `Disk::lock_path`, so a device named by a stale list, a queued flash or an automation
+/// client is refused as well.
+use super::device_assignment::{marketed_size_gb, wildcard_match};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::RwLock;
+
+/// Rules in effect, set from the application settings
+static ACTIVE_RULES: RwLock<Vec<DeviceRule>> = RwLock::new(Vec::new());
+
+/// What happens to the devices a rule matches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Only devices matching an allow rule are shown
+    Allow,
+    /// Matching devices are hidden
+    #[default]
+    Deny,
+}
+
+impl fmt::Display for RuleAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleAction::Allow => write!(f, "Only allow"),
+            RuleAction::Deny => write!(f, "Hide"),
+        }
+    }
+}
+
+/// Rule that hides or allows matching devices
+///
+/// Every criterion that is set must match. A rule without criteria matches nothing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceRule {
+    #[serde(default)]
+    pub action: RuleAction,
+    /// Exact serial number or WWN, compared case-insensitively
+    #[serde(default)]
+    pub serial: Option<String>,
+    /// Pattern where `*` matches any text, compared with the model name and vendor id
+    #[serde(default)]
+    pub vendor: Option<String>,
+    /// Device path pattern, e.g. `/dev/nvme*`
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Smallest capacity in whole gigabytes, inclusive
+    #[serde(default)]
+    pub min_size_gb: Option<u64>,
+    /// Largest capacity in whole gigabytes, inclusive
+    #[serde(default)]
+    pub max_size_gb: Option<u64>,
+    /// Only removable devices if true, only fixed ones if false
+    #[serde(default)]
+    pub removable: Option<bool>,
+}
+
+/// What a rule is matched against
+#[derive(Debug, Clone, Copy)]
+pub struct RuleTarget<'a> {
+    pub path: &'a str,
+    /// Serial number or WWN, if the device reports one
+    pub serial: Option<&'a str>,
+    /// Model name as listed by the OS
+    pub model: &'a str,
+    /// USB vendor id on Linux, SCSI vendor string on Windows
+    pub vendor_id: Option<&'a str>,
+    /// Capacity in bytes
+    pub size_bytes: u64,
+    pub is_removable: bool,
+}
+
+impl DeviceRule {
+    pub fn has_criteria(&self) -> bool {
+        self.serial.is_some()
+            || self.vendor.is_some()
+            || self.path.is_some()
+            || self.min_size_gb.is_some()
+            || self.max_size_gb.is_some()
+            || self.removable.is_some()
+    }
+
+    pub fn matches(&self, device: &RuleTarget) -> bool {
+        if !self.has_criteria() {
+            return false;
+        }
+
+        let serial_matches = self.serial.as_deref().is_none_or(|serial| {
+            device
+                .serial
+                .is_some_and(|actual| actual.trim().eq_ignore_ascii_case(serial.trim()))
+        });
+        let vendor_matches = self.vendor.as_deref().is_none_or(|pattern| {
+            let pattern = pattern.trim();
+            wildcard_match(pattern, device.model.trim())
+                || device
+                    .vendor_id
+                    .is_some_and(|vendor_id| wildcard_match(pattern, vendor_id.trim()))
+        });
+        let path_matches = self
+            .path
+            .as_deref()
+            .is_none_or(|pattern| wildcard_match(pattern.trim(), device.path));
+        let size_gb = marketed_size_gb(device.size_bytes);
+        let size_matches = self.min_size_gb.is_none_or(|min| size_gb >= min)
+            && self.max_size_gb.is_none_or(|max| size_gb <= max);
+        let removable_matches = self
+            .removable
+            .is_none_or(|removable| device.is_removable == removable);
+
+        serial_matches && vendor_matches && path_matches && size_matches && removable_matches
+    }
+
+    /// Short description of the criteria, e.g. `path "/dev/nvme*", up to 256 GB`
+    pub fn describe(&self) -> String {
+        let mut criteria = Vec::new();
+        if let Some(serial) = &self.serial {
+            criteria.push(format!("serial {}", serial));
+        }
+        if let Some(vendor) = &self.vendor {
+            criteria.push(format!("vendor \"{}\"", vendor));
+        }
+        if let Some(path) = &self.path {
+            criteria.push(format!("path \"{}\"", path));
+        }
+        match (self.min_size_gb, self.max_size_gb) {
+            (Some(min), Some(max)) => criteria.push(format!("{} to {} GB", min, max)),
+            (Some(min), None) => criteria.push(format!("at least {} GB", min)),
+            (None, Some(max)) => criteria.push(format!("up to {} GB", max)),
+            (None, None) => {}
+        }
+        match self.removable {
+            Some(true) => criteria.push("removable".to_string()),
+            Some(false) => criteria.push("fixed".to_string()),
+            None => {}
+        }
+        criteria.join(", ")
+    }
+}
+
+/// Check a device against the rules, returning why it is blocked
+pub fn check(rules: &[DeviceRule], device: &RuleTarget) -> Result<(), String> {
+    if let Some(rule) = rules
+        .iter()
+        .find(|rule| rule.action == RuleAction::Deny && rule.matches(device))
+    {
+        return Err(format!(
+            "{} is hidden by the device rule: {}",
+            device.path,
+            rule.describe()
+        ));
+    }
+
+    let mut allow_rules = rules
+        .iter()
+        .filter(|rule| rule.action == RuleAction::Allow && rule.has_criteria())
+        .peekable();
+    if allow_rules.peek().is_some() && !allow_rules.any(|rule| rule.matches(device)) {
+        return Err(format!(
+            "{} doesn't match any of the device rules that allow devices",
+            device.path
+        ));
+    }
+    Ok(())
+}
+
+/// Replace the rules in effect
+pub fn set_active(rules: Vec<DeviceRule>) {
+    match ACTIVE_RULES.write() {
+        Ok(mut active) => *active = rules,
+        Err(poisoned) => *poisoned.into_inner() = rules,
+    }
+}
+
+/// The rules in effect
+pub fn active() -> Vec<DeviceRule> {
+    match ACTIVE_RULES.read() {
+        Ok(active) => active.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARD: RuleTarget<'static> = RuleTarget {
+        path: "/dev/sdb",
+        serial: Some("4C530001230905114170"),
+        model: "SanDisk Extreme Pro",
+        vendor_id: Some("0781"),
+        size_bytes: 63_864_569_856,
+        is_removable: true,
+    };
+
+    const NVME: RuleTarget<'static> = RuleTarget {
+        path: "/dev/nvme0n1",
+        serial: Some("S4EWNX0R123456"),
+        model: "Samsung SSD 980 PRO",
+        vendor_id: None,
+        size_bytes: 1_000_204_886_016,
+        is_removable: false,
+    };
+
+    fn rule(action: RuleAction) -> DeviceRule {
+        DeviceRule {
+            action,
+            ..DeviceRule::default()
+        }
+    }
+
+    #[test]
+    fn test_rule_without_criteria_matches_nothing() {
+        assert!(!rule(RuleAction::Deny).matches(&CARD));
+        assert_eq!(check(&[rule(RuleAction::Allow)], &CARD), Ok(()));
+    }
+
+    #[test]
+    fn test_deny_rules_hide_matching_devices() {
+        let rules = vec![DeviceRule {
+            path: Some("/dev/nvme*".to_string()),
+            ..rule(RuleAction::Deny)
+        }];
+        assert!(check(&rules, &NVME).unwrap_err().contains("/dev/nvme*"));
+        assert_eq!(check(&rules, &CARD), Ok(()));
+
+        let by_vendor = vec![DeviceRule {
+            vendor: Some("0781".to_string()),
+            ..rule(RuleAction::Deny)
+        }];
+        assert!(check(&by_vendor, &CARD).is_err());
+        let by_serial = vec![DeviceRule {
+            serial: Some("s4ewnx0r123456".to_string()),
+            ..rule(RuleAction::Deny)
+        }];
+        assert!(check(&by_serial, &NVME).is_err());
+    }
+
+    #[test]
+    fn test_allow_rules_hide_everything_else() {
+        let rules = vec![DeviceRule {
+            max_size_gb: Some(256),
+            removable: Some(true),
+            ..rule(RuleAction::Allow)
+        }];
+        assert_eq!(check(&rules, &CARD), Ok(()));
+        assert!(check(&rules, &NVME).is_err());
+
+        let large_card = RuleTarget {
+            size_bytes: 512_110_190_592,
+            ..CARD
+        };
+        assert!(check(&rules, &large_card).is_err());
+    }
+
+    #[test]
+    fn test_deny_wins_over_allow() {
+        let rules = vec![
+            DeviceRule {
+                vendor: Some("SanDisk*".to_string()),
+                ..rule(RuleAction::Allow)
+            },
+            DeviceRule {
+                serial: Some(CARD.serial.unwrap().to_string()),
+                ..rule(RuleAction::Deny)
+            },
+        ];
+        assert!(check(&rules, &CARD).unwrap_err().contains("serial"));
+    }
+
+    #[test]
+    fn test_size_range_uses_marketed_size() {
+        let rule = DeviceRule {
+            min_size_gb: Some(64),
+            max_size_gb: Some(64),
+            ..rule(RuleAction::Deny)
+        };
+        assert!(rule.matches(&CARD));
+        assert_eq!(rule.describe(), "64 to 64 GB");
+    }
+}
@@ -0,0 +1,153 @@
+/// Timeout and cancellation wrapper for blocking device probes
+///
+/// Opening a device or reading its partition table can block for a long time on flaky
+/// card readers. Probes run on the blocking thread pool and the caller stops waiting
+/// after a timeout, so one bad device never holds up the rest of the device list.
+use crate::models::CancelToken;
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tracing::warn;
+
+/// Default time a single device probe may take before it is abandoned
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time allowed for enumerating all devices
+pub const LIST_DEVICES_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How often the cancel token is checked while waiting for a probe
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Devices with a probe still running on the blocking pool
+///
+/// A blocking call can't be interrupted, so a timed-out probe keeps its thread until the
+/// OS call returns. New probes for the same device are refused until then instead of
+/// piling up more stuck threads on every refresh.
+static IN_FLIGHT: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Removes a device from [`IN_FLIGHT`] when dropped
+struct InFlightGuard(String);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Result of a probe run through [`probe_with_timeout`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProbeOutcome<T> {
+    /// The probe finished in time
+    Completed(T),
+    /// The probe didn't finish within the timeout
+    TimedOut,
+    /// The cancel token was triggered while waiting
+    Cancelled,
+    /// An earlier probe of the same device is still stuck
+    Busy,
+}
+
+/// Run a blocking probe against `device` with a timeout and cancellation
+///
+/// # Arguments
+/// * `device` - Device path, used to detect probes that are still stuck
+/// * `timeout` - How long to wait for the probe to finish
+/// * `cancel_token` - Stops waiting early when cancelled, e.g. on a new refresh
+/// * `probe` - Blocking operation to run on the blocking thread pool
+pub async fn probe_with_timeout<T, F>(
+    device: &str,
+    timeout: Duration,
+    cancel_token: &CancelToken,
+    probe: F,
+) -> ProbeOutcome<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    if cancel_token.is_cancelled() {
+        return ProbeOutcome::Cancelled;
+    }
+
+    if !IN_FLIGHT.lock().unwrap().insert(device.to_string()) {
+        warn!("Previous probe of {} is still running, skipping", device);
+        return ProbeOutcome::Busy;
+    }
+
+    let guard = InFlightGuard(device.to_string());
+    let handle = tokio::task::spawn_blocking(move || {
+        // Released when the blocking call returns, even if we stopped waiting for it
+        let _guard = guard;
+        probe()
+    });
+
+    let cancelled = async {
+        while !cancel_token.is_cancelled() {
+            tokio::time::sleep(CANCEL_POLL_INTERVAL).await;
+        }
+    };
+
+    tokio::select! {
+        result = tokio::time::timeout(timeout, handle) => match result {
+            Ok(Ok(value)) => ProbeOutcome::Completed(value),
+            Ok(Err(e)) => {
+                warn!("Probe of {} failed: {}", device, e);
+                ProbeOutcome::TimedOut
+            }
+            Err(_) => {
+                warn!("Probe of {} timed out after {:?}", device, timeout);
+                ProbeOutcome::TimedOut
+            }
+        },
+        _ = cancelled => ProbeOutcome::Cancelled,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_probe_completes() {
+        let token = CancelToken::new();
+        let outcome =
+            probe_with_timeout("test-complete", Duration::from_secs(1), &token, || 42).await;
+        assert_eq!(outcome, ProbeOutcome::Completed(42));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_stuck_probe_times_out_and_blocks_retries() {
+        let token = CancelToken::new();
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+
+        let outcome =
+            probe_with_timeout("test-stuck", Duration::from_millis(50), &token, move || {
+                wait.recv().ok();
+            })
+            .await;
+        assert_eq!(outcome, ProbeOutcome::TimedOut);
+
+        // The first probe is still blocked, so a second one must not start
+        let outcome = probe_with_timeout("test-stuck", Duration::from_secs(1), &token, || ()).await;
+        assert_eq!(outcome, ProbeOutcome::Busy);
+
+        // Once the stuck call returns the device can be probed again
+        release.send(()).unwrap();
+        for _ in 0..50 {
+            if !IN_FLIGHT.lock().unwrap().contains("test-stuck") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let outcome = probe_with_timeout("test-stuck", Duration::from_secs(1), &token, || 7).await;
+        assert_eq!(outcome, ProbeOutcome::Completed(7));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_cancelled_probe() {
+        let token = CancelToken::new();
+        token.cancel();
+        let outcome =
+            probe_with_timeout("test-cancelled", Duration::from_secs(1), &token, || 1).await;
+        assert_eq!(outcome, ProbeOutcome::Cancelled);
+    }
+}
@@ -98,12 +98,27 @@ pub fn request_elevation() -> Result<(), String> {
 
     let verb = "runas\0".encode_utf16().collect::<Vec<u16>>();
 
+    // The elevated instance has to keep using the portable files
+    let parameters = format!(
+        "{}\0",
+        if super::paths::is_portable() {
+            super::paths::PORTABLE_FLAG
+        } else {
+            ""
+        }
+    )
+    .encode_utf16()
+    .collect::<Vec<u16>>();
+
+    // The elevated instance takes over, which it couldn't while this one holds the lock
+    super::single_instance::release();
+
     unsafe {
         let result = ShellExecuteW(
             0 as HWND,
             verb.as_ptr(),
             exe_path_wide.as_ptr(),
-            ptr::null(),
+            parameters.as_ptr(),
             ptr::null(),
             SW_SHOWNORMAL,
         );
@@ -141,6 +156,74 @@ pub fn ensure_elevation(force_request: bool) -> Result<bool, String> {
     Ok(false)
 }
 
+/// How the application gets access to raw disks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivilegeMode {
+    /// Running as Administrator / root, devices are opened directly
+    Elevated,
+    /// Linux without root: devices are opened through UDisks2, which asks polkit for
+    /// authorization and shows the desktop's password prompt when needed
+    Polkit,
+    /// Devices are opened by the separately elevated `golem-disk-helper` process
+    Helper,
+    /// Disk operations will fail until the application is restarted with more rights
+    Unprivileged,
+}
+
+impl PrivilegeMode {
+    /// Whether flashing and editing can be offered at all
+    pub fn can_access_disks(self) -> bool {
+        !matches!(self, PrivilegeMode::Unprivileged)
+    }
+
+    /// Why elevated rights are needed, shown next to the elevation prompt
+    pub fn explanation(self) -> &'static str {
+        match self {
+            PrivilegeMode::Elevated => "Running with full access to storage devices",
+            PrivilegeMode::Helper => {
+                "Storage devices are opened by the privileged helper, the rest of the \
+                 application runs without elevated rights."
+            }
+            PrivilegeMode::Polkit => {
+                "Writing an image needs raw access to the device. Your system will ask for \
+                 your password when a device is opened."
+            }
+            PrivilegeMode::Unprivileged if cfg!(windows) => {
+                "Writing an image needs raw access to the device, and Windows only allows \
+                 that for administrators."
+            }
+            PrivilegeMode::Unprivileged => {
+                "Writing an image needs raw access to the device, which requires root or a \
+                 running UDisks2 service."
+            }
+        }
+    }
+}
+
+/// Determine how disks can be accessed by the current process
+pub fn privilege_mode() -> PrivilegeMode {
+    if is_elevated() {
+        return PrivilegeMode::Elevated;
+    }
+
+    if super::privileged_helper::is_running() {
+        return PrivilegeMode::Helper;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // UDisks2 is D-Bus activated, so a reachable system bus is enough to go through polkit
+        let system_bus_available = std::env::var_os("DBUS_SYSTEM_BUS_ADDRESS").is_some()
+            || std::path::Path::new("/run/dbus/system_bus_socket").exists()
+            || std::path::Path::new("/var/run/dbus/system_bus_socket").exists();
+        if system_bus_available {
+            return PrivilegeMode::Polkit;
+        }
+    }
+
+    PrivilegeMode::Unprivileged
+}
+
 /// Display elevation status information
 pub fn get_elevation_status() -> String {
     #[cfg(windows)]
@@ -148,6 +231,11 @@ pub fn get_elevation_status() -> String {
         let elevated = is_elevated();
         let admin_user = is_admin_user();
 
+        if !elevated && super::privileged_helper::is_running() {
+            return "Process is not elevated. Disk access goes through the privileged helper."
+                .to_string();
+        }
+
         match (elevated, admin_user) {
             (true, true) => "Running with administrator privileges".to_string(),
             (false, true) => {
@@ -164,10 +252,17 @@ pub fn get_elevation_status() -> String {
     #[cfg(not(windows))]
     {
         // On non-Windows systems, check if running as root
-        if unsafe { libc::geteuid() } == 0 {
-            "Running as root".to_string()
-        } else {
-            "Not running as root. Some operations may require sudo.".to_string()
+        match privilege_mode() {
+            PrivilegeMode::Elevated => "Running as root".to_string(),
+            PrivilegeMode::Polkit => {
+                "Not running as root. Disk access will be authorized through polkit.".to_string()
+            }
+            PrivilegeMode::Helper => {
+                "Not running as root. Disk access goes through the privileged helper.".to_string()
+            }
+            PrivilegeMode::Unprivileged => {
+                "Not running as root. Some operations may require sudo.".to_string()
+            }
         }
     }
 }
@@ -210,6 +305,16 @@ mod tests {
         assert!(!status.is_empty());
     }
 
+    #[test]
+    fn test_privilege_mode_consistency() {
+        let mode = privilege_mode();
+        assert_eq!(mode == PrivilegeMode::Elevated, is_elevated());
+        assert!(!mode.explanation().is_empty());
+        assert!(PrivilegeMode::Polkit.can_access_disks());
+        assert!(PrivilegeMode::Helper.can_access_disks());
+        assert!(!PrivilegeMode::Unprivileged.can_access_disks());
+    }
+
     #[test]
     #[cfg(windows)]
     fn test_admin_checks() {
@@ -1,20 +1,102 @@
 use ethereum_types::Address;
 use std::str::FromStr;
+use tiny_keccak::{Hasher, Keccak};
 
-/// Validates an Ethereum address with checksum validation
+/// Outcome of checking a wallet address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressCheck {
+    Valid,
+    /// Well-formed, but the mixed-case EIP-55 checksum doesn't match, usually a typo
+    BadChecksum,
+    Invalid,
+}
+
+/// Checks the format of an Ethereum address and its EIP-55 checksum
 ///
-/// Uses the ethers-core library for proper validation of EIP-55 checksummed addresses
-/// and standard address format.
+/// Addresses written all in lower or all in upper case carry no checksum and are only
+/// checked for their format.
+pub fn check_eth_address(address: &str) -> AddressCheck {
+    // Explicit check for 0x prefix - ethers will try to add it if missing
+    let Some(hex) = address.strip_prefix("0x") else {
+        return AddressCheck::Invalid;
+    };
+    if Address::from_str(address).is_err() {
+        return AddressCheck::Invalid;
+    }
+
+    let has_lower = hex.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = hex.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper && checksummed(hex) != hex {
+        return AddressCheck::BadChecksum;
+    }
+    AddressCheck::Valid
+}
+
+/// Validates an Ethereum address with checksum validation
 ///
-/// Returns true if the address is valid, false otherwise
+/// Returns true if the address is well-formed and, if it is written in mixed case, its
+/// EIP-55 checksum matches.
 pub fn is_valid_eth_address(address: &str) -> bool {
-    // Explicit check for 0x prefix - ethers will try to add it if missing
-    if !address.starts_with("0x") {
-        return false;
+    check_eth_address(address) == AddressCheck::Valid
+}
+
+/// Whether `address` is written in the mixed case of its EIP-55 checksum
+///
+/// All lower or upper case addresses are well-formed but can't be checked for typos.
+pub fn is_checksummed(address: &str) -> bool {
+    check_eth_address(address) == AddressCheck::Valid
+        && address
+            .strip_prefix("0x")
+            .is_some_and(|hex| checksummed(hex) == hex)
+}
+
+/// A wallet address as copied from a wallet app, web page or payment link, in its plain form
+///
+/// Surrounding whitespace and quotes, the `ethereum:` scheme of EIP-681 payment links and
+/// their `pay-` prefix, chain ID and parameters are removed, and a missing `0x` is added to
+/// 40 hex digits. Anything that isn't an address is returned trimmed, to be shown as invalid.
+pub fn normalize_eth_address(input: &str) -> String {
+    let mut address = input.trim().trim_matches(['"', '\'', '`']).trim();
+    if address
+        .get(..9)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("ethereum:"))
+    {
+        address = &address[9..];
+        address = address.strip_prefix("pay-").unwrap_or(address);
+        // ethereum:0x...@1/transfer?value=1
+        address = address.split(['@', '/', '?']).next().unwrap_or_default();
     }
+    let address = address.trim();
 
-    // Try to parse the address using ethers-core
-    Address::from_str(address).is_ok()
+    if address.len() == 40 && address.chars().all(|c| c.is_ascii_hexdigit()) {
+        format!("0x{}", address)
+    } else if let Some(hex) = address.strip_prefix("0X") {
+        format!("0x{}", hex)
+    } else {
+        address.to_string()
+    }
+}
+
+/// `hex`, the address without `0x`, in the letter case of its EIP-55 checksum
+fn checksummed(hex: &str) -> String {
+    let lower = hex.to_ascii_lowercase();
+    let mut hash = [0u8; 32];
+    let mut keccak = Keccak::v256();
+    keccak.update(lower.as_bytes());
+    keccak.finalize(&mut hash);
+
+    lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect()
 }
 
 /// Formats an Ethereum address with proper EIP-55 checksum
@@ -79,6 +161,62 @@ mod tests {
         assert!(!is_valid_eth_address("Abc"));
     }
 
+    #[test]
+    fn test_checksum() {
+        assert_eq!(
+            check_eth_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+            AddressCheck::Valid
+        );
+        // One letter in the wrong case
+        assert_eq!(
+            check_eth_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"),
+            AddressCheck::BadChecksum
+        );
+        assert!(!is_valid_eth_address(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"
+        ));
+        // No checksum to compare with
+        assert_eq!(
+            check_eth_address("0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"),
+            AddressCheck::Valid
+        );
+        assert_eq!(check_eth_address("0x5aAeb6053F3E94"), AddressCheck::Invalid);
+    }
+
+    #[test]
+    fn test_is_checksummed() {
+        assert!(is_checksummed("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+        assert!(!is_checksummed(
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+        ));
+        assert!(!is_checksummed(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"
+        ));
+        assert!(!is_checksummed(""));
+    }
+
+    #[test]
+    fn test_normalize_eth_address() {
+        let address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        assert_eq!(normalize_eth_address(address), address);
+        assert_eq!(normalize_eth_address(&format!("  {}\n", address)), address);
+        assert_eq!(normalize_eth_address(&format!("\"{}\"", address)), address);
+        assert_eq!(
+            normalize_eth_address(&format!("ethereum:{}", address)),
+            address
+        );
+        assert_eq!(
+            normalize_eth_address(&format!("Ethereum:pay-{}@137/transfer?value=1", address)),
+            address
+        );
+        assert_eq!(normalize_eth_address(&address[2..]), address);
+        assert_eq!(
+            normalize_eth_address("0X5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"),
+            "0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"
+        );
+        assert_eq!(normalize_eth_address(" not an address "), "not an address");
+    }
+
     #[test]
     fn test_format_eth_address() {
         // Test that formatting works correctly
@@ -0,0 +1,283 @@
+/// How much has been written to each device, for rotating worn media
+///
+/// Benches reflash the same SD cards over and over, and cards wear out after enough writes,
+/// usually by failing in the field rather than on the bench. Every flash adds the bytes it
+/// wrote to the record of the device's serial number in `flash_history.json` in the data
+/// directory, and the flash workflow warns once a card has been flashed unusually often, so
+/// it can be retired first. The speed of the last complete flash is kept as well, to estimate
+/// how long the next one will take. Devices that don't report a serial number aren't tracked,
+/// there is no telling them apart.
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+const HISTORY_FILE: &str = "flash_history.json";
+
+/// Number of flashes after which a device is reported as worn
+pub const WEAR_WARNING_FLASHES: u32 = 100;
+
+/// What was written to one device
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceWear {
+    /// Flashes that wrote to the device, whether they succeeded or not
+    pub flashes: u32,
+    pub bytes_written: u64,
+    /// Capacity in bytes when the device was last flashed
+    pub device_size: u64,
+    /// When the device was last flashed, in seconds since the Unix epoch
+    pub last_flashed: i64,
+    /// Average speed of the last flash that completed, in bytes per second; 0 if none did
+    #[serde(default)]
+    pub write_rate: u64,
+}
+
+impl DeviceWear {
+    /// How many times the whole capacity has been written
+    pub fn full_writes(&self) -> f64 {
+        if self.device_size > 0 {
+            self.bytes_written as f64 / self.device_size as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether the device has been flashed often enough to be replaced soon
+    pub fn is_worn(&self) -> bool {
+        self.flashes >= WEAR_WARNING_FLASHES
+    }
+
+    /// e.g. "Flashed 12 times, 96.0 GB written (1.5 full writes)"
+    pub fn describe(&self) -> String {
+        format!(
+            "Flashed {} {}, {} written ({:.1} full writes)",
+            self.flashes,
+            if self.flashes == 1 { "time" } else { "times" },
+            crate::disk::layout::format_size(self.bytes_written),
+            self.full_writes()
+        )
+    }
+}
+
+/// The speed a flash is expected to go at, as measured on earlier flashes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeasuredSpeed {
+    pub bytes_per_second: u64,
+    /// Whether it was measured on the target device rather than on the last device flashed
+    pub of_target: bool,
+}
+
+impl MeasuredSpeed {
+    /// How long writing and verifying `bytes` takes at this speed
+    pub fn estimate(&self, bytes: u64) -> Duration {
+        Duration::from_secs(bytes.div_ceil(self.bytes_per_second.max(1)))
+    }
+}
+
+/// The records of all devices, keyed by serial number or WWN
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FlashHistory {
+    #[serde(default)]
+    devices: BTreeMap<String, DeviceWear>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl FlashHistory {
+    /// Load the history, starting an empty one if there is none or it can't be read
+    pub fn load() -> Self {
+        let path = match super::paths::data_dir() {
+            Ok(dir) => dir.join(HISTORY_FILE),
+            Err(e) => {
+                warn!("{:#}", e);
+                return Self::default();
+            }
+        };
+        if !path.exists() {
+            return Self {
+                path: Some(path),
+                ..Self::default()
+            };
+        }
+        Self::load_from(&path).unwrap_or_else(|e| {
+            warn!("Starting a new flash history: {:#}", e);
+            Self {
+                path: Some(path),
+                ..Self::default()
+            }
+        })
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut history: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        history.path = Some(path.to_path_buf());
+        Ok(history)
+    }
+
+    /// The record of the device with this serial number, if it was flashed before
+    pub fn wear(&self, serial: &str) -> Option<&DeviceWear> {
+        self.devices.get(serial)
+    }
+
+    /// Speed to estimate a flash to the device with `serial` by
+    ///
+    /// That of the device itself if it was flashed completely before, otherwise that of the
+    /// device flashed last, which on a bench is usually the same kind of card.
+    pub fn measured_speed(&self, serial: Option<&str>) -> Option<MeasuredSpeed> {
+        let own = serial
+            .and_then(|serial| self.devices.get(serial))
+            .filter(|wear| wear.write_rate > 0)
+            .map(|wear| MeasuredSpeed {
+                bytes_per_second: wear.write_rate,
+                of_target: true,
+            });
+        own.or_else(|| {
+            self.devices
+                .values()
+                .filter(|wear| wear.write_rate > 0)
+                .max_by_key(|wear| wear.last_flashed)
+                .map(|wear| MeasuredSpeed {
+                    bytes_per_second: wear.write_rate,
+                    of_target: false,
+                })
+        })
+    }
+
+    /// Add a flash that wrote `bytes` to the device and save the history
+    ///
+    /// Flashes that ended before anything was written don't count. `write_rate` is the
+    /// average speed of a flash that completed, the device's measured speed is kept otherwise.
+    pub fn record(
+        &mut self,
+        serial: &str,
+        bytes: u64,
+        device_size: u64,
+        finished_at: i64,
+        write_rate: Option<u64>,
+    ) -> Result<()> {
+        if bytes == 0 {
+            return Ok(());
+        }
+        let wear = self.devices.entry(serial.to_string()).or_default();
+        wear.flashes += 1;
+        wear.bytes_written = wear.bytes_written.saturating_add(bytes);
+        wear.device_size = device_size;
+        wear.last_flashed = finished_at;
+        if let Some(rate) = write_rate.filter(|&rate| rate > 0) {
+            wear.write_rate = rate;
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let content =
+            serde_json::to_string_pretty(self).context("Failed to serialize the history")?;
+        std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn test_record_adds_up_and_is_saved() {
+        let path = std::env::temp_dir().join(format!(
+            "golem-history-{}/{}",
+            std::process::id(),
+            HISTORY_FILE
+        ));
+        let mut history = FlashHistory {
+            path: Some(path.clone()),
+            ..FlashHistory::default()
+        };
+        history
+            .record("SN123", 8 * GB, 32 * GB, 1_700_000_000, Some(40_000_000))
+            .unwrap();
+        history
+            .record("SN123", 8 * GB, 32 * GB, 1_700_000_100, None)
+            .unwrap();
+        let wear = history.wear("SN123").unwrap();
+        assert_eq!(wear.flashes, 2);
+        assert_eq!(wear.bytes_written, 16 * GB);
+        assert_eq!(wear.full_writes(), 0.5);
+        assert_eq!(wear.write_rate, 40_000_000);
+        assert_eq!(
+            wear.describe(),
+            "Flashed 2 times, 16.0 GB written (0.5 full writes)"
+        );
+
+        // A flash that failed before writing anything doesn't wear the card
+        history.record("SN123", 0, 32 * GB, 0, None).unwrap();
+        history.record("SN456", 0, 32 * GB, 0, None).unwrap();
+        assert_eq!(history.wear("SN123").unwrap().flashes, 2);
+        assert!(history.wear("SN456").is_none());
+
+        let loaded = FlashHistory::load_from(&path).unwrap();
+        assert_eq!(loaded.wear("SN123"), history.wear("SN123"));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_worn_after_many_flashes() {
+        let wear = DeviceWear {
+            flashes: WEAR_WARNING_FLASHES - 1,
+            ..DeviceWear::default()
+        };
+        assert!(!wear.is_worn());
+        assert_eq!(wear.full_writes(), 0.0);
+        assert!(
+            DeviceWear {
+                flashes: WEAR_WARNING_FLASHES,
+                ..wear
+            }
+            .is_worn()
+        );
+    }
+
+    #[test]
+    fn test_measured_speed() {
+        let mut history = FlashHistory::default();
+        assert_eq!(history.measured_speed(Some("SN123")), None);
+
+        history
+            .record("SN123", 8 * GB, 32 * GB, 1_700_000_000, Some(20_000_000))
+            .unwrap();
+        history
+            .record("SN456", 8 * GB, 32 * GB, 1_700_000_100, Some(50_000_000))
+            .unwrap();
+        history
+            .record("SN789", 8 * GB, 32 * GB, 1_700_000_200, None)
+            .unwrap();
+
+        let own = history.measured_speed(Some("SN123")).unwrap();
+        assert_eq!(
+            own,
+            MeasuredSpeed {
+                bytes_per_second: 20_000_000,
+                of_target: true
+            }
+        );
+        assert_eq!(own.estimate(100_000_000), Duration::from_secs(5));
+
+        // Devices without a complete flash get the speed of the one flashed last
+        let latest = history.measured_speed(Some("SN789")).unwrap();
+        assert_eq!(latest.bytes_per_second, 50_000_000);
+        assert!(!latest.of_target);
+        assert_eq!(history.measured_speed(None), Some(latest));
+    }
+}
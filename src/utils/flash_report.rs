@@ -0,0 +1,465 @@
+/// Reports documenting a finished flash
+///
+/// Hosting providers deploying nodes commercially have to document what was put on each
+/// device. A report lists the image and its hashes, the device, the configuration it got
+/// and when it was written, and is saved as a standalone HTML page or a PDF.
+use chrono::{DateTime, Local};
+
+/// Format a report is saved in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Html,
+    Pdf,
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Html => "html",
+            ReportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// Everything recorded about one flash
+#[derive(Debug, Clone)]
+pub struct FlashReport {
+    /// Channel the image was published in, e.g. `release`
+    pub image_channel: String,
+    pub image_version: String,
+    /// SHA-256 of the compressed image
+    pub compressed_sha256: String,
+    /// SHA-256 and size of the data written to the device, if the image was analyzed
+    pub uncompressed_sha256: Option<String>,
+    pub uncompressed_size: Option<u64>,
+    pub device_name: String,
+    /// Name the user gave the device, see [`crate::utils::device_nicknames`]
+    pub device_nickname: Option<String>,
+    pub device_path: String,
+    /// Serial number or WWN, if the device reports one
+    pub device_serial: Option<String>,
+    pub device_size: u64,
+    /// Configuration written to the device, as label and value
+    pub configuration: Vec<(String, String)>,
+    pub started_at: DateTime<Local>,
+    pub finished_at: Option<DateTime<Local>>,
+    /// The written data was read back and matched the image
+    pub verified: bool,
+}
+
+impl FlashReport {
+    /// Configuration summary for a report
+    ///
+    /// Only the number of SSH keys is listed and the contents of extra settings and the
+    /// first-boot file are left out, since reports are handed to third parties.
+    pub fn describe_configuration(
+        config: &crate::disk::ImageConfiguration,
+    ) -> Vec<(String, String)> {
+        let optional = |value: &Option<String>| {
+            value
+                .as_deref()
+                .filter(|value| !value.trim().is_empty())
+                .unwrap_or("(not set)")
+                .to_string()
+        };
+
+        let mut summary = vec![
+            (
+                "Payment network".to_string(),
+                format!("{:?}", config.payment_network),
+            ),
+            (
+                "Network type".to_string(),
+                format!("{:?}", config.network_type),
+            ),
+            ("Subnet".to_string(), config.subnet.clone()),
+            ("Wallet address".to_string(), config.glm_account.clone()),
+            ("Node name".to_string(), optional(&config.glm_node_name)),
+            (
+                "Non-interactive install".to_string(),
+                if config.non_interactive_install {
+                    "Yes".to_string()
+                } else {
+                    "No".to_string()
+                },
+            ),
+            ("SSH keys".to_string(), config.ssh_keys.len().to_string()),
+            (
+                "Configuration server".to_string(),
+                optional(&config.configuration_server),
+            ),
+            (
+                "Metrics server".to_string(),
+                optional(&config.metrics_server),
+            ),
+            (
+                "Central net host".to_string(),
+                optional(&config.central_net_host),
+            ),
+        ];
+        let extra_settings = config.extra_env.len() + config.extra_toml.len();
+        if extra_settings > 0 {
+            summary.push(("Extra settings".to_string(), extra_settings.to_string()));
+        }
+        if let Some(firstboot) = &config.firstboot {
+            summary.push((
+                "First-boot file".to_string(),
+                format!(
+                    "{} ({} bytes)",
+                    firstboot.kind.file_name(),
+                    firstboot.content.len()
+                ),
+            ));
+        }
+        summary
+    }
+
+    /// Suggested file name, e.g. `flash-report-4C5300012309-20250101-120000.pdf`
+    pub fn file_name(&self, format: ReportFormat) -> String {
+        let device: String = self
+            .device_serial
+            .as_deref()
+            .unwrap_or(&self.device_name)
+            .trim()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let device = device.trim_matches('_');
+        let device = if device.is_empty() { "device" } else { device };
+
+        format!(
+            "flash-report-{}-{}.{}",
+            device,
+            self.started_at.format("%Y%m%d-%H%M%S"),
+            format.extension()
+        )
+    }
+
+    /// Report contents as titled sections of label/value rows
+    pub fn sections(&self) -> Vec<(&'static str, Vec<(String, String)>)> {
+        let timestamp = |time: &DateTime<Local>| time.format("%Y-%m-%d %H:%M:%S %:z").to_string();
+        let unknown = || "(unknown)".to_string();
+
+        let result = vec![
+            (
+                "Status".to_string(),
+                if self.verified {
+                    "Written and verified".to_string()
+                } else {
+                    "Written, verification skipped".to_string()
+                },
+            ),
+            ("Started".to_string(), timestamp(&self.started_at)),
+            (
+                "Finished".to_string(),
+                self.finished_at.as_ref().map_or_else(unknown, timestamp),
+            ),
+            (
+                "Imager version".to_string(),
+                env!("CARGO_PKG_VERSION").to_string(),
+            ),
+        ];
+        let image = vec![
+            ("Channel".to_string(), self.image_channel.clone()),
+            ("Version".to_string(), self.image_version.clone()),
+            (
+                "SHA-256 (compressed)".to_string(),
+                self.compressed_sha256.clone(),
+            ),
+            (
+                "SHA-256 (written data)".to_string(),
+                self.uncompressed_sha256.clone().unwrap_or_else(unknown),
+            ),
+            (
+                "Size".to_string(),
+                self.uncompressed_size
+                    .map_or_else(unknown, |size| format!("{} bytes", size)),
+            ),
+        ];
+        let mut device = vec![("Name".to_string(), self.device_name.clone())];
+        if let Some(nickname) = &self.device_nickname {
+            device.push(("Nickname".to_string(), nickname.clone()));
+        }
+        device.extend([
+            ("Path".to_string(), self.device_path.clone()),
+            (
+                "Serial number".to_string(),
+                self.device_serial.clone().unwrap_or_else(unknown),
+            ),
+            ("Size".to_string(), format!("{} bytes", self.device_size)),
+        ]);
+
+        vec![
+            ("Result", result),
+            ("Image", image),
+            ("Device", device),
+            ("Configuration", self.configuration.clone()),
+        ]
+    }
+
+    /// The report as a standalone HTML page that also prints well
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Golem GPU Imager Flash Report</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em auto; max-width: 50em; color: #222; }\n\
+             h1 { font-size: 1.6em; }\n\
+             h2 { font-size: 1.2em; margin-top: 1.5em; border-bottom: 1px solid #ccc; }\n\
+             table { border-collapse: collapse; width: 100%; }\n\
+             th { text-align: left; width: 14em; font-weight: normal; color: #555; }\n\
+             th, td { padding: 0.25em 0.5em 0.25em 0; vertical-align: top; }\n\
+             td { font-family: monospace; word-break: break-all; }\n\
+             </style>\n</head>\n<body>\n<h1>Golem GPU Imager Flash Report</h1>\n",
+        );
+        for (title, rows) in self.sections() {
+            html.push_str(&format!("<h2>{}</h2>\n<table>\n", escape_html(title)));
+            for (label, value) in rows {
+                html.push_str(&format!(
+                    "<tr><th>{}</th><td>{}</td></tr>\n",
+                    escape_html(&label),
+                    escape_html(&value)
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// The report as a PDF with one line per row, continued on further pages if needed
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let mut lines = vec![PdfLine::Title("Golem GPU Imager Flash Report".to_string())];
+        for (title, rows) in self.sections() {
+            lines.push(PdfLine::Heading(title.to_string()));
+            for (label, value) in rows {
+                let mut chunks = wrap(&value, PDF_VALUE_WIDTH).into_iter();
+                lines.push(PdfLine::Row(label, chunks.next().unwrap_or_default()));
+                lines.extend(chunks.map(|chunk| PdfLine::Row(String::new(), chunk)));
+            }
+        }
+        write_pdf(&lines)
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A4 in PDF points
+const PDF_PAGE_SIZE: (u32, u32) = (595, 842);
+const PDF_MARGIN: u32 = 56;
+/// Characters of a value per line, so hashes fit next to the labels
+const PDF_VALUE_WIDTH: usize = 64;
+
+enum PdfLine {
+    Title(String),
+    Heading(String),
+    Row(String, String),
+}
+
+impl PdfLine {
+    /// Vertical space taken by the line, in points
+    fn height(&self) -> u32 {
+        match self {
+            PdfLine::Title(_) => 30,
+            PdfLine::Heading(_) => 26,
+            PdfLine::Row(..) => 14,
+        }
+    }
+}
+
+/// Split `text` into lines of at most `width` characters
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(width)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+/// Escape text for a PDF string literal using the standard Helvetica encoding
+fn pdf_string(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+/// Content stream of a page
+fn pdf_page_content(lines: &[&PdfLine]) -> String {
+    let (_, page_height) = PDF_PAGE_SIZE;
+    let mut content = String::new();
+    let mut y = page_height - PDF_MARGIN;
+    for line in lines {
+        y -= line.height();
+        match line {
+            PdfLine::Title(text) => content.push_str(&format!(
+                "BT /F2 18 Tf {} {} Td ({}) Tj ET\n",
+                PDF_MARGIN,
+                y,
+                pdf_string(text)
+            )),
+            PdfLine::Heading(text) => content.push_str(&format!(
+                "BT /F2 12 Tf {} {} Td ({}) Tj ET\n",
+                PDF_MARGIN,
+                y,
+                pdf_string(text)
+            )),
+            PdfLine::Row(label, value) => content.push_str(&format!(
+                "BT /F1 9 Tf {} {} Td ({}) Tj ET\nBT /F3 9 Tf {} {} Td ({}) Tj ET\n",
+                PDF_MARGIN,
+                y,
+                pdf_string(label),
+                PDF_MARGIN + 130,
+                y,
+                pdf_string(value)
+            )),
+        }
+    }
+    content
+}
+
+/// Lay out the lines on A4 pages and serialize the document
+fn write_pdf(lines: &[PdfLine]) -> Vec<u8> {
+    let (page_width, page_height) = PDF_PAGE_SIZE;
+    let usable_height = page_height - 2 * PDF_MARGIN;
+    let mut pages: Vec<Vec<&PdfLine>> = vec![Vec::new()];
+    let mut used = 0;
+    for line in lines {
+        if used + line.height() > usable_height && !pages[pages.len() - 1].is_empty() {
+            pages.push(Vec::new());
+            used = 0;
+        }
+        used += line.height();
+        pages.last_mut().unwrap().push(line);
+    }
+
+    // Objects 1-5 are the catalog, page tree and fonts; each page adds a page and its content
+    let page_ids: Vec<usize> = (0..pages.len()).map(|index| 6 + 2 * index).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold >>".to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+    for (page, id) in pages.iter().zip(&page_ids) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+            page_width,
+            page_height,
+            id + 1
+        ));
+        let content = pdf_page_content(page);
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", index + 1, object).as_bytes());
+    }
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn report() -> FlashReport {
+        FlashReport {
+            image_channel: "release".to_string(),
+            image_version: "v0.1.5".to_string(),
+            compressed_sha256: "ab".repeat(32),
+            uncompressed_sha256: Some("cd".repeat(32)),
+            uncompressed_size: Some(8_589_934_592),
+            device_name: "SanDisk <Extreme>".to_string(),
+            device_nickname: Some("Bench slot 3".to_string()),
+            device_path: "/dev/sdb".to_string(),
+            device_serial: Some("4C5300012309".to_string()),
+            device_size: 63_864_569_856,
+            configuration: vec![("Subnet".to_string(), "public".to_string())],
+            started_at: Local.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap(),
+            finished_at: None,
+            verified: true,
+        }
+    }
+
+    #[test]
+    fn test_file_name() {
+        let mut report = report();
+        assert_eq!(
+            report.file_name(ReportFormat::Pdf),
+            "flash-report-4C5300012309-20250101-120000.pdf"
+        );
+        report.device_serial = None;
+        assert_eq!(
+            report.file_name(ReportFormat::Html),
+            "flash-report-SanDisk__Extreme-20250101-120000.html"
+        );
+    }
+
+    #[test]
+    fn test_html_escapes_values() {
+        let html = report().to_html();
+        assert!(html.contains("SanDisk &lt;Extreme&gt;"));
+        assert!(html.contains("Bench slot 3"));
+        assert!(html.contains(&"cd".repeat(32)));
+        assert!(html.contains("Written and verified"));
+    }
+
+    #[test]
+    fn test_pdf_structure() {
+        let pdf = report().to_pdf();
+        let text = String::from_utf8(pdf).unwrap();
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("(SanDisk <Extreme>) Tj"));
+
+        // startxref has to point at the cross-reference table
+        let start = text.rsplit("startxref\n").next().unwrap();
+        let offset: usize = start.lines().next().unwrap().parse().unwrap();
+        assert!(text[offset..].starts_with("xref\n"));
+    }
+
+    #[test]
+    fn test_pdf_string() {
+        assert_eq!(pdf_string("a (b) \\ ü"), "a \\(b\\) \\\\ ?");
+    }
+}
@@ -0,0 +1,354 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+/// Index of cached images, stored next to them in the cache directory
+const INDEX_FILE: &str = "cache-index.json";
+
+/// Default limit for the total size of cached images (40 GiB, about three images)
+pub const DEFAULT_SIZE_LIMIT: u64 = 40 * 1024 * 1024 * 1024;
+
+/// A downloaded image in the cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// File name inside the cache directory
+    pub file_name: String,
+    /// SHA-256 of the compressed image
+    pub sha256: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Modification time (seconds since the epoch) when the hash was last checked
+    pub modified: u64,
+    /// When the image was last downloaded or flashed (seconds since the epoch)
+    pub last_used: u64,
+    /// Pinned images are never evicted
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheIndex {
+    /// `None` means the cache may grow without limit
+    #[serde(default = "default_size_limit")]
+    size_limit: Option<u64>,
+    #[serde(default)]
+    entries: Vec<CacheEntry>,
+}
+
+fn default_size_limit() -> Option<u64> {
+    Some(DEFAULT_SIZE_LIMIT)
+}
+
+impl Default for CacheIndex {
+    fn default() -> Self {
+        Self {
+            size_limit: default_size_limit(),
+            entries: Vec::new(),
+        }
+    }
+}
+
+/// Tracks downloaded images so old ones can be evicted when the cache grows too large
+pub struct ImageCache {
+    dir: PathBuf,
+    index: CacheIndex,
+}
+
+impl ImageCache {
+    /// Load the cache index from `dir`, forgetting images whose files were removed
+    pub fn open(dir: &Path) -> Self {
+        let index_path = dir.join(INDEX_FILE);
+        let mut index = match fs::read_to_string(&index_path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable cache index {:?}: {}", index_path, e);
+                CacheIndex::default()
+            }),
+            Err(_) => CacheIndex::default(),
+        };
+        index
+            .entries
+            .retain(|entry| dir.join(&entry.file_name).is_file());
+
+        debug!(
+            "Image cache at {:?} holds {} image(s)",
+            dir,
+            index.entries.len()
+        );
+        Self {
+            dir: dir.to_path_buf(),
+            index,
+        }
+    }
+
+    /// All cached images, most recently used first
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        let mut entries = self.index.entries.clone();
+        entries.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+        entries
+    }
+
+    /// Combined size of all cached images
+    pub fn total_size(&self) -> u64 {
+        self.index.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    pub fn size_limit(&self) -> Option<u64> {
+        self.index.size_limit
+    }
+
+    /// Change the size limit and evict images that no longer fit
+    pub fn set_size_limit(&mut self, size_limit: Option<u64>) -> Result<Vec<String>> {
+        self.index.size_limit = size_limit;
+        self.save()?;
+        self.evict(None)
+    }
+
+    /// Add a freshly downloaded and verified image
+    pub fn record(&mut self, file_name: &str, sha256: &str) -> Result<()> {
+        let (size, modified) = file_stamp(&self.dir.join(file_name))?;
+        let pinned = self.entry_mut(file_name).is_some_and(|entry| entry.pinned);
+        self.index
+            .entries
+            .retain(|entry| entry.file_name != file_name);
+        self.index.entries.push(CacheEntry {
+            file_name: file_name.to_string(),
+            sha256: sha256.to_lowercase(),
+            size,
+            modified,
+            last_used: now(),
+            pinned,
+        });
+        self.save()
+    }
+
+    /// Mark an image as just used so it is evicted last
+    pub fn touch(&mut self, file_name: &str) -> Result<()> {
+        if let Some(entry) = self.entry_mut(file_name) {
+            entry.last_used = now();
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn set_pinned(&mut self, file_name: &str, pinned: bool) -> Result<()> {
+        if let Some(entry) = self.entry_mut(file_name) {
+            entry.pinned = pinned;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Delete an image from the cache
+    pub fn remove(&mut self, file_name: &str) -> Result<()> {
+        let path = self.dir.join(file_name);
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("Failed to delete {:?}", path))?;
+        }
+        self.index
+            .entries
+            .retain(|entry| entry.file_name != file_name);
+        info!("Removed cached image {}", file_name);
+        self.save()
+    }
+
+    /// Delete every image that isn't pinned
+    pub fn clear(&mut self) -> Result<()> {
+        let unpinned: Vec<String> = self
+            .index
+            .entries
+            .iter()
+            .filter(|entry| !entry.pinned)
+            .map(|entry| entry.file_name.clone())
+            .collect();
+        for file_name in unpinned {
+            self.remove(&file_name)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the least recently used unpinned images until the cache fits its limit
+    ///
+    /// # Arguments
+    /// * `keep` - Image that must survive, e.g. the one just downloaded
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>>` - File names of the evicted images
+    pub fn evict(&mut self, keep: Option<&str>) -> Result<Vec<String>> {
+        let Some(limit) = self.index.size_limit else {
+            return Ok(Vec::new());
+        };
+
+        let mut candidates: Vec<CacheEntry> = self
+            .index
+            .entries
+            .iter()
+            .filter(|entry| !entry.pinned && Some(entry.file_name.as_str()) != keep)
+            .cloned()
+            .collect();
+        candidates.sort_by(|a, b| a.last_used.cmp(&b.last_used));
+
+        let mut evicted = Vec::new();
+        for entry in candidates {
+            if self.total_size() <= limit {
+                break;
+            }
+            info!(
+                "Evicting {} from the image cache to stay under {} bytes",
+                entry.file_name, limit
+            );
+            self.remove(&entry.file_name)?;
+            evicted.push(entry.file_name);
+        }
+        Ok(evicted)
+    }
+
+    /// Check that a cached image is still intact before it is reused
+    ///
+    /// The full hash is only recomputed when the file's size or modification time changed
+    /// since it was last checked. Images that fail the check are deleted.
+    pub fn validate(&mut self, file_name: &str, sha256: &str) -> bool {
+        let path = self.dir.join(file_name);
+        let Ok((size, modified)) = file_stamp(&path) else {
+            return false;
+        };
+
+        let unchanged = self.entry_mut(file_name).is_some_and(|entry| {
+            entry.size == size
+                && entry.modified == modified
+                && entry.sha256.eq_ignore_ascii_case(sha256)
+        });
+        if unchanged {
+            return true;
+        }
+
+        info!("Cached image {} changed, verifying its hash", file_name);
+        match hash_file(&path) {
+            Ok(hash) if hash.eq_ignore_ascii_case(sha256) => {
+                if let Err(e) = self.record(file_name, sha256) {
+                    warn!("Failed to update cache index: {}", e);
+                }
+                true
+            }
+            Ok(hash) => {
+                warn!(
+                    "Cached image {} is corrupted (expected {}, got {}), deleting it",
+                    file_name, sha256, hash
+                );
+                if let Err(e) = self.remove(file_name) {
+                    warn!("Failed to delete corrupted image: {}", e);
+                }
+                false
+            }
+            Err(e) => {
+                warn!("Failed to verify cached image {}: {}", file_name, e);
+                false
+            }
+        }
+    }
+
+    fn entry_mut(&mut self, file_name: &str) -> Option<&mut CacheEntry> {
+        self.index
+            .entries
+            .iter_mut()
+            .find(|entry| entry.file_name == file_name)
+    }
+
+    fn save(&self) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_string_pretty(&self.index)?;
+        fs::write(self.dir.join(INDEX_FILE), json).context("Failed to write cache index")
+    }
+}
+
+/// Size and modification time of a file
+fn file_stamp(path: &Path) -> Result<(u64, u64)> {
+    let metadata = fs::metadata(path).with_context(|| format!("Failed to stat {:?}", path))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs());
+    Ok((metadata.len(), modified))
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 4 * 1024 * 1024];
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("golem-image-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn add_image(cache: &mut ImageCache, dir: &Path, name: &str, data: &[u8], last_used: u64) {
+        fs::write(dir.join(name), data).unwrap();
+        cache
+            .record(name, &hex::encode(Sha256::digest(data)))
+            .unwrap();
+        cache.entry_mut(name).unwrap().last_used = last_used;
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_unpinned() {
+        let dir = temp_cache_dir("evict");
+        let mut cache = ImageCache::open(&dir);
+        add_image(&mut cache, &dir, "old.img.xz", &[1; 100], 1);
+        add_image(&mut cache, &dir, "pinned.img.xz", &[2; 100], 0);
+        add_image(&mut cache, &dir, "new.img.xz", &[3; 100], 3);
+        cache.set_pinned("pinned.img.xz", true).unwrap();
+
+        let evicted = cache.set_size_limit(Some(250)).unwrap();
+        assert_eq!(evicted, vec!["old.img.xz".to_string()]);
+        assert!(!dir.join("old.img.xz").exists());
+        assert!(dir.join("pinned.img.xz").exists());
+
+        // The index survives a restart
+        let reopened = ImageCache::open(&dir);
+        assert_eq!(reopened.total_size(), 200);
+        assert_eq!(reopened.size_limit(), Some(250));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_validate_deletes_modified_image() {
+        let dir = temp_cache_dir("validate");
+        let mut cache = ImageCache::open(&dir);
+        add_image(&mut cache, &dir, "image.img.xz", b"original", 1);
+        let sha256 = hex::encode(Sha256::digest(b"original"));
+        assert!(cache.validate("image.img.xz", &sha256));
+
+        fs::write(dir.join("image.img.xz"), b"tampered!").unwrap();
+        assert!(!cache.validate("image.img.xz", &sha256));
+        assert!(!dir.join("image.img.xz").exists());
+        assert!(cache.entries().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,267 @@
+/// Metadata of image files picked by the user, which have no manifest entry
+///
+/// The uncompressed size and hash of a local image are found by reading it once, so the
+/// write can be verified like that of a repository image. The result is cached under the
+/// file's path, size and modification time: picking the same file again is instant, while
+/// a rebuilt image is analyzed anew.
+use crate::disk::BlockHasher;
+use crate::models::{CancelToken, ImageMetadata};
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, info, warn};
+
+const BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// What is known about the contents of a local image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageAnalysis {
+    pub metadata: ImageMetadata,
+    /// SHA-256 of each block of the uncompressed image, if asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hashes: Option<Vec<String>>,
+}
+
+/// A cached analysis and the state of the file it was made of
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    path: PathBuf,
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+    analysis: ImageAnalysis,
+}
+
+impl CacheEntry {
+    fn matches(&self, key: &FileKey) -> bool {
+        self.path == key.path
+            && self.size == key.size
+            && self.modified_secs == key.modified_secs
+            && self.modified_nanos == key.modified_nanos
+    }
+}
+
+/// Identity of a version of an image file
+struct FileKey {
+    path: PathBuf,
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+impl FileKey {
+    fn of(path: &Path) -> Result<Self> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Failed to find {}", path.display()))?;
+        let metadata = std::fs::metadata(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Ok(Self {
+            path,
+            size: metadata.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+        })
+    }
+
+    /// Name of the cache file, one per image path
+    fn cache_file_name(&self) -> String {
+        let path_hash = Sha256::digest(self.path.to_string_lossy().as_bytes());
+        format!("local-{}.metadata.json", &hex::encode(path_hash)[..16])
+    }
+}
+
+/// Analyze a local image, using the cached result if the file hasn't changed since
+///
+/// `on_progress` is called with the uncompressed bytes read so far. Must be called from a
+/// blocking thread.
+pub fn analyze(
+    path: &Path,
+    with_block_hashes: bool,
+    cancel_token: &CancelToken,
+    on_progress: impl FnMut(u64),
+) -> Result<ImageAnalysis> {
+    let cache_dir = super::paths::data_dir()?;
+    analyze_with_cache(
+        &cache_dir,
+        path,
+        with_block_hashes,
+        cancel_token,
+        on_progress,
+    )
+}
+
+fn analyze_with_cache(
+    cache_dir: &Path,
+    path: &Path,
+    with_block_hashes: bool,
+    cancel_token: &CancelToken,
+    on_progress: impl FnMut(u64),
+) -> Result<ImageAnalysis> {
+    let key = FileKey::of(path)?;
+    let cache_path = cache_dir.join(key.cache_file_name());
+
+    if let Some(analysis) = load_cached(&cache_path, &key)
+        .filter(|analysis| !with_block_hashes || analysis.block_hashes.is_some())
+    {
+        debug!("Using cached metadata of {}", path.display());
+        return Ok(analysis);
+    }
+
+    info!("Analyzing image file {}", path.display());
+    let mut image = crate::disk::open_image_file(path)?;
+    let analysis = analyze_data(&mut image, with_block_hashes, cancel_token, on_progress)?;
+
+    let entry = CacheEntry {
+        path: key.path,
+        size: key.size,
+        modified_secs: key.modified_secs,
+        modified_nanos: key.modified_nanos,
+        analysis,
+    };
+    if let Err(e) = store_cached(&cache_path, &entry) {
+        warn!("Failed to cache metadata of {}: {:#}", path.display(), e);
+    }
+    Ok(entry.analysis)
+}
+
+/// Hash uncompressed image data
+fn analyze_data(
+    image: &mut dyn Read,
+    with_block_hashes: bool,
+    cancel_token: &CancelToken,
+    mut on_progress: impl FnMut(u64),
+) -> Result<ImageAnalysis> {
+    let mut hasher = Sha256::new();
+    let mut blocks = with_block_hashes.then(BlockHasher::new);
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut total = 0u64;
+    loop {
+        if cancel_token.is_cancelled() {
+            return Err(anyhow!("Image analysis cancelled"));
+        }
+        let bytes_read = match image.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(anyhow!("The image file can't be read: {}", e)),
+        };
+        hasher.update(&buffer[..bytes_read]);
+        if let Some(blocks) = &mut blocks {
+            blocks.update(&buffer[..bytes_read]);
+        }
+        total += bytes_read as u64;
+        on_progress(total);
+    }
+    if total == 0 {
+        return Err(anyhow!("The image file is empty"));
+    }
+
+    let uncompressed_hash = hex::encode(hasher.finalize());
+    info!(
+        "Local image holds {} bytes, hash {}",
+        total,
+        &uncompressed_hash[..16]
+    );
+    Ok(ImageAnalysis {
+        metadata: ImageMetadata {
+            // There is no compressed hash to check a local file against
+            compressed_hash: String::new(),
+            uncompressed_hash,
+            uncompressed_size: total,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        },
+        block_hashes: blocks.map(|blocks| blocks.finish().iter().map(hex::encode).collect()),
+    })
+}
+
+fn load_cached(cache_path: &Path, key: &FileKey) -> Option<ImageAnalysis> {
+    let content = std::fs::read_to_string(cache_path).ok()?;
+    let entry: CacheEntry = serde_json::from_str(&content)
+        .inspect_err(|e| warn!("Ignoring {}: {}", cache_path.display(), e))
+        .ok()?;
+    entry.matches(key).then_some(entry.analysis)
+}
+
+fn store_cached(cache_path: &Path, entry: &CacheEntry) -> Result<()> {
+    if let Some(dir) = cache_path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let content = serde_json::to_string_pretty(entry)?;
+    std::fs::write(cache_path, content)
+        .with_context(|| format!("Failed to write {}", cache_path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("golem-image-meta-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_analysis_is_cached_until_file_changes() {
+        let dir = temp_dir("cache");
+        let image = dir.join("golem.img");
+        let mut data = vec![0u8; 8192];
+        data[510] = 0x55;
+        data[511] = 0xAA;
+        std::fs::write(&image, &data).unwrap();
+        let cancel_token = CancelToken::new();
+
+        let mut reads = 0;
+        let analysis =
+            analyze_with_cache(&dir, &image, false, &cancel_token, |_| reads += 1).unwrap();
+        assert_eq!(analysis.metadata.uncompressed_size, 8192);
+        assert_eq!(
+            analysis.metadata.uncompressed_hash,
+            hex::encode(Sha256::digest(&data))
+        );
+        assert_eq!(analysis.block_hashes, None);
+        assert!(reads > 0);
+
+        // Unchanged file: served from the cache
+        let mut reads = 0;
+        let cached =
+            analyze_with_cache(&dir, &image, false, &cancel_token, |_| reads += 1).unwrap();
+        assert_eq!(cached, analysis);
+        assert_eq!(reads, 0);
+
+        // Block hashes weren't computed the first time
+        let with_blocks = analyze_with_cache(&dir, &image, true, &cancel_token, |_| {}).unwrap();
+        assert_eq!(with_blocks.block_hashes.map(|hashes| hashes.len()), Some(1));
+
+        // A different size invalidates the entry
+        data.extend_from_slice(&[1u8; 4096]);
+        std::fs::write(&image, &data).unwrap();
+        let changed = analyze_with_cache(&dir, &image, false, &cancel_token, |_| {}).unwrap();
+        assert_eq!(changed.metadata.uncompressed_size, 12288);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_empty_image_is_rejected() {
+        let error = analyze_data(
+            &mut std::io::Cursor::new(Vec::new()),
+            false,
+            &CancelToken::new(),
+            |_| {},
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("empty"));
+    }
+}
@@ -1,6 +1,5 @@
 use crate::models::ImageMetadata;
 use anyhow::Result;
-use directories::ProjectDirs;
 use std::fs;
 use std::path::PathBuf;
 use tracing::{debug, error, info};
@@ -8,31 +7,28 @@ use tracing::{debug, error, info};
 /// Manages storage and retrieval of image metadata
 #[derive(Clone)]
 pub struct MetadataManager {
-    project_dirs: ProjectDirs,
+    data_dir: PathBuf,
 }
 
 impl MetadataManager {
     /// Create a new MetadataManager instance
     pub fn new() -> Result<Self> {
-        let project_dirs = ProjectDirs::from("network", "Golem Factory", "GPU Imager")
-            .ok_or_else(|| anyhow::anyhow!("Failed to get project directories"))?;
+        let data_dir = crate::utils::paths::data_dir()?;
 
         // Ensure data directory exists
-        let data_dir = project_dirs.data_dir();
         if !data_dir.exists() {
-            fs::create_dir_all(data_dir)?;
+            fs::create_dir_all(&data_dir)?;
             info!("Created metadata data directory: {:?}", data_dir);
         }
 
         debug!("MetadataManager using data directory: {:?}", data_dir);
 
-        Ok(Self { project_dirs })
+        Ok(Self { data_dir })
     }
 
     /// Get the path for storing metadata for a given compressed image hash
     fn get_metadata_path(&self, compressed_hash: &str) -> PathBuf {
-        self.project_dirs
-            .data_dir()
+        self.data_dir
             .join(format!("{}.metadata.json", compressed_hash))
     }
 
@@ -104,7 +100,7 @@ impl MetadataManager {
     /// List all images that have metadata stored
     #[allow(dead_code)]
     pub fn list_images_with_metadata(&self) -> Result<Vec<String>> {
-        let data_dir = self.project_dirs.data_dir();
+        let data_dir = &self.data_dir;
         let mut hashes = Vec::new();
 
         if !data_dir.exists() {
@@ -162,6 +158,7 @@ impl MetadataManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use directories::ProjectDirs;
     use tempfile::TempDir;
 
     fn create_test_metadata() -> ImageMetadata {
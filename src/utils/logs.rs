@@ -0,0 +1,328 @@
+/// Access to the application's log files
+///
+/// The log is written to a file that rotates daily, e.g. `golem-gpu-imager.log.2025-01-01`
+/// in the data directory. On Windows that is buried in AppData, so the log viewer reads the
+/// end of the newest file and puts it together with some details about the system for bug
+/// reports. How much is logged and how long the files are kept is set on the settings
+/// screen; `RUST_LOG` still takes precedence for developers.
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+/// Name of the log files before the date suffix added by the rotation
+pub const LOG_FILE_NAME: &str = "golem-gpu-imager.log";
+
+/// How much of the end of a log file is read
+const MAX_TAIL_BYTES: u64 = 512 * 1024;
+
+/// Number of log lines included in copied diagnostics
+const DIAGNOSTICS_LINES: usize = 300;
+
+/// Variable that overrides the configured log level
+const ENV_FILTER_VAR: &str = "RUST_LOG";
+
+/// Lets the log level be changed while the application runs
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Log files in `dir`, newest first
+pub fn log_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list log files in {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let is_log = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(LOG_FILE_NAME));
+        if is_log && path.is_file() {
+            files.push(path);
+        }
+    }
+    // The date suffix sorts chronologically
+    files.sort();
+    files.reverse();
+    Ok(files)
+}
+
+/// The file the application is currently logging to, if there is one
+pub fn latest_log_file() -> Result<Option<PathBuf>> {
+    let dir = super::paths::log_dir()?;
+    if !dir.exists() {
+        return Ok(None);
+    }
+    Ok(log_files(&dir)?.into_iter().next())
+}
+
+/// Severity of a log line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    fn parse(token: &str) -> Option<Self> {
+        match token {
+            "TRACE" => Some(Self::Trace),
+            "DEBUG" => Some(Self::Debug),
+            "INFO" => Some(Self::Info),
+            "WARN" => Some(Self::Warn),
+            "ERROR" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    fn directive(self) -> &'static str {
+        match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Filter that logs this application at `level`
+///
+/// Other crates log at most at info level, their debug output would drown ours.
+pub fn filter_directive(level: LogLevel) -> String {
+    format!(
+        "{},golem_gpu_imager={},iced_winit=error",
+        level.max(LogLevel::Info).directive(),
+        level.directive()
+    )
+}
+
+/// Whether `RUST_LOG` is set, in which case the configured level is ignored
+pub fn level_overridden() -> bool {
+    std::env::var_os(ENV_FILTER_VAR).is_some()
+}
+
+/// The filter to start logging with: `RUST_LOG` if set, otherwise the configured level
+pub fn initial_filter(level: LogLevel) -> EnvFilter {
+    EnvFilter::try_from_env(ENV_FILTER_VAR)
+        .unwrap_or_else(|_| EnvFilter::new(filter_directive(level)))
+}
+
+/// Remember the handle of the filter so `set_level` can replace it
+pub fn set_filter_handle(handle: reload::Handle<EnvFilter, Registry>) {
+    let _ = FILTER_HANDLE.set(handle);
+}
+
+/// Change how much is logged from now on
+pub fn set_level(level: LogLevel) -> Result<()> {
+    if level_overridden() {
+        return Ok(());
+    }
+    let handle = FILTER_HANDLE
+        .get()
+        .ok_or_else(|| anyhow!("Logging has not been set up"))?;
+    handle
+        .reload(EnvFilter::new(filter_directive(level)))
+        .context("Failed to change the log level")
+}
+
+/// Delete the oldest log files until all of them together are at most `max_bytes`
+///
+/// The newest file is kept even if it's larger, it is still being written to.
+///
+/// # Returns
+/// * `Result<usize>` - Number of files deleted
+pub fn prune_logs(dir: &Path, max_bytes: u64) -> Result<usize> {
+    let files = log_files(dir)?;
+    let mut total = 0u64;
+    let mut deleted = 0;
+    for (index, file) in files.iter().enumerate() {
+        total += std::fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+        if index > 0 && total > max_bytes {
+            std::fs::remove_file(file)
+                .with_context(|| format!("Failed to delete old log file {}", file.display()))?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+/// One line of the log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    /// Level of the event the line belongs to; continuation lines of multi-line messages
+    /// get the level of the line before them
+    pub level: LogLevel,
+    pub text: String,
+}
+
+/// Split log output into lines and determine their levels
+///
+/// Lines look like `2025-01-01T12:00:00.000000Z  INFO golem_gpu_imager::ui: message`.
+pub fn parse_lines(content: &str) -> Vec<LogLine> {
+    let mut level = LogLevel::Info;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            if let Some(parsed) = line.split_whitespace().nth(1).and_then(LogLevel::parse) {
+                level = parsed;
+            }
+            LogLine {
+                level,
+                text: line.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Read the lines at the end of a log file
+pub fn read_tail(path: &Path) -> Result<Vec<LogLine>> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+    let size = file.metadata()?.len();
+    let start = size.saturating_sub(MAX_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut data = Vec::with_capacity((size - start) as usize);
+    file.read_to_end(&mut data)
+        .with_context(|| format!("Failed to read log file {}", path.display()))?;
+    let content = String::from_utf8_lossy(&data);
+
+    // The first line is most likely cut off when reading from the middle of the file
+    let content = if start > 0 {
+        content.split_once('\n').map_or("", |(_, rest)| rest)
+    } else {
+        &content
+    };
+    Ok(parse_lines(content))
+}
+
+/// Text to attach to a bug report: the version, the system and the end of the log
+pub fn diagnostics(log_file: Option<&Path>, privileges: &str, lines: &[LogLine]) -> String {
+    let mut report = format!(
+        "Golem GPU Imager v{}\nSystem: {} ({})\nPrivileges: {}\nLog file: {}\n\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        privileges,
+        log_file.map_or_else(|| "none".to_string(), |path| path.display().to_string()),
+    );
+    let start = lines.len().saturating_sub(DIAGNOSTICS_LINES);
+    for line in &lines[start..] {
+        report.push_str(&line.text);
+        report.push('\n');
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "\
+2025-01-01T12:00:00.000001Z  INFO golem_gpu_imager: src/main.rs:75: Starting Golem GPU Imager
+2025-01-01T12:00:01.000001Z  WARN golem_gpu_imager::disk: src/disk.rs:10: Device is busy
+  caused by: resource busy
+
+2025-01-01T12:00:02.000001Z ERROR golem_gpu_imager::disk: src/disk.rs:20: Flash failed
+";
+
+    #[test]
+    fn test_parse_lines() {
+        let lines = parse_lines(LOG);
+        let levels: Vec<LogLevel> = lines.iter().map(|line| line.level).collect();
+        assert_eq!(
+            levels,
+            vec![
+                LogLevel::Info,
+                LogLevel::Warn,
+                LogLevel::Warn,
+                LogLevel::Error
+            ]
+        );
+        assert_eq!(lines[2].text, "  caused by: resource busy");
+        assert!(LogLevel::Warn > LogLevel::Info);
+    }
+
+    #[test]
+    fn test_read_tail_skips_partial_line() {
+        let dir = std::env::temp_dir().join(format!("golem-logs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.2025-01-01", LOG_FILE_NAME));
+
+        let filler = "x".repeat(MAX_TAIL_BYTES as usize);
+        std::fs::write(&path, format!("{}\n{}", filler, LOG)).unwrap();
+        let lines = read_tail(&path).unwrap();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].text.ends_with("Starting Golem GPU Imager"));
+
+        std::fs::write(dir.join(format!("{}.2025-01-02", LOG_FILE_NAME)), "").unwrap();
+        std::fs::write(dir.join("other.txt"), "").unwrap();
+        let files = log_files(&dir).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].to_string_lossy().ends_with("2025-01-02"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_filter_directive() {
+        assert_eq!(
+            filter_directive(LogLevel::Debug),
+            "info,golem_gpu_imager=debug,iced_winit=error"
+        );
+        assert_eq!(
+            filter_directive(LogLevel::Warn),
+            "warn,golem_gpu_imager=warn,iced_winit=error"
+        );
+    }
+
+    #[test]
+    fn test_prune_logs_keeps_newest() {
+        let dir = std::env::temp_dir().join(format!("golem-prune-logs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for day in 1..=3 {
+            let path = dir.join(format!("{}.2025-01-0{}", LOG_FILE_NAME, day));
+            std::fs::write(path, vec![b'x'; 100]).unwrap();
+        }
+
+        assert_eq!(prune_logs(&dir, 250).unwrap(), 1);
+        assert_eq!(log_files(&dir).unwrap().len(), 2);
+        assert_eq!(prune_logs(&dir, 0).unwrap(), 1);
+        let files = log_files(&dir).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].to_string_lossy().ends_with("2025-01-03"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let lines = parse_lines(LOG);
+        let report = diagnostics(None, "Running as root", &lines);
+        assert!(report.starts_with(&format!(
+            "Golem GPU Imager v{}\n",
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert!(report.contains("Log file: none\n"));
+        assert!(report.ends_with("Flash failed\n"));
+    }
+}
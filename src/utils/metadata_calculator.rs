@@ -1,7 +1,7 @@
 use crate::models::{CancelToken, ImageMetadata};
 use anyhow::{Result, anyhow};
-use iced::task::{self, Sipper};
 use sha2::{Digest, Sha256};
+use sipper::{Sipper, sipper};
 use std::fs::File;
 use std::io::Read;
 use std::num::NonZeroUsize;
@@ -37,7 +37,7 @@ pub fn calculate_image_metadata(
 ) -> impl Sipper<Result<MetadataProgress>, MetadataProgress> + Send + 'static {
     let image_path = image_path.to_path_buf();
 
-    task::sipper(async move |mut sipper| -> Result<MetadataProgress> {
+    sipper(async move |mut sipper| -> Result<MetadataProgress> {
         let image_path_str = image_path.to_string_lossy().to_string();
         info!("Starting metadata calculation for: {}", image_path_str);
 
@@ -0,0 +1,168 @@
+/// Locations of the files the application writes
+///
+/// Normally everything goes to the per-user directories of the platform, so the install
+/// directory is never written to and read-only AppImage and Flatpak installs work. In portable
+/// mode, selected with `--portable` or a file named `portable` next to the executable, the
+/// settings, presets, logs and downloads are kept in a directory next to the executable
+/// instead, e.g. to carry the application around on a USB stick. For an AppImage that is the
+/// directory the AppImage file is in, as the executable itself lives in a read-only mount.
+use anyhow::{Result, anyhow};
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Command line flag that selects portable mode
+pub const PORTABLE_FLAG: &str = "--portable";
+
+/// File next to the executable that selects portable mode without the flag
+const PORTABLE_MARKER: &str = "portable";
+
+/// Directory next to the executable that holds everything in portable mode
+const PORTABLE_DATA_DIR: &str = "golem-gpu-imager-data";
+
+static PORTABLE_ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Directories the application keeps its files in
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppDirs {
+    /// Settings and presets
+    pub config: PathBuf,
+    /// Image metadata and configuration backups
+    pub data: PathBuf,
+    /// Logs and crash reports
+    pub data_local: PathBuf,
+    /// Downloaded images
+    pub cache: PathBuf,
+}
+
+impl AppDirs {
+    /// The per-user directories of the platform
+    fn installed() -> Result<Self> {
+        let data_dirs = ProjectDirs::from("network", "Golem Factory", "GPU Imager")
+            .ok_or_else(|| anyhow!("Failed to determine the application data directory"))?;
+        // Presets were always stored under this name, keep finding them
+        let config_dirs = ProjectDirs::from("com", "golem", "golem-gpu-imager")
+            .ok_or_else(|| anyhow!("Failed to determine the configuration directory"))?;
+
+        Ok(Self {
+            config: config_dirs.config_dir().to_path_buf(),
+            data: data_dirs.data_dir().to_path_buf(),
+            data_local: data_dirs.data_local_dir().to_path_buf(),
+            cache: data_dirs.cache_dir().to_path_buf(),
+        })
+    }
+
+    /// Everything below `root`
+    fn portable(root: &Path) -> Self {
+        Self {
+            config: root.join("config"),
+            data: root.join("data"),
+            data_local: root.join("data"),
+            cache: root.join("cache"),
+        }
+    }
+}
+
+/// Choose between portable and installed mode; call once at startup before anything is
+/// written
+///
+/// # Arguments
+/// * `args` - The command line arguments, without the program name
+pub fn init(args: &[String]) {
+    let portable = args.iter().any(|arg| arg == PORTABLE_FLAG);
+    let root = install_dir().and_then(|dir| {
+        (portable || dir.join(PORTABLE_MARKER).exists()).then(|| dir.join(PORTABLE_DATA_DIR))
+    });
+    let _ = PORTABLE_ROOT.set(root);
+}
+
+/// Whether the application runs in portable mode
+pub fn is_portable() -> bool {
+    portable_root().is_some()
+}
+
+/// The directory everything is kept in when running portable
+pub fn portable_root() -> Option<&'static Path> {
+    PORTABLE_ROOT.get().and_then(|root| root.as_deref())
+}
+
+/// The directory the application was started from; for an AppImage the one containing it
+fn install_dir() -> Option<PathBuf> {
+    let exe = match std::env::var_os("APPIMAGE") {
+        Some(appimage) => PathBuf::from(appimage),
+        None => std::env::current_exe().ok()?,
+    };
+    exe.parent().map(Path::to_path_buf)
+}
+
+/// The directories for the current mode
+pub fn dirs() -> Result<AppDirs> {
+    match portable_root() {
+        Some(root) => Ok(AppDirs::portable(root)),
+        None => AppDirs::installed(),
+    }
+}
+
+/// Directory for settings and presets
+pub fn config_dir() -> Result<PathBuf> {
+    Ok(dirs()?.config)
+}
+
+/// Directory for image metadata
+pub fn data_dir() -> Result<PathBuf> {
+    Ok(dirs()?.data)
+}
+
+/// Directory downloaded images are cached in
+pub fn cache_dir() -> Result<PathBuf> {
+    Ok(dirs()?.cache)
+}
+
+/// Directory the log files are written to
+pub fn log_dir() -> Result<PathBuf> {
+    Ok(dirs()?.data_local.join("logs"))
+}
+
+/// Directory crash reports are written to
+pub fn crash_dir() -> Result<PathBuf> {
+    Ok(dirs()?.data_local.join("crashes"))
+}
+
+/// Directory the partition tables of wiped disks are kept in until the wipe can't be undone
+pub fn wipe_snapshot_dir() -> Result<PathBuf> {
+    Ok(dirs()?.data_local.join("wipe-snapshots"))
+}
+
+/// Directory where backups are kept when the user doesn't choose a location
+pub fn backup_dir() -> Result<PathBuf> {
+    Ok(dirs()?.data.join("config-backups"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portable_dirs_stay_below_root() {
+        let root = Path::new("/media/usb/golem-gpu-imager-data");
+        let dirs = AppDirs::portable(root);
+        for dir in [&dirs.config, &dirs.data, &dirs.data_local, &dirs.cache] {
+            assert!(
+                dir.starts_with(root),
+                "{} is outside the root",
+                dir.display()
+            );
+        }
+        assert_ne!(dirs.config, dirs.cache);
+    }
+
+    #[test]
+    fn test_installed_dirs_by_default() {
+        // Tests never call init(), so the per-user directories are used
+        assert!(!is_portable());
+        if let Ok(installed) = AppDirs::installed() {
+            assert_eq!(dirs().unwrap(), installed);
+            assert_eq!(log_dir().unwrap(), installed.data_local.join("logs"));
+        }
+    }
+}
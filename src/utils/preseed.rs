@@ -0,0 +1,209 @@
+/// Starting the flash workflow with its choices made on the command line
+///
+/// Shortcuts and scripts can open the window with an image file, a target device and a
+/// preset already chosen, e.g. `--image golem.img.xz --device /dev/sdb --preset Rack`.
+/// The workflow then stops on the configuration screen, or with `--auto-start` writes the
+/// image as soon as it is analyzed and the device is found. A later start of the imager
+/// hands its command line to the running window, which fills in the workflow the same way.
+///
+/// The installers register the imager for `golem-imager://` links and `.img.xz` files, which
+/// the system passes as the only argument. A link such as
+/// `golem-imager://flash?image=https://.../golem-gpu-live.img.xz&preset=Mainnet%20Production`
+/// from the website's "Flash this image" button names a repository image and a preset.
+use std::path::PathBuf;
+
+/// Scheme of the links the website opens the imager with
+pub const URL_SCHEME: &str = "golem-imager";
+
+const IMAGE_FLAG: &str = "--image";
+const DEVICE_FLAG: &str = "--device";
+const PRESET_FLAG: &str = "--preset";
+const AUTO_START_FLAG: &str = "--auto-start";
+
+/// Choices of the flash workflow given on the command line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preseed {
+    /// Image file to write
+    pub image: Option<PathBuf>,
+    /// Download URL of the repository image to write, from a link
+    pub image_url: Option<String>,
+    /// Path of the target disk, as the device list shows it
+    pub device: Option<String>,
+    /// Name of the preset to configure the image with
+    pub preset: Option<String>,
+    /// Write without waiting for the user once the image and the device are selected
+    pub auto_start: bool,
+}
+
+/// The choices on the command line, `None` if it makes none
+///
+/// Values follow their flag as the next argument or after `=`, e.g. `--device=/dev/sdb`.
+/// An argument without a flag is a link or an image file opened through the system.
+/// Arguments of other features are left alone.
+///
+/// # Arguments
+/// * `args` - The command line arguments, without the program name
+pub fn from_args(args: &[String]) -> Result<Option<Preseed>, String> {
+    let mut preseed = Preseed::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == AUTO_START_FLAG {
+            preseed.auto_start = true;
+            continue;
+        }
+        if !arg.starts_with('-') {
+            opened(&mut preseed, arg)?;
+            continue;
+        }
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        if ![IMAGE_FLAG, DEVICE_FLAG, PRESET_FLAG].contains(&flag) {
+            continue;
+        }
+        let value = inline_value
+            .or_else(|| args.next().cloned())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag {
+            // The running window may have been started from another directory
+            IMAGE_FLAG => {
+                preseed.image =
+                    Some(std::path::absolute(&value).unwrap_or_else(|_| PathBuf::from(value)))
+            }
+            DEVICE_FLAG => preseed.device = Some(value),
+            _ => preseed.preset = Some(value),
+        }
+    }
+
+    if preseed == Preseed::default() {
+        return Ok(None);
+    }
+    let has_image = preseed.image.is_some() || preseed.image_url.is_some();
+    if preseed.auto_start && !(has_image && preseed.device.is_some()) {
+        return Err(format!(
+            "{} needs both {} and {}",
+            AUTO_START_FLAG, IMAGE_FLAG, DEVICE_FLAG
+        ));
+    }
+    Ok(Some(preseed))
+}
+
+/// Take the choices of `arg`, a link or a file the system opened the imager with
+///
+/// Desktop environments may pass files as `file://` URLs.
+fn opened(preseed: &mut Preseed, arg: &str) -> Result<(), String> {
+    let Ok(url) = reqwest::Url::parse(arg) else {
+        preseed.image = Some(std::path::absolute(arg).unwrap_or_else(|_| PathBuf::from(arg)));
+        return Ok(());
+    };
+    match url.scheme() {
+        "file" => {
+            let path = url
+                .to_file_path()
+                .map_err(|()| format!("{} is not a local file", arg))?;
+            preseed.image = Some(path);
+        }
+        URL_SCHEME if url.host_str() == Some("flash") => {
+            for (key, value) in url.query_pairs() {
+                match key.as_ref() {
+                    "image" => preseed.image_url = Some(value.into_owned()),
+                    "preset" => preseed.preset = Some(value.into_owned()),
+                    _ => {}
+                }
+            }
+            if preseed.image_url.is_none() {
+                return Err(format!("The link {} names no image", arg));
+            }
+        }
+        // A Windows path such as C:\images\golem.img parses as a URL with the scheme "c"
+        scheme if scheme.len() == 1 => {
+            preseed.image = Some(PathBuf::from(arg));
+        }
+        _ => return Err(format!("Unsupported link {}", arg)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_from_args() {
+        assert_eq!(from_args(&args("--portable")), Ok(None));
+        assert_eq!(
+            from_args(&args(
+                "--portable --image /images/golem.img.xz --device=/dev/sdb --preset Rack --auto-start"
+            )),
+            Ok(Some(Preseed {
+                image: Some(PathBuf::from("/images/golem.img.xz")),
+                image_url: None,
+                device: Some("/dev/sdb".to_string()),
+                preset: Some("Rack".to_string()),
+                auto_start: true,
+            }))
+        );
+        assert_eq!(
+            from_args(&args("--device /dev/sdb")),
+            Ok(Some(Preseed {
+                device: Some("/dev/sdb".to_string()),
+                ..Preseed::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_links_and_opened_files() {
+        assert_eq!(
+            from_args(&args(
+                "golem-imager://flash?image=https%3A%2F%2Frepo.example.com%2Fgolem.img.xz&preset=Mainnet%20Production"
+            )),
+            Ok(Some(Preseed {
+                image_url: Some("https://repo.example.com/golem.img.xz".to_string()),
+                preset: Some("Mainnet Production".to_string()),
+                ..Preseed::default()
+            }))
+        );
+        assert_eq!(
+            from_args(&args("file:///images/golem%20live.img.xz")),
+            Ok(Some(Preseed {
+                image: Some(PathBuf::from("/images/golem live.img.xz")),
+                ..Preseed::default()
+            }))
+        );
+        assert_eq!(
+            from_args(&args("/images/golem.img.xz --automation")),
+            Ok(Some(Preseed {
+                image: Some(PathBuf::from("/images/golem.img.xz")),
+                ..Preseed::default()
+            }))
+        );
+        assert_eq!(
+            from_args(&args("golem-imager://flash?preset=Rack")),
+            Err("The link golem-imager://flash?preset=Rack names no image".to_string())
+        );
+        assert!(from_args(&args("golem-imager://erase?device=/dev/sda")).is_err());
+    }
+
+    #[test]
+    fn test_incomplete_arguments_are_refused() {
+        assert_eq!(
+            from_args(&args("--image")),
+            Err("--image needs a value".to_string())
+        );
+        assert_eq!(
+            from_args(&args("--preset=")),
+            Err("--preset needs a value".to_string())
+        );
+        assert_eq!(
+            from_args(&args("--image golem.img --auto-start")),
+            Err("--auto-start needs both --image and --device".to_string())
+        );
+    }
+}
@@ -2,31 +2,117 @@ use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::PathBuf;
 
-use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
 use crate::models::{ConfigurationPreset, NetworkType, PaymentNetwork};
+use crate::utils::device_assignment::DeviceAssignment;
 
 /// Struct to hold configuration presets and manage their persistence
 pub struct PresetManager {
     presets: Vec<ConfigurationPreset>,
+    assignments: Vec<DeviceAssignment>,
+    template_index: u64,
+    history: Vec<PresetSnapshot>, // Oldest first
     config_dir: PathBuf,
 }
 
 #[derive(Serialize, Deserialize)]
 struct PresetsToml {
+    /// Value of `{index}` for the next flashed device
+    #[serde(default = "first_template_index")]
+    template_index: u64,
     presets: Vec<ConfigurationPreset>,
+    #[serde(default)]
+    assignments: Vec<DeviceAssignment>,
+    #[serde(default)]
+    history: Vec<PresetSnapshot>,
+}
+
+fn first_template_index() -> u64 {
+    1
+}
+
+/// Number of changes that can be undone
+const MAX_HISTORY: usize = 20;
+
+/// The presets as they were before a change, to undo it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetSnapshot {
+    /// What the change did, e.g. "Delete preset Mainnet Production"
+    pub change: String,
+    /// When the change was made, RFC 3339
+    pub changed_at: String,
+    pub presets: Vec<ConfigurationPreset>,
+    #[serde(default)]
+    pub assignments: Vec<DeviceAssignment>,
+}
+
+/// The presets the imager ships with
+fn builtin_presets() -> Vec<ConfigurationPreset> {
+    vec![
+        ConfigurationPreset {
+            name: "Testnet Development".to_string(),
+            payment_network: PaymentNetwork::Testnet,
+            subnet: "public".to_string(),
+            network_type: NetworkType::Central,
+            wallet_address: "".to_string(),
+            is_default: true,
+            non_interactive_install: false,
+            ssh_keys: Vec::new(),
+            configuration_server: None,
+            metrics_server: None,
+            central_net_host: None,
+            node_name: None,
+            extra_env: Vec::new(),
+            extra_toml: Vec::new(),
+            firstboot: None,
+            shared: false,
+        },
+        ConfigurationPreset {
+            name: "Mainnet Production".to_string(),
+            payment_network: PaymentNetwork::Mainnet,
+            subnet: "production".to_string(),
+            network_type: NetworkType::Central,
+            wallet_address: "".to_string(),
+            is_default: false,
+            non_interactive_install: false,
+            ssh_keys: Vec::new(),
+            configuration_server: None,
+            metrics_server: None,
+            central_net_host: None,
+            node_name: None,
+            extra_env: Vec::new(),
+            extra_toml: Vec::new(),
+            firstboot: None,
+            shared: false,
+        },
+        ConfigurationPreset {
+            name: "Susteen Support".to_string(),
+            payment_network: PaymentNetwork::Testnet,
+            subnet: "susteen".to_string(),
+            network_type: NetworkType::Central,
+            wallet_address: "0x206bfe4F439a83b65A5B9c2C3B1cc6cB49054cc4".to_string(),
+            is_default: false,
+            non_interactive_install: true,
+            ssh_keys: vec!["ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPeI8LZGexCdqXozb+gPKnZCQLr7AlXqRCgJpM9eS/y3 reqc@pop-os".to_string()],
+            configuration_server: Some("http://63.176.129.155/config.toml".to_string()),
+            metrics_server: Some("http://63.176.129.155:9091".to_string()),
+            central_net_host: None,
+            node_name: None,
+            extra_env: Vec::new(),
+            extra_toml: Vec::new(),
+            firstboot: None,
+            shared: false,
+        },
+    ]
 }
 
 impl PresetManager {
     /// Create a new PresetManager instance
     pub fn new() -> Result<Self, String> {
-        // Get the project directories
-        let project_dirs = ProjectDirs::from("com", "golem", "golem-gpu-imager")
-            .ok_or_else(|| "Failed to determine project directories".to_string())?;
-
         // Get the config directory
-        let config_dir = project_dirs.config_dir().to_path_buf();
+        let config_dir = crate::utils::paths::config_dir()
+            .map_err(|e| format!("Failed to determine project directories: {}", e))?;
 
         // Create the config directory if it doesn't exist
         if !config_dir.exists() {
@@ -37,6 +123,9 @@ impl PresetManager {
         // Initialize with empty presets
         Ok(Self {
             presets: Vec::new(),
+            assignments: Vec::new(),
+            template_index: first_template_index(),
+            history: Vec::new(),
             config_dir,
         })
     }
@@ -57,11 +146,26 @@ impl PresetManager {
         Ok(())
     }
 
-    /// Get the list of all presets
+    /// Get the list of all presets, the local ones followed by the shared ones
     pub fn get_presets(&self) -> &Vec<ConfigurationPreset> {
         &self.presets
     }
 
+    /// Replace the shared presets, e.g. once the team's collection was fetched
+    ///
+    /// A shared preset named like a local one is left out, the local preset is used instead.
+    pub fn set_shared_presets(&mut self, shared: Vec<ConfigurationPreset>) {
+        self.presets.retain(|p| !p.shared);
+        for mut preset in shared {
+            if self.presets.iter().any(|p| p.name == preset.name) {
+                continue;
+            }
+            preset.shared = true;
+            preset.is_default = false;
+            self.presets.push(preset);
+        }
+    }
+
     /// Get the default preset (if exists)
     pub fn get_default_preset(&self) -> Option<&ConfigurationPreset> {
         self.presets.iter().find(|p| p.is_default)
@@ -69,8 +173,10 @@ impl PresetManager {
 
     /// Add a new preset
     pub fn add_preset(&mut self, preset: ConfigurationPreset) -> Result<(), String> {
+        self.record(format!("Add preset {}", preset.name));
+
         // If this is the first preset, make it default
-        let is_first = self.presets.is_empty();
+        let is_first = self.local_count() == 0;
 
         // If the preset is being set as default, unset default on all other presets
         if preset.is_default || is_first {
@@ -81,11 +187,13 @@ impl PresetManager {
 
         // Ensure first preset is default
         let mut new_preset = preset;
+        new_preset.shared = false;
         if is_first {
             new_preset.is_default = true;
         }
 
-        self.presets.push(new_preset);
+        // Local presets are listed before the shared ones
+        self.presets.insert(self.local_count(), new_preset);
         self.save_presets()?;
 
         Ok(())
@@ -96,11 +204,18 @@ impl PresetManager {
     pub fn update_preset(
         &mut self,
         index: usize,
-        preset: ConfigurationPreset,
+        mut preset: ConfigurationPreset,
     ) -> Result<(), String> {
-        if index >= self.presets.len() {
-            return Err("Preset index out of bounds".to_string());
-        }
+        self.check_local(index)?;
+        preset.shared = false;
+        self.record(if preset.name == self.presets[index].name {
+            format!("Edit preset {}", preset.name)
+        } else {
+            format!(
+                "Rename preset {} to {}",
+                self.presets[index].name, preset.name
+            )
+        });
 
         // If the preset is being set as default, unset default on all other presets
         if preset.is_default {
@@ -109,17 +224,38 @@ impl PresetManager {
             }
         }
 
+        // Keep device assignments pointing at the preset when it is renamed
+        let old_name = &self.presets[index].name;
+        for assignment in &mut self.assignments {
+            if assignment.preset == *old_name {
+                assignment.preset = preset.name.clone();
+            }
+        }
+
         self.presets[index] = preset;
         self.save_presets()?;
 
         Ok(())
     }
 
+    /// Move the preset at `from` to position `to`, the order the presets are listed in
+    pub fn move_preset(&mut self, from: usize, to: usize) -> Result<(), String> {
+        self.check_local(from)?;
+        self.check_local(to)?;
+        self.record(format!("Move preset {}", self.presets[from].name));
+
+        let preset = self.presets.remove(from);
+        self.presets.insert(to, preset);
+        self.save_presets()
+    }
+
     /// Set a preset as default
     pub fn set_default_preset(&mut self, index: usize) -> Result<(), String> {
-        if index >= self.presets.len() {
-            return Err("Preset index out of bounds".to_string());
-        }
+        self.check_local(index)?;
+        self.record(format!(
+            "Make {} the default preset",
+            self.presets[index].name
+        ));
 
         // Unset default on all presets
         for p in &mut self.presets {
@@ -136,17 +272,18 @@ impl PresetManager {
 
     /// Delete a preset
     pub fn delete_preset(&mut self, index: usize) -> Result<(), String> {
-        if index >= self.presets.len() {
-            return Err("Preset index out of bounds".to_string());
-        }
+        self.check_local(index)?;
+        self.record(format!("Delete preset {}", self.presets[index].name));
 
         let was_default = self.presets[index].is_default;
 
-        // Remove the preset
-        self.presets.remove(index);
+        // Remove the preset and the device assignments that selected it
+        let removed = self.presets.remove(index);
+        self.assignments
+            .retain(|assignment| assignment.preset != removed.name);
 
         // If the deleted preset was default and we still have presets, set the first one as default
-        if was_default && !self.presets.is_empty() {
+        if was_default && self.local_count() > 0 {
             self.presets[0].is_default = true;
         }
 
@@ -155,50 +292,136 @@ impl PresetManager {
         Ok(())
     }
 
+    /// Get the device assignments, in the order they are matched
+    pub fn get_assignments(&self) -> &Vec<DeviceAssignment> {
+        &self.assignments
+    }
+
+    /// Add a device assignment
+    pub fn add_assignment(&mut self, assignment: DeviceAssignment) -> Result<(), String> {
+        if !assignment.has_criteria() {
+            return Err("A device assignment needs a serial number, model or size".to_string());
+        }
+        if !self.presets.iter().any(|p| p.name == assignment.preset) {
+            return Err(format!("Unknown preset: {}", assignment.preset));
+        }
+        self.record(format!(
+            "Assign {} to {}",
+            assignment.preset,
+            assignment.describe()
+        ));
+
+        self.assignments.push(assignment);
+        self.save_presets()
+    }
+
+    /// Delete a device assignment
+    pub fn delete_assignment(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.assignments.len() {
+            return Err("Assignment index out of bounds".to_string());
+        }
+        self.record(format!(
+            "Delete the assignment of {}",
+            self.assignments[index].preset
+        ));
+
+        self.assignments.remove(index);
+        self.save_presets()
+    }
+
+    /// Reset the presets the imager ships with to their original settings
+    ///
+    /// Built-in presets that were deleted are added back; other presets are left alone.
+    pub fn restore_builtin_presets(&mut self) -> Result<(), String> {
+        self.record("Restore built-in presets".to_string());
+
+        let has_default = self.presets.iter().any(|p| p.is_default);
+        for mut builtin in builtin_presets() {
+            match self
+                .presets
+                .iter_mut()
+                .find(|p| !p.shared && p.name == builtin.name)
+            {
+                Some(preset) => {
+                    builtin.is_default = preset.is_default;
+                    *preset = builtin;
+                }
+                None => {
+                    builtin.is_default &= !has_default;
+                    self.presets.insert(self.local_count(), builtin);
+                }
+            }
+        }
+        self.save_presets()
+    }
+
+    /// Changes that can be undone, oldest first
+    pub fn history(&self) -> &[PresetSnapshot] {
+        &self.history
+    }
+
+    /// Undo the last change, returning what it did, `None` if there is nothing to undo
+    pub fn undo(&mut self) -> Result<Option<String>, String> {
+        let Some(snapshot) = self.history.pop() else {
+            return Ok(None);
+        };
+        let shared = self.presets.split_off(self.local_count());
+        self.presets = snapshot.presets;
+        self.presets.extend(shared);
+        self.assignments = snapshot.assignments;
+        self.save_presets()?;
+        Ok(Some(snapshot.change))
+    }
+
+    /// Remember the presets before a change, dropping the oldest snapshot past the limit
+    fn record(&mut self, change: String) {
+        self.history.push(PresetSnapshot {
+            change,
+            changed_at: chrono::Utc::now().to_rfc3339(),
+            presets: self.local_presets(),
+            assignments: self.assignments.clone(),
+        });
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Number of local presets, which come before the shared ones
+    fn local_count(&self) -> usize {
+        self.presets.iter().filter(|p| !p.shared).count()
+    }
+
+    fn local_presets(&self) -> Vec<ConfigurationPreset> {
+        self.presets.iter().filter(|p| !p.shared).cloned().collect()
+    }
+
+    /// Fail unless `index` is a local preset; shared presets are read-only
+    fn check_local(&self, index: usize) -> Result<(), String> {
+        match self.presets.get(index) {
+            None => Err("Preset index out of bounds".to_string()),
+            Some(preset) if preset.shared => Err(format!(
+                "{} is a shared preset and can't be changed",
+                preset.name
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Value of `{index}` in preset templates for the next flashed device
+    pub fn template_index(&self) -> u64 {
+        self.template_index
+    }
+
+    /// Move the template counter on once a device was flashed with the current value
+    pub fn advance_template_index(&mut self) -> Result<(), String> {
+        self.template_index += 1;
+        self.save_presets()
+    }
+
     /// Create default presets
     #[allow(dead_code)]
     fn create_default_presets(&mut self) {
-        self.presets = vec![
-            ConfigurationPreset {
-                name: "Testnet Development".to_string(),
-                payment_network: PaymentNetwork::Testnet,
-                subnet: "public".to_string(),
-                network_type: NetworkType::Central,
-                wallet_address: "".to_string(),
-                is_default: true,
-                non_interactive_install: false,
-                ssh_keys: Vec::new(),
-                configuration_server: None,
-                metrics_server: None,
-                central_net_host: None,
-            },
-            ConfigurationPreset {
-                name: "Mainnet Production".to_string(),
-                payment_network: PaymentNetwork::Mainnet,
-                subnet: "production".to_string(),
-                network_type: NetworkType::Central,
-                wallet_address: "".to_string(),
-                is_default: false,
-                non_interactive_install: false,
-                ssh_keys: Vec::new(),
-                configuration_server: None,
-                metrics_server: None,
-                central_net_host: None,
-            },
-            ConfigurationPreset {
-                name: "Susteen Support".to_string(),
-                payment_network: PaymentNetwork::Testnet,
-                subnet: "susteen".to_string(),
-                network_type: NetworkType::Central,
-                wallet_address: "0x206bfe4F439a83b65A5B9c2C3B1cc6cB49054cc4".to_string(),
-                is_default: false,
-                non_interactive_install: true,
-                ssh_keys: vec!["ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPeI8LZGexCdqXozb+gPKnZCQLr7AlXqRCgJpM9eS/y3 reqc@pop-os".to_string()],
-                configuration_server: Some("http://63.176.129.155/config.toml".to_string()),
-                metrics_server: Some("http://63.176.129.155:9091".to_string()),
-                central_net_host: None,
-            },
-        ];
+        self.presets = builtin_presets();
 
         // Save the default presets to disk
         let _ = self.save_presets();
@@ -227,6 +450,9 @@ impl PresetManager {
 
         // Update the presets
         self.presets = presets_toml.presets;
+        self.assignments = presets_toml.assignments;
+        self.template_index = presets_toml.template_index;
+        self.history = presets_toml.history;
 
         Ok(())
     }
@@ -243,7 +469,10 @@ impl PresetManager {
 
         // Create the presets TOML structure
         let presets_toml = PresetsToml {
-            presets: self.presets.clone(),
+            template_index: self.template_index,
+            presets: self.local_presets(),
+            assignments: self.assignments.clone(),
+            history: self.history.clone(),
         };
 
         // Serialize to TOML
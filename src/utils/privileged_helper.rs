@@ -0,0 +1,224 @@
+/// Privileged helper process for raw disk access
+///
+/// Instead of running the whole UI as root / Administrator, a small helper binary
+/// (`golem-disk-helper`, shipped next to the main executable) is started with elevated
+/// rights. It opens the target device and hands the handle back, so writing and
+/// verification still happen in this process.
+use golem_disk_helper::HelperClient;
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How long to wait for the user to approve the elevation prompt
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Delay between connection attempts while the helper starts
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Connection to the running helper, if one was started
+static HELPER: LazyLock<Mutex<Option<HelperClient>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Whether disk access currently goes through the helper
+pub fn is_running() -> bool {
+    HELPER.lock().unwrap().is_some()
+}
+
+/// Start the helper with elevated rights and connect to it
+///
+/// Shows the system's elevation prompt (pkexec on Linux, UAC on Windows) and waits until
+/// the helper accepts our connection.
+pub async fn start() -> Result<(), String> {
+    if is_running() {
+        return Ok(());
+    }
+
+    let helper_path = helper_path()?;
+    let endpoint = endpoint();
+    info!(
+        "Starting privileged helper {} on {}",
+        helper_path.display(),
+        endpoint
+    );
+    let launcher = launch_elevated(&helper_path, &endpoint)?;
+
+    let client = tokio::task::spawn_blocking(move || connect_with_retry(&endpoint, launcher))
+        .await
+        .map_err(|e| format!("Helper connection task failed: {}", e))??;
+
+    *HELPER.lock().unwrap() = Some(client);
+    info!("Privileged helper connected");
+    Ok(())
+}
+
+/// Stop the helper, if running
+pub fn stop() {
+    let Some(client) = HELPER.lock().unwrap().take() else {
+        return;
+    };
+    if let Err(e) = client.shutdown() {
+        warn!("Failed to stop privileged helper: {}", e);
+    }
+}
+
+/// Prepare a device for writing and open it through the helper
+///
+/// Blocking; call from a blocking thread.
+///
+/// # Arguments
+/// * `path` - Whole-disk device path
+/// * `clean` - Remove all partitions first (Windows only, no-op on Linux)
+pub fn open_device(path: &str, clean: bool) -> anyhow::Result<File> {
+    let mut guard = HELPER.lock().unwrap();
+    let client = guard
+        .as_mut()
+        .ok_or_else(|| anyhow::anyhow!("Privileged helper is not running"))?;
+
+    client.unmount(path)?;
+    if clean {
+        client.clean_disk(path)?;
+    }
+    client.open_device(path, true)
+}
+
+/// Remove all partitions from a device through the helper
+///
+/// Blocking; call from a blocking thread.
+pub fn clean_disk(path: &str) -> anyhow::Result<()> {
+    let mut guard = HELPER.lock().unwrap();
+    let client = guard
+        .as_mut()
+        .ok_or_else(|| anyhow::anyhow!("Privileged helper is not running"))?;
+    client.clean_disk(path)
+}
+
+/// The helper binary is installed next to the main executable
+fn helper_path() -> Result<PathBuf, String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to get current executable path: {}", e))?;
+    let path = exe.with_file_name(format!("golem-disk-helper{}", std::env::consts::EXE_SUFFIX));
+    if !path.exists() {
+        return Err(format!("Privileged helper not found at {}", path.display()));
+    }
+    Ok(path)
+}
+
+/// Socket path or pipe name unique to this process
+fn endpoint() -> String {
+    #[cfg(windows)]
+    {
+        format!(r"\\.\pipe\golem-disk-helper-{}", std::process::id())
+    }
+
+    #[cfg(not(windows))]
+    {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        dir.join(format!("golem-disk-helper-{}.sock", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Start the helper through pkexec
+///
+/// # Returns
+/// * `Result<Option<Child>, String>` - The pkexec process, which exits if authorization is denied
+#[cfg(not(windows))]
+fn launch_elevated(helper_path: &std::path::Path, endpoint: &str) -> Result<Option<Child>, String> {
+    std::process::Command::new("pkexec")
+        .arg(helper_path)
+        .arg("--endpoint")
+        .arg(endpoint)
+        .arg("--client-pid")
+        .arg(std::process::id().to_string())
+        .spawn()
+        .map(Some)
+        .map_err(|e| format!("Failed to start pkexec: {}", e))
+}
+
+/// Start the helper through the UAC prompt
+#[cfg(windows)]
+fn launch_elevated(helper_path: &std::path::Path, endpoint: &str) -> Result<Option<Child>, String> {
+    use windows_sys::Win32::{
+        Foundation::HWND,
+        UI::{Shell::ShellExecuteW, WindowsAndMessaging::SW_HIDE},
+    };
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    let file = wide(&helper_path.to_string_lossy());
+    let parameters = wide(&format!(
+        "--endpoint {} --client-pid {}",
+        endpoint,
+        std::process::id()
+    ));
+    let verb = wide("runas");
+
+    // ShellExecuteW returns a value > 32 on success
+    let result = unsafe {
+        ShellExecuteW(
+            0 as HWND,
+            verb.as_ptr(),
+            file.as_ptr(),
+            parameters.as_ptr(),
+            std::ptr::null(),
+            SW_HIDE,
+        )
+    };
+    if result as i32 <= 32 {
+        return Err(format!(
+            "Failed to start privileged helper. Error code: {}",
+            result as i32
+        ));
+    }
+    Ok(None)
+}
+
+/// Keep trying to connect until the helper is listening or the user gave up
+fn connect_with_retry(endpoint: &str, mut launcher: Option<Child>) -> Result<HelperClient, String> {
+    let started = Instant::now();
+    loop {
+        if let Some(Ok(Some(status))) = launcher.as_mut().map(Child::try_wait) {
+            return Err(format!(
+                "Privileged helper exited before connecting ({}), authorization may have been denied",
+                status
+            ));
+        }
+
+        match HelperClient::connect(endpoint) {
+            Ok(client) => return Ok(client),
+            Err(e) if started.elapsed() >= CONNECT_TIMEOUT => {
+                return Err(format!(
+                    "Privileged helper did not start (was the prompt dismissed?): {}",
+                    e
+                ));
+            }
+            Err(_) => std::thread::sleep(CONNECT_RETRY_INTERVAL),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_fail_without_helper() {
+        assert!(!is_running());
+        assert!(open_device("/dev/null", false).is_err());
+        assert!(clean_disk("/dev/null").is_err());
+        // Stopping a helper that was never started is a no-op
+        stop();
+    }
+
+    #[test]
+    fn test_endpoint_is_unique_to_process() {
+        assert!(endpoint().contains(&std::process::id().to_string()));
+    }
+}
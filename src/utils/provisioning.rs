@@ -0,0 +1,378 @@
+/// Configuration values preset and locked by the provisioning of an imaging station
+///
+/// Kiosks and imaging benches are set up once by an administrator and then used by
+/// operators who should not change sensitive settings such as the wallet or the subnet.
+/// Values can be given as defaults, filled into a new configuration but still editable, or
+/// locked, in which case the configuration editors show them read-only and every
+/// configuration written uses them, whatever a preset or a device contains.
+///
+/// Values come from environment variables named after the setting, e.g.
+/// `GOLEM_IMAGER_LOCK_SUBNET=prod-eu` or `GOLEM_IMAGER_DEFAULT_PAYMENT_NETWORK=mainnet`, and
+/// from a provisioning file with `[defaults]` and `[locked]` tables, `provisioning.toml` in
+/// the configuration directory or the file `GOLEM_IMAGER_PROVISIONING` points at. The
+/// environment takes precedence over the file, and a locked value over a default.
+use crate::disk::ConfigField;
+use crate::models::{NetworkType, PaymentNetwork};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tracing::{info, warn};
+
+/// Prefix of environment variables with a default value
+const DEFAULT_PREFIX: &str = "GOLEM_IMAGER_DEFAULT_";
+
+/// Prefix of environment variables with a locked value
+const LOCK_PREFIX: &str = "GOLEM_IMAGER_LOCK_";
+
+/// Environment variable with the path of the provisioning file
+const FILE_VARIABLE: &str = "GOLEM_IMAGER_PROVISIONING";
+
+/// Provisioning file looked for in the configuration directory
+const PROVISIONING_FILE: &str = "provisioning.toml";
+
+static ACTIVE: RwLock<Provisioning> = RwLock::new(Provisioning::new());
+
+/// Preset and locked values of configuration settings
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provisioning {
+    values: BTreeMap<ConfigField, String>, // Normalized, see `normalize`
+    locked: BTreeSet<ConfigField>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProvisioningFile {
+    #[serde(default)]
+    defaults: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    locked: BTreeMap<String, toml::Value>,
+}
+
+impl Provisioning {
+    /// Nothing preset or locked
+    pub const fn new() -> Self {
+        Self {
+            values: BTreeMap::new(),
+            locked: BTreeSet::new(),
+        }
+    }
+
+    /// Read the provisioning file and the environment, logging values that are ignored
+    pub fn load() -> Self {
+        let file = match provisioning_file() {
+            Ok(Some(path)) => match std::fs::read_to_string(&path) {
+                Ok(content) => {
+                    info!(
+                        "Reading the provisioning of this station from {}",
+                        path.display()
+                    );
+                    Some(content)
+                }
+                Err(e) => {
+                    warn!("Failed to read {}: {}", path.display(), e);
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(e) => {
+                warn!("{:#}", e);
+                None
+            }
+        };
+
+        let (provisioning, errors) = Self::from_sources(file.as_deref(), std::env::vars());
+        for error in errors {
+            warn!("Ignoring provisioned value: {}", error);
+        }
+        if !provisioning.locked.is_empty() {
+            info!(
+                "Locked settings: {}",
+                provisioning
+                    .locked
+                    .iter()
+                    .map(|field| field.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        provisioning
+    }
+
+    /// The values of a provisioning file and environment variables, with what was wrong
+    ///
+    /// # Arguments
+    /// * `file` - Contents of the provisioning file, if there is one
+    /// * `env` - Environment variables; those without one of the prefixes are skipped
+    pub fn from_sources(
+        file: Option<&str>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> (Self, Vec<String>) {
+        let mut provisioning = Self::new();
+        let mut errors = Vec::new();
+
+        if let Some(content) = file {
+            match toml::from_str::<ProvisioningFile>(content) {
+                Ok(file) => {
+                    for (lock, values) in [(false, file.defaults), (true, file.locked)] {
+                        for (name, value) in values {
+                            let value = match value {
+                                toml::Value::String(value) => value,
+                                toml::Value::Array(items) => items
+                                    .iter()
+                                    .map(|item| item.as_str().unwrap_or_default().to_string())
+                                    .collect::<Vec<_>>()
+                                    .join("\n"),
+                                value => value.to_string(),
+                            };
+                            if let Err(e) = provisioning.set(&name, &value, lock) {
+                                errors.push(e);
+                            }
+                        }
+                    }
+                }
+                Err(e) => errors.push(format!("The provisioning file can't be parsed: {}", e)),
+            }
+        }
+
+        for (key, value) in env {
+            let (name, lock) = if let Some(name) = key.strip_prefix(LOCK_PREFIX) {
+                (name, true)
+            } else if let Some(name) = key.strip_prefix(DEFAULT_PREFIX) {
+                (name, false)
+            } else {
+                continue;
+            };
+            if let Err(e) = provisioning.set(&name.to_ascii_lowercase(), &value, lock) {
+                errors.push(e);
+            }
+        }
+
+        (provisioning, errors)
+    }
+
+    /// Whether nothing is preset or locked
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Whether operators can't change `field`
+    pub fn is_locked(&self, field: ConfigField) -> bool {
+        self.locked.contains(&field)
+    }
+
+    /// The preset or locked value of `field`
+    pub fn value(&self, field: ConfigField) -> Option<&str> {
+        self.values.get(&field).map(String::as_str)
+    }
+
+    /// The value of `field` if it is locked
+    pub fn locked_value(&self, field: ConfigField) -> Option<&str> {
+        self.value(field).filter(|_| self.is_locked(field))
+    }
+
+    /// Set the value of the setting called `name`, unless a locked value is already set
+    fn set(&mut self, name: &str, value: &str, lock: bool) -> Result<(), String> {
+        let field = ConfigField::ALL
+            .into_iter()
+            .find(|field| field.name() == name)
+            .ok_or_else(|| format!("There is no setting called {}", name))?;
+        let value = normalize(field, value).map_err(|e| format!("{}: {}", name, e))?;
+        if !lock && self.is_locked(field) {
+            return Ok(());
+        }
+        self.values.insert(field, value);
+        if lock {
+            self.locked.insert(field);
+        }
+        Ok(())
+    }
+}
+
+/// `value` in the form the configuration state reads it, or why it can't be used
+fn normalize(field: ConfigField, value: &str) -> Result<String, String> {
+    let value = value.trim();
+    match field {
+        ConfigField::PaymentNetwork => payment_network(value)
+            .map(|network| network.to_string().to_ascii_lowercase())
+            .ok_or_else(|| "expected mainnet or testnet".to_string()),
+        ConfigField::NetworkType => network_type(value)
+            .map(|network_type| network_type.to_string().to_ascii_lowercase())
+            .ok_or_else(|| "expected central or hybrid".to_string()),
+        ConfigField::NonInteractiveInstall => flag(value)
+            .map(|flag| flag.to_string())
+            .ok_or_else(|| "expected true or false".to_string()),
+        ConfigField::GlmAccount => {
+            if value.is_empty() || super::eth::is_valid_eth_address(value) {
+                Ok(value.to_string())
+            } else {
+                Err("not an Ethereum address".to_string())
+            }
+        }
+        ConfigField::Subnet => match super::validation::subnet_error(value) {
+            Some(error) => Err(error),
+            None => Ok(value.to_string()),
+        },
+        ConfigField::CentralNetHost => {
+            if value.is_empty() || super::validation::is_valid_central_net_host(value) {
+                Ok(value.to_string())
+            } else {
+                Err("not a host:port address".to_string())
+            }
+        }
+        ConfigField::SshKeys => Ok(value
+            .lines()
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")),
+        ConfigField::GlmPerHour => Err("the price is not set by the imager".to_string()),
+        ConfigField::GlmNodeName | ConfigField::ConfigurationServer | ConfigField::Metrics => {
+            Ok(value.to_string())
+        }
+    }
+}
+
+/// A payment network value as written in provisioning, e.g. `mainnet`
+pub fn payment_network(value: &str) -> Option<PaymentNetwork> {
+    match value.to_ascii_lowercase().as_str() {
+        "mainnet" => Some(PaymentNetwork::Mainnet),
+        "testnet" => Some(PaymentNetwork::Testnet),
+        _ => None,
+    }
+}
+
+/// A network type value as written in provisioning, e.g. `central`
+pub fn network_type(value: &str) -> Option<NetworkType> {
+    match value.to_ascii_lowercase().as_str() {
+        "central" => Some(NetworkType::Central),
+        "hybrid" => Some(NetworkType::Hybrid),
+        _ => None,
+    }
+}
+
+/// A yes/no value as written in provisioning, e.g. `true` or `1`
+pub fn flag(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Location of the provisioning file, `None` if there is none
+fn provisioning_file() -> Result<Option<PathBuf>> {
+    if let Some(path) = std::env::var_os(FILE_VARIABLE) {
+        return Ok(Some(PathBuf::from(path)));
+    }
+    let path = super::paths::config_dir()
+        .context("Failed to look for the provisioning file")?
+        .join(PROVISIONING_FILE);
+    Ok(path.exists().then_some(path))
+}
+
+/// Use `provisioning` from now on; called once at startup
+pub fn set_active(provisioning: Provisioning) {
+    match ACTIVE.write() {
+        Ok(mut active) => *active = provisioning,
+        Err(poisoned) => *poisoned.into_inner() = provisioning,
+    }
+}
+
+/// The provisioning in use
+pub fn active() -> Provisioning {
+    match ACTIVE.read() {
+        Ok(active) => active.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+/// Whether `field` is locked by the provisioning in use
+pub fn is_locked(field: ConfigField) -> bool {
+    match ACTIVE.read() {
+        Ok(active) => active.is_locked(field),
+        Err(poisoned) => poisoned.into_inner().is_locked(field),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_environment_locks_and_defaults() {
+        let (provisioning, errors) = Provisioning::from_sources(
+            None,
+            env(&[
+                ("GOLEM_IMAGER_LOCK_SUBNET", "prod-eu"),
+                ("GOLEM_IMAGER_DEFAULT_PAYMENT_NETWORK", "Mainnet"),
+                ("GOLEM_IMAGER_DEFAULT_SUBNET", "public"),
+                ("PATH", "/usr/bin"),
+            ]),
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(
+            provisioning.locked_value(ConfigField::Subnet),
+            Some("prod-eu")
+        );
+        assert_eq!(
+            provisioning.value(ConfigField::PaymentNetwork),
+            Some("mainnet")
+        );
+        assert!(!provisioning.is_locked(ConfigField::PaymentNetwork));
+        assert_eq!(provisioning.locked_value(ConfigField::PaymentNetwork), None);
+    }
+
+    #[test]
+    fn test_environment_overrides_file() {
+        let file = r#"
+            [defaults]
+            network_type = "hybrid"
+            non_interactive_install = true
+
+            [locked]
+            subnet = "bench-1"
+            ssh_keys = ["ssh-ed25519 AAAA admin@bench", "ssh-ed25519 BBBB ops@bench"]
+        "#;
+        let (provisioning, errors) =
+            Provisioning::from_sources(Some(file), env(&[("GOLEM_IMAGER_LOCK_SUBNET", "bench-2")]));
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(
+            provisioning.locked_value(ConfigField::Subnet),
+            Some("bench-2")
+        );
+        assert_eq!(provisioning.value(ConfigField::NetworkType), Some("hybrid"));
+        assert_eq!(
+            provisioning.value(ConfigField::NonInteractiveInstall),
+            Some("true")
+        );
+        assert_eq!(
+            provisioning.locked_value(ConfigField::SshKeys),
+            Some("ssh-ed25519 AAAA admin@bench\nssh-ed25519 BBBB ops@bench")
+        );
+    }
+
+    #[test]
+    fn test_invalid_values_are_reported() {
+        let (provisioning, errors) = Provisioning::from_sources(
+            Some("[locked]\nwallet = \"0x1\"\n"),
+            env(&[
+                ("GOLEM_IMAGER_LOCK_GLM_ACCOUNT", "0x123"),
+                ("GOLEM_IMAGER_LOCK_PAYMENT_NETWORK", "goerli"),
+                ("GOLEM_IMAGER_DEFAULT_SUBNET", "bad subnet"),
+            ]),
+        );
+        assert!(provisioning.is_empty());
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors[0].contains("no setting called wallet"));
+
+        let (_, errors) = Provisioning::from_sources(Some("[locked"), Vec::new());
+        assert!(errors[0].contains("can't be parsed"));
+    }
+}
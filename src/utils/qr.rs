@@ -0,0 +1,79 @@
+/// Reading QR codes from pictures
+///
+/// Wallet apps show the receiving address as a QR code. A screenshot or photo of it can be
+/// picked instead of typing the address; the first code found in the picture is decoded.
+/// Payment links (`ethereum:0x...`) are returned as they are, the caller normalizes them.
+use anyhow::{Context, Result, anyhow};
+use std::path::Path;
+
+/// Text of the first QR code in the picture at `path`
+pub fn decode_image_file(path: &Path) -> Result<String> {
+    let picture = image::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .into_luma8();
+    decode_greyscale(
+        picture.width() as usize,
+        picture.height() as usize,
+        |x, y| picture.get_pixel(x as u32, y as u32).0[0],
+    )
+}
+
+/// Text of the first QR code in a greyscale picture of `width` x `height` pixels
+pub fn decode_greyscale(
+    width: usize,
+    height: usize,
+    pixel: impl FnMut(usize, usize) -> u8,
+) -> Result<String> {
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(width, height, pixel);
+    let grids = prepared.detect_grids();
+    let grid = grids
+        .first()
+        .ok_or_else(|| anyhow!("No QR code found in the picture"))?;
+    let (_, content) = grid
+        .decode()
+        .map_err(|e| anyhow!("The QR code can't be read: {}", e))?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `text` as a QR code with a quiet zone, four pixels per module
+    fn draw(text: &str) -> (usize, Vec<u8>) {
+        const SCALE: usize = 4;
+        const QUIET_ZONE: usize = 4;
+
+        let code = qrcode::QrCode::new(text.as_bytes()).unwrap();
+        let modules = code.width();
+        let colors = code.to_colors();
+        let size = (modules + 2 * QUIET_ZONE) * SCALE;
+        let mut pixels = vec![255u8; size * size];
+        for y in 0..size {
+            for x in 0..size {
+                let (mx, my) = (x / SCALE, y / SCALE);
+                if (QUIET_ZONE..QUIET_ZONE + modules).contains(&mx)
+                    && (QUIET_ZONE..QUIET_ZONE + modules).contains(&my)
+                    && colors[(my - QUIET_ZONE) * modules + mx - QUIET_ZONE] == qrcode::Color::Dark
+                {
+                    pixels[y * size + x] = 0;
+                }
+            }
+        }
+        (size, pixels)
+    }
+
+    #[test]
+    fn test_decode_wallet_qr() {
+        let link = "ethereum:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed@1";
+        let (size, pixels) = draw(link);
+        let decoded = decode_greyscale(size, size, |x, y| pixels[y * size + x]).unwrap();
+        assert_eq!(decoded, link);
+    }
+
+    #[test]
+    fn test_no_qr_code() {
+        let blank = decode_greyscale(64, 64, |_, _| 255);
+        assert!(blank.unwrap_err().to_string().contains("No QR code"));
+    }
+}
@@ -0,0 +1,113 @@
+/// Images flashed recently, offered again on the image selection screen
+///
+/// Benches tend to flash the same few images for weeks, so every completed flash puts its
+/// image at the top of a short list kept in the settings. An entry is either an image file,
+/// which is picked again from its path, or a repository image, which is found again in the
+/// repository by its channel and version.
+use serde::{Deserialize, Serialize};
+
+/// Most images remembered, older ones are dropped
+pub const MAX_RECENT_IMAGES: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentImage {
+    /// Path of the image file, or the download URL of a repository image
+    pub location: String,
+    /// Channel of a repository image, none for image files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Version id of a repository image, the file name of an image file
+    pub version: String,
+    /// SHA-256 of the uncompressed image
+    #[serde(default)]
+    pub sha256: String,
+    /// When the image was last flashed, in seconds since the Unix epoch
+    pub last_used: i64,
+}
+
+impl RecentImage {
+    /// Whether the image is the same as `other`, whenever it was used
+    fn is_same(&self, other: &RecentImage) -> bool {
+        match (&self.channel, &other.channel) {
+            (Some(channel), Some(other_channel)) => {
+                channel == other_channel && self.version == other.version
+            }
+            (None, None) => self.location == other.location,
+            _ => false,
+        }
+    }
+
+    /// Whether an image file can still be read; repository images always can be fetched
+    pub fn is_available(&self) -> bool {
+        self.channel.is_some() || std::path::Path::new(&self.location).is_file()
+    }
+}
+
+/// Put `image` at the top of `recent`, replacing an earlier use of the same image
+pub fn remember(recent: &mut Vec<RecentImage>, image: RecentImage) {
+    recent.retain(|entry| !entry.is_same(&image));
+    recent.insert(0, image);
+    recent.truncate(MAX_RECENT_IMAGES);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, last_used: i64) -> RecentImage {
+        RecentImage {
+            location: path.to_string(),
+            channel: None,
+            version: path.rsplit('/').next().unwrap_or_default().to_string(),
+            sha256: String::new(),
+            last_used,
+        }
+    }
+
+    #[test]
+    fn test_remember_moves_image_to_top() {
+        let mut recent = vec![file("/images/a.img", 1), file("/images/b.img", 2)];
+        remember(&mut recent, file("/images/b.img", 3));
+        assert_eq!(
+            recent,
+            vec![file("/images/b.img", 3), file("/images/a.img", 1)]
+        );
+
+        let release = RecentImage {
+            location: "https://repo.example.com/golem-gpu-live-1.0.img.xz".to_string(),
+            channel: Some("release".to_string()),
+            version: "1.0".to_string(),
+            sha256: "00".repeat(32),
+            last_used: 4,
+        };
+        remember(&mut recent, release.clone());
+        remember(
+            &mut recent,
+            RecentImage {
+                last_used: 5,
+                ..release
+            },
+        );
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0].last_used, 5);
+    }
+
+    #[test]
+    fn test_list_is_limited() {
+        let mut recent = Vec::new();
+        for i in 0..MAX_RECENT_IMAGES as i64 + 3 {
+            remember(&mut recent, file(&format!("/images/{}.img", i), i));
+        }
+        assert_eq!(recent.len(), MAX_RECENT_IMAGES);
+        assert_eq!(recent[0].last_used, MAX_RECENT_IMAGES as i64 + 2);
+    }
+
+    #[test]
+    fn test_missing_files_are_unavailable() {
+        assert!(!file("/nonexistent/image.img", 0).is_available());
+        let path = std::env::temp_dir().join(format!("golem-recent-{}.img", std::process::id()));
+        std::fs::write(&path, b"image").unwrap();
+        assert!(file(&path.display().to_string(), 0).is_available());
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,17 +1,26 @@
+use crate::disk::{ConfigSchema, ImagePartition};
 use crate::models::CancelToken;
+use crate::utils::compatibility::ImageRequirements;
+use crate::utils::image_cache::ImageCache;
 use crate::utils::streaming_hash_calculator::{ProcessingProgress, StreamingHashCalculator};
-use directories::ProjectDirs;
 use futures_util::StreamExt;
-use iced::task;
 use reqwest;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use sipper::{Sipper, sipper};
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Read;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tokio::io::AsyncWriteExt;
+use tracing::{debug, info, warn};
+
+/// Signature verification of the repository metadata
+pub mod manifest;
+use manifest::ManifestStatus;
+
+/// Peer-to-peer image transports (IPFS, BitTorrent)
+mod transport;
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Version {
@@ -19,6 +28,21 @@ pub struct Version {
     pub path: String,
     pub sha256: String,
     pub created: String,
+    /// IPFS CID of the compressed image, if it was published there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipfs: Option<String>,
+    /// BitTorrent magnet link for the compressed image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub magnet: Option<String>,
+    /// Id of the configuration schema the image expects, the current one when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_schema: Option<String>,
+    /// Partitions of the uncompressed image, for progress while it is written
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partitions: Vec<ImagePartition>,
+    /// Architecture and GPU families the image is built for
+    #[serde(default, skip_serializing_if = "ImageRequirements::is_empty")]
+    pub requirements: ImageRequirements,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -30,6 +54,16 @@ pub struct Channel {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RepoMetadata {
     pub channels: Vec<Channel>,
+    /// Configuration schemas of images newer than the bundled ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config_schemas: Vec<ConfigSchema>,
+}
+
+impl RepoMetadata {
+    /// Configuration schema of an image listed in this manifest
+    pub fn config_schema(&self, version: &Version) -> ConfigSchema {
+        ConfigSchema::resolve(version.config_schema.as_deref(), &self.config_schemas)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -88,10 +122,12 @@ pub struct DownloadImage {
 }
 
 pub struct ImageRepo {
-    project_dirs: ProjectDirs,
+    cache_dir: PathBuf,
     metadata: Arc<Mutex<Option<RepoMetadata>>>,
     repo_url: String,
     downloads: Arc<Mutex<HashMap<String, DownloadStatus>>>,
+    manifest_status: Arc<Mutex<ManifestStatus>>,
+    cache: Arc<Mutex<ImageCache>>,
 }
 
 impl Default for ImageRepo {
@@ -102,16 +138,19 @@ impl Default for ImageRepo {
 
 impl ImageRepo {
     pub fn new() -> Self {
-        let project_dirs =
-            directories::ProjectDirs::from("network", "Golem Factory", "GPU Imager").unwrap();
+        let cache_dir = crate::utils::paths::cache_dir().unwrap();
         let repo_url =
             "https://repo-golem-gpu-live.s3.eu-central-1.amazonaws.com/images".to_string();
 
+        let cache = ImageCache::open(&cache_dir);
+
         Self {
-            project_dirs,
+            cache_dir,
             metadata: Arc::new(Mutex::new(None)),
             repo_url,
             downloads: Arc::new(Mutex::new(HashMap::new())),
+            manifest_status: Arc::new(Mutex::new(ManifestStatus::Unknown)),
+            cache: Arc::new(Mutex::new(cache)),
         }
     }
 
@@ -129,9 +168,21 @@ impl ImageRepo {
             ));
         }
 
-        let metadata: RepoMetadata = response
-            .json()
+        let manifest = response
+            .bytes()
             .await
+            .map_err(|e| format!("Failed to fetch repository metadata: {}", e))?;
+
+        // The signature is checked against the exact bytes that are parsed below
+        let signature = self.fetch_metadata_signature().await;
+        let status = manifest::verify(&manifest, signature.as_deref());
+        match &status {
+            ManifestStatus::Verified => info!("Repository metadata signature verified"),
+            other => warn!("Repository metadata not verified: {:?}", other),
+        }
+        *self.manifest_status.lock().unwrap() = status;
+
+        let metadata: RepoMetadata = serde_json::from_slice(&manifest)
             .map_err(|e| format!("Failed to parse metadata: {}", e))?;
 
         // Cache the metadata
@@ -142,6 +193,28 @@ impl ImageRepo {
         Ok(metadata)
     }
 
+    /// Fetch `meta.json.minisig`, `None` if the repository doesn't publish one
+    async fn fetch_metadata_signature(&self) -> Option<String> {
+        let signature_url = format!("{}/meta.json.minisig", self.repo_url);
+        let response = match reqwest::get(&signature_url).await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                debug!("No metadata signature, status: {}", response.status());
+                return None;
+            }
+            Err(e) => {
+                warn!("Failed to fetch metadata signature: {}", e);
+                return None;
+            }
+        };
+        response.text().await.ok()
+    }
+
+    /// Signature status of the last fetched metadata
+    pub fn manifest_status(&self) -> ManifestStatus {
+        self.manifest_status.lock().unwrap().clone()
+    }
+
     #[allow(dead_code)]
     pub fn get_newest_version_for_channel(&self, channel_name: &str) -> Option<Version> {
         let metadata = self.metadata.lock().ok()?;
@@ -167,6 +240,13 @@ impl ImageRepo {
             .map(|c| c.versions.clone())
     }
 
+    /// Look up a version as published in the repository metadata
+    pub fn find_version(&self, channel_name: &str, version_id: &str) -> Option<Version> {
+        self.get_all_versions_for_channel(channel_name)?
+            .into_iter()
+            .find(|v| v.id == version_id)
+    }
+
     #[allow(dead_code)]
     pub fn get_available_channels(&self) -> Vec<String> {
         if let Ok(metadata) = self.metadata.lock() {
@@ -191,46 +271,35 @@ impl ImageRepo {
             .unwrap_or(DownloadStatus::NotStarted)
     }
 
-    pub fn get_image_path(&self, version: &Version) -> PathBuf {
-        let cache_dir = self.project_dirs.cache_dir().to_path_buf();
-        cache_dir.join(&version.path)
+    /// Where the compressed image for `version` is served from
+    pub fn get_image_url(&self, version: &Version) -> String {
+        format!("{}/{}", self.repo_url, version.path)
     }
 
-    pub fn is_image_downloaded(&self, version: &Version) -> bool {
-        let path = self.get_image_path(version);
-        path.exists()
+    pub fn get_image_path(&self, version: &Version) -> PathBuf {
+        self.cache_dir.join(&version.path)
     }
 
-    fn verify_hash(&self, file_path: &Path, expected_hash: &str) -> Result<(), String> {
-        let mut file = File::open(file_path)
-            .map_err(|e| format!("Failed to open file for hash verification: {}", e))?;
-
-        let mut hasher = Sha256::new();
-        let mut buffer = [0; 8192];
-
-        loop {
-            let bytes_read = file
-                .read(&mut buffer)
-                .map_err(|e| format!("Failed to read file during hash verification: {}", e))?;
+    /// The download cache, for the cache management screen
+    pub fn cache(&self) -> std::sync::MutexGuard<'_, ImageCache> {
+        self.cache.lock().unwrap()
+    }
 
-            if bytes_read == 0 {
-                break;
-            }
+    /// Check a cached image's hash before reusing it; blocking, as it may hash the file
+    pub fn validate_cached_image(&self, file_name: &str, sha256: &str) -> bool {
+        self.cache().validate(file_name, sha256)
+    }
 
-            hasher.update(&buffer[..bytes_read]);
+    /// Record that a cached image was used, so it is evicted last
+    pub fn touch_cached_image(&self, file_name: &str) {
+        if let Err(e) = self.cache().touch(file_name) {
+            warn!("Failed to update image cache: {}", e);
         }
+    }
 
-        let hash = hasher.finalize();
-        let hash_hex = hex::encode(hash);
-
-        if hash_hex == expected_hash.to_lowercase() {
-            Ok(())
-        } else {
-            Err(format!(
-                "Hash verification failed. Expected: {}, got: {}",
-                expected_hash, hash_hex
-            ))
-        }
+    pub fn is_image_downloaded(&self, version: &Version) -> bool {
+        let path = self.get_image_path(version);
+        path.exists()
     }
 
     pub fn start_download(
@@ -238,25 +307,21 @@ impl ImageRepo {
         channel_name: &str,
         version: Version,
         cancel_token: CancelToken,
-    ) -> impl task::Sipper<Result<(), Error>, DownloadStatus> + 'static {
+    ) -> impl Sipper<Result<(), Error>, DownloadStatus> + 'static {
         let this = self.clone();
         let _channel_name = channel_name.to_string();
         let version_id = version.id.clone();
-        task::sipper(async move |mut sipper| -> Result<(), Error> {
+        sipper(async move |mut sipper| -> Result<(), Error> {
             let this = this.clone();
-            let repo_url = &this.repo_url;
-            let file_url = format!("{}/{}", repo_url, version.path);
+            let file_url = this.get_image_url(&version);
             let expected_hash = version.sha256.clone();
             let version_clone = version.clone();
 
             // Create cache directory if it doesn't exist
-            let cache_dir = this.project_dirs.cache_dir();
-            fs::create_dir_all(cache_dir)?;
+            let cache_dir = this.cache_dir.clone();
+            fs::create_dir_all(&cache_dir)?;
 
-            let final_path = {
-                let cache_dir = this.project_dirs.cache_dir().to_path_buf();
-                cache_dir.join(&version_clone.path)
-            };
+            let final_path = cache_dir.join(&version_clone.path);
 
             // If already downloaded and verified, check if we have cached metadata
             if final_path.exists() {
@@ -265,8 +330,9 @@ impl ImageRepo {
                     .map_err(|e| Error(format!("Failed to create metadata manager: {}", e)))?;
 
                 if let Ok(Some(metadata)) = metadata_manager.load_metadata(&expected_hash) {
-                    // Verify hash quickly
-                    if this.verify_hash(&final_path, &expected_hash).is_ok() {
+                    // Only rehashed if the file changed since it was last verified
+                    if this.validate_cached_image(&version_clone.path, &expected_hash) {
+                        this.touch_cached_image(&version_clone.path);
                         let status = DownloadStatus::Completed {
                             path: final_path.clone(),
                             metadata,
@@ -297,51 +363,90 @@ impl ImageRepo {
                 .insert(version_id.clone(), status.clone());
             sipper.send(status).await;
 
-            // Make the request
-            let response = reqwest::get(&file_url).await?;
+            // Try peers first; the file is verified against the same hash either way
+            let (peer_tx, mut peer_rx) = tokio::sync::mpsc::unbounded_channel();
+            let this_clone = this.clone();
+            let version_id_clone = version_id.clone();
+            let mut sipper_clone = sipper.clone();
+            let peer_progress_handler = tokio::spawn(async move {
+                while let Some(progress) = peer_rx.recv().await {
+                    let status = DownloadStatus::Processing(progress);
+                    this_clone
+                        .downloads
+                        .lock()
+                        .unwrap()
+                        .insert(version_id_clone.clone(), status.clone());
+                    let _ = sipper_clone.send(status).await;
+                }
+            });
+            let fetched_from_peers =
+                transport::fetch_from_peers(&version_clone, &temp_path, &cancel_token, &peer_tx)
+                    .await;
+            drop(peer_tx);
+            let _ = peer_progress_handler.await;
 
-            if !response.status().is_success() {
-                return Err(Error(format!(
-                    "Failed to download file, status: {}",
-                    response.status()
-                )));
+            if cancel_token.is_cancelled() {
+                let _ = fs::remove_file(&temp_path);
+                return Err(Error("Download cancelled by user".to_string()));
             }
 
-            let total_size = response.content_length().unwrap_or(0);
-            let mut downloaded = 0u64;
-            let mut output_file = tokio::fs::File::create(&temp_path).await?;
-            let mut stream = response.bytes_stream();
-
-            // Download phase: stream chunks and calculate compressed hash
-            while let Some(item) = stream.next().await {
-                if cancel_token.is_cancelled() {
-                    let _ = fs::remove_file(&temp_path);
-                    return Err(Error("Download cancelled by user".to_string()));
+            if fetched_from_peers {
+                let mut file = File::open(&temp_path)?;
+                let mut buffer = vec![0u8; 4 * 1024 * 1024];
+                loop {
+                    let bytes_read = file.read(&mut buffer)?;
+                    if bytes_read == 0 {
+                        break;
+                    }
+                    calculator.process_download_chunk(&buffer[..bytes_read])?;
+                }
+            } else {
+                // Make the request
+                let response = reqwest::get(&file_url).await?;
+
+                if !response.status().is_success() {
+                    return Err(Error(format!(
+                        "Failed to download file, status: {}",
+                        response.status()
+                    )));
                 }
 
-                let chunk = item?;
+                let total_size = response.content_length().unwrap_or(0);
+                let mut downloaded = 0u64;
+                let mut output_file = tokio::fs::File::create(&temp_path).await?;
+                let mut stream = response.bytes_stream();
 
-                // Process chunk for compressed hash calculation
-                calculator.process_download_chunk(&chunk)?;
+                // Download phase: stream chunks and calculate compressed hash
+                while let Some(item) = stream.next().await {
+                    if cancel_token.is_cancelled() {
+                        let _ = fs::remove_file(&temp_path);
+                        return Err(Error("Download cancelled by user".to_string()));
+                    }
 
-                // Write to file
-                output_file.write_all(&chunk).await?;
+                    let chunk = item?;
 
-                downloaded += chunk.len() as u64;
+                    // Process chunk for compressed hash calculation
+                    calculator.process_download_chunk(&chunk)?;
 
-                // Send download progress
-                let progress = ProcessingProgress::new_download(downloaded, total_size);
-                let status = DownloadStatus::Processing(progress);
-                this.downloads
-                    .lock()
-                    .unwrap()
-                    .insert(version_id.clone(), status.clone());
-                sipper.send(status).await;
-            }
+                    // Write to file
+                    output_file.write_all(&chunk).await?;
+
+                    downloaded += chunk.len() as u64;
 
-            // Close the file
-            output_file.flush().await?;
-            drop(output_file);
+                    // Send download progress
+                    let progress = ProcessingProgress::new_download(downloaded, total_size);
+                    let status = DownloadStatus::Processing(progress);
+                    this.downloads
+                        .lock()
+                        .unwrap()
+                        .insert(version_id.clone(), status.clone());
+                    sipper.send(status).await;
+                }
+
+                // Close the file
+                output_file.flush().await?;
+                drop(output_file);
+            }
 
             // Verify compressed hash
             let compressed_hash = calculator.finalize_compressed_hash();
@@ -359,6 +464,24 @@ impl ImageRepo {
                 return Err(e.into());
             }
 
+            // Track the new image and make room for it by evicting old ones
+            {
+                let mut cache = this.cache.lock().unwrap();
+                if let Err(e) = cache.record(&version_clone.path, &expected_hash) {
+                    warn!(
+                        "Failed to add {} to the image cache: {}",
+                        version_clone.path, e
+                    );
+                }
+                match cache.evict(Some(&version_clone.path)) {
+                    Ok(evicted) if !evicted.is_empty() => {
+                        info!("Evicted old images from the cache: {:?}", evicted)
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to evict old images: {}", e),
+                }
+            }
+
             // Metadata calculation phase
             let final_path_clone = final_path.clone();
             let version_id_clone = version_id.clone();
@@ -427,7 +550,7 @@ impl ImageRepo {
 
     #[allow(dead_code)]
     pub fn clean_cache(&self) -> Result<(), String> {
-        let cache_dir = self.project_dirs.cache_dir();
+        let cache_dir = &self.cache_dir;
 
         // Clean up partial downloads
         if let Ok(entries) = fs::read_dir(cache_dir) {
@@ -450,14 +573,13 @@ impl ImageRepo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use directories::ProjectDirs;
     use tokio::runtime::Runtime;
 
     #[test]
     fn it_works() {
         let repo = super::ImageRepo::new();
-        eprintln!("local={:?}", repo.project_dirs.state_dir());
-        eprintln!("cache={:?}", repo.project_dirs.cache_dir());
-        eprintln!("config={:?}", repo.project_dirs.config_dir());
+        eprintln!("cache={:?}", repo.cache_dir);
     }
 
     #[test]
@@ -481,6 +603,29 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_manifest_config_schemas() {
+        let metadata: RepoMetadata = serde_json::from_str(
+            r#"{
+                "channels": [{"name": "release", "versions": [
+                    {"id": "v1", "path": "a", "sha256": "", "created": "", "config_schema": "legacy"},
+                    {"id": "v3", "path": "b", "sha256": "", "created": "", "config_schema": "gen3"},
+                    {"id": "v2", "path": "c", "sha256": "", "created": ""}
+                ]}],
+                "config_schemas": [{"id": "gen3", "env_keys": {"SUBNET": "YA_SUBNET"}}]
+            }"#,
+        )
+        .unwrap();
+        let versions = &metadata.channels[0].versions;
+
+        assert!(metadata.config_schema(&versions[0]).hides_fields());
+        assert_eq!(metadata.config_schema(&versions[1]).id, "gen3");
+        assert_eq!(
+            metadata.config_schema(&versions[2]),
+            *ConfigSchema::current()
+        );
+    }
+
     #[test]
     fn test_path() {
         let project_dirs = ProjectDirs::from("network", "Golem Factory", "GPU Imager").unwrap();
@@ -0,0 +1,162 @@
+/// Signature verification of the repository metadata
+///
+/// The repository's `meta.json` lists the SHA-256 of every image, so whoever can change
+/// it can get any image flashed onto a provider node. The file is signed with minisign
+/// (Ed25519) and published as `meta.json.minisig`; the signature is checked against
+/// public keys compiled into the binary before any of it is trusted.
+use minisign_verify::{PublicKey, Signature};
+use tracing::warn;
+
+/// Trusted signing keys, one base64 minisign public key per line
+const TRUSTED_KEYS: &str = include_str!("../../../resources/trusted-keys.pub");
+
+/// Outcome of checking the repository metadata's signature
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestStatus {
+    /// Metadata has not been fetched yet
+    Unknown,
+    /// Signed by one of the trusted keys
+    Verified,
+    /// The repository published no signature
+    Unsigned,
+    /// A signature exists but does not match the metadata, or no key is bundled to check it
+    Invalid(String),
+}
+
+impl ManifestStatus {
+    /// Warning to show before flashing, if the images can't be trusted
+    pub fn warning(&self) -> Option<String> {
+        match self {
+            ManifestStatus::Unknown | ManifestStatus::Verified => None,
+            ManifestStatus::Unsigned => Some(
+                "The image list is not signed. Its images could not be verified as official Golem releases."
+                    .to_string(),
+            ),
+            ManifestStatus::Invalid(reason) => Some(format!(
+                "The image list failed signature verification ({}). It may have been modified, so its images can't be flashed.",
+                reason
+            )),
+        }
+    }
+
+    /// Whether images from the repository must not be written
+    pub fn blocks_flashing(&self) -> bool {
+        matches!(self, ManifestStatus::Invalid(_))
+    }
+}
+
+/// Check `manifest` against its minisign signature using the bundled keys
+///
+/// A build without trusted keys can't verify anything and fails closed.
+///
+/// # Arguments
+/// * `manifest` - Raw bytes of `meta.json`
+/// * `signature` - Contents of `meta.json.minisig`, `None` if the repository has none
+pub fn verify(manifest: &[u8], signature: Option<&str>) -> ManifestStatus {
+    let keys = bundled_keys();
+    if keys.is_empty() {
+        warn!("No trusted signing keys are bundled, repository metadata can't be verified");
+        return ManifestStatus::Invalid("no trusted signing keys are bundled".to_string());
+    }
+    verify_with_keys(manifest, signature, &keys)
+}
+
+/// The bundled signing keys, which sign the application's release installers as well
+pub fn bundled_keys() -> Vec<PublicKey> {
+    trusted_keys(TRUSTED_KEYS)
+}
+
+/// Check `content` against a minisign `signature` made with one of `keys`
+pub fn verify_signature(content: &[u8], signature: &str, keys: &[PublicKey]) -> Result<(), String> {
+    let signature =
+        Signature::decode(signature).map_err(|e| format!("malformed signature: {}", e))?;
+
+    let mut last_error = "no trusted keys".to_string();
+    for key in keys {
+        match key.verify(content, &signature, false) {
+            Ok(()) => return Ok(()),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(last_error)
+}
+
+/// Parse a key list in the `trusted-keys.pub` format, skipping keys that don't decode
+fn trusted_keys(list: &str) -> Vec<PublicKey> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| {
+            !line.is_empty() && !line.starts_with('#') && !line.starts_with("untrusted comment:")
+        })
+        .filter_map(|line| match PublicKey::from_base64(line) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                warn!("Ignoring invalid trusted key {}: {}", line, e);
+                None
+            }
+        })
+        .collect()
+}
+
+fn verify_with_keys(
+    manifest: &[u8],
+    signature: Option<&str>,
+    keys: &[PublicKey],
+) -> ManifestStatus {
+    let Some(signature) = signature else {
+        return ManifestStatus::Unsigned;
+    };
+    match verify_signature(manifest, signature, keys) {
+        Ok(()) => ManifestStatus::Verified,
+        Err(reason) => ManifestStatus::Invalid(reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_KEY: &str = "RWTzHwEtWKsm2Z6u5QM2uzozYqE8k2Vu+1G4ALCITQWTkC+oWS33d4ap";
+    const TEST_MANIFEST: &[u8] = br#"{"channels":[]}"#;
+    const TEST_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUTzHwEtWKsm2atRvtLBje4IhZ10MolriNxnQCrzOBKQIjt/aZj+B5DsfPihpGQzz/EhqKOVfsWKLPHHVQRTn48DE7mioR/ARgI=
+trusted comment: timestamp:1760000000\tfile:meta.json\thashed
+G8fY1So+83GXdkZoKRtsIPnmyBCeRmwSDz4d8fxBwpDJzCGSqrgIHd0/v1wlkqnp2afSDnkmVMRiGzPSiEu0Ag==
+";
+
+    #[test]
+    fn test_verifies_signed_manifest() {
+        let keys = trusted_keys(&format!("# test key\n{}\n", TEST_KEY));
+        assert_eq!(keys.len(), 1);
+        assert_eq!(
+            verify_with_keys(TEST_MANIFEST, Some(TEST_SIGNATURE), &keys),
+            ManifestStatus::Verified
+        );
+    }
+
+    #[test]
+    fn test_detects_modified_manifest() {
+        let keys = trusted_keys(TEST_KEY);
+        let modified = br#"{"channels":[{}]}"#;
+        let status = verify_with_keys(modified, Some(TEST_SIGNATURE), &keys);
+        assert!(matches!(status, ManifestStatus::Invalid(_)));
+        assert!(status.warning().is_some());
+        assert!(status.blocks_flashing());
+    }
+
+    #[test]
+    fn test_missing_signature_is_unsigned() {
+        let keys = trusted_keys(TEST_KEY);
+        let status = verify_with_keys(TEST_MANIFEST, None, &keys);
+        assert_eq!(status, ManifestStatus::Unsigned);
+        assert!(status.warning().is_some());
+        assert!(!status.blocks_flashing());
+        assert!(ManifestStatus::Verified.warning().is_none());
+    }
+
+    #[test]
+    fn test_no_keys_fails_closed() {
+        let status = verify_with_keys(TEST_MANIFEST, Some(TEST_SIGNATURE), &[]);
+        assert!(status.blocks_flashing());
+    }
+}
@@ -0,0 +1,204 @@
+/// Peer-to-peer download of official images
+///
+/// Images can be published over IPFS and BitTorrent in addition to the central repository.
+/// Far from the repository's region these are often faster, and every image fetched from
+/// peers is one less served by the repository. Both transports are optional features; a
+/// failed peer download falls back to HTTPS. Whatever the transport, the file is only
+/// accepted if it matches the SHA-256 the repository published for the version.
+use super::Version;
+use crate::models::CancelToken;
+use crate::utils::streaming_hash_calculator::ProcessingProgress;
+use std::path::Path;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Public gateway used to fetch content by CID
+#[cfg(feature = "ipfs")]
+const IPFS_GATEWAY: &str = "https://ipfs.io";
+
+/// How often torrent progress is reported
+#[cfg(feature = "torrent")]
+const TORRENT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Try to fetch the compressed image for `version` from peers into `target`
+///
+/// # Returns
+/// * `bool` - Whether `target` now holds the image; on `false` download it over HTTPS
+pub async fn fetch_from_peers(
+    version: &Version,
+    target: &Path,
+    cancel_token: &CancelToken,
+    progress: &UnboundedSender<ProcessingProgress>,
+) -> bool {
+    #[cfg(feature = "torrent")]
+    if let Some(magnet) = &version.magnet {
+        match fetch_torrent(magnet, target, cancel_token, progress).await {
+            Ok(()) => return true,
+            Err(e) => tracing::warn!("Torrent download of {} failed: {}", version.id, e),
+        }
+    }
+
+    #[cfg(feature = "ipfs")]
+    if let Some(cid) = &version.ipfs {
+        match fetch_ipfs(cid, target, cancel_token, progress).await {
+            Ok(()) => return true,
+            Err(e) => tracing::warn!("IPFS download of {} failed: {}", version.id, e),
+        }
+    }
+
+    let _ = (version, target, cancel_token, progress);
+    false
+}
+
+/// Gateway URL serving the content with the given CID
+#[cfg(any(feature = "ipfs", test))]
+fn ipfs_url(gateway: &str, cid: &str) -> String {
+    format!("{}/ipfs/{}", gateway.trim_end_matches('/'), cid)
+}
+
+/// Download a CID through the IPFS HTTP gateway
+#[cfg(feature = "ipfs")]
+async fn fetch_ipfs(
+    cid: &str,
+    target: &Path,
+    cancel_token: &CancelToken,
+    progress: &UnboundedSender<ProcessingProgress>,
+) -> Result<(), String> {
+    use futures_util::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    let url = ipfs_url(IPFS_GATEWAY, cid);
+    tracing::info!("Downloading image from IPFS: {}", url);
+
+    let response = reqwest::get(&url)
+        .await
+        .map_err(|e| format!("Failed to reach IPFS gateway: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "IPFS gateway returned status {}",
+            response.status()
+        ));
+    }
+
+    let total_size = response.content_length().unwrap_or(0);
+    let mut downloaded = 0u64;
+    let mut output_file = tokio::fs::File::create(target)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    let mut stream = response.bytes_stream();
+
+    while let Some(item) = stream.next().await {
+        if cancel_token.is_cancelled() {
+            return Err("Download cancelled by user".to_string());
+        }
+
+        let chunk = item.map_err(|e| format!("IPFS transfer failed: {}", e))?;
+        output_file
+            .write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+
+        downloaded += chunk.len() as u64;
+        let _ = progress.send(ProcessingProgress::new_download(downloaded, total_size));
+    }
+
+    output_file
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    Ok(())
+}
+
+/// Download a single-file torrent and move its file to `target`
+#[cfg(feature = "torrent")]
+async fn fetch_torrent(
+    magnet: &str,
+    target: &Path,
+    cancel_token: &CancelToken,
+    progress: &UnboundedSender<ProcessingProgress>,
+) -> Result<(), String> {
+    use librqbit::{AddTorrent, AddTorrentResponse, Session};
+
+    // The torrent's files land in their own directory next to the target
+    let output_dir = target.with_extension("torrent.d");
+    tracing::info!(
+        "Downloading image over BitTorrent into {}",
+        output_dir.display()
+    );
+
+    let session = Session::new(output_dir.clone())
+        .await
+        .map_err(|e| format!("Failed to start torrent session: {}", e))?;
+    let handle = match session
+        .add_torrent(AddTorrent::from_url(magnet), None)
+        .await
+        .map_err(|e| format!("Failed to add torrent: {}", e))?
+    {
+        AddTorrentResponse::Added(_, handle) | AddTorrentResponse::AlreadyManaged(_, handle) => {
+            handle
+        }
+        AddTorrentResponse::ListOnly(_) => {
+            return Err("Torrent was only listed, not started".to_string());
+        }
+    };
+
+    let result = loop {
+        if cancel_token.is_cancelled() {
+            break Err("Download cancelled by user".to_string());
+        }
+
+        let stats = handle.stats();
+        let _ = progress.send(ProcessingProgress::new_download(
+            stats.progress_bytes,
+            stats.total_bytes,
+        ));
+        if stats.finished {
+            break Ok(());
+        }
+
+        tokio::time::sleep(TORRENT_POLL_INTERVAL).await;
+    };
+    session.stop().await;
+
+    let moved = result.and_then(|()| {
+        let file_name = target
+            .file_name()
+            .ok_or_else(|| format!("Invalid target path {}", target.display()))?;
+        std::fs::rename(output_dir.join(file_name), target)
+            .map_err(|e| format!("Torrent did not contain the expected image file: {}", e))
+    });
+    let _ = std::fs::remove_dir_all(&output_dir);
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ipfs_url() {
+        assert_eq!(
+            ipfs_url("https://ipfs.io/", "bafybeigdyrzt"),
+            "https://ipfs.io/ipfs/bafybeigdyrzt"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_version_without_peer_sources_uses_https() {
+        let version = Version {
+            id: "v1".to_string(),
+            path: "image.img.xz".to_string(),
+            sha256: String::new(),
+            created: String::new(),
+            ipfs: None,
+            magnet: None,
+            config_schema: None,
+            partitions: Vec::new(),
+            requirements: Default::default(),
+        };
+        let (progress_tx, _progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let target = std::env::temp_dir().join("golem-transport-test.img.xz");
+
+        assert!(!fetch_from_peers(&version, &target, &CancelToken::new(), &progress_tx).await);
+        assert!(!target.exists());
+    }
+}
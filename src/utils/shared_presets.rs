@@ -0,0 +1,294 @@
+/// Presets shared by a team from a URL of their choosing
+///
+/// A fleet operator can publish a collection of presets, e.g. next to their configuration
+/// server, and point every imaging station at it in the settings. The collection is fetched
+/// at startup over HTTPS and listed next to the local presets, read-only: it is changed by
+/// publishing a new collection, not from the imager. A SHA-256 pin or a minisign public key
+/// in the settings makes sure only the collection the team published is used. The last one
+/// that checked out is kept in the cache directory, so the presets are still there when the
+/// station is offline.
+use crate::models::ConfigurationPreset;
+use anyhow::{Context, Result, anyhow};
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Where the shared presets come from and how they are checked
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SharedPresetsSettings {
+    /// HTTPS URL of the collection, nothing is fetched while it is empty
+    #[serde(default)]
+    pub url: String,
+    /// SHA-256 the collection must have, in hex; empty to accept any content
+    #[serde(default)]
+    pub sha256: String,
+    /// Minisign public key the collection must be signed with, the signature being
+    /// published at the URL with `.minisig` appended; empty if it isn't signed
+    #[serde(default)]
+    pub public_key: String,
+}
+
+impl SharedPresetsSettings {
+    /// Whether a collection should be fetched
+    pub fn is_active(&self) -> bool {
+        !self.url.trim().is_empty()
+    }
+
+    /// What is wrong with the settings, if anything
+    pub fn error(&self) -> Option<String> {
+        let url = self.url.trim();
+        if url.is_empty() {
+            return None;
+        }
+        if !url.starts_with("https://") {
+            return Some("Shared presets are only fetched over HTTPS".to_string());
+        }
+        let pin = self.sha256.trim();
+        if !pin.is_empty() && (pin.len() != 64 || !pin.chars().all(|c| c.is_ascii_hexdigit())) {
+            return Some("The SHA-256 pin must be 64 hexadecimal characters".to_string());
+        }
+        let key = self.public_key.trim();
+        if !key.is_empty() && PublicKey::from_base64(key).is_err() {
+            return Some("The public key is not a minisign public key".to_string());
+        }
+        None
+    }
+}
+
+/// The collection file, as published by the team
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedPresetsFile {
+    presets: Vec<ConfigurationPreset>,
+}
+
+/// A collection that passed the checks
+#[derive(Debug, Clone)]
+pub struct SharedPresets {
+    pub presets: Vec<ConfigurationPreset>,
+    /// The collection couldn't be fetched and the cached copy is used; why, if so
+    pub offline: Option<String>,
+}
+
+/// Fetch the collection the settings point at, falling back to the cached copy
+pub async fn load(settings: &SharedPresetsSettings) -> Result<SharedPresets> {
+    if let Some(error) = settings.error() {
+        return Err(anyhow!(error));
+    }
+    let url = settings.url.trim();
+
+    let fetch_error = match fetch(url, settings).await {
+        Ok((content, signature)) => {
+            let presets = parse(&content)?;
+            if let Err(e) = save_cache(url, &content, signature.as_deref()) {
+                warn!("Failed to cache the shared presets: {:#}", e);
+            }
+            info!("Loaded {} shared presets from {}", presets.len(), url);
+            return Ok(SharedPresets {
+                presets,
+                offline: None,
+            });
+        }
+        Err(e) => e,
+    };
+
+    // The cached copy is checked again, the pin may have changed since it was saved
+    let Ok((content, signature)) = load_cache(url) else {
+        return Err(fetch_error);
+    };
+    verify(&content, signature.as_deref(), settings)
+        .context("The cached shared presets don't match the pin")?;
+    warn!("Using the cached shared presets: {:#}", fetch_error);
+    Ok(SharedPresets {
+        presets: parse(&content)?,
+        offline: Some(format!("{:#}", fetch_error)),
+    })
+}
+
+/// Download the collection and its signature and check them
+async fn fetch(url: &str, settings: &SharedPresetsSettings) -> Result<(Vec<u8>, Option<String>)> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("golem-gpu-imager/", env!("CARGO_PKG_VERSION")))
+        .https_only(true)
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+        .build()
+        .context("Failed to create HTTP client")?;
+
+    let content = get(&client, url).await?;
+    let signature = if settings.public_key.trim().is_empty() {
+        None
+    } else {
+        let signature = get(&client, &format!("{}.minisig", url))
+            .await
+            .context("Failed to download the signature of the shared presets")?;
+        Some(String::from_utf8_lossy(&signature).into_owned())
+    };
+    verify(&content, signature.as_deref(), settings)?;
+    Ok((content, signature))
+}
+
+async fn get(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download {}", url))?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to download {}, status: {}",
+            url,
+            response.status()
+        ));
+    }
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Check `content` against the pin and the signing key of the settings
+fn verify(content: &[u8], signature: Option<&str>, settings: &SharedPresetsSettings) -> Result<()> {
+    let pin = settings.sha256.trim();
+    if !pin.is_empty() {
+        let sha256 = hex::encode(Sha256::digest(content));
+        if !sha256.eq_ignore_ascii_case(pin) {
+            return Err(anyhow!(
+                "The shared presets have SHA-256 {}, but {} is pinned",
+                sha256,
+                pin
+            ));
+        }
+    }
+
+    let key = settings.public_key.trim();
+    if !key.is_empty() {
+        let key = PublicKey::from_base64(key)
+            .map_err(|e| anyhow!("Invalid public key for the shared presets: {}", e))?;
+        let signature = signature.ok_or_else(|| anyhow!("The shared presets are not signed"))?;
+        let signature = Signature::decode(signature)
+            .map_err(|e| anyhow!("Malformed signature of the shared presets: {}", e))?;
+        key.verify(content, &signature, false)
+            .map_err(|e| anyhow!("The signature of the shared presets is invalid: {}", e))?;
+    }
+    Ok(())
+}
+
+/// The presets of a collection, marked shared and never the default
+fn parse(content: &[u8]) -> Result<Vec<ConfigurationPreset>> {
+    let file: SharedPresetsFile =
+        serde_json::from_slice(content).context("Failed to parse the shared presets")?;
+    Ok(file
+        .presets
+        .into_iter()
+        .filter(|preset| !preset.name.trim().is_empty())
+        .map(|mut preset| {
+            preset.shared = true;
+            preset.is_default = false;
+            preset
+        })
+        .collect())
+}
+
+/// Cached copy of the collection at `url` and of its signature
+fn cache_paths(url: &str) -> Result<(PathBuf, PathBuf)> {
+    let name = format!(
+        "shared-presets-{}.json",
+        &hex::encode(Sha256::digest(url.as_bytes()))[..16]
+    );
+    let path = super::paths::cache_dir()?.join(name);
+    let signature = path.with_extension("json.minisig");
+    Ok((path, signature))
+}
+
+fn save_cache(url: &str, content: &[u8], signature: Option<&str>) -> Result<()> {
+    let (path, signature_path) = cache_paths(url)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    match signature {
+        Some(signature) => std::fs::write(&signature_path, signature)
+            .with_context(|| format!("Failed to write {}", signature_path.display())),
+        None => {
+            let _ = std::fs::remove_file(&signature_path);
+            Ok(())
+        }
+    }
+}
+
+fn load_cache(url: &str) -> Result<(Vec<u8>, Option<String>)> {
+    let (path, signature_path) = cache_paths(url)?;
+    let content =
+        std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok((content, std::fs::read_to_string(signature_path).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLECTION: &[u8] = br#"{"presets": [
+        {"name": "Rack A", "payment_network": "Mainnet", "subnet": "public",
+         "network_type": "Central", "wallet_address": "", "is_default": true},
+        {"name": " ", "payment_network": "Testnet", "subnet": "public",
+         "network_type": "Central", "wallet_address": "", "is_default": false}
+    ]}"#;
+
+    fn pinned(sha256: &str) -> SharedPresetsSettings {
+        SharedPresetsSettings {
+            url: "https://presets.example.com/team.json".to_string(),
+            sha256: sha256.to_string(),
+            public_key: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_marks_presets_shared() {
+        let presets = parse(COLLECTION).unwrap();
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].name, "Rack A");
+        assert!(presets[0].shared);
+        assert!(!presets[0].is_default);
+        assert!(parse(b"[]").is_err());
+    }
+
+    #[test]
+    fn test_sha256_pin() {
+        let sha256 = hex::encode(Sha256::digest(COLLECTION));
+        assert!(verify(COLLECTION, None, &pinned(&sha256)).is_ok());
+        assert!(verify(COLLECTION, None, &pinned(&sha256.to_uppercase())).is_ok());
+        assert!(verify(COLLECTION, None, &pinned(&"0".repeat(64))).is_err());
+        assert!(verify(COLLECTION, None, &pinned("")).is_ok());
+    }
+
+    #[test]
+    fn test_signature_is_required_with_a_key() {
+        let settings = SharedPresetsSettings {
+            public_key: "RWTzHwEtWKsm2Z6u5QM2uzozYqE8k2Vu+1G4ALCITQWTkC+oWS33d4ap".to_string(),
+            ..pinned("")
+        };
+        assert!(settings.error().is_none());
+        let error = verify(COLLECTION, None, &settings).unwrap_err();
+        assert!(error.to_string().contains("not signed"));
+    }
+
+    #[test]
+    fn test_settings_errors() {
+        assert!(SharedPresetsSettings::default().error().is_none());
+        assert!(!SharedPresetsSettings::default().is_active());
+
+        let plain_http = SharedPresetsSettings {
+            url: "http://presets.example.com/team.json".to_string(),
+            ..Default::default()
+        };
+        assert!(plain_http.error().unwrap().contains("HTTPS"));
+        assert!(pinned("abc").error().unwrap().contains("64"));
+        let bad_key = SharedPresetsSettings {
+            public_key: "not a key".to_string(),
+            ..pinned("")
+        };
+        assert!(bad_key.error().is_some());
+    }
+}
@@ -0,0 +1,370 @@
+/// Only one imager at a time
+///
+/// Two imagers flashing the same disk would overwrite each other's writes, so the first
+/// one started holds a lock (a lock file on Linux and macOS, a named mutex on Windows) for
+/// as long as it runs. A second one started meanwhile doesn't open a window: it passes its
+/// command line to the first over a local socket (a named pipe on Windows), which brings
+/// its window to the front, and exits.
+use anyhow::{Context, Result, bail};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// How long a second instance waits for the first to answer
+const HANDOFF_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Delay between attempts to reach a first instance that is still starting
+const HANDOFF_RETRY_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What starting this instance came to
+#[derive(Debug, PartialEq, Eq)]
+pub enum Claim {
+    /// No other instance is running, so this one starts; it holds the lock until it exits
+    Primary,
+    /// The running instance was asked to show its window, this one should exit
+    HandedOff,
+}
+
+/// The first instance's side of the handoff
+struct Instance {
+    activations: Mutex<mpsc::UnboundedReceiver<Vec<String>>>,
+    lock: Mutex<Option<platform::Lock>>,
+}
+
+static INSTANCE: OnceLock<Instance> = OnceLock::new();
+
+/// Become the only running instance, or hand `args` over to the one already running
+///
+/// Fails only if another instance holds the lock but doesn't take the handoff. If the lock
+/// itself can't be taken, e.g. on a read-only runtime directory, the instance starts
+/// without it.
+///
+/// # Arguments
+/// * `args` - The command line arguments, without the program name
+pub fn claim(args: &[String]) -> Result<Claim> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to create the single instance runtime")?;
+
+    let lock = match platform::lock() {
+        Ok(Some(lock)) => lock,
+        Ok(None) => {
+            info!("Another instance is running, handing over to it");
+            runtime.block_on(hand_off(args))?;
+            return Ok(Claim::HandedOff);
+        }
+        Err(e) => {
+            warn!("Cannot tell whether another instance is running: {:#}", e);
+            return Ok(Claim::Primary);
+        }
+    };
+
+    let (activations_tx, activations_rx) = mpsc::unbounded_channel();
+    // Without the listener later starts can't reach this instance, but are still refused
+    match runtime.block_on(platform::bind()) {
+        Ok(listener) => {
+            std::thread::Builder::new()
+                .name("single-instance".to_string())
+                .spawn(move || runtime.block_on(platform::run(listener, activations_tx)))
+                .context("Failed to start the single instance thread")?;
+        }
+        Err(e) => warn!("Other instances can't hand over to this one: {:#}", e),
+    }
+
+    let _ = INSTANCE.set(Instance {
+        activations: Mutex::new(activations_rx),
+        lock: Mutex::new(Some(lock)),
+    });
+    Ok(Claim::Primary)
+}
+
+/// Whether this instance holds the lock
+pub fn is_primary() -> bool {
+    INSTANCE
+        .get()
+        .and_then(|instance| instance.lock.lock().ok())
+        .is_some_and(|lock| lock.is_some())
+}
+
+/// Command lines of the instances started since the last call
+pub fn take_activations() -> Vec<Vec<String>> {
+    let Some(Ok(mut activations)) = INSTANCE.get().map(|instance| instance.activations.lock())
+    else {
+        return Vec::new();
+    };
+    let mut taken = Vec::new();
+    while let Ok(args) = activations.try_recv() {
+        taken.push(args);
+    }
+    taken
+}
+
+/// Let another instance start, e.g. the elevated one replacing this process
+pub fn release() {
+    let Some(instance) = INSTANCE.get() else {
+        return;
+    };
+    let released = instance
+        .lock
+        .lock()
+        .map(|mut lock| lock.take().is_some())
+        .unwrap_or(false);
+    if released {
+        debug!("Released the single instance lock");
+        platform::cleanup();
+    }
+}
+
+/// Send `args` to the running instance and wait until it has taken them
+async fn hand_off(args: &[String]) -> Result<()> {
+    let mut line = serde_json::to_string(args)?;
+    line.push('\n');
+
+    let attempt = async {
+        loop {
+            match platform::connect().await {
+                Ok(stream) => return Ok::<_, anyhow::Error>(stream),
+                // The running instance may still be starting its listener
+                Err(e) => {
+                    debug!("Running instance not reachable yet: {}", e);
+                    tokio::time::sleep(HANDOFF_RETRY_INTERVAL).await;
+                }
+            }
+        }
+    };
+    let answer = tokio::time::timeout(HANDOFF_TIMEOUT, async {
+        let stream = attempt.await?;
+        let (reader, mut writer) = tokio::io::split(stream);
+        writer.write_all(line.as_bytes()).await?;
+        writer.flush().await?;
+        let mut answer = String::new();
+        BufReader::new(reader).read_line(&mut answer).await?;
+        Ok::<_, anyhow::Error>(answer)
+    })
+    .await
+    .context("The running instance doesn't answer")??;
+
+    if answer.trim() != "ok" {
+        bail!(
+            "The running instance refused the handoff: {}",
+            answer.trim()
+        );
+    }
+    Ok(())
+}
+
+/// Take one command line from a later instance and acknowledge it
+async fn serve_connection<S>(stream: S, activations: &mpsc::UnboundedSender<Vec<String>>)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut line = String::new();
+    if let Err(e) = BufReader::new(reader).read_line(&mut line).await {
+        warn!("Failed to read from a later instance: {}", e);
+        return;
+    }
+    let answer = match serde_json::from_str::<Vec<String>>(&line) {
+        Ok(args) => {
+            info!("Another instance was started with {:?}", args);
+            let _ = activations.send(args);
+            "ok\n"
+        }
+        Err(e) => {
+            warn!("Invalid handoff from a later instance: {}", e);
+            "invalid\n"
+        }
+    };
+    let _ = writer.write_all(answer.as_bytes()).await;
+    let _ = writer.flush().await;
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use super::*;
+    use std::fs::{File, OpenOptions};
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use tokio::net::{UnixListener, UnixStream};
+
+    /// The locked file, unlocked when it is closed
+    pub type Lock = File;
+
+    fn runtime_dir() -> PathBuf {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
+    }
+
+    fn socket_path() -> PathBuf {
+        runtime_dir().join("golem-gpu-imager-instance.sock")
+    }
+
+    /// Lock the instance file, None if another instance holds it
+    pub fn lock() -> Result<Option<Lock>> {
+        let path = runtime_dir().join("golem-gpu-imager.lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(file)),
+            Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+            Err(std::fs::TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+            }
+        }
+    }
+
+    /// Listen on the socket, which only the current user may connect to
+    pub async fn bind() -> Result<UnixListener> {
+        let path = socket_path();
+        // Only the lock holder gets here, so a socket found is left behind by a crash
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to restrict access to {}", path.display()))?;
+        Ok(listener)
+    }
+
+    pub async fn run(listener: UnixListener, activations: mpsc::UnboundedSender<Vec<String>>) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => serve_connection(stream, &activations).await,
+                Err(e) => {
+                    warn!("Failed to accept a later instance: {}", e);
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+            }
+        }
+    }
+
+    pub async fn connect() -> std::io::Result<UnixStream> {
+        UnixStream::connect(socket_path()).await
+    }
+
+    pub fn cleanup() {
+        let _ = std::fs::remove_file(socket_path());
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    use tokio::net::windows::named_pipe::{
+        ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions,
+    };
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ALREADY_EXISTS, GetLastError, HANDLE};
+    use windows_sys::Win32::System::Threading::CreateMutexW;
+
+    const MUTEX_NAME: &str = r"Local\golem-gpu-imager";
+    const PIPE_NAME: &str = r"\\.\pipe\golem-gpu-imager-instance";
+
+    /// The named mutex, released when its handle is closed
+    pub struct Lock(HANDLE);
+
+    // The handle is only ever closed
+    unsafe impl Send for Lock {}
+
+    impl Drop for Lock {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    /// Create the instance mutex, None if another instance already did
+    pub fn lock() -> Result<Option<Lock>> {
+        let name: Vec<u16> = MUTEX_NAME.encode_utf16().chain(Some(0)).collect();
+        let handle = unsafe { CreateMutexW(std::ptr::null(), 0, name.as_ptr()) };
+        if handle == 0 {
+            bail!(
+                "Failed to create the instance mutex: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        let lock = Lock(handle);
+        if unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+            return Ok(None);
+        }
+        Ok(Some(lock))
+    }
+
+    /// Create the first instance of the pipe; remote clients are rejected
+    pub async fn bind() -> Result<NamedPipeServer> {
+        ServerOptions::new()
+            .first_pipe_instance(true)
+            .reject_remote_clients(true)
+            .create(PIPE_NAME)
+            .with_context(|| format!("Failed to create the pipe {}", PIPE_NAME))
+    }
+
+    pub async fn run(mut server: NamedPipeServer, activations: mpsc::UnboundedSender<Vec<String>>) {
+        loop {
+            if let Err(e) = server.connect().await {
+                warn!("Failed to accept a later instance: {}", e);
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                continue;
+            }
+            // Each client needs a pipe instance of its own
+            let next = match ServerOptions::new()
+                .reject_remote_clients(true)
+                .create(PIPE_NAME)
+            {
+                Ok(next) => next,
+                Err(e) => {
+                    warn!("Failed to create the next instance pipe: {}", e);
+                    return;
+                }
+            };
+            let client = std::mem::replace(&mut server, next);
+            serve_connection(client, &activations).await;
+        }
+    }
+
+    pub async fn connect() -> std::io::Result<NamedPipeClient> {
+        ClientOptions::new().open(PIPE_NAME)
+    }
+
+    // Pipes disappear with the process
+    pub fn cleanup() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn handoff_answer(line: &str) -> (String, Vec<Vec<String>>) {
+        let (client, server) = tokio::io::duplex(1024);
+        let (activations_tx, mut activations_rx) = mpsc::unbounded_channel();
+        let serve = tokio::spawn(async move { serve_connection(server, &activations_tx).await });
+
+        let (reader, mut writer) = tokio::io::split(client);
+        writer.write_all(line.as_bytes()).await.unwrap();
+        let mut answer = String::new();
+        BufReader::new(reader).read_line(&mut answer).await.unwrap();
+        serve.await.unwrap();
+
+        let mut activations = Vec::new();
+        while let Ok(args) = activations_rx.try_recv() {
+            activations.push(args);
+        }
+        (answer, activations)
+    }
+
+    #[tokio::test]
+    async fn test_handoff_passes_the_command_line() {
+        let (answer, activations) = handoff_answer("[\"--portable\"]\n").await;
+        assert_eq!(answer, "ok\n");
+        assert_eq!(activations, vec![vec!["--portable".to_string()]]);
+
+        let (answer, activations) = handoff_answer("focus\n").await;
+        assert_eq!(answer, "invalid\n");
+        assert!(activations.is_empty());
+    }
+}
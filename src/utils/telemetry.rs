@@ -0,0 +1,392 @@
+/// Opt-in statistics about finished flashes for monitoring a provisioning pipeline
+///
+/// Organizations imaging hundreds of nodes want to know how long flashes take, how fast the
+/// devices are and why writes fail. When turned on in the settings, one record per finished,
+/// failed or cancelled flash is sent to a collector, either as a plain JSON document or as
+/// OTLP metrics in their JSON encoding. Records contain no wallet addresses, device serial
+/// numbers or other configuration, only the outcome, timings and the image version.
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::fmt;
+use std::time::Duration;
+
+/// Path OTLP collectors receive metrics on
+const OTLP_METRICS_PATH: &str = "/v1/metrics";
+
+/// How records are encoded when they are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TelemetryFormat {
+    /// One JSON document per flash, POSTed to the endpoint as is
+    Json,
+    /// OTLP/HTTP with the JSON encoding, for OpenTelemetry collectors
+    Otlp,
+}
+
+impl fmt::Display for TelemetryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TelemetryFormat::Json => write!(f, "JSON"),
+            TelemetryFormat::Otlp => write!(f, "OpenTelemetry (OTLP)"),
+        }
+    }
+}
+
+/// Where and how statistics are sent; nothing is sent unless `enabled` is set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetrySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// URL records are POSTed to; for OTLP the collector's base URL works too
+    #[serde(default)]
+    pub endpoint: String,
+    #[serde(default = "default_format")]
+    pub format: TelemetryFormat,
+    /// Name of this imaging station, to tell the records of several stations apart
+    #[serde(default)]
+    pub station: String,
+}
+
+fn default_format() -> TelemetryFormat {
+    TelemetryFormat::Json
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: String::new(),
+            format: default_format(),
+            station: String::new(),
+        }
+    }
+}
+
+impl TelemetrySettings {
+    /// Whether records should be sent
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.endpoint.trim().is_empty()
+    }
+}
+
+/// How a flash ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashOutcome {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl FlashOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FlashOutcome::Succeeded => "succeeded",
+            FlashOutcome::Failed => "failed",
+            FlashOutcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Rough cause of a failed flash, so failures can be counted without the full messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Permission,
+    DeviceBusy,
+    DeviceRemoved,
+    Verification,
+    Network,
+    Io,
+    Other,
+}
+
+impl ErrorCategory {
+    /// Sort an error message into a category
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|pattern| error.contains(pattern));
+
+        if has(&[
+            "permission denied",
+            "access is denied",
+            "not authorized",
+            "privilege",
+        ]) {
+            ErrorCategory::Permission
+        } else if has(&["busy", "in use", "locked", "mounted"]) {
+            ErrorCategory::DeviceBusy
+        } else if has(&["no such device", "not found", "removed", "disconnected"]) {
+            ErrorCategory::DeviceRemoved
+        } else if has(&["verif", "hash mismatch", "checksum"]) {
+            ErrorCategory::Verification
+        } else if has(&["download", "connection", "timed out", "dns", "http"]) {
+            ErrorCategory::Network
+        } else if has(&["i/o", "io error", "input/output", "write", "read"]) {
+            ErrorCategory::Io
+        } else {
+            ErrorCategory::Other
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Permission => "permission",
+            ErrorCategory::DeviceBusy => "device_busy",
+            ErrorCategory::DeviceRemoved => "device_removed",
+            ErrorCategory::Verification => "verification",
+            ErrorCategory::Network => "network",
+            ErrorCategory::Io => "io",
+            ErrorCategory::Other => "other",
+        }
+    }
+}
+
+/// Statistics of one flash
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlashMetrics {
+    pub outcome: FlashOutcome,
+    pub duration_secs: f64,
+    /// Bytes of the image written to the device
+    pub bytes_written: u64,
+    pub verified: bool,
+    pub error_category: Option<ErrorCategory>,
+    /// Failed attempts on the same device that came before this one
+    pub retries: u32,
+    pub image_channel: String,
+    pub image_version: String,
+    pub device_size: u64,
+    /// When the flash ended, in seconds since the Unix epoch
+    pub finished_at: i64,
+}
+
+impl FlashMetrics {
+    /// Average speed over the whole flash in bytes per second
+    pub fn throughput(&self) -> f64 {
+        if self.duration_secs > 0.0 {
+            self.bytes_written as f64 / self.duration_secs
+        } else {
+            0.0
+        }
+    }
+
+    /// The record as a plain JSON document
+    pub fn to_json(&self, station: &str) -> Value {
+        json!({
+            "event": "flash",
+            "imager_version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "station": station_name(station),
+            "outcome": self.outcome,
+            "duration_secs": self.duration_secs,
+            "bytes_written": self.bytes_written,
+            "throughput_bytes_per_sec": self.throughput(),
+            "verified": self.verified,
+            "error_category": self.error_category,
+            "retries": self.retries,
+            "image_channel": self.image_channel,
+            "image_version": self.image_version,
+            "device_size": self.device_size,
+            "finished_at": self.finished_at,
+        })
+    }
+
+    /// The record as an OTLP `ExportMetricsServiceRequest` in the JSON encoding
+    pub fn to_otlp(&self, station: &str) -> Value {
+        let time = (self.finished_at.max(0) as u64 * 1_000_000_000).to_string();
+        let mut attributes = vec![
+            otlp_attribute("outcome", self.outcome.as_str()),
+            otlp_attribute("image.channel", &self.image_channel),
+            otlp_attribute("image.version", &self.image_version),
+            json!({ "key": "verified", "value": { "boolValue": self.verified } }),
+        ];
+        if let Some(category) = self.error_category {
+            attributes.push(otlp_attribute("error.category", category.as_str()));
+        }
+        let gauge = |name: &str, unit: &str, value: f64| {
+            json!({
+                "name": name,
+                "unit": unit,
+                "gauge": { "dataPoints": [{
+                    "timeUnixNano": time,
+                    "asDouble": value,
+                    "attributes": attributes,
+                }]},
+            })
+        };
+        let counter = |name: &str, value: u64| {
+            json!({
+                "name": name,
+                "unit": "1",
+                "sum": {
+                    "aggregationTemporality": 1, // Delta: each record counts one flash
+                    "isMonotonic": true,
+                    "dataPoints": [{
+                        "timeUnixNano": time,
+                        "asInt": value.to_string(),
+                        "attributes": attributes,
+                    }],
+                },
+            })
+        };
+
+        json!({
+            "resourceMetrics": [{
+                "resource": { "attributes": [
+                    otlp_attribute("service.name", "golem-gpu-imager"),
+                    otlp_attribute("service.version", env!("CARGO_PKG_VERSION")),
+                    otlp_attribute("service.instance.id", &station_name(station)),
+                    otlp_attribute("os.type", std::env::consts::OS),
+                ]},
+                "scopeMetrics": [{
+                    "scope": { "name": "golem_gpu_imager" },
+                    "metrics": [
+                        counter("golem_imager.flash.count", 1),
+                        counter("golem_imager.flash.retries", self.retries as u64),
+                        gauge("golem_imager.flash.duration", "s", self.duration_secs),
+                        gauge("golem_imager.flash.bytes", "By", self.bytes_written as f64),
+                        gauge("golem_imager.flash.throughput", "By/s", self.throughput()),
+                    ],
+                }],
+            }],
+        })
+    }
+}
+
+fn otlp_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// The configured station name, or the host name if none is set
+fn station_name(station: &str) -> String {
+    let station = station.trim();
+    if !station.is_empty() {
+        return station.to_string();
+    }
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+        .or_else(|| {
+            std::fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|name| name.trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// URL the records are POSTed to
+fn export_url(settings: &TelemetrySettings) -> String {
+    let endpoint = settings.endpoint.trim();
+    match settings.format {
+        TelemetryFormat::Otlp if !endpoint.ends_with(OTLP_METRICS_PATH) => {
+            format!("{}{}", endpoint.trim_end_matches('/'), OTLP_METRICS_PATH)
+        }
+        _ => endpoint.to_string(),
+    }
+}
+
+/// Send the record of one flash to the configured collector
+pub async fn export(settings: TelemetrySettings, metrics: FlashMetrics) -> Result<()> {
+    if !settings.is_active() {
+        return Ok(());
+    }
+    let body = match settings.format {
+        TelemetryFormat::Json => metrics.to_json(&settings.station),
+        TelemetryFormat::Otlp => metrics.to_otlp(&settings.station),
+    };
+    let url = export_url(&settings);
+
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("golem-gpu-imager/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(15))
+        .build()
+        .context("Failed to create HTTP client")?;
+    let response = client
+        .post(&url)
+        .json(&body)
+        .send()
+        .await
+        .with_context(|| format!("Failed to send statistics to {}", url))?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "{} rejected the statistics with status {}",
+            url,
+            response.status()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> FlashMetrics {
+        FlashMetrics {
+            outcome: FlashOutcome::Failed,
+            duration_secs: 100.0,
+            bytes_written: 500_000_000,
+            verified: false,
+            error_category: Some(ErrorCategory::classify("Write failed: Input/output error")),
+            retries: 2,
+            image_channel: "release".to_string(),
+            image_version: "0.2.3".to_string(),
+            device_size: 32_000_000_000,
+            finished_at: 1_735_732_800,
+        }
+    }
+
+    #[test]
+    fn test_classify_errors() {
+        assert_eq!(
+            ErrorCategory::classify("Failed to open /dev/sdb: Permission denied"),
+            ErrorCategory::Permission
+        );
+        assert_eq!(
+            ErrorCategory::classify("Device or resource busy"),
+            ErrorCategory::DeviceBusy
+        );
+        assert_eq!(
+            ErrorCategory::classify("Verification failed: hash mismatch"),
+            ErrorCategory::Verification
+        );
+        assert_eq!(
+            ErrorCategory::classify("Something odd"),
+            ErrorCategory::Other
+        );
+    }
+
+    #[test]
+    fn test_json_record() {
+        let record = metrics().to_json("station-1");
+        assert_eq!(record["station"], "station-1");
+        assert_eq!(record["outcome"], "failed");
+        assert_eq!(record["error_category"], "io");
+        assert_eq!(record["retries"], 2);
+        assert_eq!(record["throughput_bytes_per_sec"], 5_000_000.0);
+    }
+
+    #[test]
+    fn test_otlp_record() {
+        let record = metrics().to_otlp("station-1");
+        let metrics = &record["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        assert_eq!(metrics[0]["name"], "golem_imager.flash.count");
+        assert_eq!(metrics[0]["sum"]["dataPoints"][0]["asInt"], "1");
+        assert_eq!(
+            metrics[0]["sum"]["dataPoints"][0]["timeUnixNano"],
+            "1735732800000000000"
+        );
+        assert_eq!(metrics[2]["gauge"]["dataPoints"][0]["asDouble"], 100.0);
+
+        let settings = TelemetrySettings {
+            enabled: true,
+            endpoint: "http://collector:4318/".to_string(),
+            format: TelemetryFormat::Otlp,
+            station: String::new(),
+        };
+        assert_eq!(export_url(&settings), "http://collector:4318/v1/metrics");
+        assert!(!TelemetrySettings::default().is_active());
+    }
+}
@@ -0,0 +1,189 @@
+/// Template variables in preset fields
+///
+/// Batch-provisioned nodes need unique names that can be traced back to the hardware.
+/// Preset fields such as the node name may contain variables that are expanded for each
+/// device when it is flashed, e.g. `gpu-{index:3}-{serial}` becomes `gpu-007-4C5300012309`.
+use crate::utils::device_assignment::DeviceInfo;
+
+/// Variables that can be used in templates, for help texts
+pub const VARIABLES: [&str; 4] = ["{serial}", "{model}", "{index}", "{date}"];
+
+/// Value substituted for `{serial}` when the device doesn't report one
+const UNKNOWN_SERIAL: &str = "unknown";
+
+/// Values the variables expand to for one device
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateContext<'a> {
+    /// Serial number or WWN of the device
+    pub serial: Option<&'a str>,
+    /// Model name as listed by the OS
+    pub model: &'a str,
+    /// Value of the flash counter
+    pub index: u64,
+    /// Date of the flash, e.g. `20250101`
+    pub date: &'a str,
+}
+
+impl<'a> TemplateContext<'a> {
+    pub fn for_device(device: &DeviceInfo<'a>, index: u64, date: &'a str) -> Self {
+        Self {
+            serial: device.serial,
+            model: device.model,
+            index,
+            date,
+        }
+    }
+}
+
+/// Whether `template` uses any variable
+pub fn has_variables(template: &str) -> bool {
+    !variables(template).is_empty()
+}
+
+/// Whether `template` uses the flash counter, which then has to be advanced
+pub fn uses_index(template: &str) -> bool {
+    variables(template).iter().any(|(name, _)| *name == "index")
+}
+
+/// Names of variables in `template` that don't exist, e.g. `hostname` for `{hostname}`
+pub fn unknown_variables(template: &str) -> Vec<String> {
+    variables(template)
+        .into_iter()
+        .filter(|(name, width)| {
+            !matches!(*name, "serial" | "model" | "index" | "date")
+                || (width.is_some() && *name != "index")
+        })
+        .map(|(name, width)| match width {
+            Some(width) => format!("{}:{}", name, width),
+            None => name.to_string(),
+        })
+        .collect()
+}
+
+/// Expand the variables in `template`
+///
+/// `{index:N}` pads the counter with zeros to N digits. Substituted text is limited to
+/// letters, digits, `-`, `_` and `.` so it is safe in names; anything else becomes `-`.
+/// Unknown variables are left as they are.
+pub fn expand(template: &str, context: &TemplateContext) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let variable = &rest[start + 1..end];
+        match substitute(variable, context) {
+            Some(value) => expanded.push_str(&sanitize(&value)),
+            None => expanded.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Value of a single variable (the text between the braces), `None` if it is unknown
+fn substitute(variable: &str, context: &TemplateContext) -> Option<String> {
+    let (name, width) = split_variable(variable)?;
+    match (name, width) {
+        ("serial", None) => Some(context.serial.unwrap_or(UNKNOWN_SERIAL).trim().to_string()),
+        ("model", None) => Some(context.model.trim().to_string()),
+        ("date", None) => Some(context.date.to_string()),
+        ("index", None) => Some(context.index.to_string()),
+        ("index", Some(width)) => Some(format!("{:0width$}", context.index, width = width)),
+        _ => None,
+    }
+}
+
+/// Variables in `template` as `(name, width)`
+fn variables(template: &str) -> Vec<(&str, Option<usize>)> {
+    let mut found = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        if let Some(variable) = split_variable(&rest[start + 1..end]) {
+            found.push(variable);
+        }
+        rest = &rest[end + 1..];
+    }
+    found
+}
+
+/// Split `index:3` into its name and width; `None` if it doesn't look like a variable
+fn split_variable(variable: &str) -> Option<(&str, Option<usize>)> {
+    let (name, width) = match variable.split_once(':') {
+        Some((name, width)) => (name, Some(width.parse().ok()?)),
+        None => (variable, None),
+    };
+    let is_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    is_name.then_some((name, width))
+}
+
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTEXT: TemplateContext<'static> = TemplateContext {
+        serial: Some("4C5300012309"),
+        model: "SanDisk Extreme",
+        index: 7,
+        date: "20250101",
+    };
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            expand("gpu-{index:3}-{serial}", &CONTEXT),
+            "gpu-007-4C5300012309"
+        );
+        assert_eq!(
+            expand("{model}-{date}-{index}", &CONTEXT),
+            "SanDisk-Extreme-20250101-7"
+        );
+        assert_eq!(expand("no variables", &CONTEXT), "no variables");
+
+        // Unknown variables and stray braces are kept
+        assert_eq!(expand("{hostname}-{serial", &CONTEXT), "{hostname}-{serial");
+
+        let no_serial = TemplateContext {
+            serial: None,
+            ..CONTEXT
+        };
+        assert_eq!(expand("node-{serial}", &no_serial), "node-unknown");
+    }
+
+    #[test]
+    fn test_variables() {
+        assert!(has_variables("gpu-{index}"));
+        assert!(!has_variables("gpu-1"));
+        assert!(uses_index("gpu-{index:4}"));
+        assert!(!uses_index("gpu-{serial}"));
+        assert_eq!(
+            unknown_variables("{serial}-{hostname}-{date:2}"),
+            vec!["hostname".to_string(), "date:2".to_string()]
+        );
+        assert!(unknown_variables("{index:3}-{model}").is_empty());
+    }
+}
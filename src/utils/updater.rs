@@ -0,0 +1,471 @@
+/// Checking for and installing new releases of the application
+///
+/// Releases are published on GitHub. At startup (unless turned off in the settings) the
+/// latest release is compared with the running version. On Windows the MSI installer of a
+/// newer release can be downloaded and started; when running as an AppImage on Linux the
+/// AppImage is replaced and restarted. Elsewhere the release page is opened instead.
+///
+/// A downloaded installer is only started once its minisign signature, published as
+/// `<installer>.minisig`, checks out against the bundled signing keys. Releases without a
+/// signature, and builds without keys, are left to the release page.
+use super::repo::manifest;
+use anyhow::{Context, Result, anyhow};
+use futures_util::StreamExt;
+use minisign_verify::PublicKey;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, info, warn};
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/golemfactory/golem-gpu-imager/releases/latest";
+
+/// Page listing all releases, for when the latest one can't be installed automatically
+pub const RELEASES_PAGE_URL: &str = "https://github.com/golemfactory/golem-gpu-imager/releases";
+
+#[derive(Debug, Clone, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    body: Option<String>,
+    html_url: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+/// A file attached to a release
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// A release newer than the running version
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateInfo {
+    /// Version without the `v` prefix, e.g. `0.3.0`
+    pub version: String,
+    pub title: String,
+    /// Release notes in Markdown
+    pub changelog: String,
+    pub page_url: String,
+    /// Installer for this platform, if the update can be installed from the application
+    pub installer: Option<ReleaseAsset>,
+    /// Minisign signature the installer is checked against before it is started
+    pub signature: Option<ReleaseAsset>,
+}
+
+/// Ask GitHub for the latest release
+///
+/// # Returns
+/// * `Result<Option<UpdateInfo>>` - The release if it is newer than the running version
+pub async fn check_for_update() -> Result<Option<UpdateInfo>> {
+    let client = http_client(Duration::from_secs(20))?;
+    let response = client
+        .get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .context("Failed to reach GitHub")?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "GitHub returned status {} for the latest release",
+            response.status()
+        ));
+    }
+    let release: Release = response
+        .json()
+        .await
+        .context("Failed to read the release information")?;
+    debug!("Latest release is {}", release.tag_name);
+
+    Ok(update_from_release(
+        release,
+        env!("CARGO_PKG_VERSION"),
+        std::env::var_os("APPIMAGE").is_some(),
+        !manifest::bundled_keys().is_empty(),
+    ))
+}
+
+/// The update `release` offers over `current`; `signed` if there are keys to check it with
+fn update_from_release(
+    release: Release,
+    current: &str,
+    appimage: bool,
+    signed: bool,
+) -> Option<UpdateInfo> {
+    if release.draft || release.prerelease || !is_newer(&release.tag_name, current) {
+        return None;
+    }
+
+    let installer = installer_asset(
+        &release.assets,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        appimage,
+    );
+    let signature = installer
+        .filter(|_| signed)
+        .and_then(|installer| installer_signature(&release.assets, installer));
+    // An installer that can't be checked is not started, the release page is offered instead
+    let installer = match (installer, &signature) {
+        (Some(installer), None) => {
+            warn!(
+                "{} of release {} can't be verified, not installing it",
+                installer.name, release.tag_name
+            );
+            None
+        }
+        (installer, _) => installer.cloned(),
+    };
+    let version = release.tag_name.trim_start_matches('v').to_string();
+    Some(UpdateInfo {
+        title: release
+            .name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| format!("Golem GPU Imager {}", version)),
+        version,
+        changelog: release.body.unwrap_or_default(),
+        page_url: release.html_url,
+        installer,
+        signature,
+    })
+}
+
+/// `x.y.z` of a version or tag like `v0.2.3`; anything after a `-` or `+` is ignored
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+/// Whether the release tagged `tag` is newer than `current`
+pub fn is_newer(tag: &str, current: &str) -> bool {
+    match (parse_version(tag), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// The asset that updates an installation on `os`/`arch`
+///
+/// Windows installs come from the MSI. On Linux only AppImages can replace themselves; the
+/// tarballs are left to the user.
+fn installer_asset<'a>(
+    assets: &'a [ReleaseAsset],
+    os: &str,
+    arch: &str,
+    appimage: bool,
+) -> Option<&'a ReleaseAsset> {
+    let arch_names: &[&str] = match arch {
+        "x86_64" => &["x86_64", "x64", "amd64"],
+        "aarch64" => &["aarch64", "arm64"],
+        _ => return None,
+    };
+    let matches_arch = |name: &str| {
+        let name = name.to_lowercase();
+        arch_names.iter().any(|arch| name.contains(arch))
+    };
+
+    match os {
+        "windows" => assets
+            .iter()
+            .find(|asset| asset.name.to_lowercase().ends_with(".msi") && matches_arch(&asset.name)),
+        "linux" if appimage => assets.iter().find(|asset| {
+            asset.name.to_lowercase().ends_with(".appimage") && matches_arch(&asset.name)
+        }),
+        _ => None,
+    }
+}
+
+/// The release's minisign signature of `installer`
+fn installer_signature(assets: &[ReleaseAsset], installer: &ReleaseAsset) -> Option<ReleaseAsset> {
+    let name = format!("{}.minisig", installer.name);
+    assets
+        .iter()
+        .find(|asset| asset.name.eq_ignore_ascii_case(&name))
+        .cloned()
+}
+
+/// Check the downloaded `installer` of `asset` against its `signature`
+fn verify_installer(
+    installer: &[u8],
+    asset: &ReleaseAsset,
+    signature: &str,
+    keys: &[PublicKey],
+) -> Result<()> {
+    manifest::verify_signature(installer, signature, keys)
+        .map_err(|e| anyhow!("The signature of {} is invalid: {}", asset.name, e))
+}
+
+/// Download the installer of `update` and check it against its signature
+///
+/// An AppImage is downloaded next to the running one so it can be moved over it; anything
+/// else goes to the temporary directory. An installer that doesn't check out is deleted.
+pub async fn download_installer(update: &UpdateInfo) -> Result<PathBuf> {
+    let asset = update
+        .installer
+        .as_ref()
+        .ok_or_else(|| anyhow!("There is no installer for this platform"))?;
+    let signature = update
+        .signature
+        .as_ref()
+        .ok_or_else(|| anyhow!("The installer of this release can't be verified"))?;
+    let path = match std::env::var_os("APPIMAGE") {
+        Some(appimage) => PathBuf::from(format!("{}.new", appimage.to_string_lossy())),
+        None => {
+            let dir = std::env::temp_dir().join("golem-gpu-imager-update");
+            tokio::fs::create_dir_all(&dir).await?;
+            dir.join(&asset.name)
+        }
+    };
+
+    info!(
+        "Downloading {} to {}",
+        asset.browser_download_url,
+        path.display()
+    );
+    let client = http_client(Duration::from_secs(30 * 60))?;
+    let response = client
+        .get(&asset.browser_download_url)
+        .send()
+        .await
+        .context("Failed to download the update")?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to download the update, status: {}",
+            response.status()
+        ));
+    }
+
+    let mut file = tokio::fs::File::create(&path)
+        .await
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut downloaded = 0u64;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.context("Failed to download the update")?;
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+    }
+    file.flush().await?;
+    if asset.size > 0 && downloaded != asset.size {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(anyhow!(
+            "The download is incomplete ({} of {} bytes)",
+            downloaded,
+            asset.size
+        ));
+    }
+
+    let checked = match download_text(&client, signature).await {
+        Ok(published) => {
+            let installer = tokio::fs::read(&path)
+                .await
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            verify_installer(&installer, asset, &published, &manifest::bundled_keys())
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = checked {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e.context("Not installing the update"));
+    }
+    info!("Verified {}", asset.name);
+    Ok(path)
+}
+
+/// A small file of the release, such as a signature
+async fn download_text(client: &reqwest::Client, asset: &ReleaseAsset) -> Result<String> {
+    let response = client
+        .get(&asset.browser_download_url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download {}", asset.name))?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to download {}, status: {}",
+            asset.name,
+            response.status()
+        ));
+    }
+    response
+        .text()
+        .await
+        .with_context(|| format!("Failed to download {}", asset.name))
+}
+
+/// Start the downloaded installer; the application should exit afterwards
+pub fn launch_installer(path: &Path) -> Result<()> {
+    if cfg!(windows) {
+        // The installer starts the new version, which would hand over to this exiting window
+        super::single_instance::release();
+        std::process::Command::new("msiexec")
+            .arg("/i")
+            .arg(path)
+            .spawn()
+            .context("Failed to start the installer")?;
+        return Ok(());
+    }
+
+    let appimage = std::env::var_os("APPIMAGE")
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("Updates can only be installed into an AppImage"))?;
+    replace_appimage(path, &appimage)?;
+    // The restarted AppImage would otherwise hand over to this exiting window and quit
+    super::single_instance::release();
+    std::process::Command::new(&appimage)
+        .spawn()
+        .with_context(|| format!("Failed to restart {}", appimage.display()))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn replace_appimage(new: &Path, appimage: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(new, std::fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to make {} executable", new.display()))?;
+    // A rename keeps the running image intact, it stays open under the old inode
+    std::fs::rename(new, appimage)
+        .with_context(|| format!("Failed to replace {}", appimage.display()))
+}
+
+#[cfg(not(unix))]
+fn replace_appimage(_new: &Path, _appimage: &Path) -> Result<()> {
+    Err(anyhow!("AppImages are only supported on Linux"))
+}
+
+fn http_client(timeout: Duration) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(concat!("golem-gpu-imager/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(timeout)
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.com/{}", name),
+            size: 1,
+        }
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v0.3.0", "0.2.3"));
+        assert!(is_newer("v0.2.10", "0.2.9"));
+        assert!(is_newer("1.0", "0.9.9"));
+        assert!(!is_newer("v0.2.3", "0.2.3"));
+        assert!(!is_newer("v0.2.3-rc1", "0.2.3"));
+        assert!(!is_newer("nightly", "0.2.3"));
+    }
+
+    #[test]
+    fn test_installer_asset() {
+        let assets = vec![
+            asset("golem-gpu-imager-linux-x64.tar.gz"),
+            asset("golem-gpu-imager-windows.zip"),
+            asset("GolemGpuImager-0.3.0-x64.msi"),
+            asset("GolemGpuImager-0.3.0-x86_64.AppImage"),
+        ];
+        assert_eq!(
+            installer_asset(&assets, "windows", "x86_64", false).map(|a| a.name.as_str()),
+            Some("GolemGpuImager-0.3.0-x64.msi")
+        );
+        assert_eq!(
+            installer_asset(&assets, "linux", "x86_64", true).map(|a| a.name.as_str()),
+            Some("GolemGpuImager-0.3.0-x86_64.AppImage")
+        );
+        // Tarball installs and other architectures are updated by hand
+        assert_eq!(installer_asset(&assets, "linux", "x86_64", false), None);
+        assert_eq!(installer_asset(&assets, "linux", "aarch64", true), None);
+    }
+
+    #[test]
+    fn test_update_from_release() {
+        let release: Release = serde_json::from_str(
+            r#"{
+                "tag_name": "v9.0.0",
+                "name": "",
+                "body": "* Faster flashing",
+                "html_url": "https://github.com/golemfactory/golem-gpu-imager/releases/tag/v9.0.0",
+                "assets": [
+                    {"name": "GolemGpuImager-9.0.0-x64.msi", "browser_download_url": "https://example.com/a.msi", "size": 10},
+                    {"name": "GolemGpuImager-9.0.0-x64.msi.minisig", "browser_download_url": "https://example.com/a.msi.minisig", "size": 1}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let update = update_from_release(release.clone(), "0.2.3", false, true).unwrap();
+        assert_eq!(update.version, "9.0.0");
+        assert_eq!(update.title, "Golem GPU Imager 9.0.0");
+        assert_eq!(update.changelog, "* Faster flashing");
+        assert_eq!(
+            update_from_release(release.clone(), "9.0.0", false, true),
+            None
+        );
+
+        // Without keys the signature can't be checked, so only the release page is offered
+        let unsigned = update_from_release(release.clone(), "0.2.3", false, false).unwrap();
+        assert_eq!(unsigned.installer, None);
+        assert_eq!(unsigned.signature, None);
+
+        let prerelease = Release {
+            prerelease: true,
+            ..release
+        };
+        assert_eq!(update_from_release(prerelease, "0.2.3", false, true), None);
+    }
+
+    #[test]
+    fn test_installer_signature() {
+        let installer = asset("GolemGpuImager-0.3.0-x64.msi");
+        let signature = asset("GolemGpuImager-0.3.0-x64.msi.minisig");
+        let assets = vec![installer.clone(), signature.clone(), asset("SHA256SUMS")];
+        assert_eq!(installer_signature(&assets, &installer), Some(signature));
+        // A checksum doesn't prove who built the installer
+        assert_eq!(
+            installer_signature(&[installer.clone(), asset("SHA256SUMS")], &installer),
+            None
+        );
+    }
+
+    #[test]
+    fn test_verify_installer() {
+        let installer = asset("a.msi");
+
+        // Signed by the test key of the repository metadata
+        const TEST_KEY: &str = "RWTzHwEtWKsm2Z6u5QM2uzozYqE8k2Vu+1G4ALCITQWTkC+oWS33d4ap";
+        const TEST_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUTzHwEtWKsm2atRvtLBje4IhZ10MolriNxnQCrzOBKQIjt/aZj+B5DsfPihpGQzz/EhqKOVfsWKLPHHVQRTn48DE7mioR/ARgI=
+trusted comment: timestamp:1760000000\tfile:meta.json\thashed
+G8fY1So+83GXdkZoKRtsIPnmyBCeRmwSDz4d8fxBwpDJzCGSqrgIHd0/v1wlkqnp2afSDnkmVMRiGzPSiEu0Ag==
+";
+        let keys = [PublicKey::from_base64(TEST_KEY).unwrap()];
+        let signed = br#"{"channels":[]}"#;
+        assert!(verify_installer(signed, &installer, TEST_SIGNATURE, &keys).is_ok());
+        assert!(verify_installer(b"tampered", &installer, TEST_SIGNATURE, &keys).is_err());
+        assert!(verify_installer(signed, &installer, TEST_SIGNATURE, &[]).is_err());
+    }
+}
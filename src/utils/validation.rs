@@ -114,8 +114,12 @@ pub fn is_valid_central_net_host(url: &str) -> bool {
         return true;
     }
     
-    // Use the same regex pattern as the server
-    let re = regex::Regex::new(r"^(([0-9a-z]{56})@)?([^:]*)(:[0-9]{1,4})?$").unwrap();
+    // The pattern of the field is the same the server uses
+    let pattern = crate::disk::ConfigField::CentralNetHost
+        .help()
+        .pattern
+        .unwrap_or_default();
+    let re = regex::Regex::new(pattern).unwrap();
     
     if let Some(captures) = re.captures(trimmed) {
         // Extract host - must not be empty
@@ -141,6 +145,132 @@ pub fn is_valid_central_net_host(url: &str) -> bool {
     }
 }
 
+/// Reason an extra golem.env key can't be written, `None` if it can
+///
+/// Keys must be shell identifiers and must not be a variable the imager writes itself.
+/// Empty keys are considered valid, the entry is skipped.
+pub fn extra_env_key_error(key: &str) -> Option<String> {
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+
+    let mut chars = key.chars();
+    let is_identifier = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier {
+        Some("Use letters, digits and '_', not starting with a digit".to_string())
+    } else if crate::disk::MANAGED_ENV_KEYS.contains(&key) {
+        Some(format!("{} is already set by the imager", key))
+    } else {
+        None
+    }
+}
+
+/// Reason an extra golemwz.toml key can't be written, `None` if it can
+///
+/// Keys must be bare TOML keys and must not be a key the imager writes itself.
+/// Empty keys are considered valid, the entry is skipped.
+pub fn extra_toml_key_error(key: &str) -> Option<String> {
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+
+    if !key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Some("Use letters, digits, '_' and '-'".to_string())
+    } else if crate::disk::MANAGED_TOML_KEYS.contains(&key) {
+        Some(format!("{} is already set by the imager", key))
+    } else {
+        None
+    }
+}
+
+/// Public subnets providers can join, with what they are for
+pub const KNOWN_SUBNETS: [(&str, &str); 3] = [
+    (
+        "public",
+        "Main public subnet where requestors look for providers",
+    ),
+    (
+        "public-beta",
+        "Former public subnet, still used by older requestors",
+    ),
+    ("devnet-beta", "Testing subnet of the Golem developers"),
+];
+
+/// Longest subnet name accepted
+const MAX_SUBNET_LENGTH: usize = 64;
+
+/// Reason a subnet name can't be used, `None` if it can
+///
+/// Template variables are left out of the check since they are expanded when flashing.
+pub fn subnet_error(subnet: &str) -> Option<String> {
+    let subnet = subnet.trim();
+    if subnet.is_empty() {
+        return Some("Enter the subnet to connect to".to_string());
+    }
+
+    // Literal text outside of `{...}` variables
+    let mut literal = String::new();
+    let mut in_variable = false;
+    for c in subnet.chars() {
+        match c {
+            '{' => in_variable = true,
+            '}' => in_variable = false,
+            c if !in_variable => literal.push(c),
+            _ => {}
+        }
+    }
+
+    if !literal
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        Some("Use letters, digits, '-', '_' and '.'".to_string())
+    } else if subnet.len() > MAX_SUBNET_LENGTH {
+        Some(format!(
+            "Subnet names are at most {} characters",
+            MAX_SUBNET_LENGTH
+        ))
+    } else {
+        None
+    }
+}
+
+/// Known subnet that `subnet` is probably a typo of, e.g. `public` for `publik`
+pub fn similar_known_subnet(subnet: &str) -> Option<&'static str> {
+    let subnet = subnet.trim();
+    if subnet.contains('{') || KNOWN_SUBNETS.iter().any(|(name, _)| *name == subnet) {
+        return None;
+    }
+    let subnet = subnet.to_ascii_lowercase();
+    KNOWN_SUBNETS
+        .iter()
+        .map(|(name, _)| (*name, edit_distance(name, &subnet)))
+        .filter(|(_, distance)| *distance <= 2)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(name, _)| name)
+}
+
+/// Number of single character insertions, deletions and substitutions turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -239,4 +369,40 @@ mod tests {
         // Invalid characters in host based on regex
         assert!(!is_valid_central_net_host("393479950594e7c676ba121033a677a1316f722460827e217c82d2b3@:5000"));
     }
+
+    #[test]
+    fn test_extra_setting_keys() {
+        assert!(extra_env_key_error("").is_none());
+        assert!(extra_env_key_error("YA_NEW_FEATURE").is_none());
+        assert!(extra_env_key_error("1ST_KEY").is_some());
+        assert!(extra_env_key_error("MY-KEY").is_some());
+        assert!(extra_env_key_error("SUBNET").is_some());
+
+        assert!(extra_toml_key_error("gpu-power-limit").is_none());
+        assert!(extra_toml_key_error("with space").is_some());
+        assert!(extra_toml_key_error("glm_account").is_some());
+        assert!(extra_toml_key_error("env").is_some());
+    }
+
+    #[test]
+    fn test_subnet_names() {
+        assert!(subnet_error("public").is_none());
+        assert!(subnet_error("my_subnet.v2-test").is_none());
+        assert!(subnet_error("farm-{serial}").is_none());
+        assert!(subnet_error("").is_some());
+        assert!(subnet_error("my subnet").is_some());
+        assert!(subnet_error("public!").is_some());
+        assert!(subnet_error(&"a".repeat(65)).is_some());
+    }
+
+    #[test]
+    fn test_similar_known_subnet() {
+        assert_eq!(similar_known_subnet("publik"), Some("public"));
+        assert_eq!(similar_known_subnet("Pubic"), Some("public"));
+        assert_eq!(similar_known_subnet("Public"), Some("public"));
+        assert_eq!(similar_known_subnet("devnet-bet"), Some("devnet-beta"));
+        assert_eq!(similar_known_subnet("public"), None);
+        assert_eq!(similar_known_subnet("my-farm"), None);
+        assert_eq!(similar_known_subnet("pub{index}"), None);
+    }
 }